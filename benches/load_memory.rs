@@ -0,0 +1,104 @@
+//! Peak memory and time of loading a large database.
+//!
+//! `cargo bench --bench load_memory` (optionally `TODOS=200000`). Compares the
+//! old whole-document `Value` parse with the streaming loader used by the file
+//! repository. Allocations are counted by a wrapping global allocator.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use rustytodo::{
+    app::repository::TodoRepository,
+    domain::todo::{Notes, Tag, Title, Todo},
+    infra::{db_schema, fs_repo::JsonFileTodoRepository},
+};
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(now, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOC: Counting = Counting;
+
+/// Run `f`, returning its result with the peak bytes it allocated on top of
+/// what was live before, and how long it took.
+fn measure<T>(f: impl FnOnce() -> T) -> (T, usize, f64) {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let start = Instant::now();
+    let out = f();
+    let secs = start.elapsed().as_secs_f64();
+    (out, PEAK.load(Ordering::Relaxed) - base, secs)
+}
+
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn main() {
+    let count: usize = std::env::var("TODOS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(50_000);
+
+    let dir = tempfile::tempdir().expect("temp dir");
+    let path = dir.path().join("db.json");
+    let todos: Vec<Todo> = (0..count)
+        .map(|i| {
+            let mut t = Todo::new(Title::parse(format!("Benchmark todo number {i}")).unwrap());
+            t.notes =
+                Some(Notes::parse("Some notes to give each record a realistic size.").unwrap());
+            t.tags.insert(Tag::parse("bench").unwrap());
+            t
+        })
+        .collect();
+    std::fs::write(&path, db_schema::write_current(&todos).unwrap()).unwrap();
+    drop(todos);
+    let size = std::fs::metadata(&path).unwrap().len() as usize;
+    println!("{count} todos, {:.1} MiB on disk", mib(size));
+
+    let (n, peak, secs) = measure(|| {
+        let text = std::fs::read_to_string(&path).unwrap();
+        let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+        let db: db_schema::v2::DbFileV2 = serde_json::from_value(v).unwrap();
+        db.todos.len()
+    });
+    println!(
+        "value tree:      peak {:>8.1} MiB  {secs:>6.2}s  ({n} todos)",
+        mib(peak)
+    );
+
+    let (n, peak, secs) = measure(|| {
+        let text = std::fs::read_to_string(&path).unwrap();
+        db_schema::load_any(&text).unwrap().len()
+    });
+    println!(
+        "load_any(text):  peak {:>8.1} MiB  {secs:>6.2}s  ({n} todos)",
+        mib(peak)
+    );
+
+    let (repo, peak, secs) =
+        measure(|| JsonFileTodoRepository::load_or_init(path.clone()).unwrap());
+    let n = repo.list().len();
+    println!(
+        "streamed file:   peak {:>8.1} MiB  {secs:>6.2}s  ({n} todos)",
+        mib(peak)
+    );
+}
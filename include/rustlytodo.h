@@ -0,0 +1,37 @@
+#ifndef RUSTLYTODO_H
+#define RUSTLYTODO_H
+
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Opaque handle owning an open store.
+typedef struct RustlytodoHandle RustlytodoHandle;
+
+// Open (or create) the database at `db_path`.
+//
+// Returns null on failure.
+RustlytodoHandle *rustlytodo_open(const char *db_path);
+
+// Close a handle returned by `rustlytodo_open`. Null is ignored.
+void rustlytodo_close(RustlytodoHandle *handle);
+
+// List all todos as a JSON array (same shape as `list --format json`).
+char *rustlytodo_list_json(RustlytodoHandle *handle);
+
+// Add a todo from a JSON request body and persist it.
+//
+// Body: `{"title": "...", "project"?, "tags"?: [..], "notes"?, "priority"?, "due"?}`.
+// Returns the created todo.
+char *rustlytodo_add_json(RustlytodoHandle *handle, const char *request_json);
+
+// Mark a todo as done by full UUID and persist it.
+char *rustlytodo_done(RustlytodoHandle *handle, const char *id);
+
+// Free a string returned by any `rustlytodo_*` function. Null is ignored.
+void rustlytodo_string_free(char *s);
+
+#endif /* RUSTLYTODO_H */
@@ -0,0 +1,248 @@
+//! Bulk editing as a plain-text table (`edit --bulk`).
+//!
+//! Todos are written out one per line, tab-separated, for the user to change
+//! in their editor. Reading the text back yields one patch per edited row;
+//! rows that were removed become delete intents. Nothing is applied here, so
+//! the caller can confirm deletions first.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::domain::todo::{
+    DueAt, Priority, ProjectName, Tag, TagsPatch, Title, Todo, TodoId, TodoPatch,
+};
+
+const HEADER: &str = "\
+# Edit the rows below, then save and quit. Delete a row to delete that todo.
+# Columns are tab-separated: id, status (open|done), priority, project, due (RFC3339), tags, title
+# Use - for no due date or no tags; separate tags with commas. Lines starting with # are ignored.
+";
+
+/// Changes to one todo.
+#[derive(Debug, Clone, Default)]
+pub struct RowEdit {
+    pub id: TodoId,
+    pub patch: TodoPatch,
+    /// `Some(true)` to mark done, `Some(false)` to reopen.
+    pub done: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BulkPlan {
+    pub edits: Vec<RowEdit>,
+    /// Todos whose rows were removed.
+    pub deletes: Vec<TodoId>,
+}
+
+impl BulkPlan {
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty() && self.deletes.is_empty()
+    }
+}
+
+/// A problem with one line of the edited table (1-based line number).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineError {
+    pub line: usize,
+    pub message: String,
+}
+
+pub fn to_table(todos: &[Todo]) -> String {
+    let mut text = String::from(HEADER);
+    for t in todos {
+        let tags = if t.tags.is_empty() {
+            "-".to_string()
+        } else {
+            t.tags
+                .iter()
+                .map(|t| t.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        text.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            t.id.as_uuid_str(),
+            if t.status.is_done() { "done" } else { "open" },
+            t.priority.label(),
+            t.project.as_str(),
+            t.due
+                .map_or_else(|| "-".to_string(), |d| d.format_rfc3339()),
+            tags,
+            t.title.as_str(),
+        ));
+    }
+    text
+}
+
+/// Compare the edited `text` against the `original` rows it was made from.
+///
+/// Every line is checked before anything is returned, so all mistakes are
+/// reported at once.
+pub fn plan(original: &[Todo], text: &str) -> Result<BulkPlan, Vec<LineError>> {
+    let by_id: BTreeMap<TodoId, &Todo> = original.iter().map(|t| (t.id, t)).collect();
+    let mut seen = BTreeSet::new();
+    let mut plan = BulkPlan::default();
+    let mut errors = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let fail = |message: String| LineError {
+            line: i + 1,
+            message,
+        };
+        match parse_row(line, &by_id) {
+            Ok(edit) if !seen.insert(edit.id) => {
+                errors.push(fail(format!("{} appears more than once", edit.id.short())));
+            }
+            Ok(edit) => {
+                if edit.done.is_some() || has_changes(&edit.patch) {
+                    plan.edits.push(edit);
+                }
+            }
+            Err(message) => errors.push(fail(message)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    plan.deletes = original
+        .iter()
+        .map(|t| t.id)
+        .filter(|id| !seen.contains(id))
+        .collect();
+    Ok(plan)
+}
+
+fn parse_row(line: &str, by_id: &BTreeMap<TodoId, &Todo>) -> Result<RowEdit, String> {
+    let cols: Vec<&str> = line.splitn(7, '\t').map(str::trim).collect();
+    let [id, status, priority, project, due, tags, title] = cols[..] else {
+        return Err(format!(
+            "expected 7 tab-separated columns, found {}",
+            cols.len()
+        ));
+    };
+
+    let id = TodoId::parse_uuid(id).map_err(|e| format!("id: {e}"))?;
+    let Some(todo) = by_id.get(&id) else {
+        return Err(format!(
+            "{} was not in the table (new todos can't be added here)",
+            id.short()
+        ));
+    };
+
+    let mut edit = RowEdit {
+        id,
+        ..RowEdit::default()
+    };
+    let patch = &mut edit.patch;
+
+    let done = match status.to_ascii_lowercase().as_str() {
+        "open" => false,
+        "done" => true,
+        other => return Err(format!("status must be open or done, not {other:?}")),
+    };
+    if done != todo.status.is_done() {
+        edit.done = Some(done);
+    }
+
+    let priority = Priority::parse(priority).map_err(|e| e.to_string())?;
+    if priority != todo.priority {
+        patch.priority = Some(priority);
+    }
+
+    let project = ProjectName::parse(project).map_err(|e| e.to_string())?;
+    if project != todo.project {
+        patch.project = Some(project);
+    }
+
+    let due = match due {
+        "-" | "" => None,
+        d => Some(DueAt::parse_rfc3339(d).map_err(|e| e.to_string())?),
+    };
+    if due != todo.due {
+        patch.due = Some(due);
+    }
+
+    let tags = match tags {
+        "-" | "" => BTreeSet::new(),
+        list => list
+            .split(',')
+            .filter(|t| !t.trim().is_empty())
+            .map(Tag::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?,
+    };
+    if tags != todo.tags {
+        patch.tags = Some(TagsPatch::Replace(tags));
+    }
+
+    let title = Title::parse(title).map_err(|e| e.to_string())?;
+    if title != todo.title {
+        patch.title = Some(title);
+    }
+
+    Ok(edit)
+}
+
+fn has_changes(p: &TodoPatch) -> bool {
+    p.title.is_some()
+        || p.project.is_some()
+        || p.priority.is_some()
+        || p.due.is_some()
+        || p.tags.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edited_table_becomes_patches_and_deletes() {
+        let keep = Todo::new(Title::parse("Keep me").unwrap());
+        let mut edit = Todo::new(Title::parse("Fix typo").unwrap());
+        edit.tags.insert(Tag::parse("docs").unwrap());
+        let gone = Todo::new(Title::parse("Drop me").unwrap());
+        let todos = vec![keep.clone(), edit.clone(), gone.clone()];
+
+        let table = to_table(&todos);
+        let edited: String = table
+            .lines()
+            .filter(|l| !l.contains("Drop me"))
+            .map(|l| {
+                if l.contains("Fix typo") {
+                    l.replace("open\tP3", "done\tP1")
+                        .replace("docs", "docs,web")
+                        .replace("Fix typo", "Fix typos")
+                } else {
+                    l.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let plan = plan(&todos, &edited).unwrap();
+        assert_eq!(plan.deletes, vec![gone.id]);
+        assert_eq!(plan.edits.len(), 1);
+        let e = &plan.edits[0];
+        assert_eq!(e.id, edit.id);
+        assert_eq!(e.done, Some(true));
+        assert_eq!(e.patch.priority, Some(Priority::P1));
+        assert_eq!(e.patch.title.as_ref().unwrap().as_str(), "Fix typos");
+        assert!(matches!(&e.patch.tags, Some(TagsPatch::Replace(tags)) if tags.len() == 2));
+        assert!(e.patch.project.is_none() && e.patch.due.is_none());
+    }
+
+    #[test]
+    fn bad_rows_are_all_reported() {
+        let t = Todo::new(Title::parse("Only").unwrap());
+        let id = t.id.as_uuid_str();
+        let text = format!(
+            "# header\n{id}\tmaybe\tP3\tInbox\t-\t-\tOnly\n{id}\topen\tP9\tInbox\t-\t-\tOnly\nnot a row\n"
+        );
+        let errors = plan(&[t], &text).unwrap_err();
+        let lines: Vec<_> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![2, 3, 4]);
+    }
+}
@@ -0,0 +1,190 @@
+//! Quick capture: a title with inline tokens for the other fields, as `add`
+//! and the GUI's quick-add box take it.
+//!
+//! `Fix login bug #work #bug @Backend !p1 due:friday` is the title "Fix login
+//! bug" with tags, project, priority and due date:
+//!
+//! - `#tag` adds a tag (`#123` stays in the title: it's usually an issue)
+//! - `@project` sets the project
+//! - `!p1` sets the priority (P1-P4 or a `[[priority_levels]]` label)
+//! - `due:<when>` sets the due date in any form `--due` accepts, with `_` for
+//!   spaces: `due:tomorrow`, `due:2026-03-13`, `due:in_3_days`
+//!
+//! Tokens can go anywhere; for everything but tags the last one wins.
+//! `add --stdin` reads a whole brain dump of these, one todo per line.
+
+use std::collections::BTreeSet;
+
+use time::OffsetDateTime;
+
+use crate::{
+    app::{due_input::parse_due, priorities::PriorityScheme},
+    domain::todo::{DueAt, Priority, ProjectName, Tag, Title, TitleRules, Todo},
+};
+
+/// What a captured line asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    /// The words that aren't tokens.
+    pub title: Title,
+    pub project: Option<ProjectName>,
+    pub tags: BTreeSet<Tag>,
+    pub priority: Option<Priority>,
+    pub due: Option<DueAt>,
+}
+
+impl Capture {
+    /// A new todo with the captured fields, and defaults for the rest.
+    pub fn into_todo(self, now: OffsetDateTime) -> Todo {
+        let mut todo = Todo::new_at(self.title, now);
+        if let Some(p) = self.project {
+            todo.project = p;
+        }
+        todo.tags = self.tags;
+        if let Some(p) = self.priority {
+            todo.priority = p;
+        }
+        todo.due = self.due;
+        todo
+    }
+}
+
+/// Split `line` into title and fields. Errors name the token at fault.
+pub fn parse(
+    line: &str,
+    rules: &TitleRules,
+    priorities: &PriorityScheme,
+    now: OffsetDateTime,
+) -> Result<Capture, String> {
+    let mut project = None;
+    let mut tags = BTreeSet::new();
+    let mut priority = None;
+    let mut due = None;
+    let mut words = Vec::new();
+    for word in line.split_whitespace() {
+        let lower = word.to_ascii_lowercase();
+        let bad = |e: &dyn std::fmt::Display| format!("{word}: {e}");
+        if let Some(name) = word.strip_prefix('#')
+            && !name.is_empty()
+            && !name.chars().all(|c| c.is_ascii_digit())
+        {
+            tags.insert(Tag::parse(name).map_err(|e| bad(&e))?);
+        } else if let Some(name) = word.strip_prefix('@')
+            && !name.is_empty()
+        {
+            project = Some(ProjectName::parse(name).map_err(|e| bad(&e))?);
+        } else if let Some(level) = word.strip_prefix('!')
+            && !level.is_empty()
+            && !level.starts_with('!')
+        {
+            priority = Some(priorities.parse(level).map_err(|e| bad(&e))?);
+        } else if lower.starts_with("due:") && word.len() > 4 {
+            let when = word[4..].replace('_', " ");
+            due = Some(parse_due(&when, now).map_err(|e| bad(&e))?);
+        } else {
+            words.push(word);
+        }
+    }
+    if words.is_empty() {
+        return Err(format!("{:?} has no title besides its tokens", line.trim()));
+    }
+    let title = Title::parse_with(words.join(" "), rules).map_err(|e| e.to_string())?;
+    Ok(Capture {
+        title,
+        project,
+        tags,
+        priority,
+        due,
+    })
+}
+
+/// Parse every non-blank line of `text`. All or nothing: the errors are
+/// those of every bad line, prefixed with its number.
+pub fn parse_lines(
+    text: &str,
+    rules: &TitleRules,
+    priorities: &PriorityScheme,
+    now: OffsetDateTime,
+) -> Result<Vec<Capture>, Vec<String>> {
+    let mut captures = Vec::new();
+    let mut errors = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse(line, rules, priorities, now) {
+            Ok(c) => captures.push(c),
+            Err(e) => errors.push(format!("line {}: {e}", n + 1)),
+        }
+    }
+    if errors.is_empty() {
+        Ok(captures)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    const NOW: OffsetDateTime = datetime!(2026-05-04 09:00 UTC);
+
+    fn capture(line: &str) -> Result<Capture, String> {
+        parse(
+            line,
+            &TitleRules::default(),
+            &PriorityScheme::default(),
+            NOW,
+        )
+    }
+
+    #[test]
+    fn tokens_fill_fields_and_the_rest_is_the_title() {
+        let c = capture("Fix login bug #work #Bug @Backend !p1 due:2026-05-08 for #123").unwrap();
+        assert_eq!(c.title.as_str(), "Fix login bug for #123");
+        assert_eq!(c.project.unwrap().as_str(), "Backend");
+        let tags: Vec<_> = c.tags.iter().map(|t| t.as_str()).collect();
+        assert_eq!(tags, ["bug", "work"]);
+        assert_eq!(c.priority, Some(Priority::P1));
+        assert_eq!(
+            c.due.unwrap().as_dt().date(),
+            NOW.date().replace_day(8).unwrap()
+        );
+
+        let c = capture("Call mom due:in_3_days !! wow").unwrap();
+        assert_eq!(c.title.as_str(), "Call mom !! wow");
+        assert_eq!(
+            c.due.unwrap().as_dt().date(),
+            NOW.date().replace_day(7).unwrap()
+        );
+
+        let c = capture("Plain title").unwrap();
+        assert!(c.tags.is_empty() && c.project.is_none() && c.due.is_none());
+    }
+
+    #[test]
+    fn bad_lines_are_reported_by_number() {
+        assert!(capture("Task !p9").unwrap_err().starts_with("!p9: "));
+        assert!(
+            capture("Task due:whenever")
+                .unwrap_err()
+                .starts_with("due:whenever: ")
+        );
+        assert!(capture("Task #a+b").unwrap_err().starts_with("#a+b: "));
+        assert_eq!(
+            capture("#work @Home").unwrap_err(),
+            "\"#work @Home\" has no title besides its tokens"
+        );
+
+        let rules = TitleRules::default();
+        let scheme = PriorityScheme::default();
+        let dump = "Buy milk #home\n\n  Write report !p2\n";
+        assert_eq!(parse_lines(dump, &rules, &scheme, NOW).unwrap().len(), 2);
+        let errors = parse_lines("Ok\nBad !p9\nAlso ok\n#only", &rules, &scheme, NOW).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("line 2: !p9: "));
+        assert!(errors[1].starts_with("line 4: "));
+    }
+}
@@ -3,16 +3,53 @@
 //! This is *not* domain logic. It's a convenient container for
 //! environment/config paths and shared cross-cutting concerns.
 
-use crate::infra::{config::AppConfig, paths::AppPaths};
+use std::sync::Arc;
+
+use time::OffsetDateTime;
+
+use crate::{
+    app::timezone::Zone,
+    domain::clock::{Clock, SystemClock},
+    infra::{config::AppConfig, paths::AppPaths},
+};
 
 #[derive(Debug, Clone)]
 pub struct AppContext {
     pub paths: AppPaths,
     pub config: AppConfig,
+    /// Where commands get the current time (the system clock unless `--as-of`
+    /// or a test says otherwise).
+    pub clock: Arc<dyn Clock>,
+    /// The zone from `timezone` in config.toml, for display and bare
+    /// date/time input.
+    pub zone: Zone,
 }
 
 impl AppContext {
     pub fn new(paths: AppPaths, config: AppConfig) -> Self {
-        Self { paths, config }
+        let zone = config.zone().unwrap_or_else(|e| {
+            tracing::warn!("{e}; showing times in UTC");
+            Zone::default()
+        });
+        Self {
+            paths,
+            config,
+            clock: Arc::new(SystemClock),
+            zone,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn now(&self) -> OffsetDateTime {
+        self.clock.now()
+    }
+
+    /// `at` as a wall-clock time in the configured zone.
+    pub fn local(&self, at: OffsetDateTime) -> OffsetDateTime {
+        self.zone.local(at)
     }
 }
@@ -0,0 +1,246 @@
+//! Named table views from config.toml (`report <name>`).
+//!
+//! Each `[reports.<name>]` table picks the todos with a `list --query`
+//! filter and says how to show them, so a team can share its views:
+//!
+//! ```toml
+//! [reports.standup]
+//! description = "What's in flight"
+//! filter = "project:Work status:open"
+//! columns = ["id", "priority", "state", "due", "title"]
+//! sort = ["priority", "due"]
+//! group_by = "project"
+//! ```
+//!
+//! All keys are optional: without a filter every listed todo is shown, and
+//! the columns default to [`DEFAULT_COLUMNS`].
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::app::query::{GroupBy, ListQuery, SortKey, UrgencyCoefficients};
+
+pub const DEFAULT_COLUMNS: [Column; 6] = [
+    Column::Id,
+    Column::Status,
+    Column::Priority,
+    Column::Project,
+    Column::Due,
+    Column::Title,
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportDefinition {
+    /// Shown above the table and by `report` without a name.
+    pub description: Option<String>,
+    /// Which todos, in the `list --query` language.
+    pub filter: String,
+    /// Column names (see [`Column::parse`]); empty for [`DEFAULT_COLUMNS`].
+    pub columns: Vec<String>,
+    /// Sort keys as for `list --sort`, the first deciding (default: due).
+    pub sort: Vec<String>,
+    /// Reverse the first sort key.
+    pub desc: bool,
+    /// Headings by project, tag, priority or due.
+    pub group_by: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// A definition checked and ready to run.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub query: ListQuery,
+    pub columns: Vec<Column>,
+    pub group_by: Option<GroupBy>,
+}
+
+impl ReportDefinition {
+    /// The query and layout this defines. Relative dates in the filter
+    /// (`due<=today`) are taken from `now`.
+    pub fn compile(
+        &self,
+        urgency: UrgencyCoefficients,
+        now: OffsetDateTime,
+    ) -> Result<Report, String> {
+        let columns = if self.columns.is_empty() {
+            DEFAULT_COLUMNS.to_vec()
+        } else {
+            self.columns
+                .iter()
+                .map(|c| {
+                    Column::parse(c).ok_or_else(|| {
+                        format!("unknown column {c} (use {})", Column::NAMES.join(", "))
+                    })
+                })
+                .collect::<Result<_, _>>()?
+        };
+        let mut keys = self.sort.iter().map(|k| {
+            SortKey::parse(k).ok_or_else(|| {
+                format!("unknown sort key {k} (use due|priority|created|title|id|time|urgency)")
+            })
+        });
+        let sort = keys.next().transpose()?.unwrap_or(SortKey::Due);
+        let then_by = keys.collect::<Result<_, _>>()?;
+        let group_by = self
+            .group_by
+            .as_deref()
+            .map(|g| {
+                GroupBy::parse(g)
+                    .ok_or_else(|| format!("unknown group_by {g} (use project|tag|priority|due)"))
+            })
+            .transpose()?;
+
+        let mut query = ListQuery {
+            sort,
+            then_by,
+            desc: self.desc,
+            limit: self.limit,
+            urgency,
+            ..ListQuery::default()
+        };
+        query
+            .add_query(&self.filter, now)
+            .map_err(|e| format!("filter: {e}"))?;
+        Ok(Report {
+            query,
+            columns,
+            group_by,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Id,
+    Status,
+    Priority,
+    Project,
+    Tags,
+    Due,
+    State,
+    Energy,
+    Estimate,
+    Tracked,
+    Ref,
+    Urgency,
+    Created,
+    Completed,
+    Title,
+}
+
+impl Column {
+    pub const ALL: [Column; 15] = [
+        Column::Id,
+        Column::Status,
+        Column::Priority,
+        Column::Project,
+        Column::Tags,
+        Column::Due,
+        Column::State,
+        Column::Energy,
+        Column::Estimate,
+        Column::Tracked,
+        Column::Ref,
+        Column::Urgency,
+        Column::Created,
+        Column::Completed,
+        Column::Title,
+    ];
+
+    pub const NAMES: [&str; 15] = [
+        "id",
+        "status",
+        "priority",
+        "project",
+        "tags",
+        "due",
+        "state",
+        "energy",
+        "estimate",
+        "tracked",
+        "ref",
+        "urgency",
+        "created",
+        "completed",
+        "title",
+    ];
+
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim().to_ascii_lowercase();
+        Self::NAMES
+            .iter()
+            .position(|n| *n == input)
+            .map(|i| Self::ALL[i])
+    }
+
+    pub fn header(self) -> &'static str {
+        match self {
+            Column::Id => "ID",
+            Column::Status => "S",
+            Column::Priority => "P",
+            Column::Project => "PROJECT",
+            Column::Tags => "TAGS",
+            Column::Due => "DUE",
+            Column::State => "STATE",
+            Column::Energy => "ENERGY",
+            Column::Estimate => "EST",
+            Column::Tracked => "TRACKED",
+            Column::Ref => "REF",
+            Column::Urgency => "URG",
+            Column::Created => "CREATED",
+            Column::Completed => "COMPLETED",
+            Column::Title => "TITLE",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn definitions_compile_to_a_query_and_columns() {
+        let now = datetime!(2026-05-04 09:00 UTC);
+        let def = ReportDefinition {
+            filter: "project:Work due<=today".into(),
+            columns: vec!["ID".into(), "state".into(), "title".into()],
+            sort: vec!["priority".into(), "due".into()],
+            group_by: Some("tag".into()),
+            ..ReportDefinition::default()
+        };
+        let report = def.compile(UrgencyCoefficients::default(), now).unwrap();
+        assert_eq!(report.columns, [Column::Id, Column::State, Column::Title]);
+        assert_eq!(report.query.sort, SortKey::Priority);
+        assert_eq!(report.query.then_by, [SortKey::Due]);
+        assert_eq!(report.query.terms.len(), 2);
+        assert_eq!(report.group_by, Some(GroupBy::Tag));
+
+        let bare = ReportDefinition::default()
+            .compile(UrgencyCoefficients::default(), now)
+            .unwrap();
+        assert_eq!(bare.columns, DEFAULT_COLUMNS);
+        assert!(bare.group_by.is_none());
+
+        let bad = |def: ReportDefinition| {
+            def.compile(UrgencyCoefficients::default(), now)
+                .unwrap_err()
+        };
+        let err = bad(ReportDefinition {
+            columns: vec!["colour".into()],
+            ..ReportDefinition::default()
+        });
+        assert!(err.starts_with("unknown column colour"), "{err}");
+        let err = bad(ReportDefinition {
+            sort: vec!["size".into()],
+            ..ReportDefinition::default()
+        });
+        assert!(err.starts_with("unknown sort key size"), "{err}");
+        let err = bad(ReportDefinition {
+            filter: "due<=someday-soon".into(),
+            ..ReportDefinition::default()
+        });
+        assert!(err.starts_with("filter: "), "{err}");
+    }
+}
@@ -0,0 +1,528 @@
+//! Tolerant parser for dictated todos (`add --dictated`).
+//!
+//! Speech-to-text gives one run-on sentence with no punctuation or quoting:
+//! "remind me to call the dentist next tuesday afternoon its urgent". This pulls
+//! out what looks like a priority, a due date/time, a project and tags, drops
+//! the conversational filler around them, and keeps the rest as the title.
+//!
+//! It is a keyword heuristic, not a grammar: matching is case-insensitive,
+//! ignores punctuation and apostrophes ("it's" = "its"), and anything it doesn't
+//! recognise simply stays in the title. Dates are interpreted in `now`'s
+//! offset; a day without a time means 17:00 (end of the working day). Weekdays
+//! are spelled out (or `tue`/`thu`/`fri`...) but not `sat`/`sun`/`mon`, which
+//! are too often ordinary words.
+
+use time::{Duration, OffsetDateTime, Time, Weekday};
+
+use crate::domain::todo::Priority;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dictation {
+    pub title: String,
+    pub priority: Option<Priority>,
+    pub due: Option<OffsetDateTime>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+}
+
+struct Word {
+    raw: String,
+    norm: String,
+    used: bool,
+}
+
+/// Longest phrases first, so "not urgent" wins over "urgent".
+const PRIORITY_PHRASES: &[(&[&str], Priority)] = &[
+    (&["as", "soon", "as", "possible"], Priority::P1),
+    (&["it", "is", "not", "urgent"], Priority::P4),
+    (&["it", "is", "urgent"], Priority::P1),
+    (&["its", "not", "urgent"], Priority::P4),
+    (&["this", "is", "urgent"], Priority::P1),
+    (&["its", "very", "important"], Priority::P1),
+    (&["its", "urgent"], Priority::P1),
+    (&["its", "important"], Priority::P1),
+    (&["very", "urgent"], Priority::P1),
+    (&["very", "important"], Priority::P1),
+    (&["high", "priority"], Priority::P1),
+    (&["top", "priority"], Priority::P1),
+    (&["priority", "one"], Priority::P1),
+    (&["priority", "two"], Priority::P2),
+    (&["medium", "priority"], Priority::P2),
+    (&["priority", "three"], Priority::P3),
+    (&["normal", "priority"], Priority::P3),
+    (&["priority", "four"], Priority::P4),
+    (&["low", "priority"], Priority::P4),
+    (&["not", "urgent"], Priority::P4),
+    (&["no", "rush"], Priority::P4),
+    (&["no", "hurry"], Priority::P4),
+    (&["urgent"], Priority::P1),
+    (&["urgently"], Priority::P1),
+    (&["asap"], Priority::P1),
+    (&["p1"], Priority::P1),
+    (&["p2"], Priority::P2),
+    (&["p3"], Priority::P3),
+    (&["p4"], Priority::P4),
+    (&["whenever"], Priority::P4),
+    (&["someday"], Priority::P4),
+];
+
+/// Conversational openers dropped from the start of the title.
+const LEAD_INS: &[&[&str]] = &[
+    &["remind", "me", "to"],
+    &["remind", "me"],
+    &["dont", "forget", "to"],
+    &["remember", "to"],
+    &["note", "to", "self"],
+    &["make", "sure", "to"],
+    &["make", "sure", "i"],
+    &["i", "need", "to"],
+    &["i", "have", "to"],
+    &["i", "want", "to"],
+    &["i", "should"],
+    &["i", "must"],
+    &["need", "to"],
+    &["have", "to"],
+    &["please"],
+    &["okay"],
+    &["ok"],
+    &["so"],
+    &["add"],
+    &["todo"],
+    &["task"],
+];
+
+const FILLERS: &[&str] = &["um", "umm", "uh", "uhh", "er", "erm", "hmm"];
+
+/// Words that only glue a removed phrase to the rest of the sentence.
+const CONNECTORS: &[&str] = &[
+    "and", "but", "by", "on", "due", "at", "for", "in", "the", "this", "next", "coming", "before",
+    "until", "its", "is", "it", "to", "under", "my", "our",
+];
+
+pub fn parse(input: &str, now: OffsetDateTime) -> Dictation {
+    let mut words = split_words(input);
+
+    for w in words
+        .iter_mut()
+        .filter(|w| FILLERS.contains(&w.norm.as_str()))
+    {
+        w.used = true;
+    }
+
+    let priority = take_priority(&mut words);
+    let due = take_due(&mut words, now);
+    let project = take_project(&mut words);
+    let tags = take_tags(&mut words);
+
+    // Openers, possibly stacked ("ok so remind me to ...").
+    loop {
+        let start = words.iter().position(|w| !w.used).unwrap_or(words.len());
+        let Some(len) = LEAD_INS
+            .iter()
+            .find(|p| matches_at(&words, start, p))
+            .map(|p| p.len())
+        else {
+            break;
+        };
+        mark(&mut words, start, len);
+    }
+    // Dangling "and"/"please" at the very end.
+    while let Some(last) = words.iter().rposition(|w| !w.used)
+        && ["and", "please", "so", "also"].contains(&words[last].norm.as_str())
+    {
+        words[last].used = true;
+    }
+
+    let title = words
+        .iter()
+        .filter(|w| !w.used)
+        .map(|w| w.raw.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Dictation {
+        title: capitalize(&title),
+        priority,
+        due,
+        project,
+        tags,
+    }
+}
+
+/// Read `input` as nothing but a due date ("tomorrow", "friday 5pm", "in 3
+/// days"). Unlike [`parse`], every word has to be part of the date.
+pub fn parse_due_phrase(input: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    let mut words = split_words(input);
+    let due = take_due(&mut words, now)?;
+    words.iter().all(|w| w.used).then_some(due)
+}
+
+fn split_words(input: &str) -> Vec<Word> {
+    input
+        .split_whitespace()
+        .filter_map(|w| {
+            let raw = w.trim_matches(|c: char| ",.!?;:\"()".contains(c));
+            let norm: String = raw
+                .chars()
+                .filter(|c| !matches!(c, '\'' | '’'))
+                .flat_map(char::to_lowercase)
+                .collect();
+            (!raw.is_empty()).then(|| Word {
+                raw: raw.to_string(),
+                norm,
+                used: false,
+            })
+        })
+        .collect()
+}
+
+fn take_priority(words: &mut [Word]) -> Option<Priority> {
+    for (phrase, p) in PRIORITY_PHRASES {
+        if let Some(i) = (0..words.len()).find(|&i| matches_at(words, i, phrase)) {
+            mark(words, i, phrase.len());
+            absorb_connectors(words, i);
+            return Some(*p);
+        }
+    }
+    None
+}
+
+/// "for the garden project", "project garden", "in project garden".
+fn take_project(words: &mut [Word]) -> Option<String> {
+    let k = (0..words.len()).find(|&k| !words[k].used && words[k].norm == "project")?;
+    let name_at = |i: usize| !words[i].used && !CONNECTORS.contains(&words[i].norm.as_str());
+
+    let before = k.checked_sub(1).filter(|&i| name_at(i));
+    let after = Some(k + 1).filter(|&i| i < words.len() && name_at(i));
+    // "the garden project" reads as a name before; otherwise prefer the word after.
+    let determiner = k >= 2 && ["the", "my", "our"].contains(&words[k - 2].norm.as_str());
+    let start = match (before, after) {
+        (Some(i), _) if determiner => i,
+        (_, Some(_)) => k,
+        (Some(i), None) => i,
+        (None, None) => return None,
+    };
+    let name = words[if start == k { k + 1 } else { start }].raw.clone();
+    mark(words, start, 2);
+    absorb_connectors(words, start);
+    Some(name)
+}
+
+/// "tag errands", "tagged home", "hashtag work", "#work".
+fn take_tags(words: &mut [Word]) -> Vec<String> {
+    let mut tags = Vec::new();
+    for i in 0..words.len() {
+        if words[i].used {
+            continue;
+        }
+        if let Some(tag) = words[i].norm.strip_prefix('#').filter(|t| !t.is_empty()) {
+            tags.push(tag.to_string());
+            words[i].used = true;
+        } else if ["tag", "tagged", "hashtag"].contains(&words[i].norm.as_str())
+            && i + 1 < words.len()
+            && !words[i + 1].used
+        {
+            tags.push(words[i + 1].norm.clone());
+            mark(words, i, 2);
+            absorb_connectors(words, i);
+        }
+    }
+    tags
+}
+
+fn take_due(words: &mut [Word], now: OffsetDateTime) -> Option<OffsetDateTime> {
+    let today = now.date();
+    let mut day = None;
+    let mut time = None;
+    let mut exact = None;
+
+    for i in 0..words.len() {
+        if words[i].used {
+            continue;
+        }
+        let rest: Vec<&str> = words[i..]
+            .iter()
+            .take_while(|w| !w.used)
+            .map(|w| w.norm.as_str())
+            .collect();
+
+        let (len, found) = match rest.as_slice() {
+            ["day", "after", "tomorrow", ..] => (3, Found::Day(today + Duration::days(2))),
+            ["tomorrow", "night", ..] => {
+                time = Some(Time::from_hms(20, 0, 0).expect("valid time"));
+                (2, Found::Day(today + Duration::days(1)))
+            }
+            ["tomorrow", ..] | ["tmrw", ..] => (1, Found::Day(today + Duration::days(1))),
+            ["today", ..] => (1, Found::Day(today)),
+            ["tonight", ..] => {
+                time = Some(Time::from_hms(20, 0, 0).expect("valid time"));
+                (1, Found::Day(today))
+            }
+            ["next", "week", ..] => (2, Found::Day(next_weekday(today, Weekday::Monday))),
+            ["end", "of", "the", "week", ..] => (4, Found::Day(upcoming(today, Weekday::Friday))),
+            ["end", "of", "week", ..] => (3, Found::Day(upcoming(today, Weekday::Friday))),
+            ["this", "weekend", ..] | ["weekend", ..] => (
+                1 + usize::from(rest[0] == "this"),
+                Found::Day(upcoming(today, Weekday::Saturday)),
+            ),
+            ["in", n, unit, ..] if number(n).is_some() && unit_duration(unit, 1).is_some() => {
+                // An amount too large to land on a date isn't a due phrase.
+                let Some(at) = number(n)
+                    .and_then(|n| unit_duration(unit, n))
+                    .and_then(|d| now.checked_add(d))
+                else {
+                    continue;
+                };
+                (3, Found::Relative(at, is_clock_unit(unit)))
+            }
+            [w, ..] if weekday(w).is_some() => (
+                1,
+                Found::Day(next_weekday(today, weekday(w).expect("checked above"))),
+            ),
+            _ => match time_at(&rest) {
+                Some((len, t)) => {
+                    time = Some(t);
+                    (len, Found::Time)
+                }
+                None => continue,
+            },
+        };
+
+        match found {
+            Found::Day(d) => day = Some(d),
+            Found::Relative(at, true) => exact = Some(at),
+            Found::Relative(at, false) => day = Some(at.date()),
+            Found::Time => {}
+        }
+        mark(words, i, len);
+        absorb_connectors(words, i);
+    }
+
+    if let Some(at) = exact {
+        return Some(at);
+    }
+    let at = |d: time::Date, t: Time| d.with_time(t).assume_offset(now.offset());
+    match (day, time) {
+        (Some(d), Some(t)) => Some(at(d, t)),
+        (Some(d), None) => Some(at(d, Time::from_hms(17, 0, 0).expect("valid time"))),
+        // A bare time means the next time the clock shows it.
+        (None, Some(t)) if at(today, t) > now => Some(at(today, t)),
+        (None, Some(t)) => Some(at(today + Duration::days(1), t)),
+        (None, None) => None,
+    }
+}
+
+enum Found {
+    Day(time::Date),
+    /// Some time from now; `true` if it has a clock component (hours/minutes).
+    Relative(OffsetDateTime, bool),
+    Time,
+}
+
+/// "morning", "in the afternoon", "at noon", "at 3 pm", "at 15:30", "3pm".
+fn time_at(rest: &[&str]) -> Option<(usize, Time)> {
+    let named = |w: &str| match w {
+        "morning" => Some((9, 0)),
+        "noon" | "midday" | "lunchtime" => Some((12, 0)),
+        "afternoon" => Some((14, 0)),
+        "evening" => Some((18, 0)),
+        "night" => Some((20, 0)),
+        _ => None,
+    };
+    let hm = |h: u8, m: u8| Time::from_hms(h, m, 0).ok();
+
+    match rest {
+        ["in", "the", w, ..] if named(w).is_some() => {
+            named(w).and_then(|(h, m)| Some((3, hm(h, m)?)))
+        }
+        [w, ..] if named(w).is_some() => named(w).and_then(|(h, m)| Some((1, hm(h, m)?))),
+        ["at", n, suffix @ ("am" | "pm" | "oclock"), ..] => clock(n, Some(suffix)).map(|t| (3, t)),
+        ["at", n, ..] => clock(n, None).map(|t| (2, t)),
+        [n, ..] if n.ends_with("am") || n.ends_with("pm") => clock(n, None).map(|t| (1, t)),
+        _ => None,
+    }
+}
+
+/// "3", "3pm", "3:30", "15:30", "3:30pm" with an optional separate suffix.
+fn clock(token: &str, suffix: Option<&str>) -> Option<Time> {
+    let (digits, meridiem) = match (token.strip_suffix("am"), token.strip_suffix("pm")) {
+        (Some(d), _) => (d, Some("am")),
+        (_, Some(d)) => (d, Some("pm")),
+        _ => (token, suffix.filter(|s| *s != "oclock")),
+    };
+    let (h, m) = match digits.split_once([':', '.']) {
+        Some((h, m)) => (h.parse::<u8>().ok()?, m.parse::<u8>().ok()?),
+        None => (number(digits)?.try_into().ok()?, 0),
+    };
+    let h = match (meridiem, h) {
+        (Some(_), 0) | (Some(_), 13..) => return None,
+        (Some("am"), 12) => 0,
+        (Some("pm"), 1..=11) => h + 12,
+        // "at 3" almost always means the afternoon.
+        (None, 1..=7) => h + 12,
+        _ => h,
+    };
+    Time::from_hms(h, m, 0).ok()
+}
+
+fn number(w: &str) -> Option<i64> {
+    const WORDS: [&str; 13] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven", "twelve",
+    ];
+    match w {
+        "a" | "an" => Some(1),
+        "couple" => Some(2),
+        _ => WORDS
+            .iter()
+            .position(|n| *n == w)
+            .map(|n| n as i64)
+            .or_else(|| w.parse().ok()),
+    }
+}
+
+/// `n` of `unit`; `None` for other words and amounts out of range.
+fn unit_duration(unit: &str, n: i64) -> Option<Duration> {
+    let secs: i64 = match unit.trim_end_matches('s') {
+        "minute" | "min" => 60,
+        "hour" | "hr" => 3_600,
+        "day" => 86_400,
+        "week" => 7 * 86_400,
+        _ => return None,
+    };
+    n.checked_mul(secs).map(Duration::seconds)
+}
+
+fn is_clock_unit(unit: &str) -> bool {
+    matches!(unit.trim_end_matches('s'), "minute" | "min" | "hour" | "hr")
+}
+
+fn weekday(w: &str) -> Option<Weekday> {
+    Some(match w {
+        "monday" => Weekday::Monday,
+        "tuesday" | "tue" | "tues" => Weekday::Tuesday,
+        "wednesday" | "wed" => Weekday::Wednesday,
+        "thursday" | "thu" | "thurs" => Weekday::Thursday,
+        "friday" | "fri" => Weekday::Friday,
+        "saturday" => Weekday::Saturday,
+        "sunday" => Weekday::Sunday,
+        _ => return None,
+    })
+}
+
+/// The next such weekday strictly after `from` (1-7 days ahead).
+fn next_weekday(from: time::Date, day: Weekday) -> time::Date {
+    let ahead = (7 + day.number_days_from_monday() - from.weekday().number_days_from_monday()) % 7;
+    from + Duration::days(if ahead == 0 { 7 } else { i64::from(ahead) })
+}
+
+/// `from` itself if it already is that weekday, else the next one.
+fn upcoming(from: time::Date, day: Weekday) -> time::Date {
+    if from.weekday() == day {
+        from
+    } else {
+        next_weekday(from, day)
+    }
+}
+
+fn matches_at(words: &[Word], i: usize, phrase: &[&str]) -> bool {
+    i + phrase.len() <= words.len()
+        && words[i..i + phrase.len()]
+            .iter()
+            .zip(phrase)
+            .all(|(w, p)| !w.used && w.norm == *p)
+}
+
+fn mark(words: &mut [Word], start: usize, len: usize) {
+    for w in &mut words[start..start + len] {
+        w.used = true;
+    }
+}
+
+/// Also drop connectors right before a removed phrase ("... *by next* friday").
+fn absorb_connectors(words: &mut [Word], mut start: usize) {
+    while start > 0
+        && !words[start - 1].used
+        && CONNECTORS.contains(&words[start - 1].norm.as_str())
+    {
+        start -= 1;
+        words[start].used = true;
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    // A Wednesday.
+    const NOW: OffsetDateTime = datetime!(2026-03-11 10:00 UTC);
+
+    #[test]
+    fn dictated_sentence_is_split_into_fields() {
+        let d = parse(
+            "remind me to call the dentist next tuesday afternoon its urgent",
+            NOW,
+        );
+        assert_eq!(d.title, "Call the dentist");
+        assert_eq!(d.priority, Some(Priority::P1));
+        assert_eq!(d.due, Some(datetime!(2026-03-17 14:00 UTC)));
+
+        let d = parse(
+            "um buy seeds for the garden project tomorrow at 9 am, not urgent",
+            NOW,
+        );
+        assert_eq!(d.title, "Buy seeds");
+        assert_eq!(d.project.as_deref(), Some("garden"));
+        assert_eq!(d.priority, Some(Priority::P4));
+        assert_eq!(d.due, Some(datetime!(2026-03-12 9:00 UTC)));
+    }
+
+    #[test]
+    fn relative_and_bare_times() {
+        assert_eq!(
+            parse("water plants in 2 hours", NOW).due,
+            Some(datetime!(2026-03-11 12:00 UTC))
+        );
+        let d = parse("I need to file taxes by friday tagged finance", NOW);
+        assert_eq!(d.title, "File taxes");
+        assert_eq!(d.tags, ["finance"]);
+        assert_eq!(d.due, Some(datetime!(2026-03-13 17:00 UTC)));
+
+        // 8am has passed today, so it means tomorrow.
+        assert_eq!(
+            parse("stretch at 8am", NOW).due,
+            Some(datetime!(2026-03-12 8:00 UTC))
+        );
+        assert_eq!(
+            parse("Read a book", NOW),
+            Dictation {
+                title: "Read a book".to_string(),
+                ..Dictation::default()
+            }
+        );
+    }
+
+    #[test]
+    fn amounts_too_large_for_a_date_are_not_due_phrases() {
+        assert_eq!(parse_due_phrase("in 99999999999 days", NOW), None);
+        assert_eq!(parse_due_phrase("in 999999999999999 weeks", NOW), None);
+        assert_eq!(
+            parse_due_phrase("in 9223372036854775807 minutes", NOW),
+            None
+        );
+        assert_eq!(
+            parse("probe in 99999999999 days", NOW).due,
+            None,
+            "left in the title rather than panicking"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Due dates as people type them on the command line or in a form.
+//!
+//! `--due` accepts, in order of preference:
+//! - RFC3339: `2026-03-13T17:00:00Z`
+//! - a local date and time: `2026-03-13 09:00` (or `2026-03-13T09:00:30`)
+//! - a plain date: `2026-03-13` (17:00, like a dictated day without a time)
+//! - a phrase: `tomorrow`, `friday 5pm`, `in 3 days`, `next week`, `tonight`,
+//!   `at 9am`, read by the same rules as `add --dictated`
+//!
+//! Everything but RFC3339 is wall-clock time in the configured [`Zone`]
+//! ([`parse_due`] uses `now`'s offset) and is stored converted to UTC.
+//! [`DueAt`] stays a plain timestamp; this is only the front door to it.
+
+use time::{
+    Date, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset,
+    format_description::BorrowedFormatItem, macros::format_description,
+};
+
+use crate::{
+    app::{dictation, timezone::Zone},
+    domain::todo::DueAt,
+};
+
+const DATE: &[BorrowedFormatItem<'_>] = format_description!("[year]-[month]-[day]");
+
+const DATE_TIMES: [&[BorrowedFormatItem<'_>]; 4] = [
+    format_description!("[year]-[month]-[day] [hour]:[minute]"),
+    format_description!("[year]-[month]-[day] [hour]:[minute]:[second]"),
+    format_description!("[year]-[month]-[day]T[hour]:[minute]"),
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]"),
+];
+
+/// Read a due date in `now`'s offset, or explain what forms are understood.
+pub fn parse_due(input: &str, now: OffsetDateTime) -> Result<DueAt, String> {
+    parse_due_in(input, now, &Zone::Fixed(now.offset()))
+}
+
+/// Read a due date with wall-clock input taken in `zone`.
+pub fn parse_due_in(input: &str, now: OffsetDateTime, zone: &Zone) -> Result<DueAt, String> {
+    let input = input.trim();
+    if let Ok(due) = DueAt::parse_rfc3339(input) {
+        return Ok(due);
+    }
+    let utc =
+        |wall: PrimitiveDateTime| DueAt::from_dt(zone.resolve(wall).to_offset(UtcOffset::UTC));
+    if let Some(wall) = DATE_TIMES
+        .iter()
+        .find_map(|format| PrimitiveDateTime::parse(input, format).ok())
+    {
+        return Ok(utc(wall));
+    }
+    if let Ok(date) = Date::parse(input, DATE) {
+        return Ok(utc(
+            date.with_time(Time::from_hms(17, 0, 0).expect("valid time"))
+        ));
+    }
+    // Dictation skips `mon`/`sat`/`sun` as too ambiguous in a sentence; a
+    // --due value is nothing but a date, so they're safe here.
+    let phrase = input
+        .split_whitespace()
+        .map(|w| match w.to_ascii_lowercase().as_str() {
+            "mon" => "monday",
+            "sat" => "saturday",
+            "sun" => "sunday",
+            _ => w,
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    // The phrase is read against a local `now`, then its wall time is placed
+    // again so a DST change between now and then is accounted for.
+    dictation::parse_due_phrase(&phrase, zone.local(now))
+        .map(|at| utc(PrimitiveDateTime::new(at.date(), at.time())))
+        .ok_or_else(|| {
+            format!(
+                "can't read due date {input:?} (try tomorrow, \"friday 5pm\", \"in 3 days\", next week, \"2026-03-13 09:00\" or RFC3339)"
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    // A Wednesday.
+    const NOW: OffsetDateTime = datetime!(2026-03-11 10:00 UTC);
+
+    fn due(input: &str) -> Option<OffsetDateTime> {
+        parse_due(input, NOW).ok().map(DueAt::as_dt)
+    }
+
+    #[test]
+    fn accepts_timestamps_dates_and_phrases() {
+        assert_eq!(
+            due("2026-03-20T09:00:00Z"),
+            Some(datetime!(2026-03-20 9:00 UTC))
+        );
+        assert_eq!(due("2026-03-20"), Some(datetime!(2026-03-20 17:00 UTC)));
+        assert_eq!(due("tomorrow"), Some(datetime!(2026-03-12 17:00 UTC)));
+        assert_eq!(due("Friday 5pm"), Some(datetime!(2026-03-13 17:00 UTC)));
+        assert_eq!(due("in 3 days"), Some(datetime!(2026-03-14 17:00 UTC)));
+        assert_eq!(due("next week"), Some(datetime!(2026-03-16 17:00 UTC)));
+        assert_eq!(due("sat at 10am"), Some(datetime!(2026-03-14 10:00 UTC)));
+
+        assert_eq!(due("call mom tomorrow"), None);
+        assert_eq!(due("soonish"), None);
+        assert!(parse_due("", NOW).is_err());
+    }
+
+    #[test]
+    fn wall_clock_input_is_read_in_the_zone_and_stored_as_utc() {
+        let berlin = Zone::from_posix("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        let due = |input| parse_due_in(input, NOW, &berlin).unwrap().as_dt();
+
+        let winter = due("2026-01-02 09:00");
+        assert_eq!(winter, datetime!(2026-01-02 8:00 UTC));
+        assert_eq!(winter.offset(), UtcOffset::UTC);
+        assert_eq!(
+            due("2026-07-02T09:00:30"),
+            datetime!(2026-07-02 7:00:30 UTC)
+        );
+        assert_eq!(due("2026-03-20"), datetime!(2026-03-20 16:00 UTC));
+        // Friday is still winter time; 20 days on is past the switch to summer.
+        assert_eq!(due("friday 5pm"), datetime!(2026-03-13 16:00 UTC));
+        assert_eq!(due("in 20 days"), datetime!(2026-03-31 15:00 UTC));
+        assert_eq!(due("2026-03-20T09:00:00Z"), datetime!(2026-03-20 9:00 UTC));
+    }
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::domain::todo::TodoId;
+
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error("todo not found")]
@@ -11,6 +13,39 @@ pub enum AppError {
     #[error("todo is already open")]
     AlreadyOpen,
 
+    #[error("a timer is already running for this todo")]
+    TimerRunning,
+
+    #[error("no timer is running for this todo")]
+    TimerNotRunning,
+
+    #[error("todo is not on the someday list")]
+    NotSomeday,
+
+    #[error("only done todos can be archived")]
+    NotDone,
+
+    #[error("todo is not archived")]
+    NotArchived,
+
+    #[error("project not found")]
+    ProjectNotFound,
+
+    #[error("a todo cannot depend on itself")]
+    SelfDependency,
+
+    #[error("that would make a dependency cycle")]
+    DependencyCycle,
+
+    #[error(
+        "duplicate todo IDs: {}",
+        .0.iter().map(TodoId::short).collect::<Vec<_>>().join(", ")
+    )]
+    DuplicateIds(Vec<TodoId>),
+
+    #[error("batch rejected at operation {op}: {reason}")]
+    BatchRejected { op: usize, reason: &'static str },
+
     #[error("refusing destructive action without confirmation (use --yes)")]
     ConfirmationRequired,
 }
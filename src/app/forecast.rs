@@ -0,0 +1,152 @@
+//! Backlog forecasts from past completion rates.
+//!
+//! Each project's rate is the number of todos completed per day over a recent
+//! window. The open backlog is assumed to be worked at that rate in priority
+//! order (then due date, then age), which gives a date the backlog is cleared
+//! and, for every dated todo, whether its turn comes before it is due.
+
+use std::collections::BTreeMap;
+
+use time::{Duration, OffsetDateTime};
+
+use crate::domain::todo::{Status, Todo, TodoId};
+
+/// A todo the current pace won't reach before its due date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtRisk {
+    pub id: TodoId,
+    pub title: String,
+    pub due: OffsetDateTime,
+    /// When it would be done at the current pace (`None` = not at all).
+    pub expected: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectForecast {
+    pub project: String,
+    pub open: usize,
+    /// Completed within the window.
+    pub completed: usize,
+    /// Completions per day over the window.
+    pub per_day: f64,
+    /// When the last open todo would be done (`None` if nothing gets done).
+    pub clear_by: Option<OffsetDateTime>,
+    /// Open todos that have a due date.
+    pub dated: usize,
+    pub at_risk: Vec<AtRisk>,
+}
+
+/// Forecast every project with open todos, by project name.
+pub fn forecast(todos: &[Todo], window: Duration, now: OffsetDateTime) -> Vec<ProjectForecast> {
+    let since = now - window;
+    let days = (window.as_seconds_f64() / 86_400.0).max(1.0 / 24.0);
+
+    let mut completed: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut open: BTreeMap<&str, Vec<&Todo>> = BTreeMap::new();
+    for t in todos {
+        match t.status {
+            Status::Done { completed_at } if completed_at >= since && completed_at <= now => {
+                *completed.entry(t.project.as_str()).or_default() += 1;
+            }
+            Status::Open if t.is_active() => open.entry(t.project.as_str()).or_default().push(t),
+            _ => {}
+        }
+    }
+
+    open.into_iter()
+        .map(|(project, mut queue)| {
+            queue.sort_by(|a, b| {
+                a.priority
+                    .cmp(&b.priority)
+                    .then_with(|| match (a.due, b.due) {
+                        (Some(x), Some(y)) => x.cmp(&y),
+                        (Some(_), None) => std::cmp::Ordering::Less,
+                        (None, Some(_)) => std::cmp::Ordering::Greater,
+                        (None, None) => std::cmp::Ordering::Equal,
+                    })
+                    .then_with(|| a.created_at.cmp(&b.created_at))
+            });
+
+            let done = completed.get(project).copied().unwrap_or(0);
+            let per_day = done as f64 / days;
+            // Time until the n-th todo in the queue (1-based) is done.
+            let finish = |n: usize| {
+                (per_day > 0.0).then(|| now + Duration::seconds_f64(n as f64 / per_day * 86_400.0))
+            };
+
+            let at_risk = queue
+                .iter()
+                .enumerate()
+                .filter_map(|(i, t)| {
+                    let due = t.due?.as_dt();
+                    let expected = finish(i + 1);
+                    expected.is_none_or(|at| at > due).then(|| AtRisk {
+                        id: t.id,
+                        title: t.title.as_str().to_string(),
+                        due,
+                        expected,
+                    })
+                })
+                .collect();
+
+            ProjectForecast {
+                project: project.to_string(),
+                open: queue.len(),
+                completed: done,
+                per_day,
+                clear_by: finish(queue.len()),
+                dated: queue.iter().filter(|t| t.due.is_some()).count(),
+                at_risk,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{DueAt, Priority, ProjectName, Title};
+    use time::macros::datetime;
+
+    fn todo(project: &str, title: &str) -> Todo {
+        let mut t = Todo::new(Title::parse(title).unwrap());
+        t.project = ProjectName::parse(project).unwrap();
+        t
+    }
+
+    #[test]
+    fn projects_pace_and_flags_unrealistic_due_dates() {
+        let now = datetime!(2026-03-01 12:00 UTC);
+        let mut todos = Vec::new();
+        // Two done per week in Work, nothing in Home.
+        for day in [2, 5, 9, 12] {
+            let mut t = todo("Work", "old");
+            t.status = Status::Done {
+                completed_at: now - Duration::days(day),
+            };
+            todos.push(t);
+        }
+        let mut urgent = todo("Work", "urgent");
+        urgent.priority = Priority::P1;
+        urgent.due = Some(DueAt::from_dt(now + Duration::days(7)));
+        let mut tight = todo("Work", "tight");
+        tight.due = Some(DueAt::from_dt(now + Duration::days(5)));
+        todos.extend([urgent.clone(), tight.clone(), todo("Home", "someday")]);
+
+        let f = forecast(&todos, Duration::days(14), now);
+        assert_eq!(f.len(), 2);
+
+        let home = &f[0];
+        assert_eq!((home.project.as_str(), home.open), ("Home", 1));
+        assert_eq!(home.clear_by, None);
+
+        let work = &f[1];
+        assert_eq!((work.open, work.completed, work.dated), (2, 4, 2));
+        assert!((work.per_day - 4.0 / 14.0).abs() < 1e-9);
+        // 3.5 days per todo: urgent (P1) first at +3.5d, tight at +7d > +5d.
+        let clear_by = work.clear_by.unwrap();
+        assert!((clear_by - (now + Duration::days(7))).abs() < Duration::seconds(1));
+        assert_eq!(work.at_risk.len(), 1);
+        assert_eq!(work.at_risk[0].id, tight.id);
+    }
+}
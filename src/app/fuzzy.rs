@@ -0,0 +1,80 @@
+//! Fuzzy matching for the todo picker (`done` without an ID, ...).
+//!
+//! A query word matches when its characters appear in the text in order,
+//! ignoring case, as in skim or fzf: `rprt` finds "Quarterly report". Runs of
+//! consecutive characters and characters that start a word score higher;
+//! gaps score lower. Every word of the query has to match.
+
+/// Score of `query` against `text`, higher is better; `None` if a word of the
+/// query doesn't match. An empty query matches everything with score 0.
+pub fn score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    query
+        .split_whitespace()
+        .map(|word| score_word(word, &text))
+        .sum()
+}
+
+fn score_word(word: &str, text: &[char]) -> Option<i32> {
+    let mut total = 0;
+    let mut from = 0;
+    let mut prev: Option<usize> = None;
+    for c in word.chars().flat_map(char::to_lowercase) {
+        let at = from + text[from..].iter().position(|&t| t == c)?;
+        let word_start = at == 0 || !text[at - 1].is_alphanumeric();
+        total += match prev {
+            Some(p) if p + 1 == at => 8,
+            Some(p) => -((at - p - 1).min(10) as i32),
+            None => -(at.min(10) as i32),
+        };
+        if word_start {
+            total += 10;
+        }
+        total += 1;
+        prev = Some(at);
+        from = at + 1;
+    }
+    Some(total)
+}
+
+/// Indices of the `items` matching `query`, best first; equal scores keep
+/// their order.
+pub fn rank<S: AsRef<str>>(query: &str, items: &[S]) -> Vec<usize> {
+    let mut scored: Vec<(usize, i32)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| score(query, item.as_ref()).map(|s| (i, s)))
+        .collect();
+    scored.sort_by_key(|&(i, s)| (std::cmp::Reverse(s), i));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characters_match_in_order_ignoring_case() {
+        assert!(score("rprt", "Quarterly report").is_some());
+        assert!(score("RePo", "quarterly report").is_some());
+        assert_eq!(score("rq", "Quarterly report"), None);
+        assert_eq!(score("", "anything"), Some(0));
+        // Every word must match, in any order.
+        assert!(score("work rep", "Report  Work").is_some());
+        assert_eq!(score("work home", "Report  Work"), None);
+    }
+
+    #[test]
+    fn word_starts_and_runs_rank_first() {
+        let items = [
+            "Buy milk  Home",
+            "Submit expense report  Work #finance",
+            "Fix CI flaky test  Work #build",
+        ];
+        assert_eq!(rank("rep", &items), [1]);
+        assert_eq!(rank("work", &items), [1, 2]);
+        // "fi" starts "Fix" but only appears inside "#finance".
+        assert_eq!(rank("fi", &items), [2, 1]);
+        assert_eq!(rank("", &items), [0, 1, 2]);
+    }
+}
@@ -0,0 +1,192 @@
+//! Undo/redo history kept by [`Store`](crate::app::store::Store).
+//!
+//! Every mutation records the affected todo as it was before and after. The
+//! changes made by one command are grouped into a [`Step`] when the caller
+//! commits them; undoing a step puts every todo back to its `before` state
+//! (re-adding deleted ones, removing added ones) and redoing puts them back to
+//! `after`. Project records are kept the same way, as the whole list before
+//! and after. The history is plain data so the CLI can keep it next to the
+//! database between runs.
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::domain::{
+    project::Project,
+    todo::{Todo, TodoId},
+};
+
+/// Steps kept on the undo stack; older ones are dropped.
+pub const MAX_STEPS: usize = 50;
+
+/// One todo before and after a mutation (`None` = didn't exist).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub id: TodoId,
+    pub before: Option<Todo>,
+    pub after: Option<Todo>,
+}
+
+/// Project records before and after a command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectsChange {
+    pub before: Vec<Project>,
+    pub after: Vec<Project>,
+}
+
+/// The changes made by one command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    pub label: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+    pub changes: Vec<Change>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projects: Option<ProjectsChange>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    /// Changes not yet committed as a step.
+    #[serde(skip)]
+    pending: Vec<Change>,
+    #[serde(skip)]
+    pending_projects: Option<ProjectsChange>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl History {
+    /// Record a mutation of one todo. Repeated changes to the same todo
+    /// before the next commit collapse into one.
+    pub fn record(&mut self, before: Option<Todo>, after: Option<Todo>) {
+        if before == after {
+            return;
+        }
+        let Some(id) = after.as_ref().or(before.as_ref()).map(|t| t.id) else {
+            return;
+        };
+        if let Some(i) = self.pending.iter().position(|c| c.id == id) {
+            if self.pending[i].before == after {
+                self.pending.remove(i);
+            } else {
+                self.pending[i].after = after;
+            }
+            return;
+        }
+        self.pending.push(Change { id, before, after });
+    }
+
+    /// Record a change to the project records.
+    pub fn record_projects(&mut self, before: Vec<Project>, after: Vec<Project>) {
+        match &mut self.pending_projects {
+            Some(change) => change.after = after,
+            None => self.pending_projects = Some(ProjectsChange { before, after }),
+        }
+    }
+
+    /// Group the pending changes into one undoable step. Returns `false` if
+    /// nothing changed.
+    pub fn commit(&mut self, label: &str, at: OffsetDateTime) -> bool {
+        let projects = self.pending_projects.take().filter(|c| c.before != c.after);
+        if self.pending.is_empty() && projects.is_none() {
+            return false;
+        }
+        self.undo.push(Step {
+            label: label.to_string(),
+            at,
+            changes: std::mem::take(&mut self.pending),
+            projects,
+        });
+        if self.undo.len() > MAX_STEPS {
+            self.undo.drain(..self.undo.len() - MAX_STEPS);
+        }
+        self.redo.clear();
+        self.dirty = true;
+        true
+    }
+
+    /// Forget uncommitted changes (e.g. after seeding a new database).
+    pub fn discard_pending(&mut self) {
+        self.pending.clear();
+        self.pending_projects = None;
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Step> {
+        self.dirty = true;
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Step> {
+        self.dirty = true;
+        self.redo.pop()
+    }
+
+    /// Put a step that was just undone on the redo stack.
+    pub fn push_redo(&mut self, step: Step) {
+        self.redo.push(step);
+    }
+
+    /// Put a step that was just redone back on the undo stack.
+    pub fn push_undo(&mut self, step: Step) {
+        self.undo.push(step);
+    }
+
+    /// Steps that can be undone, most recent last.
+    pub fn undo_steps(&self) -> &[Step] {
+        &self.undo
+    }
+
+    /// Steps that can be redone, most recent last.
+    pub fn redo_steps(&self) -> &[Step] {
+        &self.redo
+    }
+
+    /// Whether the stacks changed since the history was loaded.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{Priority, Title};
+    use time::macros::datetime;
+
+    #[test]
+    fn changes_collapse_per_todo_and_commit_clears_redo() {
+        let at = datetime!(2026-03-01 12:00 UTC);
+        let a = Todo::new(Title::parse("A").unwrap());
+        let mut a2 = a.clone();
+        a2.priority = Priority::P1;
+        let mut a3 = a2.clone();
+        a3.priority = Priority::P2;
+
+        let mut h = History::default();
+        assert!(!h.commit("noop", at));
+        h.record(Some(a.clone()), Some(a2.clone()));
+        h.record(Some(a2.clone()), Some(a3.clone()));
+        assert!(h.commit("edit", at));
+        let step = &h.undo_steps()[0];
+        assert_eq!(step.changes.len(), 1);
+        assert_eq!(step.changes[0].before.as_ref(), Some(&a));
+        assert_eq!(step.changes[0].after.as_ref(), Some(&a3));
+
+        // Adding then deleting in one command leaves nothing to undo.
+        let b = Todo::new(Title::parse("B").unwrap());
+        h.record(None, Some(b.clone()));
+        h.record(Some(b), None);
+        assert!(!h.commit("add+delete", at));
+
+        let step = h.pop_undo().unwrap();
+        h.push_redo(step);
+        assert_eq!(h.redo_steps().len(), 1);
+        h.record(Some(a.clone()), Some(a2));
+        h.commit("edit again", at);
+        assert!(h.redo_steps().is_empty());
+        assert!(h.is_dirty());
+    }
+}
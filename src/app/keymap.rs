@@ -0,0 +1,391 @@
+//! Keybindings for the interactive UI (`[keybindings]` in config.toml).
+//!
+//! Each [`Action`] has default keys; the config table replaces them per
+//! action: `done = ["x", "space"]`, or a single `quit = "ctrl+q"`. Keys are a
+//! character or a named key (`enter`, `esc`, `up`, `f5`, ...), optionally
+//! prefixed with `ctrl+`, `alt+` and `shift+`. The resulting [`KeyMap`] must
+//! not give one key to two actions.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Something a key can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Add,
+    Edit,
+    Done,
+    Delete,
+    Search,
+    Undo,
+    Redo,
+    Up,
+    Down,
+    Help,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::Add,
+        Action::Edit,
+        Action::Done,
+        Action::Delete,
+        Action::Search,
+        Action::Undo,
+        Action::Redo,
+        Action::Up,
+        Action::Down,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Add => "add",
+            Action::Edit => "edit",
+            Action::Done => "done",
+            Action::Delete => "delete",
+            Action::Search => "search",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Help => "help",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Add => "add a todo",
+            Action::Edit => "edit the selected todo",
+            Action::Done => "toggle done on the selected todo",
+            Action::Delete => "delete the selected todo",
+            Action::Search => "search titles and notes",
+            Action::Undo => "undo the last change",
+            Action::Redo => "redo the last undone change",
+            Action::Up => "select the previous todo",
+            Action::Down => "select the next todo",
+            Action::Help => "show the keybindings",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        let s = input.trim().to_ascii_lowercase();
+        Self::ALL.into_iter().find(|a| a.name() == s)
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Add => &["a"],
+            Action::Edit => &["e"],
+            Action::Done => &["x", "space"],
+            Action::Delete => &["d"],
+            Action::Search => &["/"],
+            Action::Undo => &["u"],
+            Action::Redo => &["ctrl+r"],
+            Action::Up => &["k", "up"],
+            Action::Down => &["j", "down"],
+            Action::Help => &["?"],
+            Action::Quit => &["q", "ctrl+c"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Esc,
+    Tab,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    F(u8),
+}
+
+const NAMED: [(&str, KeyCode); 14] = [
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("space", KeyCode::Char(' ')),
+];
+
+/// A key with its modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl Key {
+    pub fn new(code: KeyCode) -> Self {
+        Self {
+            code,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    /// Parse `x`, `ctrl+r`, `alt+shift+up`, `f5`, ... Letters with `ctrl+`
+    /// or `alt+` are case-insensitive (terminals can't tell them apart).
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let mut key = Key::new(KeyCode::Esc);
+        let mut rest = input;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            let Some((modifier, tail)) = lower.split_once('+').filter(|(_, t)| !t.is_empty())
+            else {
+                break;
+            };
+            match modifier {
+                "ctrl" | "control" => key.ctrl = true,
+                "alt" | "meta" => key.alt = true,
+                "shift" => key.shift = true,
+                _ => break,
+            }
+            rest = &rest[rest.len() - tail.len()..];
+        }
+
+        let mut chars = rest.chars();
+        key.code = match (chars.next(), chars.next()) {
+            (Some(c), None) if !c.is_whitespace() && !c.is_control() => {
+                KeyCode::Char(if key.ctrl || key.alt {
+                    c.to_ascii_lowercase()
+                } else {
+                    c
+                })
+            }
+            _ => {
+                let name = rest.to_ascii_lowercase();
+                let function = name
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse::<u8>().ok())
+                    .filter(|n| (1..=12).contains(n));
+                match NAMED.iter().find(|(n, _)| *n == name) {
+                    Some((_, code)) => *code,
+                    None => match function {
+                        Some(n) => KeyCode::F(n),
+                        None => {
+                            return Err(format!(
+                                "unknown key {input:?} (use a character, f1-f12 or {}, with optional ctrl+, alt+, shift+)",
+                                NAMED.map(|(n, _)| n).join(", ")
+                            ));
+                        }
+                    },
+                }
+            }
+        };
+        Ok(key)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (on, name) in [
+            (self.ctrl, "ctrl+"),
+            (self.alt, "alt+"),
+            (self.shift, "shift+"),
+        ] {
+            if on {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(c) => match NAMED.iter().find(|(_, code)| *code == self.code) {
+                Some((name, _)) => f.write_str(name),
+                None => write!(f, "{c}"),
+            },
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => {
+                let (name, _) = NAMED
+                    .iter()
+                    .find(|(_, c)| *c == code)
+                    .expect("every named key is listed");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+/// One key (`"x"`) or several (`["x", "space"]`) in config.toml.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeySpec {
+    fn keys(&self) -> &[String] {
+        match self {
+            KeySpec::One(key) => std::slice::from_ref(key),
+            KeySpec::Many(keys) => keys,
+        }
+    }
+}
+
+/// The keys of every action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    bindings: BTreeMap<Action, Vec<Key>>,
+    /// Actions whose keys come from config.toml.
+    custom: Vec<Action>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|a| {
+                let keys = a.default_keys().iter().map(|k| Key::parse(k).unwrap());
+                (a, keys.collect())
+            })
+            .collect();
+        Self {
+            bindings,
+            custom: Vec::new(),
+        }
+    }
+}
+
+impl KeyMap {
+    /// The defaults with `overrides` (the `[keybindings]` table) applied.
+    /// Errors name the entry at fault.
+    pub fn from_config(overrides: &BTreeMap<String, KeySpec>) -> Result<Self, String> {
+        let mut map = KeyMap::default();
+        for (name, spec) in overrides {
+            let Some(action) = Action::parse(name) else {
+                let names: Vec<_> = Action::ALL.iter().map(|a| a.name()).collect();
+                return Err(format!(
+                    "[keybindings] unknown action {name} (use {})",
+                    names.join(", ")
+                ));
+            };
+            let keys = spec
+                .keys()
+                .iter()
+                .map(|k| Key::parse(k).map_err(|e| format!("[keybindings] {name}: {e}")))
+                .collect::<Result<Vec<_>, _>>()?;
+            if keys.is_empty() {
+                return Err(format!("[keybindings] {name}: needs at least one key"));
+            }
+            map.bindings.insert(action, keys);
+            map.custom.push(action);
+        }
+
+        let mut owner: BTreeMap<Key, Action> = BTreeMap::new();
+        for (&action, keys) in &map.bindings {
+            for &key in keys {
+                if let Some(other) = owner.insert(key, action).filter(|o| *o != action) {
+                    return Err(format!(
+                        "[keybindings] {key} is bound to both {} and {}",
+                        other.name(),
+                        action.name()
+                    ));
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// The action bound to `key`, if any.
+    pub fn action_for(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(a, _)| *a)
+    }
+
+    /// Were `action`'s keys set in config.toml?
+    pub fn is_custom(&self, action: Action) -> bool {
+        self.custom.contains(&action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(entries: &[(&str, KeySpec)]) -> BTreeMap<String, KeySpec> {
+        entries
+            .iter()
+            .map(|(n, s)| (n.to_string(), s.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn keys_parse_and_print_canonically() {
+        for (input, shown) in [
+            ("x", "x"),
+            ("X", "X"),
+            ("Ctrl+R", "ctrl+r"),
+            ("alt+shift+Up", "alt+shift+up"),
+            ("space", "space"),
+            ("F5", "f5"),
+            ("+", "+"),
+            ("ctrl++", "ctrl++"),
+        ] {
+            let key = Key::parse(input).unwrap_or_else(|e| panic!("{input}: {e}"));
+            assert_eq!(key.to_string(), shown, "{input}");
+        }
+        for bad in ["", "f13", "hyper+x", "ctrl+", "enterr"] {
+            assert!(Key::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn overrides_replace_defaults_and_conflicts_are_refused() {
+        let map = KeyMap::from_config(&overrides(&[
+            ("Done", KeySpec::One("enter".into())),
+            ("quit", KeySpec::Many(vec!["ctrl+q".into(), "x".into()])),
+        ]))
+        .unwrap();
+        assert_eq!(
+            map.action_for(Key::parse("enter").unwrap()),
+            Some(Action::Done)
+        );
+        assert_eq!(map.action_for(Key::parse("x").unwrap()), Some(Action::Quit));
+        assert_eq!(map.action_for(Key::parse("q").unwrap()), None);
+        assert!(map.is_custom(Action::Quit) && !map.is_custom(Action::Add));
+
+        let err =
+            KeyMap::from_config(&overrides(&[("add", KeySpec::One("d".into()))])).unwrap_err();
+        assert_eq!(err, "[keybindings] d is bound to both add and delete");
+        let err =
+            KeyMap::from_config(&overrides(&[("fly", KeySpec::One("f".into()))])).unwrap_err();
+        assert!(err.starts_with("[keybindings] unknown action fly"), "{err}");
+        let err =
+            KeyMap::from_config(&overrides(&[("add", KeySpec::Many(Vec::new()))])).unwrap_err();
+        assert!(err.contains("needs at least one key"), "{err}");
+    }
+}
@@ -0,0 +1,217 @@
+//! Eisenhower matrix: active todos sorted into urgent/important quadrants.
+//!
+//! Urgency comes from the due date (overdue, or due within a horizon) and
+//! importance from the effective priority (P1 and P2). Moving a todo to
+//! another quadrant is a [`TodoPatch`] of the priority and due date that
+//! would put it there.
+
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+    app::query::effective_priorities,
+    domain::todo::{DueAt, Priority, Todo, TodoPatch},
+};
+
+/// How far from the horizon a todo moved out of the urgent half is due.
+const RESCHEDULE_BY: Duration = Duration::weeks(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Quadrant {
+    /// Urgent and important.
+    DoFirst,
+    /// Important, not urgent.
+    Schedule,
+    /// Urgent, not important.
+    Delegate,
+    /// Neither.
+    Eliminate,
+}
+
+impl Quadrant {
+    /// In reading order: top row, then bottom row.
+    pub const ALL: [Quadrant; 4] = [
+        Quadrant::DoFirst,
+        Quadrant::Schedule,
+        Quadrant::Delegate,
+        Quadrant::Eliminate,
+    ];
+
+    pub fn of(urgent: bool, important: bool) -> Self {
+        match (urgent, important) {
+            (true, true) => Quadrant::DoFirst,
+            (false, true) => Quadrant::Schedule,
+            (true, false) => Quadrant::Delegate,
+            (false, false) => Quadrant::Eliminate,
+        }
+    }
+
+    /// `do`, `schedule`, `delegate`, `eliminate`, or their number 1-4.
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "1" | "do" | "do-first" | "dofirst" => Some(Quadrant::DoFirst),
+            "2" | "schedule" => Some(Quadrant::Schedule),
+            "3" | "delegate" => Some(Quadrant::Delegate),
+            "4" | "eliminate" | "drop" => Some(Quadrant::Eliminate),
+            _ => None,
+        }
+    }
+
+    /// 1-4, in [`Quadrant::ALL`] order.
+    pub fn number(self) -> usize {
+        self as usize + 1
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Quadrant::DoFirst => "Do first",
+            Quadrant::Schedule => "Schedule",
+            Quadrant::Delegate => "Delegate",
+            Quadrant::Eliminate => "Eliminate",
+        }
+    }
+
+    pub fn is_urgent(self) -> bool {
+        matches!(self, Quadrant::DoFirst | Quadrant::Delegate)
+    }
+
+    pub fn is_important(self) -> bool {
+        matches!(self, Quadrant::DoFirst | Quadrant::Schedule)
+    }
+}
+
+/// Where urgency ends: `now + within`, or `None` past the latest date.
+pub fn horizon(now: OffsetDateTime, within: Duration) -> Option<OffsetDateTime> {
+    now.checked_add(within)
+}
+
+/// Due by `horizon` (overdue included); with no horizon, anything dated is.
+pub fn is_urgent(todo: &Todo, horizon: Option<OffsetDateTime>) -> bool {
+    todo.due
+        .is_some_and(|d| horizon.is_none_or(|by| d.as_dt() <= by))
+}
+
+pub fn is_important(priority: Priority) -> bool {
+    priority <= Priority::P2
+}
+
+/// Active todos of `todos` by quadrant, in [`Quadrant::ALL`] order; each
+/// quadrant lists the most important first, then the soonest due.
+pub fn matrix(todos: &[Todo], now: OffsetDateTime, within: Duration) -> Vec<(Quadrant, Vec<Todo>)> {
+    let eff = effective_priorities(todos);
+    let by = horizon(now, within);
+    let mut cells: Vec<(Quadrant, Vec<(Priority, Todo)>)> =
+        Quadrant::ALL.iter().map(|q| (*q, Vec::new())).collect();
+    for todo in todos.iter().filter(|t| t.is_active()) {
+        let priority = eff.get(&todo.id).map_or(todo.priority, |e| e.priority);
+        let q = Quadrant::of(is_urgent(todo, by), is_important(priority));
+        if let Some((_, cell)) = cells.iter_mut().find(|(c, _)| *c == q) {
+            cell.push((priority, todo.clone()));
+        }
+    }
+    // Undated todos after dated ones.
+    let due = |t: &Todo| (t.due.is_none(), t.due);
+    cells
+        .into_iter()
+        .map(|(q, mut cell)| {
+            cell.sort_by(|(pa, a), (pb, b)| {
+                pa.cmp(pb)
+                    .then_with(|| due(a).cmp(&due(b)))
+                    .then_with(|| a.created_at.cmp(&b.created_at))
+            });
+            (q, cell.into_iter().map(|(_, t)| t).collect())
+        })
+        .collect()
+}
+
+/// The changes that move `todo` into `to`: P2 to become important, P3 to stop
+/// being so; due at the horizon to become urgent, a week past it to stop.
+/// Only what has to change is touched, so an empty patch means it's there.
+/// A horizon past the latest date makes every dated todo urgent, so those
+/// become urgent due now and stop being so undated.
+pub fn move_to(todo: &Todo, to: Quadrant, now: OffsetDateTime, within: Duration) -> TodoPatch {
+    let mut patch = TodoPatch::default();
+    match (is_important(todo.priority), to.is_important()) {
+        (false, true) => patch.priority = Some(Priority::P2),
+        (true, false) => patch.priority = Some(Priority::P3),
+        _ => {}
+    }
+    let by = horizon(now, within);
+    match (is_urgent(todo, by), to.is_urgent()) {
+        (false, true) => patch.due = Some(Some(DueAt::from_dt(by.unwrap_or(now)))),
+        (true, false) => {
+            let later = by.and_then(|by| by.checked_add(RESCHEDULE_BY));
+            patch.due = Some(later.map(DueAt::from_dt));
+        }
+        _ => {}
+    }
+    patch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::Title;
+    use time::macros::datetime;
+
+    const NOW: OffsetDateTime = datetime!(2026-05-04 09:00 UTC);
+    const WITHIN: Duration = Duration::days(2);
+
+    fn todo(title: &str, priority: Priority, due_in_hours: Option<i64>) -> Todo {
+        let mut t = Todo::new_at(Title::parse(title).unwrap(), NOW);
+        t.priority = priority;
+        t.due = due_in_hours.map(|h| DueAt::from_dt(NOW + Duration::hours(h)));
+        t
+    }
+
+    fn titles(cells: &[(Quadrant, Vec<Todo>)], q: Quadrant) -> Vec<&str> {
+        let (_, cell) = cells.iter().find(|(c, _)| *c == q).unwrap();
+        cell.iter().map(|t| t.title.as_str()).collect()
+    }
+
+    #[test]
+    fn todos_fall_into_quadrants_and_move_between_them() {
+        let mut done = todo("Filed", Priority::P1, Some(-1));
+        done.mark_done_at(NOW).unwrap();
+        let todos = vec![
+            todo("Fix outage", Priority::P1, Some(-3)),
+            todo("Plan roadmap", Priority::P2, Some(24 * 10)),
+            todo("Reply to newsletter", Priority::P4, Some(5)),
+            todo("Tidy desk", Priority::P3, None),
+            todo("Call landlord", Priority::P2, Some(47)),
+            done,
+        ];
+        let cells = matrix(&todos, NOW, WITHIN);
+        assert_eq!(
+            titles(&cells, Quadrant::DoFirst),
+            ["Fix outage", "Call landlord"]
+        );
+        assert_eq!(titles(&cells, Quadrant::Schedule), ["Plan roadmap"]);
+        assert_eq!(titles(&cells, Quadrant::Delegate), ["Reply to newsletter"]);
+        assert_eq!(titles(&cells, Quadrant::Eliminate), ["Tidy desk"]);
+
+        for (from, to) in [
+            (3, Quadrant::DoFirst),
+            (0, Quadrant::Eliminate),
+            (1, Quadrant::Delegate),
+        ] {
+            let mut moved = todos[from].clone();
+            moved.apply_patch_at(move_to(&moved, to, NOW, WITHIN), NOW);
+            let cells = matrix(&[moved.clone()], NOW, WITHIN);
+            assert_eq!(titles(&cells, to), [moved.title.as_str()], "{to:?}");
+        }
+        let patch = move_to(&todos[0], Quadrant::DoFirst, NOW, WITHIN);
+        assert!(patch.priority.is_none() && patch.due.is_none());
+        assert_eq!(Quadrant::parse("2"), Some(Quadrant::Schedule));
+
+        // A horizon past the latest date makes every dated todo urgent.
+        let forever = Duration::days(i64::from(u32::MAX));
+        let cells = matrix(&todos, NOW, forever);
+        assert_eq!(
+            titles(&cells, Quadrant::DoFirst),
+            ["Fix outage", "Call landlord", "Plan roadmap"]
+        );
+        let patch = move_to(&todos[1], Quadrant::Eliminate, NOW, forever);
+        assert_eq!(patch.due, Some(None));
+        assert_eq!(Quadrant::parse("Delegate"), Some(Quadrant::Delegate));
+    }
+}
@@ -0,0 +1,440 @@
+//! Three-way merge of two edited copies of the same list.
+//!
+//! Used when the database file changed on disk while a long-running UI still
+//! had edits of its own: `base` is what the UI loaded, `local` what it has now,
+//! `remote` what is on disk. Edits to different todos, or to different fields
+//! of the same todo, are combined; a field both sides changed keeps the local
+//! value and is reported.
+//!
+//! [`combine`] folds several unrelated copies of a list (exports from
+//! different machines) into one without a common base, using the field-level
+//! last-writer-wins rule from sync.
+
+use std::collections::BTreeMap;
+
+use crate::domain::{
+    crdt::{self, TodoField},
+    todo::{Todo, TodoId},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct ThreeWayMerge {
+    pub todos: Vec<Todo>,
+    /// Fields changed on both sides (the local value was kept).
+    pub conflicts: Vec<(TodoId, TodoField)>,
+}
+
+pub fn three_way(base: &[Todo], local: &[Todo], remote: &[Todo]) -> ThreeWayMerge {
+    let base: BTreeMap<TodoId, &Todo> = base.iter().map(|t| (t.id, t)).collect();
+    let local_by_id: BTreeMap<TodoId, &Todo> = local.iter().map(|t| (t.id, t)).collect();
+    let mut out = ThreeWayMerge::default();
+
+    // Remote order first, then todos only the local side has.
+    for r in remote {
+        let merged = match (base.get(&r.id), local_by_id.get(&r.id)) {
+            // Added on both sides with the same id can't really happen; keep local.
+            (None, Some(l)) => Some((*l).clone()),
+            (None, None) => Some(r.clone()),
+            // Deleted locally: honour it unless remote edited the todo since.
+            (Some(b), None) => (*b != r).then(|| r.clone()),
+            (Some(b), Some(l)) => Some(merge_one(b, l, r, &mut out.conflicts)),
+        };
+        out.todos.extend(merged);
+    }
+    let remote_ids: BTreeMap<TodoId, ()> = remote.iter().map(|t| (t.id, ())).collect();
+    for l in local.iter().filter(|l| !remote_ids.contains_key(&l.id)) {
+        match base.get(&l.id) {
+            // Deleted on disk: keep it only if edited locally since.
+            Some(b) if *b == l => {}
+            _ => out.todos.push(l.clone()),
+        }
+    }
+    out
+}
+
+fn merge_one(
+    base: &Todo,
+    local: &Todo,
+    remote: &Todo,
+    conflicts: &mut Vec<(TodoId, TodoField)>,
+) -> Todo {
+    if local == base {
+        return remote.clone();
+    }
+    if remote == base || remote == local {
+        return local.clone();
+    }
+
+    let remote_changed = crdt::diff_fields(base, remote);
+    let mut merged = remote.clone();
+    for field in crdt::diff_fields(base, local) {
+        if remote_changed.contains(&field)
+            && crdt::field_value(remote, field) != crdt::field_value(local, field)
+        {
+            conflicts.push((local.id, field));
+        }
+        crdt::copy_field(&mut merged, local, field);
+    }
+    merged.updated_at = local.updated_at.max(remote.updated_at);
+    merged
+}
+
+/// What folding one list into the others with [`combine`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CombineStats {
+    pub read: usize,
+    /// Todos no earlier list had.
+    pub added: usize,
+    /// Todos an earlier list had in another version.
+    pub merged: usize,
+    /// Fields edited on both sides without either having seen the other.
+    pub conflicts: usize,
+}
+
+/// Merge `lists` into one by id, in order; for each list, how it contributed.
+///
+/// Todos keep the position they first appeared in. Different versions of the
+/// same todo merge field by field (see [`crdt::merge`]), so the result doesn't
+/// depend on the order of the lists, only its ordering does.
+pub fn combine(lists: Vec<Vec<Todo>>) -> (Vec<Todo>, Vec<CombineStats>) {
+    let mut todos: Vec<Todo> = Vec::new();
+    let mut index: BTreeMap<TodoId, usize> = BTreeMap::new();
+    let mut stats = Vec::with_capacity(lists.len());
+
+    for list in lists {
+        let mut s = CombineStats {
+            read: list.len(),
+            ..CombineStats::default()
+        };
+        for todo in list {
+            match index.get(&todo.id) {
+                None => {
+                    index.insert(todo.id, todos.len());
+                    todos.push(todo);
+                    s.added += 1;
+                }
+                Some(&i) if todos[i] == todo => {}
+                Some(&i) => {
+                    let m = crdt::merge(&todos[i], &todo);
+                    let mut merged = m.merged;
+                    merged.version = todos[i].version.clone();
+                    merged.version.merge(&todo.version);
+                    todos[i] = merged;
+                    s.merged += 1;
+                    s.conflicts += m.conflicting.len();
+                }
+            }
+        }
+        stats.push(s);
+    }
+    (todos, stats)
+}
+
+/// What [`newest_wins`] did.
+#[derive(Debug, Clone, Default)]
+pub struct NewestWins {
+    pub todos: Vec<Todo>,
+    /// Todos taken from the remote side (new there, or edited more recently).
+    pub from_remote: usize,
+    /// Todos dropped because one side deleted them and the other didn't edit them.
+    pub deleted: usize,
+    /// Todos edited on both sides; the later `updated_at` was kept.
+    pub conflicts: Vec<TodoId>,
+}
+
+/// Whole-todo merge for git sync: by id, the copy with the later
+/// `updated_at` wins (local on ties). `base` is the common ancestor, which
+/// tells a deletion on one side apart from an addition on the other.
+pub fn newest_wins(base: &[Todo], local: &[Todo], remote: &[Todo]) -> NewestWins {
+    let base: BTreeMap<TodoId, &Todo> = base.iter().map(|t| (t.id, t)).collect();
+    let remote_by_id: BTreeMap<TodoId, &Todo> = remote.iter().map(|t| (t.id, t)).collect();
+    let local_ids: BTreeMap<TodoId, ()> = local.iter().map(|t| (t.id, ())).collect();
+    let mut out = NewestWins::default();
+
+    // Local order first, then todos only the remote side has.
+    for l in local {
+        match (remote_by_id.get(&l.id), base.get(&l.id)) {
+            (Some(r), b) => {
+                let changed = |t: &Todo| b.is_none_or(|b| *b != t);
+                if changed(l) && changed(r) && l != *r {
+                    out.conflicts.push(l.id);
+                }
+                if r.updated_at > l.updated_at {
+                    out.todos.push((*r).clone());
+                    out.from_remote += 1;
+                } else {
+                    out.todos.push(l.clone());
+                }
+            }
+            // Deleted remotely: honour it unless edited locally since.
+            (None, Some(b)) if *b == l => out.deleted += 1,
+            (None, _) => out.todos.push(l.clone()),
+        }
+    }
+    for r in remote.iter().filter(|r| !local_ids.contains_key(&r.id)) {
+        match base.get(&r.id) {
+            // Deleted locally.
+            Some(b) if *b == r => out.deleted += 1,
+            _ => {
+                out.todos.push(r.clone());
+                out.from_remote += 1;
+            }
+        }
+    }
+    out
+}
+
+/// How `import` combines a file with the todos already there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// The file's todos become the whole list.
+    #[default]
+    Replace,
+    /// Match by id, or for todos from another system by their external
+    /// reference: new todos are added, known ones are taken from the file
+    /// only if edited later there; todos missing from the file stay.
+    Merge,
+    /// Add every todo in the file, with fresh ids where they clash.
+    Append,
+}
+
+impl ImportMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "replace" => Some(Self::Replace),
+            "merge" => Some(Self::Merge),
+            "append" => Some(Self::Append),
+            _ => None,
+        }
+    }
+}
+
+/// What [`import`] did with the file's todos.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    pub inserted: usize,
+    /// Known todos replaced by a newer copy from the file.
+    pub updated: usize,
+    /// Known todos whose copy in the file was not newer.
+    pub skipped: usize,
+    /// Existing todos dropped (only when replacing).
+    pub removed: usize,
+}
+
+/// Combine `incoming` with `current` as `mode` says. Existing todos keep
+/// their order; new ones follow in file order.
+pub fn import(current: &[Todo], incoming: Vec<Todo>, mode: ImportMode) -> (Vec<Todo>, ImportStats) {
+    let mut stats = ImportStats::default();
+    match mode {
+        ImportMode::Replace => {
+            let ids: BTreeMap<TodoId, ()> = incoming.iter().map(|t| (t.id, ())).collect();
+            stats.removed = current.iter().filter(|t| !ids.contains_key(&t.id)).count();
+            stats.inserted = incoming.len();
+            (incoming, stats)
+        }
+        ImportMode::Merge => {
+            let mut todos = current.to_vec();
+            let mut index: BTreeMap<TodoId, usize> =
+                todos.iter().enumerate().map(|(i, t)| (t.id, i)).collect();
+            for mut todo in incoming {
+                let known = index.get(&todo.id).copied().or_else(|| {
+                    let r = todo.external_ref.as_ref()?;
+                    todos
+                        .iter()
+                        .position(|t| t.external_ref.as_ref().is_some_and(|x| x.same_item(r)))
+                });
+                match known {
+                    Some(i) if todo.updated_at > todos[i].updated_at => {
+                        // Matched by reference: it stays the same todo here.
+                        todo.id = todos[i].id;
+                        todo.created_at = todos[i].created_at;
+                        todos[i] = todo;
+                        stats.updated += 1;
+                    }
+                    Some(_) => stats.skipped += 1,
+                    None => {
+                        index.insert(todo.id, todos.len());
+                        todos.push(todo);
+                        stats.inserted += 1;
+                    }
+                }
+            }
+            (todos, stats)
+        }
+        ImportMode::Append => {
+            let taken: BTreeMap<TodoId, ()> = current.iter().map(|t| (t.id, ())).collect();
+            // Clashing todos get new ids; links between appended todos follow.
+            let renamed: BTreeMap<TodoId, TodoId> = incoming
+                .iter()
+                .filter(|t| taken.contains_key(&t.id))
+                .map(|t| (t.id, TodoId::new()))
+                .collect();
+            let mut todos = current.to_vec();
+            for mut todo in incoming {
+                if let Some(id) = renamed.get(&todo.id) {
+                    todo.id = *id;
+                }
+                todo.depends_on = todo
+                    .depends_on
+                    .iter()
+                    .map(|d| *renamed.get(d).unwrap_or(d))
+                    .collect();
+                todo.parent = todo.parent.map(|p| *renamed.get(&p).unwrap_or(&p));
+                todos.push(todo);
+                stats.inserted += 1;
+            }
+            (todos, stats)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{Priority, Title};
+
+    #[test]
+    fn combines_edits_and_reports_overlaps() {
+        let a = Todo::new(Title::parse("A").unwrap());
+        let b = Todo::new(Title::parse("B").unwrap());
+        let gone = Todo::new(Title::parse("Gone").unwrap());
+        let base = vec![a.clone(), b.clone(), gone.clone()];
+
+        let mut local_a = a.clone();
+        local_a.priority = Priority::P1;
+        local_a.title = Title::parse("A (mine)").unwrap();
+        let local_new = Todo::new(Title::parse("New here").unwrap());
+        let local = vec![local_a, b.clone(), gone, local_new.clone()];
+
+        let mut remote_a = a.clone();
+        remote_a.title = Title::parse("A (theirs)").unwrap();
+        remote_a.notes = Some(crate::domain::todo::Notes::parse("from disk").unwrap());
+        let mut remote_b = b.clone();
+        remote_b.priority = Priority::P4;
+        let remote = vec![remote_a, remote_b];
+
+        let m = three_way(&base, &local, &remote);
+        let ids: Vec<_> = m.todos.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![a.id, b.id, local_new.id]);
+
+        let merged_a = &m.todos[0];
+        assert_eq!(merged_a.title.as_str(), "A (mine)");
+        assert_eq!(merged_a.priority, Priority::P1);
+        assert!(merged_a.notes.is_some());
+        assert_eq!(m.todos[1].priority, Priority::P4);
+        assert_eq!(m.conflicts, vec![(a.id, TodoField::Title)]);
+    }
+
+    #[test]
+    fn combine_unions_lists_and_merges_shared_todos() {
+        use crate::domain::todo::TodoPatch;
+
+        let shared = Todo::new(Title::parse("Shared").unwrap());
+        let laptop_only = Todo::new(Title::parse("Laptop").unwrap());
+        let mut desktop_shared = shared.clone();
+        desktop_shared.apply_patch(TodoPatch {
+            priority: Some(Priority::P1),
+            ..TodoPatch::default()
+        });
+        let desktop_only = Todo::new(Title::parse("Desktop").unwrap());
+
+        let (todos, stats) = combine(vec![
+            vec![shared.clone(), laptop_only.clone()],
+            vec![desktop_shared, desktop_only.clone(), laptop_only.clone()],
+        ]);
+        let ids: Vec<_> = todos.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![shared.id, laptop_only.id, desktop_only.id]);
+        assert_eq!(todos[0].priority, Priority::P1);
+        assert_eq!(
+            stats[1],
+            CombineStats {
+                read: 3,
+                added: 1,
+                merged: 1,
+                conflicts: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn newest_wins_keeps_later_edits_and_honours_deletions() {
+        use time::Duration;
+
+        let kept = Todo::new(Title::parse("Kept").unwrap());
+        let edited = Todo::new(Title::parse("Edited").unwrap());
+        let gone_remote = Todo::new(Title::parse("Deleted remotely").unwrap());
+        let gone_local = Todo::new(Title::parse("Deleted locally").unwrap());
+        let base = vec![
+            kept.clone(),
+            edited.clone(),
+            gone_remote.clone(),
+            gone_local.clone(),
+        ];
+
+        let mut local_edit = edited.clone();
+        local_edit.priority = Priority::P1;
+        local_edit.updated_at += Duration::minutes(1);
+        let mut remote_edit = edited.clone();
+        remote_edit.priority = Priority::P4;
+        remote_edit.updated_at += Duration::minutes(2);
+        let remote_new = Todo::new(Title::parse("New remotely").unwrap());
+
+        let local = vec![kept.clone(), local_edit, gone_remote];
+        let remote = vec![kept.clone(), remote_edit, gone_local, remote_new.clone()];
+
+        let m = newest_wins(&base, &local, &remote);
+        let ids: Vec<_> = m.todos.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![kept.id, edited.id, remote_new.id]);
+        assert_eq!(m.todos[1].priority, Priority::P4);
+        assert_eq!((m.from_remote, m.deleted), (2, 2));
+        assert_eq!(m.conflicts, vec![edited.id]);
+    }
+
+    #[test]
+    fn import_modes_replace_merge_or_append() {
+        use time::Duration;
+
+        let old = Todo::new(Title::parse("Old").unwrap());
+        let mut stale = old.clone();
+        stale.title = Title::parse("Stale").unwrap();
+        stale.updated_at -= Duration::minutes(1);
+        let mut newer = Todo::new(Title::parse("Edited").unwrap());
+        let current = vec![old.clone(), newer.clone()];
+        newer.title = Title::parse("Edited later").unwrap();
+        newer.updated_at += Duration::minutes(1);
+        let mut fresh = Todo::new(Title::parse("Fresh").unwrap());
+        fresh.depends_on.insert(newer.id);
+        let file = vec![stale, newer.clone(), fresh.clone()];
+
+        let (todos, stats) = import(&current, file.clone(), ImportMode::Replace);
+        assert_eq!(todos, file);
+        assert_eq!((stats.inserted, stats.removed), (3, 0));
+
+        let (todos, stats) = import(&current, file.clone(), ImportMode::Merge);
+        let titles: Vec<_> = todos.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["Old", "Edited later", "Fresh"]);
+        assert_eq!(
+            stats,
+            ImportStats {
+                inserted: 1,
+                updated: 1,
+                skipped: 1,
+                removed: 0
+            }
+        );
+
+        let (todos, stats) = import(&current, file, ImportMode::Append);
+        assert_eq!((todos.len(), stats.inserted), (5, 3));
+        let ids: BTreeMap<TodoId, ()> = todos.iter().map(|t| (t.id, ())).collect();
+        assert_eq!(ids.len(), 5);
+        // The appended copy of "Fresh" keeps its id and now points at the
+        // appended copy of the todo it depended on.
+        let appended = todos.iter().find(|t| t.id == fresh.id).unwrap();
+        let dep = appended.depends_on.iter().next().unwrap();
+        assert_ne!(*dep, newer.id);
+        assert_eq!(
+            todos.iter().find(|t| t.id == *dep).unwrap().title.as_str(),
+            "Edited later"
+        );
+    }
+}
@@ -2,10 +2,41 @@
 //!
 //! Coordinates use-cases and domain objects.
 
+pub mod bulk_edit;
+pub mod capture;
+#[cfg(feature = "native")]
 pub mod context;
+pub mod custom_reports;
+pub mod dictation;
+pub mod due_input;
 pub mod errors;
+pub mod forecast;
+pub mod fuzzy;
+pub mod history;
+pub mod keymap;
+pub mod matrix;
+pub mod merge;
+pub mod planning;
+pub mod priorities;
+pub mod projection;
+pub mod projects;
 pub mod query;
+pub mod rate_limit;
+pub mod redact;
+pub mod regex;
+pub mod reminders;
+pub mod replace;
+pub mod report;
 pub mod repository;
 pub mod seed;
 pub mod service;
+pub mod stats;
 pub mod store;
+pub mod subtasks;
+pub mod sync;
+pub mod templates;
+pub mod timesheet;
+pub mod timezone;
+pub mod warnings;
+pub mod workflow;
+pub mod write_queue;
@@ -0,0 +1,208 @@
+//! Project planning over dependencies and estimates.
+//!
+//! Critical path method: a forward pass gives each open todo its earliest
+//! start/finish, a backward pass its latest start; the difference is *slack*,
+//! how long it can slip without delaying the whole set. Zero-slack todos form
+//! the critical path.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::domain::todo::{Todo, TodoId};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PlanError {
+    #[error("dependency cycle between {} todos", .0.len())]
+    Cycle(Vec<TodoId>),
+}
+
+/// One open todo scheduled as early as its dependencies allow.
+///
+/// Times are minutes from "now" (the start of the plan).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedTask {
+    pub id: TodoId,
+    pub title: String,
+    pub duration: u32,
+    pub earliest_start: u32,
+    pub earliest_finish: u32,
+    pub slack: u32,
+    /// True if this task lies on the reported critical path.
+    pub critical: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CriticalPath {
+    /// All scheduled tasks, by earliest start.
+    pub tasks: Vec<PlannedTask>,
+    /// The longest chain, first task first.
+    pub path: Vec<TodoId>,
+    /// Length of the critical path in minutes.
+    pub total: u32,
+    /// Tasks without an estimate (counted as zero length).
+    pub unestimated: usize,
+}
+
+/// Plan the open todos in `project` (all projects if `None`).
+///
+/// Dependencies on done todos, or on todos outside the scope, count as met.
+pub fn critical_path(todos: &[Todo], project: Option<&str>) -> Result<CriticalPath, PlanError> {
+    let scope: BTreeMap<TodoId, &Todo> = todos
+        .iter()
+        .filter(|t| !t.status.is_done())
+        .filter(|t| project.is_none_or(|p| t.project.as_str().eq_ignore_ascii_case(p.trim())))
+        .map(|t| (t.id, t))
+        .collect();
+
+    let deps_of = |t: &Todo| -> Vec<TodoId> {
+        t.depends_on
+            .iter()
+            .copied()
+            .filter(|d| scope.contains_key(d))
+            .collect()
+    };
+    let duration = |t: &Todo| t.estimate.map_or(0, |e| e.minutes());
+
+    // Kahn's algorithm; whatever never reaches in-degree zero is on a cycle.
+    let mut pending: BTreeMap<TodoId, usize> =
+        scope.values().map(|t| (t.id, deps_of(t).len())).collect();
+    let mut dependents: BTreeMap<TodoId, Vec<TodoId>> = BTreeMap::new();
+    for t in scope.values() {
+        for d in deps_of(t) {
+            dependents.entry(d).or_default().push(t.id);
+        }
+    }
+
+    let mut order = Vec::with_capacity(scope.len());
+    let mut ready: Vec<TodoId> = pending
+        .iter()
+        .filter(|&(_, &n)| n == 0)
+        .map(|(&id, _)| id)
+        .collect();
+    while let Some(id) = ready.pop() {
+        order.push(id);
+        for next in dependents.get(&id).into_iter().flatten() {
+            let n = pending.get_mut(next).expect("dependents are in scope");
+            *n -= 1;
+            if *n == 0 {
+                ready.push(*next);
+            }
+        }
+    }
+    if order.len() < scope.len() {
+        let on_cycle = pending
+            .into_iter()
+            .filter(|&(_, n)| n > 0)
+            .map(|(id, _)| id)
+            .collect();
+        return Err(PlanError::Cycle(on_cycle));
+    }
+
+    // Forward pass.
+    let mut finish: BTreeMap<TodoId, u32> = BTreeMap::new();
+    let mut start: BTreeMap<TodoId, u32> = BTreeMap::new();
+    for id in &order {
+        let t = scope[id];
+        let es = deps_of(t).iter().map(|d| finish[d]).max().unwrap_or(0);
+        start.insert(*id, es);
+        finish.insert(*id, es + duration(t));
+    }
+    let total = finish.values().copied().max().unwrap_or(0);
+
+    // Backward pass.
+    let mut latest_start: BTreeMap<TodoId, u32> = BTreeMap::new();
+    for id in order.iter().rev() {
+        let lf = dependents
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|d| latest_start[d])
+            .min()
+            .unwrap_or(total);
+        latest_start.insert(*id, lf - duration(scope[id]));
+    }
+
+    // Walk back from the task that finishes last through zero-slack links.
+    let slack = |id: &TodoId| latest_start[id] - start[id];
+    let mut path = Vec::new();
+    let mut cursor = order
+        .iter()
+        .filter(|id| finish[*id] == total && slack(id) == 0)
+        .min_by_key(|id| scope[*id].created_at)
+        .copied();
+    while let Some(id) = cursor {
+        path.push(id);
+        cursor = deps_of(scope[&id])
+            .into_iter()
+            .filter(|d| finish[d] == start[&id] && slack(d) == 0)
+            .min_by_key(|d| scope[d].created_at);
+    }
+    path.reverse();
+
+    let mut tasks: Vec<PlannedTask> = order
+        .iter()
+        .map(|id| PlannedTask {
+            id: *id,
+            title: scope[id].title.as_str().to_string(),
+            duration: duration(scope[id]),
+            earliest_start: start[id],
+            earliest_finish: finish[id],
+            slack: slack(id),
+            critical: path.contains(id),
+        })
+        .collect();
+    tasks.sort_by(|a, b| {
+        a.earliest_start
+            .cmp(&b.earliest_start)
+            .then_with(|| a.slack.cmp(&b.slack))
+            .then_with(|| a.title.cmp(&b.title))
+    });
+
+    Ok(CriticalPath {
+        unestimated: scope.values().filter(|t| t.estimate.is_none()).count(),
+        tasks,
+        path,
+        total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{Estimate, Title};
+
+    fn task(title: &str, minutes: u32, deps: &[&Todo]) -> Todo {
+        let mut t = Todo::new(Title::parse(title).unwrap());
+        t.estimate = Some(Estimate::from_minutes(minutes));
+        t.depends_on = deps.iter().map(|d| d.id).collect();
+        t
+    }
+
+    #[test]
+    fn longest_chain_is_critical_and_others_get_slack() {
+        let design = task("Design", 120, &[]);
+        let build = task("Build", 240, &[&design]);
+        let docs = task("Docs", 60, &[&design]);
+        let ship = task("Ship", 30, &[&build, &docs]);
+        let todos = vec![ship.clone(), docs.clone(), build.clone(), design.clone()];
+
+        let plan = critical_path(&todos, None).unwrap();
+        assert_eq!(plan.total, 390);
+        assert_eq!(plan.path, vec![design.id, build.id, ship.id]);
+
+        let docs_task = plan.tasks.iter().find(|t| t.id == docs.id).unwrap();
+        assert_eq!(docs_task.slack, 180);
+        assert!(!docs_task.critical);
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let mut a = task("A", 10, &[]);
+        let b = task("B", 10, &[&a]);
+        a.depends_on.insert(b.id);
+
+        let err = critical_path(&[a, b], None).unwrap_err();
+        assert!(matches!(err, PlanError::Cycle(ids) if ids.len() == 2));
+    }
+}
@@ -0,0 +1,198 @@
+//! Team-specific priority names (`[[priority_levels]]` in config.toml).
+//!
+//! Todos always store one of P1-P4; a scheme gives some of them other
+//! labels (and colors) for input and display:
+//!
+//! ```toml
+//! [[priority_levels]]
+//! label = "High"
+//! priority = "P1"
+//! color = "#d70000"
+//!
+//! [[priority_levels]]
+//! label = "Low"
+//! priority = "P3"
+//! ```
+//!
+//! A priority without a level of its own is shown as the next less urgent
+//! level (P2 as "Low" above), or the most urgent one below it if there is
+//! none (P4 as "Low"). P1-P4 are always accepted as input.
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::todo::Priority;
+
+const ALL: [Priority; 4] = [Priority::P1, Priority::P2, Priority::P3, Priority::P4];
+
+/// One `[[priority_levels]]` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriorityLevel {
+    pub label: String,
+    /// The stored priority it stands for.
+    pub priority: Priority,
+    /// `#rrggbb` or `#rgb`, for colored output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+/// The levels in use, most urgent first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriorityScheme {
+    levels: Vec<PriorityLevel>,
+}
+
+impl Default for PriorityScheme {
+    fn default() -> Self {
+        let levels = ALL
+            .into_iter()
+            .map(|priority| PriorityLevel {
+                label: priority.label().to_string(),
+                priority,
+                color: None,
+            })
+            .collect();
+        Self { levels }
+    }
+}
+
+impl PriorityScheme {
+    /// The defaults (P1-P4), or `levels` if any. Errors name the level at
+    /// fault; colors are checked by the caller.
+    pub fn from_config(levels: &[PriorityLevel]) -> Result<Self, String> {
+        if levels.is_empty() {
+            return Ok(Self::default());
+        }
+        let mut sorted: Vec<PriorityLevel> = Vec::new();
+        for level in levels {
+            let label = level.label.trim();
+            if label.is_empty() {
+                return Err(format!(
+                    "[[priority_levels]] {}: label cannot be empty",
+                    level.priority.label()
+                ));
+            }
+            if let Some(other) = sorted.iter().find(|l| l.priority == level.priority) {
+                return Err(format!(
+                    "[[priority_levels]] {} and {label} are both {}",
+                    other.label,
+                    level.priority.label()
+                ));
+            }
+            if sorted.iter().any(|l| l.label.eq_ignore_ascii_case(label)) {
+                return Err(format!("[[priority_levels]] {label} is listed twice"));
+            }
+            // "P1" meaning P3 would make `--priority P1` ambiguous.
+            if let Ok(p) = Priority::parse(label)
+                && p != level.priority
+            {
+                return Err(format!(
+                    "[[priority_levels]] {label} can only stand for {}",
+                    p.label()
+                ));
+            }
+            sorted.push(PriorityLevel {
+                label: label.to_string(),
+                ..level.clone()
+            });
+        }
+        sorted.sort_by_key(|l| l.priority);
+        Ok(Self { levels: sorted })
+    }
+
+    pub fn levels(&self) -> &[PriorityLevel] {
+        &self.levels
+    }
+
+    /// The level `priority` is shown as.
+    pub fn level(&self, priority: Priority) -> &PriorityLevel {
+        self.levels
+            .iter()
+            .find(|l| l.priority >= priority)
+            .or_else(|| self.levels.last())
+            .expect("a scheme has at least one level")
+    }
+
+    pub fn label(&self, priority: Priority) -> &str {
+        &self.level(priority).label
+    }
+
+    /// The configured color for `priority`'s level, if any.
+    pub fn color(&self, priority: Priority) -> Option<&str> {
+        self.level(priority).color.as_deref()
+    }
+
+    /// Characters in the longest label, for table columns.
+    pub fn width(&self) -> usize {
+        self.levels
+            .iter()
+            .map(|l| l.label.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// A label (any case) or P1-P4.
+    pub fn parse(&self, input: &str) -> Result<Priority, String> {
+        let s = input.trim();
+        if let Some(level) = self.levels.iter().find(|l| l.label.eq_ignore_ascii_case(s)) {
+            return Ok(level.priority);
+        }
+        Priority::parse(s).map_err(|_| {
+            let labels: Vec<_> = self.levels.iter().map(|l| l.label.as_str()).collect();
+            format!("unknown priority {s} (use {})", labels.join(", "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(label: &str, priority: Priority) -> PriorityLevel {
+        PriorityLevel {
+            label: label.to_string(),
+            priority,
+            color: None,
+        }
+    }
+
+    #[test]
+    fn labels_map_onto_stored_priorities() {
+        let scheme = PriorityScheme::from_config(&[
+            level("Low", Priority::P3),
+            level(" High ", Priority::P1),
+        ])
+        .unwrap();
+        assert_eq!(scheme.label(Priority::P1), "High");
+        assert_eq!(scheme.label(Priority::P2), "Low");
+        assert_eq!(scheme.label(Priority::P4), "Low");
+        assert_eq!(scheme.parse("high"), Ok(Priority::P1));
+        assert_eq!(scheme.parse("P4"), Ok(Priority::P4));
+        assert_eq!(
+            scheme.parse("urgent").unwrap_err(),
+            "unknown priority urgent (use High, Low)"
+        );
+        assert_eq!(scheme.width(), 4);
+
+        let default = PriorityScheme::default();
+        assert_eq!(default.label(Priority::P2), "P2");
+        assert_eq!(default.parse("p3"), Ok(Priority::P3));
+    }
+
+    #[test]
+    fn ambiguous_schemes_are_refused() {
+        let err = |levels: &[PriorityLevel]| PriorityScheme::from_config(levels).unwrap_err();
+        assert_eq!(
+            err(&[level("High", Priority::P1), level("Top", Priority::P1)]),
+            "[[priority_levels]] High and Top are both P1"
+        );
+        assert_eq!(
+            err(&[level("High", Priority::P1), level("high", Priority::P2)]),
+            "[[priority_levels]] high is listed twice"
+        );
+        assert_eq!(
+            err(&[level("P1", Priority::P3)]),
+            "[[priority_levels]] P1 can only stand for P1"
+        );
+        assert!(err(&[level(" ", Priority::P2)]).contains("label cannot be empty"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Field selection for JSON output (`list --format json --fields ...`).
+//!
+//! A [`Projection`] names the keys to keep; [`Projection::apply`] wraps a
+//! todo so serializing it writes only those keys, in the order asked for.
+//! Requested keys are always present (`null` when unset), so scripts can rely
+//! on the shape of every object.
+
+use serde::{Serialize, Serializer, ser::SerializeMap};
+
+use crate::domain::todo::Todo;
+
+/// Keys of a serialized [`Todo`].
+const KEYS: [&str; 28] = [
+    "id",
+    "title",
+    "notes",
+    "project",
+    "tags",
+    "status",
+    "priority",
+    "due",
+    "badge",
+    "color",
+    "estimate",
+    "energy",
+    "depends_on",
+    "parent",
+    "time_entries",
+    "source",
+    "someday",
+    "remind_at",
+    "notified_at",
+    "escalation",
+    "state",
+    "external_ref",
+    "attachments",
+    "archived",
+    "created_at",
+    "updated_at",
+    "version",
+    "field_stamps",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Projection {
+    keys: Vec<&'static str>,
+}
+
+impl Projection {
+    /// Parse field names (`id`, `title`, `due`, ...); repeats are dropped.
+    pub fn parse<S: AsRef<str>>(fields: &[S]) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        for field in fields {
+            let name = field.as_ref().trim().to_ascii_lowercase().replace('-', "_");
+            let Some(key) = KEYS.iter().find(|k| **k == name) else {
+                return Err(format!(
+                    "unknown field {} (use {})",
+                    field.as_ref().trim(),
+                    KEYS.join(", ")
+                ));
+            };
+            if !keys.contains(key) {
+                keys.push(*key);
+            }
+        }
+        if keys.is_empty() {
+            return Err("--fields needs at least one field".to_string());
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn apply<'a>(&'a self, todo: &'a Todo) -> Projected<'a> {
+        Projected {
+            todo,
+            projection: self,
+        }
+    }
+}
+
+/// A todo serialized with only the keys of a [`Projection`].
+pub struct Projected<'a> {
+    todo: &'a Todo,
+    projection: &'a Projection,
+}
+
+impl Serialize for Projected<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let t = self.todo;
+        let mut map = serializer.serialize_map(Some(self.projection.keys.len()))?;
+        for &key in &self.projection.keys {
+            match key {
+                "id" => map.serialize_entry(key, &t.id)?,
+                "title" => map.serialize_entry(key, &t.title)?,
+                "notes" => map.serialize_entry(key, &t.notes)?,
+                "project" => map.serialize_entry(key, &t.project)?,
+                "tags" => map.serialize_entry(key, &t.tags)?,
+                "status" => map.serialize_entry(key, &t.status)?,
+                "priority" => map.serialize_entry(key, &t.priority)?,
+                "due" => map.serialize_entry(key, &t.due)?,
+                "badge" => map.serialize_entry(key, &t.badge)?,
+                "color" => map.serialize_entry(key, &t.color)?,
+                "estimate" => map.serialize_entry(key, &t.estimate)?,
+                "energy" => map.serialize_entry(key, &t.energy)?,
+                "depends_on" => map.serialize_entry(key, &t.depends_on)?,
+                "parent" => map.serialize_entry(key, &t.parent)?,
+                "time_entries" => map.serialize_entry(key, &t.time_entries)?,
+                "source" => map.serialize_entry(key, &t.source)?,
+                "someday" => map.serialize_entry(key, &t.someday)?,
+                "remind_at" => map.serialize_entry(key, &t.remind_at)?,
+                "notified_at" => map.serialize_entry(key, &t.notified_at)?,
+                "escalation" => map.serialize_entry(key, &t.escalation)?,
+                "state" => map.serialize_entry(key, &t.state)?,
+                "external_ref" => map.serialize_entry(key, &t.external_ref)?,
+                "attachments" => map.serialize_entry(key, &t.attachments)?,
+                "archived" => map.serialize_entry(key, &t.archived)?,
+                "created_at" => map.serialize_entry(key, &t.created_at)?,
+                "updated_at" => map.serialize_entry(key, &t.updated_at)?,
+                "version" => map.serialize_entry(key, &t.version)?,
+                "field_stamps" => map.serialize_entry(key, &t.field_stamps)?,
+                _ => unreachable!("keys come from KEYS"),
+            }
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::Title;
+
+    #[test]
+    fn projected_todos_keep_only_the_requested_keys_in_order() {
+        let todo = Todo::new(Title::parse("Ship it").unwrap());
+        let projection = Projection::parse(&["title", "due", "ID", "title"]).unwrap();
+        let text = serde_json::to_string(&projection.apply(&todo)).unwrap();
+        let id = serde_json::to_string(&todo.id).unwrap();
+        let expected = format!(r#"{{"title":"Ship it","due":null,"id":{id}}}"#);
+        assert_eq!(text, expected);
+
+        // Every key gives the same value as serializing the whole todo.
+        let full = serde_json::to_value(&todo).unwrap();
+        let all = Projection::parse(&KEYS).unwrap();
+        let json = serde_json::to_value(all.apply(&todo)).unwrap();
+        for (key, value) in full.as_object().unwrap() {
+            assert_eq!(&json[key], value, "{key}");
+        }
+
+        assert!(Projection::parse(&["title", "colour"]).is_err());
+        assert!(Projection::parse::<&str>(&[]).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Project overview and record upkeep (`project` subcommands).
+//!
+//! Projects exist as soon as a todo names them; [`Project`] records only add
+//! metadata. The overview joins both so a project shows up whether it has a
+//! record, todos, or both.
+
+use std::collections::BTreeMap;
+
+use crate::domain::{
+    project::Project,
+    todo::{ProjectName, Todo},
+};
+
+/// One row of `project list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectSummary {
+    pub project: Project,
+    /// Open todos (someday items included).
+    pub open: usize,
+    pub done: usize,
+}
+
+/// Every project with a record or a todo, by name (case-insensitive).
+///
+/// Projects without a record get a default one, named as their first todo
+/// spells it.
+pub fn overview(projects: &[Project], todos: &[Todo]) -> Vec<ProjectSummary> {
+    let mut rows: BTreeMap<String, ProjectSummary> = BTreeMap::new();
+    for p in projects {
+        rows.entry(p.name.as_str().to_lowercase())
+            .or_insert_with(|| ProjectSummary {
+                project: p.clone(),
+                open: 0,
+                done: 0,
+            });
+    }
+    for t in todos {
+        let row = rows
+            .entry(t.project.as_str().to_lowercase())
+            .or_insert_with(|| ProjectSummary {
+                project: Project::new(t.project.clone()),
+                open: 0,
+                done: 0,
+            });
+        if t.status.is_done() {
+            row.done += 1;
+        } else {
+            row.open += 1;
+        }
+    }
+    rows.into_values().collect()
+}
+
+/// The record for `name`, if there is one.
+pub fn find<'a>(projects: &'a [Project], name: &str) -> Option<&'a Project> {
+    projects.iter().find(|p| p.is_named(name))
+}
+
+/// Rename the record of project `from` to `to`.
+///
+/// If `to` already has a record the two are merged: `to` keeps its own
+/// settings and takes over the description and color it lacked.
+pub fn rename_record(projects: &mut Vec<Project>, from: &str, to: &ProjectName) {
+    let Some(i) = projects.iter().position(|p| p.is_named(from)) else {
+        return;
+    };
+    let same = projects[i].is_named(to.as_str());
+    match projects.iter().position(|p| p.is_named(to.as_str())) {
+        Some(j) if !same => {
+            let old = projects.remove(i);
+            let target = &mut projects[if j > i { j - 1 } else { j }];
+            target.description = target.description.take().or(old.description);
+            target.color = target.color.or(old.color);
+        }
+        _ => projects[i].name = to.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{Color, Title};
+
+    fn todo(title: &str, project: &str) -> Todo {
+        let mut t = Todo::new(Title::parse(title).unwrap());
+        t.project = ProjectName::parse(project).unwrap();
+        t
+    }
+
+    fn project(name: &str) -> Project {
+        Project::new(ProjectName::parse(name).unwrap())
+    }
+
+    #[test]
+    fn overview_joins_records_and_todo_names() {
+        let mut home = project("Home");
+        home.archived = true;
+        let mut done = todo("Filed", "work");
+        done.mark_done().unwrap();
+        let todos = vec![todo("Report", "Work"), done, todo("Milk", "Errands")];
+
+        let rows = overview(&[home, project("Garden")], &todos);
+        let names: Vec<_> = rows.iter().map(|r| r.project.name.as_str()).collect();
+        assert_eq!(names, ["Errands", "Garden", "Home", "Work"]);
+        assert!(rows[2].project.archived);
+        assert_eq!((rows[3].open, rows[3].done), (1, 1));
+    }
+
+    #[test]
+    fn renaming_onto_an_existing_record_merges_them() {
+        let mut old = project("Side");
+        old.description = Some("weekend hacking".to_string());
+        old.color = Some(Color::Green);
+        let mut main = project("Main");
+        main.color = Some(Color::Blue);
+        let mut projects = vec![old, main];
+
+        rename_record(&mut projects, "side", &ProjectName::parse("MAIN").unwrap());
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].name.as_str(), "Main");
+        assert_eq!(projects[0].color, Some(Color::Blue));
+        assert_eq!(projects[0].description.as_deref(), Some("weekend hacking"));
+
+        // A change of case is a plain rename.
+        rename_record(&mut projects, "main", &ProjectName::parse("main").unwrap());
+        assert_eq!(projects[0].name.as_str(), "main");
+    }
+}
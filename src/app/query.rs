@@ -2,8 +2,24 @@
 //!
 //! Keeps UI thin and reusable for TUI later.
 
-use crate::domain::todo::{Priority, Todo};
-use time::OffsetDateTime;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::due_input::parse_due,
+    domain::{
+        todo::{Energy, Priority, ProjectName, Tag, Todo, TodoId},
+        tracking,
+    },
+};
+use time::{
+    Date, Duration, OffsetDateTime, format_description::BorrowedFormatItem,
+    macros::format_description,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StatusFilter {
@@ -16,6 +32,36 @@ pub enum SortKey {
     Due,
     Priority,
     Created,
+    Title,
+    Id,
+    /// Tracked time, most first.
+    Time,
+    /// [`urgency`], most urgent first.
+    Urgency,
+}
+
+impl SortKey {
+    /// Tie-breakers applied after the requested keys, so the order is total
+    /// and the same on every run.
+    pub const TIE_BREAKERS: [SortKey; 4] = [
+        SortKey::Due,
+        SortKey::Priority,
+        SortKey::Created,
+        SortKey::Id,
+    ];
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "due" => Some(SortKey::Due),
+            "priority" => Some(SortKey::Priority),
+            "created" => Some(SortKey::Created),
+            "title" => Some(SortKey::Title),
+            "id" => Some(SortKey::Id),
+            "time" => Some(SortKey::Time),
+            "urgency" => Some(SortKey::Urgency),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -26,8 +72,37 @@ pub struct ListQuery {
     pub search: Option<String>,
     pub overdue: bool,
     pub priority: Option<Priority>,
+    pub energy: Option<Energy>,
+    /// Creation source: a kind (`import`) or exact source (`import:a.csv`).
+    pub source: Option<String>,
+    /// Show only someday/maybe items instead of hiding them.
+    pub someday: bool,
+    /// Show only archived todos instead of hiding them.
+    pub archived: bool,
+    /// Workflow column (see [`in_state`]).
+    pub state: Option<String>,
+    /// `Some(true)`: only todos waiting on an open dependency; `Some(false)`:
+    /// only open todos that aren't (ready to start). Needs the whole list, so
+    /// it is applied by [`find_in`] and [`count_in`] rather than [`matches`].
+    pub blocked: Option<bool>,
     pub sort: SortKey,
+    /// Keys to break ties on, before the fixed [`SortKey::TIE_BREAKERS`].
+    pub then_by: Vec<SortKey>,
+    /// Reverse the primary sort key (ties keep their ascending order).
     pub desc: bool,
+    /// Cursor: start right after this todo in the sorted order. It doesn't
+    /// have to match the filters any more, but it must still exist; a cursor
+    /// pointing at a deleted todo yields nothing.
+    pub after: Option<TodoId>,
+    /// Matches to skip (after the cursor, if any).
+    pub offset: usize,
+    /// At most this many matches (`None` = all).
+    pub limit: Option<usize>,
+    /// Terms of the query language (see [`ListQuery::add_query`]), all of
+    /// which must hold.
+    pub terms: Vec<Term>,
+    /// How [`SortKey::Urgency`] weighs todos.
+    pub urgency: UrgencyCoefficients,
 }
 
 impl Default for ListQuery {
@@ -39,88 +114,1273 @@ impl Default for ListQuery {
             search: None,
             overdue: false,
             priority: None,
+            energy: None,
+            source: None,
+            someday: false,
+            archived: false,
+            state: None,
+            blocked: None,
             sort: SortKey::Due,
+            then_by: Vec::new(),
             desc: false,
+            after: None,
+            offset: 0,
+            limit: None,
+            terms: Vec::new(),
+            urgency: UrgencyCoefficients::default(),
         }
     }
 }
 
-pub fn apply_list_query(mut todos: Vec<Todo>, q: &ListQuery, now: OffsetDateTime) -> Vec<Todo> {
-    // Filter
-    todos.retain(|t| {
-        // status
-        if let Some(sf) = q.status {
-            let is_done = t.status.is_done();
-            match sf {
-                StatusFilter::Open if is_done => return false,
-                StatusFilter::Done if !is_done => return false,
-                _ => {}
+impl ListQuery {
+    /// Narrow the query by one `key=value` term, as taken by `--filter`.
+    ///
+    /// Keys: status, project, tag, search, priority, energy, source, state;
+    /// `overdue`, `someday`, `archived`, `blocked` and `ready` take no value.
+    pub fn add_filter(&mut self, term: &str) -> Result<(), String> {
+        let (key, value) = term.split_once('=').unwrap_or((term, ""));
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "status" => {
+                self.status = Some(match value.to_ascii_lowercase().as_str() {
+                    "open" => StatusFilter::Open,
+                    "done" => StatusFilter::Done,
+                    other => return Err(format!("unknown status {other} (use open|done)")),
+                })
+            }
+            "project" => self.project = Some(value.to_string()),
+            "tag" => self.tag = Some(value.trim_start_matches('#').to_string()),
+            "search" => self.search = Some(value.to_string()),
+            "priority" => self.priority = Some(Priority::parse(value).map_err(|e| e.to_string())?),
+            "energy" => self.energy = Some(Energy::parse(value).map_err(|e| e.to_string())?),
+            "source" => self.source = Some(value.to_string()),
+            "state" => self.state = Some(value.to_string()),
+            "overdue" => self.overdue = true,
+            "someday" => self.someday = true,
+            "archived" => self.archived = true,
+            "blocked" => self.blocked = Some(true),
+            "ready" => self.blocked = Some(false),
+            other => {
+                return Err(format!(
+                    "unknown filter {other} (use status|project|tag|search|priority|energy|source|state|overdue|someday|archived|blocked|ready)"
+                ));
             }
         }
+        Ok(())
+    }
 
-        // project (case-insensitive match)
-        if let Some(p) = &q.project {
-            if !t.project.as_str().eq_ignore_ascii_case(p.trim()) {
-                return false;
+    /// Narrow the query by the terms of a query-language string, as taken by
+    /// `list "project:Work tag:rust due<2026-02-01 -tag:blocked urgent"`.
+    ///
+    /// Terms are separated by spaces (or a bare `and`) and must all hold:
+    /// - `project:NAME`, `tag:NAME` (or `#NAME`), `source:SOURCE`
+    /// - `ref:jira` or `ref:jira:ABC-123` (an external reference)
+    /// - `status:open|done`, `is:open|done|overdue|someday|archived`
+    /// - `energy:low|medium|high`
+    /// - `state:in-progress` (any workflow state, or `open`/`done`)
+    /// - `priority:P1` (or `p:P1`), and ranges like `priority<=P2` (P1 and P2)
+    /// - `due:2026-02-01`, `due<2026-02-01`, `due>=today`, `due:none`; dates
+    ///   and `today`/`tomorrow`/`yesterday` stand for the whole day in `now`'s
+    ///   offset, anything else `--due` accepts for that instant
+    /// - any other word (or a `"quoted phrase"`) is searched in title/notes
+    ///
+    /// A leading `-` negates a term. Values with spaces can be quoted:
+    /// `project:"Side project"`.
+    pub fn add_query(&mut self, input: &str, now: OffsetDateTime) -> Result<(), String> {
+        for token in tokenize(input)? {
+            if token.eq_ignore_ascii_case("and") {
+                continue;
             }
+            let (negated, token) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, token.as_str()),
+            };
+            let condition = if token.starts_with('"') {
+                Condition::Text(unquote(token))
+            } else if let Some(tag) = token.strip_prefix('#') {
+                Condition::Tag(tag.to_ascii_lowercase())
+            } else if let Some((key, op, value)) = split_term(token) {
+                let value = unquote(value);
+                if value.is_empty() {
+                    return Err(format!("{key}{} needs a value", op.as_str()));
+                }
+                match parse_condition(&key.to_ascii_lowercase(), op, &value, now)? {
+                    Parsed::Condition(c) => c,
+                    Parsed::Someday => {
+                        self.someday = !negated;
+                        continue;
+                    }
+                    Parsed::Archived => {
+                        self.archived = !negated;
+                        continue;
+                    }
+                }
+            } else {
+                Condition::Text(unquote(token))
+            };
+            self.terms.push(Term { negated, condition });
+        }
+        Ok(())
+    }
+}
+
+/// One term of the query language.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub negated: bool,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Project name, case-insensitive.
+    Project(String),
+    /// Normalized (lowercase) tag.
+    Tag(String),
+    /// Text in the title or notes, case-insensitive.
+    Text(String),
+    Status(StatusFilter),
+    Overdue,
+    Energy(Energy),
+    /// A source kind or exact source, as with [`ListQuery::source`].
+    Source(String),
+    /// An external reference: a provider or `provider:key`.
+    Ref(String),
+    /// Workflow column, as with [`ListQuery::state`].
+    State(String),
+    /// Priority in `min..=max`.
+    Priority {
+        min: Priority,
+        max: Priority,
+    },
+    /// Due in `from..until` (`None` = unbounded). Undated todos never match.
+    Due {
+        from: Option<OffsetDateTime>,
+        until: Option<OffsetDateTime>,
+    },
+    /// No due date.
+    NoDue,
+}
+
+impl Condition {
+    pub fn holds(&self, t: &Todo, now: OffsetDateTime) -> bool {
+        match self {
+            Condition::Project(p) => t.project.as_str().eq_ignore_ascii_case(p),
+            Condition::Tag(tag) => t.tags.iter().any(|x| x.as_str() == tag),
+            Condition::Text(text) => mentions(t, &text.to_ascii_lowercase()),
+            Condition::Status(StatusFilter::Open) => !t.status.is_done(),
+            Condition::Status(StatusFilter::Done) => t.status.is_done(),
+            Condition::Overdue => t.is_overdue(now),
+            Condition::Energy(e) => t.energy == Some(*e),
+            Condition::Source(src) => t.source.as_ref().is_some_and(|s| s.matches(src)),
+            Condition::Ref(r) => t.external_ref.as_ref().is_some_and(|x| x.matches(r)),
+            Condition::State(state) => in_state(t, state),
+            Condition::Priority { min, max } => (*min..=*max).contains(&t.priority),
+            Condition::Due { from, until } => t.due.map(|d| d.as_dt()).is_some_and(|due| {
+                from.is_none_or(|from| due >= from) && until.is_none_or(|until| due < until)
+            }),
+            Condition::NoDue => t.due.is_none(),
         }
+    }
+}
 
-        // tag (normalized tags are lowercase)
-        if let Some(tag) = &q.tag {
-            let needle = tag.trim().to_ascii_lowercase();
-            if !t.tags.iter().any(|x| x.as_str() == needle) {
-                return false;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Is,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::Is => ":",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+}
+
+enum Parsed {
+    Condition(Condition),
+    /// `is:someday` switches lists rather than filtering.
+    Someday,
+    /// So does `is:archived`.
+    Archived,
+}
+
+const PRIORITIES: [Priority; 4] = [Priority::P1, Priority::P2, Priority::P3, Priority::P4];
+
+const DATE: &[BorrowedFormatItem<'_>] = format_description!("[year]-[month]-[day]");
+
+fn parse_condition(key: &str, op: Op, value: &str, now: OffsetDateTime) -> Result<Parsed, String> {
+    let condition = match (key, op) {
+        ("due", Op::Is) if value.eq_ignore_ascii_case("none") => Condition::NoDue,
+        ("due", op) => {
+            let (start, end) = due_span(value, now)?;
+            let (from, until) = match op {
+                Op::Is => (Some(start), Some(end)),
+                Op::Lt => (None, Some(start)),
+                Op::Le => (None, Some(end)),
+                Op::Gt => (Some(end), None),
+                Op::Ge => (Some(start), None),
+            };
+            Condition::Due { from, until }
+        }
+        ("priority" | "p", op) => {
+            let p = Priority::parse(value).map_err(|e| e.to_string())?;
+            let i = PRIORITIES.iter().position(|x| *x == p).unwrap_or_default();
+            let (min, max) = match op {
+                Op::Is => (i, i),
+                Op::Lt if i == 0 => return Err(format!("nothing is above {}", p.label())),
+                Op::Lt => (0, i - 1),
+                Op::Le => (0, i),
+                Op::Gt if i == PRIORITIES.len() - 1 => {
+                    return Err(format!("nothing is below {}", p.label()));
+                }
+                Op::Gt => (i + 1, PRIORITIES.len() - 1),
+                Op::Ge => (i, PRIORITIES.len() - 1),
+            };
+            Condition::Priority {
+                min: PRIORITIES[min],
+                max: PRIORITIES[max],
+            }
+        }
+        (_, Op::Lt | Op::Le | Op::Gt | Op::Ge) => {
+            return Err(format!(
+                "{key}{} - only due and priority take ranges",
+                op.as_str()
+            ));
+        }
+        ("project", _) => Condition::Project(value.to_string()),
+        ("tag", _) => Condition::Tag(value.trim_start_matches('#').to_ascii_lowercase()),
+        ("source", _) => Condition::Source(value.to_string()),
+        ("ref", _) => Condition::Ref(value.to_string()),
+        ("state", _) => Condition::State(value.to_string()),
+        ("energy", _) => Condition::Energy(Energy::parse(value).map_err(|e| e.to_string())?),
+        ("status" | "is", _) => match value.to_ascii_lowercase().as_str() {
+            "open" => Condition::Status(StatusFilter::Open),
+            "done" => Condition::Status(StatusFilter::Done),
+            "overdue" if key == "is" => Condition::Overdue,
+            "someday" if key == "is" => return Ok(Parsed::Someday),
+            "archived" if key == "is" => return Ok(Parsed::Archived),
+            other if key == "is" => {
+                return Err(format!(
+                    "unknown is:{other} (use open|done|overdue|someday|archived)"
+                ));
             }
+            other => return Err(format!("unknown status {other} (use open|done)")),
+        },
+        (other, _) => {
+            return Err(format!(
+                "unknown query field {other} (use project|tag|source|ref|state|status|is|energy|priority|due, or quote the text)"
+            ));
         }
+    };
+    Ok(Parsed::Condition(condition))
+}
 
-        // priority
-        if let Some(pr) = q.priority {
-            if t.priority != pr {
-                return false;
+/// The time a due value covers, as `start..end`: a whole day for dates and
+/// day names, a single instant for anything else.
+fn due_span(value: &str, now: OffsetDateTime) -> Result<(OffsetDateTime, OffsetDateTime), String> {
+    let today = now.date();
+    let day = match value.to_ascii_lowercase().as_str() {
+        "today" => Some(today),
+        "tomorrow" => today.next_day(),
+        "yesterday" => today.previous_day(),
+        _ => Date::parse(value, DATE).ok(),
+    };
+    if let Some(day) = day {
+        let start = day.midnight().assume_offset(now.offset());
+        return Ok((start, start + Duration::DAY));
+    }
+    let at = parse_due(value, now)?.as_dt();
+    Ok((at, at + Duration::NANOSECOND))
+}
+
+/// Split on spaces outside double quotes, keeping the quotes.
+fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
             }
+            c => current.push(c),
         }
+    }
+    if quoted {
+        return Err(format!("unclosed quote in query {input:?}"));
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
 
-        // overdue
-        if q.overdue && !t.is_overdue(now) {
-            return false;
+/// `key<op>value`, if the token starts with a field name.
+fn split_term(token: &str) -> Option<(&str, Op, &str)> {
+    let i = token.find([':', '<', '>', '=', '"'])?;
+    let key = &token[..i];
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let rest = &token[i..];
+    let (op, len) = if rest.starts_with("<=") {
+        (Op::Le, 2)
+    } else if rest.starts_with(">=") {
+        (Op::Ge, 2)
+    } else {
+        match rest.as_bytes()[0] {
+            b':' | b'=' => (Op::Is, 1),
+            b'<' => (Op::Lt, 1),
+            b'>' => (Op::Gt, 1),
+            _ => return None,
         }
+    };
+    Some((key, op, &rest[len..]))
+}
 
-        // search (title + notes)
-        if let Some(s) = &q.search {
-            let needle = s.trim().to_ascii_lowercase();
-            if needle.is_empty() {
-                // ignore empty search
-            } else {
-                let title = t.title.as_str().to_ascii_lowercase();
-                let notes = t
-                    .notes
-                    .as_ref()
-                    .map(|n| n.as_str().to_ascii_lowercase())
-                    .unwrap_or_default();
-
-                if !title.contains(&needle) && !notes.contains(&needle) {
-                    return false;
+fn unquote(value: &str) -> String {
+    value.replace('"', "")
+}
+
+/// Is `t` in the board column `state`: a workflow state, `open` (open and
+/// not started) or `done`?
+pub fn in_state(t: &Todo, state: &str) -> bool {
+    let state = state.trim();
+    if t.status.is_done() {
+        return state.eq_ignore_ascii_case("done");
+    }
+    match &t.state {
+        None => state.eq_ignore_ascii_case("open"),
+        Some(s) => s.as_str().eq_ignore_ascii_case(state),
+    }
+}
+
+/// Does `needle` (lowercase) appear in the title or notes of `t`?
+fn mentions(t: &Todo, needle: &str) -> bool {
+    t.title.as_str().to_ascii_lowercase().contains(needle)
+        || t.notes
+            .as_ref()
+            .is_some_and(|n| n.as_str().to_ascii_lowercase().contains(needle))
+}
+
+/// Priority a todo is effectively worked at.
+///
+/// An open todo that blocks a more important open todo (directly or through a
+/// chain) inherits that priority, so low-priority prerequisites don't stall
+/// important work. Computed on the fly; never stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectivePriority {
+    pub priority: Priority,
+    /// The dependent this priority was inherited from, if raised.
+    pub via: Option<TodoId>,
+}
+
+/// Effective priority of every open todo in `todos`.
+pub fn effective_priorities(todos: &[Todo]) -> BTreeMap<TodoId, EffectivePriority> {
+    let mut eff: BTreeMap<TodoId, EffectivePriority> = todos
+        .iter()
+        .filter(|t| !t.status.is_done())
+        .map(|t| {
+            let e = EffectivePriority {
+                priority: t.priority,
+                via: None,
+            };
+            (t.id, e)
+        })
+        .collect();
+
+    // Push priorities down dependency edges until nothing improves. Values only
+    // ever move towards P1, so this terminates even with cycles.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for t in todos {
+            let Some(p) = eff.get(&t.id).map(|e| e.priority) else {
+                continue;
+            };
+            for dep in &t.depends_on {
+                if let Some(e) = eff.get_mut(dep)
+                    && p < e.priority
+                {
+                    *e = EffectivePriority {
+                        priority: p,
+                        via: Some(t.id),
+                    };
+                    changed = true;
                 }
             }
         }
+    }
+
+    eff
+}
+
+/// Weights of the [`urgency`] factors (`[urgency]` in config.toml). Each
+/// factor is scaled to 0..=1 before it is multiplied by its coefficient.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UrgencyCoefficients {
+    /// P1 counts fully, P2 0.65, P3 0.3, P4 not at all.
+    pub priority: f64,
+    /// Fully at a week overdue, 0.2 two weeks or more ahead, linear between.
+    pub due: f64,
+    /// Grows with age up to `max_age_days`.
+    pub age: f64,
+    pub max_age_days: f64,
+    /// 0.8 for one tag, 0.9 for two, fully for three or more.
+    pub tags: f64,
+    /// Added for each tag a todo has: `[urgency.tag] next = 15.0`. Negative
+    /// values push todos down.
+    pub tag: BTreeMap<String, f64>,
+}
+
+impl Default for UrgencyCoefficients {
+    fn default() -> Self {
+        Self {
+            priority: 6.0,
+            due: 12.0,
+            age: 2.0,
+            max_age_days: 365.0,
+            tags: 1.0,
+            tag: BTreeMap::new(),
+        }
+    }
+}
 
-        true
+/// How pressing `todo` is at `now`, Taskwarrior style: the weighted sum of
+/// its (effective) `priority`, how close it is to due, its age and its tags.
+/// Done todos score 0.
+pub fn urgency(
+    todo: &Todo,
+    priority: Priority,
+    now: OffsetDateTime,
+    c: &UrgencyCoefficients,
+) -> f64 {
+    if todo.status.is_done() {
+        return 0.0;
+    }
+    let priority_factor = match priority {
+        Priority::P1 => 1.0,
+        Priority::P2 => 0.65,
+        Priority::P3 => 0.3,
+        Priority::P4 => 0.0,
+    };
+    let due_factor = todo.due.map_or(0.0, |due| {
+        let overdue_days = (now - due.as_dt()).as_seconds_f64() / 86_400.0;
+        ((overdue_days + 14.0) * 0.8 / 21.0 + 0.2).clamp(0.2, 1.0)
     });
+    let age_days = (now - todo.created_at).as_seconds_f64() / 86_400.0;
+    let age_factor = match c.max_age_days {
+        max if max > 0.0 => (age_days / max).clamp(0.0, 1.0),
+        _ => 0.0,
+    };
+    let tags_factor = match todo.tags.len() {
+        0 => 0.0,
+        1 => 0.8,
+        2 => 0.9,
+        _ => 1.0,
+    };
+    let per_tag: f64 = todo.tags.iter().filter_map(|t| c.tag.get(t.as_str())).sum();
+
+    c.priority * priority_factor
+        + c.due * due_factor
+        + c.age * age_factor
+        + c.tags * tags_factor
+        + per_tag
+}
 
-    // Sort
-    todos.sort_by(|a, b| match q.sort {
-        SortKey::Due => {
-            // None due sorts after Some due by default (strong default UX).
-            // Within due: earlier first.
-            a.due.cmp(&b.due)
+/// True if every dependency of `todo` is done (or no longer exists).
+pub fn is_unblocked(todo: &Todo, todos: &[Todo]) -> bool {
+    todo.depends_on.iter().all(|dep| {
+        todos
+            .iter()
+            .find(|t| t.id == *dep)
+            .is_none_or(|t| t.status.is_done())
+    })
+}
+
+/// True if `from` depends on `target`, directly or through other todos.
+pub fn depends_transitively(todos: &[Todo], from: TodoId, target: TodoId) -> bool {
+    let mut seen = BTreeSet::new();
+    let mut stack = vec![from];
+    while let Some(id) = stack.pop() {
+        if !seen.insert(id) {
+            continue;
         }
-        SortKey::Priority => a.priority.cmp(&b.priority), // P1 < P4
-        SortKey::Created => a.created_at.cmp(&b.created_at),
+        let Some(t) = todos.iter().find(|t| t.id == id) else {
+            continue;
+        };
+        if t.depends_on.contains(&target) {
+            return true;
+        }
+        stack.extend(t.depends_on.iter().copied());
+    }
+    false
+}
+
+/// The most important open todos that can be started right now.
+///
+/// Ordered by effective priority, then due date (undated last), then age.
+/// With `max_energy`, only todos rated at or below that energy are picked;
+/// unrated ones are left out since there's no telling what they take.
+pub fn next_actions(
+    todos: &[Todo],
+    limit: usize,
+    max_energy: Option<Energy>,
+) -> Vec<(Todo, EffectivePriority)> {
+    let eff = effective_priorities(todos);
+
+    let mut ready: Vec<_> = todos
+        .iter()
+        .filter(|t| t.is_active() && is_unblocked(t, todos))
+        .filter(|t| max_energy.is_none_or(|max| t.energy.is_some_and(|e| e <= max)))
+        .filter_map(|t| eff.get(&t.id).map(|e| (t.clone(), *e)))
+        .collect();
+
+    ready.sort_by(|(a, ea), (b, eb)| {
+        ea.priority
+            .cmp(&eb.priority)
+            .then_with(|| match (a.due, b.due) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            })
+            .then_with(|| a.created_at.cmp(&b.created_at))
     });
+    ready.truncate(limit);
+    ready
+}
+
+/// What a wall display shows: active todos that are late, and the rest due today.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Board {
+    /// Due before `now`, oldest first.
+    pub overdue: Vec<Todo>,
+    /// Due later on the same (UTC) day as `now`.
+    pub today: Vec<Todo>,
+}
+
+pub fn board(todos: &[Todo], now: OffsetDateTime) -> Board {
+    let today = now.to_offset(time::UtcOffset::UTC).date();
+    let mut board = Board::default();
+    for t in todos.iter().filter(|t| t.is_active()) {
+        let Some(due) = t.due.map(|d| d.as_dt()) else {
+            continue;
+        };
+        if due < now {
+            board.overdue.push(t.clone());
+        } else if due.to_offset(time::UtcOffset::UTC).date() == today {
+            board.today.push(t.clone());
+        }
+    }
+    for list in [&mut board.overdue, &mut board.today] {
+        list.sort_by(|a, b| a.due.cmp(&b.due).then_with(|| a.priority.cmp(&b.priority)));
+    }
+    board
+}
+
+/// What `list --group-by` buckets todos by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Project,
+    Tag,
+    Priority,
+    Due,
+}
+
+impl GroupBy {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "project" => Some(Self::Project),
+            "tag" => Some(Self::Tag),
+            "priority" => Some(Self::Priority),
+            "due" => Some(Self::Due),
+            _ => None,
+        }
+    }
+}
+
+/// When a todo is due, relative to today (UTC, like [`board`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DueBucket {
+    Overdue,
+    Today,
+    Tomorrow,
+    /// Within the 7 days after tomorrow.
+    ThisWeek,
+    Later,
+    NoDueDate,
+}
+
+impl DueBucket {
+    pub fn of(t: &Todo, now: OffsetDateTime) -> Self {
+        let Some(due) = t.due.map(|d| d.as_dt()) else {
+            return Self::NoDueDate;
+        };
+        if t.is_overdue(now) {
+            return Self::Overdue;
+        }
+        let today = now.to_offset(time::UtcOffset::UTC).date();
+        let days = (due.to_offset(time::UtcOffset::UTC).date() - today).whole_days();
+        match days {
+            ..=0 => Self::Today,
+            1 => Self::Tomorrow,
+            2..=8 => Self::ThisWeek,
+            _ => Self::Later,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Overdue => "Overdue",
+            Self::Today => "Today",
+            Self::Tomorrow => "Tomorrow",
+            Self::ThisWeek => "This week",
+            Self::Later => "Later",
+            Self::NoDueDate => "No due date",
+        }
+    }
+}
+
+/// The heading of one group. Groups sort in this order: projects and tags
+/// by name (untagged last), P1 first, soonest due first.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GroupKey {
+    Project(ProjectName),
+    Tag(Tag),
+    Untagged,
+    Priority(Priority),
+    Due(DueBucket),
+}
+
+impl GroupKey {
+    /// Heading text; priorities are shown by the caller's scheme.
+    pub fn label(&self, priority: impl Fn(Priority) -> String) -> String {
+        match self {
+            Self::Project(p) => p.as_str().to_string(),
+            Self::Tag(t) => format!("#{}", t.as_str()),
+            Self::Untagged => crate::app::stats::UNTAGGED.to_string(),
+            Self::Priority(p) => priority(*p),
+            Self::Due(b) => b.label().to_string(),
+        }
+    }
+}
+
+/// Split `todos` into groups, keeping their order within each group. By tag,
+/// a todo shows up under each of its tags.
+pub fn group(todos: Vec<Todo>, by: GroupBy, now: OffsetDateTime) -> Vec<(GroupKey, Vec<Todo>)> {
+    let mut groups: BTreeMap<GroupKey, Vec<Todo>> = BTreeMap::new();
+    for t in todos {
+        let keys = match by {
+            GroupBy::Project => vec![GroupKey::Project(t.project.clone())],
+            GroupBy::Tag if t.tags.is_empty() => vec![GroupKey::Untagged],
+            GroupBy::Tag => t.tags.iter().cloned().map(GroupKey::Tag).collect(),
+            GroupBy::Priority => vec![GroupKey::Priority(t.priority)],
+            GroupBy::Due => vec![GroupKey::Due(DueBucket::of(&t, now))],
+        };
+        for key in keys {
+            groups.entry(key).or_default().push(t.clone());
+        }
+    }
+    groups.into_iter().collect()
+}
+
+/// Does `t` pass the filters of `q`? (Sorting is left to the caller.)
+pub fn matches(t: &Todo, q: &ListQuery, now: OffsetDateTime) -> bool {
+    // someday items are a separate list
+    if t.is_someday() != q.someday {
+        return false;
+    }
+    // and so is the archive
+    if t.is_archived() != q.archived {
+        return false;
+    }
+
+    // status
+    if let Some(sf) = q.status {
+        let is_done = t.status.is_done();
+        match sf {
+            StatusFilter::Open if is_done => return false,
+            StatusFilter::Done if !is_done => return false,
+            _ => {}
+        }
+    }
 
-    if q.desc {
-        todos.reverse();
+    // project (case-insensitive match)
+    if let Some(p) = &q.project
+        && !t.project.as_str().eq_ignore_ascii_case(p.trim())
+    {
+        return false;
     }
 
+    // tag (normalized tags are lowercase)
+    if let Some(tag) = &q.tag {
+        let needle = tag.trim().to_ascii_lowercase();
+        if !t.tags.iter().any(|x| x.as_str() == needle) {
+            return false;
+        }
+    }
+
+    // priority
+    if let Some(pr) = q.priority
+        && t.priority != pr
+    {
+        return false;
+    }
+
+    if let Some(state) = &q.state
+        && !in_state(t, state)
+    {
+        return false;
+    }
+
+    // energy
+    if let Some(en) = q.energy
+        && t.energy != Some(en)
+    {
+        return false;
+    }
+
+    // source
+    if let Some(src) = &q.source
+        && !t.source.as_ref().is_some_and(|s| s.matches(src))
+    {
+        return false;
+    }
+
+    // overdue
+    if q.overdue && !t.is_overdue(now) {
+        return false;
+    }
+
+    // search (title + notes)
+    if let Some(s) = &q.search {
+        let needle = s.trim().to_ascii_lowercase();
+        // ignore empty search
+        if !needle.is_empty() && !mentions(t, &needle) {
+            return false;
+        }
+    }
+
+    // query-language terms
+    if !q
+        .terms
+        .iter()
+        .all(|term| term.condition.holds(t, now) != term.negated)
+    {
+        return false;
+    }
+
+    true
+}
+
+pub fn apply_list_query(mut todos: Vec<Todo>, q: &ListQuery, now: OffsetDateTime) -> Vec<Todo> {
+    // Inherited priorities depend on the whole list, not just what passes the filter.
+    let eff = effective_priorities(&todos);
+    let cursor = q.after.map(|id| todos.iter().find(|t| t.id == id).cloned());
+    let blocked = blocked_filter(q, &todos);
+    todos.retain(|t| matches(t, q, now) && blocked(t));
+    sort_matches(&mut todos, q, &eff, now);
+    paginate(todos, q, &eff, now, cursor.as_ref().map(Option::as_ref))
+}
+
+/// [`apply_list_query`] over borrowed todos: only the matches are cloned.
+pub fn find_in(todos: &[Todo], q: &ListQuery, now: OffsetDateTime) -> Vec<Todo> {
+    let eff = effective_priorities(todos);
+    let cursor = q.after.map(|id| todos.iter().find(|t| t.id == id));
+    let blocked = blocked_filter(q, todos);
+    let mut found: Vec<Todo> = todos
+        .iter()
+        .filter(|t| matches(t, q, now) && blocked(t))
+        .cloned()
+        .collect();
+    sort_matches(&mut found, q, &eff, now);
+    paginate(found, q, &eff, now, cursor)
+}
+
+/// How many of `todos` pass the filters of `q` (ignoring pagination).
+pub fn count_in(todos: &[Todo], q: &ListQuery, now: OffsetDateTime) -> usize {
+    let blocked = blocked_filter(q, todos);
     todos
+        .iter()
+        .filter(|t| matches(t, q, now) && blocked(t))
+        .count()
+}
+
+/// The [`ListQuery::blocked`] filter, which needs to know which of `todos`
+/// are still open.
+fn blocked_filter(q: &ListQuery, todos: &[Todo]) -> impl Fn(&Todo) -> bool + use<> {
+    let want = q.blocked;
+    let open: BTreeSet<TodoId> = match want {
+        Some(_) => todos
+            .iter()
+            .filter(|t| !t.status.is_done())
+            .map(|t| t.id)
+            .collect(),
+        None => BTreeSet::new(),
+    };
+    move |t: &Todo| {
+        want.is_none_or(|want| {
+            let waiting = t.depends_on.iter().any(|d| open.contains(d));
+            if want {
+                waiting
+            } else {
+                !waiting && !t.status.is_done()
+            }
+        })
+    }
+}
+
+fn sort_matches(
+    todos: &mut [Todo],
+    q: &ListQuery,
+    eff: &BTreeMap<TodoId, EffectivePriority>,
+    now: OffsetDateTime,
+) {
+    todos.sort_by(|a, b| compare(q, eff, now, a, b));
+}
+
+/// The order of [`ListQuery::sort`]: requested key, then `then_by`, then the
+/// fixed tie-breakers.
+fn compare(
+    q: &ListQuery,
+    eff: &BTreeMap<TodoId, EffectivePriority>,
+    now: OffsetDateTime,
+    a: &Todo,
+    b: &Todo,
+) -> Ordering {
+    let priority_of = |t: &Todo| eff.get(&t.id).map_or(t.priority, |e| e.priority);
+    // Running timers count up to `now`.
+    let tracked = |t: &Todo| tracking::total(&t.time_entries, None, now);
+    let cmp = |key: SortKey| match key {
+        // Undated todos sort after dated ones; within due: earlier first.
+        SortKey::Due => match (a.due, b.due) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        },
+        SortKey::Priority => priority_of(a).cmp(&priority_of(b)), // P1 < P4
+        SortKey::Created => a.created_at.cmp(&b.created_at),
+        SortKey::Title => a
+            .title
+            .as_str()
+            .to_lowercase()
+            .cmp(&b.title.as_str().to_lowercase()),
+        SortKey::Id => a.id.cmp(&b.id),
+        SortKey::Time => tracked(b).cmp(&tracked(a)),
+        SortKey::Urgency => {
+            let score = |t: &Todo| urgency(t, priority_of(t), now, &q.urgency);
+            score(b).total_cmp(&score(a))
+        }
+    };
+    let primary = cmp(q.sort);
+    let primary = if q.desc { primary.reverse() } else { primary };
+    q.then_by
+        .iter()
+        .chain(&SortKey::TIE_BREAKERS)
+        .fold(primary, |ord, &key| ord.then_with(|| cmp(key)))
+}
+
+/// Apply the cursor, offset and limit to sorted matches. `cursor` is the
+/// looked-up [`ListQuery::after`] todo (`Some(None)` if it doesn't exist).
+fn paginate(
+    mut sorted: Vec<Todo>,
+    q: &ListQuery,
+    eff: &BTreeMap<TodoId, EffectivePriority>,
+    now: OffsetDateTime,
+    cursor: Option<Option<&Todo>>,
+) -> Vec<Todo> {
+    let start = match cursor {
+        None => 0,
+        Some(None) => sorted.len(),
+        // The order is total, so everything after the cursor compares greater.
+        Some(Some(c)) => {
+            sorted.partition_point(|t| compare(q, eff, now, t, c) != Ordering::Greater)
+        }
+    };
+    let start = start.saturating_add(q.offset).min(sorted.len());
+    let end = q
+        .limit
+        .map_or(sorted.len(), |n| start.saturating_add(n).min(sorted.len()));
+    sorted.truncate(end);
+    sorted.drain(..start);
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::Title;
+
+    fn todo(title: &str, priority: Priority) -> Todo {
+        let mut t = Todo::new(Title::parse(title).unwrap());
+        t.priority = priority;
+        t
+    }
+
+    #[test]
+    fn blockers_inherit_priority_through_chains() {
+        let mut launch = todo("Launch", Priority::P1);
+        let mut design = todo("Design", Priority::P3);
+        let research = todo("Research", Priority::P4);
+        let unrelated = todo("Unrelated", Priority::P2);
+        design.depends_on.insert(research.id);
+        launch.depends_on.insert(design.id);
+        let todos = vec![
+            launch.clone(),
+            design.clone(),
+            research.clone(),
+            unrelated.clone(),
+        ];
+
+        let eff = effective_priorities(&todos);
+        assert_eq!(eff[&research.id].priority, Priority::P1);
+        assert_eq!(eff[&research.id].via, Some(design.id));
+        assert_eq!(eff[&unrelated.id].via, None);
+
+        // Only the unblocked research task and the unrelated one are actionable,
+        // and the inherited P1 outranks the P2.
+        let next: Vec<_> = next_actions(&todos, 10, None)
+            .into_iter()
+            .map(|(t, _)| t.id)
+            .collect();
+        assert_eq!(next, vec![research.id, unrelated.id]);
+
+        // Low-energy picks skip unrated and heavier work.
+        let mut todos = todos;
+        todos[3].energy = Some(Energy::Low);
+        todos[2].energy = Some(Energy::High);
+        let low: Vec<_> = next_actions(&todos, 10, Some(Energy::Low))
+            .into_iter()
+            .map(|(t, _)| t.id)
+            .collect();
+        assert_eq!(low, vec![unrelated.id]);
+    }
+
+    #[test]
+    fn sort_ties_are_broken_deterministically() {
+        let now = OffsetDateTime::now_utc();
+        let mut todos: Vec<_> = ["b", "a", "c"]
+            .into_iter()
+            .map(|t| todo(t, Priority::P2))
+            .collect();
+        todos[2].priority = Priority::P1;
+        let created = todos[0].created_at;
+        for t in &mut todos {
+            t.created_at = created;
+        }
+
+        let titles = |q: &ListQuery| -> Vec<String> {
+            let mut shuffled = todos.clone();
+            shuffled.reverse();
+            let a: Vec<_> = apply_list_query(todos.clone(), q, now)
+                .iter()
+                .map(|t| t.title.as_str().to_string())
+                .collect();
+            let b: Vec<_> = apply_list_query(shuffled, q, now)
+                .iter()
+                .map(|t| t.title.as_str().to_string())
+                .collect();
+            assert_eq!(a, b, "order must not depend on input order");
+            a
+        };
+
+        // All undated: priority breaks the tie, then (equal creation) the id.
+        assert_eq!(titles(&ListQuery::default())[0], "c");
+
+        let by_title = ListQuery {
+            then_by: vec![SortKey::Title],
+            ..ListQuery::default()
+        };
+        assert_eq!(titles(&by_title), ["a", "b", "c"]);
+
+        let desc = ListQuery {
+            sort: SortKey::Priority,
+            desc: true,
+            then_by: vec![SortKey::Title],
+            ..ListQuery::default()
+        };
+        assert_eq!(titles(&desc), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn board_splits_overdue_and_due_today() {
+        use crate::domain::todo::DueAt;
+        use time::{Duration, macros::datetime};
+
+        let now = datetime!(2026-03-10 12:00 UTC);
+        let due = |title: &str, at: OffsetDateTime| {
+            let mut t = todo(title, Priority::P3);
+            t.due = Some(DueAt::from_dt(at));
+            t
+        };
+        let mut finished = due("Finished", now - Duration::days(1));
+        finished.mark_done().unwrap();
+        let todos = vec![
+            due("Tonight", now + Duration::hours(6)),
+            due("This morning", now - Duration::hours(3)),
+            due("Last week", now - Duration::days(7)),
+            due("Tomorrow", now + Duration::hours(13)),
+            finished,
+            todo("Someday", Priority::P1),
+        ];
+
+        let b = board(&todos, now);
+        let titles = |l: &[Todo]| {
+            l.iter()
+                .map(|t| t.title.as_str().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(&b.overdue), ["Last week", "This morning"]);
+        assert_eq!(titles(&b.today), ["Tonight"]);
+    }
+
+    #[test]
+    fn pages_follow_the_sort_order_and_cursors_survive_deletes() {
+        let todos: Vec<_> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|t| todo(t, Priority::P3))
+            .collect();
+        let now = OffsetDateTime::now_utc();
+        let titles = |l: &[Todo]| {
+            l.iter()
+                .map(|t| t.title.as_str().to_string())
+                .collect::<Vec<_>>()
+        };
+        let mut q = ListQuery {
+            sort: SortKey::Title,
+            offset: 1,
+            limit: Some(2),
+            ..ListQuery::default()
+        };
+        assert_eq!(titles(&find_in(&todos, &q, now)), ["b", "c"]);
+
+        // Continue after "c" even once it no longer matches the filter.
+        q.offset = 0;
+        q.after = Some(todos[2].id);
+        q.search = Some("d".to_string());
+        assert_eq!(titles(&find_in(&todos, &q, now)), ["d"]);
+        q.search = None;
+        assert_eq!(
+            titles(&apply_list_query(todos.clone(), &q, now)),
+            ["d", "e"]
+        );
+
+        // A cursor todo that is gone yields nothing; offsets past the end too.
+        let gone = todo("gone", Priority::P3);
+        q.after = Some(gone.id);
+        assert!(find_in(&todos, &q, now).is_empty());
+        q.after = None;
+        q.offset = 10;
+        assert!(find_in(&todos, &q, now).is_empty());
+    }
+
+    #[test]
+    fn query_language_compiles_to_terms() {
+        use crate::domain::todo::{DueAt, ProjectName, Tag};
+        use time::macros::datetime;
+
+        let now = datetime!(2026-01-20 12:00 UTC);
+        let mut fix = todo("Fix urgent bug", Priority::P1);
+        fix.project = ProjectName::parse("Work").unwrap();
+        fix.tags.insert(Tag::parse("rust").unwrap());
+        fix.due = Some(DueAt::from_dt(datetime!(2026-01-31 09:00 UTC)));
+        let mut blocked = fix.clone();
+        blocked.id = TodoId::new();
+        blocked.tags.insert(Tag::parse("blocked").unwrap());
+        let mut late = fix.clone();
+        late.id = TodoId::new();
+        late.due = Some(DueAt::from_dt(datetime!(2026-02-01 09:00 UTC)));
+        let mut calm = fix.clone();
+        calm.id = TodoId::new();
+        calm.title = Title::parse("Fix bug").unwrap();
+        let todos = [fix.clone(), blocked, late.clone(), calm];
+
+        let ids = |input: &str| {
+            let mut q = ListQuery::default();
+            q.add_query(input, now).unwrap();
+            todos
+                .iter()
+                .filter(|t| matches(t, &q, now))
+                .map(|t| t.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids("project:Work tag:rust due<2026-02-01 -tag:blocked urgent"),
+            [fix.id]
+        );
+        assert_eq!(ids("due:2026-02-01"), [late.id]);
+        assert_eq!(
+            ids("priority<=P2 \"urgent bug\" -#blocked"),
+            [fix.id, late.id]
+        );
+        assert!(ids("p>P1").is_empty());
+        assert!(ids("due:none").is_empty());
+        assert_eq!(ids("state:open").len(), 4);
+        assert!(ids("state:in-progress").is_empty());
+
+        let mut q = ListQuery::default();
+        assert!(q.add_query("tag<rust", now).is_err());
+        assert!(q.add_query("colour:red", now).is_err());
+        assert!(q.add_query("project:\"Side", now).is_err());
+        q.add_query("is:someday", now).unwrap();
+        assert!(q.someday && q.terms.is_empty());
+    }
+
+    #[test]
+    fn time_sort_puts_most_tracked_first_counting_running_timers() {
+        use crate::domain::tracking::TimeEntry;
+        use time::{Duration, macros::datetime};
+
+        let now = datetime!(2026-03-10 12:00 UTC);
+        let tracked = |title: &str, mins: i64, running: bool| {
+            let mut t = todo(title, Priority::P3);
+            let mut entry = TimeEntry::start(now - Duration::minutes(mins));
+            if !running {
+                entry.end = Some(now);
+            }
+            t.time_entries.push(entry);
+            t
+        };
+        let todos = vec![
+            todo("none", Priority::P1),
+            tracked("short", 10, false),
+            tracked("running", 90, true),
+            tracked("long", 60, false),
+        ];
+        let q = ListQuery {
+            sort: SortKey::Time,
+            ..ListQuery::default()
+        };
+        let titles: Vec<_> = find_in(&todos, &q, now)
+            .iter()
+            .map(|t| t.title.as_str().to_string())
+            .collect();
+        assert_eq!(titles, ["running", "long", "short", "none"]);
+    }
+
+    #[test]
+    fn groups_keep_list_order_and_sort_by_key() {
+        use crate::domain::todo::DueAt;
+        use time::{Duration, macros::datetime};
+
+        let now = datetime!(2026-03-10 12:00 UTC);
+        let due = |title: &str, p: Priority, hours: Option<i64>, tags: &[&str]| {
+            let mut t = todo(title, p);
+            t.due = hours.map(|h| DueAt::from_dt(now + Duration::hours(h)));
+            t.tags = tags.iter().map(|s| Tag::parse(*s).unwrap()).collect();
+            t
+        };
+        let todos = vec![
+            due("late", Priority::P2, Some(-3), &["home"]),
+            due("soon", Priority::P1, Some(2), &["work", "home"]),
+            due("tomorrow", Priority::P2, Some(24), &[]),
+            due("next week", Priority::P4, Some(24 * 5), &["work"]),
+            due("whenever", Priority::P1, None, &[]),
+        ];
+        let titles = |groups: &[(GroupKey, Vec<Todo>)]| -> Vec<(String, Vec<String>)> {
+            groups
+                .iter()
+                .map(|(k, ts)| {
+                    (
+                        k.label(|p| p.label().to_string()),
+                        ts.iter().map(|t| t.title.as_str().to_string()).collect(),
+                    )
+                })
+                .collect()
+        };
+
+        let by_due = group(todos.clone(), GroupBy::Due, now);
+        let by_due = titles(&by_due);
+        assert_eq!(by_due[0], ("Overdue".into(), vec!["late".into()]));
+        assert_eq!(by_due[1], ("Today".into(), vec!["soon".into()]));
+        assert_eq!(by_due[2], ("Tomorrow".into(), vec!["tomorrow".into()]));
+        assert_eq!(by_due[3], ("This week".into(), vec!["next week".into()]));
+        assert_eq!(by_due[4], ("No due date".into(), vec!["whenever".into()]));
+
+        let by_tag = titles(&group(todos.clone(), GroupBy::Tag, now));
+        let heads: Vec<_> = by_tag
+            .iter()
+            .map(|(k, ts)| (k.as_str(), ts.len()))
+            .collect();
+        assert_eq!(heads, [("#home", 2), ("#work", 2), ("(untagged)", 2)]);
+        assert_eq!(by_tag[0].1, ["late", "soon"]);
+
+        let by_priority = titles(&group(todos, GroupBy::Priority, now));
+        assert_eq!(
+            by_priority[0],
+            ("P1".into(), vec!["soon".into(), "whenever".into()])
+        );
+        assert_eq!(by_priority.len(), 3);
+    }
+
+    #[test]
+    fn urgency_weighs_priority_due_age_and_tags() {
+        use crate::domain::todo::{DueAt, Tag};
+        use time::macros::datetime;
+
+        let now = datetime!(2026-05-04 09:00 UTC);
+        let c = UrgencyCoefficients::default();
+        let fresh = |title, priority| {
+            let mut t = todo(title, priority);
+            t.created_at = now;
+            t
+        };
+
+        let plain = fresh("Plain", Priority::P4);
+        assert_eq!(urgency(&plain, Priority::P4, now, &c), 0.0);
+        assert_eq!(urgency(&plain, Priority::P1, now, &c), 6.0);
+
+        let mut overdue = fresh("Overdue", Priority::P4);
+        overdue.due = Some(DueAt::from_dt(now - Duration::days(10)));
+        assert_eq!(urgency(&overdue, Priority::P4, now, &c), 12.0);
+        let mut later = fresh("Later", Priority::P4);
+        later.due = Some(DueAt::from_dt(now + Duration::days(30)));
+        assert!((urgency(&later, Priority::P4, now, &c) - 2.4).abs() < 1e-9);
+
+        let mut old = fresh("Old", Priority::P4);
+        old.created_at = now - Duration::days(730);
+        assert_eq!(urgency(&old, Priority::P4, now, &c), 2.0);
+
+        let mut tagged = fresh("Tagged", Priority::P4);
+        tagged.tags.insert(Tag::parse("next").unwrap());
+        let boosted = UrgencyCoefficients {
+            tag: [("next".to_string(), 15.0)].into(),
+            ..c.clone()
+        };
+        assert!((urgency(&tagged, Priority::P4, now, &c) - 0.8).abs() < 1e-9);
+        assert!((urgency(&tagged, Priority::P4, now, &boosted) - 15.8).abs() < 1e-9);
+
+        let mut done = overdue.clone();
+        done.status = crate::domain::todo::Status::Done { completed_at: now };
+        assert_eq!(urgency(&done, Priority::P1, now, &c), 0.0);
+
+        // Sorting puts the overdue todo ahead of the important one.
+        let q = ListQuery {
+            sort: SortKey::Urgency,
+            urgency: boosted,
+            ..ListQuery::default()
+        };
+        let important = fresh("Important", Priority::P1);
+        let found = find_in(&[plain, important, overdue, tagged], &q, now);
+        let titles: Vec<_> = found.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["Tagged", "Overdue", "Important", "Plain"]);
+    }
 }
@@ -0,0 +1,89 @@
+//! Per-client request allowances for servers (`serve`).
+//!
+//! A token bucket per client: it holds up to a minute's worth of requests and
+//! refills continuously, so a client may burst up to its limit and then gets
+//! one request per `60 / limit` seconds. Time is passed in, so tests don't
+//! sleep.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Clients tracked before idle, refilled buckets are dropped.
+const MAX_CLIENTS: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    available: f64,
+    at: Instant,
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: HashMap<String, Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spend one of `client`'s `per_minute` requests. `Err` holds how long
+    /// until the next one is allowed. A limit of 0 means unlimited.
+    pub fn check(&mut self, client: &str, per_minute: u32, now: Instant) -> Result<(), Duration> {
+        if per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = f64::from(per_minute);
+        let per_second = capacity / 60.0;
+        if self.buckets.len() >= MAX_CLIENTS && !self.buckets.contains_key(client) {
+            self.buckets.retain(|_, b| {
+                b.available + now.duration_since(b.at).as_secs_f64() * per_second < capacity
+            });
+        }
+
+        let bucket = self.buckets.entry(client.to_string()).or_insert(Bucket {
+            available: capacity,
+            at: now,
+        });
+        let refilled = now.duration_since(bucket.at).as_secs_f64() * per_second;
+        bucket.available = (bucket.available + refilled).min(capacity);
+        bucket.at = now;
+        if bucket.available >= 1.0 {
+            bucket.available -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.available) / per_second,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clients_get_their_burst_then_a_steady_rate() {
+        let mut limiter = RateLimiter::new();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check("laptop", 3, start).is_ok());
+        }
+        let wait = limiter.check("laptop", 3, start).unwrap_err();
+        assert_eq!(wait.as_secs(), 20);
+        // Others have their own allowance.
+        assert!(limiter.check("wall", 3, start).is_ok());
+
+        let later = start + Duration::from_secs(20);
+        assert!(limiter.check("laptop", 3, later).is_ok());
+        assert!(limiter.check("laptop", 3, later).is_err());
+
+        for _ in 0..100 {
+            assert!(limiter.check("laptop", 0, later).is_ok());
+        }
+    }
+}
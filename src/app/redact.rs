@@ -0,0 +1,174 @@
+//! Redacted exports (`export --redact notes,time`).
+//!
+//! A shared export keeps what a team needs to coordinate (ids, project,
+//! status, priority, dates, workflow state, dependencies) and drops the fields
+//! named. Names can also be policies from config.toml, so a team can agree on
+//! one:
+//!
+//! ```toml
+//! [redact]
+//! team = ["notes", "time", "source"]
+//! ```
+//!
+//! Redaction happens on the exported copies; the database is left alone.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::domain::todo::{Title, Todo};
+
+/// A field `--redact` can strip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RedactField {
+    /// Replaced by `[redacted]`: titles must not be empty.
+    Title,
+    /// Todo notes and the notes on time entries.
+    Notes,
+    Tags,
+    /// Tracked time entries.
+    Time,
+    /// Where the todo came from (may name a file or a mail sender).
+    Source,
+    Badge,
+}
+
+impl RedactField {
+    pub const ALL: [RedactField; 6] = [
+        RedactField::Title,
+        RedactField::Notes,
+        RedactField::Tags,
+        RedactField::Time,
+        RedactField::Source,
+        RedactField::Badge,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RedactField::Title => "title",
+            RedactField::Notes => "notes",
+            RedactField::Tags => "tags",
+            RedactField::Time => "time",
+            RedactField::Source => "source",
+            RedactField::Badge => "badge",
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        Self::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(input))
+    }
+}
+
+/// The fields to strip from an export.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redaction {
+    pub fields: BTreeSet<RedactField>,
+}
+
+impl Redaction {
+    /// Resolve `names` (fields or names of `policies`, ignoring case) into one
+    /// redaction. Errors name the first unknown entry.
+    pub fn parse(
+        names: &[String],
+        policies: &BTreeMap<String, Vec<String>>,
+    ) -> Result<Self, String> {
+        let mut fields = BTreeSet::new();
+        for name in names.iter().map(|n| n.trim()).filter(|n| !n.is_empty()) {
+            if let Some(field) = RedactField::parse(name) {
+                fields.insert(field);
+                continue;
+            }
+            let Some((policy, listed)) =
+                policies.iter().find(|(p, _)| p.eq_ignore_ascii_case(name))
+            else {
+                let known: Vec<_> = RedactField::ALL.iter().map(|f| f.name()).collect();
+                return Err(format!(
+                    "unknown redact field {name} (use {}, or a policy from [redact] in config.toml)",
+                    known.join("|")
+                ));
+            };
+            for field in listed {
+                fields.insert(RedactField::parse(field).ok_or_else(|| {
+                    format!(
+                        "redact policy {policy} lists unknown field {}",
+                        field.trim()
+                    )
+                })?);
+            }
+        }
+        Ok(Self { fields })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Field names, for messages: `notes, time`.
+    pub fn describe(&self) -> String {
+        let names: Vec<_> = self.fields.iter().map(|f| f.name()).collect();
+        names.join(", ")
+    }
+
+    /// `todo` with the redacted fields stripped.
+    pub fn apply(&self, mut todo: Todo) -> Todo {
+        for field in &self.fields {
+            match field {
+                RedactField::Title => {
+                    todo.title = Title::parse("[redacted]").expect("placeholder title is valid");
+                }
+                RedactField::Notes => {
+                    todo.notes = None;
+                    for entry in &mut todo.time_entries {
+                        entry.note = None;
+                    }
+                }
+                RedactField::Tags => todo.tags.clear(),
+                RedactField::Time => todo.time_entries.clear(),
+                RedactField::Source => todo.source = None,
+                RedactField::Badge => todo.badge = None,
+            }
+        }
+        todo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{Notes, Tag};
+
+    #[test]
+    fn policies_expand_and_fields_are_stripped() {
+        let policies = BTreeMap::from([(
+            "Team".to_string(),
+            vec!["notes".to_string(), "time".to_string()],
+        )]);
+        let r = Redaction::parse(&["team".into(), " TAGS ".into()], &policies).unwrap();
+        assert_eq!(r.describe(), "notes, tags, time");
+
+        let err = Redaction::parse(&["attachments".into()], &policies).unwrap_err();
+        assert!(err.starts_with("unknown redact field attachments"), "{err}");
+        let broken = BTreeMap::from([("x".to_string(), vec!["secrets".to_string()])]);
+        assert_eq!(
+            Redaction::parse(&["x".into()], &broken),
+            Err("redact policy x lists unknown field secrets".to_string())
+        );
+
+        let mut todo = Todo::new(Title::parse("Salary talk").unwrap());
+        todo.notes = Some(Notes::parse("ask for 10%").unwrap());
+        todo.tags.insert(Tag::parse("hr").unwrap());
+        let now = time::OffsetDateTime::now_utc();
+        todo.start_timer(now).unwrap();
+        let kept = r.apply(todo.clone());
+        assert_eq!(kept.title, todo.title);
+        assert!(kept.notes.is_none() && kept.tags.is_empty() && kept.time_entries.is_empty());
+        assert_eq!((kept.id, kept.project), (todo.id, todo.project.clone()));
+
+        let hidden = Redaction::parse(&["title".into()], &BTreeMap::new())
+            .unwrap()
+            .apply(todo);
+        assert_eq!(hidden.title.as_str(), "[redacted]");
+        assert!(hidden.notes.is_some());
+    }
+}
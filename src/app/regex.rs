@@ -0,0 +1,109 @@
+//! Regular expressions for `replace --regex`, on top of the `regex` crate.
+//!
+//! Patterns use that crate's syntax, and matching takes time linear in the
+//! text whatever the pattern, so a pathological `--search` can't hang a
+//! run. There are no lookarounds or backreferences.
+//!
+//! Replacements refer to groups as `$1` or `${1}` (`$0` is the whole match,
+//! `$$` a dollar sign); use the braces when letters or digits follow, as
+//! `$1a` names a group called `1a`.
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    inner: regex::Regex,
+}
+
+impl Regex {
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Self, String> {
+        regex::RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map(|inner| Self { inner })
+            .map_err(|e| e.to_string())
+    }
+
+    /// Matches `text` itself, special characters and all.
+    pub fn literal(text: &str, ignore_case: bool) -> Self {
+        Self::new(&regex::escape(text), ignore_case).expect("escaped text is a valid pattern")
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.inner.is_match(text)
+    }
+
+    /// Replace every match, expanding `$1`-style group references in `with`
+    /// unless `literal`; returns the new text and how many matches there were.
+    pub fn replace_all(&self, text: &str, with: &str, literal: bool) -> (String, usize) {
+        let count = self.inner.find_iter(text).count();
+        if count == 0 {
+            return (text.to_string(), 0);
+        }
+        let replaced = if literal {
+            self.inner.replace_all(text, regex::NoExpand(with))
+        } else {
+            self.inner.replace_all(text, with)
+        };
+        (replaced.into_owned(), count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(pattern: &str, text: &str, with: &str) -> String {
+        Regex::new(pattern, false)
+            .unwrap()
+            .replace_all(text, with, false)
+            .0
+    }
+
+    #[test]
+    fn matches_the_common_subset() {
+        let m = |p: &str, t: &str| Regex::new(p, false).unwrap().is_match(t);
+        assert!(m("client[XY]", "call clientX"));
+        assert!(!m("^client", "call client"));
+        assert!(m(r"\bv\d+\.\d+$", "release v1.20"));
+        assert!(!m(r"\bv\d+$", "dev12"));
+        assert!(m("colou?r", "color") && m("colou?r", "colour"));
+        assert!(m("(foo|bar)+baz", "xxbarfoobaz"));
+        assert!(m("^(a*)*b$", "aab") && m("(x?)*y", "y"));
+        assert!(m("a{2,3}b", "caaab") && !m("^a{2,3}b", "ab"));
+        assert!(m("[^a-z ]", "all lower, but a comma"));
+        assert!(Regex::new("acme", true).unwrap().is_match("ACME corp"));
+        assert!(
+            Regex::literal("a.b", false).is_match("a.b")
+                && !Regex::literal("a.b", false).is_match("axb")
+        );
+
+        for bad in ["(a", "a)", "[a-", "*a", r"\q", "a{3,1}", "(?=x)"] {
+            assert!(Regex::new(bad, false).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn replacements_expand_groups() {
+        assert_eq!(
+            replace(r"(\w+)@(\w+)", "mail bob@acme now", "$2:${1}"),
+            "mail acme:bob now"
+        );
+        assert_eq!(replace("o", "foo", "0"), "f00");
+        assert_eq!(replace("x*", "ab", "-"), "-a-b-");
+        assert_eq!(replace(r"\d+", "costs 5", "$$$0"), "costs $5");
+        assert_eq!(replace("a+?", "aaa", "b"), "bbb");
+        // Lazy vs greedy.
+        assert_eq!(replace("<.+>", "<a><b>", "[]"), "[]");
+        assert_eq!(replace("<.+?>", "<a><b>", "[]"), "[][]");
+        let (text, n) = Regex::literal("$1", false).replace_all("pay $1", "$2", true);
+        assert_eq!((text.as_str(), n), ("pay $2", 1));
+    }
+
+    #[test]
+    fn nested_repeats_run_in_linear_time() {
+        let re = Regex::new("^(a*)*$", false).unwrap();
+        assert!(!re.is_match(&("a".repeat(30) + "!")));
+        let long = "a".repeat(100_000);
+        assert!(re.is_match(&long));
+        assert!(!re.is_match(&(long + "!")));
+    }
+}
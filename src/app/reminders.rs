@@ -0,0 +1,185 @@
+//! Which todos `notify` should alert about.
+//!
+//! An open todo is due an alert once its reminder time or its due date has
+//! passed. Each alert fires once: `notified_at` records when the last one was
+//! sent, and only a trigger later than that fires again. Editing the reminder
+//! or due date clears it, so the new time is announced even if it has passed. That keeps a cron job or systemd timer running `notify`
+//! every few minutes from repeating itself.
+//!
+//! A todo with a due date can also follow an escalation chain (its own, or
+//! its priority's from `[escalation]` in config.toml): each step of the chain
+//! is one more trigger, so a P1 can be announced a day ahead, an hour ahead
+//! and then every half hour until it is done.
+
+use std::collections::BTreeMap;
+
+use time::OffsetDateTime;
+
+use crate::domain::{
+    escalation::Escalation,
+    todo::{Priority, Todo, TodoId},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    /// Its `remind_at` time came.
+    Reminder,
+    /// Its due date came.
+    Due,
+    /// An escalation step before the due date came.
+    Upcoming,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub id: TodoId,
+    pub title: String,
+    pub kind: AlertKind,
+    /// The trigger time that passed.
+    pub at: OffsetDateTime,
+    /// Due before `now` (possibly while a reminder is what fired).
+    pub overdue: bool,
+}
+
+/// Alerts not sent yet, oldest trigger first. `chains` are the escalation
+/// chains per priority, for todos without their own.
+pub fn pending(
+    todos: &[Todo],
+    now: OffsetDateTime,
+    chains: &BTreeMap<Priority, Escalation>,
+) -> Vec<Alert> {
+    let mut alerts: Vec<_> = todos
+        .iter()
+        .filter(|t| !t.status.is_done() && t.is_active())
+        .filter_map(|t| {
+            let reminder = t.remind_at.map(|r| (r.as_dt(), AlertKind::Reminder));
+            let due = t.due.map(|d| (d.as_dt(), AlertKind::Due));
+            let chain = t.escalation.as_ref().or(chains.get(&t.priority));
+            let step = t.due.zip(chain).and_then(|(due, chain)| {
+                let at = chain.last_trigger(due.as_dt(), now)?;
+                let kind = if at < due.as_dt() {
+                    AlertKind::Upcoming
+                } else {
+                    AlertKind::Due
+                };
+                Some((at, kind))
+            });
+            let (at, kind) = [reminder, due, step]
+                .into_iter()
+                .flatten()
+                .filter(|(at, _)| *at <= now)
+                .max_by_key(|(at, _)| *at)?;
+            if t.notified_at.is_some_and(|sent| sent >= at) {
+                return None;
+            }
+            Some(Alert {
+                id: t.id,
+                title: t.title.as_str().to_string(),
+                kind,
+                at,
+                overdue: t.is_overdue(now),
+            })
+        })
+        .collect();
+    alerts.sort_by_key(|a| (a.at, a.id));
+    alerts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{DueAt, Title};
+    use time::{Duration, macros::datetime};
+
+    #[test]
+    fn each_trigger_fires_once() {
+        let now = datetime!(2026-03-10 12:00 UTC);
+        let mut call = Todo::new(Title::parse("Call").unwrap());
+        call.remind_at = Some(DueAt::from_dt(now - Duration::minutes(5)));
+        call.due = Some(DueAt::from_dt(now + Duration::hours(2)));
+        let mut late = Todo::new(Title::parse("Late").unwrap());
+        late.due = Some(DueAt::from_dt(now - Duration::days(1)));
+        let mut done = late.clone();
+        done.id = TodoId::new();
+        done.mark_done().unwrap();
+        let mut later = Todo::new(Title::parse("Later").unwrap());
+        later.remind_at = Some(DueAt::from_dt(now + Duration::hours(1)));
+
+        let mut todos = vec![call, late, done, later];
+        let alerts = pending(&todos, now, &BTreeMap::new());
+        let kinds: Vec<_> = alerts.iter().map(|a| (a.title.as_str(), a.kind)).collect();
+        assert_eq!(
+            kinds,
+            [("Late", AlertKind::Due), ("Call", AlertKind::Reminder)]
+        );
+        assert!(alerts[0].overdue && !alerts[1].overdue);
+
+        for t in &mut todos {
+            t.mark_notified(now);
+        }
+        assert!(pending(&todos, now, &BTreeMap::new()).is_empty());
+
+        // A new reminder time re-arms it.
+        todos[1].remind_at = Some(DueAt::from_dt(now + Duration::minutes(30)));
+        let alerts = pending(&todos, now + Duration::hours(1), &BTreeMap::new());
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].title, "Late");
+        assert_eq!(alerts[1].title, "Later");
+    }
+
+    #[test]
+    fn escalation_chains_add_triggers_until_done() {
+        let due = datetime!(2026-03-10 12:00 UTC);
+        let mut urgent = Todo::new(Title::parse("Ship it").unwrap());
+        urgent.priority = Priority::P1;
+        urgent.due = Some(DueAt::from_dt(due));
+        let mut calm = urgent.clone();
+        calm.id = TodoId::new();
+        calm.title = Title::parse("Someday").unwrap();
+        calm.priority = Priority::P4;
+        let mut todos = vec![urgent, calm];
+        let chains = BTreeMap::from([(
+            Priority::P1,
+            Escalation::parse("-1d, -1h, every 30m").unwrap(),
+        )]);
+
+        let mut fired = Vec::new();
+        let mut now = due - Duration::days(2);
+        while now <= due + Duration::hours(1) {
+            for alert in pending(&todos, now, &chains) {
+                fired.push((alert.title.clone(), alert.kind, alert.at));
+                todos
+                    .iter_mut()
+                    .find(|t| t.id == alert.id)
+                    .unwrap()
+                    .mark_notified(now);
+            }
+            now += Duration::minutes(10);
+        }
+        let times = |title: &str| -> Vec<_> {
+            fired
+                .iter()
+                .filter(|f| f.0 == title)
+                .map(|f| (f.1, f.2))
+                .collect()
+        };
+        assert_eq!(
+            times("Ship it"),
+            [
+                (AlertKind::Upcoming, due - Duration::days(1)),
+                (AlertKind::Upcoming, due - Duration::hours(1)),
+                (AlertKind::Due, due),
+                (AlertKind::Due, due + Duration::minutes(30)),
+                (AlertKind::Due, due + Duration::hours(1)),
+            ]
+        );
+        assert_eq!(times("Someday"), [(AlertKind::Due, due)]);
+
+        // A todo's own chain wins; `off` leaves only the due alert.
+        todos[0].escalation = Some(Escalation::parse("off").unwrap());
+        todos[0].notified_at = None;
+        let alerts = pending(&todos, due + Duration::hours(3), &chains);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!((alerts[0].kind, alerts[0].at), (AlertKind::Due, due));
+    }
+}
@@ -0,0 +1,168 @@
+//! Search and replace over titles and notes (`replace`).
+//!
+//! [`plan`] works out every change up front and validates the results, so the
+//! caller can preview them and apply all or nothing.
+
+use crate::{
+    app::regex::Regex,
+    domain::todo::{Notes, Title, Todo, TodoId, TodoPatch},
+};
+
+/// Which text fields a replacement looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fields {
+    pub title: bool,
+    pub notes: bool,
+}
+
+impl Default for Fields {
+    fn default() -> Self {
+        Self {
+            title: true,
+            notes: true,
+        }
+    }
+}
+
+/// One field of one todo changing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub id: TodoId,
+    pub field: &'static str,
+    pub before: String,
+    pub after: String,
+    /// Matches replaced in this field.
+    pub count: usize,
+}
+
+/// A change whose result isn't a valid value (e.g. an emptied title).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejected {
+    pub id: TodoId,
+    pub field: &'static str,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplacePlan {
+    pub changes: Vec<Change>,
+    pub rejected: Vec<Rejected>,
+    /// One patch per changed todo; only meaningful if nothing was rejected.
+    pub patches: Vec<(TodoId, TodoPatch)>,
+}
+
+impl ReplacePlan {
+    pub fn todo_count(&self) -> usize {
+        self.patches.len()
+    }
+}
+
+/// Replace `pattern` with `with` in the `fields` of `todos`.
+pub fn plan(
+    todos: &[Todo],
+    pattern: &Regex,
+    with: &str,
+    literal: bool,
+    fields: Fields,
+) -> ReplacePlan {
+    let mut plan = ReplacePlan::default();
+    for t in todos {
+        let mut patch = TodoPatch::default();
+        let mut changed = false;
+
+        if fields.title {
+            let (after, count) = pattern.replace_all(t.title.as_str(), with, literal);
+            if after != t.title.as_str() {
+                match Title::parse(&after) {
+                    Ok(title) => patch.title = Some(title),
+                    Err(e) => plan.rejected.push(Rejected {
+                        id: t.id,
+                        field: "title",
+                        reason: e.to_string(),
+                    }),
+                }
+                plan.changes.push(Change {
+                    id: t.id,
+                    field: "title",
+                    before: t.title.as_str().to_string(),
+                    after,
+                    count,
+                });
+                changed = true;
+            }
+        }
+
+        if let Some(notes) = t.notes.as_ref().filter(|_| fields.notes) {
+            let (after, count) = pattern.replace_all(notes.as_str(), with, literal);
+            if after != notes.as_str() {
+                match Notes::parse(&after) {
+                    // Replacing everything away clears the notes.
+                    Ok(n) if n.as_str().is_empty() => patch.notes = Some(None),
+                    Ok(n) => patch.notes = Some(Some(n)),
+                    Err(e) => plan.rejected.push(Rejected {
+                        id: t.id,
+                        field: "notes",
+                        reason: e.to_string(),
+                    }),
+                }
+                plan.changes.push(Change {
+                    id: t.id,
+                    field: "notes",
+                    before: notes.as_str().to_string(),
+                    after,
+                    count,
+                });
+                changed = true;
+            }
+        }
+
+        if changed {
+            plan.patches.push((t.id, patch));
+        }
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(title: &str, notes: Option<&str>) -> Todo {
+        let mut t = Todo::new(Title::parse(title).unwrap());
+        t.notes = notes.map(|n| Notes::parse(n).unwrap());
+        t
+    }
+
+    #[test]
+    fn plans_changes_per_field() {
+        let todos = vec![
+            todo("Invoice clientX", Some("ask clientX about clientX-2")),
+            todo("Buy milk", None),
+        ];
+        let pattern = Regex::literal("clientX", true);
+        let plan = plan(&todos, &pattern, "ClientY", true, Fields::default());
+        assert!(plan.rejected.is_empty());
+        assert_eq!(plan.todo_count(), 1);
+        let counts: Vec<_> = plan.changes.iter().map(|c| (c.field, c.count)).collect();
+        assert_eq!(counts, [("title", 1), ("notes", 2)]);
+        assert_eq!(plan.changes[1].after, "ask ClientY about ClientY-2");
+
+        let titles_only = Fields {
+            title: true,
+            notes: false,
+        };
+        let plan = super::plan(&todos, &pattern, "ClientY", true, titles_only);
+        assert_eq!(plan.changes.len(), 1);
+    }
+
+    #[test]
+    fn empty_titles_are_rejected() {
+        let todos = vec![todo("clientX", Some("clientX"))];
+        let pattern = Regex::new("^client.$", false).unwrap();
+        let plan = plan(&todos, &pattern, "", false, Fields::default());
+        assert_eq!(plan.rejected.len(), 1);
+        assert_eq!(plan.rejected[0].field, "title");
+        // Notes may become empty: that clears them.
+        assert_eq!(plan.patches[0].1.notes, Some(None));
+    }
+}
@@ -0,0 +1,212 @@
+//! Markdown reports of open and completed todos, for pasting into a standup
+//! or a weekly review.
+//!
+//! A report has a `## Completed` and an `## Open` section, each split into
+//! groups: by project, or by day (the UTC date a todo was completed, or is
+//! due). Todos on the someday list are left out; so are todos completed
+//! before `since`, if given.
+
+use std::collections::BTreeMap;
+
+use time::{Date, OffsetDateTime};
+
+use crate::domain::todo::{Status, Todo};
+
+/// How a report groups todos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    Project,
+    Day,
+}
+
+impl Grouping {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "project" => Some(Self::Project),
+            "day" => Some(Self::Day),
+            _ => None,
+        }
+    }
+}
+
+/// Counts for a summary line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReportCounts {
+    pub completed: usize,
+    pub open: usize,
+}
+
+/// Render `todos` as a Markdown report titled `title`.
+pub fn render(
+    title: &str,
+    todos: &[Todo],
+    grouping: Grouping,
+    since: Option<OffsetDateTime>,
+) -> (String, ReportCounts) {
+    let mut completed: Vec<(OffsetDateTime, &Todo)> = todos
+        .iter()
+        .filter_map(|t| match t.status {
+            Status::Done { completed_at } if since.is_none_or(|s| completed_at >= s) => {
+                Some((completed_at, t))
+            }
+            _ => None,
+        })
+        .collect();
+    completed.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.id.cmp(&b.1.id)));
+
+    let mut open: Vec<&Todo> = todos.iter().filter(|t| t.is_active()).collect();
+    open.sort_by(|a, b| {
+        a.due
+            .is_none()
+            .cmp(&b.due.is_none())
+            .then_with(|| a.due.cmp(&b.due))
+            .then_with(|| a.priority.cmp(&b.priority))
+            .then_with(|| a.created_at.cmp(&b.created_at))
+    });
+
+    let mut md = format!("# {title}\n");
+    if let Some(since) = since {
+        md.push_str(&format!("\nCompleted since {}.\n", since.date()));
+    }
+
+    md.push_str("\n## Completed\n");
+    let done = completed.iter().map(|(at, t)| (Some(at.date()), *t));
+    write_groups(&mut md, done, grouping, true, "Nothing completed");
+
+    md.push_str("\n## Open\n");
+    let pending = open.iter().map(|t| (t.due.map(|d| d.as_dt().date()), *t));
+    write_groups(&mut md, pending, grouping, false, "Nothing open");
+
+    let counts = ReportCounts {
+        completed: completed.len(),
+        open: open.len(),
+    };
+    (md, counts)
+}
+
+/// One `###` heading per group, in order of first appearance for days (the
+/// todos come sorted) and by name for projects.
+fn write_groups<'a>(
+    md: &mut String,
+    todos: impl Iterator<Item = (Option<Date>, &'a Todo)>,
+    grouping: Grouping,
+    done: bool,
+    empty: &str,
+) {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    let mut by_project: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (day, todo) in todos {
+        match grouping {
+            Grouping::Project => {
+                let line = item(todo, done, day.filter(|_| !done), false);
+                by_project
+                    .entry(todo.project.as_str().to_string())
+                    .or_default()
+                    .push(line);
+            }
+            Grouping::Day => {
+                let heading = match day {
+                    Some(d) => d.to_string(),
+                    None => "No due date".to_string(),
+                };
+                let line = item(todo, done, None, true);
+                match groups.last_mut() {
+                    Some((h, lines)) if *h == heading => lines.push(line),
+                    _ => groups.push((heading, vec![line])),
+                }
+            }
+        }
+    }
+    groups.extend(by_project);
+
+    if groups.is_empty() {
+        md.push_str(&format!("\n_{empty}._\n"));
+    }
+    for (heading, lines) in groups {
+        md.push_str(&format!("\n### {heading}\n\n"));
+        for line in lines {
+            md.push_str(&line);
+            md.push('\n');
+        }
+    }
+}
+
+/// A task list item: `- [x] Title (project) #tag due 2026-05-01`.
+fn item(todo: &Todo, done: bool, due: Option<Date>, with_project: bool) -> String {
+    let mut line = format!(
+        "- [{}] {}",
+        if done { 'x' } else { ' ' },
+        todo.title.as_str().replace('\n', " ")
+    );
+    if with_project {
+        line.push_str(&format!(" ({})", todo.project.as_str()));
+    }
+    for tag in &todo.tags {
+        line.push_str(&format!(" #{}", tag.as_str()));
+    }
+    if let Some(due) = due {
+        line.push_str(&format!(" due {due}"));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{DueAt, ProjectName, Tag, Title};
+    use time::macros::datetime;
+
+    fn todo(title: &str, project: &str, now: OffsetDateTime) -> Todo {
+        let mut t = Todo::new_at(Title::parse(title).unwrap(), now);
+        t.project = ProjectName::parse(project).unwrap();
+        t
+    }
+
+    #[test]
+    fn groups_by_project_and_by_day() {
+        let now = datetime!(2026-05-04 09:00 UTC);
+        let mut shipped = todo("Ship release", "Work", now);
+        shipped
+            .mark_done_at(datetime!(2026-05-01 17:00 UTC))
+            .unwrap();
+        let mut old = todo("Old chore", "Home", now);
+        old.mark_done_at(datetime!(2026-04-01 10:00 UTC)).unwrap();
+        let mut review = todo("Review PR", "Work", now);
+        review.due = Some(DueAt::from_dt(datetime!(2026-05-05 12:00 UTC)));
+        review.tags.insert(Tag::parse("code").unwrap());
+        let groceries = todo("Groceries", "Home", now);
+        let mut parked = todo("Learn piano", "Home", now);
+        parked.park(now).unwrap();
+        let todos = [shipped, old, review, groceries, parked];
+
+        let since = Some(datetime!(2026-04-27 00:00 UTC));
+        let (md, counts) = render("Week", &todos, Grouping::Project, since);
+        assert_eq!(
+            counts,
+            ReportCounts {
+                completed: 1,
+                open: 2
+            }
+        );
+        assert_eq!(
+            md,
+            "# Week\n\nCompleted since 2026-04-27.\n\
+             \n## Completed\n\n### Work\n\n- [x] Ship release\n\
+             \n## Open\n\n### Home\n\n- [ ] Groceries\n\
+             \n### Work\n\n- [ ] Review PR #code due 2026-05-05\n"
+        );
+
+        let (md, _) = render("Standup", &todos, Grouping::Day, None);
+        assert_eq!(
+            md,
+            "# Standup\n\
+             \n## Completed\n\n### 2026-04-01\n\n- [x] Old chore (Home)\n\
+             \n### 2026-05-01\n\n- [x] Ship release (Work)\n\
+             \n## Open\n\n### 2026-05-05\n\n- [ ] Review PR (Work) #code\n\
+             \n### No due date\n\n- [ ] Groceries (Home)\n"
+        );
+
+        let (md, _) = render("Empty", &[], Grouping::Day, None);
+        assert!(md.contains("_Nothing completed._") && md.contains("_Nothing open._"));
+    }
+}
@@ -3,11 +3,27 @@
 //! The UI and application logic depend on this trait,
 //! not on any concrete storage implementation.
 
-use crate::domain::todo::{Todo, TodoId};
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    app::{
+        errors::AppError,
+        query::{self, ListQuery},
+    },
+    domain::{
+        project::Project,
+        todo::{Todo, TodoId},
+    },
+};
 
 /// Abstraction over todo storage.
 pub trait TodoRepository {
-    fn add(&mut self, todo: Todo);
+    /// Store a new todo. Errors with [`AppError::DuplicateIds`] if its ID is
+    /// stored already.
+    fn add(&mut self, todo: Todo) -> Result<(), AppError>;
     fn list(&self) -> Vec<Todo>;
     /// Replace an existing todo with the same ID.
     fn replace(&mut self, todo: Todo) -> bool;
@@ -15,9 +31,244 @@ pub trait TodoRepository {
     /// Get a todo by full ID (exact match).
     fn get(&self, id: TodoId) -> Option<Todo>;
 
-    /// Replace the entire dataset (used for import/migrations).
-    fn set_all(&mut self, todos: Vec<Todo>);
+    /// Replace the entire dataset (used for import/migrations). Errors with
+    /// [`AppError::DuplicateIds`], changing nothing, if two todos share an
+    /// ID; see [`dedupe`] for dropping them instead.
+    fn set_all(&mut self, todos: Vec<Todo>) -> Result<(), AppError>;
 
     /// Remove by ID. Returns true if removed.
     fn remove(&mut self, id: TodoId) -> bool;
+
+    /// Project records, in the order they were stored.
+    fn projects(&self) -> Vec<Project>;
+
+    /// Replace all project records.
+    fn set_projects(&mut self, projects: Vec<Project>);
+
+    /// Todos matching `query`, filtered and sorted like
+    /// [`query::apply_list_query`].
+    ///
+    /// The default materializes the whole list first. Backends that can filter
+    /// and sort in storage (SQLite, a server) should override this and
+    /// [`count`](Self::count). Note that sorting by priority uses effective
+    /// priority, which depends on dependency links outside the matches.
+    fn find(&self, query: &ListQuery, now: OffsetDateTime) -> Vec<Todo> {
+        query::apply_list_query(self.list(), query, now)
+    }
+
+    /// How many todos match `query`'s filters, ignoring its cursor, offset
+    /// and limit (the total to show next to a page).
+    fn count(&self, query: &ListQuery, now: OffsetDateTime) -> usize {
+        query::count_in(&self.list(), query, now)
+    }
+
+    /// Apply `ops` in order, all or nothing: if any op can't apply, none do.
+    ///
+    /// The default checks the whole batch against the current IDs with
+    /// [`check_batch`] before touching anything. Backends with transactions
+    /// (SQLite) should override it to run the ops in one.
+    fn apply_batch(&mut self, ops: Vec<RepoOp>) -> Result<BatchOutcome, AppError> {
+        check_batch(&self.list(), &ops)?;
+        let mut outcome = BatchOutcome::default();
+        for op in ops {
+            match op {
+                RepoOp::Add(todo) => {
+                    self.add(todo)?;
+                    outcome.added += 1;
+                }
+                RepoOp::Replace(todo) => {
+                    self.replace(todo);
+                    outcome.replaced += 1;
+                }
+                RepoOp::Remove(id) => {
+                    self.remove(id);
+                    outcome.removed += 1;
+                }
+                RepoOp::SetProjects(projects) => {
+                    self.set_projects(projects);
+                    outcome.projects_set = true;
+                }
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+/// One mutation in a [`TodoRepository::apply_batch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoOp {
+    /// Insert a todo whose ID isn't stored yet.
+    Add(Todo),
+    /// Replace the stored todo with the same ID.
+    Replace(Todo),
+    /// Remove a stored todo.
+    Remove(TodoId),
+    /// Replace all project records.
+    SetProjects(Vec<Project>),
+}
+
+impl RepoOp {
+    /// Build the op that puts `id` into `state` (`None` = absent), given
+    /// whether it is stored now. `None` if nothing needs doing.
+    pub fn restore(id: TodoId, state: Option<Todo>, stored: bool) -> Option<Self> {
+        match (state, stored) {
+            (Some(todo), true) => Some(RepoOp::Replace(todo)),
+            (Some(todo), false) => Some(RepoOp::Add(todo)),
+            (None, true) => Some(RepoOp::Remove(id)),
+            (None, false) => None,
+        }
+    }
+}
+
+/// What an applied batch did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchOutcome {
+    pub added: usize,
+    pub replaced: usize,
+    pub removed: usize,
+    pub projects_set: bool,
+}
+
+/// Check that every op in `ops` applies, in order, to a store holding
+/// `existing`: adds need a new ID, replaces and removes an existing one.
+/// The error names the first op (from 1) that wouldn't.
+pub fn check_batch(existing: &[Todo], ops: &[RepoOp]) -> Result<(), AppError> {
+    let mut ids: BTreeSet<TodoId> = existing.iter().map(|t| t.id).collect();
+    for (i, op) in ops.iter().enumerate() {
+        let reason = match op {
+            RepoOp::Add(todo) if !ids.insert(todo.id) => "the todo already exists",
+            RepoOp::Replace(todo) if !ids.contains(&todo.id) => "todo not found",
+            RepoOp::Remove(id) if !ids.remove(id) => "todo not found",
+            _ => continue,
+        };
+        return Err(AppError::BatchRejected { op: i + 1, reason });
+    }
+    Ok(())
+}
+
+/// What to do with todos whose ID another todo already has, when a whole
+/// list is stored at once (`import`, sync, `git pull`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicatePolicy {
+    /// Refuse the list.
+    #[default]
+    Reject,
+    /// Keep the first todo with each ID.
+    KeepFirst,
+    /// Keep the last todo with each ID, in the place of the first.
+    KeepLast,
+}
+
+/// IDs held by more than one of `todos`, each once, in order.
+pub fn duplicate_ids(todos: &[Todo]) -> Vec<TodoId> {
+    let mut seen = BTreeSet::new();
+    let mut dups = Vec::new();
+    for todo in todos {
+        if !seen.insert(todo.id) && !dups.contains(&todo.id) {
+            dups.push(todo.id);
+        }
+    }
+    dups
+}
+
+/// Apply `policy` to `todos`: the list left with unique IDs, and the IDs
+/// that were duplicated.
+pub fn dedupe(
+    todos: Vec<Todo>,
+    policy: DuplicatePolicy,
+) -> Result<(Vec<Todo>, Vec<TodoId>), AppError> {
+    let dups = duplicate_ids(&todos);
+    if dups.is_empty() {
+        return Ok((todos, dups));
+    }
+    let mut kept: Vec<Todo> = Vec::with_capacity(todos.len());
+    for todo in todos {
+        match kept.iter().position(|t| t.id == todo.id) {
+            None => kept.push(todo),
+            Some(_) if policy == DuplicatePolicy::Reject => {
+                return Err(AppError::DuplicateIds(dups));
+            }
+            Some(_) if policy == DuplicatePolicy::KeepFirst => {}
+            Some(i) => kept[i] = todo,
+        }
+    }
+    Ok((kept, dups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{domain::todo::Title, infra::memory_repo::MemoryTodoRepository};
+
+    fn todo(title: &str) -> Todo {
+        Todo::new(Title::parse(title).unwrap())
+    }
+
+    #[test]
+    fn batches_apply_all_or_nothing() {
+        let mut repo = MemoryTodoRepository::new();
+        let a = todo("A");
+        repo.add(a.clone()).unwrap();
+
+        let b = todo("B");
+        let mut renamed = a.clone();
+        renamed.title = Title::parse("A2").unwrap();
+        let outcome = repo
+            .apply_batch(vec![
+                RepoOp::Add(b.clone()),
+                RepoOp::Replace(renamed),
+                RepoOp::Remove(b.id),
+            ])
+            .unwrap();
+        assert_eq!(
+            (outcome.added, outcome.replaced, outcome.removed),
+            (1, 1, 1)
+        );
+        let list = repo.list();
+        let titles: Vec<_> = list.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["A2"]);
+
+        // The last op fails (`b` is gone), so the first one doesn't happen.
+        let c = todo("C");
+        let err = repo
+            .apply_batch(vec![RepoOp::Add(c.clone()), RepoOp::Replace(b.clone())])
+            .unwrap_err();
+        assert!(
+            matches!(err, AppError::BatchRejected { op: 2, .. }),
+            "{err}"
+        );
+        assert!(repo.get(c.id).is_none());
+        assert!(repo.apply_batch(vec![RepoOp::Add(a)]).is_err());
+    }
+
+    #[test]
+    fn duplicate_ids_are_refused_or_dropped_by_policy() {
+        let a = todo("A");
+        let b = todo("B");
+        let mut a2 = a.clone();
+        a2.title = Title::parse("A again").unwrap();
+        let list = vec![a.clone(), b.clone(), a2];
+
+        let mut repo = MemoryTodoRepository::new();
+        let err = repo.set_all(list.clone()).unwrap_err();
+        assert!(
+            matches!(&err, AppError::DuplicateIds(ids) if ids == &[a.id]),
+            "{err}"
+        );
+        assert!(repo.list().is_empty());
+        repo.add(b.clone()).unwrap();
+        assert!(matches!(repo.add(b), Err(AppError::DuplicateIds(_))));
+
+        assert!(dedupe(list.clone(), DuplicatePolicy::Reject).is_err());
+        for (policy, title) in [
+            (DuplicatePolicy::KeepFirst, "A"),
+            (DuplicatePolicy::KeepLast, "A again"),
+        ] {
+            let (kept, dups) = dedupe(list.clone(), policy).unwrap();
+            assert_eq!(dups, [a.id]);
+            assert_eq!(kept.len(), 2);
+            assert_eq!(kept[0].title.as_str(), title, "{policy:?}");
+        }
+    }
 }
@@ -7,22 +7,23 @@ use time::OffsetDateTime;
 
 use crate::domain::todo::{DueAt, Notes, Priority, ProjectName, Tag, Title, Todo};
 
-pub fn default_todos() -> Vec<Todo> {
-    let now = OffsetDateTime::now_utc();
-
+pub fn default_todos(now: OffsetDateTime) -> Vec<Todo> {
     let inbox = ProjectName::inbox();
     let work = ProjectName::parse("Work").unwrap();
 
-    let mut t1 = Todo::new(Title::parse("Welcome to rustlytodo").unwrap());
+    let mut t1 = Todo::new_at(Title::parse("Welcome to rustlytodo").unwrap(), now);
     t1.project = inbox.clone();
     t1.priority = Priority::P2;
     t1.notes = Some(Notes::parse("Tip: use `todo add \"...\" --tag work`").unwrap());
 
-    let mut t2 = Todo::new(Title::parse("Press ? to view keybindings (TUI later)").unwrap());
+    let mut t2 = Todo::new_at(
+        Title::parse("Press ? to view keybindings (TUI later)").unwrap(),
+        now,
+    );
     t2.project = inbox.clone();
     t2.priority = Priority::P4;
 
-    let mut t3 = Todo::new(Title::parse("Fix CI flaky test").unwrap());
+    let mut t3 = Todo::new_at(Title::parse("Fix CI flaky test").unwrap(), now);
     t3.project = work;
     t3.priority = Priority::P1;
     t3.due = Some(DueAt::from_dt(now + Duration::days(3)));
@@ -3,15 +3,26 @@
 //! This is where orchestration logic lives.
 
 use anyhow::Result;
+use time::OffsetDateTime;
+
+use std::collections::BTreeSet;
 
 use crate::{
-    app::repository::TodoRepository,
+    app::{
+        errors::AppError,
+        query,
+        query::ListQuery,
+        repository::TodoRepository,
+        warnings::{self, Warning},
+    },
     domain::todo::{Title, Todo, TodoId, TodoPatch},
 };
 
 /// High-level application service.
 pub struct TodoService<R> {
     pub repo: R,
+    /// Warnings from adds and edits, until taken.
+    warnings: Vec<Warning>,
 }
 
 impl<R> TodoService<R>
@@ -19,13 +30,17 @@ where
     R: TodoRepository,
 {
     pub fn new(repo: R) -> Self {
-        Self { repo }
+        Self {
+            repo,
+            warnings: Vec::new(),
+        }
     }
 
-    pub fn add_todo(&mut self, title: Title) -> Result<TodoId> {
-        let todo = Todo::new(title);
+    pub fn add_todo(&mut self, title: Title, now: OffsetDateTime) -> Result<TodoId> {
+        let todo = Todo::new_at(title, now);
         let id = todo.id;
-        self.repo.add(todo);
+        self.warn(None, &todo, now);
+        self.repo.add(todo)?;
         Ok(id)
     }
 
@@ -33,27 +48,89 @@ where
         self.repo.list()
     }
 
+    pub fn find_todos(&self, query: &ListQuery, now: OffsetDateTime) -> Vec<Todo> {
+        self.repo.find(query, now)
+    }
+
+    pub fn count_todos(&self, query: &ListQuery, now: OffsetDateTime) -> usize {
+        self.repo.count(query, now)
+    }
+
     /// Insert a fully-constructed Todo (used for seeding / imports later).
     ///
     /// This avoids UI or seed logic needing access to repository internals.
-    pub fn insert_todo(&mut self, todo: Todo) {
-        self.repo.add(todo);
+    pub fn insert_todo(&mut self, todo: Todo, now: OffsetDateTime) -> Result<(), AppError> {
+        let warnings = self.check(None, &todo, now);
+        self.repo.add(todo)?;
+        self.warnings.extend(warnings);
+        Ok(())
     }
 
-    pub fn edit_todo(&mut self, id: TodoId, patch: TodoPatch) -> Result<bool> {
+    /// Apply `patch` to `id`. New dependencies must not lead back to `id`
+    /// ([`AppError::DependencyCycle`]).
+    pub fn edit_todo(&mut self, id: TodoId, patch: TodoPatch, now: OffsetDateTime) -> Result<bool> {
+        if let Some(deps) = &patch.depends_on {
+            check_dependencies(&self.repo.list(), id, deps)?;
+        }
         if let Some(mut todo) = self.repo.get(id) {
-            todo.apply_patch(patch);
+            let before = todo.clone();
+            todo.apply_patch_at(patch, now);
+            self.warn(Some(&before), &todo, now);
             Ok(self.repo.replace(todo))
         } else {
             Ok(false)
         }
     }
 
+    /// Warnings collected since the last call, oldest first.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn check(&self, before: Option<&Todo>, after: &Todo, now: OffsetDateTime) -> Vec<Warning> {
+        let mut projects: Vec<String> = self
+            .repo
+            .projects()
+            .into_iter()
+            .map(|p| p.name.as_str().to_string())
+            .collect();
+        projects.extend(
+            self.repo
+                .list()
+                .into_iter()
+                .map(|t| t.project.as_str().to_string()),
+        );
+        warnings::check(before, after, &projects, now)
+    }
+
+    fn warn(&mut self, before: Option<&Todo>, after: &Todo, now: OffsetDateTime) {
+        let warnings = self.check(before, after, now);
+        self.warnings.extend(warnings);
+    }
+
     pub fn repo_mut(&mut self) -> &mut R {
         &mut self.repo
     }
 }
 
+/// Can `id` depend on all of `deps` without a cycle?
+pub fn check_dependencies(
+    todos: &[Todo],
+    id: TodoId,
+    deps: &BTreeSet<TodoId>,
+) -> Result<(), AppError> {
+    if deps.contains(&id) {
+        return Err(AppError::SelfDependency);
+    }
+    if deps
+        .iter()
+        .any(|&dep| query::depends_transitively(todos, dep, id))
+    {
+        return Err(AppError::DependencyCycle);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -64,8 +141,9 @@ mod tests {
         let repo = MemoryTodoRepository::new();
         let mut svc = TodoService::new(repo);
 
-        svc.add_todo(Title::parse("Hello").unwrap()).unwrap();
-        svc.add_todo(Title::parse("World").unwrap()).unwrap();
+        let now = OffsetDateTime::now_utc();
+        svc.add_todo(Title::parse("Hello").unwrap(), now).unwrap();
+        svc.add_todo(Title::parse("World").unwrap(), now).unwrap();
 
         let todos = svc.list_todos();
         assert_eq!(todos.len(), 2);
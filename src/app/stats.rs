@@ -0,0 +1,442 @@
+//! Aggregations over todos for reporting.
+
+use std::collections::BTreeMap;
+
+use time::{
+    Date, Duration, OffsetDateTime, format_description::well_known::Rfc3339,
+    macros::format_description,
+};
+
+use crate::domain::{
+    todo::{Status, Todo},
+    tracking,
+};
+
+/// How `time_by` buckets tracked time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeGroup {
+    Tag,
+    Project,
+    Todo,
+}
+
+impl TimeGroup {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "tag" => Some(Self::Tag),
+            "project" => Some(Self::Project),
+            "todo" => Some(Self::Todo),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tag => "tag",
+            Self::Project => "project",
+            Self::Todo => "todo",
+        }
+    }
+}
+
+/// Tracked time for one group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeRow {
+    pub key: String,
+    pub tracked: Duration,
+    /// Time entries that contributed (at least partly inside the window).
+    pub entries: usize,
+    pub todos: usize,
+}
+
+/// Label used for todos without tags when grouping by tag.
+pub const UNTAGGED: &str = "(untagged)";
+
+/// Tracked time per group since `since` (all time if `None`), largest first.
+///
+/// With [`TimeGroup::Tag`] a todo counts under each of its tags, so rows can
+/// add up to more than [`total_tracked`].
+pub fn time_by(
+    todos: &[Todo],
+    group: TimeGroup,
+    since: Option<OffsetDateTime>,
+    now: OffsetDateTime,
+) -> Vec<TimeRow> {
+    let mut rows: BTreeMap<String, TimeRow> = BTreeMap::new();
+
+    for t in todos {
+        let tracked = tracking::total(&t.time_entries, since, now);
+        if tracked <= Duration::ZERO {
+            continue;
+        }
+        let entries = t
+            .time_entries
+            .iter()
+            .filter(|e| e.within(since, now) > Duration::ZERO)
+            .count();
+
+        let keys: Vec<String> = match group {
+            TimeGroup::Tag if t.tags.is_empty() => vec![UNTAGGED.to_string()],
+            TimeGroup::Tag => t.tags.iter().map(|x| x.as_str().to_string()).collect(),
+            TimeGroup::Project => vec![t.project.as_str().to_string()],
+            TimeGroup::Todo => vec![format!("{} {}", t.id.short(), t.title.as_str())],
+        };
+
+        for key in keys {
+            let row = rows.entry(key.clone()).or_insert(TimeRow {
+                key,
+                tracked: Duration::ZERO,
+                entries: 0,
+                todos: 0,
+            });
+            row.tracked += tracked;
+            row.entries += entries;
+            row.todos += 1;
+        }
+    }
+
+    let mut rows: Vec<TimeRow> = rows.into_values().collect();
+    rows.sort_by(|a, b| b.tracked.cmp(&a.tracked).then_with(|| a.key.cmp(&b.key)));
+    rows
+}
+
+/// Total tracked time across `todos`, each todo counted once.
+pub fn total_tracked(
+    todos: &[Todo],
+    since: Option<OffsetDateTime>,
+    now: OffsetDateTime,
+) -> Duration {
+    todos
+        .iter()
+        .map(|t| tracking::total(&t.time_entries, since, now))
+        .sum()
+}
+
+/// Open-task load of one project (someday items don't count).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectLoad {
+    pub project: String,
+    pub open: usize,
+    pub overdue: usize,
+    /// Due later today (not yet overdue), as on the dashboard board.
+    pub due_today: usize,
+}
+
+/// Open/overdue/due-today counts per project, by project name.
+///
+/// Projects with only done todos are kept (with zero counts) so time series
+/// drop to zero instead of disappearing.
+pub fn project_load(todos: &[Todo], now: OffsetDateTime) -> Vec<ProjectLoad> {
+    let board = crate::app::query::board(todos, now);
+    let mut rows: BTreeMap<String, ProjectLoad> = todos
+        .iter()
+        .map(|t| {
+            let project = t.project.as_str().to_string();
+            let row = ProjectLoad {
+                project: project.clone(),
+                ..ProjectLoad::default()
+            };
+            (project, row)
+        })
+        .collect();
+
+    for t in todos.iter().filter(|t| t.is_active()) {
+        if let Some(r) = rows.get_mut(t.project.as_str()) {
+            r.open += 1;
+        }
+    }
+    for t in &board.overdue {
+        if let Some(r) = rows.get_mut(t.project.as_str()) {
+            r.overdue += 1;
+        }
+    }
+    for t in &board.today {
+        if let Some(r) = rows.get_mut(t.project.as_str()) {
+            r.due_today += 1;
+        }
+    }
+
+    rows.into_values().collect()
+}
+
+/// Todo counts for one project, tag or priority.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CountRow {
+    pub key: String,
+    /// Not done (someday items included).
+    pub open: usize,
+    pub done: usize,
+    pub overdue: usize,
+}
+
+/// Everything `stats` reports: counts now, and completions over a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overview {
+    pub total: usize,
+    pub open: usize,
+    pub done: usize,
+    pub overdue: usize,
+    /// By project name, then tag (a todo under each of its tags, or
+    /// [`UNTAGGED`]), then priority P1..P4; each sorted by key.
+    pub by_project: Vec<CountRow>,
+    pub by_tag: Vec<CountRow>,
+    pub by_priority: Vec<CountRow>,
+    /// Length of the window ending at `now`.
+    pub days: u32,
+    /// Todos created within the window.
+    pub created: usize,
+    /// Todos completed within the window (whenever they were created).
+    pub completed: usize,
+    /// Share of the todos created within the window that are done by now.
+    pub completion_rate: Option<f64>,
+    /// Mean creation-to-completion time of the todos completed in the window.
+    pub avg_time_to_complete: Option<Duration>,
+}
+
+/// Aggregate `todos` for `stats`, looking back `days` days from `now`.
+pub fn overview(todos: &[Todo], days: u32, now: OffsetDateTime) -> Overview {
+    // A window reaching past the earliest date covers everything.
+    let since = now
+        .checked_sub(Duration::days(i64::from(days)))
+        .unwrap_or_else(|| Date::MIN.midnight().assume_offset(now.offset()));
+    let mut by_project: BTreeMap<String, CountRow> = BTreeMap::new();
+    let mut by_tag: BTreeMap<String, CountRow> = BTreeMap::new();
+    let mut by_priority: BTreeMap<String, CountRow> = BTreeMap::new();
+    let bump = |rows: &mut BTreeMap<String, CountRow>, key: &str, t: &Todo| {
+        let row = rows.entry(key.to_string()).or_insert_with(|| CountRow {
+            key: key.to_string(),
+            ..CountRow::default()
+        });
+        if t.status.is_done() {
+            row.done += 1;
+        } else {
+            row.open += 1;
+        }
+        if t.is_overdue(now) {
+            row.overdue += 1;
+        }
+    };
+
+    let (mut created, mut created_done, mut completed) = (0, 0, 0);
+    let mut to_complete = Duration::ZERO;
+    for t in todos {
+        bump(&mut by_project, t.project.as_str(), t);
+        if t.tags.is_empty() {
+            bump(&mut by_tag, UNTAGGED, t);
+        }
+        for tag in &t.tags {
+            bump(&mut by_tag, tag.as_str(), t);
+        }
+        bump(&mut by_priority, t.priority.label(), t);
+
+        if t.created_at >= since {
+            created += 1;
+            created_done += usize::from(t.status.is_done());
+        }
+        if let Status::Done { completed_at } = t.status
+            && completed_at >= since
+        {
+            completed += 1;
+            to_complete += (completed_at - t.created_at).max(Duration::ZERO);
+        }
+    }
+
+    let done = todos.iter().filter(|t| t.status.is_done()).count();
+    Overview {
+        total: todos.len(),
+        open: todos.len() - done,
+        done,
+        overdue: todos.iter().filter(|t| t.is_overdue(now)).count(),
+        by_project: by_project.into_values().collect(),
+        by_tag: by_tag.into_values().collect(),
+        by_priority: by_priority.into_values().collect(),
+        days,
+        created,
+        completed,
+        completion_rate: (created > 0).then(|| created_done as f64 / created as f64),
+        avg_time_to_complete: (completed > 0).then(|| to_complete / completed as u32),
+    }
+}
+
+/// Parse a `--since` value relative to `now`.
+///
+/// Accepts a lookback (`30d`, `2w`, `12h`, `90m`), a date (`2026-01-01`, midnight
+/// UTC) or an RFC3339 datetime.
+pub fn parse_since(input: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    let s = input.trim();
+
+    if let Some(unit) = s.chars().last()
+        && let Ok(n) = s[..s.len() - unit.len_utf8()].parse::<i64>()
+    {
+        let unit_secs: i64 = match unit.to_ascii_lowercase() {
+            'w' => 7 * 86_400,
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            _ => return None,
+        };
+        // Lookbacks past the representable range are no valid time at all.
+        let back = Duration::seconds(n.checked_mul(unit_secs)?);
+        return now.checked_sub(back);
+    }
+
+    if let Ok(d) = Date::parse(s, format_description!("[year]-[month]-[day]")) {
+        return Some(d.midnight().assume_utc());
+    }
+
+    OffsetDateTime::parse(s, &Rfc3339).ok()
+}
+
+/// Whole minutes, for display with `format_minutes`.
+pub fn minutes(d: Duration) -> u32 {
+    u32::try_from(d.whole_minutes().max(0)).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        todo::{Tag, Title},
+        tracking::TimeEntry,
+    };
+
+    fn tracked(title: &str, tags: &[&str], spans: &[(i64, i64)]) -> Todo {
+        let t0 = OffsetDateTime::UNIX_EPOCH;
+        let mut t = Todo::new(Title::parse(title).unwrap());
+        t.tags = tags.iter().map(|x| Tag::parse(*x).unwrap()).collect();
+        t.time_entries = spans
+            .iter()
+            .map(|&(from, to)| TimeEntry {
+                start: t0 + Duration::hours(from),
+                end: Some(t0 + Duration::hours(to)),
+                note: None,
+            })
+            .collect();
+        t
+    }
+
+    #[test]
+    fn groups_by_tag_within_window() {
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::days(1);
+        let todos = vec![
+            tracked("Client A", &["billable", "acme"], &[(0, 2), (10, 11)]),
+            tracked("Chores", &[], &[(12, 13)]),
+        ];
+
+        let since = Some(OffsetDateTime::UNIX_EPOCH + Duration::hours(5));
+        let rows = time_by(&todos, TimeGroup::Tag, since, now);
+        let summary: Vec<_> = rows
+            .iter()
+            .map(|r| (r.key.as_str(), minutes(r.tracked), r.entries))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("(untagged)", 60, 1), ("acme", 60, 1), ("billable", 60, 1)]
+        );
+        assert_eq!(total_tracked(&todos, None, now), Duration::hours(4));
+    }
+
+    #[test]
+    fn since_accepts_lookbacks_and_dates() {
+        let now = OffsetDateTime::parse("2026-03-31T12:00:00Z", &Rfc3339).unwrap();
+        assert_eq!(parse_since("30d", now), Some(now - Duration::days(30)));
+        assert_eq!(parse_since("2w", now), Some(now - Duration::weeks(2)));
+        assert_eq!(
+            parse_since("2026-03-01", now),
+            OffsetDateTime::parse("2026-03-01T00:00:00Z", &Rfc3339).ok()
+        );
+        assert_eq!(parse_since("soon", now), None);
+    }
+
+    #[test]
+    fn lookbacks_out_of_range_are_rejected() {
+        let now = OffsetDateTime::parse("2026-03-31T12:00:00Z", &Rfc3339).unwrap();
+        assert_eq!(parse_since("9999999999d", now), None);
+        assert_eq!(parse_since("9223372036854775807w", now), None);
+        assert_eq!(parse_since("-9999999999d", now), None);
+    }
+
+    #[test]
+    fn overview_counts_groups_and_completions_in_window() {
+        use crate::domain::todo::{DueAt, Priority};
+
+        let now = OffsetDateTime::parse("2026-03-31T12:00:00Z", &Rfc3339).unwrap();
+        let todo = |title: &str, tags: &[&str], age_days: i64| {
+            let mut t = tracked(title, tags, &[]);
+            t.created_at = now - Duration::days(age_days);
+            t
+        };
+        let done = |mut t: Todo, days_ago: i64| {
+            t.status = Status::Done {
+                completed_at: now - Duration::days(days_ago),
+            };
+            t
+        };
+        let mut late = todo("Late", &["work"], 20);
+        late.due = Some(DueAt::from_dt(now - Duration::days(1)));
+        late.priority = Priority::P1;
+        let todos = vec![
+            late,
+            todo("Fresh", &[], 2),
+            // Created and finished inside the window: 2 days to complete.
+            done(todo("Quick", &["work", "home"], 5), 3),
+            // Finished in the window, created long before: 40 days.
+            done(todo("Slow", &["home"], 50), 10),
+            // Finished before the window.
+            done(todo("Ancient", &[], 90), 60),
+        ];
+
+        let o = overview(&todos, 30, now);
+        assert_eq!((o.total, o.open, o.done, o.overdue), (5, 2, 3, 1));
+        let tags: Vec<_> = o
+            .by_tag
+            .iter()
+            .map(|r| (r.key.as_str(), r.open, r.done, r.overdue))
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                ("(untagged)", 1, 1, 0),
+                ("home", 0, 2, 0),
+                ("work", 1, 1, 1)
+            ]
+        );
+        let priorities: Vec<_> = o.by_priority.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(priorities, ["P1", "P3"]);
+        assert_eq!((o.created, o.completed), (3, 2));
+        assert_eq!(o.completion_rate, Some(1.0 / 3.0));
+        assert_eq!(o.avg_time_to_complete, Some(Duration::days(21)));
+
+        // A window longer than the calendar takes in everything.
+        let all = overview(&todos, u32::MAX, now);
+        assert_eq!((all.created, all.completed), (5, 3));
+    }
+
+    #[test]
+    fn project_load_counts_open_overdue_and_today() {
+        use crate::domain::todo::{DueAt, ProjectName};
+
+        let now = OffsetDateTime::parse("2026-03-10T12:00:00Z", &Rfc3339).unwrap();
+        let todo = |project: &str, due: Option<Duration>| {
+            let mut t = Todo::new(Title::parse("Task").unwrap());
+            t.project = ProjectName::parse(project).unwrap();
+            t.due = due.map(|d| DueAt::from_dt(now + d));
+            t
+        };
+        let mut shipped = todo("Web", Some(-Duration::days(2)));
+        shipped.mark_done().unwrap();
+        let todos = vec![
+            todo("Home", Some(-Duration::hours(1))),
+            todo("Home", Some(Duration::hours(2))),
+            todo("Home", None),
+            shipped,
+        ];
+
+        let load = project_load(&todos, now);
+        let summary: Vec<_> = load
+            .iter()
+            .map(|l| (l.project.as_str(), l.open, l.overdue, l.due_today))
+            .collect();
+        assert_eq!(summary, vec![("Home", 3, 1, 1), ("Web", 0, 0, 0)]);
+    }
+}
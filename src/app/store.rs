@@ -1,23 +1,45 @@
 //! Store: central application state holder.
 //!
-//! For now it just owns a repository, but later it will also own:
+//! Owns the repository, the undo/redo [`History`] and the [`Clock`] changes
+//! are stamped with. Every mutation made through the store records the
+//! affected todos; the caller commits them as one step per command. Later it
+//! will also own:
 //! - loaded configuration
-//! - undo/redo stacks
 //! - dirty tracking for persistence
 
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
 use anyhow::Result;
+use time::OffsetDateTime;
 
 use crate::{
-    app::{errors::AppError, repository::TodoRepository, service::TodoService},
+    app::{
+        errors::AppError,
+        history::{History, Step},
+        projects,
+        query::ListQuery,
+        repository::{self, BatchOutcome, DuplicatePolicy, RepoOp, TodoRepository},
+        service::{self, TodoService},
+        warnings::Warning,
+    },
     domain::{
+        clock::{Clock, SystemClock},
         errors::DomainError,
-        todo::{Title, Todo, TodoId, TodoPatch},
+        project::Project,
+        todo::{ProjectName, Status, Title, Todo, TodoId, TodoPatch},
+        tracking::TimeEntry,
     },
 };
 
 /// App store that owns stateful dependencies.
 pub struct Store<R> {
     service: TodoService<R>,
+    history: History,
+    clock: Arc<dyn Clock>,
+    duplicates: DuplicatePolicy,
 }
 
 impl<R> Store<R>
@@ -27,34 +49,109 @@ where
     pub fn new(repo: R) -> Self {
         Self {
             service: TodoService::new(repo),
+            history: History::default(),
+            clock: Arc::new(SystemClock),
+            duplicates: DuplicatePolicy::default(),
         }
     }
 
+    /// Take the time from `clock` instead of the system.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// How [`set_all`](Self::set_all) treats todos sharing an ID.
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Warnings from adds and edits since the last call (see
+    /// [`warnings`](crate::app::warnings)).
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.service.take_warnings()
+    }
+
+    /// The current time, by the store's clock.
+    pub fn now(&self) -> OffsetDateTime {
+        self.clock.now()
+    }
+
+    /// Continue from a history saved by an earlier run.
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
     pub fn add_todo(&mut self, title: Title) -> Result<TodoId> {
-        self.service.add_todo(title)
+        let now = self.now();
+        let id = self.service.add_todo(title, now)?;
+        let after = self.repo_mut().get(id);
+        self.history.record(None, after);
+        Ok(id)
     }
 
     pub fn list_todos(&self) -> Vec<Todo> {
         self.service.list_todos()
     }
 
+    /// Todos matching `query`, filtered and sorted by the repository.
+    pub fn find_todos(&self, query: &ListQuery, now: OffsetDateTime) -> Vec<Todo> {
+        self.service.find_todos(query, now)
+    }
+
+    pub fn count_todos(&self, query: &ListQuery, now: OffsetDateTime) -> usize {
+        self.service.count_todos(query, now)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.list_todos().is_empty()
     }
 
-    /// Insert an already-built Todo (for seeding / import).
-    pub fn insert_todo(&mut self, todo: Todo) {
-        self.service.insert_todo(todo);
+    /// Insert an already-built Todo (for seeding / import). Its ID must be
+    /// new ([`AppError::DuplicateIds`]).
+    pub fn insert_todo(&mut self, todo: Todo) -> Result<(), AppError> {
+        let now = self.now();
+        self.service.insert_todo(todo.clone(), now)?;
+        self.history.record(None, Some(todo));
+        Ok(())
     }
 
-    pub fn insert_many(&mut self, todos: Vec<Todo>) {
+    pub fn insert_many(&mut self, todos: Vec<Todo>) -> Result<(), AppError> {
         for todo in todos {
-            self.insert_todo(todo);
+            self.insert_todo(todo)?;
         }
+        Ok(())
     }
 
     pub fn edit_todo(&mut self, id: TodoId, patch: TodoPatch) -> Result<bool> {
-        self.service.edit_todo(id, patch)
+        let now = self.now();
+        self.tracked(id, |s| s.service.edit_todo(id, patch, now))
+    }
+
+    /// Replace a stored todo with an updated copy (same id).
+    pub fn replace_todo(&mut self, todo: Todo) -> bool {
+        let id = todo.id;
+        self.tracked(id, |s| s.repo_mut().replace(todo))
+    }
+
+    /// Record that `notify` alerted about `id`. Not an undoable step: undoing
+    /// it would only make the next `notify` repeat the alert.
+    pub fn mark_notified(&mut self, id: TodoId, now: OffsetDateTime) -> bool {
+        let Some(mut todo) = self.repo_mut().get(id) else {
+            return false;
+        };
+        todo.mark_notified(now);
+        self.repo_mut().replace(todo)
     }
 
     /// Escape hatch for infra-specific operations (like saving).
@@ -65,16 +162,33 @@ where
         self.service.repo_mut()
     }
 
-    pub fn set_all(&mut self, todos: Vec<Todo>) {
-        self.repo_mut().set_all(todos);
+    /// Replace every todo. Todos sharing an ID are handled by the store's
+    /// [`DuplicatePolicy`]; returns the IDs that were duplicated (empty
+    /// unless the policy keeps one of each).
+    pub fn set_all(&mut self, todos: Vec<Todo>) -> Result<Vec<TodoId>, AppError> {
+        let (todos, dups) = repository::dedupe(todos, self.duplicates)?;
+        let mut before: BTreeMap<TodoId, Todo> =
+            self.list_todos().into_iter().map(|t| (t.id, t)).collect();
+        for t in &todos {
+            self.history.record(before.remove(&t.id), Some(t.clone()));
+        }
+        for (_, gone) in before {
+            self.history.record(Some(gone), None);
+        }
+        self.repo_mut().set_all(todos)?;
+        Ok(dups)
     }
 
     pub fn mark_done(&mut self, id: TodoId) -> Result<(), AppError> {
+        self.tracked(id, |s| s.mark_done_untracked(id))
+    }
+
+    fn mark_done_untracked(&mut self, id: TodoId) -> Result<(), AppError> {
         let Some(mut todo) = self.repo_mut().get(id) else {
             return Err(AppError::TodoNotFound);
         };
 
-        match todo.mark_done() {
+        match todo.mark_done_at(self.now()) {
             Ok(()) => {}
             Err(DomainError::AlreadyDone) => return Err(AppError::AlreadyDone),
             Err(_) => return Err(AppError::TodoNotFound),
@@ -88,11 +202,15 @@ where
     }
 
     pub fn mark_open(&mut self, id: TodoId) -> Result<(), AppError> {
+        self.tracked(id, |s| s.mark_open_untracked(id))
+    }
+
+    fn mark_open_untracked(&mut self, id: TodoId) -> Result<(), AppError> {
         let Some(mut todo) = self.repo_mut().get(id) else {
             return Err(AppError::TodoNotFound);
         };
 
-        match todo.mark_open() {
+        match todo.mark_open_at(self.now()) {
             Ok(()) => {}
             Err(DomainError::AlreadyOpen) => return Err(AppError::AlreadyOpen),
             Err(_) => return Err(AppError::TodoNotFound),
@@ -105,11 +223,591 @@ where
         }
     }
 
-    pub fn delete(&mut self, id: TodoId) -> Result<(), AppError> {
-        if self.repo_mut().remove(id) {
+    /// Park `id` on the someday/maybe list (re-parking marks it reviewed).
+    pub fn park(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        self.tracked(id, |s| s.park_untracked(id, now))
+    }
+
+    fn park_untracked(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        let Some(mut todo) = self.repo_mut().get(id) else {
+            return Err(AppError::TodoNotFound);
+        };
+
+        match todo.park(now) {
+            Ok(()) => {}
+            Err(DomainError::AlreadyDone) => return Err(AppError::AlreadyDone),
+            Err(_) => return Err(AppError::TodoNotFound),
+        }
+
+        if self.repo_mut().replace(todo) {
+            Ok(())
+        } else {
+            Err(AppError::TodoNotFound)
+        }
+    }
+
+    /// Bring `id` back from the someday/maybe list.
+    pub fn promote(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        self.tracked(id, |s| s.promote_untracked(id, now))
+    }
+
+    fn promote_untracked(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        let Some(mut todo) = self.repo_mut().get(id) else {
+            return Err(AppError::TodoNotFound);
+        };
+
+        match todo.promote(now) {
+            Ok(()) => {}
+            Err(DomainError::NotSomeday) => return Err(AppError::NotSomeday),
+            Err(_) => return Err(AppError::TodoNotFound),
+        }
+
+        if self.repo_mut().replace(todo) {
+            Ok(())
+        } else {
+            Err(AppError::TodoNotFound)
+        }
+    }
+
+    /// Move done todo `id` to the archive.
+    pub fn archive(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        self.tracked(id, |s| s.archive_untracked(id, now))
+    }
+
+    fn archive_untracked(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        let Some(mut todo) = self.repo_mut().get(id) else {
+            return Err(AppError::TodoNotFound);
+        };
+
+        match todo.archive(now) {
+            Ok(()) => {}
+            Err(DomainError::NotDone) => return Err(AppError::NotDone),
+            Err(_) => return Err(AppError::TodoNotFound),
+        }
+
+        if self.repo_mut().replace(todo) {
+            Ok(())
+        } else {
+            Err(AppError::TodoNotFound)
+        }
+    }
+
+    /// Bring `id` back from the archive.
+    pub fn unarchive(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        self.tracked(id, |s| s.unarchive_untracked(id, now))
+    }
+
+    fn unarchive_untracked(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        let Some(mut todo) = self.repo_mut().get(id) else {
+            return Err(AppError::TodoNotFound);
+        };
+
+        match todo.unarchive(now) {
+            Ok(()) => {}
+            Err(DomainError::NotArchived) => return Err(AppError::NotArchived),
+            Err(_) => return Err(AppError::TodoNotFound),
+        }
+
+        if self.repo_mut().replace(todo) {
+            Ok(())
+        } else {
+            Err(AppError::TodoNotFound)
+        }
+    }
+
+    /// Archive every todo completed more than `days` ago. Nothing is that old
+    /// when the cutoff falls before the earliest date.
+    pub fn archive_older_than(&mut self, days: u32, now: OffsetDateTime) -> Vec<Todo> {
+        match now.checked_sub(time::Duration::days(days.into())) {
+            Some(cutoff) => self.archive_completed(cutoff, now),
+            None => Vec::new(),
+        }
+    }
+
+    /// Archive every todo completed before `cutoff`, returning them oldest
+    /// first as archived.
+    pub fn archive_completed(&mut self, cutoff: OffsetDateTime, now: OffsetDateTime) -> Vec<Todo> {
+        let mut due: Vec<Todo> = self
+            .list_todos()
+            .into_iter()
+            .filter(|t| t.archivable(cutoff))
+            .collect();
+        due.sort_by_key(|t| match t.status {
+            Status::Done { completed_at } => completed_at,
+            Status::Open => t.updated_at,
+        });
+        let mut archived = Vec::new();
+        for t in due {
+            if self.archive(t.id, now).is_ok()
+                && let Some(t) = self.repo_mut().get(t.id)
+            {
+                archived.push(t);
+            }
+        }
+        archived
+    }
+
+    /// Make `id` wait for `on` to be done. `Ok(false)` if it already did.
+    pub fn block(&mut self, id: TodoId, on: TodoId) -> Result<bool, AppError> {
+        self.tracked(id, |s| s.block_untracked(id, on))
+    }
+
+    fn block_untracked(&mut self, id: TodoId, on: TodoId) -> Result<bool, AppError> {
+        let Some(todo) = self.repo_mut().get(id) else {
+            return Err(AppError::TodoNotFound);
+        };
+        if self.repo_mut().get(on).is_none() {
+            return Err(AppError::TodoNotFound);
+        }
+        if todo.depends_on.contains(&on) {
+            return Ok(false);
+        }
+        let mut deps = todo.depends_on.clone();
+        deps.insert(on);
+        service::check_dependencies(&self.list_todos(), id, &deps)?;
+        self.set_dependencies(todo, deps)
+    }
+
+    /// Stop `id` waiting for `on`, or for anything with `None`. Returns how
+    /// many dependencies were removed.
+    pub fn unblock(&mut self, id: TodoId, on: Option<TodoId>) -> Result<usize, AppError> {
+        self.tracked(id, |s| s.unblock_untracked(id, on))
+    }
+
+    fn unblock_untracked(&mut self, id: TodoId, on: Option<TodoId>) -> Result<usize, AppError> {
+        let Some(todo) = self.repo_mut().get(id) else {
+            return Err(AppError::TodoNotFound);
+        };
+        let mut deps = todo.depends_on.clone();
+        match on {
+            Some(on) => deps.retain(|d| *d != on),
+            None => deps.clear(),
+        }
+        let removed = todo.depends_on.len() - deps.len();
+        if removed > 0 {
+            self.set_dependencies(todo, deps)?;
+        }
+        Ok(removed)
+    }
+
+    fn set_dependencies(
+        &mut self,
+        mut todo: Todo,
+        deps: std::collections::BTreeSet<TodoId>,
+    ) -> Result<bool, AppError> {
+        let patch = TodoPatch {
+            depends_on: Some(deps),
+            ..TodoPatch::default()
+        };
+        todo.apply_patch_at(patch, self.now());
+        if self.repo_mut().replace(todo) {
+            Ok(true)
+        } else {
+            Err(AppError::TodoNotFound)
+        }
+    }
+
+    /// Start a timer on `id`.
+    pub fn start_timer(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        self.tracked(id, |s| s.start_timer_untracked(id, now))
+    }
+
+    fn start_timer_untracked(&mut self, id: TodoId, now: OffsetDateTime) -> Result<(), AppError> {
+        let Some(mut todo) = self.repo_mut().get(id) else {
+            return Err(AppError::TodoNotFound);
+        };
+
+        match todo.start_timer(now) {
+            Ok(()) => {}
+            Err(DomainError::TimerRunning) => return Err(AppError::TimerRunning),
+            Err(_) => return Err(AppError::TodoNotFound),
+        }
+
+        if self.repo_mut().replace(todo) {
             Ok(())
         } else {
             Err(AppError::TodoNotFound)
         }
     }
+
+    /// Stop the running timer on `id`, returning the finished entry.
+    pub fn stop_timer(
+        &mut self,
+        id: TodoId,
+        now: OffsetDateTime,
+        note: Option<String>,
+    ) -> Result<TimeEntry, AppError> {
+        self.tracked(id, |s| s.stop_timer_untracked(id, now, note))
+    }
+
+    fn stop_timer_untracked(
+        &mut self,
+        id: TodoId,
+        now: OffsetDateTime,
+        note: Option<String>,
+    ) -> Result<TimeEntry, AppError> {
+        let Some(mut todo) = self.repo_mut().get(id) else {
+            return Err(AppError::TodoNotFound);
+        };
+
+        let entry = match todo.stop_timer(now, note) {
+            Ok(entry) => entry,
+            Err(DomainError::TimerNotRunning) => return Err(AppError::TimerNotRunning),
+            Err(_) => return Err(AppError::TodoNotFound),
+        };
+
+        if self.repo_mut().replace(todo) {
+            Ok(entry)
+        } else {
+            Err(AppError::TodoNotFound)
+        }
+    }
+
+    pub fn delete(&mut self, id: TodoId) -> Result<(), AppError> {
+        self.tracked(id, |s| {
+            if s.repo_mut().remove(id) {
+                Ok(())
+            } else {
+                Err(AppError::TodoNotFound)
+            }
+        })
+    }
+
+    pub fn projects(&self) -> Vec<Project> {
+        self.service.repo.projects()
+    }
+
+    /// Change the record of project `name`, creating it if there is none.
+    pub fn update_project(&mut self, name: &ProjectName, f: impl FnOnce(&mut Project)) {
+        let mut projects = self.projects();
+        let i = match projects.iter().position(|p| p.is_named(name.as_str())) {
+            Some(i) => i,
+            None => {
+                projects.push(Project::new(name.clone()));
+                projects.len() - 1
+            }
+        };
+        f(&mut projects[i]);
+        self.set_projects(projects);
+    }
+
+    /// Move every todo of project `from` to `to`, and its record with them.
+    ///
+    /// Returns how many todos moved. Renaming onto an existing project merges
+    /// the two (see [`projects::rename_record`]).
+    pub fn rename_project(&mut self, from: &str, to: &ProjectName) -> Result<usize, AppError> {
+        let mut projects = self.projects();
+        let moving: Vec<_> = self
+            .list_todos()
+            .into_iter()
+            .filter(|t| t.project.as_str().eq_ignore_ascii_case(from.trim()))
+            .collect();
+        if moving.is_empty() && projects::find(&projects, from).is_none() {
+            return Err(AppError::ProjectNotFound);
+        }
+
+        let now = self.now();
+        let mut ops: Vec<_> = moving
+            .iter()
+            .cloned()
+            .map(|mut todo| {
+                let patch = TodoPatch {
+                    project: Some(to.clone()),
+                    ..TodoPatch::default()
+                };
+                todo.apply_patch_at(patch, now);
+                RepoOp::Replace(todo)
+            })
+            .collect();
+        projects::rename_record(&mut projects, from, to);
+        ops.push(RepoOp::SetProjects(projects));
+        self.apply_batch(ops)?;
+        Ok(moving.len())
+    }
+
+    /// Move `ids` into project `to`, skipping todos already there. Each move
+    /// is recorded, so undoing the command puts them all back. Returns how
+    /// many moved.
+    pub fn move_todos(&mut self, ids: &[TodoId], to: &ProjectName) -> usize {
+        let now = self.now();
+        let mut ops = Vec::new();
+        for &id in ids {
+            let Some(mut todo) = self.repo_mut().get(id) else {
+                continue;
+            };
+            if todo.project.as_str().eq_ignore_ascii_case(to.as_str())
+                || ops
+                    .iter()
+                    .any(|op| matches!(op, RepoOp::Replace(t) if t.id == id))
+            {
+                continue;
+            }
+            let patch = TodoPatch {
+                project: Some(to.clone()),
+                ..TodoPatch::default()
+            };
+            todo.apply_patch_at(patch, now);
+            ops.push(RepoOp::Replace(todo));
+        }
+        // Every op replaces a todo that was just read, so the batch applies.
+        self.apply_batch(ops).map_or(0, |outcome| outcome.replaced)
+    }
+
+    /// Apply `ops` all or nothing (see [`TodoRepository::apply_batch`]),
+    /// recording each change for undo.
+    pub fn apply_batch(&mut self, ops: Vec<RepoOp>) -> Result<BatchOutcome, AppError> {
+        let mut ids: Vec<TodoId> = Vec::new();
+        let mut projects = None;
+        for op in &ops {
+            let id = match op {
+                RepoOp::Add(todo) | RepoOp::Replace(todo) => todo.id,
+                RepoOp::Remove(id) => *id,
+                RepoOp::SetProjects(p) => {
+                    projects = Some(p.clone());
+                    continue;
+                }
+            };
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        let before: Vec<_> = ids.iter().map(|&id| self.repo_mut().get(id)).collect();
+        let before_projects = self.projects();
+
+        let outcome = self.repo_mut().apply_batch(ops)?;
+        for (id, before) in ids.into_iter().zip(before) {
+            let after = self.repo_mut().get(id);
+            self.history.record(before, after);
+        }
+        if let Some(after) = projects {
+            self.history.record_projects(before_projects, after);
+        }
+        Ok(outcome)
+    }
+
+    fn set_projects(&mut self, projects: Vec<Project>) {
+        let before = self.projects();
+        self.history.record_projects(before, projects.clone());
+        self.repo_mut().set_projects(projects);
+    }
+
+    /// Revert the most recent step, moving it to the redo stack. The step
+    /// is applied as one batch; if that fails it stays on the undo stack.
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.history.pop_undo()?;
+        let states = step.changes.iter().rev().map(|c| (c.id, c.before.clone()));
+        let projects = step.projects.as_ref().map(|p| p.before.clone());
+        if self.restore(states, projects).is_err() {
+            self.history.push_undo(step);
+            return None;
+        }
+        self.history.push_redo(step.clone());
+        Some(step)
+    }
+
+    /// Re-apply the most recently undone step.
+    pub fn redo(&mut self) -> Option<Step> {
+        let step = self.history.pop_redo()?;
+        let states = step.changes.iter().map(|c| (c.id, c.after.clone()));
+        let projects = step.projects.as_ref().map(|p| p.after.clone());
+        if self.restore(states, projects).is_err() {
+            self.history.push_redo(step);
+            return None;
+        }
+        self.history.push_undo(step.clone());
+        Some(step)
+    }
+
+    /// Put each todo into its state (`None` = absent) and set the project
+    /// records, in one batch and without recording it.
+    fn restore(
+        &mut self,
+        states: impl Iterator<Item = (TodoId, Option<Todo>)>,
+        projects: Option<Vec<Project>>,
+    ) -> Result<BatchOutcome, AppError> {
+        let repo = self.repo_mut();
+        let mut stored: BTreeSet<TodoId> = repo.list().iter().map(|t| t.id).collect();
+        let mut ops = Vec::new();
+        for (id, state) in states {
+            let present = state.is_some();
+            ops.extend(RepoOp::restore(id, state, stored.contains(&id)));
+            if present {
+                stored.insert(id);
+            } else {
+                stored.remove(&id);
+            }
+        }
+        ops.extend(projects.map(RepoOp::SetProjects));
+        repo.apply_batch(ops)
+    }
+
+    /// Run `f` and record what it did to `id`.
+    fn tracked<T>(&mut self, id: TodoId, f: impl FnOnce(&mut Self) -> T) -> T {
+        let before = self.repo_mut().get(id);
+        let result = f(self);
+        let after = self.repo_mut().get(id);
+        self.history.record(before, after);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{domain::todo::Priority, infra::memory_repo::MemoryTodoRepository};
+    use time::macros::datetime;
+
+    #[test]
+    fn changes_are_stamped_by_the_store_clock() {
+        use crate::testing::FixedClock;
+
+        let clock = FixedClock::new(datetime!(2026-03-01 12:00 UTC));
+        let mut store = Store::new(MemoryTodoRepository::new()).with_clock(Arc::new(clock.clone()));
+        let id = store.add_todo(Title::parse("A").unwrap()).unwrap();
+        clock.advance(time::Duration::hours(2));
+        store.mark_done(id).unwrap();
+
+        let todo = store.repo_mut().get(id).unwrap();
+        assert_eq!(todo.created_at, datetime!(2026-03-01 12:00 UTC));
+        assert_eq!(
+            todo.status,
+            Status::Done {
+                completed_at: datetime!(2026-03-01 14:00 UTC)
+            }
+        );
+        assert_eq!(todo.updated_at, datetime!(2026-03-01 14:00 UTC));
+    }
+
+    #[test]
+    fn undo_and_redo_revert_whole_steps() {
+        let at = datetime!(2026-03-01 12:00 UTC);
+        let mut store = Store::new(MemoryTodoRepository::new());
+        let a = store.add_todo(Title::parse("A").unwrap()).unwrap();
+        let b = store.add_todo(Title::parse("B").unwrap()).unwrap();
+        store.history_mut().commit("add", at);
+
+        let patch = TodoPatch {
+            priority: Some(Priority::P1),
+            ..TodoPatch::default()
+        };
+        store.edit_todo(a, patch).unwrap();
+        store.mark_done(a).unwrap();
+        store.delete(b).unwrap();
+        store.history_mut().commit("busy", at);
+
+        let step = store.undo().unwrap();
+        assert_eq!(step.label, "busy");
+        let todos = store.list_todos();
+        assert_eq!(todos.len(), 2);
+        let restored = todos.iter().find(|t| t.id == a).unwrap();
+        assert!(!restored.status.is_done());
+        assert_ne!(restored.priority, Priority::P1);
+
+        store.redo().unwrap();
+        let todos = store.list_todos();
+        assert_eq!(todos.len(), 1);
+        assert!(todos[0].status.is_done());
+
+        store.undo().unwrap();
+        store.undo().unwrap();
+        assert!(store.is_empty());
+        assert!(store.undo().is_none());
+    }
+
+    #[test]
+    fn moving_todos_skips_those_already_there_and_undoes_together() {
+        use crate::domain::todo::ProjectName;
+
+        let at = datetime!(2026-03-01 12:00 UTC);
+        let mut store = Store::new(MemoryTodoRepository::new());
+        let a = store.add_todo(Title::parse("A").unwrap()).unwrap();
+        let b = store.add_todo(Title::parse("B").unwrap()).unwrap();
+        let work = ProjectName::parse("Work").unwrap();
+        store.move_todos(&[b], &work);
+        store.history_mut().commit("setup", at);
+
+        let lower = ProjectName::parse("work").unwrap();
+        assert_eq!(store.move_todos(&[a, b], &lower), 1);
+        store.history_mut().commit("move", at);
+        let project = |store: &Store<MemoryTodoRepository>, id| {
+            let todos = store.list_todos();
+            todos
+                .iter()
+                .find(|t| t.id == id)
+                .unwrap()
+                .project
+                .as_str()
+                .to_string()
+        };
+        assert_eq!(
+            (project(&store, a), project(&store, b)),
+            ("work".into(), "Work".into())
+        );
+
+        assert_eq!(store.undo().unwrap().label, "move");
+        assert_eq!(project(&store, a), "Inbox");
+    }
+
+    #[test]
+    fn renaming_a_project_moves_its_todos_and_undoes_as_one_step() {
+        use crate::domain::todo::{Color, ProjectName};
+
+        let at = datetime!(2026-03-01 12:00 UTC);
+        let mut store = Store::new(MemoryTodoRepository::new());
+        let work = ProjectName::parse("Work").unwrap();
+        let a = store.add_todo(Title::parse("A").unwrap()).unwrap();
+        store.add_todo(Title::parse("B").unwrap()).unwrap();
+        store
+            .edit_todo(
+                a,
+                TodoPatch {
+                    project: Some(work.clone()),
+                    ..TodoPatch::default()
+                },
+            )
+            .unwrap();
+        store.update_project(&work, |p| p.color = Some(Color::Blue));
+        store.history_mut().commit("setup", at);
+
+        let office = ProjectName::parse("Office").unwrap();
+        assert_eq!(store.rename_project("work", &office).unwrap(), 1);
+        assert!(matches!(
+            store.rename_project("nope", &office),
+            Err(AppError::ProjectNotFound)
+        ));
+        store.history_mut().commit("rename", at);
+        assert_eq!(store.repo_mut().get(a).unwrap().project, office);
+        assert_eq!(store.projects()[0].name, office);
+
+        store.undo().unwrap();
+        assert_eq!(store.repo_mut().get(a).unwrap().project, work);
+        assert_eq!(store.projects()[0].name, work);
+        assert_eq!(store.projects()[0].color, Some(Color::Blue));
+    }
+
+    #[test]
+    fn blocking_rejects_cycles_even_through_edits() {
+        let mut store = Store::new(MemoryTodoRepository::new());
+        let a = store.add_todo(Title::parse("A").unwrap()).unwrap();
+        let b = store.add_todo(Title::parse("B").unwrap()).unwrap();
+        let c = store.add_todo(Title::parse("C").unwrap()).unwrap();
+
+        assert!(store.block(a, b).unwrap());
+        assert!(!store.block(a, b).unwrap());
+        assert!(store.block(b, c).unwrap());
+        assert!(matches!(store.block(c, a), Err(AppError::DependencyCycle)));
+        assert!(matches!(store.block(c, c), Err(AppError::SelfDependency)));
+        let edit = store.edit_todo(
+            c,
+            TodoPatch {
+                depends_on: Some([a].into()),
+                ..TodoPatch::default()
+            },
+        );
+        assert!(edit.unwrap_err().downcast_ref::<AppError>().is_some());
+
+        assert_eq!(store.unblock(a, Some(c)).unwrap(), 0);
+        assert_eq!(store.unblock(a, None).unwrap(), 1);
+        assert!(store.block(c, a).unwrap());
+    }
 }
@@ -0,0 +1,137 @@
+//! Subtasks: todos with a `parent`.
+//!
+//! A parent's progress is the share of its direct subtasks that are done.
+//! With `complete_parents` in config.toml, finishing the last open subtask
+//! completes the parent too (and so on up the chain).
+
+use std::collections::BTreeMap;
+
+use crate::domain::todo::{Todo, TodoId};
+
+/// Done and total subtasks of one parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    /// Whole percent done, rounded down (100 only when all are done).
+    pub fn percent(self) -> u8 {
+        if self.total == 0 {
+            return 0;
+        }
+        u8::try_from(self.done * 100 / self.total).unwrap_or(100)
+    }
+}
+
+/// Progress of every todo that has subtasks.
+pub fn progress_by_parent(todos: &[Todo]) -> BTreeMap<TodoId, Progress> {
+    let mut map: BTreeMap<TodoId, Progress> = BTreeMap::new();
+    for todo in todos {
+        if let Some(parent) = todo.parent {
+            let p = map.entry(parent).or_default();
+            p.total += 1;
+            p.done += usize::from(todo.status.is_done());
+        }
+    }
+    map
+}
+
+/// Progress of `id`'s subtasks, if it has any.
+pub fn progress(todos: &[Todo], id: TodoId) -> Option<Progress> {
+    progress_by_parent(todos).remove(&id)
+}
+
+/// Can `id` become a subtask of `parent`? Not of itself or of one of its
+/// own subtasks.
+pub fn check_parent(todos: &[Todo], id: TodoId, parent: TodoId) -> Result<(), String> {
+    let parent_of = |t: TodoId| todos.iter().find(|x| x.id == t).and_then(|x| x.parent);
+    if !todos.iter().any(|t| t.id == parent) {
+        return Err(format!("no todo {}", parent.short()));
+    }
+    let mut at = Some(parent);
+    while let Some(p) = at {
+        if p == id {
+            return Err(format!(
+                "{} can't be a subtask of its own subtask {}",
+                id.short(),
+                parent.short()
+            ));
+        }
+        at = parent_of(p);
+    }
+    Ok(())
+}
+
+/// Open parents (nearest first) that are complete once `done` is: those
+/// whose subtasks are then all done, walking up while each one completes.
+pub fn parents_to_complete(todos: &[Todo], done: TodoId) -> Vec<TodoId> {
+    let mut completed = vec![done];
+    let mut at = todos.iter().find(|t| t.id == done).and_then(|t| t.parent);
+    while let Some(parent) = at {
+        let Some(todo) = todos.iter().find(|t| t.id == parent) else {
+            break;
+        };
+        let all_done = todos
+            .iter()
+            .filter(|t| t.parent == Some(parent))
+            .all(|t| t.status.is_done() || completed.contains(&t.id));
+        if !all_done || todo.status.is_done() {
+            break;
+        }
+        completed.push(parent);
+        at = todo.parent;
+    }
+    completed.remove(0);
+    completed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::Title;
+
+    fn todo(title: &str, parent: Option<&Todo>) -> Todo {
+        let mut t = Todo::new(Title::parse(title).unwrap());
+        t.parent = parent.map(|p| p.id);
+        t
+    }
+
+    #[test]
+    fn progress_and_completing_up_the_chain() {
+        let release = todo("Release", None);
+        let docs = todo("Docs", Some(&release));
+        let build = todo("Build", Some(&release));
+        let linux = todo("Linux", Some(&build));
+        let mut mac = todo("Mac", Some(&build));
+        mac.mark_done().unwrap();
+        let mut todos = vec![release.clone(), docs, build.clone(), linux.clone(), mac];
+
+        let p = progress(&todos, build.id).unwrap();
+        assert_eq!((p.done, p.total, p.percent()), (1, 2, 50));
+        assert_eq!(progress(&todos, linux.id), None);
+
+        // Finishing Linux completes Build, but Docs keeps Release open...
+        assert_eq!(parents_to_complete(&todos, linux.id), [build.id]);
+        // ...until Docs is done too.
+        todos[1].mark_done().unwrap();
+        assert_eq!(
+            parents_to_complete(&todos, linux.id),
+            [build.id, release.id]
+        );
+        assert_eq!(progress(&todos, release.id).unwrap().percent(), 50);
+    }
+
+    #[test]
+    fn parents_cannot_form_cycles() {
+        let a = todo("A", None);
+        let b = todo("B", Some(&a));
+        let c = todo("C", Some(&b));
+        let todos = vec![a.clone(), b.clone(), c.clone()];
+        assert!(check_parent(&todos, c.id, a.id).is_ok());
+        assert!(check_parent(&todos, a.id, c.id).is_err());
+        assert!(check_parent(&todos, a.id, a.id).is_err());
+        assert!(check_parent(&todos, a.id, TodoId::new()).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! Delta-sync protocol between devices.
+//!
+//! Pure logic: callers load/save [`SyncState`] and the todos, ship
+//! [`SyncDelta`]s over any transport (files today), and apply what they receive.
+//!
+//! Protocol:
+//! 1. [`stamp_local_changes`] bumps this device's counter on every todo edited
+//!    since the last sync and records tombstones for todos deleted since then.
+//!    Local edits between two syncs therefore count as a single version step.
+//! 2. [`build_delta`] selects every todo/tombstone the peer hasn't seen yet,
+//!    judged by the last vector that peer reported to us.
+//! 3. [`apply_delta`] merges causally: strictly newer versions win; concurrent
+//!    versions merge field by field (see `domain::crdt`), so every replica
+//!    converges on the same result. Fields edited on both sides are returned as
+//!    conflicts, with the losing version, so nothing is dropped silently.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::domain::{
+    crdt::{self, Dot, FieldMerge, TodoField},
+    todo::{Todo, TodoId},
+    version::{Causality, VersionVector},
+};
+
+/// What a todo looked like at the last sync (used to detect local edits).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub version: VersionVector,
+    pub updated_at: OffsetDateTime,
+}
+
+/// Per-replica sync bookkeeping, persisted next to the database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncState {
+    /// This replica's id in version vectors.
+    pub device_id: String,
+    /// Everything this replica has seen.
+    #[serde(default)]
+    pub seen: VersionVector,
+    /// Last vector each peer reported, i.e. what that peer already has.
+    #[serde(default)]
+    pub peers: BTreeMap<String, VersionVector>,
+    #[serde(default)]
+    pub snapshot: BTreeMap<TodoId, SnapshotEntry>,
+    /// Deleted todos, kept so deletions propagate.
+    #[serde(default)]
+    pub tombstones: BTreeMap<TodoId, VersionVector>,
+}
+
+impl SyncState {
+    pub fn new(device_id: impl Into<String>) -> Self {
+        Self {
+            device_id: device_id.into(),
+            seen: VersionVector::new(),
+            peers: BTreeMap::new(),
+            snapshot: BTreeMap::new(),
+            tombstones: BTreeMap::new(),
+        }
+    }
+
+    fn refresh_snapshot(&mut self, todos: &[Todo]) {
+        self.snapshot = todos
+            .iter()
+            .map(|t| {
+                let entry = SnapshotEntry {
+                    version: t.version.clone(),
+                    updated_at: t.updated_at,
+                };
+                (t.id, entry)
+            })
+            .collect();
+    }
+}
+
+/// Changes sent from one replica to another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncDelta {
+    /// Sender's device id.
+    pub from: String,
+    /// Everything the sender has seen (becomes our view of that peer).
+    pub seen: VersionVector,
+    pub todos: Vec<Todo>,
+    pub tombstones: BTreeMap<TodoId, VersionVector>,
+}
+
+/// Concurrent edits to the same field(s) of one todo.
+///
+/// `kept` is what the replica now holds; `discarded` is the version whose
+/// values lost on `fields`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub kept: Todo,
+    pub discarded: Todo,
+    pub fields: Vec<TodoField>,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub inserted: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// Version local edits and deletions made since the last sync.
+///
+/// Returns how many todos were stamped.
+pub fn stamp_local_changes(state: &mut SyncState, todos: &mut [Todo]) -> usize {
+    let device = state.device_id.clone();
+    let mut stamped = 0;
+
+    for t in todos.iter_mut() {
+        let edited = match state.snapshot.get(&t.id) {
+            Some(s) => s.updated_at != t.updated_at || s.version != t.version,
+            None => true,
+        };
+        if edited {
+            t.version.increment(&device);
+            let dot = Dot {
+                device: device.clone(),
+                counter: t.version.get(&device),
+            };
+            // Fresh local edits carry no origin yet; received ones already do.
+            for stamp in t.field_stamps.values_mut() {
+                stamp.origin.get_or_insert_with(|| dot.clone());
+            }
+            stamped += 1;
+        }
+        state.seen.merge(&t.version);
+    }
+
+    let deleted: Vec<(TodoId, VersionVector)> = state
+        .snapshot
+        .iter()
+        .filter(|(id, _)| !todos.iter().any(|t| t.id == **id))
+        .map(|(id, s)| (*id, s.version.clone()))
+        .collect();
+
+    for (id, mut version) in deleted {
+        version.increment(&device);
+        state.seen.merge(&version);
+        state.tombstones.insert(id, version);
+        stamped += 1;
+    }
+
+    state.refresh_snapshot(todos);
+    stamped
+}
+
+/// Everything `peer` hasn't seen yet (everything, if the peer is unknown).
+pub fn build_delta(state: &SyncState, todos: &[Todo], peer: Option<&str>) -> SyncDelta {
+    let empty = VersionVector::new();
+    let peer_seen = peer.and_then(|p| state.peers.get(p)).unwrap_or(&empty);
+
+    SyncDelta {
+        from: state.device_id.clone(),
+        seen: state.seen.clone(),
+        todos: todos
+            .iter()
+            .filter(|t| !peer_seen.dominates(&t.version))
+            .cloned()
+            .collect(),
+        tombstones: state
+            .tombstones
+            .iter()
+            .filter(|(_, v)| !peer_seen.dominates(v))
+            .map(|(id, v)| (*id, v.clone()))
+            .collect(),
+    }
+}
+
+/// Merge a peer's delta into `todos`.
+///
+/// Call [`stamp_local_changes`] first so unsynced local edits are detected as
+/// concurrent rather than overwritten.
+pub fn apply_delta(state: &mut SyncState, todos: &mut Vec<Todo>, delta: SyncDelta) -> SyncReport {
+    let mut report = SyncReport::default();
+
+    for remote in delta.todos {
+        if let Some(tomb) = state.tombstones.get(&remote.id) {
+            if tomb.dominates(&remote.version) {
+                report.unchanged += 1;
+                continue;
+            }
+            // Edited elsewhere after (or concurrently with) our delete: the edit wins.
+            state.tombstones.remove(&remote.id);
+        }
+
+        let Some(local) = todos.iter_mut().find(|t| t.id == remote.id) else {
+            todos.push(remote);
+            report.inserted += 1;
+            continue;
+        };
+
+        match local.version.compare(&remote.version) {
+            Causality::Equal | Causality::After => report.unchanged += 1,
+            Causality::Before => {
+                *local = remote;
+                report.updated += 1;
+            }
+            Causality::Concurrent => {
+                let FieldMerge {
+                    mut merged,
+                    conflicting,
+                } = crdt::merge(local, &remote);
+                merged.version = local.version.clone();
+                merged.version.merge(&remote.version);
+
+                if let Some(&first) = conflicting.first() {
+                    let lost_remote =
+                        crdt::field_value(&merged, first) != crdt::field_value(&remote, first);
+                    let discarded = if lost_remote { remote } else { local.clone() };
+                    report.conflicts.push(SyncConflict {
+                        kept: merged.clone(),
+                        discarded,
+                        fields: conflicting,
+                    });
+                }
+
+                *local = merged;
+                report.updated += 1;
+            }
+        }
+    }
+
+    for (id, tomb) in delta.tombstones {
+        if let Some(pos) = todos.iter().position(|t| t.id == id) {
+            if !tomb.dominates(&todos[pos].version) {
+                // We have edits the deleting replica never saw; keep them.
+                continue;
+            }
+            todos.remove(pos);
+            report.deleted += 1;
+        }
+        state.tombstones.entry(id).or_default().merge(&tomb);
+    }
+
+    state.seen.merge(&delta.seen);
+    state.peers.insert(delta.from, delta.seen);
+    state.refresh_snapshot(todos);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{Priority, Title, TodoPatch};
+    use time::Duration;
+
+    fn replica(device: &str, todos: &[Todo]) -> (SyncState, Vec<Todo>) {
+        (SyncState::new(device), todos.to_vec())
+    }
+
+    /// Exchange deltas both ways between two replicas.
+    fn sync_pair(a: &mut (SyncState, Vec<Todo>), b: &mut (SyncState, Vec<Todo>)) {
+        stamp_local_changes(&mut a.0, &mut a.1);
+        stamp_local_changes(&mut b.0, &mut b.1);
+        let to_b = build_delta(&a.0, &a.1, Some(&b.0.device_id));
+        let to_a = build_delta(&b.0, &b.1, Some(&a.0.device_id));
+        apply_delta(&mut b.0, &mut b.1, to_b);
+        apply_delta(&mut a.0, &mut a.1, to_a);
+    }
+
+    #[test]
+    fn new_todos_propagate_both_ways() {
+        let mut a = replica("a", &[Todo::new(Title::parse("from a").unwrap())]);
+        let mut b = replica("b", &[Todo::new(Title::parse("from b").unwrap())]);
+
+        sync_pair(&mut a, &mut b);
+
+        assert_eq!(a.1.len(), 2);
+        assert_eq!(b.1.len(), 2);
+    }
+
+    #[test]
+    fn newer_edit_wins_without_conflict() {
+        let mut a = replica("a", &[Todo::new(Title::parse("v1").unwrap())]);
+        let mut b = replica("b", &[]);
+        sync_pair(&mut a, &mut b);
+
+        b.1[0].title = Title::parse("v2").unwrap();
+        b.1[0].updated_at += Duration::seconds(1);
+        sync_pair(&mut a, &mut b);
+
+        assert_eq!(a.1[0].title.as_str(), "v2");
+        assert_eq!(a.1[0].version, b.1[0].version);
+    }
+
+    fn retitle(todo: &mut Todo, title: &str) {
+        todo.apply_patch(TodoPatch {
+            title: Some(Title::parse(title).unwrap()),
+            ..TodoPatch::default()
+        });
+    }
+
+    #[test]
+    fn concurrent_edits_to_one_field_converge_and_report_conflict() {
+        let mut a = replica("a", &[Todo::new(Title::parse("v1").unwrap())]);
+        let mut b = replica("b", &[]);
+        sync_pair(&mut a, &mut b);
+
+        retitle(&mut a.1[0], "edit on a");
+        retitle(&mut b.1[0], "edit on b");
+        // Make b's edit unambiguously later.
+        b.1[0].field_stamps.get_mut(&TodoField::Title).unwrap().at += Duration::seconds(1);
+
+        stamp_local_changes(&mut a.0, &mut a.1);
+        stamp_local_changes(&mut b.0, &mut b.1);
+        let to_b = build_delta(&a.0, &a.1, None);
+        let to_a = build_delta(&b.0, &b.1, None);
+        let rb = apply_delta(&mut b.0, &mut b.1, to_b);
+        let ra = apply_delta(&mut a.0, &mut a.1, to_a);
+
+        assert_eq!(ra.conflicts.len(), 1);
+        assert_eq!(rb.conflicts.len(), 1);
+        assert_eq!(ra.conflicts[0].discarded.title.as_str(), "edit on a");
+        assert_eq!(ra.conflicts[0].fields, vec![TodoField::Title]);
+        assert_eq!(a.1[0].title.as_str(), "edit on b");
+        assert_eq!(b.1[0].title.as_str(), "edit on b");
+        assert_eq!(a.1[0].version, b.1[0].version);
+    }
+
+    #[test]
+    fn concurrent_edits_to_different_fields_merge_cleanly() {
+        let mut a = replica("a", &[Todo::new(Title::parse("v1").unwrap())]);
+        let mut b = replica("b", &[]);
+        sync_pair(&mut a, &mut b);
+
+        retitle(&mut a.1[0], "renamed on a");
+        b.1[0].apply_patch(TodoPatch {
+            priority: Some(Priority::P1),
+            ..TodoPatch::default()
+        });
+
+        stamp_local_changes(&mut a.0, &mut a.1);
+        stamp_local_changes(&mut b.0, &mut b.1);
+        let to_b = build_delta(&a.0, &a.1, None);
+        let to_a = build_delta(&b.0, &b.1, None);
+        let rb = apply_delta(&mut b.0, &mut b.1, to_b);
+        let ra = apply_delta(&mut a.0, &mut a.1, to_a);
+
+        assert!(ra.conflicts.is_empty() && rb.conflicts.is_empty());
+        for (_, todos) in [&a, &b] {
+            assert_eq!(todos[0].title.as_str(), "renamed on a");
+            assert_eq!(todos[0].priority, Priority::P1);
+        }
+    }
+
+    #[test]
+    fn deletes_propagate_but_lose_to_unseen_edits() {
+        let t1 = Todo::new(Title::parse("one").unwrap());
+        let t2 = Todo::new(Title::parse("two").unwrap());
+        let mut a = replica("a", &[t1.clone(), t2.clone()]);
+        let mut b = replica("b", &[]);
+        sync_pair(&mut a, &mut b);
+
+        // a deletes both; b concurrently edits t2.
+        a.1.clear();
+        let t2_b = b.1.iter_mut().find(|t| t.id == t2.id).unwrap();
+        t2_b.updated_at += Duration::seconds(1);
+        sync_pair(&mut a, &mut b);
+
+        assert!(!b.1.iter().any(|t| t.id == t1.id));
+        assert!(b.1.iter().any(|t| t.id == t2.id));
+        assert!(a.1.iter().any(|t| t.id == t2.id));
+    }
+
+    #[test]
+    fn delta_skips_what_peer_has_seen() {
+        let mut a = replica("a", &[Todo::new(Title::parse("x").unwrap())]);
+        let mut b = replica("b", &[]);
+        sync_pair(&mut a, &mut b);
+        sync_pair(&mut a, &mut b);
+
+        stamp_local_changes(&mut a.0, &mut a.1);
+        let delta = build_delta(&a.0, &a.1, Some("b"));
+        assert!(delta.todos.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+//! Todo templates with title placeholders (`add --template <name>`).
+//!
+//! Templates live in config.toml under `[templates.<name>]`. Their title and
+//! notes may contain placeholders that are filled in when a todo is created
+//! from them, so "Weekly report W{week}" becomes "Weekly report W07":
+//!
+//! - `{date}` - `2026-02-13`
+//! - `{year}`, `{month}`, `{day}` - `2026`, `02`, `13`
+//! - `{week}` - ISO week number, `07`
+//! - `{weekday}` - `Friday`
+//! - `{project}` - the new todo's project
+//!
+//! `{{` and `}}` stand for literal braces. Dates are UTC.
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TodoTemplate {
+    pub title: String,
+    pub notes: Option<String>,
+    pub project: Option<String>,
+    pub priority: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// Values available to placeholders.
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateVars<'a> {
+    pub now: OffsetDateTime,
+    pub project: &'a str,
+}
+
+/// Fill in the placeholders of `template`.
+pub fn expand(template: &str, vars: TemplateVars<'_>) -> Result<String, String> {
+    let date = vars.now.date();
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if let Some(after) = tail.strip_prefix("{{").or_else(|| tail.strip_prefix("}}")) {
+            out.push_str(&tail[..1]);
+            rest = after;
+            continue;
+        }
+        if tail.starts_with('}') {
+            return Err("unmatched } in template (write }} for a literal brace)".to_string());
+        }
+        let Some(end) = tail.find('}') else {
+            return Err("unclosed { in template (write {{ for a literal brace)".to_string());
+        };
+        let name = &tail[1..end];
+        let value = match name.trim().to_ascii_lowercase().as_str() {
+            "date" => date.to_string(),
+            "year" => date.year().to_string(),
+            "month" => format!("{:02}", u8::from(date.month())),
+            "day" => format!("{:02}", date.day()),
+            "week" => format!("{:02}", date.iso_week()),
+            "weekday" => date.weekday().to_string(),
+            "project" => vars.project.to_string(),
+            _ => {
+                return Err(format!(
+                    "unknown placeholder {{{name}}} (use date, year, month, day, week, weekday, project)"
+                ));
+            }
+        };
+        out.push_str(&value);
+        rest = &tail[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn fills_placeholders_and_keeps_escaped_braces() {
+        let vars = TemplateVars {
+            now: datetime!(2026-02-13 09:30 UTC),
+            project: "Acme",
+        };
+        assert_eq!(
+            expand("Weekly report W{week} ({project}, {weekday} {date})", vars).unwrap(),
+            "Weekly report W07 (Acme, Friday 2026-02-13)"
+        );
+        assert_eq!(
+            expand("{{literal}} {year}-{month}-{day}", vars).unwrap(),
+            "{literal} 2026-02-13"
+        );
+        assert!(expand("Report {quarter}", vars).is_err());
+        assert!(expand("Report {week", vars).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! Timesheets built from tracked time.
+//!
+//! One row per todo per day (time entries attributed to the UTC date they
+//! started). Durations are rounded per row, using the rounding configured for
+//! the todo's project.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+
+use crate::domain::todo::{Todo, TodoId, format_minutes};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    #[default]
+    Up,
+    Down,
+    Nearest,
+}
+
+/// Round durations to a multiple of `increment` minutes (0 = no rounding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rounding {
+    pub increment: u32,
+    pub mode: RoundingMode,
+}
+
+impl Rounding {
+    pub fn apply(self, minutes: u32) -> u32 {
+        let step = self.increment;
+        if step <= 1 {
+            return minutes;
+        }
+        let down = minutes / step * step;
+        let rest = minutes % step;
+        let up = if rest == 0 { down } else { down + step };
+        match self.mode {
+            RoundingMode::Up => up,
+            RoundingMode::Down => down,
+            RoundingMode::Nearest if rest * 2 >= step => up,
+            RoundingMode::Nearest => down,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimesheetRow {
+    pub date: Date,
+    pub todo: TodoId,
+    pub task: String,
+    pub project: String,
+    /// Tracked minutes before rounding.
+    pub raw_minutes: u32,
+    /// Billable minutes after rounding.
+    pub minutes: u32,
+    /// Time entry notes, joined with "; ".
+    pub notes: String,
+}
+
+/// Timesheet rows for finished time entries since `since`, by date then task.
+///
+/// `rounding` is asked for the rule of each row's project.
+pub fn build(
+    todos: &[Todo],
+    since: Option<OffsetDateTime>,
+    project: Option<&str>,
+    rounding: impl Fn(&str) -> Rounding,
+) -> Vec<TimesheetRow> {
+    let mut days: BTreeMap<(Date, String, TodoId), (u64, Vec<String>)> = BTreeMap::new();
+
+    for t in todos {
+        if project.is_some_and(|p| !t.project.as_str().eq_ignore_ascii_case(p.trim())) {
+            continue;
+        }
+        for e in &t.time_entries {
+            let Some(end) = e.end else { continue };
+            let millis = e.within(since, end).whole_milliseconds();
+            if millis <= 0 {
+                continue;
+            }
+
+            let date = since.map_or(e.start, |s| s.max(e.start)).date();
+            let slot = days
+                .entry((date, t.title.as_str().to_string(), t.id))
+                .or_default();
+            slot.0 += u64::try_from(millis).unwrap_or(u64::MAX);
+            if let Some(note) = e.note.as_ref().filter(|n| !n.trim().is_empty()) {
+                slot.1.push(note.trim().to_string());
+            }
+        }
+    }
+
+    let projects: BTreeMap<TodoId, &str> =
+        todos.iter().map(|t| (t.id, t.project.as_str())).collect();
+    days.into_iter()
+        .map(|((date, task, id), (millis, notes))| {
+            let project = projects[&id].to_string();
+            // Any started minute counts.
+            let raw_minutes = u32::try_from(millis.div_ceil(60_000)).unwrap_or(u32::MAX);
+            TimesheetRow {
+                date,
+                todo: id,
+                minutes: rounding(&project).apply(raw_minutes),
+                raw_minutes,
+                task,
+                project,
+                notes: notes.join("; "),
+            }
+        })
+        .collect()
+}
+
+/// `H:MM`, the usual timesheet duration notation.
+pub fn format_hm(minutes: u32) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Render rows as a Markdown table with a total line.
+pub fn to_markdown(rows: &[TimesheetRow]) -> String {
+    let mut md = String::from("| Date | Project | Task | Duration | Notes |\n");
+    md.push_str("|------|---------|------|---------:|-------|\n");
+    for r in rows {
+        md.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            r.date,
+            escape_md(&r.project),
+            escape_md(&r.task),
+            format_hm(r.minutes),
+            escape_md(&r.notes)
+        ));
+    }
+    let total: u32 = rows.iter().map(|r| r.minutes).sum();
+    md.push_str(&format!(
+        "| **Total** | | | **{}** | {} |\n",
+        format_hm(total),
+        format_minutes(total)
+    ));
+    md
+}
+
+fn escape_md(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        todo::{ProjectName, Title},
+        tracking::TimeEntry,
+    };
+    use time::Duration;
+
+    #[test]
+    fn rounding_modes() {
+        let r = |increment, mode| Rounding { increment, mode };
+        assert_eq!(r(15, RoundingMode::Up).apply(16), 30);
+        assert_eq!(r(15, RoundingMode::Down).apply(29), 15);
+        assert_eq!(r(15, RoundingMode::Nearest).apply(22), 15);
+        assert_eq!(r(15, RoundingMode::Nearest).apply(23), 30);
+        assert_eq!(r(0, RoundingMode::Up).apply(7), 7);
+    }
+
+    #[test]
+    fn rows_group_per_day_and_round_per_project() {
+        let day = OffsetDateTime::UNIX_EPOCH + Duration::days(10);
+        let mut t = Todo::new(Title::parse("Fix login").unwrap());
+        t.project = ProjectName::parse("Acme").unwrap();
+        for (from, mins, note) in [(9, 20, "triage"), (14, 25, "patch"), (24 + 9, 5, "")] {
+            let start = day + Duration::hours(from);
+            t.time_entries.push(TimeEntry {
+                start,
+                end: Some(start + Duration::minutes(mins)),
+                note: Some(note.to_string()),
+            });
+        }
+        t.time_entries
+            .push(TimeEntry::start(day + Duration::hours(40)));
+
+        let rounding = |p: &str| Rounding {
+            increment: if p == "Acme" { 15 } else { 0 },
+            mode: RoundingMode::Up,
+        };
+        let rows = build(&[t], None, Some("acme"), rounding);
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].raw_minutes, rows[0].minutes), (45, 45));
+        assert_eq!(rows[0].notes, "triage; patch");
+        assert_eq!((rows[1].raw_minutes, rows[1].minutes), (5, 15));
+        assert!(to_markdown(&rows).contains("| **Total** | | | **1:00** |"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Time zones for display and input (`timezone` in config.toml).
+//!
+//! Timestamps are stored as given (UTC unless typed with an offset); a
+//! [`Zone`] is only the lens `list`/`show` look through and the place bare
+//! wall-clock input like `2026-01-02 09:00` is read in.
+//!
+//! Named zones are [`jiff`] time zones; finding them (in the system's tz
+//! database, or the system's own zone) is left to the caller.
+
+use jiff::{Timestamp, tz::TimeZone};
+use time::{Duration, OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+/// A fixed offset or a zone's rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Zone {
+    Fixed(UtcOffset),
+    Rules(TimeZone),
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Self::Fixed(UtcOffset::UTC)
+    }
+}
+
+impl Zone {
+    /// The offset in effect at `at`.
+    pub fn offset_at(&self, at: OffsetDateTime) -> UtcOffset {
+        let tz = match self {
+            Self::Fixed(offset) => return *offset,
+            Self::Rules(tz) => tz,
+        };
+        // Clamped: an offset can carry `at` just past jiff's range.
+        let secs = at
+            .unix_timestamp()
+            .clamp(Timestamp::MIN.as_second(), Timestamp::MAX.as_second());
+        let at = Timestamp::from_second(secs).unwrap_or(Timestamp::UNIX_EPOCH);
+        UtcOffset::from_whole_seconds(tz.to_offset(at).seconds()).unwrap_or(UtcOffset::UTC)
+    }
+
+    /// `at` as the zone's wall clock shows it (as is, where that would fall
+    /// past the end of the supported range).
+    pub fn local(&self, at: OffsetDateTime) -> OffsetDateTime {
+        at.checked_to_offset(self.offset_at(at)).unwrap_or(at)
+    }
+
+    /// The instant the zone's clocks show `wall`. A time skipped by a
+    /// daylight-saving jump lands after it; a repeated one, on its first run.
+    pub fn resolve(&self, wall: PrimitiveDateTime) -> OffsetDateTime {
+        // Offsets change at most once within a couple of days.
+        // At the ends of the supported range one of the two may not exist.
+        let naive = wall.assume_utc();
+        let candidates: Vec<OffsetDateTime> = [
+            naive.checked_sub(Duration::DAY),
+            naive.checked_add(Duration::DAY),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|around| wall.assume_offset(self.offset_at(around)))
+        .collect();
+        let valid = candidates
+            .iter()
+            .filter(|c| self.offset_at(**c) == c.offset())
+            .min();
+        match valid.or(candidates.iter().max()) {
+            Some(c) => *c,
+            None => naive,
+        }
+    }
+
+    /// Read a POSIX TZ string such as `CET-1CEST,M3.5.0,M10.5.0/3`.
+    pub fn from_posix(spec: &str) -> Result<Self, String> {
+        TimeZone::posix(spec)
+            .map(Self::Rules)
+            .map_err(|e| format!("can't read TZ rule {spec:?}: {e}"))
+    }
+
+    /// `+02:00`, `-05:30`, `UTC` or `Z`.
+    pub fn parse_offset(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if input.eq_ignore_ascii_case("utc") || input.eq_ignore_ascii_case("z") {
+            return Some(Self::Fixed(UtcOffset::UTC));
+        }
+        let (sign, rest) = match input.as_bytes().first()? {
+            b'+' => (1, &input[1..]),
+            b'-' => (-1, &input[1..]),
+            _ => return None,
+        };
+        let (h, m) = rest.split_once(':').unwrap_or((rest, "0"));
+        let (h, m): (i8, i8) = (h.parse().ok()?, m.parse().ok()?);
+        let offset = UtcOffset::from_hms(sign * h, sign * m, 0).ok()?;
+        Some(Self::Fixed(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::{datetime, offset};
+
+    fn berlin() -> Zone {
+        Zone::from_posix("CET-1CEST,M3.5.0,M10.5.0/3").unwrap()
+    }
+
+    #[test]
+    fn posix_rules_switch_on_the_right_days() {
+        let z = berlin();
+        assert_eq!(z.offset_at(datetime!(2026-01-15 12:00 UTC)), offset!(+1));
+        assert_eq!(z.offset_at(datetime!(2026-07-15 12:00 UTC)), offset!(+2));
+        // 2026-03-29 01:00 UTC is 02:00 CET, when clocks jump to 03:00 CEST.
+        assert_eq!(z.offset_at(datetime!(2026-03-29 00:59 UTC)), offset!(+1));
+        assert_eq!(z.offset_at(datetime!(2026-03-29 01:00 UTC)), offset!(+2));
+        assert_eq!(z.offset_at(datetime!(2026-10-25 00:59 UTC)), offset!(+2));
+        assert_eq!(z.offset_at(datetime!(2026-10-25 01:00 UTC)), offset!(+1));
+
+        let sydney = Zone::from_posix("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(
+            sydney.offset_at(datetime!(2026-01-15 00:00 UTC)),
+            offset!(+11)
+        );
+        assert_eq!(
+            sydney.offset_at(datetime!(2026-07-15 00:00 UTC)),
+            offset!(+10)
+        );
+
+        let fixed = Zone::from_posix("<+0530>-5:30").unwrap();
+        assert_eq!(
+            fixed.offset_at(datetime!(2026-07-15 00:00 UTC)),
+            offset!(+5:30)
+        );
+        assert!(Zone::from_posix("nonsense").is_err());
+    }
+
+    #[test]
+    fn wall_times_resolve_through_jumps() {
+        let z = berlin();
+        assert_eq!(
+            z.resolve(datetime!(2026-07-01 09:00)),
+            datetime!(2026-07-01 09:00 +2)
+        );
+        assert_eq!(
+            z.resolve(datetime!(2026-01-02 09:00)),
+            datetime!(2026-01-02 09:00 +1)
+        );
+        // 02:30 doesn't exist on the spring-forward night; 02:30 happens
+        // twice on the fall-back one.
+        assert_eq!(
+            z.resolve(datetime!(2026-03-29 02:30))
+                .to_offset(UtcOffset::UTC),
+            datetime!(2026-03-29 01:30 UTC)
+        );
+        assert_eq!(
+            z.resolve(datetime!(2026-10-25 02:30)),
+            datetime!(2026-10-25 02:30 +2)
+        );
+        assert_eq!(
+            Zone::parse_offset("-05:30")
+                .unwrap()
+                .resolve(datetime!(2026-01-02 09:00)),
+            datetime!(2026-01-02 09:00 -5:30)
+        );
+    }
+
+    #[test]
+    fn wall_times_resolve_at_the_ends_of_the_range() {
+        let z = berlin();
+        assert_eq!(
+            z.resolve(datetime!(9999-12-31 00:00)),
+            datetime!(9999-12-31 00:00 +1)
+        );
+        assert_eq!(
+            z.resolve(datetime!(-9999-01-01 00:00)).date(),
+            datetime!(-9999-01-01 00:00).date()
+        );
+        let ahead = Zone::parse_offset("+14:00").unwrap();
+        let last = datetime!(9999-12-31 23:30 UTC);
+        assert_eq!(ahead.local(last), last);
+        let utc = Zone::parse_offset("UTC").unwrap();
+        assert_eq!(
+            utc.resolve(datetime!(-9999-01-01 00:00)),
+            datetime!(-9999-01-01 00:00 UTC)
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Soft validation: things worth pointing out about a change that don't
+//! stop it (errors do that).
+//!
+//! The service checks each todo it adds or edits and keeps the warnings
+//! until the caller takes them ([`TodoService::take_warnings`]). Only what
+//! the change touched is checked: editing the title of an overdue todo
+//! doesn't warn about its due date again.
+//!
+//! [`TodoService::take_warnings`]: crate::app::service::TodoService::take_warnings
+
+use std::fmt;
+
+use serde::{Serialize, Serializer, ser::SerializeStruct};
+use time::OffsetDateTime;
+
+use crate::domain::todo::{Todo, TodoId};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    /// An open todo was given a due date that has passed.
+    DueInPast,
+    /// The reminder comes after the due date.
+    RemindAfterDue,
+    /// A new tag has the name of a project (`--tag work` meant `--project work`?).
+    TagLooksLikeProject(String),
+}
+
+impl WarningKind {
+    /// Stable identifier for JSON output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            WarningKind::DueInPast => "due_in_past",
+            WarningKind::RemindAfterDue => "remind_after_due",
+            WarningKind::TagLooksLikeProject(_) => "tag_looks_like_project",
+        }
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WarningKind::DueInPast => f.write_str("due date is in the past"),
+            WarningKind::RemindAfterDue => f.write_str("reminder is after the due date"),
+            WarningKind::TagLooksLikeProject(tag) => {
+                write!(f, "tag #{tag} looks like a project (use --project?)")
+            }
+        }
+    }
+}
+
+/// A warning about one todo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub id: TodoId,
+    pub kind: WarningKind,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.id.short(), self.kind)
+    }
+}
+
+impl Serialize for Warning {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Warning", 3)?;
+        s.serialize_field("id", &self.id)?;
+        s.serialize_field("code", self.kind.code())?;
+        s.serialize_field("message", &self.kind.to_string())?;
+        s.end()
+    }
+}
+
+/// Warnings about `after`, which was `before` (`None` for a new todo).
+/// `projects` are the project names in use.
+pub fn check(
+    before: Option<&Todo>,
+    after: &Todo,
+    projects: &[String],
+    now: OffsetDateTime,
+) -> Vec<Warning> {
+    let mut kinds = Vec::new();
+    let due_changed = before.is_none_or(|b| b.due != after.due);
+    let remind_changed = before.is_none_or(|b| b.remind_at != after.remind_at);
+
+    if let Some(due) = after.due {
+        if due_changed && !after.status.is_done() && due.as_dt() < now {
+            kinds.push(WarningKind::DueInPast);
+        }
+        if (due_changed || remind_changed) && after.remind_at.is_some_and(|r| r > due) {
+            kinds.push(WarningKind::RemindAfterDue);
+        }
+    }
+    for tag in &after.tags {
+        let new = before.is_none_or(|b| !b.tags.contains(tag));
+        if new
+            && projects
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(tag.as_str()))
+        {
+            kinds.push(WarningKind::TagLooksLikeProject(tag.as_str().to_string()));
+        }
+    }
+
+    kinds
+        .into_iter()
+        .map(|kind| Warning { id: after.id, kind })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{DueAt, Tag, Title};
+    use time::macros::datetime;
+
+    #[test]
+    fn only_what_changed_is_checked() {
+        let now = datetime!(2026-05-01 12:00 UTC);
+        let projects = vec!["Work".to_string()];
+        let mut todo = Todo::new_at(Title::parse("Report").unwrap(), now);
+        todo.due = Some(DueAt::from_dt(datetime!(2026-04-30 09:00 UTC)));
+        todo.remind_at = Some(DueAt::from_dt(datetime!(2026-05-02 09:00 UTC)));
+        todo.tags.insert(Tag::parse("work").unwrap());
+
+        let kinds: Vec<_> = check(None, &todo, &projects, now)
+            .into_iter()
+            .map(|w| w.kind.code())
+            .collect();
+        assert_eq!(
+            kinds,
+            ["due_in_past", "remind_after_due", "tag_looks_like_project"]
+        );
+
+        let mut retitled = todo.clone();
+        retitled.title = Title::parse("Quarterly report").unwrap();
+        assert!(check(Some(&todo), &retitled, &projects, now).is_empty());
+
+        let mut done = todo.clone();
+        done.due = Some(DueAt::from_dt(datetime!(2026-04-29 09:00 UTC)));
+        done.mark_done_at(now).unwrap();
+        let warnings = check(Some(&todo), &done, &projects, now);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].to_string(),
+            format!("{}: reminder is after the due date", todo.id.short())
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Workflow states and the kanban board built from them (`board`, and the
+//! TUI's columns).
+//!
+//! Every board has an `open` column first and a `done` column last; in between
+//! come the workflow states of the projects shown ([`Project::workflow`]), in
+//! their configured order. A todo left in a state its project no longer lists
+//! still gets a column, after the configured ones, so nothing disappears.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    app::projects,
+    domain::{
+        project::Project,
+        todo::{Todo, WorkflowState},
+    },
+};
+
+/// Name of the column for open todos not in any state.
+pub const OPEN: &str = "open";
+/// Name of the column for finished todos.
+pub const DONE: &str = "done";
+
+/// States of project `name` (the default workflow if it has no record).
+pub fn states_for(all: &[Project], name: &str) -> Vec<WorkflowState> {
+    projects::find(all, name).map_or_else(|| vec![WorkflowState::in_progress()], Project::workflow)
+}
+
+/// The state `start` moves a todo of project `name` into.
+pub fn first_state(all: &[Project], name: &str) -> WorkflowState {
+    states_for(all, name)
+        .into_iter()
+        .next()
+        .unwrap_or_else(WorkflowState::in_progress)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub todos: Vec<Todo>,
+}
+
+/// Board columns for `todos` (someday items left out), in their given order
+/// within each column.
+pub fn columns(todos: &[Todo], all: &[Project]) -> Vec<Column> {
+    let column = |name: &str| Column {
+        name: name.to_string(),
+        todos: Vec::new(),
+    };
+    let mut states: Vec<WorkflowState> = Vec::new();
+    let mut seen = BTreeSet::new();
+    for t in todos {
+        if seen.insert(t.project.as_str().to_lowercase()) {
+            for s in states_for(all, t.project.as_str()) {
+                if !states.contains(&s) {
+                    states.push(s);
+                }
+            }
+        }
+    }
+    for s in todos.iter().filter_map(|t| t.state.as_ref()) {
+        if !states.contains(s) {
+            states.push(s.clone());
+        }
+    }
+
+    let mut board: Vec<Column> = std::iter::once(column(OPEN))
+        .chain(states.iter().map(|s| column(s.as_str())))
+        .chain(std::iter::once(column(DONE)))
+        .collect();
+    let last = board.len() - 1;
+    for t in todos.iter().filter(|t| !t.is_someday()) {
+        let at = if t.status.is_done() {
+            last
+        } else {
+            match &t.state {
+                None => 0,
+                Some(s) => 1 + states.iter().position(|x| x == s).unwrap_or_default(),
+            }
+        };
+        board[at].todos.push(t.clone());
+    }
+    board
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{ProjectName, Title};
+
+    fn todo(project: &str, state: Option<&str>) -> Todo {
+        let mut t = Todo::new(Title::parse("Task").unwrap());
+        t.project = ProjectName::parse(project).unwrap();
+        t.state = state.map(|s| WorkflowState::parse(s).unwrap());
+        t
+    }
+
+    #[test]
+    fn columns_follow_each_projects_workflow() {
+        let mut work = Project::new(ProjectName::parse("Work").unwrap());
+        work.states = ["doing", "review"]
+            .map(|s| WorkflowState::parse(s).unwrap())
+            .to_vec();
+        let mut done = todo("Home", None);
+        done.mark_done().unwrap();
+        let todos = vec![
+            todo("Work", Some("review")),
+            todo("Home", Some("in-progress")),
+            todo("Work", None),
+            // A state Work dropped since.
+            todo("Work", Some("blocked")),
+            done,
+        ];
+
+        let board = columns(&todos, std::slice::from_ref(&work));
+        let shape: Vec<_> = board
+            .iter()
+            .map(|c| (c.name.as_str(), c.todos.len()))
+            .collect();
+        assert_eq!(
+            shape,
+            [
+                ("open", 1),
+                ("doing", 0),
+                ("review", 1),
+                ("in-progress", 1),
+                ("blocked", 1),
+                ("done", 1)
+            ]
+        );
+        assert_eq!(first_state(&[work], "work").as_str(), "doing");
+        assert_eq!(first_state(&[], "Home").as_str(), "in-progress");
+    }
+
+    #[test]
+    fn open_and_done_are_reserved() {
+        assert!(WorkflowState::parse("Done").is_err());
+        assert!(WorkflowState::parse("in progress").is_err());
+        assert_eq!(WorkflowState::parse(" Review ").unwrap().as_str(), "review");
+    }
+}
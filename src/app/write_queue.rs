@@ -0,0 +1,260 @@
+//! Single-writer queue for servers that take mutations from many clients.
+//!
+//! Request handlers [`submit`](WriteQueue::submit) a [`Mutation`] and block
+//! until it is acknowledged. One writer thread owns the store: it drains
+//! whatever is queued, loads the store once, applies the batch in arrival
+//! order and persists it with a single save. Concurrent clients therefore
+//! never interleave partial writes, and saves never overlap.
+//!
+//! Each mutation gets its own acknowledgement: one that fails (say, marking a
+//! missing todo done) is rejected without holding back the rest of its batch.
+
+use std::{
+    fmt,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use anyhow::Result;
+
+use crate::{
+    app::{errors::AppError, repository::TodoRepository, store::Store},
+    domain::todo::{Todo, TodoId, TodoPatch},
+};
+
+/// Most mutations applied between two saves.
+pub const MAX_BATCH: usize = 64;
+
+/// A change a client asked for.
+#[derive(Debug, Clone)]
+pub enum Mutation {
+    Add(Box<Todo>),
+    Edit(TodoId, Box<TodoPatch>),
+    Done(TodoId),
+    Reopen(TodoId),
+    Delete(TodoId),
+}
+
+/// Why a mutation wasn't applied.
+#[derive(Debug)]
+pub enum WriteError {
+    /// The mutation doesn't fit the todos as they are; nothing was written.
+    Rejected(AppError),
+    /// Loading or saving the store failed; the whole batch was dropped.
+    Storage(String),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected(e) => write!(f, "{e}"),
+            Self::Storage(e) => write!(f, "storage failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for WriteError {}
+
+/// Outcome of one mutation: the todo it touched, once saved.
+pub type Ack = Result<TodoId, WriteError>;
+
+struct Job {
+    mutation: Mutation,
+    reply: Sender<Ack>,
+}
+
+/// Handle for submitting mutations; clone it into each request handler.
+#[derive(Clone)]
+pub struct WriteQueue {
+    jobs: Sender<Job>,
+}
+
+impl WriteQueue {
+    /// Start the writer thread. `load` is called once per batch, so edits made
+    /// by other processes in between are picked up; `save` once per batch
+    /// that changed anything.
+    pub fn spawn<R, L, S>(load: L, save: S) -> Self
+    where
+        R: TodoRepository + 'static,
+        L: FnMut() -> Result<Store<R>> + Send + 'static,
+        S: FnMut(&mut Store<R>) -> Result<()> + Send + 'static,
+    {
+        let (jobs, rx) = mpsc::channel();
+        thread::spawn(move || writer(rx, load, save));
+        Self { jobs }
+    }
+
+    /// Queue `mutation` and wait until it is saved or rejected.
+    pub fn submit(&self, mutation: Mutation) -> Ack {
+        let (reply, ack) = mpsc::channel();
+        let stopped = || WriteError::Storage("the writer stopped".to_string());
+        self.jobs
+            .send(Job { mutation, reply })
+            .map_err(|_| stopped())?;
+        ack.recv().map_err(|_| stopped())?
+    }
+}
+
+fn writer<R, L, S>(rx: Receiver<Job>, mut load: L, mut save: S)
+where
+    R: TodoRepository,
+    L: FnMut() -> Result<Store<R>>,
+    S: FnMut(&mut Store<R>) -> Result<()>,
+{
+    while let Ok(first) = rx.recv() {
+        let mut batch = vec![first];
+        while batch.len() < MAX_BATCH
+            && let Ok(job) = rx.try_recv()
+        {
+            batch.push(job);
+        }
+        let acks = apply_batch(&batch, &mut load, &mut save);
+        for (job, ack) in batch.into_iter().zip(acks) {
+            // The client may have hung up; its change is saved regardless.
+            let _ = job.reply.send(ack);
+        }
+    }
+}
+
+fn apply_batch<R, L, S>(batch: &[Job], load: &mut L, save: &mut S) -> Vec<Ack>
+where
+    R: TodoRepository,
+    L: FnMut() -> Result<Store<R>>,
+    S: FnMut(&mut Store<R>) -> Result<()>,
+{
+    let failed = |e: anyhow::Error| {
+        batch
+            .iter()
+            .map(|_| Err(WriteError::Storage(format!("{e:#}"))))
+            .collect()
+    };
+    let mut store = match load() {
+        Ok(store) => store,
+        Err(e) => return failed(e),
+    };
+    let acks: Vec<Ack> = batch
+        .iter()
+        .map(|job| apply(&mut store, &job.mutation))
+        .collect();
+    if acks.iter().any(Result::is_ok)
+        && let Err(e) = save(&mut store)
+    {
+        return failed(e);
+    }
+    acks
+}
+
+fn apply<R: TodoRepository>(store: &mut Store<R>, mutation: &Mutation) -> Ack {
+    let applied = match mutation {
+        Mutation::Add(todo) => store.insert_todo((**todo).clone()).map(|()| todo.id),
+        Mutation::Edit(id, patch) => match store.edit_todo(*id, (**patch).clone()) {
+            Ok(true) => Ok(*id),
+            Ok(false) => Err(AppError::TodoNotFound),
+            Err(e) => {
+                return Err(match e.downcast::<AppError>() {
+                    Ok(e) => WriteError::Rejected(e),
+                    Err(e) => WriteError::Storage(format!("{e:#}")),
+                });
+            }
+        },
+        Mutation::Done(id) => store.mark_done(*id).map(|()| *id),
+        Mutation::Reopen(id) => store.mark_open(*id).map(|()| *id),
+        Mutation::Delete(id) => store.delete(*id).map(|()| *id),
+    };
+    applied.map_err(WriteError::Rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{domain::todo::Title, infra::memory_repo::MemoryTodoRepository};
+
+    /// A "disk" the writer loads from and saves to, counting saves.
+    #[derive(Default)]
+    struct Disk {
+        todos: Vec<Todo>,
+        saves: usize,
+    }
+
+    fn queue(disk: &Arc<Mutex<Disk>>) -> WriteQueue {
+        let (from, to) = (Arc::clone(disk), Arc::clone(disk));
+        WriteQueue::spawn(
+            move || {
+                let mut store = Store::new(MemoryTodoRepository::new());
+                store.set_all(from.lock().unwrap().todos.clone())?;
+                Ok(store)
+            },
+            move |store: &mut Store<MemoryTodoRepository>| {
+                // A slow disk, so submissions pile up behind each save.
+                thread::sleep(Duration::from_millis(20));
+                let mut disk = to.lock().unwrap();
+                disk.todos = store.list_todos();
+                disk.saves += 1;
+                Ok(())
+            },
+        )
+    }
+
+    fn todo(title: &str) -> Todo {
+        Todo::new(Title::parse(title).unwrap())
+    }
+
+    #[test]
+    fn concurrent_submissions_are_all_saved_in_fewer_batches() {
+        let disk = Arc::new(Mutex::new(Disk::default()));
+        let queue = queue(&disk);
+
+        let clients: Vec<_> = (0..8)
+            .map(|client| {
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    (0..5)
+                        .map(|n| {
+                            queue.submit(Mutation::Add(Box::new(todo(&format!(
+                                "Task {client}-{n}"
+                            )))))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let acks: Vec<Ack> = clients
+            .into_iter()
+            .flat_map(|c| c.join().unwrap())
+            .collect();
+
+        assert!(acks.iter().all(Result::is_ok));
+        let disk = disk.lock().unwrap();
+        assert_eq!(disk.todos.len(), 40);
+        assert!(disk.saves < 40, "{} saves", disk.saves);
+    }
+
+    #[test]
+    fn a_rejected_mutation_does_not_hold_back_the_others() {
+        let disk = Arc::new(Mutex::new(Disk::default()));
+        let queue = queue(&disk);
+
+        let id = queue
+            .submit(Mutation::Add(Box::new(todo("Write report"))))
+            .unwrap();
+        assert!(matches!(
+            queue.submit(Mutation::Reopen(id)),
+            Err(WriteError::Rejected(AppError::AlreadyOpen))
+        ));
+        assert_eq!(queue.submit(Mutation::Done(id)).unwrap(), id);
+        assert!(matches!(
+            queue.submit(Mutation::Done(TodoId::new())),
+            Err(WriteError::Rejected(AppError::TodoNotFound))
+        ));
+
+        let disk = disk.lock().unwrap();
+        assert!(disk.todos[0].status.is_done());
+        // Rejected mutations alone don't cause a save.
+        assert_eq!(disk.saves, 2);
+    }
+}
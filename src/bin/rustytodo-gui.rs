@@ -0,0 +1,21 @@
+//! Desktop companion entry point (feature `gui`).
+//!
+//! Shares paths, config, and the database with the CLI binary.
+
+use anyhow::{Context, Result};
+use rustytodo::{
+    app::context::AppContext,
+    infra::{config::AppConfig, logfile, paths::AppPaths},
+};
+
+fn main() -> Result<()> {
+    let paths = AppPaths::detect()?;
+    std::fs::create_dir_all(&paths.config_dir)
+        .with_context(|| format!("failed creating config dir: {}", paths.config_dir.display()))?;
+    std::fs::create_dir_all(&paths.data_dir)
+        .with_context(|| format!("failed creating data dir: {}", paths.data_dir.display()))?;
+
+    let config = AppConfig::load_or_create(&paths)?;
+    logfile::init(tracing::Level::INFO, &paths.data_dir, &config.log);
+    rustytodo::ui::gui::run(AppContext::new(paths, config))
+}
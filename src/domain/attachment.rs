@@ -0,0 +1,123 @@
+//! Files and links attached to a todo.
+//!
+//! An [`Attachment`] is a label plus where the thing is: a URL, a file copied
+//! into the attachments folder in the data dir (stored relative to it, so
+//! the folder can move with the data), or a file left where it was.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::errors::DomainError;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Attachment {
+    pub label: String,
+    #[serde(flatten)]
+    pub target: AttachmentTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentTarget {
+    /// An http(s) link.
+    Url(String),
+    /// A copy in the attachments folder, relative to it.
+    File(PathBuf),
+    /// A file outside the data dir, linked where it is.
+    Path(PathBuf),
+}
+
+impl Attachment {
+    /// `label`, or the last part of the target if none is given.
+    pub fn new(label: Option<&str>, target: AttachmentTarget) -> Result<Self, DomainError> {
+        if let AttachmentTarget::Url(url) = &target {
+            let lower = url.to_ascii_lowercase();
+            if !(lower.starts_with("https://") || lower.starts_with("http://"))
+                || url.chars().any(char::is_whitespace)
+            {
+                return Err(DomainError::InvalidRefUrl);
+            }
+        }
+        let label = match label {
+            Some(label) => label.trim().to_string(),
+            None => target.name(),
+        };
+        if label.is_empty() || label.contains(['\n', '\r']) {
+            return Err(DomainError::InvalidAttachmentLabel);
+        }
+        Ok(Self { label, target })
+    }
+}
+
+impl AttachmentTarget {
+    /// The file name, or the URL without its scheme and trailing slash.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Url(url) => {
+                let rest = url.split_once("://").map_or(url.as_str(), |(_, r)| r);
+                rest.trim_end_matches('/').to_string()
+            }
+            Self::File(path) | Self::Path(path) => path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The attachment `which` names: its number in the list (from 1) or its
+/// label, ignoring case.
+pub fn find(attachments: &[Attachment], which: &str) -> Option<usize> {
+    let which = which.trim();
+    if let Ok(n) = which.parse::<usize>() {
+        return (1..=attachments.len()).contains(&n).then(|| n - 1);
+    }
+    attachments
+        .iter()
+        .position(|a| a.label.eq_ignore_ascii_case(which))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attachments_default_their_label_and_are_found_by_number_or_label() {
+        let spec = Attachment::new(
+            None,
+            AttachmentTarget::File(PathBuf::from("3f2a/spec v2.pdf")),
+        )
+        .unwrap();
+        assert_eq!(spec.label, "spec v2.pdf");
+        let board = Attachment::new(
+            Some("Board"),
+            AttachmentTarget::Url("https://trello.com/b/x/".into()),
+        )
+        .unwrap();
+        let link =
+            Attachment::new(None, AttachmentTarget::Url("https://example.com/a/".into())).unwrap();
+        assert_eq!(link.label, "example.com/a");
+        assert_eq!(
+            Attachment::new(None, AttachmentTarget::Url("ftp://x".into())),
+            Err(DomainError::InvalidRefUrl)
+        );
+        assert_eq!(
+            Attachment::new(Some(" "), AttachmentTarget::Path("/tmp/a".into())),
+            Err(DomainError::InvalidAttachmentLabel)
+        );
+
+        let list = [spec, board];
+        assert_eq!(find(&list, "2"), Some(1));
+        assert_eq!(find(&list, "board"), Some(1));
+        assert_eq!(find(&list, "3"), None);
+        assert_eq!(find(&list, "0"), None);
+
+        let json = serde_json::to_value(&list[1]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"label": "Board", "url": "https://trello.com/b/x/"})
+        );
+        assert_eq!(serde_json::from_value::<Attachment>(json).unwrap(), list[1]);
+    }
+}
@@ -0,0 +1,24 @@
+//! Where "now" comes from.
+//!
+//! Code that needs the current time takes it from a [`Clock`] (or as a `now`
+//! argument) instead of reading the system time itself, so tests, `--as-of`
+//! and replays can run against a time of their choosing. [`SystemClock`] is
+//! the real one; `testing::FixedClock` stands still until moved.
+
+use std::fmt::Debug;
+
+use time::OffsetDateTime;
+
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> OffsetDateTime;
+}
+
+/// The system time, in UTC.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
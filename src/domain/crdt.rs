@@ -0,0 +1,381 @@
+//! Field-level CRDT merge for todos.
+//!
+//! Every user-editable field is a last-writer-wins register: edits record a
+//! per-field timestamp, and merging two replicas takes each field from whichever
+//! side wrote it last. Two offline edits to *different* fields therefore both
+//! survive instead of one whole record winning.
+//!
+//! Each stamp may also carry the [`Dot`] (device + counter) of the sync step that
+//! published it, which lets a merge tell a genuine same-field conflict (both
+//! edits unseen by the other side) from one side simply being stale.
+
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use crate::domain::todo::Todo;
+
+/// User-editable todo fields tracked as LWW registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoField {
+    Title,
+    Notes,
+    Project,
+    Tags,
+    Status,
+    Priority,
+    Due,
+    Badge,
+    Color,
+    Estimate,
+    Energy,
+    DependsOn,
+    Parent,
+    TimeEntries,
+    Someday,
+    RemindAt,
+    NotifiedAt,
+    State,
+    Escalation,
+    ExternalRef,
+    Attachments,
+    Archived,
+}
+
+impl TodoField {
+    pub const ALL: [TodoField; 22] = [
+        TodoField::Title,
+        TodoField::Notes,
+        TodoField::Project,
+        TodoField::Tags,
+        TodoField::Status,
+        TodoField::Priority,
+        TodoField::Due,
+        TodoField::Badge,
+        TodoField::Color,
+        TodoField::Estimate,
+        TodoField::Energy,
+        TodoField::DependsOn,
+        TodoField::Parent,
+        TodoField::TimeEntries,
+        TodoField::Someday,
+        TodoField::RemindAt,
+        TodoField::NotifiedAt,
+        TodoField::State,
+        TodoField::Escalation,
+        TodoField::ExternalRef,
+        TodoField::Attachments,
+        TodoField::Archived,
+    ];
+
+    /// Parse a field name as printed by [`TodoField::name`].
+    pub fn parse(input: &str) -> Option<Self> {
+        let s = input.trim().to_ascii_lowercase();
+        Self::ALL.into_iter().find(|f| f.name() == s)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TodoField::Title => "title",
+            TodoField::Notes => "notes",
+            TodoField::Project => "project",
+            TodoField::Tags => "tags",
+            TodoField::Status => "status",
+            TodoField::Priority => "priority",
+            TodoField::Due => "due",
+            TodoField::Badge => "badge",
+            TodoField::Color => "color",
+            TodoField::Estimate => "estimate",
+            TodoField::Energy => "energy",
+            TodoField::DependsOn => "depends_on",
+            TodoField::Parent => "parent",
+            TodoField::TimeEntries => "time_entries",
+            TodoField::Someday => "someday",
+            TodoField::RemindAt => "remind_at",
+            TodoField::NotifiedAt => "notified_at",
+            TodoField::State => "state",
+            TodoField::Escalation => "escalation",
+            TodoField::ExternalRef => "external_ref",
+            TodoField::Attachments => "attachments",
+            TodoField::Archived => "archived",
+        }
+    }
+}
+
+/// The sync step (device, counter) that first published an edit.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Dot {
+    pub device: String,
+    pub counter: u64,
+}
+
+/// When a field was last written, and (once synced) by which sync step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldStamp {
+    pub at: OffsetDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Dot>,
+}
+
+impl FieldStamp {
+    pub fn at(at: OffsetDateTime) -> Self {
+        Self { at, origin: None }
+    }
+
+    /// Total order used for LWW: time first, then origin as a tie-breaker.
+    fn lww_cmp(&self, other: &FieldStamp) -> Ordering {
+        self.at
+            .cmp(&other.at)
+            .then_with(|| self.origin.cmp(&other.origin))
+    }
+}
+
+/// Per-field stamps; fields never edited after creation have no entry.
+pub type FieldStamps = BTreeMap<TodoField, FieldStamp>;
+
+/// Result of merging two concurrent versions of the same todo.
+#[derive(Debug, Clone)]
+pub struct FieldMerge {
+    pub merged: Todo,
+    /// Fields both sides edited without seeing the other's edit.
+    pub conflicting: Vec<TodoField>,
+}
+
+/// Current value of a field, as JSON (for tie-breaking and diffs).
+pub fn field_value(todo: &Todo, field: TodoField) -> Value {
+    let v = match field {
+        TodoField::Title => serde_json::to_value(&todo.title),
+        TodoField::Notes => serde_json::to_value(&todo.notes),
+        TodoField::Project => serde_json::to_value(&todo.project),
+        TodoField::Tags => serde_json::to_value(&todo.tags),
+        TodoField::Status => serde_json::to_value(todo.status),
+        TodoField::Priority => serde_json::to_value(todo.priority),
+        TodoField::Due => serde_json::to_value(todo.due),
+        TodoField::Badge => serde_json::to_value(&todo.badge),
+        TodoField::Color => serde_json::to_value(todo.color),
+        TodoField::Estimate => serde_json::to_value(todo.estimate),
+        TodoField::Energy => serde_json::to_value(todo.energy),
+        TodoField::DependsOn => serde_json::to_value(&todo.depends_on),
+        TodoField::Parent => serde_json::to_value(todo.parent),
+        TodoField::TimeEntries => serde_json::to_value(&todo.time_entries),
+        TodoField::Someday => serde_json::to_value(todo.someday),
+        TodoField::RemindAt => serde_json::to_value(todo.remind_at),
+        TodoField::NotifiedAt => serde_json::to_value(todo.notified_at),
+        TodoField::State => serde_json::to_value(&todo.state),
+        TodoField::Escalation => serde_json::to_value(&todo.escalation),
+        TodoField::ExternalRef => serde_json::to_value(&todo.external_ref),
+        TodoField::Attachments => serde_json::to_value(&todo.attachments),
+        TodoField::Archived => serde_json::to_value(todo.archived),
+    };
+    v.unwrap_or(Value::Null)
+}
+
+/// Copy one field (value and stamp) from `src` into `dst`.
+pub fn copy_field(dst: &mut Todo, src: &Todo, field: TodoField) {
+    match field {
+        TodoField::Title => dst.title = src.title.clone(),
+        TodoField::Notes => dst.notes = src.notes.clone(),
+        TodoField::Project => dst.project = src.project.clone(),
+        TodoField::Tags => dst.tags = src.tags.clone(),
+        TodoField::Status => dst.status = src.status,
+        TodoField::Priority => dst.priority = src.priority,
+        TodoField::Due => dst.due = src.due,
+        TodoField::Badge => dst.badge = src.badge.clone(),
+        TodoField::Color => dst.color = src.color,
+        TodoField::Estimate => dst.estimate = src.estimate,
+        TodoField::Energy => dst.energy = src.energy,
+        TodoField::DependsOn => dst.depends_on = src.depends_on.clone(),
+        TodoField::Parent => dst.parent = src.parent,
+        TodoField::TimeEntries => dst.time_entries = src.time_entries.clone(),
+        TodoField::Someday => dst.someday = src.someday,
+        TodoField::RemindAt => dst.remind_at = src.remind_at,
+        TodoField::NotifiedAt => dst.notified_at = src.notified_at,
+        TodoField::State => dst.state = src.state.clone(),
+        TodoField::Escalation => dst.escalation = src.escalation.clone(),
+        TodoField::ExternalRef => dst.external_ref = src.external_ref.clone(),
+        TodoField::Attachments => dst.attachments = src.attachments.clone(),
+        TodoField::Archived => dst.archived = src.archived,
+    }
+    match src.field_stamps.get(&field) {
+        Some(stamp) => dst.field_stamps.insert(field, stamp.clone()),
+        None => dst.field_stamps.remove(&field),
+    };
+}
+
+/// Fields whose values differ between `a` and `b`.
+pub fn diff_fields(a: &Todo, b: &Todo) -> Vec<TodoField> {
+    TodoField::ALL
+        .into_iter()
+        .filter(|&f| field_value(a, f) != field_value(b, f))
+        .collect()
+}
+
+/// Take `field` from `src` as a fresh local edit at `now`.
+///
+/// Unlike [`copy_field`], the stamp is renewed so the choice wins future merges.
+pub fn adopt_field(dst: &mut Todo, src: &Todo, field: TodoField, now: OffsetDateTime) {
+    copy_field(dst, src, field);
+    dst.field_stamps.insert(field, FieldStamp::at(now));
+    dst.updated_at = now;
+}
+
+/// Merge two versions of the same todo field by field.
+///
+/// Symmetric: `merge(a, b)` and `merge(b, a)` produce the same todo. The
+/// version vector of the result is left to the caller.
+pub fn merge(a: &Todo, b: &Todo) -> FieldMerge {
+    let mut merged = a.clone();
+    let mut conflicting = Vec::new();
+
+    for field in TodoField::ALL {
+        let va = field_value(a, field);
+        let vb = field_value(b, field);
+        if va == vb {
+            continue;
+        }
+
+        let sa = a.field_stamp(field);
+        let sb = b.field_stamp(field);
+        let b_wins = match sb.lww_cmp(&sa) {
+            // Same stamp, different values: fall back to a content order.
+            Ordering::Equal => {
+                let (ja, jb) = (va.to_string(), vb.to_string());
+                jb > ja
+            }
+            ord => ord.is_gt(),
+        };
+        if b_wins {
+            copy_field(&mut merged, b, field);
+        }
+
+        if unseen_by(&sa, b) && unseen_by(&sb, a) {
+            conflicting.push(field);
+        }
+    }
+
+    merged.created_at = a.created_at.min(b.created_at);
+    merged.updated_at = a.updated_at.max(b.updated_at);
+
+    FieldMerge {
+        merged,
+        conflicting,
+    }
+}
+
+/// True if `stamp` records an edit that `other` hasn't synced yet.
+fn unseen_by(stamp: &FieldStamp, other: &Todo) -> bool {
+    stamp
+        .origin
+        .as_ref()
+        .is_some_and(|dot| other.version.get(&dot.device) < dot.counter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{Priority, Title, TodoPatch};
+    use time::Duration;
+
+    fn publish(todo: &mut Todo, device: &str) {
+        todo.version.increment(device);
+        let counter = todo.version.get(device);
+        for stamp in todo.field_stamps.values_mut() {
+            stamp.origin.get_or_insert(Dot {
+                device: device.to_string(),
+                counter,
+            });
+        }
+    }
+
+    #[test]
+    fn edits_to_different_fields_both_survive() {
+        let base = Todo::new(Title::parse("Base").unwrap());
+
+        let mut a = base.clone();
+        a.apply_patch(TodoPatch {
+            title: Some(Title::parse("Renamed").unwrap()),
+            ..TodoPatch::default()
+        });
+        publish(&mut a, "a");
+
+        let mut b = base.clone();
+        b.apply_patch(TodoPatch {
+            priority: Some(Priority::P1),
+            ..TodoPatch::default()
+        });
+        publish(&mut b, "b");
+
+        let m = merge(&a, &b);
+        assert_eq!(m.merged.title.as_str(), "Renamed");
+        assert_eq!(m.merged.priority, Priority::P1);
+        assert!(m.conflicting.is_empty());
+    }
+
+    #[test]
+    fn same_field_edit_is_lww_and_reported() {
+        let base = Todo::new(Title::parse("Base").unwrap());
+        let later = base.created_at + Duration::minutes(5);
+
+        let mut a = base.clone();
+        a.title = Title::parse("From A").unwrap();
+        a.field_stamps.insert(
+            TodoField::Title,
+            FieldStamp::at(base.created_at + Duration::minutes(1)),
+        );
+        publish(&mut a, "a");
+
+        let mut b = base.clone();
+        b.title = Title::parse("From B").unwrap();
+        b.field_stamps
+            .insert(TodoField::Title, FieldStamp::at(later));
+        publish(&mut b, "b");
+
+        let ab = merge(&a, &b);
+        let ba = merge(&b, &a);
+        assert_eq!(ab.merged.title.as_str(), "From B");
+        assert_eq!(ba.merged.title.as_str(), "From B");
+        assert_eq!(ab.conflicting, vec![TodoField::Title]);
+    }
+
+    #[test]
+    fn adopt_field_renews_stamp() {
+        let local = Todo::new(Title::parse("Local").unwrap());
+        let other = Todo::new(Title::parse("Other").unwrap());
+        assert_eq!(diff_fields(&local, &other), vec![TodoField::Title]);
+
+        let mut merged = local.clone();
+        let now = local.created_at + Duration::hours(1);
+        adopt_field(&mut merged, &other, TodoField::Title, now);
+        assert_eq!(merged.title.as_str(), "Other");
+        assert_eq!(merged.field_stamp(TodoField::Title).at, now);
+        assert!(diff_fields(&merged, &other).is_empty());
+    }
+
+    #[test]
+    fn stale_value_is_not_a_conflict() {
+        let mut a = Todo::new(Title::parse("Old").unwrap());
+        publish(&mut a, "a");
+        let b_base = a.clone();
+
+        a.apply_patch(TodoPatch {
+            title: Some(Title::parse("New").unwrap()),
+            ..TodoPatch::default()
+        });
+        publish(&mut a, "a");
+
+        let mut b = b_base;
+        b.apply_patch(TodoPatch {
+            priority: Some(Priority::P2),
+            ..TodoPatch::default()
+        });
+        publish(&mut b, "b");
+
+        let m = merge(&a, &b);
+        assert_eq!(m.merged.title.as_str(), "New");
+        assert_eq!(m.merged.priority, Priority::P2);
+        assert!(m.conflicting.is_empty());
+    }
+}
@@ -6,6 +6,9 @@ pub enum DomainError {
     #[error("todo title cannot be empty")]
     EmptyTitle,
 
+    #[error("todo title is too long (max {max} characters)")]
+    TitleTooLong { max: usize },
+
     #[error("notes are too long (max {max} chars)")]
     NotesTooLong { max: usize },
 
@@ -24,12 +27,68 @@ pub enum DomainError {
     #[error("due datetime must be RFC3339, e.g. 2026-01-02T09:00:00Z")]
     InvalidDueAt,
 
+    #[error("badge must be 1-{max} characters without spaces (e.g. an emoji)")]
+    InvalidBadge { max: usize },
+
+    #[error("color must be one of red, orange, yellow, green, blue, purple, gray")]
+    InvalidColor,
+
+    #[error("energy must be one of low, medium, high")]
+    InvalidEnergy,
+
+    #[error("source must be one of cli, tui, gui, api, email or import:<file>")]
+    InvalidSource,
+
+    #[error("estimate must be a duration like 45m, 2h, 1h30m or 1d")]
+    InvalidEstimate,
+
     #[error("cannot mark as dome: already done")]
     AlreadyDone,
 
     #[error("cannot mark as open: already open")]
     AlreadyOpen,
 
+    #[error("a timer is already running for this todo")]
+    TimerRunning,
+
+    #[error("no timer is running for this todo")]
+    TimerNotRunning,
+
+    #[error("todo is not on the someday list")]
+    NotSomeday,
+
+    #[error("todo is not done")]
+    NotDone,
+
+    #[error("todo is not archived")]
+    NotArchived,
+
+    #[error("state must be a-z, 0-9, '-' or '_' and not open or done (e.g. in-progress)")]
+    InvalidState,
+
+    #[error(
+        "escalation must be steps like -1d, -1h, due, +2h and one every 30m, comma-separated (or off)"
+    )]
+    InvalidEscalation,
+
+    #[error("reference must be provider:key, e.g. jira:ABC-123 or github:owner/repo#12")]
+    InvalidExternalRef,
+
+    #[error("link must be an http(s) URL")]
+    InvalidRefUrl,
+
+    #[error("attachment label must be one non-empty line")]
+    InvalidAttachmentLabel,
+
     #[error("invalid todo id (expected UUID)")]
     InvalidTodoId,
+
+    #[error("routine name cannot be empty")]
+    EmptyRoutineName,
+
+    #[error("a routine needs at least one step")]
+    NoRoutineSteps,
+
+    #[error("no step {0:?} in this routine (use its number or text)")]
+    UnknownStep(String),
 }
@@ -0,0 +1,230 @@
+//! Reminder escalation chains.
+//!
+//! An [`Escalation`] lists when `notify` alerts about a todo relative to its
+//! due time: `-1d, -1h, every 30m` alerts a day before, an hour before, and
+//! then every half hour while it is overdue (the alert at the due time itself
+//! always fires). Chains are configured per priority in config.toml and can be
+//! overridden per todo; an empty chain (`off`) leaves only the due alert.
+
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+use super::errors::DomainError;
+
+/// Steps further than this from the due time (about ten years) are refused.
+const MAX_MINUTES: i64 = 10 * 366 * 24 * 60;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Escalation {
+    /// Minutes from the due time, negative before it; sorted.
+    offsets: Vec<i64>,
+    /// Repeat interval in minutes once overdue.
+    every: Option<i64>,
+}
+
+impl Escalation {
+    /// Parse comma-separated steps: offsets like `-1d`, `-2h`, `-30m` or
+    /// `+1h` (`due` for the due time), and at most one `every <duration>`.
+    /// `off` (or nothing) is the empty chain.
+    pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
+        Self::parse_within(input.as_ref(), MAX_MINUTES)
+    }
+
+    /// [`Escalation::parse`] with steps up to `max` minutes from the due time.
+    fn parse_within(input: &str, max: i64) -> Result<Self, DomainError> {
+        let input = input.trim();
+        let mut chain = Escalation::default();
+        if input.eq_ignore_ascii_case("off") {
+            return Ok(chain);
+        }
+        for step in input.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let lower = step.to_ascii_lowercase();
+            if let Some(every) = lower.strip_prefix("every") {
+                let minutes = minutes(every.trim()).filter(|m| (1..=max).contains(m));
+                if chain.every.is_some() || minutes.is_none() {
+                    return Err(DomainError::InvalidEscalation);
+                }
+                chain.every = minutes;
+                continue;
+            }
+            let offset = match lower.as_str() {
+                "due" | "0" => 0,
+                s => {
+                    let (sign, rest) = match (s.strip_prefix('-'), s.strip_prefix('+')) {
+                        (Some(rest), _) => (-1, rest),
+                        (None, Some(rest)) => (1, rest),
+                        (None, None) => return Err(DomainError::InvalidEscalation),
+                    };
+                    sign * minutes(rest)
+                        .filter(|m| *m <= max)
+                        .ok_or(DomainError::InvalidEscalation)?
+                }
+            };
+            if !chain.offsets.contains(&offset) {
+                chain.offsets.push(offset);
+            }
+        }
+        chain.offsets.sort_unstable();
+        Ok(chain)
+    }
+
+    pub fn is_off(&self) -> bool {
+        self.offsets.is_empty() && self.every.is_none()
+    }
+
+    /// The latest alert time of the chain at or before `now` for a todo due
+    /// at `due`, if any has come.
+    pub fn last_trigger(&self, due: OffsetDateTime, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        let step = self
+            .offsets
+            .iter()
+            .filter_map(|m| due.checked_add(Duration::minutes(*m)))
+            .filter(|at| *at <= now)
+            .max();
+        let repeat = self.every.filter(|_| now > due).and_then(|every| {
+            let overdue = (now - due).whole_minutes();
+            due.checked_add(Duration::minutes(overdue / every * every))
+        });
+        step.max(repeat)
+    }
+}
+
+/// Minutes in `2d`, `1h30m`, `45m` (a day is 24 hours here).
+fn minutes(s: &str) -> Option<i64> {
+    let mut total: i64 = 0;
+    let mut digits = String::new();
+    for c in s.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            'w' => 7 * 24 * 60,
+            'd' => 24 * 60,
+            'h' => 60,
+            'm' => 1,
+            _ => return None,
+        };
+        total = total.checked_add(digits.parse::<i64>().ok()?.checked_mul(unit)?)?;
+        digits.clear();
+    }
+    (digits.is_empty() && !s.is_empty()).then_some(total)
+}
+
+/// `1d`, `1h30m`: the inverse of [`minutes`], ignoring the sign.
+fn format_minutes(m: i64) -> String {
+    let mut rest = m.abs();
+    let mut out = String::new();
+    for (unit, size) in [("d", 24 * 60), ("h", 60), ("m", 1)] {
+        if rest >= size {
+            out.push_str(&format!("{}{unit}", rest / size));
+            rest %= size;
+        }
+    }
+    out
+}
+
+impl std::fmt::Display for Escalation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_off() {
+            return f.write_str("off");
+        }
+        let mut steps: Vec<String> = self
+            .offsets
+            .iter()
+            .map(|&m| match m {
+                0 => "due".to_string(),
+                m if m < 0 => format!("-{}", format_minutes(m)),
+                m => format!("+{}", format_minutes(m)),
+            })
+            .collect();
+        if let Some(every) = self.every {
+            steps.push(format!("every {}", format_minutes(every)));
+        }
+        f.write_str(&steps.join(", "))
+    }
+}
+
+impl From<Escalation> for String {
+    fn from(e: Escalation) -> Self {
+        e.to_string()
+    }
+}
+
+impl TryFrom<String> for Escalation {
+    type Error = DomainError;
+
+    /// Stored chains aren't held to the ten-year limit, so a database saved
+    /// before it still loads; [`Escalation::last_trigger`] skips steps that
+    /// don't fit in a date.
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Escalation::parse_within(&s, i64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn chains_parse_and_print_canonically() {
+        let chain = Escalation::parse("every 30m, -1h, -1d, due, -60m").unwrap();
+        assert_eq!(chain.to_string(), "-1d, -1h, due, every 30m");
+        assert_eq!(Escalation::parse("+1h30m").unwrap().to_string(), "+1h30m");
+        assert!(Escalation::parse("OFF").unwrap().is_off());
+        for bad in [
+            "1d",
+            "-1x",
+            "every 0m",
+            "every 1h, every 2h",
+            "-",
+            "-99999999999999m",
+            "every 99999999999999m",
+        ] {
+            assert_eq!(
+                Escalation::parse(bad),
+                Err(DomainError::InvalidEscalation),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn last_trigger_steps_then_repeats_while_overdue() {
+        let due = datetime!(2026-03-10 12:00 UTC);
+        let chain = Escalation::parse("-1d, -1h, every 30m").unwrap();
+        let at = |now| chain.last_trigger(due, now);
+        assert_eq!(at(datetime!(2026-03-09 11:00 UTC)), None);
+        assert_eq!(
+            at(datetime!(2026-03-10 09:00 UTC)),
+            Some(datetime!(2026-03-09 12:00 UTC))
+        );
+        assert_eq!(
+            at(datetime!(2026-03-10 11:59 UTC)),
+            Some(datetime!(2026-03-10 11:00 UTC))
+        );
+        assert_eq!(
+            at(datetime!(2026-03-10 13:10 UTC)),
+            Some(datetime!(2026-03-10 13:00 UTC))
+        );
+        assert_eq!(
+            Escalation::default().last_trigger(due, datetime!(2026-03-11 0:00 UTC)),
+            None
+        );
+
+        // Steps past the end of the date range never come, and repeats work
+        // across the whole range.
+        let end = datetime!(9999-12-31 12:00 UTC);
+        let late = Escalation::parse("-1h, +520w").unwrap();
+        assert_eq!(late.last_trigger(end, end), Some(end - Duration::hours(1)));
+        let every = Escalation::parse("every 1m").unwrap();
+        assert_eq!(
+            every.last_trigger(datetime!(-9999-01-01 0:00 UTC), end),
+            Some(end)
+        );
+        let stored: Escalation = serde_json::from_str("\"-99999999999999m\"").unwrap();
+        assert_eq!(stored.last_trigger(end, end), None);
+    }
+}
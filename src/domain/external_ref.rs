@@ -0,0 +1,156 @@
+//! Links from a todo to an item in another system (an issue tracker, a
+//! Todoist task, ...).
+//!
+//! An [`ExternalRef`] is written `provider:key`, e.g. `jira:ABC-123`. The pair
+//! identifies the item, so importers and sync integrations use it to find the
+//! todo they created earlier. The link to open it is stored alongside: given
+//! explicitly, from a `{key}` template in config.toml, or built in for a few
+//! providers whose URLs follow from the key.
+
+use serde::{Deserialize, Serialize};
+
+use super::errors::DomainError;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ExternalRef {
+    /// Lowercase provider name: `jira`, `github`, `todoist`, ...
+    pub provider: String,
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl ExternalRef {
+    /// Parse `provider:key`, without a link yet.
+    pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
+        let (provider, key) = input
+            .as_ref()
+            .trim()
+            .split_once(':')
+            .ok_or(DomainError::InvalidExternalRef)?;
+        let provider = provider.trim().to_ascii_lowercase();
+        let key = key.trim();
+        let valid_provider = !provider.is_empty()
+            && provider
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_provider || key.is_empty() || key.chars().any(char::is_whitespace) {
+            return Err(DomainError::InvalidExternalRef);
+        }
+        Ok(Self {
+            provider,
+            key: key.to_string(),
+            url: None,
+        })
+    }
+
+    /// Set the link explicitly.
+    pub fn with_url(mut self, url: impl AsRef<str>) -> Result<Self, DomainError> {
+        let url = url.as_ref().trim();
+        let lower = url.to_ascii_lowercase();
+        if !(lower.starts_with("https://") || lower.starts_with("http://"))
+            || url.chars().any(char::is_whitespace)
+        {
+            return Err(DomainError::InvalidRefUrl);
+        }
+        self.url = Some(url.to_string());
+        Ok(self)
+    }
+
+    /// Fill in the link from `template` (with `{key}` standing for the key),
+    /// else from the built-in pattern for the provider, if any. A link that
+    /// is already set stays.
+    pub fn resolve_url(mut self, template: Option<&str>) -> Self {
+        if self.url.is_none() {
+            self.url = match template {
+                Some(t) => Some(t.replace("{key}", &self.key)),
+                None => self.builtin_url(),
+            };
+        }
+        self
+    }
+
+    fn builtin_url(&self) -> Option<String> {
+        match self.provider.as_str() {
+            // owner/repo#12
+            "github" | "gitlab" => {
+                let (repo, number) = self.key.split_once('#')?;
+                let host = format!("https://{}.com", self.provider);
+                Some(match self.provider.as_str() {
+                    "github" => format!("{host}/{repo}/issues/{number}"),
+                    _ => format!("{host}/{repo}/-/issues/{number}"),
+                })
+            }
+            "todoist" => Some(format!("https://app.todoist.com/app/task/{}", self.key)),
+            _ => None,
+        }
+    }
+
+    /// Same provider and key (the link doesn't matter).
+    pub fn same_item(&self, other: &ExternalRef) -> bool {
+        self.provider == other.provider && self.key == other.key
+    }
+
+    /// Does `filter` (`provider` or `provider:key`, case-insensitive) match?
+    pub fn matches(&self, filter: &str) -> bool {
+        match filter.split_once(':') {
+            Some((provider, key)) => {
+                self.provider.eq_ignore_ascii_case(provider) && self.key.eq_ignore_ascii_case(key)
+            }
+            None => self.provider.eq_ignore_ascii_case(filter),
+        }
+    }
+}
+
+impl std::fmt::Display for ExternalRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.provider, self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refs_parse_and_find_their_links() {
+        let jira = ExternalRef::parse(" Jira:ABC-123 ").unwrap();
+        assert_eq!(jira.to_string(), "jira:ABC-123");
+        assert_eq!(jira.clone().resolve_url(None).url, None);
+        let linked = jira.resolve_url(Some("https://acme.atlassian.net/browse/{key}"));
+        assert_eq!(
+            linked.url.as_deref(),
+            Some("https://acme.atlassian.net/browse/ABC-123")
+        );
+
+        let gh = ExternalRef::parse("github:rust-lang/rust#1").unwrap();
+        assert_eq!(
+            gh.resolve_url(None).url.as_deref(),
+            Some("https://github.com/rust-lang/rust/issues/1")
+        );
+        let explicit = ExternalRef::parse("linear:ENG-7")
+            .unwrap()
+            .with_url("https://linear.app/acme/issue/ENG-7")
+            .unwrap();
+        assert!(
+            explicit
+                .clone()
+                .resolve_url(Some("x"))
+                .url
+                .unwrap()
+                .contains("linear.app")
+        );
+        assert!(explicit.matches("LINEAR") && explicit.matches("linear:eng-7"));
+        assert!(!explicit.matches("jira"));
+
+        for bad in ["ABC-123", ":x", "jira:", "ji ra:x", "jira:A B"] {
+            assert_eq!(
+                ExternalRef::parse(bad),
+                Err(DomainError::InvalidExternalRef),
+                "{bad}"
+            );
+        }
+        let any = ExternalRef::parse("a:b").unwrap();
+        assert_eq!(any.with_url("ftp://x"), Err(DomainError::InvalidRefUrl));
+    }
+}
@@ -2,5 +2,15 @@
 //!
 //! No IO, no CLI, no persistence.
 
+pub mod attachment;
+pub mod clock;
+pub mod crdt;
 pub mod errors;
+pub mod escalation;
+pub mod external_ref;
+pub mod project;
+pub mod routine;
+pub mod text;
 pub mod todo;
+pub mod tracking;
+pub mod version;
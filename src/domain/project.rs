@@ -0,0 +1,53 @@
+//! Projects as first-class records.
+//!
+//! Todos name their project with a [`ProjectName`]; a [`Project`] adds the
+//! metadata that belongs to the project itself. A name without a record is
+//! still a valid project (records are created on first edit), and names
+//! match case-insensitively, as in `list --project`.
+
+use serde::{Deserialize, Serialize};
+
+use super::todo::{Color, ProjectName, WorkflowState};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Project {
+    pub name: ProjectName,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    /// Archived projects are left out of `project list` unless asked for.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+    /// Workflow states between open and done, in board order (see
+    /// [`Project::workflow`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub states: Vec<WorkflowState>,
+}
+
+impl Project {
+    pub fn new(name: ProjectName) -> Self {
+        Self {
+            name,
+            description: None,
+            color: None,
+            archived: false,
+            states: Vec::new(),
+        }
+    }
+
+    /// The states todos move through here: the configured ones, or just
+    /// `in-progress`.
+    pub fn workflow(&self) -> Vec<WorkflowState> {
+        if self.states.is_empty() {
+            vec![WorkflowState::in_progress()]
+        } else {
+            self.states.clone()
+        }
+    }
+
+    /// Does this record belong to the project called `name`?
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.as_str().eq_ignore_ascii_case(name.trim())
+    }
+}
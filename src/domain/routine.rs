@@ -0,0 +1,247 @@
+//! Routines: named checklists done once a day.
+//!
+//! A [`Routine`] is a list of steps ("Morning routine": stretch, water,
+//! journal). Starting it on a day opens that day's run; ticking off every step
+//! completes the day. Runs remember the step texts rather than positions, so
+//! reordering or rewording steps later doesn't rewrite history, and a day
+//! counts as complete only against the steps the routine has now.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use super::errors::DomainError;
+
+time::serde::format_description!(iso_date, Date, "[year]-[month]-[day]");
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Routine {
+    pub name: String,
+    pub steps: Vec<String>,
+    /// Started days, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runs: Vec<RoutineRun>,
+}
+
+/// One day's pass through a routine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutineRun {
+    #[serde(with = "iso_date")]
+    pub date: Date,
+    /// Texts of the steps ticked off that day.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub done: BTreeSet<String>,
+}
+
+/// Streak figures for `routine show` / `routine list`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Streak {
+    /// Complete days in a row up to today (or yesterday, while today is
+    /// still in progress).
+    pub current: u32,
+    pub best: u32,
+    /// Complete days overall.
+    pub completed: u32,
+}
+
+impl Routine {
+    pub fn new(name: &str, steps: Vec<String>) -> Result<Self, DomainError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DomainError::EmptyRoutineName);
+        }
+        let steps: Vec<String> = steps
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if steps.is_empty() {
+            return Err(DomainError::NoRoutineSteps);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            steps,
+            runs: Vec::new(),
+        })
+    }
+
+    /// Does `name` pick this routine? The full name, or the start of any of
+    /// its words ("morning" for "Morning routine"), ignoring case.
+    pub fn answers_to(&self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        let own = self.name.to_lowercase();
+        !name.is_empty() && (own == name || own.split_whitespace().any(|w| w.starts_with(&name)))
+    }
+
+    pub fn run(&self, date: Date) -> Option<&RoutineRun> {
+        self.runs.iter().find(|r| r.date == date)
+    }
+
+    /// Open `date`'s run (keeping it if already started).
+    pub fn start(&mut self, date: Date) -> &mut RoutineRun {
+        let at = match self.runs.binary_search_by_key(&date, |r| r.date) {
+            Ok(at) => at,
+            Err(at) => {
+                self.runs.insert(
+                    at,
+                    RoutineRun {
+                        date,
+                        done: BTreeSet::new(),
+                    },
+                );
+                at
+            }
+        };
+        &mut self.runs[at]
+    }
+
+    /// The step `input` names: its 1-based number or its text (ignoring case).
+    pub fn step(&self, input: &str) -> Result<&str, DomainError> {
+        let input = input.trim();
+        let found = match input.parse::<usize>() {
+            Ok(n) => n.checked_sub(1).and_then(|i| self.steps.get(i)),
+            Err(_) => self.steps.iter().find(|s| s.eq_ignore_ascii_case(input)),
+        };
+        found
+            .map(String::as_str)
+            .ok_or_else(|| DomainError::UnknownStep(input.to_string()))
+    }
+
+    /// Tick off (or with `done == false`, untick) a step on `date`, starting
+    /// the day if needed.
+    pub fn check(&mut self, date: Date, step: &str, done: bool) -> Result<(), DomainError> {
+        let step = self.step(step)?.to_string();
+        let run = self.start(date);
+        if done {
+            run.done.insert(step);
+        } else {
+            run.done.remove(&step);
+        }
+        Ok(())
+    }
+
+    /// Were all current steps done on `date`?
+    pub fn is_complete(&self, date: Date) -> bool {
+        self.run(date)
+            .is_some_and(|r| self.steps.iter().all(|s| r.done.contains(s)))
+    }
+
+    pub fn streak(&self, today: Date) -> Streak {
+        let complete: Vec<Date> = self
+            .runs
+            .iter()
+            .map(|r| r.date)
+            .filter(|d| *d <= today && self.is_complete(*d))
+            .collect();
+
+        let mut streak = Streak {
+            completed: complete.len() as u32,
+            ..Streak::default()
+        };
+        let mut run = 0;
+        let mut prev: Option<Date> = None;
+        for &d in &complete {
+            run = match prev {
+                Some(p) if p.next_day() == Some(d) => run + 1,
+                _ => 1,
+            };
+            streak.best = streak.best.max(run);
+            prev = Some(d);
+        }
+        if let Some(last) = prev
+            && (last == today || last.next_day() == Some(today))
+        {
+            streak.current = run;
+        }
+        streak
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    fn morning() -> Routine {
+        Routine::new(
+            "Morning routine",
+            vec![
+                "Stretch".into(),
+                " Water ".into(),
+                "".into(),
+                "Journal".into(),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn new_validates_and_names_match_by_word() {
+        let r = morning();
+        assert_eq!(r.steps, ["Stretch", "Water", "Journal"]);
+        assert!(r.answers_to("morning") && r.answers_to("ROUT") && r.answers_to("morning routine"));
+        assert!(!r.answers_to("evening") && !r.answers_to(""));
+        assert_eq!(
+            Routine::new(" ", vec!["x".into()]),
+            Err(DomainError::EmptyRoutineName)
+        );
+        assert_eq!(
+            Routine::new("Evening", vec![" ".into()]),
+            Err(DomainError::NoRoutineSteps)
+        );
+    }
+
+    #[test]
+    fn steps_are_checked_by_number_or_text() {
+        let mut r = morning();
+        let day = date!(2026 - 03 - 10);
+        r.check(day, "1", true).unwrap();
+        r.check(day, "water", true).unwrap();
+        assert!(!r.is_complete(day));
+        assert_eq!(
+            r.check(day, "4", true),
+            Err(DomainError::UnknownStep("4".into()))
+        );
+        r.check(day, "Journal", true).unwrap();
+        assert!(r.is_complete(day));
+        r.check(day, "2", false).unwrap();
+        assert!(!r.is_complete(day));
+    }
+
+    #[test]
+    fn streaks_count_consecutive_complete_days() {
+        let mut r = morning();
+        let complete = |r: &mut Routine, d: Date| {
+            for s in ["1", "2", "3"] {
+                r.check(d, s, true).unwrap();
+            }
+        };
+        for d in [
+            date!(2026 - 03 - 01),
+            date!(2026 - 03 - 02),
+            date!(2026 - 03 - 03),
+            date!(2026 - 03 - 05),
+            date!(2026 - 03 - 06),
+        ] {
+            complete(&mut r, d);
+        }
+        // Today started but unfinished: the streak still runs to yesterday.
+        r.start(date!(2026 - 03 - 07));
+
+        let today = date!(2026 - 03 - 07);
+        assert_eq!(
+            r.streak(today),
+            Streak {
+                current: 2,
+                best: 3,
+                completed: 5
+            }
+        );
+        assert_eq!(r.streak(date!(2026 - 03 - 09)).current, 0);
+
+        // A new step makes earlier days incomplete against today's list.
+        r.steps.push("Meditate".into());
+        assert_eq!(r.streak(today).completed, 0);
+    }
+}
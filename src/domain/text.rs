@@ -0,0 +1,164 @@
+//! User-perceived characters and display width, for limiting and truncating
+//! text without slicing emoji apart.
+//!
+//! A grapheme here is a base character with what attaches to it: combining
+//! marks, variation selectors, skin tones, tag characters, keycaps, anything
+//! joined by a zero-width joiner, and regional indicators in pairs (flags).
+//! That covers emoji sequences and the common combining marks; it is not the
+//! full Unicode segmentation algorithm.
+
+use std::borrow::Cow;
+
+const ZWJ: char = '\u{200D}';
+
+/// Attaches to the character before it.
+fn is_extend(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{0483}'..='\u{0489}'
+        | '\u{0591}'..='\u{05BD}'
+        | '\u{0610}'..='\u{061A}'
+        | '\u{064B}'..='\u{065F}'
+        | '\u{0900}'..='\u{0903}'
+        | '\u{093A}'..='\u{094F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200C}'
+        | ZWJ
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}'
+        | '\u{E0100}'..='\u{E01EF}')
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c, '\u{1F1E6}'..='\u{1F1FF}')
+}
+
+/// Takes two terminal columns.
+fn is_wide(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{115F}'
+        | '\u{231A}'..='\u{231B}'
+        | '\u{23E9}'..='\u{23EC}'
+        | '\u{2E80}'..='\u{303E}'
+        | '\u{3041}'..='\u{A4CF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FE30}'..='\u{FE4F}'
+        | '\u{FF00}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE6}'
+        | '\u{1F1E6}'..='\u{1F1FF}'
+        | '\u{1F300}'..='\u{1F64F}'
+        | '\u{1F680}'..='\u{1F6FF}'
+        | '\u{1F900}'..='\u{1F9FF}'
+        | '\u{1FA70}'..='\u{1FAFF}'
+        | '\u{20000}'..='\u{3FFFD}')
+}
+
+/// The graphemes of `s`, in order.
+pub fn graphemes(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let mut chars = rest.char_indices().peekable();
+        let (_, first) = chars.next()?;
+        let mut end = first.len_utf8();
+        let mut joined = false;
+        let mut pairable = is_regional_indicator(first);
+        while let Some(&(i, c)) = chars.peek() {
+            let attaches = is_extend(c)
+                || joined
+                || (pairable && is_regional_indicator(c))
+                || (first == '\r' && c == '\n' && i == 1);
+            if !attaches {
+                break;
+            }
+            pairable = false;
+            joined = c == ZWJ;
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        let (grapheme, tail) = rest.split_at(end);
+        rest = tail;
+        Some(grapheme)
+    })
+}
+
+/// Terminal columns for one grapheme: 2 for wide characters and emoji
+/// shown as emoji, 0 for control characters, otherwise 1.
+fn grapheme_width(g: &str) -> usize {
+    let mut chars = g.chars();
+    let Some(first) = chars.next() else {
+        return 0;
+    };
+    if first.is_control() {
+        0
+    } else if is_wide(first) || g.contains('\u{FE0F}') {
+        2
+    } else {
+        1
+    }
+}
+
+/// Terminal columns taken by `s`.
+pub fn width(s: &str) -> usize {
+    graphemes(s).map(grapheme_width).sum()
+}
+
+/// `s` cut to at most `max` columns, ending in `…` if anything was cut.
+/// Never splits a grapheme.
+pub fn truncate(s: &str, max: usize) -> Cow<'_, str> {
+    if width(s) <= max {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for g in graphemes(s) {
+        let w = grapheme_width(g);
+        if used + w + 1 > max {
+            break;
+        }
+        out.push_str(g);
+        used += w;
+    }
+    if max > 0 {
+        out.push('…');
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emoji_sequences_stay_whole() {
+        let family = "👨\u{200D}👩\u{200D}👧";
+        let thumbs = "👍🏽";
+        let flag = "🇳🇵";
+        let accent = "e\u{0301}";
+        let s = format!("{family}{thumbs}{flag}{flag}{accent}x");
+        let parts: Vec<_> = graphemes(&s).collect();
+        assert_eq!(parts, [family, thumbs, flag, flag, accent, "x"]);
+        assert_eq!(graphemes("a\r\nb").count(), 3);
+        assert_eq!(width(&s), 2 + 2 + 2 + 2 + 1 + 1);
+        assert_eq!(width("日本"), 4);
+        assert_eq!(width("❤\u{FE0F}"), 2);
+    }
+
+    #[test]
+    fn truncation_fits_the_width() {
+        assert_eq!(truncate("Short", 10), "Short");
+        assert!(matches!(truncate("Short", 5), Cow::Borrowed(_)));
+        assert_eq!(truncate("Buy milk today", 8), "Buy mil…");
+        assert_eq!(truncate("🎉🎉🎉 party", 6), "🎉🎉…");
+        assert_eq!(truncate("🎉🎉🎉 party", 5), "🎉🎉…");
+        assert_eq!(
+            truncate("👨\u{200D}👩\u{200D}👧 family", 3),
+            "👨\u{200D}👩\u{200D}👧…"
+        );
+        assert_eq!(truncate("abc", 0), "");
+    }
+}
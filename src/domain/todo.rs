@@ -2,18 +2,28 @@
 //!
 //! This is intentionally minimal for now.
 
+use std::borrow::Cow;
 use std::collections::BTreeSet;
 
 use serde::{Deserialize, Serialize};
 use time::{OffsetDateTime, format_description::well_known::Rfc3339};
 use uuid::Uuid;
 
-use crate::domain::errors::DomainError;
+use crate::domain::{
+    attachment::Attachment,
+    crdt::{FieldStamp, FieldStamps, TodoField},
+    errors::DomainError,
+    escalation::Escalation,
+    external_ref::ExternalRef,
+    text,
+    tracking::TimeEntry,
+    version::VersionVector,
+};
 
 /// Strongly-typed identifier for a Todo.
 ///
 /// Newtype pattern prevents mixing IDs accidentally.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct TodoId(Uuid);
 
 impl TodoId {
@@ -45,24 +55,68 @@ impl TodoId {
     }
 }
 
+impl Default for TodoId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How new titles are cleaned up and limited (`[titles]` in config.toml).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TitleRules {
+    /// Most characters (graphemes, so an emoji sequence counts once); 0 for
+    /// no limit.
+    pub max_len: usize,
+    /// Turn runs of whitespace, including newlines, into one space.
+    pub collapse_whitespace: bool,
+}
+
+impl Default for TitleRules {
+    fn default() -> Self {
+        Self {
+            max_len: 500,
+            collapse_whitespace: true,
+        }
+    }
+}
+
 /// Avalidated todo title.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Title(String);
 
 impl Title {
-    // Parse and validate a title
+    /// Parse and validate a title under the default [`TitleRules`].
     pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
+        Self::parse_with(input, &TitleRules::default())
+    }
+
+    pub fn parse_with(input: impl AsRef<str>, rules: &TitleRules) -> Result<Self, DomainError> {
         let trimmed = input.as_ref().trim();
         if trimmed.is_empty() {
             return Err(DomainError::EmptyTitle);
         }
+        let title = if rules.collapse_whitespace {
+            trimmed.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else {
+            trimmed.to_string()
+        };
+        if rules.max_len > 0 && text::graphemes(&title).count() > rules.max_len {
+            return Err(DomainError::TitleTooLong { max: rules.max_len });
+        }
 
-        Ok(Self(trimmed.to_string()))
+        Ok(Self(title))
     }
 
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// The title in at most `width` terminal columns, ending in `…` if cut.
+    /// Emoji and other multi-codepoint characters are never split.
+    pub fn truncated(&self, width: usize) -> Cow<'_, str> {
+        text::truncate(&self.0, width)
+    }
 }
 
 /// Notes (optional, validated).
@@ -70,7 +124,7 @@ impl Title {
 pub struct Notes(String);
 
 impl Notes {
-    const MAX_LEN: usize = 10_000;
+    pub const MAX_LEN: usize = 10_000;
 
     pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
         let s = input.as_ref().trim().to_string();
@@ -137,6 +191,35 @@ impl Tag {
     }
 }
 
+/// A workflow state between open and done, such as `in-progress` or
+/// `review` (a kanban column). Normalized like tags; `open` and `done` are the
+/// fixed columns on either side and can't be used.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct WorkflowState(String);
+
+impl WorkflowState {
+    pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
+        let normalized = input.as_ref().trim().to_ascii_lowercase();
+        let ok = !normalized.is_empty()
+            && normalized
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !ok || normalized == "open" || normalized == "done" {
+            return Err(DomainError::InvalidState);
+        }
+        Ok(Self(normalized))
+    }
+
+    /// The state `start` moves a todo into when its project has no others.
+    pub fn in_progress() -> Self {
+        Self("in-progress".to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 /// Priority level.
 ///
 /// P1 is highest urgency; P4 is lowest.
@@ -172,6 +255,197 @@ impl Priority {
     }
 }
 
+/// Short visual marker shown before the title (an emoji or a few characters).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Badge(String);
+
+impl Badge {
+    pub const MAX_CHARS: usize = 8;
+
+    pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
+        let trimmed = input.as_ref().trim();
+        if trimmed.is_empty()
+            || trimmed.chars().count() > Self::MAX_CHARS
+            || trimmed.chars().any(char::is_whitespace)
+        {
+            return Err(DomainError::InvalidBadge {
+                max: Self::MAX_CHARS,
+            });
+        }
+        Ok(Self(trimmed.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Highlight color for a todo, for visual scanning beyond priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Gray,
+}
+
+impl Color {
+    pub const ALL: [Color; 7] = [
+        Color::Red,
+        Color::Orange,
+        Color::Yellow,
+        Color::Green,
+        Color::Blue,
+        Color::Purple,
+        Color::Gray,
+    ];
+
+    pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
+        let s = input.as_ref().trim().to_ascii_lowercase();
+        let s = if s == "grey" { "gray".to_string() } else { s };
+        Self::ALL
+            .into_iter()
+            .find(|c| c.label() == s)
+            .ok_or(DomainError::InvalidColor)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Color::Red => "red",
+            Color::Orange => "orange",
+            Color::Yellow => "yellow",
+            Color::Green => "green",
+            Color::Blue => "blue",
+            Color::Purple => "purple",
+            Color::Gray => "gray",
+        }
+    }
+
+    /// RGB value for graphical frontends.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Red => (220, 50, 47),
+            Color::Orange => (230, 120, 20),
+            Color::Yellow => (200, 170, 0),
+            Color::Green => (60, 170, 60),
+            Color::Blue => (40, 120, 220),
+            Color::Purple => (150, 80, 200),
+            Color::Gray => (140, 140, 140),
+        }
+    }
+}
+
+/// How much energy a todo takes, for picking work that fits the moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Energy {
+    Low,
+    Medium,
+    High,
+}
+
+impl Energy {
+    pub const ALL: [Energy; 3] = [Energy::Low, Energy::Medium, Energy::High];
+
+    pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
+        let s = input.as_ref().trim().to_ascii_lowercase();
+        let s = if s == "med" { "medium" } else { s.as_str() };
+        Self::ALL
+            .into_iter()
+            .find(|e| e.label() == s)
+            .ok_or(DomainError::InvalidEnergy)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Energy::Low => "low",
+            Energy::Medium => "medium",
+            Energy::High => "high",
+        }
+    }
+}
+
+/// Where a todo was created, for auditing integrations.
+///
+/// Stored as a short string: `cli`, `tui`, `gui`, `api`, `email` or
+/// `import:<file>`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Source {
+    Cli,
+    Tui,
+    Gui,
+    Api,
+    Email,
+    /// Imported from the named file.
+    Import(String),
+}
+
+impl Source {
+    pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
+        let s = input.as_ref().trim();
+        if let Some((kind, file)) = s.split_once(':')
+            && kind.eq_ignore_ascii_case("import")
+            && !file.trim().is_empty()
+        {
+            return Ok(Source::Import(file.trim().to_string()));
+        }
+        match s.to_ascii_lowercase().as_str() {
+            "cli" => Ok(Source::Cli),
+            "tui" => Ok(Source::Tui),
+            "gui" => Ok(Source::Gui),
+            "api" => Ok(Source::Api),
+            "email" => Ok(Source::Email),
+            _ => Err(DomainError::InvalidSource),
+        }
+    }
+
+    /// Kind without the file name (`import` for any import).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Source::Cli => "cli",
+            Source::Tui => "tui",
+            Source::Gui => "gui",
+            Source::Api => "api",
+            Source::Email => "email",
+            Source::Import(_) => "import",
+        }
+    }
+
+    /// Does this match a `--source` filter? A bare kind matches any file.
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.trim();
+        self.kind().eq_ignore_ascii_case(filter) || Source::parse(filter).is_ok_and(|f| f == *self)
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Import(file) => write!(f, "import:{file}"),
+            other => f.write_str(other.kind()),
+        }
+    }
+}
+
+impl From<Source> for String {
+    fn from(s: Source) -> Self {
+        s.to_string()
+    }
+}
+
+impl TryFrom<String> for Source {
+    type Error = DomainError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Source::parse(s)
+    }
+}
+
 /// Due datetime (UTC for now).
 ///
 /// We store this as an `OffsetDateTime`. For now we treat input as RFC3339.
@@ -203,6 +477,71 @@ impl DueAt {
     }
 }
 
+/// Estimated effort, in minutes.
+///
+/// Parsed from durations like `45m`, `2h`, `1h30m` or `1d` (a day is 8 working
+/// hours); a bare number means minutes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Estimate(u32);
+
+impl Estimate {
+    pub const MINUTES_PER_DAY: u32 = 8 * 60;
+
+    pub fn parse(input: impl AsRef<str>) -> Result<Self, DomainError> {
+        let s = input.as_ref().trim().to_ascii_lowercase();
+        if let Ok(minutes) = s.parse::<u32>() {
+            return Ok(Self(minutes));
+        }
+
+        let mut total: u32 = 0;
+        let mut digits = String::new();
+        for c in s.chars().filter(|c| !c.is_whitespace()) {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                continue;
+            }
+            let unit = match c {
+                'd' => Self::MINUTES_PER_DAY,
+                'h' => 60,
+                'm' => 1,
+                _ => return Err(DomainError::InvalidEstimate),
+            };
+            let n: u32 = digits.parse().map_err(|_| DomainError::InvalidEstimate)?;
+            total = n
+                .checked_mul(unit)
+                .and_then(|m| total.checked_add(m))
+                .ok_or(DomainError::InvalidEstimate)?;
+            digits.clear();
+        }
+        if !digits.is_empty() || s.is_empty() {
+            return Err(DomainError::InvalidEstimate);
+        }
+        Ok(Self(total))
+    }
+
+    pub fn from_minutes(minutes: u32) -> Self {
+        Self(minutes)
+    }
+
+    pub fn minutes(self) -> u32 {
+        self.0
+    }
+
+    /// Compact display, e.g. `1h 30m`.
+    pub fn label(self) -> String {
+        format_minutes(self.0)
+    }
+}
+
+/// Format a minute count as `2h`, `45m` or `1h 30m`.
+pub fn format_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
 /// Todo status.
 ///
 /// If Done, we record when it was completed (UTC).
@@ -219,7 +558,7 @@ impl Status {
 }
 
 /// Core Todo entity
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Todo {
     pub id: TodoId,
     pub title: Title,
@@ -229,8 +568,64 @@ pub struct Todo {
     pub status: Status,
     pub priority: Priority,
     pub due: Option<DueAt>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub badge: Option<Badge>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    /// Estimated effort.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<Estimate>,
+    /// Energy needed to work on it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy: Option<Energy>,
+    /// Todos that must be done before this one.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub depends_on: BTreeSet<TodoId>,
+    /// The todo this is a subtask of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<TodoId>,
+    /// Tracked work (see `domain::tracking`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub time_entries: Vec<TimeEntry>,
+    /// Where it was created (unknown for todos predating this field).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Source>,
+    /// Parked on the someday/maybe list since this time (or since it was last
+    /// kept there during `review`). Hidden from lists, `next` and the board.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub someday: Option<OffsetDateTime>,
+    /// When `notify` should send a reminder, independent of the due date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remind_at: Option<DueAt>,
+    /// When `notify` last sent a notification for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notified_at: Option<OffsetDateTime>,
+    /// Reminder escalation for this todo, instead of its priority's chain
+    /// from config.toml (see [`Escalation`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation: Option<Escalation>,
+    /// Workflow state while open (see [`WorkflowState`]); none means not
+    /// started. Cleared when it is done.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<WorkflowState>,
+    /// The item in another system this todo tracks (see [`ExternalRef`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_ref: Option<ExternalRef>,
+    /// Files and links attached to it (see [`Attachment`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// Moved to the archive at this time, some while after it was done.
+    /// Hidden from lists; reopening it brings it back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived: Option<OffsetDateTime>,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
+    /// Sync version (see `domain::version`). Empty until the todo is first synced.
+    #[serde(default, skip_serializing_if = "VersionVector::is_empty")]
+    pub version: VersionVector,
+    /// Last-write stamps per field, for field-level merges (see `domain::crdt`).
+    #[serde(default, skip_serializing_if = "FieldStamps::is_empty")]
+    pub field_stamps: FieldStamps,
 }
 
 impl Todo {
@@ -242,7 +637,11 @@ impl Todo {
     /// - tags empty
     /// - notes None
     pub fn new(title: Title) -> Self {
-        let now = OffsetDateTime::now_utc();
+        Self::new_at(title, OffsetDateTime::now_utc())
+    }
+
+    /// Like [`Todo::new`], created at `now`.
+    pub fn new_at(title: Title, now: OffsetDateTime) -> Self {
         Self {
             id: TodoId::new(),
             title,
@@ -252,18 +651,46 @@ impl Todo {
             status: Status::Open,
             priority: Priority::default(),
             due: None,
+            badge: None,
+            color: None,
+            estimate: None,
+            energy: None,
+            depends_on: BTreeSet::new(),
+            parent: None,
+            time_entries: Vec::new(),
+            source: None,
+            someday: None,
+            remind_at: None,
+            notified_at: None,
+            escalation: None,
+            state: None,
+            external_ref: None,
+            attachments: Vec::new(),
+            archived: None,
             created_at: now,
             updated_at: now,
+            version: VersionVector::new(),
+            field_stamps: FieldStamps::new(),
         }
     }
 
     /// Mark done, if currently open.
     pub fn mark_done(&mut self) -> Result<(), DomainError> {
+        self.mark_done_at(OffsetDateTime::now_utc())
+    }
+
+    /// Mark done at `now`, if currently open.
+    pub fn mark_done_at(&mut self, now: OffsetDateTime) -> Result<(), DomainError> {
         match self.status {
             Status::Open => {
-                let now = OffsetDateTime::now_utc();
                 self.status = Status::Done { completed_at: now };
-                self.updated_at = now;
+                self.touch(TodoField::Status, now);
+                if self.someday.take().is_some() {
+                    self.touch(TodoField::Someday, now);
+                }
+                if self.state.take().is_some() {
+                    self.touch(TodoField::State, now);
+                }
                 Ok(())
             }
             Status::Done { .. } => Err(DomainError::AlreadyDone),
@@ -272,28 +699,137 @@ impl Todo {
 
     /// Mark open/undone, if currently done.
     pub fn mark_open(&mut self) -> Result<(), DomainError> {
+        self.mark_open_at(OffsetDateTime::now_utc())
+    }
+
+    /// Mark open/undone at `now`, if currently done.
+    pub fn mark_open_at(&mut self, now: OffsetDateTime) -> Result<(), DomainError> {
         match self.status {
             Status::Done { .. } => {
-                let now = OffsetDateTime::now_utc();
                 self.status = Status::Open;
-                self.updated_at = now;
+                self.touch(TodoField::Status, now);
+                if self.archived.take().is_some() {
+                    self.touch(TodoField::Archived, now);
+                }
                 Ok(())
             }
             Status::Open => Err(DomainError::AlreadyOpen),
         }
     }
 
-    /// Convenience for UI rendering.
-    pub fn status_symbol(&self) -> &'static str {
-        match self.status {
-            Status::Open => "☐",
-            Status::Done { .. } => "☑",
+    pub fn is_someday(&self) -> bool {
+        self.someday.is_some()
+    }
+
+    /// Open and not parked: something to actually work on.
+    pub fn is_active(&self) -> bool {
+        !self.status.is_done() && !self.is_someday()
+    }
+
+    /// Move to the someday/maybe list (or, if already there, mark it reviewed).
+    pub fn park(&mut self, now: OffsetDateTime) -> Result<(), DomainError> {
+        if self.status.is_done() {
+            return Err(DomainError::AlreadyDone);
         }
+        self.someday = Some(now);
+        self.touch(TodoField::Someday, now);
+        Ok(())
     }
 
-    /// Returns true if the todo is open and its due date is before `now`.
+    /// Bring back from the someday/maybe list.
+    pub fn promote(&mut self, now: OffsetDateTime) -> Result<(), DomainError> {
+        if self.someday.take().is_none() {
+            return Err(DomainError::NotSomeday);
+        }
+        self.touch(TodoField::Someday, now);
+        Ok(())
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived.is_some()
+    }
+
+    /// Move to the archive. Only done todos can be archived.
+    pub fn archive(&mut self, now: OffsetDateTime) -> Result<(), DomainError> {
+        if !self.status.is_done() {
+            return Err(DomainError::NotDone);
+        }
+        self.archived = Some(now);
+        self.touch(TodoField::Archived, now);
+        Ok(())
+    }
+
+    /// Bring back from the archive (still done).
+    pub fn unarchive(&mut self, now: OffsetDateTime) -> Result<(), DomainError> {
+        if self.archived.take().is_none() {
+            return Err(DomainError::NotArchived);
+        }
+        self.touch(TodoField::Archived, now);
+        Ok(())
+    }
+
+    /// Done before `cutoff` and not archived yet.
+    pub fn archivable(&self, cutoff: OffsetDateTime) -> bool {
+        !self.is_archived()
+            && matches!(self.status, Status::Done { completed_at } if completed_at < cutoff)
+    }
+
+    /// Record that a notification was sent for it at `now`.
+    pub fn mark_notified(&mut self, now: OffsetDateTime) {
+        self.notified_at = Some(now);
+        self.touch(TodoField::NotifiedAt, now);
+    }
+
+    /// The running time entry, if any.
+    pub fn running_timer(&self) -> Option<&TimeEntry> {
+        self.time_entries.iter().find(|e| e.is_running())
+    }
+
+    /// Start tracking time at `now`.
+    pub fn start_timer(&mut self, now: OffsetDateTime) -> Result<(), DomainError> {
+        if self.running_timer().is_some() {
+            return Err(DomainError::TimerRunning);
+        }
+        self.time_entries.push(TimeEntry::start(now));
+        self.touch(TodoField::TimeEntries, now);
+        Ok(())
+    }
+
+    /// Stop the running timer at `now`, returning the finished entry.
+    pub fn stop_timer(
+        &mut self,
+        now: OffsetDateTime,
+        note: Option<String>,
+    ) -> Result<TimeEntry, DomainError> {
+        let Some(entry) = self.time_entries.iter_mut().find(|e| e.is_running()) else {
+            return Err(DomainError::TimerNotRunning);
+        };
+        entry.end = Some(now.max(entry.start));
+        entry.note = note;
+        let finished = entry.clone();
+        self.touch(TodoField::TimeEntries, now);
+        Ok(finished)
+    }
+
+    /// When `field` was last written (creation time if never edited).
+    pub fn field_stamp(&self, field: TodoField) -> FieldStamp {
+        self.field_stamps
+            .get(&field)
+            .cloned()
+            .unwrap_or_else(|| FieldStamp::at(self.created_at))
+    }
+
+    /// Record an edit of `field` at `now`.
+    fn touch(&mut self, field: TodoField, now: OffsetDateTime) {
+        self.field_stamps.insert(field, FieldStamp::at(now));
+        self.updated_at = now;
+    }
+
+    /// Returns true if the todo is active and its due date is before `now`.
+    ///
+    /// Someday items are never overdue; parking one is a decision not to do it now.
     pub fn is_overdue(&self, now: OffsetDateTime) -> bool {
-        if self.status.is_done() {
+        if !self.is_active() {
             return false;
         }
 
@@ -316,42 +852,143 @@ pub struct TodoPatch {
     pub notes: Option<Option<Notes>>, // Some(None) means "clear notes"
     pub project: Option<ProjectName>,
     pub priority: Option<Priority>,
-    pub due: Option<Option<DueAt>>,  // Some(None) means "clear due"
-    pub tags: Option<BTreeSet<Tag>>, // if present, replaces full set
+    pub due: Option<Option<DueAt>>, // Some(None) means "clear due"
+    pub remind_at: Option<Option<DueAt>>, // Some(None) means "clear reminder"
+    pub escalation: Option<Option<Escalation>>, // Some(None) means "use the priority's"
+    pub state: Option<Option<WorkflowState>>, // Some(None) means "back to open"
+    pub external_ref: Option<Option<ExternalRef>>, // Some(None) means "clear reference"
+    pub attachments: Option<Vec<Attachment>>, // if present, replaces the list
+    pub tags: Option<TagsPatch>,
+    pub badge: Option<Option<Badge>>, // Some(None) means "clear badge"
+    pub color: Option<Option<Color>>, // Some(None) means "clear color"
+    pub estimate: Option<Option<Estimate>>, // Some(None) means "clear estimate"
+    pub energy: Option<Option<Energy>>, // Some(None) means "clear energy"
+    pub depends_on: Option<BTreeSet<TodoId>>, // if present, replaces full set
+    pub parent: Option<Option<TodoId>>, // Some(None) means "no longer a subtask"
+}
+
+/// How a [`TodoPatch`] changes the tag set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagsPatch {
+    /// Replace the whole set.
+    Replace(BTreeSet<Tag>),
+    /// Add and remove individual tags, keeping the others (removals win
+    /// over additions of the same tag).
+    Change {
+        add: BTreeSet<Tag>,
+        remove: BTreeSet<Tag>,
+    },
+}
+
+impl TagsPatch {
+    /// The tag set after applying this to `tags`.
+    pub fn apply(&self, tags: &BTreeSet<Tag>) -> BTreeSet<Tag> {
+        match self {
+            TagsPatch::Replace(set) => set.clone(),
+            TagsPatch::Change { add, remove } => tags
+                .union(add)
+                .filter(|t| !remove.contains(*t))
+                .cloned()
+                .collect(),
+        }
+    }
 }
 
 impl Todo {
     /// Apply a patch and update `updated_at` if anything changed.
     pub fn apply_patch(&mut self, patch: TodoPatch) {
-        let mut changed = false;
+        self.apply_patch_at(patch, OffsetDateTime::now_utc());
+    }
+
+    /// Like [`Todo::apply_patch`], stamping changes with `now`.
+    pub fn apply_patch_at(&mut self, patch: TodoPatch, now: OffsetDateTime) {
+        let mut changed = Vec::new();
 
         if let Some(title) = patch.title {
             self.title = title;
-            changed = true;
+            changed.push(TodoField::Title);
         }
         if let Some(notes_opt) = patch.notes {
             self.notes = notes_opt;
-            changed = true;
+            changed.push(TodoField::Notes);
         }
         if let Some(project) = patch.project {
             self.project = project;
-            changed = true;
+            changed.push(TodoField::Project);
         }
         if let Some(priority) = patch.priority {
             self.priority = priority;
-            changed = true;
+            changed.push(TodoField::Priority);
         }
         if let Some(due_opt) = patch.due {
             self.due = due_opt;
-            changed = true;
+            changed.push(TodoField::Due);
+        }
+        if let Some(remind_opt) = patch.remind_at {
+            self.remind_at = remind_opt;
+            changed.push(TodoField::RemindAt);
+        }
+        if let Some(escalation) = patch.escalation {
+            self.escalation = escalation;
+            changed.push(TodoField::Escalation);
+        }
+        if let Some(state) = patch.state {
+            self.state = state;
+            changed.push(TodoField::State);
+        }
+        if let Some(external_ref) = patch.external_ref {
+            self.external_ref = external_ref;
+            changed.push(TodoField::ExternalRef);
+        }
+        if let Some(attachments) = patch.attachments {
+            self.attachments = attachments;
+            changed.push(TodoField::Attachments);
         }
         if let Some(tags) = patch.tags {
-            self.tags = tags;
-            changed = true;
+            let tags = tags.apply(&self.tags);
+            // A delta that adds tags already there changes nothing.
+            if tags != self.tags {
+                self.tags = tags;
+                changed.push(TodoField::Tags);
+            }
+        }
+        if let Some(badge) = patch.badge {
+            self.badge = badge;
+            changed.push(TodoField::Badge);
+        }
+        if let Some(color) = patch.color {
+            self.color = color;
+            changed.push(TodoField::Color);
+        }
+        if let Some(estimate) = patch.estimate {
+            self.estimate = estimate;
+            changed.push(TodoField::Estimate);
+        }
+        if let Some(energy) = patch.energy {
+            self.energy = energy;
+            changed.push(TodoField::Energy);
+        }
+        if let Some(deps) = patch.depends_on {
+            self.depends_on = deps;
+            changed.push(TodoField::DependsOn);
+        }
+        if let Some(parent) = patch.parent {
+            self.parent = parent;
+            changed.push(TodoField::Parent);
         }
 
-        if changed {
-            self.updated_at = OffsetDateTime::now_utc();
+        // A new reminder or due date deserves a new notification.
+        let rescheduled = changed
+            .iter()
+            .any(|f| matches!(f, TodoField::Due | TodoField::RemindAt));
+        if rescheduled && self.notified_at.take().is_some() {
+            changed.push(TodoField::NotifiedAt);
+        }
+
+        if !changed.is_empty() {
+            for field in changed {
+                self.touch(field, now);
+            }
         }
     }
 }
@@ -381,6 +1018,32 @@ mod tests {
         assert_eq!(title.as_str(), "Buy milk");
     }
 
+    #[test]
+    fn title_rules_collapse_whitespace_and_limit_graphemes() {
+        let title = Title::parse("Call\n  mom \t today").unwrap();
+        assert_eq!(title.as_str(), "Call mom today");
+
+        let keep = TitleRules {
+            collapse_whitespace: false,
+            ..TitleRules::default()
+        };
+        assert_eq!(Title::parse_with("a  b", &keep).unwrap().as_str(), "a  b");
+
+        let short = TitleRules {
+            max_len: 3,
+            ..TitleRules::default()
+        };
+        let family = "👨\u{200D}👩\u{200D}👧";
+        assert!(Title::parse_with(format!("{family}{family}ab"), &short).is_err());
+        let ok = Title::parse_with(format!("{family} a"), &short).unwrap();
+        assert_eq!(ok.truncated(2), "…");
+        assert_eq!(ok.truncated(3), format!("{family}…"));
+        assert_eq!(
+            Title::parse_with("abcd", &short).unwrap_err(),
+            DomainError::TitleTooLong { max: 3 }
+        );
+    }
+
     #[test]
     fn tag_is_normalized_to_lowercase() {
         let t = Tag::parse("Work").unwrap();
@@ -399,6 +1062,35 @@ mod tests {
         assert_eq!(ProjectName::parse("Work").unwrap().as_str(), "Work");
     }
 
+    #[test]
+    fn badge_and_color_validation() {
+        assert_eq!(Badge::parse(" 🔥 ").unwrap().as_str(), "🔥");
+        assert!(Badge::parse("").is_err());
+        assert!(Badge::parse("two words").is_err());
+        assert_eq!(Color::parse("Red").unwrap(), Color::Red);
+        assert_eq!(Color::parse("grey").unwrap(), Color::Gray);
+        assert!(Color::parse("mauve").is_err());
+        assert_eq!(Energy::parse(" Med ").unwrap(), Energy::Medium);
+        assert!(Energy::parse("extreme").is_err());
+
+        let src = Source::parse("import:tasks.csv").unwrap();
+        assert_eq!(src.to_string(), "import:tasks.csv");
+        assert!(src.matches("import") && src.matches("Import:tasks.csv"));
+        assert!(!src.matches("import:other.csv") && !src.matches("cli"));
+        assert_eq!(serde_json::to_value(Source::Cli).unwrap(), "cli");
+    }
+
+    #[test]
+    fn estimate_parses_units() {
+        assert_eq!(Estimate::parse("45").unwrap().minutes(), 45);
+        assert_eq!(Estimate::parse("1h30m").unwrap().minutes(), 90);
+        assert_eq!(Estimate::parse("1d 2h").unwrap().minutes(), 600);
+        assert_eq!(Estimate::parse("90m").unwrap().label(), "1h 30m");
+        assert!(Estimate::parse("2x").is_err());
+        assert!(Estimate::parse("h").is_err());
+        assert!(Estimate::parse("3h5").is_err());
+    }
+
     #[test]
     fn notes_max_len_is_enforced() {
         let big = "a".repeat(10_001);
@@ -481,11 +1173,83 @@ mod tests {
         let mut todo = Todo::new(Title::parse("A").unwrap());
         let before = todo.updated_at;
 
-        let mut patch = TodoPatch::default();
-        patch.priority = Some(Priority::P1);
+        let patch = TodoPatch {
+            priority: Some(Priority::P1),
+            ..TodoPatch::default()
+        };
 
         todo.apply_patch(patch);
         assert!(todo.updated_at >= before);
         assert_eq!(todo.priority, Priority::P1);
     }
+
+    #[test]
+    fn tag_deltas_keep_other_tags() {
+        let tags = |names: &[&str]| -> BTreeSet<Tag> {
+            names.iter().map(|n| Tag::parse(*n).unwrap()).collect()
+        };
+        let mut todo = Todo::new(Title::parse("A").unwrap());
+        todo.tags = tags(&["home", "urgent"]);
+        let stamp = todo.updated_at - time::Duration::minutes(1);
+        todo.updated_at = stamp;
+
+        todo.apply_patch(TodoPatch {
+            tags: Some(TagsPatch::Change {
+                add: tags(&["home"]),
+                remove: tags(&["errand"]),
+            }),
+            ..TodoPatch::default()
+        });
+        assert_eq!(todo.updated_at, stamp, "no-op delta leaves it untouched");
+
+        todo.apply_patch(TodoPatch {
+            tags: Some(TagsPatch::Change {
+                add: tags(&["work", "urgent"]),
+                remove: tags(&["home", "work"]),
+            }),
+            ..TodoPatch::default()
+        });
+        assert_eq!(todo.tags, tags(&["urgent"]));
+        assert!(todo.updated_at > stamp);
+    }
+
+    #[test]
+    fn someday_items_are_parked_and_promoted() {
+        let mut todo = Todo::new(Title::parse("Learn the cello").unwrap());
+        let now = OffsetDateTime::now_utc();
+        todo.due = Some(DueAt::from_dt(now - time::Duration::days(1)));
+
+        todo.park(now).unwrap();
+        assert!(!todo.is_active());
+        assert!(!todo.is_overdue(now));
+
+        todo.promote(now).unwrap();
+        assert!(todo.is_overdue(now));
+        assert_eq!(todo.promote(now), Err(DomainError::NotSomeday));
+
+        todo.park(now).unwrap();
+        todo.mark_done().unwrap();
+        assert!(!todo.is_someday());
+        assert_eq!(todo.park(now), Err(DomainError::AlreadyDone));
+    }
+
+    #[test]
+    fn only_old_done_todos_are_archived_and_reopening_restores_them() {
+        let mut todo = Todo::new(Title::parse("File taxes").unwrap());
+        let now = OffsetDateTime::now_utc();
+        assert_eq!(todo.archive(now), Err(DomainError::NotDone));
+
+        todo.mark_done().unwrap();
+        assert!(!todo.archivable(now - time::Duration::days(1)));
+        assert!(todo.archivable(now + time::Duration::days(1)));
+
+        todo.archive(now).unwrap();
+        assert!(!todo.archivable(now + time::Duration::days(1)));
+        todo.unarchive(now).unwrap();
+        assert_eq!(todo.unarchive(now), Err(DomainError::NotArchived));
+
+        todo.archive(now).unwrap();
+        todo.mark_open().unwrap();
+        assert!(!todo.is_archived());
+    }
 }
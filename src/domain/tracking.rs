@@ -0,0 +1,82 @@
+//! Time tracking.
+//!
+//! A todo keeps a list of time entries; at most one of them is running (has no
+//! end yet).
+
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+/// One stretch of work on a todo.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeEntry {
+    pub start: OffsetDateTime,
+    /// `None` while the timer is running.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<OffsetDateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl TimeEntry {
+    pub fn start(at: OffsetDateTime) -> Self {
+        Self {
+            start: at,
+            end: None,
+            note: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.end.is_none()
+    }
+
+    /// Length of the entry; running entries count up to `now`.
+    pub fn duration(&self, now: OffsetDateTime) -> Duration {
+        self.within(None, now)
+    }
+
+    /// The part of the entry at or after `since` (all of it if `None`).
+    pub fn within(&self, since: Option<OffsetDateTime>, now: OffsetDateTime) -> Duration {
+        let start = since.map_or(self.start, |s| s.max(self.start));
+        let end = self.end.unwrap_or(now);
+        (end - start).max(Duration::ZERO)
+    }
+}
+
+/// Total of `entries` at or after `since`.
+pub fn total(
+    entries: &[TimeEntry],
+    since: Option<OffsetDateTime>,
+    now: OffsetDateTime,
+) -> Duration {
+    entries.iter().map(|e| e.within(since, now)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_clipped_to_the_window() {
+        let t0 = OffsetDateTime::UNIX_EPOCH;
+        let done = TimeEntry {
+            start: t0,
+            end: Some(t0 + Duration::hours(2)),
+            note: None,
+        };
+        let running = TimeEntry::start(t0 + Duration::hours(5));
+        let now = t0 + Duration::hours(6);
+
+        assert_eq!(done.duration(now), Duration::hours(2));
+        assert_eq!(running.duration(now), Duration::hours(1));
+
+        let since = Some(t0 + Duration::hours(1));
+        assert_eq!(
+            total(&[done.clone(), running], since, now),
+            Duration::hours(2)
+        );
+
+        let after = Some(t0 + Duration::hours(3));
+        assert_eq!(done.within(after, now), Duration::ZERO);
+    }
+}
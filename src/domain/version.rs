@@ -0,0 +1,124 @@
+//! Version vectors for multi-device sync.
+//!
+//! Each device owns one counter. A todo's vector records how many of each
+//! device's edits it has seen, which lets two replicas tell "newer" apart from
+//! "edited concurrently" without trusting wall clocks.
+
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use serde::{Deserialize, Serialize};
+
+/// How two version vectors relate causally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Causality {
+    Equal,
+    /// Left has seen everything right has, and more.
+    After,
+    /// Right has seen everything left has, and more.
+    Before,
+    /// Both sides have edits the other hasn't seen.
+    Concurrent,
+}
+
+/// Map of device id -> edit counter. Missing entries count as 0.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionVector(BTreeMap<String, u64>);
+
+impl VersionVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, device: &str) -> u64 {
+        self.0.get(device).copied().unwrap_or(0)
+    }
+
+    /// Record one more edit by `device`.
+    pub fn increment(&mut self, device: &str) {
+        *self.0.entry(device.to_string()).or_insert(0) += 1;
+    }
+
+    /// Pointwise maximum (the smallest vector that has seen both).
+    pub fn merge(&mut self, other: &VersionVector) {
+        for (device, &n) in &other.0 {
+            let slot = self.0.entry(device.clone()).or_insert(0);
+            *slot = (*slot).max(n);
+        }
+    }
+
+    /// Compare causally against `other`.
+    pub fn compare(&self, other: &VersionVector) -> Causality {
+        let mut ahead = false;
+        let mut behind = false;
+
+        for device in self.0.keys().chain(other.0.keys()) {
+            match self.get(device).cmp(&other.get(device)) {
+                Ordering::Greater => ahead = true,
+                Ordering::Less => behind = true,
+                Ordering::Equal => {}
+            }
+        }
+
+        match (ahead, behind) {
+            (false, false) => Causality::Equal,
+            (true, false) => Causality::After,
+            (false, true) => Causality::Before,
+            (true, true) => Causality::Concurrent,
+        }
+    }
+
+    /// True if `other` has seen nothing this vector hasn't.
+    pub fn dominates(&self, other: &VersionVector) -> bool {
+        matches!(self.compare(other), Causality::Equal | Causality::After)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.0.iter().map(|(d, &n)| (d.as_str(), n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vv(entries: &[(&str, u64)]) -> VersionVector {
+        let mut v = VersionVector::new();
+        for &(d, n) in entries {
+            for _ in 0..n {
+                v.increment(d);
+            }
+        }
+        v
+    }
+
+    #[test]
+    fn compare_detects_order_and_concurrency() {
+        let a = vv(&[("a", 2), ("b", 1)]);
+        let b = vv(&[("a", 1), ("b", 1)]);
+        let c = vv(&[("a", 1), ("b", 2)]);
+
+        assert_eq!(a.compare(&a.clone()), Causality::Equal);
+        assert_eq!(a.compare(&b), Causality::After);
+        assert_eq!(b.compare(&a), Causality::Before);
+        assert_eq!(a.compare(&c), Causality::Concurrent);
+    }
+
+    #[test]
+    fn missing_entries_count_as_zero() {
+        let empty = VersionVector::new();
+        let one = vv(&[("a", 1)]);
+        assert_eq!(empty.compare(&one), Causality::Before);
+        assert!(one.dominates(&empty));
+    }
+
+    #[test]
+    fn merge_takes_pointwise_max() {
+        let mut a = vv(&[("a", 3), ("b", 1)]);
+        a.merge(&vv(&[("b", 4), ("c", 1)]));
+        assert_eq!(a, vv(&[("a", 3), ("b", 4), ("c", 1)]));
+    }
+}
@@ -0,0 +1,267 @@
+//! C-compatible bindings layer (feature `ffi`).
+//!
+//! Lets GUI shells (Tauri, Swift, Kotlin, ...) embed the same engine the CLI uses.
+//!
+//! Conventions:
+//! - Every call that returns `*mut c_char` returns a JSON envelope:
+//!   `{"ok": true, "data": ...}` or `{"ok": false, "error": "..."}`.
+//! - Returned strings are owned by Rust; free them with `rustlytodo_string_free`.
+//! - Handles are opaque; free them with `rustlytodo_close`.
+//!
+//! The C header lives in `include/rustlytodo.h` and is generated with cbindgen
+//! (see `cbindgen.toml`).
+
+use std::{
+    collections::BTreeSet,
+    ffi::{CStr, CString, c_char},
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::{
+    app::store::Store,
+    domain::todo::{DueAt, Notes, Priority, ProjectName, Source, Tag, Title, Todo, TodoId},
+    infra::fs_repo::JsonFileTodoRepository,
+};
+
+/// Opaque handle owning an open store.
+pub struct RustlytodoHandle {
+    store: Store<JsonFileTodoRepository>,
+}
+
+/// JSON body accepted by `rustlytodo_add_json`.
+#[derive(Debug, Deserialize)]
+struct AddRequest {
+    title: String,
+    project: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    notes: Option<String>,
+    priority: Option<String>,
+    due: Option<String>,
+}
+
+impl AddRequest {
+    fn into_todo(self) -> Result<Todo> {
+        let mut todo = Todo::new(Title::parse(self.title)?);
+        todo.source = Some(Source::Api);
+
+        if let Some(p) = self.project {
+            todo.project = ProjectName::parse(p)?;
+        }
+        if let Some(n) = self.notes {
+            todo.notes = Some(Notes::parse(n)?);
+        }
+        if !self.tags.is_empty() {
+            let mut set = BTreeSet::new();
+            for t in self.tags {
+                set.insert(Tag::parse(t)?);
+            }
+            todo.tags = set;
+        }
+        if let Some(p) = self.priority {
+            todo.priority = Priority::parse(p)?;
+        }
+        if let Some(d) = self.due {
+            todo.due = Some(DueAt::parse_rfc3339(d)?);
+        }
+
+        Ok(todo)
+    }
+}
+
+/// Open (or create) the database at `db_path`.
+///
+/// Returns null on failure.
+///
+/// # Safety
+/// `db_path` must be a valid, NUL-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rustlytodo_open(db_path: *const c_char) -> *mut RustlytodoHandle {
+    let result = catch_unwind(|| -> Result<RustlytodoHandle> {
+        // SAFETY: upheld by the caller.
+        let path = unsafe { read_str(db_path) }?;
+        let repo = JsonFileTodoRepository::load_or_init(PathBuf::from(path))?;
+        Ok(RustlytodoHandle {
+            store: Store::new(repo),
+        })
+    });
+
+    match result {
+        Ok(Ok(handle)) => Box::into_raw(Box::new(handle)),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Close a handle returned by `rustlytodo_open`. Null is ignored.
+///
+/// # Safety
+/// `handle` must come from `rustlytodo_open` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rustlytodo_close(handle: *mut RustlytodoHandle) {
+    if !handle.is_null() {
+        // SAFETY: upheld by the caller.
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// List all todos as a JSON array (same shape as `list --format json`).
+///
+/// # Safety
+/// `handle` must be a live handle from `rustlytodo_open`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rustlytodo_list_json(handle: *mut RustlytodoHandle) -> *mut c_char {
+    with_handle(handle, |h| {
+        let todos = h.store.list_todos();
+        serde_json::to_value(todos).context("failed serializing todos to json")
+    })
+}
+
+/// Add a todo from a JSON request body and persist it.
+///
+/// Body: `{"title": "...", "project"?, "tags"?: [..], "notes"?, "priority"?, "due"?}`.
+/// Returns the created todo.
+///
+/// # Safety
+/// `handle` must be live; `request_json` must be a NUL-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rustlytodo_add_json(
+    handle: *mut RustlytodoHandle,
+    request_json: *const c_char,
+) -> *mut c_char {
+    with_handle(handle, |h| {
+        // SAFETY: upheld by the caller.
+        let body = unsafe { read_str(request_json) }?;
+        let req: AddRequest = serde_json::from_str(body).context("invalid add request json")?;
+        let todo = req.into_todo()?;
+        let value = serde_json::to_value(&todo).context("failed serializing todo to json")?;
+
+        h.store.insert_todo(todo)?;
+        h.store.repo_mut().save_atomic()?;
+        Ok(value)
+    })
+}
+
+/// Mark a todo as done by full UUID and persist it.
+///
+/// # Safety
+/// `handle` must be live; `id` must be a NUL-terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rustlytodo_done(
+    handle: *mut RustlytodoHandle,
+    id: *const c_char,
+) -> *mut c_char {
+    with_handle(handle, |h| {
+        // SAFETY: upheld by the caller.
+        let id = TodoId::parse_uuid(unsafe { read_str(id) }?)?;
+        h.store.mark_done(id)?;
+        h.store.repo_mut().save_atomic()?;
+        Ok(json!({ "id": id.as_uuid_str() }))
+    })
+}
+
+/// Free a string returned by any `rustlytodo_*` function. Null is ignored.
+///
+/// # Safety
+/// `s` must come from this library and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rustlytodo_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: upheld by the caller.
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// # Safety
+/// `ptr` must be null or a valid NUL-terminated string.
+unsafe fn read_str<'a>(ptr: *const c_char) -> Result<&'a str> {
+    if ptr.is_null() {
+        return Err(anyhow!("null string argument"));
+    }
+    // SAFETY: upheld by the caller.
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .context("string argument is not valid UTF-8")
+}
+
+/// Run `f` against the handle, catching errors and panics into a JSON envelope.
+fn with_handle(
+    handle: *mut RustlytodoHandle,
+    f: impl FnOnce(&mut RustlytodoHandle) -> Result<Value>,
+) -> *mut c_char {
+    let envelope = if handle.is_null() {
+        json!({ "ok": false, "error": "null handle" })
+    } else {
+        // SAFETY: non-null handles come from `rustlytodo_open` per the API contract.
+        let h = unsafe { &mut *handle };
+        match catch_unwind(AssertUnwindSafe(|| f(h))) {
+            Ok(Ok(data)) => json!({ "ok": true, "data": data }),
+            Ok(Err(e)) => json!({ "ok": false, "error": format!("{e:#}") }),
+            Err(_) => json!({ "ok": false, "error": "internal panic" }),
+        }
+    };
+
+    // serde_json never emits interior NULs, so this cannot fail.
+    CString::new(envelope.to_string())
+        .map(CString::into_raw)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn call(ptr: *mut c_char) -> Value {
+        assert!(!ptr.is_null());
+        let v = serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { rustlytodo_string_free(ptr) };
+        v
+    }
+
+    #[test]
+    fn add_list_done_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = CString::new(dir.path().join("db.json").to_str().unwrap()).unwrap();
+
+        let h = unsafe { rustlytodo_open(path.as_ptr()) };
+        assert!(!h.is_null());
+
+        let body = CString::new(r#"{"title":"From C","tags":["ffi"],"priority":"p1"}"#).unwrap();
+        let added = call(unsafe { rustlytodo_add_json(h, body.as_ptr()) });
+        assert_eq!(added["ok"], true);
+        let id = added["data"]["id"].as_str().unwrap().to_string();
+
+        let id_c = CString::new(id).unwrap();
+        let done = call(unsafe { rustlytodo_done(h, id_c.as_ptr()) });
+        assert_eq!(done["ok"], true);
+
+        let listed = call(unsafe { rustlytodo_list_json(h) });
+        let todos: Vec<Todo> = serde_json::from_value(listed["data"].clone()).unwrap();
+        assert_eq!(todos.len(), 1);
+        assert!(todos[0].status.is_done());
+
+        unsafe { rustlytodo_close(h) };
+    }
+
+    #[test]
+    fn invalid_input_is_reported_as_error_envelope() {
+        let dir = tempdir().unwrap();
+        let path = CString::new(dir.path().join("db.json").to_str().unwrap()).unwrap();
+        let h = unsafe { rustlytodo_open(path.as_ptr()) };
+
+        let body = CString::new(r#"{"title":"   "}"#).unwrap();
+        let res = call(unsafe { rustlytodo_add_json(h, body.as_ptr()) });
+        assert_eq!(res["ok"], false);
+        assert_eq!(res["error"], "todo title cannot be empty");
+
+        let res = call(unsafe { rustlytodo_list_json(std::ptr::null_mut()) });
+        assert_eq!(res["ok"], false);
+
+        unsafe { rustlytodo_close(h) };
+    }
+}
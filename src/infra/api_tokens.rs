@@ -0,0 +1,167 @@
+//! API tokens for `serve` (`api_tokens.json` in the data dir).
+//!
+//! Only a SHA-256 of each token is stored; the token itself is printed once,
+//! by `serve token create`. Each token has a scope: `read` for the GET
+//! routes, `write` to change todos as well, `admin` for everything including
+//! `POST /-/reload`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use crate::infra::{paths::AppPaths, perms};
+
+/// What a token may do; each scope includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl Scope {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "read" | "read-only" | "readonly" => Some(Self::Read),
+            "write" => Some(Self::Write),
+            "admin" => Some(Self::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiToken {
+    /// Unique; what `serve token revoke` takes.
+    pub name: String,
+    pub scope: Scope,
+    /// Hex SHA-256 of the token.
+    pub hash: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created: OffsetDateTime,
+    /// Requests per minute, instead of the server's `rate_limit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<u32>,
+}
+
+pub fn path(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join("api_tokens.json")
+}
+
+/// Tokens in `path`; none if it doesn't exist yet.
+pub fn load(path: &Path) -> Result<Vec<ApiToken>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed reading API tokens: {}", path.display()))?;
+    serde_json::from_str(&text)
+        .with_context(|| format!("failed parsing API tokens: {}", path.display()))
+}
+
+pub fn save(path: &Path, tokens: &[ApiToken]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        perms::create_dir(dir).with_context(|| format!("failed creating {}", dir.display()))?;
+    }
+    let text = serde_json::to_string_pretty(tokens)?;
+    perms::write(path, text).with_context(|| format!("failed writing {}", path.display()))
+}
+
+/// Add a token named `name` and return its secret (shown once, never stored).
+pub fn create(
+    tokens: &mut Vec<ApiToken>,
+    name: &str,
+    scope: Scope,
+    now: OffsetDateTime,
+) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().any(char::is_whitespace) {
+        bail!("token names can't be empty or contain spaces");
+    }
+    if tokens.iter().any(|t| t.name == name) {
+        bail!("a token named {name} already exists; revoke it first");
+    }
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow!("os random source failed: {e}"))?;
+    let secret = format!("rtd_{}", hex(&bytes));
+    tokens.push(ApiToken {
+        name: name.to_string(),
+        scope,
+        hash: digest(&secret),
+        created: now,
+        rate_limit: None,
+    });
+    Ok(secret)
+}
+
+/// Drop the token named `name`; false if there is none.
+pub fn revoke(tokens: &mut Vec<ApiToken>, name: &str) -> bool {
+    let before = tokens.len();
+    tokens.retain(|t| t.name != name.trim());
+    tokens.len() < before
+}
+
+/// The token whose secret is `presented`, if any.
+pub fn find<'a>(tokens: &'a [ApiToken], presented: &str) -> Option<&'a ApiToken> {
+    let hash = digest(presented.trim());
+    // Compare every byte so timing doesn't tell how much of a hash matched.
+    tokens.iter().find(|t| {
+        t.hash.len() == hash.len()
+            && t.hash
+                .bytes()
+                .zip(hash.bytes())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    })
+}
+
+fn digest(secret: &str) -> String {
+    hex(&Sha256::digest(secret.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use time::macros::datetime;
+
+    #[test]
+    fn tokens_are_stored_hashed_and_found_by_secret() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("api_tokens.json");
+        let now = datetime!(2026-05-04 09:00 UTC);
+
+        let mut tokens = load(&file).unwrap();
+        let secret = create(&mut tokens, "laptop", Scope::Write, now).unwrap();
+        assert!(create(&mut tokens, "laptop", Scope::Read, now).is_err());
+        assert!(create(&mut tokens, "my laptop", Scope::Read, now).is_err());
+        save(&file, &tokens).unwrap();
+
+        let text = std::fs::read_to_string(&file).unwrap();
+        assert!(!text.contains(&secret));
+        let mut tokens = load(&file).unwrap();
+        assert_eq!(find(&tokens, &secret).unwrap().scope, Scope::Write);
+        assert!(find(&tokens, "rtd_guess").is_none());
+
+        assert!(revoke(&mut tokens, "laptop"));
+        assert!(!revoke(&mut tokens, "laptop"));
+        assert!(find(&tokens, &secret).is_none());
+        assert!(Scope::Admin > Scope::Write && Scope::Write > Scope::Read);
+    }
+}
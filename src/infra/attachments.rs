@@ -0,0 +1,111 @@
+//! The attachments folder in the data dir (`attach`, `detach`).
+//!
+//! Attached files are copied to `attachments/<todo id>/<file name>`, so they
+//! survive the original moving or going away; a name already taken in that
+//! todo's folder gets a number, as in `spec (2).pdf`. Todos store the copy's
+//! path relative to the folder. Files attached with `--link` stay where they
+//! are and are never touched.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    domain::{attachment::AttachmentTarget, todo::TodoId},
+    infra::paths::AppPaths,
+};
+
+pub fn dir(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join("attachments")
+}
+
+/// Copy `source` into `todo`'s folder under `dir`; returns the copy's path
+/// relative to `dir`.
+pub fn copy_in(dir: &Path, todo: TodoId, source: &Path) -> Result<PathBuf> {
+    if !source.is_file() {
+        bail!("{} is not a file", source.display());
+    }
+    let Some(name) = source.file_name() else {
+        bail!("{} has no file name", source.display());
+    };
+    let folder = PathBuf::from(todo.as_uuid_str());
+    std::fs::create_dir_all(dir.join(&folder))
+        .with_context(|| format!("failed creating {}", dir.join(&folder).display()))?;
+
+    let name = Path::new(name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = name
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut relative = folder.join(name);
+    let mut n = 1;
+    while dir.join(&relative).exists() {
+        n += 1;
+        relative = folder.join(format!("{stem} ({n}){ext}"));
+    }
+    let to = dir.join(&relative);
+    std::fs::copy(source, &to)
+        .with_context(|| format!("failed copying {} -> {}", source.display(), to.display()))?;
+    Ok(relative)
+}
+
+/// Delete a copy made by [`copy_in`], and its todo's folder once empty. A
+/// copy already gone is fine.
+pub fn remove(dir: &Path, relative: &Path) -> Result<()> {
+    let path = dir.join(relative);
+    match std::fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("failed removing {}", path.display())),
+    }
+    if let Some(folder) = path.parent().filter(|f| *f != dir) {
+        // Fails while other attachments are left in it.
+        let _ = std::fs::remove_dir(folder);
+    }
+    Ok(())
+}
+
+/// Where an attached file is on disk; `None` for links.
+pub fn locate(dir: &Path, target: &AttachmentTarget) -> Option<PathBuf> {
+    match target {
+        AttachmentTarget::Url(_) => None,
+        AttachmentTarget::File(relative) => Some(dir.join(relative)),
+        AttachmentTarget::Path(path) => Some(path.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_get_unique_names_and_their_folder_goes_with_the_last() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("attachments");
+        let source = tmp.path().join("spec.pdf");
+        std::fs::write(&source, b"%PDF").unwrap();
+        let todo = TodoId::new();
+
+        let first = copy_in(&dir, todo, &source).unwrap();
+        let second = copy_in(&dir, todo, &source).unwrap();
+        assert_eq!(
+            first,
+            Path::new(todo.as_uuid_str().as_str()).join("spec.pdf")
+        );
+        assert_eq!(second.file_name().unwrap(), "spec (2).pdf");
+        let target = AttachmentTarget::File(second.clone());
+        assert_eq!(
+            std::fs::read(locate(&dir, &target).unwrap()).unwrap(),
+            b"%PDF"
+        );
+        assert!(copy_in(&dir, todo, tmp.path()).is_err());
+
+        remove(&dir, &first).unwrap();
+        assert!(dir.join(todo.as_uuid_str()).is_dir());
+        remove(&dir, &second).unwrap();
+        remove(&dir, &second).unwrap();
+        assert!(!dir.join(todo.as_uuid_str()).exists());
+        assert!(source.exists());
+    }
+}
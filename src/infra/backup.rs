@@ -0,0 +1,568 @@
+//! Off-site backups of the data dir (`backup push`, `[backup]` in
+//! config.toml).
+//!
+//! A backup is one file, `rustytodo-backup-20260310T120000Z.json`: a JSON
+//! bundle of every file in the data dir (logs, crash reports and local
+//! snapshots aside) plus the database and its journal, undo history and
+//! routines when `storage_path` puts them elsewhere. With `encrypt` it is
+//! sealed like an encrypted database, so the target only ever sees
+//! ciphertext. After a push the target keeps the newest `keep` backups.
+//!
+//! Targets implement [`BackupTarget`]. `--target` takes a name from
+//! `[backup.targets]` or one of:
+//!
+//! - `s3://bucket/prefix`, through the `aws` CLI and its credentials
+//! - `https://host/path/`, a WebDAV folder, through `curl` (log in with
+//!   `~/.netrc`)
+//! - `remote:path`, an rclone remote, through `rclone`
+//! - a local folder (e.g. a mounted drive), as a path or `file://` URL
+
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD as B64};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::infra::{
+    db_crypto::{self, DbKey},
+    fs_repo, history_file, journal, perms, routines_file,
+    snapshot::{parse_stamp, stamp},
+};
+
+const PREFIX: &str = "rustytodo-backup-";
+const SUFFIX: &str = ".json";
+const FORMAT: &str = "rustytodo-backup-v1";
+
+/// Data dir folders left out of backups: noise, or backups themselves.
+const SKIPPED_DIRS: &[&str] = &["logs", "crashes", "snapshots"];
+
+/// Somewhere backups can be kept.
+pub trait BackupTarget {
+    /// Where the files go, for messages.
+    fn describe(&self) -> String;
+
+    /// Store `bytes` as `name`.
+    fn put(&self, name: &str, bytes: &[u8]) -> Result<()>;
+
+    fn get(&self, name: &str) -> Result<Vec<u8>>;
+
+    /// Names of the files there, in any order.
+    fn list(&self) -> Result<Vec<String>>;
+
+    fn delete(&self, name: &str) -> Result<()>;
+}
+
+/// The target `spec` names (see the module docs).
+pub fn target(spec: &str) -> Result<Box<dyn BackupTarget>> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        bail!("backup target cannot be empty");
+    }
+    if let Some(path) = spec.strip_prefix("file://") {
+        return Ok(Box::new(Folder(PathBuf::from(path))));
+    }
+    if spec.starts_with("s3://") {
+        let url = spec.trim_end_matches('/');
+        if url.len() <= "s3://".len() {
+            bail!("s3 target needs a bucket: s3://bucket/prefix");
+        }
+        return Ok(Box::new(S3 {
+            url: url.to_string(),
+        }));
+    }
+    if spec.starts_with("https://") || spec.starts_with("http://") {
+        let url = format!("{}/", spec.trim_end_matches('/'));
+        return Ok(Box::new(WebDav { url }));
+    }
+    let path = Path::new(spec);
+    // `C:\...` is a path, not an rclone remote called C.
+    let is_path = path.is_absolute() || spec.starts_with('.') || path.exists();
+    if !is_path && let Some((remote, _)) = spec.split_once(':') {
+        if remote.is_empty() || remote.contains(['/', '\\']) {
+            bail!("unknown backup target {spec} (use s3://, https://, remote:path or a folder)");
+        }
+        return Ok(Box::new(Rclone {
+            remote: spec.trim_end_matches('/').to_string(),
+        }));
+    }
+    Ok(Box::new(Folder(path.to_path_buf())))
+}
+
+/// What [`push`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pushed {
+    pub name: String,
+    pub files: usize,
+    pub bytes: usize,
+    /// Older backups deleted to stay within `keep`.
+    pub pruned: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Bundle {
+    format: String,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+    /// `/`-separated path -> base64 contents.
+    files: BTreeMap<String, String>,
+}
+
+/// Bundle the files under `data_dir` (and the database's, if it lives
+/// elsewhere), seal the bundle with `key` if given, upload it and prune
+/// the target down to `keep` backups (0 keeps all).
+pub fn push(
+    target: &dyn BackupTarget,
+    data_dir: &Path,
+    db_path: &Path,
+    key: Option<&DbKey>,
+    keep: usize,
+    now: OffsetDateTime,
+) -> Result<Pushed> {
+    let files = collect(data_dir, db_path)?;
+    let bundle = Bundle {
+        format: FORMAT.to_string(),
+        created_at: now,
+        files: files
+            .iter()
+            .map(|(name, bytes)| (name.clone(), B64.encode(bytes)))
+            .collect(),
+    };
+    let mut bytes = serde_json::to_vec(&bundle).context("failed serializing backup")?;
+    if let Some(key) = key {
+        bytes = key.seal(&bytes)?;
+    }
+    let name = format!("{PREFIX}{}{SUFFIX}", stamp(now));
+    target
+        .put(&name, &bytes)
+        .with_context(|| format!("failed uploading {name} to {}", target.describe()))?;
+
+    let mut pruned = 0;
+    let all = list(target)?;
+    if keep > 0 && all.len() > keep {
+        for (old, _) in &all[..all.len() - keep] {
+            target
+                .delete(old)
+                .with_context(|| format!("failed removing old backup {old}"))?;
+            pruned += 1;
+        }
+    }
+    Ok(Pushed {
+        name,
+        files: files.len(),
+        bytes: bytes.len(),
+        pruned,
+    })
+}
+
+/// Backups on `target`, oldest first.
+pub fn list(target: &dyn BackupTarget) -> Result<Vec<(String, OffsetDateTime)>> {
+    let mut found: Vec<_> = target
+        .list()?
+        .into_iter()
+        .filter_map(|name| {
+            let at = parse_stamp(name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?)?;
+            Some((name, at))
+        })
+        .collect();
+    found.sort_by_key(|(_, at)| *at);
+    Ok(found)
+}
+
+/// Download backup `name` and write its files under `out`. `key` is asked
+/// for only if the backup is sealed. Returns how many files were written.
+pub fn pull(
+    target: &dyn BackupTarget,
+    name: &str,
+    out: &Path,
+    key: impl FnOnce() -> Result<DbKey>,
+) -> Result<usize> {
+    let mut bytes = target
+        .get(name)
+        .with_context(|| format!("failed downloading {name} from {}", target.describe()))?;
+    if db_crypto::is_encrypted(&bytes) {
+        bytes = key()?.open(&bytes)?;
+    }
+    let bundle: Bundle = serde_json::from_slice(&bytes).context("failed reading backup")?;
+    if bundle.format != FORMAT {
+        bail!(
+            "{name} is not a backup this version can read ({})",
+            bundle.format
+        );
+    }
+    for (file, data) in &bundle.files {
+        // Never write outside `out`, whatever the bundle says.
+        if file.split('/').any(|part| matches!(part, "" | "." | "..")) || file.contains(['\\', ':'])
+        {
+            bail!("backup holds an invalid path: {file}");
+        }
+        let path = out.join(file);
+        if let Some(parent) = path.parent() {
+            perms::create_dir(parent)
+                .with_context(|| format!("failed creating {}", parent.display()))?;
+        }
+        let data = B64
+            .decode(data)
+            .map_err(|e| anyhow!("backup file {file} is damaged: {e}"))?;
+        perms::write(&path, data).with_context(|| format!("failed writing {}", path.display()))?;
+    }
+    Ok(bundle.files.len())
+}
+
+/// The files a backup holds, by `/`-separated name.
+fn collect(data_dir: &Path, db_path: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    add_dir(&mut files, data_dir, "", true)?;
+    if !db_path.starts_with(data_dir) {
+        let siblings = [
+            db_path.to_path_buf(),
+            journal::journal_path(db_path),
+            history_file::history_path(db_path),
+            routines_file::routines_path(db_path),
+        ];
+        for path in siblings.iter().filter(|p| p.is_file()) {
+            add_file(&mut files, path, "database/")?;
+        }
+        let shards = fs_repo::shard_dir(db_path);
+        if shards.is_dir() {
+            let name = file_name(&shards);
+            add_dir(&mut files, &shards, &format!("database/{name}/"), false)?;
+        }
+    }
+    Ok(files)
+}
+
+fn add_dir(
+    files: &mut BTreeMap<String, Vec<u8>>,
+    dir: &Path,
+    prefix: &str,
+    top: bool,
+) -> Result<()> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = file_name(&path);
+        if path.is_dir() {
+            if !(top && SKIPPED_DIRS.contains(&name.as_str())) {
+                add_dir(files, &path, &format!("{prefix}{name}/"), false)?;
+            }
+        } else if path.is_file() {
+            add_file(files, &path, prefix)?;
+        }
+    }
+    Ok(())
+}
+
+fn add_file(files: &mut BTreeMap<String, Vec<u8>>, path: &Path, prefix: &str) -> Result<()> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed reading {}", path.display()))?;
+    files.insert(format!("{prefix}{}", file_name(path)), bytes);
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// A folder on this machine, e.g. a mounted drive.
+struct Folder(PathBuf);
+
+impl BackupTarget for Folder {
+    fn describe(&self) -> String {
+        self.0.display().to_string()
+    }
+
+    fn put(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        perms::create_dir(&self.0)
+            .with_context(|| format!("failed creating {}", self.0.display()))?;
+        Ok(perms::write(&self.0.join(name), bytes)?)
+    }
+
+    fn get(&self, name: &str) -> Result<Vec<u8>> {
+        Ok(std::fs::read(self.0.join(name))?)
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let Ok(entries) = std::fs::read_dir(&self.0) else {
+            return Ok(Vec::new());
+        };
+        Ok(entries
+            .flatten()
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        Ok(std::fs::remove_file(self.0.join(name))?)
+    }
+}
+
+/// An S3 bucket (or compatible store) through the `aws` CLI.
+struct S3 {
+    /// `s3://bucket/prefix`, without a trailing slash.
+    url: String,
+}
+
+impl BackupTarget for S3 {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn put(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        let object = format!("{}/{name}", self.url);
+        run_tool("aws", &["s3", "cp", "-", &object], Some(bytes)).map(drop)
+    }
+
+    fn get(&self, name: &str) -> Result<Vec<u8>> {
+        let object = format!("{}/{name}", self.url);
+        run_tool("aws", &["s3", "cp", &object, "-"], None)
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let folder = format!("{}/", self.url);
+        let listing = run_tool("aws", &["s3", "ls", &folder], None)?;
+        Ok(parse_s3_listing(&String::from_utf8_lossy(&listing)))
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let object = format!("{}/{name}", self.url);
+        run_tool("aws", &["s3", "rm", &object], None).map(drop)
+    }
+}
+
+/// A WebDAV folder through `curl`.
+struct WebDav {
+    /// Ends with a slash.
+    url: String,
+}
+
+impl WebDav {
+    fn curl(&self, args: &[&str], input: Option<&[u8]>) -> Result<Vec<u8>> {
+        let mut all = vec!["--silent", "--show-error", "--fail", "--netrc-optional"];
+        all.extend_from_slice(args);
+        run_tool("curl", &all, input)
+    }
+}
+
+impl BackupTarget for WebDav {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn put(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        let url = format!("{}{name}", self.url);
+        self.curl(&["--upload-file", "-", &url], Some(bytes))
+            .map(drop)
+    }
+
+    fn get(&self, name: &str) -> Result<Vec<u8>> {
+        self.curl(&[&format!("{}{name}", self.url)], None)
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let reply = self.curl(
+            &["--request", "PROPFIND", "--header", "Depth: 1", &self.url],
+            None,
+        )?;
+        Ok(parse_propfind(&String::from_utf8_lossy(&reply)))
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let url = format!("{}{name}", self.url);
+        self.curl(&["--request", "DELETE", &url], None).map(drop)
+    }
+}
+
+/// Any rclone remote (`remote:path`) through `rclone`.
+struct Rclone {
+    remote: String,
+}
+
+impl Rclone {
+    fn path(&self, name: &str) -> String {
+        if self.remote.ends_with(':') {
+            format!("{}{name}", self.remote)
+        } else {
+            format!("{}/{name}", self.remote)
+        }
+    }
+}
+
+impl BackupTarget for Rclone {
+    fn describe(&self) -> String {
+        self.remote.clone()
+    }
+
+    fn put(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        run_tool("rclone", &["rcat", &self.path(name)], Some(bytes)).map(drop)
+    }
+
+    fn get(&self, name: &str) -> Result<Vec<u8>> {
+        run_tool("rclone", &["cat", &self.path(name)], None)
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let listing = run_tool("rclone", &["lsf", "--files-only", &self.remote], None)?;
+        Ok(String::from_utf8_lossy(&listing)
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        run_tool("rclone", &["deletefile", &self.path(name)], None).map(drop)
+    }
+}
+
+/// Run `program` with `input` on stdin and return its stdout.
+fn run_tool(program: &str, args: &[&str], input: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed running {program} (is it installed?)"))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input)
+            .with_context(|| format!("failed sending data to {program}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("failed running {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} {} failed: {}",
+            args.iter()
+                .find(|a| !a.starts_with('-'))
+                .copied()
+                .unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// File names in `aws s3 ls` output (`2026-03-10 12:00:00  1234 name`);
+/// `PRE folder/` lines are skipped.
+fn parse_s3_listing(listing: &str) -> Vec<String> {
+    listing
+        .lines()
+        .filter(|l| !l.trim_start().starts_with("PRE "))
+        .filter_map(|l| l.split_whitespace().nth(3).map(String::from))
+        .collect()
+}
+
+/// File names in a WebDAV `PROPFIND` reply: the last part of each `href`
+/// that isn't a folder.
+fn parse_propfind(reply: &str) -> Vec<String> {
+    reply
+        .split('<')
+        .filter_map(|tag| {
+            let (name, text) = tag.split_once('>')?;
+            let local = name.rsplit(':').next()?;
+            if !local.eq_ignore_ascii_case("href") {
+                return None;
+            }
+            let text = text.trim();
+            if text.ends_with('/') {
+                return None;
+            }
+            text.rsplit('/').next().map(String::from)
+        })
+        .filter(|n| !n.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Duration, macros::datetime};
+
+    #[test]
+    fn pushes_are_pruned_and_pull_back_sealed() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join("data");
+        std::fs::create_dir_all(data.join("logs")).unwrap();
+        std::fs::write(data.join("db.json"), "{\"todos\":[]}").unwrap();
+        std::fs::write(data.join("logs/rustlytodo.log"), "noise").unwrap();
+        let elsewhere = dir.path().join("elsewhere/todo.json");
+        std::fs::create_dir_all(elsewhere.parent().unwrap()).unwrap();
+        std::fs::write(&elsewhere, "{}").unwrap();
+        std::fs::write(journal::journal_path(&elsewhere), "").unwrap();
+
+        let remote = target(&format!("file://{}", dir.path().join("remote").display())).unwrap();
+        let key = DbKey::new("correct horse").unwrap();
+        let start = datetime!(2026-03-10 12:00 UTC);
+        for day in 0..3 {
+            let now = start + Duration::days(day);
+            let pushed = push(&*remote, &data, &elsewhere, Some(&key), 2, now).unwrap();
+            assert_eq!(pushed.files, 3);
+            assert_eq!(pushed.pruned, usize::from(day == 2));
+        }
+        let kept = list(&*remote).unwrap();
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[1].0, "rustytodo-backup-20260312T120000Z.json");
+        let raw = remote.get(&kept[1].0).unwrap();
+        assert!(db_crypto::is_encrypted(&raw));
+
+        let out = dir.path().join("restored");
+        let written = pull(&*remote, &kept[1].0, &out, || Ok(key.clone())).unwrap();
+        assert_eq!(written, 3);
+        assert_eq!(
+            std::fs::read_to_string(out.join("db.json")).unwrap(),
+            "{\"todos\":[]}"
+        );
+        assert!(out.join("database/todo.journal.jsonl").is_file());
+        assert!(!out.join("logs").exists());
+    }
+
+    #[test]
+    fn targets_and_listings_parse() {
+        assert_eq!(
+            target("s3://bucket/todo/").unwrap().describe(),
+            "s3://bucket/todo"
+        );
+        assert_eq!(
+            target("https://dav.example.com/todo").unwrap().describe(),
+            "https://dav.example.com/todo/"
+        );
+        assert_eq!(
+            target("gdrive:backups/").unwrap().describe(),
+            "gdrive:backups"
+        );
+        assert!(target("s3://").is_err());
+        assert!(target(" ").is_err());
+
+        let ls = "                           PRE old/\n\
+                  2026-03-10 12:00:01       2048 rustytodo-backup-20260310T120000Z.json\n";
+        assert_eq!(
+            parse_s3_listing(ls),
+            ["rustytodo-backup-20260310T120000Z.json"]
+        );
+        let propfind = r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:">
+            <d:response><d:href>/todo/</d:href></d:response>
+            <d:response><d:href>/todo/rustytodo-backup-20260310T120000Z.json</d:href></d:response>
+            <D:response><D:href>/todo/notes.txt</D:href></D:response></d:multistatus>"#;
+        assert_eq!(
+            parse_propfind(propfind),
+            ["rustytodo-backup-20260310T120000Z.json", "notes.txt"]
+        );
+    }
+}
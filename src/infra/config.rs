@@ -2,50 +2,612 @@
 //!
 //! This is intentionally small for now. We'll expand it as features land:
 //! - theme selection
-//! - keybind customization
 //! - default filters/sorting
 //! - storage path override
 //!
 //! Design goal: typed config with sane defaults and helpful errors.
 
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
-use crate::infra::paths::AppPaths;
+use crate::{
+    app::{
+        custom_reports::ReportDefinition,
+        keymap::{KeyMap, KeySpec},
+        priorities::{PriorityLevel, PriorityScheme},
+        query::UrgencyCoefficients,
+        repository::DuplicatePolicy,
+        templates::TodoTemplate,
+        timesheet::Rounding,
+        timezone::Zone,
+    },
+    domain::{
+        escalation::Escalation,
+        todo::{Priority, TitleRules},
+    },
+    infra::{
+        db_crypto::{self, DbKey},
+        fs_repo,
+        paths::AppPaths,
+        perms,
+    },
+    ui::theme::{Rgb, ThemeSpec},
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     /// Optional override for where the main database file lives.
     /// If None, we'll use paths.data_dir in later milestones.
     pub storage_path: Option<PathBuf>,
 
-    /// UI theme preference (we'll implement in the TUI milestones).
-    pub theme: Theme,
+    /// Store one file per project under `<db>.shards/` instead of a single
+    /// file, for very large lists. Existing data is converted on the next run.
+    pub shard_by_project: bool,
+
+    /// Encrypt the database (and undo history) with a passphrase. Turning it
+    /// off decrypts them on the next run. See `db_crypto` for where the
+    /// passphrase comes from.
+    pub encryption: bool,
+
+    /// Command printing the database passphrase, e.g. `pass show todo`.
+    pub encryption_passphrase_command: Option<String>,
+
+    /// Colors for `list` and the GUI: `theme = "Dark"` (or `Light`,
+    /// `HighContrast`), or a `[theme]` table whose `[theme.colors]` overrides
+    /// single colors of the palette.
+    pub theme: ThemeConfig,
+
+    /// Colored output: auto (terminals, unless `NO_COLOR` is set), always
+    /// or never. `--color` overrides it for one run.
+    pub color: ColorChoice,
+
+    /// Status symbols in `list`/`show`: auto|unicode|ascii|nerd-font. `auto`
+    /// falls back to ASCII on terminals that can't show ☐/☑.
+    pub symbols: Symbols,
+
+    /// Show due dates in `list`/`show` as RFC 3339 timestamps rather than
+    /// `due in 2d`, `today 17:00` or `overdue 3h`. `--absolute` does it for
+    /// one run.
+    pub absolute_dates: bool,
+
+    /// Zone `list`/`show` display times in and bare input like
+    /// `2026-01-02 09:00` is read in: `local` (the `TZ` variable, else the
+    /// system's zone), an IANA name like `Europe/Berlin`, or an offset like
+    /// `+02:00` or `UTC`.
+    pub timezone: String,
+
+    /// Your own names for priorities (`[[priority_levels]]` tables with
+    /// `label`, `priority` = the P1-P4 it stands for, optional `color`), e.g.
+    /// High/Med/Low. Unset, priorities are P1-P4.
+    pub priority_levels: Vec<PriorityLevel>,
+
+    /// Mark a todo done when its last open subtask is done.
+    pub complete_parents: bool,
+
+    /// Cleanup and length limit for new titles (`[titles]` table):
+    /// `max_len = 200` (characters; 0 for none), `collapse_whitespace = false`
+    /// to keep repeated spaces.
+    pub titles: TitleRules,
 
     /// If true, we may show extra UI hints / debug info later.
     pub show_hints: bool,
+
+    /// Operations that ask before going ahead: `delete`, `import`, `move`,
+    /// `replace`. Pass the global `--force` to skip the question (e.g. in
+    /// scripts).
+    pub confirm: Vec<String>,
+
+    /// Todos sharing an ID in a list stored at once (`import`, sync,
+    /// `git pull`): `reject` (the default), `keep-first` or `keep-last`.
+    pub duplicate_ids: DuplicatePolicy,
+
+    /// Access journal next to the database (`[journal]` table).
+    pub journal: JournalConfig,
+
+    /// Timesheet export settings (`[timesheet]` table).
+    pub timesheet: TimesheetConfig,
+
+    /// Weights of `list --sort urgency` (`[urgency]` table): `priority`,
+    /// `due`, `age`, `tags`, and `[urgency.tag]` for individual tags.
+    pub urgency: UrgencyCoefficients,
+
+    /// Publish todo events to an MQTT broker (`[mqtt]` table).
+    pub mqtt: MqttConfig,
+
+    /// HTTP server settings (`[serve]` table).
+    pub serve: ServeConfig,
+
+    /// Scripts in `hooks/` run before and after commands (`[hooks]` table).
+    pub hooks: HooksConfig,
+
+    /// JSON log file in the data dir (`[log]` table).
+    pub log: LogConfig,
+
+    /// Version the database with git (`[git]` table).
+    pub git: GitConfig,
+
+    /// Scheduled JSON exports for off-machine backups (`[snapshot]` table).
+    pub snapshot: SnapshotConfig,
+
+    /// Off-site backups of the data dir for `backup push` (`[backup]` table).
+    pub backup: BackupConfig,
+
+    /// Move old done todos out of the way (`[archive]` table).
+    pub archive: ArchiveConfig,
+
+    /// Named todo templates for `add --template` (`[templates.<name>]` tables).
+    pub templates: BTreeMap<String, TodoTemplate>,
+
+    /// Named table views for `report <name>` (`[reports.<name>]` tables):
+    /// `filter`, `columns`, `sort`, `group_by`, ... (see `custom_reports`).
+    pub reports: BTreeMap<String, ReportDefinition>,
+
+    /// Named redaction policies for `export --redact` (`[redact]` table):
+    /// `team = ["notes", "time"]`.
+    pub redact: BTreeMap<String, Vec<String>>,
+
+    /// Reminder escalation chains per priority for `notify` (`[escalation]`
+    /// table): `P1 = "-1d, -1h, every 30m"`. `edit --escalate` overrides them
+    /// for one todo.
+    pub escalation: BTreeMap<String, String>,
+
+    /// Link templates for `--ref` per provider (`[links]` table), with
+    /// `{key}` for the key: `jira = "https://acme.atlassian.net/browse/{key}"`.
+    /// GitHub, GitLab and Todoist links work without one.
+    pub links: BTreeMap<String, String>,
+
+    /// Keys for the interactive UI per action (`[keybindings]` table):
+    /// `done = ["x", "space"]`, `quit = "ctrl+q"`. Unlisted actions keep
+    /// their defaults; `keys` prints the result.
+    pub keybindings: BTreeMap<String, KeySpec>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    /// Append an entry for every command that changes todos.
+    pub enabled: bool,
+
+    /// Link entries by hash so `verify-journal` can detect tampering.
+    /// Implies `enabled`.
+    pub hash_chain: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitConfig {
+    /// Keep the database directory in a git repository, created on first
+    /// use, and commit after every command that changes todos. Logs, keys
+    /// and other local state are ignored. `sync init` does the same for one
+    /// directory and adds a remote.
+    pub autosave: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    /// Write a snapshot when a command runs and the last one is older than
+    /// this (e.g. `7d`, `12h`). Unset, snapshots are only taken by
+    /// `snapshot`.
+    pub every: Option<String>,
+
+    /// Folder for snapshots (default: `snapshots` in the data dir).
+    pub folder: Option<PathBuf>,
+
+    /// How many snapshots to keep in the folder, newest first; 0 keeps all.
+    pub keep: usize,
+
+    /// Command run after each snapshot to copy it elsewhere, with the file in
+    /// `$RUSTYTODO_SNAPSHOT`: `rclone copy "$RUSTYTODO_SNAPSHOT" backup:todo`.
+    pub upload_command: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Named targets for `backup push --target <name>`, in any form
+    /// `--target` takes: `offsite = "s3://bucket/todo"`,
+    /// `nas = "https://dav.example.com/todo/"`, `drive = "gdrive:todo"`.
+    pub targets: BTreeMap<String, String>,
+
+    /// Backups to keep on a target, newest first; older ones are deleted
+    /// after each push. 0 keeps all.
+    pub keep: usize,
+
+    /// Seal backups with the database passphrase (asked for if the database
+    /// itself isn't encrypted).
+    pub encrypt: bool,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            targets: BTreeMap::new(),
+            keep: 10,
+            encrypt: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    /// Archive todos completed more than this many days ago whenever a
+    /// command runs. Unset, todos are only archived by `archive`.
+    pub after_days: Option<u32>,
+}
+
+impl ArchiveConfig {
+    /// The longest `after_days` accepted: a hundred years.
+    pub const MAX_AFTER_DAYS: u32 = 36_525;
+
+    /// Check `after_days` is in range (checked on load).
+    pub fn check(&self) -> Result<(), String> {
+        match self.after_days {
+            Some(days) if days > Self::MAX_AFTER_DAYS => Err(format!(
+                "[archive] after_days: {days} is more than {}",
+                Self::MAX_AFTER_DAYS
+            )),
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Also write every log line as JSON to `<data dir>/logs/rustlytodo.log`,
+    /// for `logs show` / `logs tail`.
+    pub file: bool,
+
+    /// Lowest level written to the file: error|warn|info|debug|trace.
+    pub level: String,
+
+    /// Start a new file once the current one reaches this size.
+    pub max_size_kb: u64,
+
+    /// Rotated files to keep besides the current one; older ones are deleted.
+    pub keep: usize,
+
+    /// Log a warning when loading, saving, a query or a sync takes longer
+    /// than this many milliseconds (0 = never).
+    pub slow_ms: u64,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            file: false,
+            level: "info".to_string(),
+            max_size_kb: 1024,
+            keep: 5,
+            slow_ms: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServeConfig {
+    /// Requests per minute each client may make (API token, or address
+    /// without one); 0 = unlimited. `serve token create --rate-limit`
+    /// overrides it per token.
+    pub rate_limit: u32,
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self { rate_limit: 300 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Run the executables in `hooks/` next to config.toml: `pre-<command>`
+    /// before a command (a non-zero exit cancels it) and `post-<event>` after
+    /// one for every todo added, done, reopened, edited or deleted.
+    pub enabled: bool,
+
+    /// Seconds a hook may run before it is killed.
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub enabled: bool,
+
+    /// Broker address as `host:port` (plain TCP, MQTT 3.1.1).
+    pub broker: String,
+
+    pub client_id: String,
+
+    /// Events go to `<prefix>/events/<kind>`, the retained overdue summary to
+    /// `<prefix>/overdue`.
+    pub topic_prefix: String,
+
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+// By hand so the password stays out of debug logs (and the log file).
+impl std::fmt::Debug for MqttConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MqttConfig")
+            .field("enabled", &self.enabled)
+            .field("broker", &self.broker)
+            .field("client_id", &self.client_id)
+            .field("topic_prefix", &self.topic_prefix)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            broker: "localhost:1883".to_string(),
+            client_id: "rustlytodo".to_string(),
+            topic_prefix: "rustlytodo".to_string(),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Theme {
+    #[default]
+    #[serde(alias = "dark")]
     Dark,
+    #[serde(alias = "light")]
     Light,
+    #[serde(alias = "high-contrast")]
     HighContrast,
 }
 
+/// A built-in palette plus color overrides. Written either as a name,
+/// `theme = "Light"`, or as a `[theme]` table with `name` and a
+/// `[theme.colors]` table of `role = "#rrggbb"` entries (see `ui::theme`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "ThemeInput")]
+pub struct ThemeConfig {
+    pub name: Theme,
+    pub colors: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThemeInput {
+    Name(Theme),
+    Table {
+        #[serde(default)]
+        name: Theme,
+        #[serde(default)]
+        colors: BTreeMap<String, String>,
+    },
+}
+
+impl From<Theme> for ThemeConfig {
+    fn from(name: Theme) -> Self {
+        Self {
+            name,
+            colors: BTreeMap::new(),
+        }
+    }
+}
+
+impl From<ThemeInput> for ThemeConfig {
+    fn from(input: ThemeInput) -> Self {
+        match input {
+            ThemeInput::Name(name) => name.into(),
+            ThemeInput::Table { name, colors } => Self { name, colors },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Symbols {
+    #[default]
+    Auto,
+    Unicode,
+    Ascii,
+    NerdFont,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimesheetConfig {
+    /// Default rounding, e.g. `rounding = { increment = 15, mode = "up" }`.
+    pub rounding: Rounding,
+
+    /// Per-project overrides keyed by project name (`[timesheet.projects.Acme]`).
+    pub projects: BTreeMap<String, Rounding>,
+}
+
+impl TimesheetConfig {
+    /// Rounding for `project` (case-insensitive), falling back to the default.
+    pub fn rounding_for(&self, project: &str) -> Rounding {
+        self.projects
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(project))
+            .map_or(self.rounding, |(_, r)| *r)
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             storage_path: None,
-            theme: Theme::Dark,
+            shard_by_project: false,
+            encryption: false,
+            encryption_passphrase_command: None,
+            theme: ThemeConfig::default(),
+            color: ColorChoice::Auto,
+            symbols: Symbols::Auto,
+            absolute_dates: false,
+            timezone: "local".to_string(),
+            priority_levels: Vec::new(),
+            complete_parents: false,
+            titles: TitleRules::default(),
             show_hints: true,
+            confirm: vec![
+                "delete".to_string(),
+                "move".to_string(),
+                "replace".to_string(),
+            ],
+            duplicate_ids: DuplicatePolicy::default(),
+            journal: JournalConfig::default(),
+            timesheet: TimesheetConfig::default(),
+            urgency: UrgencyCoefficients::default(),
+            mqtt: MqttConfig::default(),
+            serve: ServeConfig::default(),
+            hooks: HooksConfig::default(),
+            log: LogConfig::default(),
+            git: GitConfig::default(),
+            snapshot: SnapshotConfig::default(),
+            backup: BackupConfig::default(),
+            archive: ArchiveConfig::default(),
+            templates: BTreeMap::new(),
+            reports: BTreeMap::new(),
+            redact: BTreeMap::new(),
+            escalation: BTreeMap::new(),
+            links: BTreeMap::new(),
+            keybindings: BTreeMap::new(),
         }
     }
 }
 
 impl AppConfig {
+    /// Does `operation` need confirmation under the `confirm` policy?
+    pub fn needs_confirmation(&self, operation: &str) -> bool {
+        self.confirm
+            .iter()
+            .any(|op| op.trim().eq_ignore_ascii_case(operation))
+    }
+
+    /// The `[links]` template for `provider` (case-insensitive).
+    pub fn link_template(&self, provider: &str) -> Option<&str> {
+        self.links
+            .iter()
+            .find(|(p, _)| p.eq_ignore_ascii_case(provider))
+            .map(|(_, t)| t.as_str())
+    }
+
+    /// Template called `name` (case-insensitive).
+    pub fn template(&self, name: &str) -> Option<&TodoTemplate> {
+        self.templates
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, t)| t)
+    }
+
+    /// Report called `name` (case-insensitive), with its name as written.
+    pub fn report(&self, name: &str) -> Option<(&str, &ReportDefinition)> {
+        self.reports
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name.trim()))
+            .map(|(n, r)| (n.as_str(), r))
+    }
+
+    /// Check every `[reports]` entry. Errors name the first bad one.
+    pub fn check_reports(&self) -> Result<(), String> {
+        let now = time::OffsetDateTime::now_utc();
+        self.reports.iter().try_for_each(|(name, report)| {
+            report
+                .compile(self.urgency.clone(), now)
+                .map(|_| ())
+                .map_err(|e| format!("[reports.{name}] {e}"))
+        })
+    }
+
+    /// The `[escalation]` chains by priority. Errors name the first bad entry.
+    pub fn escalation_chains(&self) -> Result<BTreeMap<Priority, Escalation>, String> {
+        self.escalation
+            .iter()
+            .map(|(priority, chain)| {
+                let priority = Priority::parse(priority)
+                    .map_err(|e| format!("[escalation] {priority}: {e}"))?;
+                let chain = Escalation::parse(chain)
+                    .map_err(|e| format!("[escalation] {}: {e}", priority.label()))?;
+                Ok((priority, chain))
+            })
+            .collect()
+    }
+
+    /// The default keys with `[keybindings]` applied (checked on load).
+    pub fn key_map(&self) -> Result<KeyMap, String> {
+        KeyMap::from_config(&self.keybindings)
+    }
+
+    /// The theme's colors with `[theme.colors]` applied (checked on load).
+    pub fn theme_spec(&self) -> Result<ThemeSpec, String> {
+        ThemeSpec::resolve(&self.theme)
+    }
+
+    /// Priority labels from `[[priority_levels]]` (checked on load).
+    pub fn priority_scheme(&self) -> Result<PriorityScheme, String> {
+        for level in &self.priority_levels {
+            if let Some(color) = &level.color {
+                Rgb::parse_hex(color)
+                    .map_err(|e| format!("[[priority_levels]] {}: {e}", level.label.trim()))?;
+            }
+        }
+        PriorityScheme::from_config(&self.priority_levels)
+    }
+
+    /// The zone `timezone` names (checked on load). `local` falls back to UTC,
+    /// with a warning, where the system's zone can't be found.
+    pub fn zone(&self) -> Result<Zone, String> {
+        let name = self.timezone.trim();
+        if name.is_empty() || name.eq_ignore_ascii_case("local") {
+            return Ok(local_zone());
+        }
+        match Zone::parse_offset(name) {
+            Some(zone) => Ok(zone),
+            None => named_zone(name),
+        }
+    }
+
     pub fn config_file_path(paths: &AppPaths) -> PathBuf {
         paths.config_dir.join("config.toml")
     }
@@ -58,9 +620,7 @@ impl AppConfig {
             let s = std::fs::read_to_string(&path)
                 .with_context(|| format!("failed reading config file: {}", path.display()))?;
 
-            let cfg: AppConfig =
-                toml::from_str(&s).with_context(|| "failed parsing config.toml")?;
-            Ok(cfg)
+            Self::parse(&s)
         } else {
             let cfg = AppConfig::default();
             cfg.save_to(&path)?;
@@ -68,18 +628,33 @@ impl AppConfig {
         }
     }
 
-    fn save_to(&self, path: &PathBuf) -> Result<()> {
+    fn parse(text: &str) -> Result<Self> {
+        let cfg: Self = toml::from_str(text).with_context(|| "failed parsing config.toml")?;
+        // Catch bad keybindings, colors, priority levels and reports now
+        // rather than when they're first used.
+        cfg.key_map()
+            .and_then(|_| cfg.theme_spec())
+            .and_then(|_| cfg.priority_scheme())
+            .and_then(|_| cfg.zone())
+            .and_then(|_| cfg.check_reports())
+            .and_then(|_| cfg.archive.check())
+            .map_err(anyhow::Error::msg)
+            .context("invalid config.toml")?;
+        Ok(cfg)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
         let toml_str =
             toml::to_string(self).with_context(|| "failed serializing config to TOML")?;
 
         // Ensure parent directory exists.
         if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).with_context(|| {
+            perms::create_dir(parent).with_context(|| {
                 format!("failed creating config directory: {}", parent.display())
             })?;
         }
 
-        std::fs::write(path, toml_str)
+        perms::write(path, toml_str)
             .with_context(|| format!("failed writing config file: {}", path.display()))?;
 
         Ok(())
@@ -89,8 +664,99 @@ impl AppConfig {
     pub fn resolve_db_path(&self, paths: &AppPaths) -> PathBuf {
         self.storage_path
             .clone()
+            .map(crate::infra::paths::long_path)
             .unwrap_or_else(|| paths.data_dir.join("db.json"))
     }
+
+    /// Key for the database at `db_path`: needed to encrypt it, or to read it
+    /// if it is already encrypted (so turning `encryption` off can decrypt).
+    pub fn db_key(&self, db_path: &Path) -> Result<Option<DbKey>> {
+        let encrypted = fs_repo::is_encrypted_on_disk(db_path);
+        if !self.encryption && !encrypted {
+            return Ok(None);
+        }
+        // A new passphrase is asked for twice.
+        let passphrase =
+            db_crypto::passphrase(self.encryption_passphrase_command.as_deref(), !encrypted)?;
+        Ok(Some(DbKey::new(&passphrase)?))
+    }
+}
+
+/// The system's zone (`TZ` if set, else `/etc/localtime` or the Windows
+/// setting), or UTC with a warning where it can't be found.
+fn local_zone() -> Zone {
+    match jiff::tz::TimeZone::try_system() {
+        Ok(tz) => Zone::Rules(tz),
+        Err(e) => {
+            tracing::warn!("can't find the system timezone ({e}); showing times in UTC");
+            Zone::default()
+        }
+    }
+}
+
+/// A zone from the tz database (the system's, or the copy built in where
+/// there is none; `TZDIR` overrides where it is).
+fn named_zone(name: &str) -> Result<Zone, String> {
+    jiff::tz::db().get(name).map(Zone::Rules).map_err(|_| {
+        format!(
+            "unknown timezone {name:?} (use local, a name like Europe/Berlin, or an offset like +02:00)"
+        )
+    })
+}
+
+/// Notices edits to config.toml so long-running modes (GUI, `serve`) can
+/// apply them without a restart.
+#[derive(Debug, Clone)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    /// Hash of the contents last seen (`None` before the first read).
+    seen: Option<u64>,
+}
+
+impl ConfigWatcher {
+    /// Watch the config file in `paths`; the current contents count as seen.
+    pub fn new(paths: &AppPaths) -> Self {
+        let path = AppConfig::config_file_path(paths);
+        let seen = std::fs::read(&path).ok().map(|b| content_hash(&b));
+        Self { path, seen }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// The new config if the file changed since the last call.
+    ///
+    /// A file that fails to parse is reported once (the caller should keep
+    /// its current config) and not again until it changes.
+    pub fn poll(&mut self) -> Result<Option<AppConfig>> {
+        let Ok(bytes) = std::fs::read(&self.path) else {
+            // Removed or mid-replace: keep what we have.
+            return Ok(None);
+        };
+        let hash = content_hash(&bytes);
+        if self.seen == Some(hash) {
+            return Ok(None);
+        }
+        self.seen = Some(hash);
+        let text = String::from_utf8_lossy(&bytes);
+        AppConfig::parse(&text).map(Some)
+    }
+
+    /// Re-read the file now, changed or not (for an explicit "reload").
+    pub fn reload(&mut self) -> Result<AppConfig> {
+        self.seen = None;
+        self.poll()?
+            .with_context(|| format!("config file not readable: {}", self.path.display()))
+    }
+}
+
+fn content_hash(bytes: &[u8]) -> u64 {
+    use std::hash::{DefaultHasher, Hasher};
+
+    let mut h = DefaultHasher::new();
+    h.write(bytes);
+    h.finish()
 }
 
 #[cfg(test)]
@@ -103,11 +769,196 @@ mod tests {
         let cfg = AppConfig::default();
         let s = toml::to_string_pretty(&cfg).unwrap();
         let parsed: AppConfig = toml::from_str(&s).unwrap();
-        assert!(matches!(parsed.theme, Theme::Dark));
+        assert!(matches!(parsed.theme.name, Theme::Dark));
         assert!(parsed.show_hints);
         assert!(parsed.storage_path.is_none());
     }
 
+    #[test]
+    fn timesheet_rounding_falls_back_per_project() {
+        let cfg: AppConfig = toml::from_str(
+            r#"
+            [timesheet]
+            rounding = { increment = 15 }
+
+            [timesheet.projects.Acme]
+            increment = 6
+            mode = "nearest"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(cfg.timesheet.rounding_for("acme").increment, 6);
+        assert_eq!(cfg.timesheet.rounding_for("Other").increment, 15);
+        assert!(cfg.show_hints);
+    }
+
+    #[test]
+    fn escalation_chains_are_keyed_by_priority() {
+        let cfg: AppConfig = toml::from_str(
+            r#"
+            [escalation]
+            p1 = "-1d, -1h, every 30m"
+            P2 = "-1h"
+            "#,
+        )
+        .unwrap();
+        let chains = cfg.escalation_chains().unwrap();
+        assert_eq!(chains[&Priority::P1].to_string(), "-1d, -1h, every 30m");
+        assert_eq!(chains.len(), 2);
+
+        let bad: AppConfig = toml::from_str("[escalation]\nP9 = \"-1h\"\n").unwrap();
+        let err = bad.escalation_chains().unwrap_err();
+        assert!(err.starts_with("[escalation] P9:"), "{err}");
+    }
+
+    #[test]
+    fn bad_keybindings_fail_the_load() {
+        let cfg = AppConfig::parse("[keybindings]\ndone = [\"x\", \"enter\"]\nquit = \"ctrl+q\"\n")
+            .unwrap();
+        let keys = cfg.key_map().unwrap();
+        assert_eq!(keys.keys(crate::app::keymap::Action::Done).len(), 2);
+
+        let err = AppConfig::parse("[keybindings]\nadd = \"ctrl+\"\n").unwrap_err();
+        let message = format!("{err:#}");
+        assert!(
+            message.starts_with("invalid config.toml: [keybindings] add: unknown key"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn reports_load_by_name_and_bad_ones_fail_the_load() {
+        let cfg = AppConfig::parse(
+            "[reports.Standup]\nfilter = \"status:open\"\ncolumns = [\"id\", \"title\"]\n",
+        )
+        .unwrap();
+        let (name, report) = cfg.report("standup").unwrap();
+        assert_eq!(name, "Standup");
+        assert_eq!(report.columns, ["id", "title"]);
+        assert!(cfg.report("weekly").is_none());
+
+        let err = AppConfig::parse("[reports.weekly]\nsort = [\"size\"]\n").unwrap_err();
+        let message = format!("{err:#}");
+        assert!(
+            message.starts_with("invalid config.toml: [reports.weekly] unknown sort key size"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn archive_after_days_is_bounded() {
+        let cfg = AppConfig::parse("[archive]\nafter_days = 30\n").unwrap();
+        assert_eq!(cfg.archive.after_days, Some(30));
+
+        let err = AppConfig::parse("[archive]\nafter_days = 4294967295\n").unwrap_err();
+        let message = format!("{err:#}");
+        assert!(
+            message.starts_with("invalid config.toml: [archive] after_days: 4294967295"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn priority_levels_load_as_tables() {
+        let cfg = AppConfig::parse(
+            "[[priority_levels]]\nlabel = \"High\"\npriority = \"P1\"\ncolor = \"#d70000\"\n\
+             [[priority_levels]]\nlabel = \"Low\"\npriority = \"P3\"\n",
+        )
+        .unwrap();
+        let scheme = cfg.priority_scheme().unwrap();
+        assert_eq!(scheme.label(Priority::P2), "Low");
+        assert_eq!(scheme.color(Priority::P1), Some("#d70000"));
+
+        let err = AppConfig::parse(
+            "[[priority_levels]]\nlabel = \"High\"\npriority = \"P1\"\ncolor = \"red\"\n",
+        )
+        .unwrap_err();
+        let message = format!("{err:#}");
+        assert!(
+            message.starts_with("invalid config.toml: [[priority_levels]] High: invalid color"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn themes_load_as_a_name_or_a_table_with_colors() {
+        let cfg = AppConfig::parse("theme = \"HighContrast\"\n").unwrap();
+        assert_eq!(cfg.theme.name, Theme::HighContrast);
+        assert!(cfg.theme.colors.is_empty());
+
+        let cfg =
+            AppConfig::parse("[theme]\nname = \"light\"\n\n[theme.colors]\ndue = \"#0000ff\"\n")
+                .unwrap();
+        assert_eq!(cfg.theme.name, Theme::Light);
+        let spec = cfg.theme_spec().unwrap();
+        assert_eq!(
+            spec.color(crate::ui::theme::Role::Due),
+            crate::ui::theme::Rgb(0, 0, 255)
+        );
+        let saved = toml::to_string(&cfg).unwrap();
+        assert_eq!(AppConfig::parse(&saved).unwrap().theme, cfg.theme);
+
+        let err = AppConfig::parse("[theme.colors]\ndue = \"blue\"\n").unwrap_err();
+        let message = format!("{err:#}");
+        assert!(
+            message.starts_with("invalid config.toml: [theme.colors] due: invalid color"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn timezones_load_by_name_or_offset() {
+        use time::macros::datetime;
+
+        let zone = |tz: &str| AppConfig::parse(&format!("timezone = \"{tz}\"\n"));
+        let at = datetime!(2026-07-01 12:00 UTC);
+        let offset = |tz| {
+            zone(tz)
+                .unwrap()
+                .zone()
+                .unwrap()
+                .offset_at(at)
+                .whole_hours()
+        };
+
+        assert_eq!(offset("+05:00"), 5);
+        assert_eq!(offset("UTC"), 0);
+        if Path::new("/usr/share/zoneinfo/Europe/Berlin").exists() {
+            assert_eq!(offset("Europe/Berlin"), 2);
+        }
+        assert!(AppConfig::default().zone().is_ok());
+
+        for bad in ["Mars/Olympus_Mons", "../../etc/passwd"] {
+            let message = format!("{:#}", zone(bad).unwrap_err());
+            assert!(message.contains("unknown timezone"), "{message}");
+        }
+    }
+
+    #[test]
+    fn watcher_reports_each_change_once() {
+        let dir = tempdir().unwrap();
+        let paths = AppPaths {
+            config_dir: dir.path().to_path_buf(),
+            data_dir: dir.path().to_path_buf(),
+        };
+        AppConfig::load_or_create(&paths).unwrap();
+        let path = AppConfig::config_file_path(&paths);
+
+        let mut watcher = ConfigWatcher::new(&paths);
+        assert!(watcher.poll().unwrap().is_none());
+
+        std::fs::write(&path, "theme = \"Light\"\n").unwrap();
+        let cfg = watcher.poll().unwrap().expect("changed");
+        assert!(matches!(cfg.theme.name, Theme::Light));
+        assert!(watcher.poll().unwrap().is_none());
+
+        std::fs::write(&path, "theme = \"Purple\"\n").unwrap();
+        assert!(watcher.poll().is_err());
+        assert!(watcher.poll().unwrap().is_none());
+        assert!(watcher.reload().is_err());
+    }
+
     #[test]
     fn load_or_create_creates_file_when_missing() {
         let dir = tempdir().unwrap();
@@ -117,7 +968,7 @@ mod tests {
         };
 
         let cfg = AppConfig::load_or_create(&paths).unwrap();
-        assert!(matches!(cfg.theme, Theme::Dark));
+        assert!(matches!(cfg.theme.name, Theme::Dark));
 
         let config_path = AppConfig::config_file_path(&paths);
         assert!(config_path.exists());
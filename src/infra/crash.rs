@@ -0,0 +1,175 @@
+//! Crash reports for panics.
+//!
+//! [`install`] replaces the panic hook: after the usual panic message, a plain
+//! text report goes to `<data dir>/crashes/crash-<timestamp>-<pid>.txt` with the
+//! version, the command line (secrets blanked out), a backtrace and the last
+//! few journal entries, and the path is printed so it can be attached to a
+//! bug report. Writing the report never panics itself; if it fails, that is
+//! printed instead.
+
+use std::{
+    backtrace::Backtrace,
+    fmt::Write as _,
+    panic::PanicHookInfo,
+    path::{Path, PathBuf},
+};
+
+use time::{OffsetDateTime, format_description::well_known::Rfc3339, macros::format_description};
+
+use crate::infra::{
+    journal::{self, JournalEntry},
+    perms,
+};
+
+/// Journal entries included in a report.
+const LAST_OPERATIONS: usize = 10;
+
+/// Flags whose value must not end up in a report.
+const SECRET_FLAGS: [&str; 4] = ["--passphrase", "--password", "--token", "--secret"];
+
+/// Where crash reports are written.
+pub fn crash_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("crashes")
+}
+
+/// Install the crash-reporting panic hook. `journal` is the database's
+/// journal file, read for the last operations.
+pub fn install(data_dir: PathBuf, journal: PathBuf) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let args = redact_args(std::env::args().skip(1));
+        // Without the database key, sealed entries are left out.
+        let recent = journal::tail(&journal, None, LAST_OPERATIONS).unwrap_or_default();
+        let text = report(
+            info,
+            &args,
+            &Backtrace::force_capture().to_string(),
+            &recent,
+            OffsetDateTime::now_utc(),
+        );
+        match write_report(&crash_dir(&data_dir), &text) {
+            Ok(path) => eprintln!(
+                "rustlytodo crashed; a report was saved to {}\nPlease attach it when reporting the bug.",
+                path.display()
+            ),
+            Err(e) => eprintln!("rustlytodo crashed and the crash report could not be saved: {e}"),
+        }
+    }));
+}
+
+/// Replace the values of [`SECRET_FLAGS`] (`--flag value` and `--flag=value`).
+pub fn redact_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut hide_next = false;
+    for arg in args {
+        if std::mem::take(&mut hide_next) {
+            out.push("<redacted>".to_string());
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((flag, _)) if SECRET_FLAGS.contains(&flag) => {
+                out.push(format!("{flag}=<redacted>"));
+            }
+            _ => {
+                hide_next = SECRET_FLAGS.contains(&arg.as_str());
+                out.push(arg);
+            }
+        }
+    }
+    out
+}
+
+/// The text of a crash report.
+pub fn report(
+    info: &PanicHookInfo<'_>,
+    args: &[String],
+    backtrace: &str,
+    recent: &[JournalEntry],
+    now: OffsetDateTime,
+) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "(no message)".to_string());
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut text = String::new();
+    let _ = writeln!(text, "rustlytodo crash report");
+    let _ = writeln!(
+        text,
+        "time:     {}",
+        now.format(&Rfc3339).unwrap_or_default()
+    );
+    let _ = writeln!(text, "version:  {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        text,
+        "platform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(text, "args:     {}", args.join(" "));
+    let _ = writeln!(text, "panic:    {message}");
+    let _ = writeln!(text, "at:       {location}");
+
+    let _ = writeln!(text, "\nLast operations:");
+    if recent.is_empty() {
+        let _ = writeln!(text, "  (none recorded)");
+    }
+    for e in recent {
+        let _ = writeln!(
+            text,
+            "  #{} {} {} {} ({} change{})",
+            e.seq,
+            e.at.format(&Rfc3339).unwrap_or_default(),
+            e.user,
+            e.command,
+            e.changes.len(),
+            if e.changes.len() == 1 { "" } else { "s" }
+        );
+    }
+
+    let _ = writeln!(text, "\nBacktrace:\n{backtrace}");
+    text
+}
+
+fn write_report(dir: &Path, text: &str) -> std::io::Result<PathBuf> {
+    perms::create_dir(dir)?;
+    let stamp = OffsetDateTime::now_utc()
+        .format(format_description!(
+            "[year][month][day]-[hour][minute][second]"
+        ))
+        .unwrap_or_default();
+    let path = dir.join(format!("crash-{stamp}-{}.txt", std::process::id()));
+    perms::write(&path, text)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_flag_values_are_redacted() {
+        let args = [
+            "sync",
+            "keys",
+            "invite",
+            "--passphrase",
+            "hunter2",
+            "--token=abc",
+            "--out",
+            "invite.txt",
+        ]
+        .map(String::from);
+        assert_eq!(
+            redact_args(args).join(" "),
+            "sync keys invite --passphrase <redacted> --token=<redacted> --out invite.txt"
+        );
+    }
+}
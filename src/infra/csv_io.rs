@@ -1,13 +1,15 @@
 //! CSV import/export helpers.
 //!
 //! CSV is intentionally "basic": it flattens a subset of fields for compatibility.
+//! Subtasks and dependencies survive it as `parent_id` and `depends_on`
+//! columns of todo ids; both are optional on import.
 
 use std::{collections::BTreeSet, path::Path};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
 
-use crate::domain::todo::{DueAt, Notes, Priority, ProjectName, Tag, Title, Todo};
+use crate::domain::todo::{DueAt, Notes, Priority, ProjectName, Tag, Title, Todo, TodoId};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct CsvTodoRow {
@@ -17,17 +19,19 @@ struct CsvTodoRow {
     status: String,   // "open" or "done"
     priority: String, // "P1".."P4"
     project: String,
-    due: Option<String>,   // RFC3339
-    notes: Option<String>, // plain text
-    tags: Option<String>,  // "tag1,tag2"
+    due: Option<String>,        // RFC3339
+    notes: Option<String>,      // plain text
+    tags: Option<String>,       // "tag1,tag2"
+    parent_id: Option<String>,  // UUID
+    depends_on: Option<String>, // "uuid1;uuid2"
 }
 
 pub fn export_csv(path: &Path, todos: &[Todo]) -> Result<()> {
-    if let Some(parent) = path.parent() {
-        if !parent.as_os_str().is_empty() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("failed creating export dir: {}", parent.display()))?;
-        }
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating export dir: {}", parent.display()))?;
     }
 
     let mut wtr = csv::Writer::from_path(path)
@@ -57,6 +61,14 @@ pub fn export_csv(path: &Path, todos: &[Todo]) -> Result<()> {
             due: t.due.map(|d| d.format_rfc3339()),
             notes: t.notes.as_ref().map(|n| n.as_str().to_string()),
             tags,
+            parent_id: t.parent.map(|p| p.as_uuid_str()),
+            depends_on: (!t.depends_on.is_empty()).then(|| {
+                t.depends_on
+                    .iter()
+                    .map(|d| d.as_uuid_str())
+                    .collect::<Vec<_>>()
+                    .join(";")
+            }),
         };
         wtr.serialize(row).context("failed writing csv row")?;
     }
@@ -70,6 +82,8 @@ pub fn import_csv(path: &Path) -> Result<Vec<Todo>> {
         .with_context(|| format!("failed opening csv file: {}", path.display()))?;
 
     let mut todos = Vec::new();
+    // (parent_id, depends_on) per row, resolved once every id is known.
+    let mut links = Vec::new();
 
     for rec in rdr.deserialize::<CsvTodoRow>() {
         let row = rec.context("failed reading csv row")?;
@@ -107,7 +121,108 @@ pub fn import_csv(path: &Path) -> Result<Vec<Todo>> {
         }
 
         todos.push(t);
+        links.push((row.parent_id, row.depends_on));
+    }
+
+    let ids: Vec<TodoId> = todos.iter().map(|t| t.id).collect();
+    for (i, (t, (parent, deps))) in todos.iter_mut().zip(links).enumerate() {
+        // Row 1 is the header.
+        let row = i + 2;
+        if let Some(parent) = parent.as_deref().filter(|p| !p.trim().is_empty()) {
+            t.parent = Some(
+                resolve_ref(&ids, t.id, parent).with_context(|| format!("row {row}: parent_id"))?,
+            );
+        }
+        for dep in deps
+            .iter()
+            .flat_map(|d| d.split(';'))
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+        {
+            t.depends_on.insert(
+                resolve_ref(&ids, t.id, dep).with_context(|| format!("row {row}: depends_on"))?,
+            );
+        }
     }
 
     Ok(todos)
 }
+
+/// A `parent_id`/`depends_on` reference: a full id (of a row or a todo
+/// already in the list being imported into), or the short id of a row.
+fn resolve_ref(ids: &[TodoId], own: TodoId, input: &str) -> Result<TodoId> {
+    let id = match TodoId::parse_uuid(input) {
+        Ok(id) => id,
+        Err(_) => {
+            let prefix = input.to_ascii_lowercase();
+            let mut matches = ids
+                .iter()
+                .filter(|id| id.as_uuid_str().starts_with(&prefix));
+            match (matches.next(), matches.next()) {
+                (Some(id), None) => *id,
+                (None, _) => bail!("no row has id {input}"),
+                (Some(_), Some(_)) => bail!("id {input} matches several rows"),
+            }
+        }
+    };
+    if id == own {
+        bail!("a todo cannot refer to itself");
+    }
+    Ok(id)
+}
+
+/// Write `stats time` rows as CSV: `<group>,minutes,hours,entries,todos`.
+pub fn write_time_rows(
+    w: impl std::io::Write,
+    group: crate::app::stats::TimeGroup,
+    rows: &[crate::app::stats::TimeRow],
+) -> Result<()> {
+    let mut wtr = csv::Writer::from_writer(w);
+    wtr.write_record([group.label(), "minutes", "hours", "entries", "todos"])
+        .context("failed writing csv header")?;
+
+    for r in rows {
+        let hours = r.tracked.as_seconds_f64() / 3600.0;
+        wtr.write_record([
+            r.key.clone(),
+            crate::app::stats::minutes(r.tracked).to_string(),
+            format!("{hours:.2}"),
+            r.entries.to_string(),
+            r.todos.to_string(),
+        ])
+        .context("failed writing csv row")?;
+    }
+
+    wtr.flush().context("failed flushing csv output")?;
+    Ok(())
+}
+
+/// Write a timesheet as CSV: `date,project,task,duration,hours,notes`.
+pub fn export_timesheet(path: &Path, rows: &[crate::app::timesheet::TimesheetRow]) -> Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating export dir: {}", parent.display()))?;
+    }
+
+    let mut wtr = csv::Writer::from_path(path)
+        .with_context(|| format!("failed creating csv file: {}", path.display()))?;
+    wtr.write_record(["date", "project", "task", "duration", "hours", "notes"])
+        .context("failed writing csv header")?;
+
+    for r in rows {
+        wtr.write_record([
+            r.date.to_string(),
+            r.project.clone(),
+            r.task.clone(),
+            crate::app::timesheet::format_hm(r.minutes),
+            format!("{:.2}", f64::from(r.minutes) / 60.0),
+            r.notes.clone(),
+        ])
+        .context("failed writing csv row")?;
+    }
+
+    wtr.flush().context("failed flushing csv writer")?;
+    Ok(())
+}
@@ -0,0 +1,288 @@
+//! Encryption at rest for the database (`encryption = true` in config.toml).
+//!
+//! An encrypted database file (or shard, or undo history) is a small JSON
+//! envelope around the usual contents, sealed with ChaCha20-Poly1305 under a
+//! key derived from a passphrase with Argon2. The salt is stored in the
+//! envelope and kept across saves, so the slow derivation runs once per
+//! process; every save gets a fresh nonce.
+//!
+//! The passphrase comes from, in order: `$RUSTYTODO_DB_PASSPHRASE`, the
+//! `encryption_passphrase_command` from config.toml (e.g. `pass show todo`),
+//! the system keyring (service `rustlytodo`, account `database`, through
+//! `secret-tool` or macOS `security`), and finally a prompt on the terminal.
+//!
+//! Journal lines and sync conflict files are sealed with the same key. Logs
+//! and routines are not encrypted.
+
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD as B64};
+use serde::{Deserialize, Serialize};
+
+use crate::infra::sync_crypto::{decode, decrypt, encrypt, random};
+
+const FORMAT: &str = "rustytodo-encrypted-v1";
+/// What every envelope starts with (they are written compactly).
+const MAGIC: &[u8] = b"{\"format\":\"rustytodo-encrypted-v1\"";
+
+pub const PASSPHRASE_ENV: &str = "RUSTYTODO_DB_PASSPHRASE";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// A salt and the key Argon2 derived from it.
+type Derived = ([u8; 16], [u8; 32]);
+
+/// A passphrase and the key derived from it, shared between clones so a
+/// long-running process (the GUI, `serve`) derives it once.
+#[derive(Clone)]
+pub struct DbKey {
+    passphrase: Arc<str>,
+    derived: Arc<Mutex<Option<Derived>>>,
+}
+
+// By hand so the passphrase stays out of debug logs.
+impl std::fmt::Debug for DbKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DbKey(<redacted>)")
+    }
+}
+
+impl DbKey {
+    pub fn new(passphrase: &str) -> Result<Self> {
+        if passphrase.is_empty() {
+            bail!("database passphrase cannot be empty");
+        }
+        Ok(Self {
+            passphrase: passphrase.into(),
+            derived: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Key for `salt`, derived (and remembered) if it isn't the current one.
+    fn key_for(&self, salt: Option<[u8; 16]>) -> Result<Derived> {
+        let mut derived = self.derived.lock().unwrap_or_else(|e| e.into_inner());
+        match (*derived, salt) {
+            (Some(d), None) => return Ok(d),
+            (Some(d), Some(s)) if d.0 == s => return Ok(d),
+            _ => {}
+        }
+        let salt = match salt {
+            Some(s) => s,
+            None => random::<16>()?,
+        };
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(self.passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("failed deriving key from passphrase: {e}"))?;
+        *derived = Some((salt, key));
+        Ok((salt, key))
+    }
+
+    /// Encrypt `plaintext` into an envelope.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let (salt, key) = self.key_for(None)?;
+        let (nonce, ciphertext) = encrypt(&key, FORMAT.as_bytes(), plaintext)?;
+        let envelope = Envelope {
+            format: FORMAT.to_string(),
+            salt: B64.encode(salt),
+            nonce: B64.encode(nonce),
+            ciphertext: B64.encode(ciphertext),
+        };
+        Ok(serde_json::to_vec(&envelope)?)
+    }
+
+    /// Decrypt an envelope written by [`Self::seal`].
+    pub fn open(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let envelope: Envelope =
+            serde_json::from_slice(bytes).context("failed parsing encrypted database")?;
+        let salt: [u8; 16] = decode(&envelope.salt, "salt")?
+            .try_into()
+            .map_err(|_| anyhow!("invalid salt in encrypted database"))?;
+        let (_, key) = self.key_for(Some(salt))?;
+        decrypt(
+            &key,
+            &decode(&envelope.nonce, "nonce")?,
+            FORMAT.as_bytes(),
+            &decode(&envelope.ciphertext, "ciphertext")?,
+        )
+        .ok_or_else(|| anyhow!("wrong passphrase or corrupted database"))
+    }
+}
+
+/// Is `bytes` an envelope written by [`DbKey::seal`]?
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.trim_ascii_start().starts_with(MAGIC)
+}
+
+/// Does the file at `path` start like an envelope? Missing files don't.
+pub fn file_is_encrypted(path: &Path) -> bool {
+    use std::io::Read;
+    let mut head = [0u8; 64];
+    let Ok(mut f) = std::fs::File::open(path) else {
+        return false;
+    };
+    let n = f.read(&mut head).unwrap_or(0);
+    is_encrypted(&head[..n])
+}
+
+/// The database passphrase, from the first source that has one (see the
+/// module docs). `confirm` asks twice when prompting, for a new database.
+pub fn passphrase(command: Option<&str>, confirm: bool) -> Result<String> {
+    if let Ok(p) = std::env::var(PASSPHRASE_ENV)
+        && !p.is_empty()
+    {
+        return Ok(p);
+    }
+    if let Some(command) = command.filter(|c| !c.trim().is_empty()) {
+        return run_passphrase_command(command);
+    }
+    if let Some(p) = keyring() {
+        return Ok(p);
+    }
+    prompt(confirm)
+}
+
+fn run_passphrase_command(command: &str) -> Result<String> {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(command);
+        c
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    };
+    let output = cmd
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .context("failed running encryption_passphrase_command")?;
+    if !output.status.success() {
+        bail!("encryption_passphrase_command failed ({})", output.status);
+    }
+    let text = String::from_utf8(output.stdout)
+        .context("encryption_passphrase_command printed invalid UTF-8")?;
+    // Only the first line: `pass show` prints more after it.
+    Ok(text.lines().next().unwrap_or_default().to_string())
+}
+
+/// The passphrase stored in the system keyring, if a keyring tool has one.
+fn keyring() -> Option<String> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        let mut c = Command::new("security");
+        c.args([
+            "find-generic-password",
+            "-s",
+            "rustlytodo",
+            "-a",
+            "database",
+            "-w",
+        ]);
+        c
+    } else if cfg!(unix) {
+        let mut c = Command::new("secret-tool");
+        c.args(["lookup", "service", "rustlytodo", "account", "database"]);
+        c
+    } else {
+        return None;
+    };
+    let output = cmd
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let text = String::from_utf8(output.stdout).ok()?;
+    let p = text.lines().next()?.to_string();
+    (output.status.success() && !p.is_empty()).then_some(p)
+}
+
+fn prompt(confirm: bool) -> Result<String> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() {
+        bail!(
+            "the database is encrypted: set {PASSPHRASE_ENV}, encryption_passphrase_command in config.toml, or run in a terminal"
+        );
+    }
+    let ask = |question: &str| -> Result<String> {
+        eprint!("{question}");
+        std::io::stderr().flush()?;
+        let echo_off = set_echo(false);
+        let mut line = String::new();
+        let read = std::io::stdin().lock().read_line(&mut line);
+        if echo_off {
+            set_echo(true);
+            eprintln!();
+        }
+        read?;
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    };
+    let p = ask("Database passphrase: ")?;
+    if confirm && ask("Repeat passphrase: ")? != p {
+        bail!("passphrases don't match");
+    }
+    Ok(p)
+}
+
+/// Turn terminal echo on or off; returns whether it worked.
+fn set_echo(on: bool) -> bool {
+    if !cfg!(unix) {
+        return false;
+    }
+    Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(Stdio::inherit())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelopes_open_with_the_same_passphrase_only() {
+        let key = DbKey::new("hunter2").unwrap();
+        let sealed = key.seal(b"{\"todos\":[]}").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!is_encrypted(b"{\"schema_version\":2}"));
+        assert!(!String::from_utf8_lossy(&sealed).contains("todos"));
+
+        // A fresh key (another process) finds the salt in the envelope.
+        let again = DbKey::new("hunter2").unwrap();
+        assert_eq!(again.open(&sealed).unwrap(), b"{\"todos\":[]}");
+        // ...and keeps it for its own saves.
+        let resealed = again.seal(b"y").unwrap();
+        let salt = |b: &[u8]| serde_json::from_slice::<Envelope>(b).unwrap().salt;
+        assert_eq!(salt(&resealed), salt(&sealed));
+
+        let wrong = DbKey::new("hunter3").unwrap().open(&sealed).unwrap_err();
+        assert_eq!(wrong.to_string(), "wrong passphrase or corrupted database");
+        assert!(DbKey::new("").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn passphrase_command_prints_the_first_line() {
+        assert_eq!(
+            run_passphrase_command("printf 's3cret\\nurl: x\\n'").unwrap(),
+            "s3cret"
+        );
+        assert!(run_passphrase_command("exit 3").is_err());
+    }
+}
@@ -1,13 +1,51 @@
 //! Versioned on-disk schema definitions.
 //!
 //! Keep these in infra so domain remains stable and pure.
+//!
+//! Files at the current version are decoded straight into todos as they are
+//! read ([`load_reader`]); only older versions go through a `Value` tree so
+//! migrations can rewrite them. That keeps loading a large database to roughly
+//! the size of the todos themselves.
+//!
+//! History: v1 held only `todos`; v2 added `projects` (see
+//! [`Project`]).
+//!
+//! A new version adds a `vN` module with its file layout and a step to
+//! [`MIGRATIONS`] that rewrites the previous version's document. Files are
+//! upgraded one step at a time, and the file as it was before is kept as a
+//! backup (see `fs_repo::back_up_before_migration`). [`describe_changes`]
+//! reports what a migration does for `migrate --dry-run`.
+use std::{fmt, io::Read};
+
 use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{IgnoredAny, MapAccess, Visitor},
+};
 use serde_json::Value;
 
-use crate::domain::todo::Todo;
+use crate::domain::{project::Project, todo::Todo};
+
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// Versions `load_any` (and so `import`) can read.
+pub const SUPPORTED_VERSIONS: &[u32] = &[1, 2];
+
+/// One step of the upgrade path between two schema versions.
+pub struct Migration {
+    pub from: u32,
+    pub to: u32,
+    pub description: &'static str,
+    pub apply: fn(Value) -> Result<Value>,
+}
 
-pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+/// Every known step, oldest first.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    to: 2,
+    description: "add project records",
+    apply: v2::from_v1,
+}];
 
 pub mod v1 {
 
@@ -19,61 +57,591 @@ pub mod v1 {
         pub schema_version: u32,
         pub todos: Vec<Todo>,
     }
+}
+
+pub mod v2 {
+
+    use super::*;
+
+    /// Schema version 2: todos plus project records.
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct DbFileV2 {
+        pub schema_version: u32,
+        #[serde(default)]
+        pub projects: Vec<Project>,
+        pub todos: Vec<Todo>,
+    }
 
-    impl DbFileV1 {
+    impl DbFileV2 {
         pub fn empty() -> Self {
             Self {
-                schema_version: super::CURRENT_SCHEMA_VERSION,
+                schema_version: 2,
+                projects: Vec::new(),
                 todos: Vec::new(),
             }
         }
     }
+
+    /// [`DbFileV2`] borrowing its contents, for writing without a copy.
+    #[derive(Serialize)]
+    pub struct DbFileV2Ref<'a> {
+        pub schema_version: u32,
+        pub projects: &'a [Project],
+        pub todos: &'a [Todo],
+    }
+
+    /// Todos are unchanged; projects start out without records.
+    pub fn from_v1(mut v: Value) -> Result<Value> {
+        let Some(obj) = v.as_object_mut() else {
+            anyhow::bail!("database is not a JSON object");
+        };
+        obj.insert("schema_version".to_string(), Value::from(2));
+        obj.entry("projects")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        Ok(v)
+    }
+}
+
+/// Everything a database file holds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DbContents {
+    pub projects: Vec<Project>,
+    pub todos: Vec<Todo>,
 }
 
-/// Load any supported schema version and convert into current in-memory representation.
+/// Decode a current-version document without an intermediate `Value`.
 ///
-/// Today, v1 == current, so conversion is trivial.
-/// Tomorrow, v2/v3 can map old fields into new domain types safely.
-pub fn load_any(json_text: &str) -> Result<Vec<Todo>> {
+/// `Ok(None)` means it is stamped with another version (or none) and has to
+/// go through [`load_any`].
+fn decode_current<'de, D: Deserializer<'de>>(de: D) -> Result<Option<DbContents>, D::Error> {
+    struct Current;
+
+    impl<'de> Visitor<'de> for Current {
+        type Value = Option<DbContents>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a database object with schema_version and todos")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut version = None;
+            let mut db = DbContents::default();
+            let mut other_version = false;
+            while let Some(key) = map.next_key::<String>()? {
+                match key.as_str() {
+                    "schema_version" => {
+                        let v = map.next_value::<u64>()?;
+                        other_version = v != u64::from(CURRENT_SCHEMA_VERSION);
+                        version = Some(v);
+                    }
+                    // Another version's contents may not decode; skip them.
+                    "todos" | "projects" if other_version => {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                    "todos" => db.todos = map.next_value()?,
+                    "projects" => db.projects = map.next_value()?,
+                    _ => {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+            }
+            Ok((version == Some(u64::from(CURRENT_SCHEMA_VERSION))).then_some(db))
+        }
+    }
+
+    de.deserialize_map(Current)
+}
+
+/// Stream a current-version database from `reader`.
+///
+/// `None` if it isn't one: read it again with [`load_any`], which migrates
+/// older versions and reports what's wrong with broken files.
+pub fn load_reader(reader: impl Read) -> Option<DbContents> {
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let db = decode_current(&mut de).ok()??;
+    de.end().ok()?;
+    Some(db)
+}
+
+/// Load any supported schema version, migrating it to the current one.
+pub fn load_db(json_text: &str) -> Result<DbContents> {
+    Ok(load_db_versioned(json_text)?.0)
+}
+
+/// Like [`load_db`], also returning the version the text was stored at.
+pub fn load_db_versioned(json_text: &str) -> Result<(DbContents, u32)> {
+    let mut de = serde_json::Deserializer::from_str(json_text);
+    if let Ok(Some(db)) = decode_current(&mut de)
+        && de.end().is_ok()
+    {
+        return Ok((db, CURRENT_SCHEMA_VERSION));
+    }
+
     let v: Value = serde_json::from_str(json_text).context("failed parsing db JSON")?;
+    let version = version_of(&v);
+    if !SUPPORTED_VERSIONS.contains(&version) {
+        let supported: Vec<_> = SUPPORTED_VERSIONS.iter().map(u32::to_string).collect();
+        anyhow::bail!(
+            "unsupported schema_version {} (supported: {})",
+            version,
+            supported.join(", ")
+        );
+    }
+    let (v, _) = upgrade(v, CURRENT_SCHEMA_VERSION)?;
+    let db: v2::DbFileV2 = serde_json::from_value(v)
+        .with_context(|| format!("failed decoding schema v{version} db"))?;
+    let db = DbContents {
+        projects: db.projects,
+        todos: db.todos,
+    };
+    Ok((db, version))
+}
+
+/// The todos of any supported schema version (see [`load_db`]).
+pub fn load_any(json_text: &str) -> Result<Vec<Todo>> {
+    Ok(load_db(json_text)?.todos)
+}
 
-    let schema_version = v
-        .get("schema_version")
+/// Parse a version as given on the command line (`2` or `v2`).
+pub fn parse_version(input: &str) -> Option<u32> {
+    let s = input.trim();
+    s.strip_prefix(['v', 'V']).unwrap_or(s).parse().ok()
+}
+
+/// Version stamped in a database file (0 if missing).
+pub fn version_of(v: &Value) -> u32 {
+    v.get("schema_version")
         .and_then(|x| x.as_u64())
-        .map(|x| x as u32)
-        .unwrap_or(0);
-
-    match schema_version {
-        1 => {
-            let db: v1::DbFileV1 =
-                serde_json::from_value(v).context("failed decoding schema v1 db")?;
-            Ok(db.todos)
+        .map_or(0, |x| x as u32)
+}
+
+/// Upgrade a database document to version `to`, one migration at a time.
+///
+/// Returns the upgraded document and the steps taken (none if it already was
+/// at `to`). Downgrades aren't supported.
+pub fn upgrade(mut v: Value, to: u32) -> Result<(Value, Vec<&'static Migration>)> {
+    if !SUPPORTED_VERSIONS.contains(&to) {
+        anyhow::bail!(
+            "unknown schema version v{to} (this build knows up to v{CURRENT_SCHEMA_VERSION})"
+        );
+    }
+    let mut steps = Vec::new();
+    let mut at = version_of(&v);
+    if at > to {
+        anyhow::bail!("database is at v{at}; downgrading to v{to} isn't supported");
+    }
+    while at < to {
+        let Some(step) = MIGRATIONS.iter().find(|m| m.from == at) else {
+            anyhow::bail!("no migration from schema v{at}");
+        };
+        v = (step.apply)(v)
+            .with_context(|| format!("migration v{} -> v{} failed", step.from, step.to))?;
+        at = step.to;
+        steps.push(step);
+    }
+    Ok((v, steps))
+}
+
+/// What upgrading `before` into `after` changed, one line per top-level key.
+///
+/// Lists of objects (`todos`, `projects`) are summarized per field: which
+/// fields were added, removed or rewritten, and in how many items.
+pub fn describe_changes(before: &Value, after: &Value) -> Vec<String> {
+    let empty = serde_json::Map::new();
+    let b = before.as_object().unwrap_or(&empty);
+    let a = after.as_object().unwrap_or(&empty);
+    let keys: std::collections::BTreeSet<&String> = b.keys().chain(a.keys()).collect();
+
+    let mut lines = Vec::new();
+    for key in keys {
+        match (b.get(key), a.get(key)) {
+            (None, Some(new)) => lines.push(format!("+ {key}: {}", summarize(new))),
+            (Some(_), None) => lines.push(format!("- {key}")),
+            (Some(old), Some(new)) if old != new => match (old, new) {
+                (Value::Array(old), Value::Array(new)) if old.len() == new.len() => {
+                    lines.extend(describe_items(key, old, new));
+                }
+                _ => lines.push(format!("~ {key}: {} -> {}", summarize(old), summarize(new))),
+            },
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// Field changes across the items of list `key`, counted per field.
+fn describe_items(key: &str, old: &[Value], new: &[Value]) -> Vec<String> {
+    use std::collections::BTreeMap;
+
+    let mut counts: BTreeMap<(&str, char), usize> = BTreeMap::new();
+    let empty = serde_json::Map::new();
+    for (o, n) in old.iter().zip(new) {
+        let o = o.as_object().unwrap_or(&empty);
+        let n = n.as_object().unwrap_or(&empty);
+        for field in o.keys().chain(n.keys().filter(|k| !o.contains_key(*k))) {
+            let mark = match (o.get(field), n.get(field)) {
+                (None, Some(_)) => '+',
+                (Some(_), None) => '-',
+                (Some(x), Some(y)) if x != y => '~',
+                _ => continue,
+            };
+            *counts.entry((field.as_str(), mark)).or_default() += 1;
         }
-        other => anyhow::bail!("unsupported schema_version {} (supported: 1)", other),
     }
+    counts
+        .into_iter()
+        .map(|((field, mark), n)| format!("{mark} {key}[].{field} in {n} of {}", old.len()))
+        .collect()
+}
+
+fn summarize(v: &Value) -> String {
+    match v {
+        Value::Array(items) if items.is_empty() => "empty list".to_string(),
+        Value::Array(items) => format!("list of {}", items.len()),
+        Value::Object(map) => format!("object with {} key(s)", map.len()),
+        other => other.to_string(),
+    }
+}
+
+/// How times are written: `time`'s compact serde form.
+pub const TIME_FORMAT: &str =
+    "[year, day of year, hour, minute, second, nanosecond, offset h, m, s]";
+
+/// A top-level field of a stored todo.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldInfo {
+    pub name: String,
+    /// JSON type as written (`string`, `array`, ...).
+    pub kind: &'static str,
+    /// Left out of the file when empty / unset.
+    pub optional: bool,
+    pub description: &'static str,
+}
+
+const FIELD_DOCS: &[(&str, &str)] = &[
+    ("id", "UUID, stable across edits and sync"),
+    ("title", "short description"),
+    ("notes", "free text, null if none"),
+    ("project", "project name (Inbox by default)"),
+    ("tags", "lowercase tags"),
+    (
+        "status",
+        "\"Open\" or {\"Done\": {\"completed_at\": <time>}}",
+    ),
+    ("priority", "P1 (highest) to P4"),
+    ("due", "due time, null if none"),
+    ("badge", "short marker shown before the title"),
+    ("color", "highlight color name"),
+    ("estimate", "estimated effort in minutes"),
+    ("energy", "low, medium or high"),
+    ("depends_on", "ids of todos that must be done first"),
+    ("parent", "id of the todo this is a subtask of"),
+    ("time_entries", "tracked work: start, end and note"),
+    (
+        "source",
+        "where it was created: cli, gui, import:<file>, ...",
+    ),
+    ("someday", "time it was parked on the someday list"),
+    ("remind_at", "time to send a reminder"),
+    ("notified_at", "time the last notification was sent"),
+    ("state", "workflow state while open, e.g. in-progress"),
+    (
+        "escalation",
+        "reminder chain overriding the priority's, e.g. \"-1d, -1h, every 30m\"",
+    ),
+    (
+        "external_ref",
+        "linked item elsewhere: provider, key and url, e.g. jira ABC-123",
+    ),
+    (
+        "attachments",
+        "attached files and links: label and url, file or path",
+    ),
+    ("archived", "time it was moved to the archive"),
+    ("created_at", "creation time"),
+    ("updated_at", "time of the last change"),
+    ("version", "sync version vector: device -> counter"),
+    ("field_stamps", "per-field last-write times for merges"),
+];
+
+/// Describe the fields of a stored todo, as serde writes them.
+///
+/// Names, JSON types and optionality come from serializing a bare and a
+/// fully-populated todo, so this can't drift from the actual format.
+pub fn describe_fields() -> Vec<FieldInfo> {
+    let bare = serde_json::to_value(crate::domain::todo::Todo::new(
+        crate::domain::todo::Title::parse("x").expect("valid title"),
+    ))
+    .unwrap_or_default();
+    let full = serde_json::to_value(full_example()).unwrap_or_default();
+    let Some(fields) = full.as_object() else {
+        return Vec::new();
+    };
+
+    fields
+        .iter()
+        .map(|(name, value)| FieldInfo {
+            name: name.clone(),
+            kind: match value {
+                Value::Null => "null",
+                Value::Bool(_) => "boolean",
+                Value::Number(_) => "number",
+                Value::String(_) => "string",
+                Value::Array(_) => "array",
+                Value::Object(_) => "object",
+            },
+            optional: bare.get(name).is_none(),
+            description: FIELD_DOCS
+                .iter()
+                .find(|(n, _)| n == name)
+                .map_or("", |(_, d)| d),
+        })
+        .collect()
+}
+
+/// A todo with every optional field set.
+fn full_example() -> Todo {
+    use crate::domain::{
+        crdt::TodoField,
+        todo::{
+            Badge, Color, DueAt, Energy, Estimate, Notes, Source, Tag, Title, TodoId, WorkflowState,
+        },
+        tracking::TimeEntry,
+    };
+
+    let mut t = Todo::new(Title::parse("Example").expect("valid title"));
+    let now = t.created_at;
+    t.notes = Notes::parse("notes").ok();
+    t.tags.extend(Tag::parse("tag"));
+    t.due = Some(DueAt::from_dt(now));
+    t.badge = Badge::parse("*").ok();
+    t.color = Some(Color::Blue);
+    t.estimate = Estimate::parse("1h").ok();
+    t.energy = Some(Energy::Low);
+    t.depends_on.insert(TodoId::new());
+    t.parent = Some(TodoId::new());
+    t.time_entries.push(TimeEntry::start(now));
+    t.source = Some(Source::Cli);
+    t.someday = Some(now);
+    t.remind_at = Some(DueAt::from_dt(now));
+    t.notified_at = Some(now);
+    t.state = Some(WorkflowState::in_progress());
+    t.escalation =
+        Some(crate::domain::escalation::Escalation::parse("-1d, -1h, every 30m").unwrap());
+    t.external_ref = crate::domain::external_ref::ExternalRef::parse("github:owner/repo#1")
+        .ok()
+        .map(|r| r.resolve_url(None));
+    t.attachments.push(crate::domain::attachment::Attachment {
+        label: "spec".into(),
+        target: crate::domain::attachment::AttachmentTarget::Url("https://example.com".into()),
+    });
+    t.archived = Some(now);
+    t.version.increment("device");
+    t.field_stamps
+        .insert(TodoField::Title, crate::domain::crdt::FieldStamp::at(now));
+    t
 }
 
 /// Serialize current in-memory state to the current on-disk format.
-pub fn write_current(todos: &[Todo]) -> Result<String> {
-    let db = v1::DbFileV1 {
+pub fn write_db(projects: &[Project], todos: &[Todo]) -> Result<String> {
+    let db = v2::DbFileV2Ref {
         schema_version: CURRENT_SCHEMA_VERSION,
-        todos: todos.to_vec(),
+        projects,
+        todos,
     };
     let s = serde_json::to_string_pretty(&db).context("failed serializing db JSON")?;
     Ok(s)
 }
 
+/// [`write_db`] without project records (exports, backups of todos).
+pub fn write_current(todos: &[Todo]) -> Result<String> {
+    write_db(&[], todos)
+}
+
+/// Fields that change without the todo being edited (or, for sync state,
+/// differ between devices); `export --reproducible --drop-volatile` leaves
+/// them out.
+pub const VOLATILE_FIELDS: &[&str] = &["updated_at", "notified_at", "version", "field_stamps"];
+
+/// [`write_current`] for files kept under version control: todos sorted by
+/// creation time then id, keys in alphabetical order, and optionally without
+/// [`VOLATILE_FIELDS`]. The same todos always give the same bytes.
+///
+/// Without the volatile fields the file is for reading and diffing; `import`
+/// needs `updated_at`.
+pub fn write_reproducible(todos: &[Todo], drop_volatile: bool) -> Result<String> {
+    let mut sorted = todos.to_vec();
+    sorted.sort_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    let db = v2::DbFileV2Ref {
+        schema_version: CURRENT_SCHEMA_VERSION,
+        projects: &[],
+        todos: &sorted,
+    };
+    // Objects in a `Value` keep their keys sorted.
+    let mut v = serde_json::to_value(&db).context("failed serializing db JSON")?;
+    if drop_volatile && let Some(todos) = v["todos"].as_array_mut() {
+        for todo in todos.iter_mut().filter_map(Value::as_object_mut) {
+            for field in VOLATILE_FIELDS {
+                todo.remove(*field);
+            }
+        }
+    }
+    let mut s = serde_json::to_string_pretty(&v).context("failed serializing db JSON")?;
+    s.push('\n');
+    Ok(s)
+}
+
+/// Add a top-level `key` to a document from [`write_current`] or
+/// [`write_reproducible`], for what an export carries besides the database
+/// (like `export --include-history`'s journal). Loading skips such keys.
+pub fn add_section(doc: &str, key: &str, section: &impl Serialize) -> Result<String> {
+    let mut v: Value = serde_json::from_str(doc).context("failed parsing db JSON")?;
+    let Some(obj) = v.as_object_mut() else {
+        anyhow::bail!("database is not a JSON object");
+    };
+    let section = serde_json::to_value(section).context("failed serializing db JSON")?;
+    obj.insert(key.to_string(), section);
+    let mut s = serde_json::to_string_pretty(&v).context("failed serializing db JSON")?;
+    if doc.ends_with('\n') {
+        s.push('\n');
+    }
+    Ok(s)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::domain::todo::Title;
 
     #[test]
-    fn load_any_reads_v1() {
+    fn every_stored_field_is_described() {
+        let fields = describe_fields();
+        for f in &fields {
+            assert!(!f.description.is_empty(), "no description for {}", f.name);
+        }
+        let names: Vec<_> = fields.iter().map(|f| f.name.as_str()).collect();
+        for (name, _) in FIELD_DOCS {
+            assert!(names.contains(name), "{name} is documented but not stored");
+        }
+        let due = fields.iter().find(|f| f.name == "due").unwrap();
+        assert_eq!((due.kind, due.optional), ("array", false));
+        assert!(fields.iter().find(|f| f.name == "energy").unwrap().optional);
+    }
+
+    #[test]
+    fn v1_upgrades_to_v2_and_current_is_a_no_op() {
+        let v1 = serde_json::json!({ "schema_version": 1, "todos": [] });
+        let (v, steps) = upgrade(v1, 2).unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(version_of(&v), 2);
+        assert_eq!(v["projects"], serde_json::json!([]));
+
+        let (v, steps) = upgrade(v, 2).unwrap();
+        assert!(steps.is_empty());
+        assert!(upgrade(v.clone(), 3).is_err());
+        assert!(upgrade(v, 1).is_err());
+        assert_eq!(parse_version("v2"), Some(2));
+    }
+
+    #[test]
+    fn changes_are_described_per_key_and_per_item_field() {
+        let v1 = serde_json::json!({
+            "schema_version": 1,
+            "todos": [{ "title": "A", "old": 1 }, { "title": "B" }],
+        });
+        let (v2, _) = upgrade(v1.clone(), 2).unwrap();
+        assert_eq!(
+            describe_changes(&v1, &v2),
+            ["+ projects: empty list", "~ schema_version: 1 -> 2"]
+        );
+
+        let rewritten = serde_json::json!({
+            "schema_version": 1,
+            "todos": [{ "title": "a", "new": 1 }, { "title": "B", "new": 2 }],
+        });
+        assert_eq!(
+            describe_changes(&v1, &rewritten),
+            [
+                "+ todos[].new in 2 of 2",
+                "- todos[].old in 1 of 2",
+                "~ todos[].title in 1 of 2",
+            ]
+        );
+        assert!(describe_changes(&v1, &v1).is_empty());
+    }
+
+    #[test]
+    fn load_db_reads_v1_and_v2() {
+        use crate::domain::todo::ProjectName;
+
         let todo = crate::domain::todo::Todo::new(Title::parse("A").unwrap());
-        let json = write_current(&[todo]).unwrap();
-        let todos = load_any(&json).unwrap();
-        assert_eq!(todos.len(), 1);
-        assert_eq!(todos[0].title.as_str(), "A");
+        let v1 = serde_json::to_string(&v1::DbFileV1 {
+            schema_version: 1,
+            todos: vec![todo.clone()],
+        })
+        .unwrap();
+        let db = load_db(&v1).unwrap();
+        assert_eq!(db.todos, vec![todo.clone()]);
+        assert!(db.projects.is_empty());
+
+        let mut work = Project::new(ProjectName::parse("Work").unwrap());
+        work.archived = true;
+        let v2 = write_db(std::slice::from_ref(&work), std::slice::from_ref(&todo)).unwrap();
+        let db = load_db(&v2).unwrap();
+        assert_eq!((db.projects, db.todos), (vec![work], vec![todo]));
+
+        assert!(load_any(r#"{"todos": []}"#).is_err());
+    }
+
+    #[test]
+    fn load_reader_streams_current_files_only() {
+        let todo = crate::domain::todo::Todo::new(Title::parse("A").unwrap());
+        let json = write_current(std::slice::from_ref(&todo)).unwrap();
+        let db = load_reader(json.as_bytes()).unwrap();
+        assert_eq!(db.todos, vec![todo]);
+
+        // Key order doesn't matter; unknown keys are skipped.
+        let reordered = r#"{"todos": [], "note": {"x": 1}, "schema_version": 2}"#;
+        assert_eq!(
+            load_reader(reordered.as_bytes()),
+            Some(DbContents::default())
+        );
+
+        // Other versions are left to load_any.
+        let v0 = r#"{"todos": [{"whatever": true}]}"#;
+        assert!(load_reader(v0.as_bytes()).is_none());
+        let v1 = r#"{"schema_version": 1, "todos": []}"#;
+        assert!(load_reader(v1.as_bytes()).is_none());
+        let v9 = r#"{"schema_version": 9, "todos": [{"whatever": true}]}"#;
+        assert!(load_reader(v9.as_bytes()).is_none());
+        assert!(load_reader(&b"{\"schema_version\": 2} trailing"[..]).is_none());
+    }
+
+    #[test]
+    fn reproducible_output_ignores_order_and_volatile_fields() {
+        let mut a = Todo::new(Title::parse("A").unwrap());
+        let mut b = Todo::new(Title::parse("B").unwrap());
+        b.created_at = a.created_at;
+        a.notified_at = Some(a.created_at);
+        let one = write_reproducible(&[a.clone(), b.clone()], true).unwrap();
+        b.updated_at += time::Duration::hours(1);
+        let two = write_reproducible(&[b, a], true).unwrap();
+        assert_eq!(one, two);
+        assert!(!one.contains("updated_at") && !one.contains("notified_at"));
+
+        let kept = write_reproducible(&[full_example()], false).unwrap();
+        let keys: Vec<_> = kept
+            .lines()
+            .filter(|l| l.starts_with("      \""))
+            .map(|l| l.trim().split('"').nth(1).unwrap())
+            .collect();
+        let mut sorted = keys.clone();
+        sorted.sort_unstable();
+        assert_eq!(keys, sorted);
+        assert!(keys.contains(&"updated_at"));
     }
 }
@@ -1,103 +1,591 @@
+//! JSON file storage.
+//!
+//! The database is normally one file. With `shard_by_project` set in
+//! config.toml it is split into one file per project under `<db>.shards/`
+//! instead, so a command that only needs one project reads only that file
+//! (see [`JsonFileTodoRepository::load_projects`]) and a save rewrites only the
+//! projects that changed. Readers detect the layout from what is on disk.
+//!
+//! Either layout can be encrypted at rest (see [`db_crypto`]): loading with a
+//! key reads encrypted and plain files alike, and saves encrypt every file.
+
 use std::{
+    collections::{BTreeMap, BTreeSet},
     fs::File,
-    io::Write,
+    hash::{DefaultHasher, Hasher},
+    io::{BufReader, Read, Write},
     path::{Path, PathBuf},
 };
 
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
 
 use crate::{
-    app::repository::TodoRepository,
-    domain::todo::{Todo, TodoId},
-    infra::db_schema,
+    app::{
+        errors::AppError,
+        query::{self, ListQuery},
+        repository::{self, TodoRepository},
+    },
+    domain::{
+        project::Project,
+        todo::{Todo, TodoId},
+    },
+    infra::{
+        db_crypto::{self, DbKey},
+        db_schema::{self, DbContents},
+        perms,
+        timings::{Op, timed},
+    },
 };
 
-/// Current schema version for the on-disk JSON file.
-const SCHEMA_VERSION: u32 = 1;
+/// Fingerprint of the database file's contents.
+///
+/// Compared against the file on disk to notice writes by other processes
+/// (a CLI command next to a running GUI, a sync client).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Revision(u64);
 
-/// On-disk schema wrapper.
-/// This lets us migrate formats later without guessing.
-#[derive(Debug, Serialize, Deserialize)]
-struct DbFile {
-    schema_version: u32,
-    todos: Vec<Todo>,
-}
+impl Revision {
+    fn of(bytes: &[u8]) -> Self {
+        let mut h = DefaultHasher::new();
+        h.write(bytes);
+        Self(h.finish())
+    }
 
-impl DbFile {
-    fn empty() -> Self {
-        Self {
-            schema_version: SCHEMA_VERSION,
-            todos: Vec::new(),
+    /// Revision of a set of shard files, by file name.
+    fn of_shards<'a>(shards: impl IntoIterator<Item = (&'a str, Revision)>) -> Self {
+        let mut h = DefaultHasher::new();
+        for (name, rev) in shards {
+            h.write(name.as_bytes());
+            h.write_u64(rev.0);
         }
+        Self(h.finish())
     }
 }
 
-/// JSON repository backed by as single file.
+/// How the database is laid out on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// One JSON file holding every todo.
+    File,
+    /// One JSON file per project under `<db>.shards/`.
+    Sharded,
+}
+
+/// Directory holding the per-project files of a sharded database.
+pub fn shard_dir(db_path: &Path) -> PathBuf {
+    db_path.with_extension("shards")
+}
+
+/// File name of the shard holding `project`.
+///
+/// Projects match case-insensitively, so "Work" and "work" share a shard. The
+/// hash keeps names that slug the same apart.
+pub fn shard_file_name(project: &str) -> String {
+    let key = project.to_lowercase();
+    let slug: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(40)
+        .collect();
+    let hash = Sha256::digest(key.as_bytes());
+    format!(
+        "{slug}-{:02x}{:02x}{:02x}{:02x}.json",
+        hash[0], hash[1], hash[2], hash[3]
+    )
+}
+
+/// JSON repository backed by a single file or a directory of shards.
 pub struct JsonFileTodoRepository {
     path: PathBuf,
     todos: Vec<Todo>,
+    projects: Vec<Project>,
+    /// Revision last read from or written to disk.
+    revision: Revision,
+    layout: Layout,
+    /// Revision of every shard file as last read or written.
+    shards: BTreeMap<String, Revision>,
+    /// Revision of every shard's decrypted JSON, to tell which changed.
+    shard_contents: BTreeMap<String, Revision>,
+    /// Shard files this repository was limited to (`None` = all of them).
+    only: Option<BTreeSet<String>>,
+    /// Encrypts saves when set.
+    key: Option<DbKey>,
+    /// Whether the files as last read or written are encrypted.
+    encrypted: bool,
+}
+
+/// Does the database at `path` (file or shards) exist encrypted?
+pub fn is_encrypted_on_disk(path: &Path) -> bool {
+    let dir = shard_dir(path);
+    if dir.is_dir() {
+        return shard_names(&dir, None)
+            .unwrap_or_default()
+            .iter()
+            .any(|name| db_crypto::file_is_encrypted(&dir.join(name)));
+    }
+    db_crypto::file_is_encrypted(path)
 }
 
 impl JsonFileTodoRepository {
     pub fn load_or_init(path: PathBuf) -> Result<Self> {
-        if path.exists() {
-            let text = std::fs::read_to_string(&path)
-                .with_context(|| format!("failed reading db file: {}", path.display()))?;
+        Self::load_with_key(path, None)
+    }
+
+    /// Like [`Self::load_or_init`], decrypting with `key` and encrypting
+    /// saves with it. Without a key an encrypted database is refused; with
+    /// one a plain database still loads, and its next save encrypts it.
+    pub fn load_with_key(path: PathBuf, key: Option<DbKey>) -> Result<Self> {
+        timed(Op::Load, || Self::open(path, key))
+    }
 
-            let todos = db_schema::load_any(&text)?;
-            Ok(Self { path, todos: todos })
+    fn open(path: PathBuf, key: Option<DbKey>) -> Result<Self> {
+        if shard_dir(&path).is_dir() {
+            Self::load_shards(path, None, key)
+        } else if path.exists() {
+            let file = read_db_file(&path, key.as_ref())?;
+            Ok(Self {
+                path,
+                todos: file.db.todos,
+                projects: file.db.projects,
+                revision: file.revision,
+                layout: Layout::File,
+                shards: BTreeMap::new(),
+                shard_contents: BTreeMap::new(),
+                only: None,
+                key,
+                encrypted: file.encrypted,
+            })
         } else {
             // Ensure parent dir exists
             if let Some(parent) = path.parent() {
-                std::fs::create_dir_all(parent).with_context(|| {
+                perms::create_dir(parent).with_context(|| {
                     format!("failed creating db parent dir: {}", parent.display())
                 })?;
             }
 
-            let repo = Self {
+            let mut repo = Self {
                 path,
                 todos: Vec::new(),
+                projects: Vec::new(),
+                revision: Revision::of(&[]),
+                layout: Layout::File,
+                shards: BTreeMap::new(),
+                shard_contents: BTreeMap::new(),
+                only: None,
+                key,
+                encrypted: false,
             };
             repo.save_atomic()?;
             Ok(repo)
         }
     }
 
+    /// Load only the todos of `projects` from a sharded database.
+    ///
+    /// The result is a read-only view: saving it is refused, since the
+    /// shards it didn't read can't be checked. Unsharded databases are loaded
+    /// whole.
+    pub fn load_projects(path: PathBuf, projects: &[&str], key: Option<DbKey>) -> Result<Self> {
+        timed(Op::Load, || {
+            if !shard_dir(&path).is_dir() {
+                return Self::open(path, key);
+            }
+            let only = projects.iter().map(|p| shard_file_name(p)).collect();
+            Self::load_shards(path, Some(only), key)
+        })
+    }
+
+    fn load_shards(
+        path: PathBuf,
+        only: Option<BTreeSet<String>>,
+        key: Option<DbKey>,
+    ) -> Result<Self> {
+        let mut todos = Vec::new();
+        let mut projects = Vec::new();
+        let mut shards = BTreeMap::new();
+        let mut shard_contents = BTreeMap::new();
+        let mut encrypted = false;
+        let dir = shard_dir(&path);
+        for name in shard_names(&dir, only.as_ref())? {
+            let file = read_db_file(&dir.join(&name), key.as_ref())
+                .with_context(|| format!("failed loading shard {name}"))?;
+            todos.extend(file.db.todos);
+            projects.extend(file.db.projects);
+            encrypted |= file.encrypted;
+            shards.insert(name.clone(), file.revision);
+            shard_contents.insert(name, file.contents);
+        }
+        Ok(Self {
+            path,
+            todos,
+            projects,
+            revision: Revision::of_shards(shards.iter().map(|(n, r)| (n.as_str(), *r))),
+            layout: Layout::Sharded,
+            shards,
+            shard_contents,
+            only,
+            key,
+            encrypted,
+        })
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Switch layouts; the next save writes the new one and removes the old.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    /// Encrypt (or with `None`, stop encrypting) from the next save on.
+    pub fn set_key(&mut self, key: Option<DbKey>) {
+        self.key = key;
+    }
+
+    pub fn key(&self) -> Option<&DbKey> {
+        self.key.as_ref()
+    }
+
+    /// Whether the database as last read or written is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    /// True if only some projects were loaded (see [`Self::load_projects`]).
+    pub fn is_partial(&self) -> bool {
+        self.only.is_some()
+    }
+
     /// Save current in-memory state to disk using an atomic replace.
     ///
     /// Durability strategy (best-effort):
     /// 1) write temp file
-    /// 2) fsync temp file
-    /// 3) rename temp -> final
-    /// 4) best-effort fsync parent dir
-    pub fn save_atomic(&self) -> Result<()> {
-        let json = db_schema::write_current(&self.todos)?;
-
-        let tmp_path = tmp_path_for(&self.path);
-
-        write_file_and_sync(&tmp_path, json.as_bytes())
-            .with_context(|| format!("failed writing temp db file: {}", tmp_path.display()))?;
-
-        // Atomic replace on most platforms when temp is in same directory.
-        std::fs::rename(&tmp_path, &self.path).with_context(|| {
-            format!(
-                "failed remaining temp db file {} -> {}",
-                tmp_path.display(),
+    /// 2) fsync temp file (`FlushFileBuffers` on Windows)
+    /// 3) rename temp -> final (write-through on Windows)
+    /// 4) best-effort fsync parent dir (not on Windows, which can't)
+    ///
+    /// Sharded databases only rewrite the shards whose contents changed.
+    pub fn save_atomic(&mut self) -> Result<()> {
+        timed(Op::Save, || self.save())
+    }
+
+    fn save(&mut self) -> Result<()> {
+        if self.is_partial() {
+            anyhow::bail!(
+                "only some projects of {} were loaded; refusing to save",
                 self.path.display()
-            )
-        })?;
+            );
+        }
+        match self.layout {
+            Layout::File => {
+                let json = db_schema::write_db(&self.projects, &self.todos)?;
+                let bytes = self.encode(json.into_bytes())?;
+                replace_file(&self.path, &bytes)?;
+                self.revision = Revision::of(&bytes);
+                let dir = shard_dir(&self.path);
+                if dir.is_dir() {
+                    std::fs::remove_dir_all(&dir)
+                        .with_context(|| format!("failed removing {}", dir.display()))?;
+                }
+                self.shards.clear();
+                self.shard_contents.clear();
+            }
+            Layout::Sharded => self.save_shards()?,
+        }
+        self.encrypted = self.key.is_some();
+        Ok(())
+    }
 
-        // Best-effort directory fsync (platform-dependent).
-        if let Some(parent) = self.path.parent() {
-            let _ = sync_dir_best_effort(parent);
+    /// `json` as written to disk: sealed if a key is set.
+    fn encode(&self, json: Vec<u8>) -> Result<Vec<u8>> {
+        match &self.key {
+            Some(key) => key.seal(&json),
+            None => Ok(json),
         }
+    }
 
+    fn save_shards(&mut self) -> Result<()> {
+        let dir = shard_dir(&self.path);
+        perms::create_dir(&dir)
+            .with_context(|| format!("failed creating shard dir: {}", dir.display()))?;
+
+        // Each shard holds its projects' records as well as their todos.
+        let mut groups: BTreeMap<String, (Vec<Project>, Vec<Todo>)> = BTreeMap::new();
+        for p in &self.projects {
+            groups
+                .entry(shard_file_name(p.name.as_str()))
+                .or_default()
+                .0
+                .push(p.clone());
+        }
+        for t in &self.todos {
+            groups
+                .entry(shard_file_name(t.project.as_str()))
+                .or_default()
+                .1
+                .push(t.clone());
+        }
+
+        // Switching encryption on or off rewrites every shard.
+        let reencode = self.encrypted != self.key.is_some();
+        let mut written = BTreeMap::new();
+        let mut contents = BTreeMap::new();
+        for (name, (projects, todos)) in groups {
+            let json = db_schema::write_db(&projects, &todos)?;
+            let content = Revision::of(json.as_bytes());
+            let rev = match self.shards.get(&name) {
+                Some(rev) if !reencode && self.shard_contents.get(&name) == Some(&content) => *rev,
+                _ => {
+                    let bytes = self.encode(json.into_bytes())?;
+                    replace_file(&dir.join(&name), &bytes)?;
+                    Revision::of(&bytes)
+                }
+            };
+            written.insert(name.clone(), rev);
+            contents.insert(name, content);
+        }
+        // Projects that are now empty.
+        for name in shard_names(&dir, None)? {
+            if !written.contains_key(&name) {
+                let path = dir.join(&name);
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("failed removing {}", path.display()))?;
+            }
+        }
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)
+                .with_context(|| format!("failed removing {}", self.path.display()))?;
+        }
+
+        self.revision = Revision::of_shards(written.iter().map(|(n, r)| (n.as_str(), *r)));
+        self.shards = written;
+        self.shard_contents = contents;
         Ok(())
     }
+
+    /// Revision of the file as this repository last read or wrote it.
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Revision of the file as it is on disk now (`None` if it was removed).
+    pub fn disk_revision(&self) -> Result<Option<Revision>> {
+        if self.layout == Layout::Sharded {
+            let dir = shard_dir(&self.path);
+            if !dir.is_dir() {
+                return Ok(None);
+            }
+            let mut shards = Vec::new();
+            for name in shard_names(&dir, self.only.as_ref())? {
+                let path = dir.join(&name);
+                let revision = file_revision(&path)
+                    .with_context(|| format!("failed reading shard: {}", path.display()))?;
+                shards.push((name, revision));
+            }
+            return Ok(Some(Revision::of_shards(
+                shards.iter().map(|(n, r)| (n.as_str(), *r)),
+            )));
+        }
+        match file_revision(&self.path) {
+            Ok(revision) => Ok(Some(revision)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => {
+                Err(e).with_context(|| format!("failed reading db file: {}", self.path.display()))
+            }
+        }
+    }
+
+    /// True if someone else wrote the file since we last read or wrote it.
+    pub fn changed_on_disk(&self) -> Result<bool> {
+        Ok(self.disk_revision()? != Some(self.revision))
+    }
 }
 
-fn tmp_path_for(path: &PathBuf) -> PathBuf {
+/// Atomically replace `path` with `bytes` (the steps of [`JsonFileTodoRepository::save_atomic`]).
+pub fn replace_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp_path = tmp_path_for(path);
+
+    write_file_and_sync(&tmp_path, bytes)
+        .with_context(|| format!("failed writing temp db file: {}", tmp_path.display()))?;
+
+    // Atomic replace on most platforms when temp is in same directory.
+    #[cfg(windows)]
+    let renamed = crate::infra::windows::replace_durably(&tmp_path, path);
+    #[cfg(not(windows))]
+    let renamed = std::fs::rename(&tmp_path, path);
+    renamed.with_context(|| {
+        format!(
+            "failed remaining temp db file {} -> {}",
+            tmp_path.display(),
+            path.display()
+        )
+    })?;
+
+    // Best-effort directory fsync (the Windows rename is already written through).
+    #[cfg(not(windows))]
+    if let Some(parent) = path.parent() {
+        let _ = sync_dir_best_effort(parent);
+    }
+    Ok(())
+}
+
+/// Shard file names in `dir` (limited to `only`), sorted.
+fn shard_names(dir: &Path, only: Option<&BTreeSet<String>>) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("failed listing {}", dir.display()))?
+    {
+        let entry = entry.with_context(|| format!("failed listing {}", dir.display()))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.ends_with(".json") && only.is_none_or(|o| o.contains(&name)) {
+            names.push(name);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Passes reads through while hashing them, so a file's [`Revision`] comes
+/// out of the same pass that parses it.
+struct HashingReader<R> {
+    inner: R,
+    hasher: DefaultHasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: DefaultHasher::new(),
+        }
+    }
+
+    fn revision(&self) -> Revision {
+        Revision(self.hasher.finish())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.write(&buf[..n]);
+        Ok(n)
+    }
+}
+
+const READ_BUFFER: usize = 64 * 1024;
+
+/// A database (or shard) file as read by [`read_db_file`].
+struct DbFile {
+    db: DbContents,
+    /// Of the bytes on disk.
+    revision: Revision,
+    /// Of the JSON inside (the same unless encrypted).
+    contents: Revision,
+    encrypted: bool,
+}
+
+/// Load a database (or shard) file and its revision.
+///
+/// Current-version files are parsed as they stream in; anything else is read
+/// again whole so it can be migrated (or its problem reported). Encrypted
+/// files are decrypted with `key` first.
+fn read_db_file(path: &Path, key: Option<&DbKey>) -> Result<DbFile> {
+    if db_crypto::file_is_encrypted(path) {
+        let Some(key) = key else {
+            bail!(
+                "{} is encrypted; set encryption = true in config.toml to open it",
+                path.display()
+            );
+        };
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("failed reading db file: {}", path.display()))?;
+        let json = key
+            .open(&bytes)
+            .with_context(|| format!("failed decrypting {}", path.display()))?;
+        let text = String::from_utf8(json)
+            .with_context(|| format!("invalid UTF-8 in {}", path.display()))?;
+        return Ok(DbFile {
+            db: load_migrating(path, &text)?,
+            revision: Revision::of(&bytes),
+            contents: Revision::of(text.as_bytes()),
+            encrypted: true,
+        });
+    }
+
+    let file =
+        File::open(path).with_context(|| format!("failed reading db file: {}", path.display()))?;
+    // Buffer outside the hasher so it is fed whole chunks, not single bytes.
+    let mut reader = BufReader::with_capacity(READ_BUFFER, HashingReader::new(file));
+    let plain = |db, revision| DbFile {
+        db,
+        revision,
+        contents: revision,
+        encrypted: false,
+    };
+    if let Some(db) = db_schema::load_reader(&mut reader) {
+        return Ok(plain(db, reader.get_ref().revision()));
+    }
+
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed reading db file: {}", path.display()))?;
+    Ok(plain(
+        load_migrating(path, &text)?,
+        Revision::of(text.as_bytes()),
+    ))
+}
+
+/// Load `text` read from `path`, backing the file up first if it is at an
+/// older schema version: the next save writes it at the current one.
+fn load_migrating(path: &Path, text: &str) -> Result<DbContents> {
+    let (db, version) = db_schema::load_db_versioned(text)?;
+    if version < db_schema::CURRENT_SCHEMA_VERSION {
+        let backup = back_up_before_migration(path, version)?;
+        tracing::info!(from = version, backup = %backup.display(), "migrating database");
+    }
+    Ok(db)
+}
+
+/// Where the schema `version` file at `path` is kept before migrating it:
+/// `<db>.v<version>.bak`.
+pub fn migration_backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut p = path.as_os_str().to_owned();
+    p.push(format!(".v{version}.bak"));
+    PathBuf::from(p)
+}
+
+/// Copy `path` (at schema `version`) to its migration backup, unless one is
+/// already there: that one holds the file as it was before the first
+/// attempt.
+pub fn back_up_before_migration(path: &Path, version: u32) -> Result<PathBuf> {
+    let backup = migration_backup_path(path, version);
+    if !backup.exists() {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("failed reading db file: {}", path.display()))?;
+        perms::write(&backup, bytes)
+            .with_context(|| format!("failed writing backup {}", backup.display()))?;
+    }
+    Ok(backup)
+}
+
+fn file_revision(path: &Path) -> std::io::Result<Revision> {
+    let mut reader = HashingReader::new(File::open(path)?);
+    std::io::copy(&mut reader, &mut std::io::sink())?;
+    Ok(reader.revision())
+}
+
+/// Where [`replace_file`] writes `path`'s new contents before renaming them
+/// into place.
+pub fn tmp_path_for(path: &Path) -> PathBuf {
     let mut p = path.to_path_buf();
     let file_name = path
         .file_name()
@@ -109,7 +597,7 @@ fn tmp_path_for(path: &PathBuf) -> PathBuf {
 
 fn write_file_and_sync(path: &Path, bytes: &[u8]) -> Result<()> {
     let mut f =
-        File::create(path).with_context(|| format!("failed creating file: {}", path.display()))?;
+        perms::create(path).with_context(|| format!("failed creating file: {}", path.display()))?;
     f.write_all(bytes)
         .with_context(|| format!("failed writing file: {}", path.display()))?;
     f.sync_all()
@@ -120,9 +608,9 @@ fn write_file_and_sync(path: &Path, bytes: &[u8]) -> Result<()> {
 
 /// Best-effort fsync of a directory.
 /// On some platforms/filesystems this may fail; that's okay.
+#[cfg(not(windows))]
 fn sync_dir_best_effort(dir: &Path) -> Result<()> {
     // On Unix-like systems (including macOS), opening a directory as a File is allowed.
-    // On Windows it may fail depending on permissions/filesystem.
     let f = File::open(dir).with_context(|| format!("failed opening dir: {}", dir.display()))?;
     f.sync_all()
         .with_context(|| format!("failed fsync dir: {}", dir.display()))?;
@@ -130,8 +618,12 @@ fn sync_dir_best_effort(dir: &Path) -> Result<()> {
 }
 
 impl TodoRepository for JsonFileTodoRepository {
-    fn add(&mut self, todo: Todo) {
+    fn add(&mut self, todo: Todo) -> Result<(), AppError> {
+        if self.todos.iter().any(|t| t.id == todo.id) {
+            return Err(AppError::DuplicateIds(vec![todo.id]));
+        }
         self.todos.push(todo);
+        Ok(())
     }
 
     fn list(&self) -> Vec<Todo> {
@@ -151,8 +643,13 @@ impl TodoRepository for JsonFileTodoRepository {
         self.todos.iter().find(|t| t.id == id).cloned()
     }
 
-    fn set_all(&mut self, todos: Vec<Todo>) {
+    fn set_all(&mut self, todos: Vec<Todo>) -> Result<(), AppError> {
+        let dups = repository::duplicate_ids(&todos);
+        if !dups.is_empty() {
+            return Err(AppError::DuplicateIds(dups));
+        }
         self.todos = todos;
+        Ok(())
     }
 
     fn remove(&mut self, id: TodoId) -> bool {
@@ -160,6 +657,22 @@ impl TodoRepository for JsonFileTodoRepository {
         self.todos.retain(|t| t.id != id);
         self.todos.len() != before
     }
+
+    fn projects(&self) -> Vec<Project> {
+        self.projects.clone()
+    }
+
+    fn set_projects(&mut self, projects: Vec<Project>) {
+        self.projects = projects;
+    }
+
+    fn find(&self, query: &ListQuery, now: OffsetDateTime) -> Vec<Todo> {
+        query::find_in(&self.todos, query, now)
+    }
+
+    fn count(&self, query: &ListQuery, now: OffsetDateTime) -> usize {
+        query::count_in(&self.todos, query, now)
+    }
 }
 
 #[cfg(test)]
@@ -174,7 +687,7 @@ mod tests {
         let path = dir.path().join("db.json");
 
         let mut repo = JsonFileTodoRepository::load_or_init(path.clone()).unwrap();
-        repo.add(Todo::new(Title::parse("A").unwrap()));
+        repo.add(Todo::new(Title::parse("A").unwrap())).unwrap();
         repo.save_atomic().unwrap();
 
         let repo2 = JsonFileTodoRepository::load_or_init(path).unwrap();
@@ -182,4 +695,143 @@ mod tests {
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].title.as_str(), "A");
     }
+
+    #[test]
+    fn external_writes_change_the_revision() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db.json");
+
+        let mut gui = JsonFileTodoRepository::load_or_init(path.clone()).unwrap();
+        assert!(!gui.changed_on_disk().unwrap());
+
+        let mut cli = JsonFileTodoRepository::load_or_init(path).unwrap();
+        cli.add(Todo::new(Title::parse("From the CLI").unwrap()))
+            .unwrap();
+        cli.save_atomic().unwrap();
+        assert!(gui.changed_on_disk().unwrap());
+        assert!(!cli.changed_on_disk().unwrap());
+
+        gui.save_atomic().unwrap();
+        assert!(!gui.changed_on_disk().unwrap());
+    }
+
+    #[test]
+    fn streamed_revision_matches_the_written_bytes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db.json");
+
+        let mut repo = JsonFileTodoRepository::load_or_init(path.clone()).unwrap();
+        // Several read buffers' worth, so the hash is fed in chunks.
+        repo.set_all(
+            (0..2_000)
+                .map(|i| Todo::new(Title::parse(format!("Todo {i}")).unwrap()))
+                .collect(),
+        )
+        .unwrap();
+        repo.save_atomic().unwrap();
+
+        let reloaded = JsonFileTodoRepository::load_or_init(path).unwrap();
+        assert_eq!(reloaded.revision(), repo.revision());
+        assert_eq!(reloaded.list(), repo.list());
+        assert!(!reloaded.changed_on_disk().unwrap());
+    }
+
+    #[test]
+    fn sharded_layout_splits_by_project_and_loads_selectively() {
+        use crate::domain::todo::ProjectName;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db.json");
+        let todo = |title: &str, project: &str| {
+            let mut t = Todo::new(Title::parse(title).unwrap());
+            t.project = ProjectName::parse(project).unwrap();
+            t
+        };
+
+        let mut repo = JsonFileTodoRepository::load_or_init(path.clone()).unwrap();
+        repo.set_all(vec![
+            todo("Report", "Work"),
+            todo("Deploy", "work"),
+            todo("Milk", "Home"),
+        ])
+        .unwrap();
+        repo.set_layout(Layout::Sharded);
+        repo.save_atomic().unwrap();
+        assert!(!path.exists());
+        assert_eq!(std::fs::read_dir(shard_dir(&path)).unwrap().count(), 2);
+        assert!(!repo.changed_on_disk().unwrap());
+
+        let all = JsonFileTodoRepository::load_or_init(path.clone()).unwrap();
+        assert_eq!((all.layout(), all.list().len()), (Layout::Sharded, 3));
+
+        let mut work =
+            JsonFileTodoRepository::load_projects(path.clone(), &["WORK"], None).unwrap();
+        assert_eq!(work.list().len(), 2);
+        assert!(work.is_partial() && work.save_atomic().is_err());
+
+        // Emptying a project drops its shard; going back to one file drops them all.
+        repo.set_all(
+            all.list()
+                .into_iter()
+                .filter(|t| t.project.as_str() != "Home")
+                .collect(),
+        )
+        .unwrap();
+        repo.save_atomic().unwrap();
+        assert_eq!(std::fs::read_dir(shard_dir(&path)).unwrap().count(), 1);
+        repo.set_layout(Layout::File);
+        repo.save_atomic().unwrap();
+        assert!(path.exists() && !shard_dir(&path).exists());
+        assert_eq!(
+            JsonFileTodoRepository::load_or_init(path)
+                .unwrap()
+                .list()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn encrypted_databases_need_the_key() {
+        use crate::domain::todo::ProjectName;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db.json");
+        let key = DbKey::new("correct horse").unwrap();
+
+        let mut repo = JsonFileTodoRepository::load_with_key(path.clone(), Some(key)).unwrap();
+        let mut t = Todo::new(Title::parse("Secret plan").unwrap());
+        t.project = ProjectName::parse("Work").unwrap();
+        repo.add(t).unwrap();
+        repo.save_atomic().unwrap();
+        assert!(repo.is_encrypted() && is_encrypted_on_disk(&path));
+        assert!(
+            !std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("Secret plan")
+        );
+        assert!(!repo.changed_on_disk().unwrap());
+
+        let refused = JsonFileTodoRepository::load_or_init(path.clone())
+            .err()
+            .unwrap();
+        assert!(refused.to_string().contains("is encrypted"), "{refused}");
+        let wrong = DbKey::new("wrong").unwrap();
+        assert!(JsonFileTodoRepository::load_with_key(path.clone(), Some(wrong)).is_err());
+
+        // Shards are encrypted too; dropping the key decrypts everything.
+        let key = DbKey::new("correct horse").unwrap();
+        let mut repo = JsonFileTodoRepository::load_with_key(path.clone(), Some(key)).unwrap();
+        assert_eq!(repo.list()[0].title.as_str(), "Secret plan");
+        repo.set_layout(Layout::Sharded);
+        repo.save_atomic().unwrap();
+        assert!(is_encrypted_on_disk(&path));
+        repo.set_key(None);
+        repo.save_atomic().unwrap();
+        assert!(!repo.is_encrypted() && !is_encrypted_on_disk(&path));
+        assert_eq!(
+            JsonFileTodoRepository::load_or_init(path).unwrap().list(),
+            repo.list()
+        );
+    }
 }
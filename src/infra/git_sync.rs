@@ -0,0 +1,306 @@
+//! Git-backed versioning and sync (`sync init`, `sync push`, `sync pull`,
+//! `history git-log`).
+//!
+//! `sync init`, or the first command run with `[git] autosave` on, turns the
+//! directory holding the database into a git repository that tracks only the
+//! database (file or shards) and its journal. From then on every command that
+//! changes todos is committed with the command line as its message, and any
+//! git remote works as a sync server: `sync push` sends the history, `sync pull` fetches the other
+//! side's and merges it todo by todo (see [`merge::newest_wins`]), using the
+//! last common commit to tell deletions from additions.
+//!
+//! Everything goes through the `git` command line, so credentials, SSH keys
+//! and remotes are whatever the user's git already has set up.
+//!
+//! [`merge::newest_wins`]: crate::app::merge::newest_wins
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    app::merge::{self, NewestWins},
+    infra::{
+        db_crypto::{self, DbKey},
+        db_schema::{self, DbContents},
+        fs_repo, journal, routines_file,
+    },
+};
+
+/// A database directory under git.
+#[derive(Debug, Clone)]
+pub struct GitSync {
+    dir: PathBuf,
+    /// Tracked files, relative to `dir`.
+    files: Vec<String>,
+    db_file: String,
+    /// Opens the committed database if it is encrypted.
+    key: Option<DbKey>,
+}
+
+/// What [`GitSync::pull`] found.
+#[derive(Debug)]
+pub enum Pulled {
+    /// The remote had nothing new.
+    UpToDate,
+    /// Only the remote changed; its database as is.
+    FastForward(DbContents),
+    /// Both changed: the merged database. Save it, then [`GitSync::commit`]
+    /// to conclude the merge.
+    Merged {
+        contents: DbContents,
+        merge: NewestWins,
+    },
+}
+
+/// A commit as listed by [`GitSync::log`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Abbreviated commit hash.
+    pub hash: String,
+    /// Author date, local time, `YYYY-MM-DD HH:MM`.
+    pub date: String,
+    /// First line of the message.
+    pub message: String,
+}
+
+impl GitSync {
+    /// The repository around `db_path`, if `sync init` set one up.
+    pub fn open(db_path: &Path) -> Option<Self> {
+        let sync = Self::at(db_path);
+        sync.dir.join(".git").is_dir().then_some(sync)
+    }
+
+    /// Git's lock on the index. A git killed mid-commit leaves it behind, and
+    /// later commits fail until it is removed.
+    pub fn index_lock(&self) -> PathBuf {
+        self.dir.join(".git").join("index.lock")
+    }
+
+    fn at(db_path: &Path) -> Self {
+        let dir = db_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        let name = |p: &Path| {
+            p.file_name()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let db_file = name(db_path);
+        let files = vec![
+            db_file.clone(),
+            name(&journal::journal_path(db_path)),
+            name(&fs_repo::shard_dir(db_path)),
+            name(&routines_file::routines_path(db_path)),
+        ];
+        Self {
+            dir,
+            files,
+            db_file,
+            key: None,
+        }
+    }
+
+    /// Decrypt committed databases with `key` when [pulling](Self::pull).
+    pub fn with_key(mut self, key: Option<DbKey>) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Create the repository (keeping an existing one) and commit the
+    /// database. `remote` becomes `origin`.
+    pub fn init(db_path: &Path, remote: Option<&str>) -> Result<Self> {
+        let sync = Self::at(db_path);
+        if !sync.dir.join(".git").is_dir() {
+            sync.git(&["init", "--quiet"])?;
+            sync.git(&["symbolic-ref", "HEAD", "refs/heads/main"])?;
+        }
+        if sync.git(&["config", "user.email"]).is_err() {
+            sync.git(&["config", "user.name", "rustlytodo"])?;
+            sync.git(&["config", "user.email", "rustlytodo@localhost"])?;
+        }
+
+        // Only the database is shared: logs, sync keys and the like stay out.
+        // An existing .gitignore belongs to someone else's repository.
+        let ignore_path = sync.dir.join(".gitignore");
+        let mut ignore = String::from("# Written by `rustlytodo sync init`.\n/*\n!/.gitignore\n");
+        for f in &sync.files {
+            ignore.push_str(&format!("!/{f}\n"));
+        }
+        if !ignore_path.exists() {
+            std::fs::write(&ignore_path, ignore)
+                .with_context(|| format!("failed writing {}", ignore_path.display()))?;
+        }
+
+        if let Some(url) = remote {
+            if sync.git(&["remote", "get-url", "origin"]).is_ok() {
+                sync.git(&["remote", "set-url", "origin", url])?;
+            } else {
+                sync.git(&["remote", "add", "origin", url])?;
+            }
+        }
+        sync.commit("Start versioning todos with git")?;
+        Ok(sync)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Commit the tracked files if they changed (or a merge is pending).
+    /// Returns whether a commit was made.
+    pub fn commit(&self, message: &str) -> Result<bool> {
+        let mut add = vec!["add", "--"];
+        add.extend(
+            std::iter::once(".gitignore")
+                .chain(self.files.iter().map(String::as_str))
+                .filter(|f| self.dir.join(f).exists()),
+        );
+        self.git(&add)?;
+        let merging = self.dir.join(".git").join("MERGE_HEAD").exists();
+        if !merging && self.git(&["diff", "--cached", "--quiet"]).is_ok() {
+            return Ok(false);
+        }
+        self.git(&["commit", "--quiet", "--no-verify", "-m", message])?;
+        Ok(true)
+    }
+
+    /// Push the current branch to the same branch on `remote`.
+    pub fn push(&self, remote: &str) -> Result<()> {
+        let branch = self.branch()?;
+        self.git(&[
+            "push",
+            "--quiet",
+            remote,
+            &format!("HEAD:refs/heads/{branch}"),
+        ])
+        .map(drop)
+        .context("push was rejected; run `sync pull` first if the remote has new changes")
+    }
+
+    /// Fetch the current branch from `remote` and merge it with `local`, the
+    /// database as currently loaded (and committed).
+    pub fn pull(&self, remote: &str, local: &DbContents) -> Result<Pulled> {
+        let branch = self.branch()?;
+        self.git(&["fetch", "--quiet", remote, &branch])
+            .with_context(|| format!("failed fetching {branch} from {remote}"))?;
+        if self.is_ancestor("FETCH_HEAD", "HEAD") {
+            return Ok(Pulled::UpToDate);
+        }
+        let theirs = self.read_db("FETCH_HEAD")?;
+        if self.is_ancestor("HEAD", "FETCH_HEAD") {
+            self.git(&["merge", "--quiet", "--ff-only", "FETCH_HEAD"])?;
+            return Ok(Pulled::FastForward(theirs));
+        }
+
+        let base = match self.git(&["merge-base", "HEAD", "FETCH_HEAD"]) {
+            Ok(rev) => self.read_db(rev.trim())?,
+            Err(_) => DbContents::default(),
+        };
+        let merge = merge::newest_wins(&base.todos, &local.todos, &theirs.todos);
+
+        let mut projects = local.projects.clone();
+        for p in theirs.projects {
+            if !projects.iter().any(|q| q.is_named(p.name.as_str())) {
+                projects.push(p);
+            }
+        }
+
+        // Record both parents; the merged database replaces the file before
+        // the merge is committed.
+        self.git(&[
+            "merge",
+            "--quiet",
+            "--no-ff",
+            "--no-commit",
+            "--allow-unrelated-histories",
+            "-s",
+            "ours",
+            "FETCH_HEAD",
+        ])?;
+        Ok(Pulled::Merged {
+            contents: DbContents {
+                projects,
+                todos: merge.todos.clone(),
+            },
+            merge,
+        })
+    }
+
+    /// The latest `limit` commits, newest first.
+    pub fn log(&self, limit: usize) -> Result<Vec<LogEntry>> {
+        let text = self.git(&[
+            "log",
+            &format!("--max-count={limit}"),
+            "--format=%h%x1f%ad%x1f%s",
+            "--date=format:%Y-%m-%d %H:%M",
+        ])?;
+        Ok(text
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(3, '\x1f');
+                Some(LogEntry {
+                    hash: parts.next()?.to_string(),
+                    date: parts.next()?.to_string(),
+                    message: parts.next()?.to_string(),
+                })
+            })
+            .collect())
+    }
+
+    fn branch(&self) -> Result<String> {
+        Ok(self
+            .git(&["symbolic-ref", "--short", "HEAD"])?
+            .trim()
+            .to_string())
+    }
+
+    fn is_ancestor(&self, a: &str, b: &str) -> bool {
+        self.git(&["merge-base", "--is-ancestor", a, b]).is_ok()
+    }
+
+    /// The database as of `rev` (empty if it didn't exist yet).
+    fn read_db(&self, rev: &str) -> Result<DbContents> {
+        let mut text = match self.git(&["show", &format!("{rev}:{}", self.db_file)]) {
+            Ok(text) => text,
+            Err(_) => return Ok(DbContents::default()),
+        };
+        if db_crypto::is_encrypted(text.as_bytes()) {
+            let Some(key) = &self.key else {
+                bail!(
+                    "{} at {rev} is encrypted; set encryption = true",
+                    self.db_file
+                );
+            };
+            let json = key
+                .open(text.as_bytes())
+                .with_context(|| format!("failed decrypting {} at {rev}", self.db_file))?;
+            text = String::from_utf8(json)
+                .with_context(|| format!("invalid UTF-8 in {} at {rev}", self.db_file))?;
+        }
+        db_schema::load_db(&text)
+            .with_context(|| format!("failed reading {} at {rev}", self.db_file))
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .context("failed running git (is it installed?)")?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.first().copied().unwrap_or_default(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
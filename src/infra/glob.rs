@@ -0,0 +1,95 @@
+//! Minimal globbing for path arguments (`import --in 'exports/*.json'`).
+//!
+//! `*` (any run of characters) and `?` (one character) are allowed in the
+//! file name only; the directory part is taken literally. That covers
+//! "every export in this folder" without pulling in a glob dependency.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Does `input` contain wildcards?
+pub fn is_pattern(input: &str) -> bool {
+    input.contains(['*', '?'])
+}
+
+/// Files matching `pattern`, sorted by path.
+pub fn expand(pattern: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if is_pattern(&dir.to_string_lossy()) {
+        anyhow::bail!("wildcards are only supported in the file name: {pattern}");
+    }
+
+    let entries =
+        std::fs::read_dir(&dir).with_context(|| format!("failed listing {}", dir.display()))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("failed listing {}", dir.display()))?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type().is_ok_and(|t| t.is_file()) && matches(&name, &file_name) {
+            files.push(dir.join(file_name));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    // Classic two-pointer match with backtracking to the last `*`.
+    let (mut pi, mut ni) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi, ni));
+                pi += 1;
+            }
+            Some(&c) if c == '?' || c == n[ni] => {
+                pi += 1;
+                ni += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    pi = sp + 1;
+                    ni = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_file_names() {
+        assert!(matches("*.json", "2024-laptop.json"));
+        assert!(matches("backup-??.json", "backup-01.json"));
+        assert!(matches("*lap*", "2024-laptop.json"));
+        assert!(!matches("*.json", "notes.csv"));
+        assert!(!matches("backup-?.json", "backup-01.json"));
+
+        let dir = tempfile::tempdir().unwrap();
+        for f in ["b.json", "a.json", "c.csv"] {
+            std::fs::write(dir.path().join(f), "[]").unwrap();
+        }
+        let pattern = dir.path().join("*.json");
+        let found = expand(pattern.to_str().unwrap()).unwrap();
+        let names: Vec<_> = found.iter().map(|p| p.file_name().unwrap()).collect();
+        assert_eq!(names, ["a.json", "b.json"]);
+    }
+}
@@ -0,0 +1,51 @@
+//! Undo/redo history on disk, in `<db>.history.json` next to the database.
+//!
+//! A missing or unreadable file just means there is nothing to undo: the
+//! history is a convenience, and a damaged one must not lock the user out of
+//! their todos. It holds copies of todos, so it is encrypted along with an
+//! encrypted database.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tracing::warn;
+
+use crate::{
+    app::history::History,
+    infra::{
+        db_crypto::{self, DbKey},
+        fs_repo::replace_file,
+    },
+};
+
+pub fn history_path(db_path: &Path) -> PathBuf {
+    db_path.with_extension("history.json")
+}
+
+pub fn load(path: &Path, key: Option<&DbKey>) -> History {
+    let Ok(mut bytes) = std::fs::read(path) else {
+        return History::default();
+    };
+    if db_crypto::is_encrypted(&bytes) {
+        match key.map(|k| k.open(&bytes)) {
+            Some(Ok(plain)) => bytes = plain,
+            _ => {
+                warn!(path = %path.display(), "ignoring undo history that can't be decrypted");
+                return History::default();
+            }
+        }
+    }
+    serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+        warn!(path = %path.display(), error = %e, "ignoring unreadable undo history");
+        History::default()
+    })
+}
+
+pub fn save(path: &Path, history: &History, key: Option<&DbKey>) -> Result<()> {
+    let mut bytes = serde_json::to_vec(history).context("failed serializing undo history")?;
+    if let Some(key) = key {
+        bytes = key.seal(&bytes)?;
+    }
+    replace_file(path, &bytes)
+        .with_context(|| format!("failed writing undo history: {}", path.display()))
+}
@@ -0,0 +1,264 @@
+//! User scripts run around commands (`[hooks]` in config.toml).
+//!
+//! Executables in `hooks/` next to config.toml are found by name:
+//! - `pre-<command>` (`pre-add`, `pre-delete`, ...) runs before the command
+//!   with `{"command": "delete", "line": "delete 3f2a"}` on stdin. Exiting
+//!   non-zero cancels the command; what it printed to stderr says why.
+//! - `post-<event>` runs after a command once per todo it affected, with the
+//!   todo on stdin as `export` writes it (as it was, for `post-delete`).
+//!   Events are `add`, `done`, `reopen`, `edit` and `delete`.
+//!
+//! Both get `RUSTYTODO_HOOK` (the hook's name) and `RUSTYTODO_COMMAND` in
+//! their environment. A hook still running after `timeout_secs` is killed.
+//! Post hooks can't undo anything, so their failures are only logged.
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::{
+    domain::todo::Todo,
+    infra::{config::HooksConfig, paths::AppPaths},
+};
+
+/// Environment variable holding the running hook's name.
+pub const HOOK_ENV: &str = "RUSTYTODO_HOOK";
+/// Environment variable holding the command's name.
+pub const COMMAND_ENV: &str = "RUSTYTODO_COMMAND";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Add,
+    Done,
+    Reopen,
+    Edit,
+    Delete,
+}
+
+impl Event {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Done => "done",
+            Self::Reopen => "reopen",
+            Self::Edit => "edit",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+pub fn dir(paths: &AppPaths) -> PathBuf {
+    paths.config_dir.join("hooks")
+}
+
+/// What happened to each todo between `before` and `after`, ordered by id.
+pub fn events(before: &[Todo], after: &[Todo]) -> Vec<(Event, Todo)> {
+    let old: BTreeMap<_, _> = before.iter().map(|t| (t.id, t)).collect();
+    let new: BTreeMap<_, _> = after.iter().map(|t| (t.id, t)).collect();
+
+    let mut events = Vec::new();
+    for (id, t) in &new {
+        let event = match old.get(id) {
+            None => Event::Add,
+            Some(prev) if prev == t => continue,
+            Some(prev) => match (prev.status.is_done(), t.status.is_done()) {
+                (false, true) => Event::Done,
+                (true, false) => Event::Reopen,
+                _ => Event::Edit,
+            },
+        };
+        events.push((event, (*t).clone()));
+    }
+    for (id, t) in &old {
+        if !new.contains_key(id) {
+            events.push((Event::Delete, (*t).clone()));
+        }
+    }
+    events.sort_by_key(|(_, t)| t.id);
+    events
+}
+
+/// Run `pre-<command>` if there is one. `Err` says why the command must not
+/// go ahead: the hook refused, or couldn't be run at all.
+pub fn run_pre(config: &HooksConfig, dir: &Path, command: &str, line: &str) -> Result<()> {
+    let name = format!("pre-{command}");
+    let Some(path) = find(dir, &name) else {
+        return Ok(());
+    };
+    let input = json!({ "command": command, "line": line }).to_string();
+    run(config, &path, &name, command, input.as_bytes())
+}
+
+/// Run the `post-<event>` hook for each of `events`; returns how many ran.
+pub fn run_post(
+    config: &HooksConfig,
+    dir: &Path,
+    command: &str,
+    events: &[(Event, Todo)],
+) -> usize {
+    let mut ran = 0;
+    for (event, todo) in events {
+        let name = format!("post-{}", event.label());
+        let Some(path) = find(dir, &name) else {
+            continue;
+        };
+        let input = match serde_json::to_vec(todo) {
+            Ok(input) => input,
+            Err(e) => {
+                warn!(hook = name, error = %e, "failed encoding todo for hook");
+                continue;
+            }
+        };
+        ran += 1;
+        if let Err(e) = run(config, &path, &name, command, &input) {
+            warn!(hook = name, id = %todo.id.short(), error = %format!("{e:#}"), "hook failed");
+        }
+    }
+    ran
+}
+
+/// The executable called `name` in `dir` (on Windows also `name.exe`,
+/// `.cmd` or `.bat`).
+fn find(dir: &Path, name: &str) -> Option<PathBuf> {
+    let candidates: &[&str] = if cfg!(windows) {
+        &["", ".exe", ".cmd", ".bat"]
+    } else {
+        &[""]
+    };
+    candidates
+        .iter()
+        .map(|ext| dir.join(format!("{name}{ext}")))
+        .find(|p| is_executable(p))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn run(config: &HooksConfig, path: &Path, name: &str, command: &str, input: &[u8]) -> Result<()> {
+    debug!(hook = name, "running hook");
+    let mut child = Command::new(path)
+        .env(HOOK_ENV, name)
+        .env(COMMAND_ENV, command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed running {name} hook: {}", path.display()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read its input has just closed the pipe.
+        let _ = stdin.write_all(input);
+    }
+    // Read stderr alongside, so a chatty hook can't fill the pipe and stall.
+    let stderr = child.stderr.take().map(|mut pipe| {
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = pipe.read_to_string(&mut text);
+            text
+        })
+    });
+
+    let timeout = Duration::from_secs(config.timeout_secs.max(1));
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            // Anything the hook started may still hold stderr open, so don't
+            // wait for it.
+            bail!("{name} hook timed out after {}s", timeout.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    if !status.success() {
+        let stderr = stderr.and_then(|t| t.join().ok()).unwrap_or_default();
+        let reason = stderr.trim();
+        if reason.is_empty() {
+            bail!("{name} hook failed ({status})");
+        }
+        bail!("{name} hook failed: {reason}");
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::domain::todo::Title;
+
+    fn script(dir: &Path, name: &str, body: &str) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn hooks_see_the_todo_and_can_refuse_or_time_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks = dir.path();
+        let config = HooksConfig {
+            enabled: true,
+            timeout_secs: 1,
+        };
+        let seen = hooks.join("seen.txt");
+        script(
+            hooks,
+            "post-done",
+            &format!(
+                "{{ echo \"$RUSTYTODO_HOOK $RUSTYTODO_COMMAND\"; cat; }} >> {}",
+                seen.display()
+            ),
+        );
+        script(hooks, "pre-delete", "echo 'not on fridays' >&2; exit 1");
+        script(hooks, "pre-import", "sleep 5");
+        // Not executable: ignored.
+        std::fs::write(hooks.join("post-add"), "#!/bin/sh\nexit 1\n").unwrap();
+
+        let open = Todo::new(Title::parse("Water plants").unwrap());
+        let mut done = open.clone();
+        done.mark_done().unwrap();
+        let added = Todo::new(Title::parse("Repot cactus").unwrap());
+        let events = events(std::slice::from_ref(&open), &[done.clone(), added.clone()]);
+        let kinds: Vec<_> = events.iter().map(|(e, t)| (*e, t.id)).collect();
+        let mut expected = vec![(Event::Done, open.id), (Event::Add, added.id)];
+        expected.sort_by_key(|(_, id)| *id);
+        assert_eq!(kinds, expected);
+
+        assert_eq!(run_post(&config, hooks, "done", &events), 1);
+        let text = std::fs::read_to_string(&seen).unwrap();
+        let (env, todo) = text.split_once('\n').unwrap();
+        assert_eq!(env, "post-done done");
+        let todo: Todo = serde_json::from_str(todo).unwrap();
+        assert_eq!(todo, done);
+
+        assert!(run_pre(&config, hooks, "add", "add x").is_ok());
+        let refused = run_pre(&config, hooks, "delete", "delete 1234").unwrap_err();
+        assert_eq!(
+            refused.to_string(),
+            "pre-delete hook failed: not on fridays"
+        );
+        let slow = run_pre(&config, hooks, "import", "import").unwrap_err();
+        assert!(slow.to_string().contains("timed out after 1s"), "{slow}");
+    }
+}
@@ -0,0 +1,479 @@
+//! Access journal: an append-only log of who changed which todos.
+//!
+//! One JSON object per line in `<db>.journal.jsonl`, next to the database so a
+//! shared or synced list carries its history along.
+//!
+//! With `hash_chain` enabled each entry also stores the previous entry's hash and
+//! its own (SHA-256 over the entry without `hash`). Editing, dropping or
+//! reordering past entries then breaks the chain, which [`verify`] reports.
+//! Truncating the tail can't be detected from the file alone; compare the chain
+//! head printed by `verify-journal` out of band for that.
+//!
+//! Next to an encrypted database every line is sealed with its key (see
+//! `db_crypto`), so titles aren't left readable beside it. Lines that can't
+//! be opened read as unreadable entries.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use crate::{
+    domain::todo::{Todo, TodoId},
+    infra::{
+        db_crypto::{self, DbKey},
+        perms,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeOp {
+    Added,
+    Updated,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalChange {
+    pub id: TodoId,
+    pub op: ChangeOp,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: u64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+    pub user: String,
+    pub command: String,
+    pub changes: Vec<JournalChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl JournalEntry {
+    /// Hash of the entry with its own `hash` field left out.
+    pub fn compute_hash(&self) -> String {
+        let unhashed = JournalEntry {
+            hash: None,
+            ..self.clone()
+        };
+        let bytes = serde_json::to_vec(&unhashed).expect("journal entries always serialize");
+        hex(&Sha256::digest(bytes))
+    }
+}
+
+/// What [`verify`] found.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub entries: usize,
+    pub chained: usize,
+    /// Hash of the last chained entry.
+    pub head: Option<String>,
+    /// Human-readable problems, each prefixed with its line number.
+    pub problems: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+pub fn journal_path(db_path: &Path) -> PathBuf {
+    db_path.with_extension("journal.jsonl")
+}
+
+/// Per-todo differences between two snapshots, ordered by id.
+pub fn diff(before: &[Todo], after: &[Todo]) -> Vec<JournalChange> {
+    let old: BTreeMap<_, _> = before.iter().map(|t| (t.id, t)).collect();
+    let new: BTreeMap<_, _> = after.iter().map(|t| (t.id, t)).collect();
+
+    let mut changes = Vec::new();
+    for (id, t) in &new {
+        let op = match old.get(id) {
+            None => ChangeOp::Added,
+            Some(prev) if prev != t => ChangeOp::Updated,
+            Some(_) => continue,
+        };
+        changes.push(JournalChange {
+            id: *id,
+            op,
+            title: t.title.as_str().to_string(),
+        });
+    }
+    for (id, t) in &old {
+        if !new.contains_key(id) {
+            changes.push(JournalChange {
+                id: *id,
+                op: ChangeOp::Removed,
+                title: t.title.as_str().to_string(),
+            });
+        }
+    }
+    changes.sort_by_key(|c| c.id);
+    changes
+}
+
+/// Append one entry for `changes` (no-op if empty), sealed with `key` if
+/// there is one.
+pub fn append(
+    path: &Path,
+    key: Option<&DbKey>,
+    command: &str,
+    changes: Vec<JournalChange>,
+    hash_chain: bool,
+) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let last = read_entries(path, key)?
+        .into_iter()
+        .rev()
+        .find_map(Result::ok);
+    let entry = JournalEntry {
+        seq: 0,
+        at: OffsetDateTime::now_utc(),
+        user: current_user(),
+        command: command.to_string(),
+        changes,
+        prev: None,
+        hash: None,
+    };
+    write_after(path, key, last.as_ref(), vec![entry], hash_chain)
+}
+
+/// The entries that touched any of `ids`, oldest first, without their
+/// changes to other todos. Entries that lost changes lose their hash too:
+/// it no longer matches.
+pub fn about(entries: Vec<JournalEntry>, ids: &BTreeSet<TodoId>) -> Vec<JournalEntry> {
+    entries
+        .into_iter()
+        .filter_map(|mut e| {
+            let all = e.changes.len();
+            e.changes.retain(|c| ids.contains(&c.id));
+            if e.changes.is_empty() {
+                return None;
+            }
+            if e.changes.len() < all {
+                e.prev = None;
+                e.hash = None;
+            }
+            Some(e)
+        })
+        .collect()
+}
+
+/// Add `incoming` entries (from another machine's export) to the journal,
+/// skipping any it already has. They keep their time, user, command and
+/// changes but are numbered after the local entries, and chained if the
+/// local journal or the incoming one is. Returns how many were added.
+pub fn restore(
+    path: &Path,
+    key: Option<&DbKey>,
+    incoming: Vec<JournalEntry>,
+    hash_chain: bool,
+) -> Result<usize> {
+    let local: Vec<JournalEntry> = read_entries(path, key)?.into_iter().flatten().collect();
+    let same = |a: &JournalEntry, b: &JournalEntry| {
+        a.at == b.at && a.user == b.user && a.command == b.command && a.changes == b.changes
+    };
+    let chain = hash_chain
+        || local.last().is_some_and(|e| e.hash.is_some())
+        || incoming.iter().any(|e| e.hash.is_some());
+    let new: Vec<JournalEntry> = incoming
+        .into_iter()
+        .filter(|e| !local.iter().any(|l| same(l, e)))
+        .collect();
+    let added = new.len();
+    write_after(path, key, local.last(), new, chain)?;
+    Ok(added)
+}
+
+/// Number (and, with `hash_chain`, chain) `entries` to follow `last`, then
+/// append them.
+fn write_after(
+    path: &Path,
+    key: Option<&DbKey>,
+    last: Option<&JournalEntry>,
+    entries: Vec<JournalEntry>,
+    hash_chain: bool,
+) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut seq = last.map_or(0, |e| e.seq);
+    let mut prev = last.and_then(|e| e.hash.clone());
+    let mut lines = String::new();
+    for mut entry in entries {
+        seq += 1;
+        entry.seq = seq;
+        entry.prev = None;
+        entry.hash = None;
+        if hash_chain {
+            entry.prev = prev.take();
+            entry.hash = Some(entry.compute_hash());
+            prev = entry.hash.clone();
+        }
+        let line = serde_json::to_string(&entry).context("failed serializing journal entry")?;
+        match key {
+            Some(key) => lines.push_str(&String::from_utf8(key.seal(line.as_bytes())?)?),
+            None => lines.push_str(&line),
+        }
+        lines.push('\n');
+    }
+
+    let mut f = perms::append(path)
+        .with_context(|| format!("failed opening journal: {}", path.display()))?;
+    f.write_all(lines.as_bytes())
+        .with_context(|| format!("failed writing journal: {}", path.display()))
+}
+
+/// Check sequence numbers and the hash chain.
+///
+/// Entries written before chaining was enabled are accepted as-is, but once the
+/// chain starts every later entry must be chained.
+pub fn verify(path: &Path, key: Option<&DbKey>) -> Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+    let mut prev: Option<JournalEntry> = None;
+
+    for (i, parsed) in read_entries(path, key)?.into_iter().enumerate() {
+        let line = i + 1;
+        let entry = match parsed {
+            Ok(e) => e,
+            Err(msg) => {
+                report.problems.push(format!("line {line}: {msg}"));
+                continue;
+            }
+        };
+        report.entries += 1;
+
+        let expected_seq = prev.as_ref().map_or(1, |p| p.seq + 1);
+        if entry.seq != expected_seq {
+            report.problems.push(format!(
+                "line {line}: expected seq {expected_seq}, found {} (entries missing or reordered)",
+                entry.seq
+            ));
+        }
+
+        let prev_hash = prev.as_ref().and_then(|p| p.hash.clone());
+        match &entry.hash {
+            Some(hash) => {
+                report.chained += 1;
+                if *hash != entry.compute_hash() {
+                    report
+                        .problems
+                        .push(format!("line {line}: contents do not match its hash"));
+                }
+                if entry.prev != prev_hash {
+                    report
+                        .problems
+                        .push(format!("line {line}: does not link to the previous entry"));
+                }
+                report.head = Some(hash.clone());
+            }
+            None if prev_hash.is_some() => report.problems.push(format!(
+                "line {line}: unchained entry after the chain started"
+            )),
+            None => {}
+        }
+
+        prev = Some(entry);
+    }
+
+    Ok(report)
+}
+
+/// The last `n` readable entries, oldest first.
+pub fn tail(path: &Path, key: Option<&DbKey>, n: usize) -> Result<Vec<JournalEntry>> {
+    let entries = entries(path, key)?;
+    let skip = entries.len().saturating_sub(n);
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+/// Every readable entry, oldest first.
+pub fn entries(path: &Path, key: Option<&DbKey>) -> Result<Vec<JournalEntry>> {
+    Ok(read_entries(path, key)?
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect())
+}
+
+/// The `journal` section of an `export --include-history` file (empty if
+/// it has none).
+pub fn from_export(json_text: &str) -> Result<Vec<JournalEntry>> {
+    #[derive(Deserialize)]
+    struct Export {
+        #[serde(default)]
+        journal: Vec<JournalEntry>,
+    }
+    let export: Export =
+        serde_json::from_str(json_text).context("failed reading the export's journal")?;
+    Ok(export.journal)
+}
+
+/// Parse every line, opening sealed ones with `key`; malformed ones come
+/// back as `Err(reason)`.
+fn read_entries(path: &Path, key: Option<&DbKey>) -> Result<Vec<Result<JournalEntry, String>>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed reading journal: {}", path.display()))?;
+    Ok(text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| read_line(l, key))
+        .collect())
+}
+
+fn read_line(line: &str, key: Option<&DbKey>) -> Result<JournalEntry, String> {
+    if !db_crypto::is_encrypted(line.as_bytes()) {
+        return serde_json::from_str(line).map_err(|e| format!("unreadable entry ({e})"));
+    }
+    let key = key.ok_or("encrypted entry (no database key)")?;
+    let plain = key
+        .open(line.as_bytes())
+        .map_err(|e| format!("unreadable entry ({e:#})"))?;
+    serde_json::from_slice(&plain).map_err(|e| format!("unreadable entry ({e})"))
+}
+
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::Title;
+    use tempfile::tempdir;
+
+    fn change(title: &str) -> Vec<JournalChange> {
+        diff(&[], &[Todo::new(Title::parse(title).unwrap())])
+    }
+
+    #[test]
+    fn chained_journal_verifies() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db.journal.jsonl");
+
+        append(&path, None, "add", change("Plain"), false).unwrap();
+        append(&path, None, "add", change("First chained"), true).unwrap();
+        append(&path, None, "add", change("Second chained"), true).unwrap();
+
+        let report = verify(&path, None).unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!((report.entries, report.chained), (3, 2));
+        assert!(report.head.is_some());
+    }
+
+    #[test]
+    fn sealed_lines_need_the_key() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db.journal.jsonl");
+        let key = DbKey::new("correct horse").unwrap();
+
+        append(&path, Some(&key), "add", change("Secret plan"), true).unwrap();
+        append(&path, Some(&key), "add", change("Other plan"), true).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("plan"), "{text}");
+
+        let titles: Vec<_> = entries(&path, Some(&key))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.changes[0].title.clone())
+            .collect();
+        assert_eq!(titles, ["Secret plan", "Other plan"]);
+        assert!(verify(&path, Some(&key)).unwrap().is_ok());
+        assert!(entries(&path, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn edited_or_dropped_entries_are_detected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db.journal.jsonl");
+        for title in ["One", "Two", "Three"] {
+            append(&path, None, "add", change(title), true).unwrap();
+        }
+        let text = std::fs::read_to_string(&path).unwrap();
+
+        std::fs::write(&path, text.replace("\"Two\"", "\"Forged\"")).unwrap();
+        let report = verify(&path, None).unwrap();
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].starts_with("line 2: contents"));
+
+        let without_second: Vec<_> = text
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, l)| l)
+            .collect();
+        std::fs::write(&path, without_second.join("\n")).unwrap();
+        let report = verify(&path, None).unwrap();
+        assert!(!report.is_ok());
+        assert!(report.problems.iter().all(|p| p.starts_with("line 2:")));
+    }
+
+    #[test]
+    fn partial_histories_restore_renumbered_and_chained() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("a.journal.jsonl");
+        let a = Todo::new(Title::parse("A").unwrap());
+        let b = Todo::new(Title::parse("B").unwrap());
+        append(
+            &source,
+            None,
+            "add",
+            diff(&[], std::slice::from_ref(&a)),
+            true,
+        )
+        .unwrap();
+        append(
+            &source,
+            None,
+            "add",
+            diff(&[], std::slice::from_ref(&b)),
+            true,
+        )
+        .unwrap();
+        append(
+            &source,
+            None,
+            "import",
+            diff(&[], &[a.clone(), b.clone()]),
+            true,
+        )
+        .unwrap();
+
+        // Only A's history: the import entry is cut down and loses its hash.
+        let only_a = about(entries(&source, None).unwrap(), &BTreeSet::from([a.id]));
+        assert_eq!(only_a.len(), 2);
+        assert!(only_a[0].hash.is_some() && only_a[1].hash.is_none());
+        assert_eq!(only_a[1].changes.len(), 1);
+
+        let target = dir.path().join("b.journal.jsonl");
+        append(&target, None, "add", change("Local"), false).unwrap();
+        assert_eq!(restore(&target, None, only_a.clone(), false).unwrap(), 2);
+        assert_eq!(restore(&target, None, only_a, false).unwrap(), 0);
+        let report = verify(&target, None).unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!((report.entries, report.chained), (3, 2));
+    }
+}
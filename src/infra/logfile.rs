@@ -0,0 +1,388 @@
+//! JSON log file with size-based rotation (`[log]` in config.toml).
+//!
+//! When enabled, every tracing event at or above the configured level is
+//! appended to `<data dir>/logs/rustlytodo.log` as one JSON object per line,
+//! alongside the usual terminal output. Once the file reaches `max_size_kb` it
+//! becomes `rustlytodo.log.1` (older files shift up to `.2`, `.3`, ...) and
+//! only `keep` rotated files are kept. `logs show` and `logs tail` read them
+//! back, so problems in the TUI or `serve` can be looked at afterwards.
+
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{
+    filter::LevelFilter,
+    layer::{Context, Layer, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+};
+
+use crate::infra::{config::LogConfig, perms};
+
+pub const FILE_NAME: &str = "rustlytodo.log";
+
+/// Where the log files live.
+pub fn log_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("logs")
+}
+
+/// Parse a level name as written in config.toml (`warn`, `INFO`, ...).
+pub fn parse_level(input: &str) -> Option<Level> {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "error" => Some(Level::ERROR),
+        "warn" | "warning" => Some(Level::WARN),
+        "info" => Some(Level::INFO),
+        "debug" => Some(Level::DEBUG),
+        "trace" => Some(Level::TRACE),
+        _ => None,
+    }
+}
+
+/// Install the global subscriber: terminal output at `terminal`, plus the
+/// JSON file if `cfg.file` is set.
+///
+/// A log file that can't be opened is reported and skipped rather than
+/// stopping the program.
+pub fn init(terminal: Level, data_dir: &Path, cfg: &LogConfig) {
+    let mut problem = None;
+    let file_layer = if cfg.file {
+        let level = parse_level(&cfg.level).unwrap_or_else(|| {
+            problem = Some(format!("unknown [log] level {:?}; using info", cfg.level));
+            Level::INFO
+        });
+        let max_bytes = cfg.max_size_kb.max(1) * 1024;
+        match RotatingFile::open(&log_dir(data_dir), max_bytes, cfg.keep) {
+            Ok(file) => Some(JsonFileLayer::new(file).with_filter(LevelFilter::from_level(level))),
+            Err(e) => {
+                problem = Some(format!("can't open log file: {e}"));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    tracing_subscriber::registry()
+        .with(
+            // On stderr, so piped output (`--format json`, `rpc`) stays clean.
+            tracing_subscriber::fmt::layer()
+                .with_writer(io::stderr)
+                .with_target(false)
+                .with_ansi(ansi_terminal())
+                .with_filter(LevelFilter::from_level(terminal)),
+        )
+        .with(file_layer)
+        .init();
+
+    if let Some(problem) = problem {
+        tracing::warn!("{problem}");
+    }
+}
+
+/// Whether the terminal shows colors. Legacy Windows consoles print the
+/// escape codes literally until virtual terminal processing is switched on.
+fn ansi_terminal() -> bool {
+    #[cfg(windows)]
+    return crate::infra::windows::enable_ansi();
+    #[cfg(not(windows))]
+    true
+}
+
+/// One line of the log file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// RFC3339, UTC.
+    pub ts: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Names of the spans the event happened in, outermost first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+impl LogEntry {
+    pub fn level(&self) -> Option<Level> {
+        parse_level(&self.level)
+    }
+
+    /// Human-readable rendering: `<ts> <LEVEL> <spans>: <message> key=value`.
+    pub fn render(&self) -> String {
+        let mut line = format!("{} {:>5} ", self.ts, self.level);
+        if !self.spans.is_empty() {
+            let _ = write!(line, "{}: ", self.spans.join(":"));
+        }
+        line.push_str(&self.message);
+        for (key, value) in &self.fields {
+            match value {
+                Value::String(s) => {
+                    let _ = write!(line, " {key}={s}");
+                }
+                other => {
+                    let _ = write!(line, " {key}={other}");
+                }
+            }
+        }
+        line
+    }
+}
+
+/// Append-only file that rotates itself by size.
+pub struct RotatingFile {
+    dir: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(dir: &Path, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        perms::create_dir(dir)?;
+        let file = open_append(&dir.join(FILE_NAME))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            keep,
+            file,
+            size,
+        })
+    }
+
+    /// Append `line` and a newline, rotating first if it wouldn't fit.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let current = self.dir.join(FILE_NAME);
+        // Drop the oldest (and any left over from a larger `keep`).
+        let mut n = self.keep.max(1);
+        while rotated_path(&self.dir, n).exists() {
+            std::fs::remove_file(rotated_path(&self.dir, n))?;
+            n += 1;
+        }
+        if self.keep == 0 {
+            std::fs::remove_file(&current)?;
+        } else {
+            for i in (1..self.keep).rev() {
+                let from = rotated_path(&self.dir, i);
+                if from.exists() {
+                    std::fs::rename(&from, rotated_path(&self.dir, i + 1))?;
+                }
+            }
+            std::fs::rename(&current, rotated_path(&self.dir, 1))?;
+        }
+        self.file = open_append(&current)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    perms::append(path)
+}
+
+fn rotated_path(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("{FILE_NAME}.{n}"))
+}
+
+/// Log files in `dir`, oldest first (the current file last).
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut rotated = Vec::new();
+    let mut n = 1;
+    while rotated_path(dir, n).exists() {
+        rotated.push(rotated_path(dir, n));
+        n += 1;
+    }
+    rotated.reverse();
+    let current = dir.join(FILE_NAME);
+    if current.exists() {
+        rotated.push(current);
+    }
+    rotated
+}
+
+/// Every entry in `dir`, oldest first. Lines that aren't entries (a write
+/// cut short by a crash) are skipped.
+pub fn read_entries(dir: &Path) -> Result<Vec<LogEntry>> {
+    let mut entries = Vec::new();
+    for path in log_files(dir) {
+        let text = std::fs::read_to_string(&path)?;
+        entries.extend(parse_lines(&text));
+    }
+    Ok(entries)
+}
+
+/// Entries in a chunk of log text.
+pub fn parse_lines(text: &str) -> impl Iterator<Item = LogEntry> + '_ {
+    text.lines().filter_map(|l| serde_json::from_str(l).ok())
+}
+
+/// Tracing layer writing [`LogEntry`] lines to a [`RotatingFile`].
+pub struct JsonFileLayer {
+    out: Mutex<RotatingFile>,
+}
+
+impl JsonFileLayer {
+    pub fn new(file: RotatingFile) -> Self {
+        Self {
+            out: Mutex::new(file),
+        }
+    }
+}
+
+impl<S> Layer<S> for JsonFileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| scope.from_root().map(|s| s.name().to_string()).collect())
+            .unwrap_or_default();
+        let entry = LogEntry {
+            ts: OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .unwrap_or_default(),
+            level: meta.level().to_string(),
+            target: meta.target().to_string(),
+            message: visitor.message,
+            spans,
+            fields: visitor.fields,
+        };
+        let Ok(line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        if let Ok(mut out) = self.out.lock() {
+            // Nowhere left to report a failing log write.
+            let _ = out.write_line(&line);
+        }
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl FieldVisitor {
+    fn put(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.put(field, Value::String(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.put(field, Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.put(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.put(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.put(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.put(field, Value::from(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn rotates_by_size_and_keeps_only_the_newest_files() {
+        let dir = tempdir().unwrap();
+        let mut file = RotatingFile::open(dir.path(), 100, 2).unwrap();
+        let entry = |i: usize| LogEntry {
+            ts: "2026-03-01T12:00:00Z".to_string(),
+            level: "INFO".to_string(),
+            target: "test".to_string(),
+            message: format!("entry {i}"),
+            spans: Vec::new(),
+            fields: Map::new(),
+        };
+        // Each line is ~90 bytes, so every write after the first rotates.
+        for i in 0..5 {
+            let line = serde_json::to_string(&entry(i)).unwrap();
+            file.write_line(&line).unwrap();
+        }
+
+        let files = log_files(dir.path());
+        assert_eq!(files.len(), 3);
+        assert!(!rotated_path(dir.path(), 3).exists());
+        let messages: Vec<_> = read_entries(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+        assert_eq!(messages, ["entry 2", "entry 3", "entry 4"]);
+    }
+
+    #[test]
+    fn entries_render_fields_and_spans() {
+        let mut fields = Map::new();
+        fields.insert("db".to_string(), Value::from("/tmp/db.json"));
+        fields.insert("todos".to_string(), Value::from(3));
+        let entry = LogEntry {
+            ts: "2026-03-01T12:00:00Z".to_string(),
+            level: "WARN".to_string(),
+            target: "rustytodo".to_string(),
+            message: "slow save".to_string(),
+            spans: vec!["command".to_string(), "save".to_string()],
+            fields,
+        };
+        assert_eq!(
+            entry.render(),
+            "2026-03-01T12:00:00Z  WARN command:save: slow save db=/tmp/db.json todos=3"
+        );
+        assert_eq!(entry.level(), Some(Level::WARN));
+    }
+}
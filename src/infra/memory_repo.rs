@@ -2,15 +2,25 @@
 //!
 //! Used for early development and tests.
 
+use time::OffsetDateTime;
+
 use crate::{
-    app::repository::TodoRepository,
-    domain::todo::{Todo, TodoId},
+    app::{
+        errors::AppError,
+        query::{self, ListQuery},
+        repository::{self, TodoRepository},
+    },
+    domain::{
+        project::Project,
+        todo::{Todo, TodoId},
+    },
 };
 
 /// Simple in-memory store.
 #[derive(Default)]
 pub struct MemoryTodoRepository {
     todos: Vec<Todo>,
+    projects: Vec<Project>,
 }
 
 impl MemoryTodoRepository {
@@ -20,8 +30,12 @@ impl MemoryTodoRepository {
 }
 
 impl TodoRepository for MemoryTodoRepository {
-    fn add(&mut self, todo: Todo) {
+    fn add(&mut self, todo: Todo) -> Result<(), AppError> {
+        if self.todos.iter().any(|t| t.id == todo.id) {
+            return Err(AppError::DuplicateIds(vec![todo.id]));
+        }
         self.todos.push(todo);
+        Ok(())
     }
 
     fn list(&self) -> Vec<Todo> {
@@ -41,8 +55,13 @@ impl TodoRepository for MemoryTodoRepository {
         self.todos.iter().find(|t| t.id == id).cloned()
     }
 
-    fn set_all(&mut self, todos: Vec<Todo>) {
+    fn set_all(&mut self, todos: Vec<Todo>) -> Result<(), AppError> {
+        let dups = repository::duplicate_ids(&todos);
+        if !dups.is_empty() {
+            return Err(AppError::DuplicateIds(dups));
+        }
         self.todos = todos;
+        Ok(())
     }
 
     fn remove(&mut self, id: TodoId) -> bool {
@@ -50,6 +69,22 @@ impl TodoRepository for MemoryTodoRepository {
         self.todos.retain(|t| t.id != id);
         self.todos.len() != before
     }
+
+    fn projects(&self) -> Vec<Project> {
+        self.projects.clone()
+    }
+
+    fn set_projects(&mut self, projects: Vec<Project>) {
+        self.projects = projects;
+    }
+
+    fn find(&self, query: &ListQuery, now: OffsetDateTime) -> Vec<Todo> {
+        query::find_in(&self.todos, query, now)
+    }
+
+    fn count(&self, query: &ListQuery, now: OffsetDateTime) -> usize {
+        query::count_in(&self.todos, query, now)
+    }
 }
 
 #[cfg(test)]
@@ -60,8 +95,8 @@ mod tests {
     #[test]
     fn repo_add_then_list_returns_items() {
         let mut repo = MemoryTodoRepository::new();
-        repo.add(Todo::new(Title::parse("One").unwrap()));
-        repo.add(Todo::new(Title::parse("Two").unwrap()));
+        repo.add(Todo::new(Title::parse("One").unwrap())).unwrap();
+        repo.add(Todo::new(Title::parse("Two").unwrap())).unwrap();
 
         let items = repo.list();
         assert_eq!(items.len(), 2);
@@ -74,7 +109,7 @@ mod tests {
         let mut repo = MemoryTodoRepository::new();
         let mut t = Todo::new(Title::parse("One").unwrap());
         let id = t.id;
-        repo.add(t.clone());
+        repo.add(t.clone()).unwrap();
 
         // mutate and replace
         t.title = Title::parse("Updated").unwrap();
@@ -83,4 +118,36 @@ mod tests {
         let got = repo.get(id).unwrap();
         assert_eq!(got.title.as_str(), "Updated");
     }
+
+    #[test]
+    fn find_and_count_match_the_in_memory_query() {
+        use crate::app::query::{SortKey, apply_list_query};
+        use crate::domain::todo::{Priority, ProjectName};
+
+        let now = OffsetDateTime::now_utc();
+        let mut repo = MemoryTodoRepository::new();
+        // The P3 blocker inherits P1 from the todo it blocks, even though the
+        // blocked todo is in another project and filtered out.
+        let blocker = Todo::new(Title::parse("Blocker").unwrap());
+        let mut blocked = Todo::new(Title::parse("Blocked").unwrap());
+        blocked.priority = Priority::P1;
+        blocked.project = ProjectName::parse("Other").unwrap();
+        blocked.depends_on.insert(blocker.id);
+        let mut p2 = Todo::new(Title::parse("P2").unwrap());
+        p2.priority = Priority::P2;
+        for t in [p2, blocked, blocker] {
+            repo.add(t).unwrap();
+        }
+
+        let q = ListQuery {
+            project: Some("Inbox".to_string()),
+            sort: SortKey::Priority,
+            ..ListQuery::default()
+        };
+        let found = repo.find(&q, now);
+        assert_eq!(found, apply_list_query(repo.list(), &q, now));
+        let titles: Vec<_> = found.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["Blocker", "P2"]);
+        assert_eq!(repo.count(&q, now), 2);
+    }
 }
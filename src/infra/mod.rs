@@ -1,10 +1,61 @@
 //! Infrastructure layer.
 //!
 //! Concrete implementations of external concerns.
+//!
+//! Filesystem-backed modules are gated behind the `native` feature;
+//! `db_schema` and `memory_repo` stay available to the portable core.
 
+#[cfg(feature = "native")]
+pub mod api_tokens;
+#[cfg(feature = "native")]
+pub mod attachments;
+#[cfg(feature = "native")]
+pub mod backup;
+#[cfg(feature = "native")]
 pub mod config;
+#[cfg(feature = "native")]
+pub mod crash;
+#[cfg(feature = "native")]
 pub mod csv_io;
+#[cfg(feature = "native")]
+pub mod db_crypto;
 pub mod db_schema;
+#[cfg(feature = "native")]
 pub mod fs_repo;
+#[cfg(feature = "native")]
+pub mod git_sync;
+#[cfg(feature = "native")]
+pub mod glob;
+#[cfg(feature = "native")]
+pub mod history_file;
+#[cfg(feature = "native")]
+pub mod hooks;
+#[cfg(feature = "native")]
+pub mod journal;
+#[cfg(feature = "native")]
+pub mod logfile;
 pub mod memory_repo;
+#[cfg(feature = "native")]
+pub mod mqtt;
+#[cfg(feature = "native")]
+pub mod notify;
+#[cfg(feature = "native")]
 pub mod paths;
+#[cfg(feature = "native")]
+pub mod perms;
+#[cfg(feature = "native")]
+pub mod recovery;
+#[cfg(feature = "native")]
+pub mod routines_file;
+#[cfg(feature = "native")]
+pub mod snapshot;
+#[cfg(feature = "native")]
+pub mod sync_crypto;
+#[cfg(feature = "native")]
+pub mod sync_store;
+#[cfg(feature = "native")]
+pub mod timings;
+#[cfg(feature = "native")]
+pub mod todoist_io;
+#[cfg(all(feature = "native", windows))]
+pub mod windows;
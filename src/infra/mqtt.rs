@@ -0,0 +1,373 @@
+//! MQTT event publishing (for home automation).
+//!
+//! After every command the CLI compares the todos before and after and
+//! publishes what happened to `<prefix>/events/<kind>`:
+//! `added`, `completed`, `overdue` (a todo just went past its due date) and
+//! `overdue_cleared` (it was finished, rescheduled or deleted). Overdue
+//! transitions depend on the clock rather than on a command, so the todos
+//! already announced as overdue are remembered in `mqtt_state.json`; run
+//! `rustytodo mqtt check` from cron to catch them between commands.
+//!
+//! A retained `<prefix>/overdue` summary (`{"count": 2, "by_priority": {"P1": 1,
+//! ...}}`) is republished whenever the overdue set changes, so automations like
+//! "lamp on while a P1 is overdue" can key off one topic.
+//!
+//! The client is deliberately minimal: MQTT 3.1.1 over plain TCP, QoS 0,
+//! connect-publish-disconnect.
+
+use std::{
+    collections::BTreeSet,
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+use crate::{
+    domain::todo::{Priority, Todo, TodoId},
+    infra::{config::MqttConfig, paths::AppPaths, perms},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Added,
+    Completed,
+    Overdue,
+    OverdueCleared,
+}
+
+impl EventKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Completed => "completed",
+            Self::Overdue => "overdue",
+            Self::OverdueCleared => "overdue_cleared",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoEvent {
+    pub kind: EventKind,
+    pub todo: Todo,
+}
+
+/// Todos already announced as overdue.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MqttState {
+    pub overdue: BTreeSet<TodoId>,
+}
+
+pub fn state_path(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join("mqtt_state.json")
+}
+
+pub fn load_state(path: &Path) -> Result<MqttState> {
+    if !path.exists() {
+        return Ok(MqttState::default());
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed reading mqtt state: {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("invalid mqtt state: {}", path.display()))
+}
+
+fn save_state(path: &Path, state: &MqttState) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        perms::create_dir(parent)
+            .with_context(|| format!("failed creating directory: {}", parent.display()))?;
+    }
+    let json = serde_json::to_string_pretty(state)?;
+    perms::write(path, json)
+        .with_context(|| format!("failed writing mqtt state: {}", path.display()))
+}
+
+/// Events caused by a command: new todos and todos that were just completed.
+pub fn change_events(before: &[Todo], after: &[Todo]) -> Vec<TodoEvent> {
+    let mut events = Vec::new();
+    for t in after {
+        let kind = match before.iter().find(|b| b.id == t.id) {
+            None => EventKind::Added,
+            Some(b) if !b.status.is_done() && t.status.is_done() => EventKind::Completed,
+            Some(_) => continue,
+        };
+        events.push(TodoEvent {
+            kind,
+            todo: t.clone(),
+        });
+    }
+    events
+}
+
+/// Overdue transitions since `announced`, plus the new overdue set.
+pub fn overdue_events(
+    todos: &[Todo],
+    announced: &BTreeSet<TodoId>,
+    now: OffsetDateTime,
+) -> (Vec<TodoEvent>, BTreeSet<TodoId>) {
+    let overdue: BTreeSet<TodoId> = todos
+        .iter()
+        .filter(|t| t.is_overdue(now))
+        .map(|t| t.id)
+        .collect();
+
+    let events = todos
+        .iter()
+        .filter_map(|t| {
+            let kind = match (announced.contains(&t.id), overdue.contains(&t.id)) {
+                (false, true) => EventKind::Overdue,
+                (true, false) => EventKind::OverdueCleared,
+                _ => return None,
+            };
+            Some(TodoEvent {
+                kind,
+                todo: t.clone(),
+            })
+        })
+        .collect();
+    (events, overdue)
+}
+
+pub fn event_payload(event: &TodoEvent, now: OffsetDateTime) -> String {
+    let t = &event.todo;
+    json!({
+        "event": event.kind.label(),
+        "at": now.format(&Rfc3339).unwrap_or_default(),
+        "id": t.id,
+        "title": t.title.as_str(),
+        "project": t.project.as_str(),
+        "priority": t.priority.label(),
+        "due": t.due.map(|d| d.format_rfc3339()),
+    })
+    .to_string()
+}
+
+pub fn overdue_summary(todos: &[Todo], now: OffsetDateTime) -> String {
+    let overdue: Vec<&Todo> = todos.iter().filter(|t| t.is_overdue(now)).collect();
+    let by_priority: serde_json::Map<String, serde_json::Value> =
+        [Priority::P1, Priority::P2, Priority::P3, Priority::P4]
+            .into_iter()
+            .map(|p| {
+                let n = overdue.iter().filter(|t| t.priority == p).count();
+                (p.label().to_string(), json!(n))
+            })
+            .collect();
+    json!({ "count": overdue.len(), "by_priority": by_priority }).to_string()
+}
+
+/// Publish the events caused by a command and any overdue transitions.
+///
+/// Doesn't connect at all when there is nothing to say. Returns the number of
+/// events published.
+pub fn publish_changes(
+    cfg: &MqttConfig,
+    state_path: &Path,
+    before: &[Todo],
+    after: &[Todo],
+) -> Result<usize> {
+    let now = OffsetDateTime::now_utc();
+    let mut state = load_state(state_path)?;
+
+    let mut events = change_events(before, after);
+    let (transitions, overdue) = overdue_events(after, &state.overdue, now);
+    events.extend(transitions);
+    let overdue_changed = overdue != state.overdue;
+    if events.is_empty() && !overdue_changed {
+        return Ok(0);
+    }
+
+    let prefix = cfg.topic_prefix.trim_end_matches('/');
+    let mut client = Client::connect(cfg)?;
+    for e in &events {
+        let topic = format!("{prefix}/events/{}", e.kind.label());
+        client.publish(&topic, &event_payload(e, now), false)?;
+    }
+    if overdue_changed {
+        client.publish(
+            &format!("{prefix}/overdue"),
+            &overdue_summary(after, now),
+            true,
+        )?;
+    }
+    client.disconnect()?;
+
+    // Only remember what was actually delivered, so a broker outage is retried.
+    state.overdue = overdue;
+    save_state(state_path, &state)?;
+    Ok(events.len())
+}
+
+/// A connected MQTT session.
+pub struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    pub fn connect(cfg: &MqttConfig) -> Result<Self> {
+        let addr = cfg
+            .broker
+            .to_socket_addrs()
+            .with_context(|| format!("invalid mqtt broker address: {}", cfg.broker))?
+            .next()
+            .ok_or_else(|| anyhow!("mqtt broker did not resolve: {}", cfg.broker))?;
+        let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)
+            .with_context(|| format!("failed connecting to mqtt broker {}", cfg.broker))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        stream.write_all(&connect_packet(cfg))?;
+
+        let mut connack = [0u8; 4];
+        stream
+            .read_exact(&mut connack)
+            .context("mqtt broker did not acknowledge the connection")?;
+        if connack[0] != 0x20 {
+            bail!(
+                "unexpected packet from mqtt broker (type {:#04x})",
+                connack[0]
+            );
+        }
+        match connack[3] {
+            0 => Ok(Self { stream }),
+            4 | 5 => bail!("mqtt broker rejected the credentials"),
+            code => bail!("mqtt broker refused the connection (code {code})"),
+        }
+    }
+
+    /// Publish at QoS 0 (fire and forget).
+    pub fn publish(&mut self, topic: &str, payload: &str, retain: bool) -> Result<()> {
+        let mut body = Vec::new();
+        put_str(&mut body, topic);
+        body.extend_from_slice(payload.as_bytes());
+        let flags = if retain { 0x31 } else { 0x30 };
+        self.stream
+            .write_all(&packet(flags, &body))
+            .with_context(|| format!("failed publishing to {topic}"))
+    }
+
+    pub fn disconnect(mut self) -> Result<()> {
+        self.stream.write_all(&[0xE0, 0x00])?;
+        self.stream.flush()?;
+        Ok(())
+    }
+}
+
+fn connect_packet(cfg: &MqttConfig) -> Vec<u8> {
+    let mut flags = 0x02; // clean session
+    if cfg.username.is_some() {
+        flags |= 0x80;
+    }
+    if cfg.password.is_some() {
+        flags |= 0x40;
+    }
+
+    let mut body = Vec::new();
+    put_str(&mut body, "MQTT");
+    body.push(4); // protocol level 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&60u16.to_be_bytes()); // keep-alive seconds
+    put_str(&mut body, &cfg.client_id);
+    for s in [&cfg.username, &cfg.password].into_iter().flatten() {
+        put_str(&mut body, s);
+    }
+    packet(0x10, &body)
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![header];
+    // Remaining length: base-128 varint.
+    let mut len = body.len();
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+    out.extend_from_slice(body);
+    out
+}
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    let len = u16::try_from(s.len()).unwrap_or(u16::MAX);
+    buf.extend_from_slice(&len.to_be_bytes());
+    buf.extend_from_slice(&s.as_bytes()[..usize::from(len)]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::{DueAt, Title};
+    use std::net::TcpListener;
+    use time::Duration;
+
+    fn due_in(title: &str, offset: Duration) -> Todo {
+        let mut t = Todo::new(Title::parse(title).unwrap());
+        t.due = Some(DueAt::from_dt(OffsetDateTime::now_utc() + offset));
+        t
+    }
+
+    #[test]
+    fn overdue_transitions_fire_once() {
+        let now = OffsetDateTime::now_utc();
+        let late = due_in("Late", -Duration::hours(1));
+        let later = due_in("Later", Duration::hours(1));
+        let todos = vec![late.clone(), later];
+
+        let (events, overdue) = overdue_events(&todos, &BTreeSet::new(), now);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EventKind::Overdue);
+
+        let (again, _) = overdue_events(&todos, &overdue, now);
+        assert!(again.is_empty());
+
+        let mut done = late;
+        done.mark_done().unwrap();
+        let (cleared, remaining) = overdue_events(&[done], &overdue, now);
+        assert_eq!(cleared[0].kind, EventKind::OverdueCleared);
+        assert!(remaining.is_empty());
+    }
+
+    #[test]
+    fn publishes_to_a_broker() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let cfg = MqttConfig {
+            enabled: true,
+            broker: listener.local_addr().unwrap().to_string(),
+            ..MqttConfig::default()
+        };
+
+        let broker = std::thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let mut connect = [0u8; 2];
+            s.read_exact(&mut connect).unwrap();
+            let mut rest = vec![0u8; usize::from(connect[1])];
+            s.read_exact(&mut rest).unwrap();
+            s.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap();
+            let mut received = Vec::new();
+            s.read_to_end(&mut received).unwrap();
+            (connect[0], received)
+        });
+
+        let mut client = Client::connect(&cfg).unwrap();
+        client.publish("rustlytodo/test", "hi", true).unwrap();
+        client.disconnect().unwrap();
+
+        let (connect_type, received) = broker.join().unwrap();
+        assert_eq!(connect_type, 0x10);
+        let mut expected = packet(0x31, b"\x00\x0frustlytodo/testhi");
+        expected.extend_from_slice(&[0xE0, 0x00]);
+        assert_eq!(received, expected);
+    }
+}
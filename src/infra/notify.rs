@@ -0,0 +1,69 @@
+//! Desktop notifications.
+//!
+//! Rather than linking a notification library (D-Bus, WinRT, Cocoa), this
+//! hands the message to the tool every desktop already has: `notify-send` on
+//! Linux and the BSDs, `osascript` on macOS and PowerShell on Windows. A
+//! missing tool is an error for the caller to report, not a panic.
+
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+
+/// Show a desktop notification with `summary` as its title.
+pub fn send(summary: &str, body: &str) -> Result<()> {
+    let mut cmd = command(summary, body);
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let status = cmd
+        .status()
+        .with_context(|| format!("failed running {program} (is it installed?)"))?;
+    if !status.success() {
+        bail!("{program} exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn command(summary: &str, body: &str) -> Command {
+    let mut cmd = Command::new("osascript");
+    cmd.arg("-e").arg(format!(
+        "display notification {} with title {}",
+        applescript_string(body),
+        applescript_string(summary)
+    ));
+    cmd
+}
+
+#[cfg(windows)]
+fn command(summary: &str, body: &str) -> Command {
+    // A tray balloon works on every Windows version without extra modules.
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $n = New-Object System.Windows.Forms.NotifyIcon; \
+         $n.Icon = [System.Drawing.SystemIcons]::Information; \
+         $n.Visible = $true; \
+         $n.ShowBalloonTip(10000, {}, {}, 'Info'); \
+         Start-Sleep -Seconds 5; $n.Dispose()",
+        powershell_string(summary),
+        powershell_string(body)
+    );
+    let mut cmd = Command::new("powershell");
+    cmd.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    cmd
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn command(summary: &str, body: &str) -> Command {
+    let mut cmd = Command::new("notify-send");
+    cmd.args(["--app-name", "rustlytodo", summary, body]);
+    cmd
+}
+
+#[cfg(target_os = "macos")]
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(windows)]
+fn powershell_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
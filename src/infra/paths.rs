@@ -1,8 +1,18 @@
 //! Platform-correct config and data paths.
+//!
+//! On Windows, network shares (`\\server\share\...`) and paths long enough to
+//! hit `MAX_PATH` once file names are appended are turned into verbatim
+//! `\\?\` paths, which the file APIs accept at any length.
+
+use std::path::PathBuf;
 
 use anyhow::{Result, anyhow};
 use directories::ProjectDirs;
 
+/// Drive paths at least this long get the `\\?\` prefix: the db file, its
+/// `.tmp`/`.journal.jsonl` siblings and shard files must still fit in 260.
+const LONG_PATH: usize = 200;
+
 #[derive(Debug, Clone)]
 pub struct AppPaths {
     pub config_dir: std::path::PathBuf,
@@ -17,8 +27,57 @@ impl AppPaths {
             .ok_or_else(|| anyhow!("could not determine project directories"))?;
 
         Ok(Self {
-            config_dir: proj.config_dir().to_path_buf(),
-            data_dir: proj.data_dir().to_path_buf(),
+            config_dir: long_path(proj.config_dir().to_path_buf()),
+            data_dir: long_path(proj.data_dir().to_path_buf()),
         })
     }
 }
+
+/// `path` in a form Windows opens regardless of length (unchanged elsewhere).
+pub fn long_path(path: PathBuf) -> PathBuf {
+    if !cfg!(windows) {
+        return path;
+    }
+    match path.to_str().and_then(verbatim) {
+        Some(v) => PathBuf::from(v),
+        None => path,
+    }
+}
+
+/// The `\\?\` form of an absolute Windows path that needs it: UNC paths always,
+/// drive paths from [`LONG_PATH`] characters. `None` to leave it as is.
+pub fn verbatim(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    // Verbatim paths skip normalization, so separators must be backslashes.
+    let path = path.replace('/', "\\");
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{share}"));
+    }
+    let bytes = path.as_bytes();
+    let drive =
+        bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\';
+    if drive && path.chars().count() >= LONG_PATH {
+        return Some(format!(r"\\?\{path}"));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbatim_prefixes_shares_and_long_drive_paths() {
+        assert_eq!(
+            verbatim(r"\\nas\home/todo").as_deref(),
+            Some(r"\\?\UNC\nas\home\todo")
+        );
+        assert_eq!(verbatim(r"C:\Users\me\AppData"), None);
+        let long = format!(r"C:\{}", "d".repeat(LONG_PATH));
+        assert_eq!(verbatim(&long), Some(format!(r"\\?\{long}")));
+        assert_eq!(verbatim(r"\\?\C:\already"), None);
+        assert_eq!(verbatim(&"relative\\".repeat(40)), None);
+    }
+}
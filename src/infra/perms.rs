@@ -0,0 +1,212 @@
+//! Owner-only permissions for the files rustlytodo writes.
+//!
+//! Todos hold whatever people type into them, so the database and everything
+//! derived from it (journal, undo history, config, logs, crash reports, sync
+//! state) is created for the current user only: mode 0600, directories 0700,
+//! on Unix. Windows has no mode bits; there, directories we create get their
+//! inherited ACL replaced by a single grant to the current user, which the
+//! files inside inherit.
+//!
+//! Only creation is restricted: files written in place (config, journal, logs)
+//! keep a mode the user has since chosen, while the database and undo history
+//! are replaced on every save and so come back private. Paths the user names
+//! (exports) follow their umask. `doctor` reports data files that others can
+//! read ([`exposed`]).
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Create (or truncate) `path` for writing, owner-only if it is new.
+pub fn create(path: &Path) -> io::Result<File> {
+    open(
+        path,
+        OpenOptions::new().write(true).create(true).truncate(true),
+    )
+}
+
+/// Open `path` for appending, creating it owner-only if needed.
+pub fn append(path: &Path) -> io::Result<File> {
+    open(path, OpenOptions::new().create(true).append(true))
+}
+
+/// [`std::fs::write`], but a new file is owner-only.
+pub fn write(path: &Path, bytes: impl AsRef<[u8]>) -> io::Result<()> {
+    create(path)?.write_all(bytes.as_ref())
+}
+
+fn open(path: &Path, opts: &mut OpenOptions) -> io::Result<File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    opts.open(path)
+}
+
+/// [`std::fs::create_dir_all`], restricting the directories it creates.
+/// Existing ones (a home directory, a synced folder) are left alone.
+pub fn create_dir(path: &Path) -> io::Result<()> {
+    if path.as_os_str().is_empty() || path.is_dir() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        create_dir(parent)?;
+    }
+    match std::fs::create_dir(path) {
+        Ok(()) => restrict_dir(path),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists && path.is_dir() => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn restrict_dir(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700))
+}
+
+#[cfg(windows)]
+fn restrict_dir(path: &Path) -> io::Result<()> {
+    // Best effort: without a user name there is nobody to grant to, and the
+    // parent (usually the profile) is private already.
+    let Ok(user) = std::env::var("USERNAME") else {
+        return Ok(());
+    };
+    let status = std::process::Command::new("icacls")
+        .arg(path)
+        .args([
+            "/inheritance:r",
+            "/grant:r",
+            &format!("{user}:(OI)(CI)F"),
+            "/Q",
+        ])
+        .stdout(std::process::Stdio::null())
+        .status();
+    if !status.is_ok_and(|s| s.success()) {
+        tracing::debug!(path = %path.display(), "could not restrict directory ACL");
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn restrict_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// A file other users can read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exposed {
+    pub path: PathBuf,
+    /// Who else can read it: `mode 644` on Unix, the ACL entry on Windows.
+    pub detail: String,
+}
+
+/// Files under `roots` (recursively; a root may be a single file) that other
+/// users can read. Unreadable entries are skipped.
+pub fn exposed(roots: &[PathBuf]) -> Vec<Exposed> {
+    let mut found = Vec::new();
+    let mut stack: Vec<PathBuf> = roots.to_vec();
+    while let Some(path) = stack.pop() {
+        let Ok(meta) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if meta.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(&path) {
+                stack.extend(entries.flatten().map(|e| e.path()));
+            }
+        } else if meta.is_file()
+            && let Some(detail) = readable_by_others(&path, &meta)
+        {
+            found.push(Exposed { path, detail });
+        }
+    }
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found.dedup();
+    found
+}
+
+#[cfg(unix)]
+fn readable_by_others(_path: &Path, meta: &std::fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = meta.permissions().mode() & 0o777;
+    (mode & 0o004 != 0).then(|| format!("mode {mode:o}"))
+}
+
+#[cfg(windows)]
+fn readable_by_others(path: &Path, _meta: &std::fs::Metadata) -> Option<String> {
+    let output = std::process::Command::new("icacls")
+        .arg(path)
+        .output()
+        .ok()?;
+    broad_grant(&String::from_utf8_lossy(&output.stdout)).map(str::to_string)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn readable_by_others(_path: &Path, _meta: &std::fs::Metadata) -> Option<String> {
+    None
+}
+
+/// The first `icacls` entry granting a group that means "everyone on this
+/// machine" (English names only; other locales go unreported).
+#[cfg(any(windows, test))]
+fn broad_grant(icacls: &str) -> Option<&str> {
+    const BROAD: [&str; 3] = [
+        "Everyone:",
+        "BUILTIN\\Users:",
+        "NT AUTHORITY\\Authenticated Users:",
+    ];
+    icacls
+        .lines()
+        .filter_map(|line| BROAD.iter().find_map(|p| line.find(p).map(|i| &line[i..])))
+        .map(str::trim)
+        .find(|entry| !entry.contains("(DENY)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn new_files_and_dirs_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+
+        let nested = dir.path().join("a/b");
+        create_dir(&nested).unwrap();
+        assert_eq!(mode(&dir.path().join("a")), 0o700);
+        assert_eq!(mode(&nested), 0o700);
+
+        let db = nested.join("db.json");
+        write(&db, "[]").unwrap();
+        assert_eq!(mode(&db), 0o600);
+        assert!(exposed(&[dir.path().to_path_buf()]).is_empty());
+
+        let shared = dir.path().join("shared.txt");
+        std::fs::write(&shared, "x").unwrap();
+        std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(0o644)).unwrap();
+        // Rewriting keeps a mode the user chose.
+        write(&shared, "y").unwrap();
+        assert_eq!(
+            exposed(&[dir.path().to_path_buf()]),
+            [Exposed {
+                path: shared,
+                detail: "mode 644".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn broad_windows_grants_are_recognised() {
+        let out = "C:\\data\\db.json NT AUTHORITY\\SYSTEM:(I)(F)\n\
+                   \x20                BUILTIN\\Users:(I)(RX)\n\
+                   \x20                HOST\\me:(I)(F)\n";
+        assert_eq!(broad_grant(out), Some("BUILTIN\\Users:(I)(RX)"));
+        assert_eq!(broad_grant("C:\\x HOST\\me:(F)\n"), None);
+        assert_eq!(broad_grant("C:\\x Everyone:(DENY)(R)\n"), None);
+    }
+}
@@ -0,0 +1,223 @@
+//! Leftovers of runs that died mid-save, dealt with at startup.
+//!
+//! Saves write `<file>.tmp` and rename it over the file (see
+//! [`replace_file`](crate::infra::fs_repo::replace_file)), so a crash in
+//! between leaves the temp file behind; a `git commit` killed halfway (git
+//! autosave) leaves `.git/index.lock`, and every later commit fails on it.
+//!
+//! Leftovers younger than [`GRACE`] may belong to a run still in progress and
+//! are left alone. Older ones are handled as follows:
+//! - a temp file that doesn't load was cut short, and is deleted;
+//! - one that loads is moved into place if its file is missing, doesn't
+//!   load, or is older (the save was complete but never renamed), and is
+//!   deleted otherwise;
+//! - a git index lock is removed.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+
+use crate::infra::{
+    db_crypto::{self, DbKey},
+    db_schema, fs_repo,
+    git_sync::GitSync,
+};
+
+/// How old a leftover must be before it is taken for a crashed run's.
+pub const GRACE: Duration = Duration::from_secs(60);
+
+/// One leftover dealt with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cleanup {
+    /// A complete temp file was moved over the file it was written for.
+    Restored { from: PathBuf, to: PathBuf },
+    /// A temp file was deleted.
+    Discarded { path: PathBuf, reason: &'static str },
+    /// A stale lock was removed.
+    Unlocked { path: PathBuf },
+}
+
+impl fmt::Display for Cleanup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Restored { from, to } => write!(
+                f,
+                "finished an interrupted save: {} -> {}",
+                from.display(),
+                to.display()
+            ),
+            Self::Discarded { path, reason } => {
+                write!(f, "removed {} ({reason})", path.display())
+            }
+            Self::Unlocked { path } => write!(f, "removed stale lock {}", path.display()),
+        }
+    }
+}
+
+/// Deal with the leftovers around the database at `db_path` (and its
+/// shards). `key` opens an encrypted database; temp files it can't open are
+/// left alone.
+pub fn recover(db_path: &Path, key: Option<&DbKey>, now: SystemTime) -> Result<Vec<Cleanup>> {
+    let mut done = Vec::new();
+
+    let tmp = fs_repo::tmp_path_for(db_path);
+    if fs_repo::shard_dir(db_path).is_dir() {
+        // A switch to one file that didn't finish; the shards are intact.
+        if is_stale(&tmp, now) {
+            done.push(discard(&tmp, "the database is sharded")?);
+        }
+    } else if let Some(cleanup) = settle(&tmp, db_path, key, now)? {
+        done.push(cleanup);
+    }
+
+    let shards = fs_repo::shard_dir(db_path);
+    if let Ok(entries) = std::fs::read_dir(&shards) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(shard) = name.strip_suffix(".tmp").filter(|s| s.ends_with(".json"))
+                && let Some(cleanup) = settle(&entry.path(), &shards.join(shard), key, now)?
+            {
+                done.push(cleanup);
+            }
+        }
+    }
+
+    if let Some(git) = GitSync::open(db_path) {
+        let lock = git.index_lock();
+        if is_stale(&lock, now) {
+            std::fs::remove_file(&lock)
+                .with_context(|| format!("failed removing {}", lock.display()))?;
+            done.push(Cleanup::Unlocked { path: lock });
+        }
+    }
+    Ok(done)
+}
+
+/// Restore or delete the temp file `tmp` written for `file`.
+fn settle(
+    tmp: &Path,
+    file: &Path,
+    key: Option<&DbKey>,
+    now: SystemTime,
+) -> Result<Option<Cleanup>> {
+    if !is_stale(tmp, now) {
+        return Ok(None);
+    }
+    let cleanup = match loads(tmp, key) {
+        None => return Ok(None),
+        Some(false) => discard(tmp, "incomplete write")?,
+        Some(true) if loads(file, key) == Some(true) && modified(file) >= modified(tmp) => {
+            discard(tmp, "older than the file it was written for")?
+        }
+        Some(true) => {
+            std::fs::rename(tmp, file).with_context(|| {
+                format!("failed renaming {} -> {}", tmp.display(), file.display())
+            })?;
+            Cleanup::Restored {
+                from: tmp.to_path_buf(),
+                to: file.to_path_buf(),
+            }
+        }
+    };
+    Ok(Some(cleanup))
+}
+
+fn discard(path: &Path, reason: &'static str) -> Result<Cleanup> {
+    std::fs::remove_file(path).with_context(|| format!("failed removing {}", path.display()))?;
+    Ok(Cleanup::Discarded {
+        path: path.to_path_buf(),
+        reason,
+    })
+}
+
+/// Whether `path` holds a whole database; `None` if it is encrypted and
+/// there is no key to tell.
+fn loads(path: &Path, key: Option<&DbKey>) -> Option<bool> {
+    let Ok(bytes) = std::fs::read(path) else {
+        return Some(false);
+    };
+    let json = if db_crypto::file_is_encrypted(path) {
+        match key?.open(&bytes) {
+            Ok(json) => json,
+            Err(_) => return Some(false),
+        }
+    } else {
+        bytes
+    };
+    Some(String::from_utf8(json).is_ok_and(|text| db_schema::load_db_versioned(&text).is_ok()))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn is_stale(path: &Path, now: SystemTime) -> bool {
+    modified(path).is_some_and(|at| now.duration_since(at).is_ok_and(|age| age >= GRACE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app::repository::TodoRepository,
+        domain::todo::{Title, Todo},
+        infra::fs_repo::JsonFileTodoRepository,
+    };
+
+    #[test]
+    fn leftover_temp_files_are_restored_or_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("db.json");
+        let tmp = dir.path().join("db.json.tmp");
+        let later = SystemTime::now() + GRACE * 2;
+        let mut repo = JsonFileTodoRepository::load_or_init(db.clone()).unwrap();
+        let saved = std::fs::read(&db).unwrap();
+
+        // A save cut short.
+        std::fs::write(&tmp, &saved[..saved.len() / 2]).unwrap();
+        assert!(
+            recover(&db, None, SystemTime::now()).unwrap().is_empty(),
+            "too fresh to judge"
+        );
+        assert_eq!(
+            recover(&db, None, later).unwrap(),
+            [Cleanup::Discarded {
+                path: tmp.clone(),
+                reason: "incomplete write"
+            }]
+        );
+        assert!(!tmp.exists());
+
+        // A complete save that never got renamed into place.
+        repo.add(Todo::new(Title::parse("Survive the crash").unwrap()))
+            .unwrap();
+        repo.save_atomic().unwrap();
+        std::fs::rename(&db, &tmp).unwrap();
+        std::fs::write(&db, b"{\"schema_version\": 3, \"tod").unwrap();
+        assert_eq!(
+            recover(&db, None, later).unwrap(),
+            [Cleanup::Restored {
+                from: tmp.clone(),
+                to: db.clone()
+            }]
+        );
+        let repo = JsonFileTodoRepository::load_or_init(db.clone()).unwrap();
+        assert_eq!(repo.list()[0].title.as_str(), "Survive the crash");
+
+        // An old copy next to a newer, good database.
+        std::fs::write(&tmp, &saved).unwrap();
+        let file = std::fs::File::options().append(true).open(&db).unwrap();
+        file.set_modified(later).unwrap();
+        let cleaned = recover(&db, None, later + GRACE).unwrap();
+        assert!(
+            matches!(&cleaned[..], [Cleanup::Discarded { .. }]),
+            "{cleaned:?}"
+        );
+        let repo = JsonFileTodoRepository::load_or_init(db).unwrap();
+        assert_eq!(repo.list().len(), 1);
+    }
+}
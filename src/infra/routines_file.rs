@@ -0,0 +1,58 @@
+//! Routines on disk, in `<db>.routines.json` next to the database.
+//!
+//! Unlike the undo history this is user data, so a file that can't be read is
+//! an error rather than an empty list.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::{domain::routine::Routine, infra::fs_repo::replace_file};
+
+pub fn routines_path(db_path: &Path) -> PathBuf {
+    db_path.with_extension("routines.json")
+}
+
+pub fn load(path: &Path) -> Result<Vec<Routine>> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("failed reading routines: {}", path.display()));
+        }
+    };
+    serde_json::from_slice(&bytes)
+        .with_context(|| format!("failed parsing routines: {}", path.display()))
+}
+
+pub fn save(path: &Path, routines: &[Routine]) -> Result<()> {
+    let bytes = serde_json::to_vec_pretty(routines).context("failed serializing routines")?;
+    replace_file(path, &bytes)
+        .with_context(|| format!("failed writing routines: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn round_trips_and_tolerates_a_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = routines_path(&dir.path().join("db.json"));
+        assert!(load(&path).unwrap().is_empty());
+
+        let mut r = Routine::new("Morning", vec!["Stretch".into()]).unwrap();
+        r.check(date!(2026 - 03 - 10), "1", true).unwrap();
+        save(&path, std::slice::from_ref(&r)).unwrap();
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("\"2026-03-10\"")
+        );
+        assert_eq!(load(&path).unwrap(), [r]);
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(load(&path).is_err());
+    }
+}
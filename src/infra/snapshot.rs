@@ -0,0 +1,241 @@
+//! Snapshots: timestamped JSON exports for backups (`[snapshot]` in
+//! config.toml, and the `snapshot` command).
+//!
+//! With `every` set, the first command run after the last snapshot got that
+//! old writes a new one, so backups happen without a cron job. Each snapshot
+//! is a full export (the same JSON as `export`) named
+//! `rustytodo-20260310T120000Z.json`; the newest `keep` are kept. The
+//! `upload_command` then gets to copy it off the machine.
+//!
+//! Snapshots of an encrypted database are sealed with the same key.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use time::{OffsetDateTime, PrimitiveDateTime, macros::format_description};
+
+use crate::{
+    domain::todo::Todo,
+    infra::{config::SnapshotConfig, db_crypto::DbKey, db_schema, paths::AppPaths, perms},
+};
+
+const PREFIX: &str = "rustytodo-";
+const SUFFIX: &str = ".json";
+
+/// Environment variable holding the snapshot path for `upload_command`.
+pub const PATH_ENV: &str = "RUSTYTODO_SNAPSHOT";
+
+/// A snapshot just written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    /// Older snapshots removed to stay within `keep`.
+    pub pruned: usize,
+    /// Whether `upload_command` ran (successfully).
+    pub uploaded: bool,
+}
+
+pub fn folder(config: &SnapshotConfig, paths: &AppPaths) -> PathBuf {
+    config
+        .folder
+        .clone()
+        .unwrap_or_else(|| paths.data_dir.join("snapshots"))
+}
+
+/// Snapshot files in `folder`, oldest first (their names sort by time).
+pub fn list(folder: &Path) -> Vec<(PathBuf, OffsetDateTime)> {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut found: Vec<_> = entries
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().into_string().ok()?;
+            let at = parse_stamp(name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?)?;
+            Some((e.path(), at))
+        })
+        .collect();
+    found.sort_by_key(|(_, at)| *at);
+    found
+}
+
+/// Is a scheduled snapshot due at `now`? Never without `every`; an `every`
+/// that doesn't parse is an error.
+pub fn is_due(config: &SnapshotConfig, folder: &Path, now: OffsetDateTime) -> Result<bool> {
+    let Some(every) = &config.every else {
+        return Ok(false);
+    };
+    let Some(cutoff) = crate::app::stats::parse_since(every, now).filter(|c| *c < now) else {
+        bail!("invalid [snapshot] every = {every:?} (use e.g. 7d, 12h)");
+    };
+    Ok(list(folder).last().is_none_or(|(_, at)| *at <= cutoff))
+}
+
+/// Write a snapshot of `todos` into `folder`, prune old ones and run the
+/// upload command. The snapshot stays even if the upload fails.
+pub fn take(
+    config: &SnapshotConfig,
+    folder: &Path,
+    todos: &[Todo],
+    key: Option<&DbKey>,
+    now: OffsetDateTime,
+) -> Result<Snapshot> {
+    perms::create_dir(folder)
+        .with_context(|| format!("failed creating snapshot folder: {}", folder.display()))?;
+    let json = db_schema::write_current(todos)?;
+    let bytes = match key {
+        Some(key) => key.seal(json.as_bytes())?,
+        None => json.into_bytes(),
+    };
+    let path = folder.join(format!("{PREFIX}{}{SUFFIX}", stamp(now)));
+    perms::write(&path, bytes)
+        .with_context(|| format!("failed writing snapshot: {}", path.display()))?;
+
+    let mut pruned = 0;
+    let all = list(folder);
+    if config.keep > 0 && all.len() > config.keep {
+        for (old, _) in &all[..all.len() - config.keep] {
+            std::fs::remove_file(old)
+                .with_context(|| format!("failed removing old snapshot: {}", old.display()))?;
+            pruned += 1;
+        }
+    }
+
+    let mut snapshot = Snapshot {
+        path,
+        pruned,
+        uploaded: false,
+    };
+    if let Some(command) = config
+        .upload_command
+        .as_deref()
+        .filter(|c| !c.trim().is_empty())
+    {
+        upload(command, &snapshot.path)?;
+        snapshot.uploaded = true;
+    }
+    Ok(snapshot)
+}
+
+fn upload(command: &str, path: &Path) -> Result<()> {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(command);
+        c
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    };
+    let status = cmd
+        .env(PATH_ENV, path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .context("failed running [snapshot] upload_command")?;
+    if !status.success() {
+        bail!("[snapshot] upload_command failed ({status})");
+    }
+    Ok(())
+}
+
+/// `at` in UTC as it appears in file names: `20260310T120000Z`.
+pub fn stamp(at: OffsetDateTime) -> String {
+    let at = at.to_offset(time::UtcOffset::UTC);
+    at.format(format_description!(
+        "[year][month][day]T[hour][minute][second]Z"
+    ))
+    .expect("timestamp formats")
+}
+
+/// The time in a [`stamp`].
+pub fn parse_stamp(s: &str) -> Option<OffsetDateTime> {
+    PrimitiveDateTime::parse(
+        s,
+        format_description!("[year][month][day]T[hour][minute][second]Z"),
+    )
+    .ok()
+    .map(PrimitiveDateTime::assume_utc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::todo::Title;
+    use time::{Duration, macros::datetime};
+
+    #[test]
+    fn snapshots_are_scheduled_and_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("snaps");
+        let config = SnapshotConfig {
+            every: Some("7d".into()),
+            keep: 2,
+            ..SnapshotConfig::default()
+        };
+        let todos = vec![Todo::new(Title::parse("Back me up").unwrap())];
+        let start = datetime!(2026-03-01 09:00 UTC);
+
+        assert!(is_due(&config, &folder, start).unwrap());
+        let first = take(&config, &folder, &todos, None, start).unwrap();
+        assert!(first.path.ends_with("rustytodo-20260301T090000Z.json"));
+        let json = std::fs::read_to_string(&first.path).unwrap();
+        assert!(json.contains("Back me up"));
+
+        assert!(!is_due(&config, &folder, start + Duration::days(6)).unwrap());
+        assert!(is_due(&config, &folder, start + Duration::days(7)).unwrap());
+        for week in 1..=2 {
+            take(
+                &config,
+                &folder,
+                &todos,
+                None,
+                start + Duration::weeks(week),
+            )
+            .unwrap();
+        }
+        let kept: Vec<_> = list(&folder).into_iter().map(|(_, at)| at).collect();
+        assert_eq!(
+            kept,
+            [start + Duration::weeks(1), start + Duration::weeks(2)]
+        );
+
+        assert!(!is_due(&SnapshotConfig::default(), &folder, start).unwrap());
+        let bad = SnapshotConfig {
+            every: Some("weekly".into()),
+            ..SnapshotConfig::default()
+        };
+        assert!(is_due(&bad, &folder, start).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn upload_command_gets_the_snapshot_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let copy = dir.path().join("offsite.json");
+        let config = SnapshotConfig {
+            upload_command: Some(format!("cp \"$RUSTYTODO_SNAPSHOT\" '{}'", copy.display())),
+            ..SnapshotConfig::default()
+        };
+        let now = OffsetDateTime::now_utc();
+        let snapshot = take(&config, &dir.path().join("snaps"), &[], None, now).unwrap();
+        assert!(snapshot.uploaded);
+        assert_eq!(
+            std::fs::read(&copy).unwrap(),
+            std::fs::read(&snapshot.path).unwrap()
+        );
+
+        let failing = SnapshotConfig {
+            upload_command: Some("exit 1".into()),
+            ..SnapshotConfig::default()
+        };
+        let later = now + time::Duration::seconds(1);
+        assert!(take(&failing, &dir.path().join("snaps"), &[], None, later).is_err());
+    }
+}
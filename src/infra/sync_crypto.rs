@@ -0,0 +1,403 @@
+//! End-to-end encryption for sync delta files.
+//!
+//! Each device holds an X25519 key pair in `sync_keys.json` (data dir) and a list
+//! of peers it trusts. Peers learn each other's public keys from *invites*: small
+//! files sealed with a shared passphrase, so whatever carries the files (a shared
+//! folder, a cloud drive, a mail server) can neither read them nor swap in a key
+//! of its own.
+//!
+//! A sealed delta is encrypted once with a fresh random content key; that key is
+//! then wrapped for every recipient with a key derived from
+//! X25519(sender, recipient). Only the listed recipients can unwrap it, and a
+//! successful unwrap also proves which trusted peer sent it.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use argon2::Argon2;
+use base64::{Engine, engine::general_purpose::STANDARD as B64};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::{
+    app::sync::SyncDelta,
+    infra::{paths::AppPaths, perms},
+};
+
+const INVITE_FORMAT: &str = "rustytodo-invite-v1";
+const SEALED_FORMAT: &str = "rustytodo-sealed-v1";
+
+pub fn keys_path(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join("sync_keys.json")
+}
+
+/// This device's key pair and the peers it accepts deltas from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncKeys {
+    pub device_id: String,
+    secret_key: String,
+    pub public_key: String,
+    /// Peer device id -> public key (base64).
+    #[serde(default)]
+    pub peers: BTreeMap<String, String>,
+}
+
+/// A device's public key, sealed with a passphrase both sides know.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invite {
+    format: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct InviteBody {
+    device_id: String,
+    public_key: String,
+}
+
+/// An encrypted [`SyncDelta`] addressed to one or more peers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedDelta {
+    format: String,
+    pub sender: String,
+    recipients: Vec<WrappedKey>,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// The content key, encrypted for one recipient.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WrappedKey {
+    device: String,
+    nonce: String,
+    key: String,
+}
+
+impl SyncKeys {
+    /// Fresh key pair for `device_id`, trusting nobody yet.
+    pub fn generate(device_id: impl Into<String>) -> Result<Self> {
+        let secret = StaticSecret::from(random::<32>()?);
+        let public = PublicKey::from(&secret);
+        Ok(Self {
+            device_id: device_id.into(),
+            secret_key: B64.encode(secret.to_bytes()),
+            public_key: B64.encode(public.as_bytes()),
+            peers: BTreeMap::new(),
+        })
+    }
+
+    /// Short, human-comparable digest of this device's public key.
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.public_key)
+    }
+
+    /// Write an invite carrying this device's id and public key.
+    pub fn invite(&self, passphrase: &str) -> Result<Invite> {
+        let salt = random::<16>()?;
+        let key = passphrase_key(passphrase, &salt)?;
+        let body = serde_json::to_vec(&InviteBody {
+            device_id: self.device_id.clone(),
+            public_key: self.public_key.clone(),
+        })
+        .context("failed serializing invite")?;
+        let (nonce, ciphertext) = encrypt(&key, INVITE_FORMAT.as_bytes(), &body)?;
+
+        Ok(Invite {
+            format: INVITE_FORMAT.to_string(),
+            salt: B64.encode(salt),
+            nonce: B64.encode(nonce),
+            ciphertext: B64.encode(ciphertext),
+        })
+    }
+
+    /// Open an invite and trust the device it names. Returns that device's id.
+    pub fn accept_invite(&mut self, invite: &Invite, passphrase: &str) -> Result<String> {
+        if invite.format != INVITE_FORMAT {
+            bail!("unsupported invite format: {}", invite.format);
+        }
+        let key = passphrase_key(passphrase, &decode(&invite.salt, "invite salt")?)?;
+        let body = decrypt(
+            &key,
+            &decode(&invite.nonce, "invite nonce")?,
+            INVITE_FORMAT.as_bytes(),
+            &decode(&invite.ciphertext, "invite")?,
+        )
+        .ok_or_else(|| anyhow!("wrong passphrase or corrupted invite"))?;
+        let body: InviteBody = serde_json::from_slice(&body).context("invalid invite contents")?;
+
+        if body.device_id == self.device_id {
+            bail!("this invite was written by this device");
+        }
+        decode_key(&body.public_key, "peer public key")?;
+        self.peers.insert(body.device_id.clone(), body.public_key);
+        Ok(body.device_id)
+    }
+
+    /// Encrypt `delta` so only `recipients` (trusted peers) can read it.
+    pub fn seal(&self, delta: &SyncDelta, recipients: &[String]) -> Result<SealedDelta> {
+        if recipients.is_empty() {
+            bail!("no trusted peers to encrypt for (add one with `sync keys add`)");
+        }
+
+        let secret = self.secret()?;
+        let content_key = random::<32>()?;
+        let mut wrapped = Vec::new();
+        for device in recipients {
+            let public = self.peer_key(device)?;
+            let wrap = wrap_key(&secret, &public, &self.device_id, device);
+            let (nonce, key) = encrypt(&wrap, device.as_bytes(), &content_key)?;
+            wrapped.push(WrappedKey {
+                device: device.clone(),
+                nonce: B64.encode(nonce),
+                key: B64.encode(key),
+            });
+        }
+
+        let plaintext = serde_json::to_vec(delta).context("failed serializing sync delta")?;
+        let aad = header_aad(&self.device_id, &wrapped);
+        let (nonce, ciphertext) = encrypt(&content_key, &aad, &plaintext)?;
+
+        Ok(SealedDelta {
+            format: SEALED_FORMAT.to_string(),
+            sender: self.device_id.clone(),
+            recipients: wrapped,
+            nonce: B64.encode(nonce),
+            ciphertext: B64.encode(ciphertext),
+        })
+    }
+
+    /// Decrypt a delta sealed by a trusted peer for this device.
+    pub fn open(&self, sealed: &SealedDelta) -> Result<SyncDelta> {
+        if sealed.format != SEALED_FORMAT {
+            bail!("unsupported sealed delta format: {}", sealed.format);
+        }
+        if !self.peers.contains_key(&sealed.sender) {
+            bail!(
+                "delta is from untrusted device {} (add it with `sync keys add`)",
+                sealed.sender
+            );
+        }
+        let mine = sealed
+            .recipients
+            .iter()
+            .find(|w| w.device == self.device_id)
+            .ok_or_else(|| anyhow!("delta was not encrypted for this device"))?;
+
+        let wrap = wrap_key(
+            &self.secret()?,
+            &self.peer_key(&sealed.sender)?,
+            &sealed.sender,
+            &self.device_id,
+        );
+        let content_key = decrypt(
+            &wrap,
+            &decode(&mine.nonce, "wrapped key nonce")?,
+            self.device_id.as_bytes(),
+            &decode(&mine.key, "wrapped key")?,
+        )
+        .ok_or_else(|| anyhow!("delta key does not match the sender's known key"))?;
+        let content_key: [u8; 32] = content_key
+            .try_into()
+            .map_err(|_| anyhow!("invalid content key length"))?;
+
+        let plaintext = decrypt(
+            &content_key,
+            &decode(&sealed.nonce, "delta nonce")?,
+            &header_aad(&sealed.sender, &sealed.recipients),
+            &decode(&sealed.ciphertext, "delta")?,
+        )
+        .ok_or_else(|| anyhow!("sealed delta was tampered with"))?;
+
+        let delta: SyncDelta =
+            serde_json::from_slice(&plaintext).context("failed parsing decrypted sync delta")?;
+        if delta.from != sealed.sender {
+            bail!("sealed delta sender does not match its contents");
+        }
+        Ok(delta)
+    }
+
+    fn secret(&self) -> Result<StaticSecret> {
+        Ok(StaticSecret::from(decode_key(
+            &self.secret_key,
+            "secret key",
+        )?))
+    }
+
+    fn peer_key(&self, device: &str) -> Result<PublicKey> {
+        let encoded = self
+            .peers
+            .get(device)
+            .ok_or_else(|| anyhow!("device {device} is not a trusted peer"))?;
+        Ok(PublicKey::from(decode_key(encoded, "peer public key")?))
+    }
+}
+
+/// Load this device's keys; `None` means encrypted sync is not set up.
+pub fn load(path: &Path) -> Result<Option<SyncKeys>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed reading sync keys: {}", path.display()))?;
+    serde_json::from_str(&text)
+        .map(Some)
+        .with_context(|| format!("failed parsing sync keys: {}", path.display()))
+}
+
+/// Save keys readable by the current user only (on Unix).
+pub fn save(path: &Path, keys: &SyncKeys) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        perms::create_dir(parent)
+            .with_context(|| format!("failed creating directory: {}", parent.display()))?;
+    }
+    let json = serde_json::to_string_pretty(keys).context("failed serializing sync keys")?;
+    perms::write(path, json)
+        .with_context(|| format!("failed writing sync keys: {}", path.display()))
+}
+
+pub fn fingerprint(public_key: &str) -> String {
+    let digest = Sha256::digest(public_key.as_bytes());
+    digest[..8]
+        .chunks(2)
+        .map(|pair| format!("{:02x}{:02x}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn random<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow!("os random source failed: {e}"))?;
+    Ok(bytes)
+}
+
+fn passphrase_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    if passphrase.is_empty() {
+        bail!("sync passphrase cannot be empty");
+    }
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("failed deriving key from passphrase: {e}"))?;
+    Ok(key)
+}
+
+fn wrap_key(secret: &StaticSecret, peer: &PublicKey, sender: &str, recipient: &str) -> [u8; 32] {
+    let shared = secret.diffie_hellman(peer);
+    let info = format!("{SEALED_FORMAT}|{sender}|{recipient}");
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, shared.as_bytes())
+        .expand(info.as_bytes(), &mut key)
+        .expect("32 bytes is a valid HKDF output length");
+    key
+}
+
+/// Binds the sender and recipient list to the ciphertext.
+fn header_aad(sender: &str, recipients: &[WrappedKey]) -> Vec<u8> {
+    let mut aad = format!("{SEALED_FORMAT}|{sender}");
+    for w in recipients {
+        aad.push('|');
+        aad.push_str(&w.device);
+    }
+    aad.into_bytes()
+}
+
+pub(crate) fn encrypt(key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Result<([u8; 12], Vec<u8>)> {
+    let nonce = random::<12>()?;
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("encryption failed"))?;
+    Ok((nonce, ciphertext))
+}
+
+pub(crate) fn decrypt(
+    key: &[u8; 32],
+    nonce: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Option<Vec<u8>> {
+    if nonce.len() != 12 {
+        return None;
+    }
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .ok()
+}
+
+pub(crate) fn decode(s: &str, what: &str) -> Result<Vec<u8>> {
+    B64.decode(s)
+        .with_context(|| format!("{what} is not valid base64"))
+}
+
+fn decode_key(s: &str, what: &str) -> Result<[u8; 32]> {
+    decode(s, what)?
+        .try_into()
+        .map_err(|_| anyhow!("{what} must be 32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::sync::SyncState;
+
+    fn paired() -> (SyncKeys, SyncKeys) {
+        let mut a = SyncKeys::generate("device-a").unwrap();
+        let mut b = SyncKeys::generate("device-b").unwrap();
+        b.accept_invite(&a.invite("correct horse").unwrap(), "correct horse")
+            .unwrap();
+        a.accept_invite(&b.invite("correct horse").unwrap(), "correct horse")
+            .unwrap();
+        (a, b)
+    }
+
+    #[test]
+    fn sealed_delta_opens_only_for_recipient() {
+        let (a, b) = paired();
+        let delta = crate::app::sync::build_delta(&SyncState::new("device-a"), &[], None);
+
+        let sealed = a.seal(&delta, &["device-b".to_string()]).unwrap();
+        assert_eq!(b.open(&sealed).unwrap().from, "device-a");
+
+        let stranger = SyncKeys::generate("device-c").unwrap();
+        assert!(stranger.open(&sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        tampered.recipients[0].device = "device-a".to_string();
+        assert!(b.open(&tampered).is_err());
+    }
+
+    #[test]
+    fn invite_needs_the_right_passphrase() {
+        let a = SyncKeys::generate("device-a").unwrap();
+        let mut b = SyncKeys::generate("device-b").unwrap();
+        let invite = a.invite("s3cret").unwrap();
+
+        assert!(b.accept_invite(&invite, "guess").is_err());
+        assert!(b.peers.is_empty());
+
+        assert_eq!(b.accept_invite(&invite, "s3cret").unwrap(), "device-a");
+        assert_eq!(b.peers["device-a"], a.public_key);
+    }
+}
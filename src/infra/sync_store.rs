@@ -0,0 +1,179 @@
+//! Sync state persistence.
+//!
+//! Lives in the data dir next to the database:
+//! - `sync_state.json`: this device's id, peer vectors, snapshot, tombstones
+//! - `conflicts/<uuid>.json`: the losing side of concurrent edits, sealed
+//!   with the database key when the database is encrypted
+//! - `sync_keys.json`: key pair and trusted peers (see `sync_crypto`)
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    app::sync::{SyncConflict, SyncDelta, SyncState},
+    domain::todo::TodoId,
+    infra::{
+        db_crypto::{self, DbKey},
+        paths::AppPaths,
+        perms,
+        sync_crypto::{Invite, SealedDelta},
+    },
+};
+
+/// A delta file as found on disk.
+#[derive(Debug)]
+pub enum DeltaFile {
+    Plain(SyncDelta),
+    Sealed(SealedDelta),
+}
+
+pub fn state_path(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join("sync_state.json")
+}
+
+pub fn conflicts_dir(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join("conflicts")
+}
+
+/// Load sync state, creating a fresh device id on first use.
+pub fn load_or_init(path: &Path) -> Result<SyncState> {
+    if path.exists() {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed reading sync state: {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("failed parsing sync state: {}", path.display()))
+    } else {
+        Ok(SyncState::new(TodoId::new().as_uuid_str()))
+    }
+}
+
+pub fn save(path: &Path, state: &SyncState) -> Result<()> {
+    write_json(path, state)
+}
+
+/// Read a delta file, plain or sealed (told apart by the sealed `format` tag).
+pub fn read_delta(path: &Path) -> Result<DeltaFile> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed reading sync delta: {}", path.display()))?;
+    let value: serde_json::Value = serde_json::from_str(&text)
+        .with_context(|| format!("failed parsing sync delta: {}", path.display()))?;
+
+    let file = if value.get("format").is_some() {
+        serde_json::from_value(value).map(DeltaFile::Sealed)
+    } else {
+        serde_json::from_value(value).map(DeltaFile::Plain)
+    };
+    file.with_context(|| format!("failed parsing sync delta: {}", path.display()))
+}
+
+pub fn write_delta(path: &Path, delta: &SyncDelta) -> Result<()> {
+    write_json(path, delta)
+}
+
+pub fn write_sealed(path: &Path, sealed: &SealedDelta) -> Result<()> {
+    write_json(path, sealed)
+}
+
+pub fn read_invite(path: &Path) -> Result<Invite> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed reading invite: {}", path.display()))?;
+    serde_json::from_str(&text)
+        .with_context(|| format!("failed parsing invite: {}", path.display()))
+}
+
+pub fn write_invite(path: &Path, invite: &Invite) -> Result<()> {
+    write_json(path, invite)
+}
+
+/// Persist conflicts as `<dir>/<uuid>.json`, one file per todo, sealed with
+/// `key` if there is one.
+pub fn write_conflicts(
+    dir: &Path,
+    conflicts: &[SyncConflict],
+    key: Option<&DbKey>,
+) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for c in conflicts {
+        let path = dir.join(format!("{}.json", c.kept.id.as_uuid_str()));
+        match key {
+            Some(key) => {
+                let json = serde_json::to_vec(c).context("failed serializing sync json")?;
+                perms::create_dir(dir)
+                    .with_context(|| format!("failed creating directory: {}", dir.display()))?;
+                perms::write(&path, key.seal(&json)?)
+                    .with_context(|| format!("failed writing file: {}", path.display()))?;
+            }
+            None => write_json(&path, c)?,
+        }
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// The JSON text of a conflict file (or a todo file given in its place),
+/// opened with `key` if it was sealed.
+pub fn read_conflict(path: &Path, key: Option<&DbKey>) -> Result<String> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed reading {}", path.display()))?;
+    let bytes = if db_crypto::is_encrypted(&bytes) {
+        let key =
+            key.with_context(|| format!("{} is encrypted but the database isn't", path.display()))?;
+        key.open(&bytes)
+            .with_context(|| format!("failed opening {}", path.display()))?
+    } else {
+        bytes
+    };
+    String::from_utf8(bytes).with_context(|| format!("{} is not UTF-8", path.display()))
+}
+
+fn write_json(path: &Path, value: &impl serde::Serialize) -> Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        perms::create_dir(parent)
+            .with_context(|| format!("failed creating directory: {}", parent.display()))?;
+    }
+
+    let json = serde_json::to_string_pretty(value).context("failed serializing sync json")?;
+    perms::write(path, json).with_context(|| format!("failed writing file: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn state_roundtrips_and_keeps_device_id() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sync_state.json");
+
+        let state = load_or_init(&path).unwrap();
+        save(&path, &state).unwrap();
+
+        let again = load_or_init(&path).unwrap();
+        assert_eq!(again.device_id, state.device_id);
+    }
+
+    #[test]
+    fn conflicts_are_sealed_with_the_database_key() {
+        use crate::domain::todo::{Title, Todo};
+
+        let dir = tempdir().unwrap();
+        let key = DbKey::new("correct horse").unwrap();
+        let todo = Todo::new(Title::parse("Secret plan").unwrap());
+        let conflict = SyncConflict {
+            kept: todo.clone(),
+            discarded: todo,
+            fields: Vec::new(),
+        };
+
+        let written = write_conflicts(dir.path(), &[conflict], Some(&key)).unwrap();
+        let raw = std::fs::read_to_string(&written[0]).unwrap();
+        assert!(!raw.contains("Secret plan"), "{raw}");
+        let text = read_conflict(&written[0], Some(&key)).unwrap();
+        assert!(text.contains("Secret plan"), "{text}");
+        assert!(read_conflict(&written[0], None).is_err());
+    }
+}
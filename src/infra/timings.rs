@@ -0,0 +1,151 @@
+//! Operation timings (`--timings`, `slow_ms` under `[log]`).
+//!
+//! Loading, saving, querying and syncing run inside [`timed`], which opens a
+//! tracing span for the operation, measures it and remembers the duration for
+//! the per-command breakdown. Anything slower than the configured threshold is
+//! logged as a warning, so slow disks or huge databases show up in the log
+//! file without asking for them.
+//!
+//! Timings are kept per thread, so concurrent commands (tests, or the server
+//! handling a request) don't mix up each other's breakdowns.
+
+use std::{
+    cell::{Cell, RefCell},
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, warn};
+
+/// Something worth timing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// The whole command, from loading the store to the last write.
+    Command,
+    Load,
+    Save,
+    Query,
+    Sync,
+}
+
+impl Op {
+    pub fn label(self) -> &'static str {
+        match self {
+            Op::Command => "command",
+            Op::Load => "load",
+            Op::Save => "save",
+            Op::Query => "query",
+            Op::Sync => "sync",
+        }
+    }
+
+    fn span(self) -> tracing::Span {
+        // Span names have to be literals.
+        match self {
+            Op::Command => tracing::info_span!("command"),
+            Op::Load => tracing::info_span!("load"),
+            Op::Save => tracing::info_span!("save"),
+            Op::Query => tracing::info_span!("query"),
+            Op::Sync => tracing::info_span!("sync"),
+        }
+    }
+}
+
+/// How long one run of an operation took.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+    pub op: Op,
+    pub elapsed: Duration,
+}
+
+/// Default for [`set_slow_threshold`].
+pub const DEFAULT_SLOW: Duration = Duration::from_millis(500);
+
+/// Timings kept per thread; long-running callers (the GUI, `serve`) that
+/// never [`take`] them only keep the most recent.
+const MAX_RECORDED: usize = 1000;
+
+thread_local! {
+    static RECORDED: RefCell<Vec<Timing>> = const { RefCell::new(Vec::new()) };
+    static SLOW: Cell<Duration> = const { Cell::new(DEFAULT_SLOW) };
+}
+
+/// Warn about operations slower than `threshold` (zero turns warnings off).
+pub fn set_slow_threshold(threshold: Duration) {
+    SLOW.with(|s| s.set(threshold));
+}
+
+/// Run `f` as `op`: inside its span, timed, and warned about if slow.
+pub fn timed<T>(op: Op, f: impl FnOnce() -> T) -> T {
+    let span = op.span();
+    let _entered = span.enter();
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+
+    RECORDED.with(|r| {
+        let mut r = r.borrow_mut();
+        if r.len() >= MAX_RECORDED {
+            r.remove(0);
+        }
+        r.push(Timing { op, elapsed });
+    });
+    let ms = elapsed.as_secs_f64() * 1000.0;
+    let slow = SLOW.with(Cell::get);
+    if !slow.is_zero() && elapsed > slow {
+        warn!(
+            op = op.label(),
+            ms,
+            threshold_ms = slow.as_millis() as u64,
+            "slow operation"
+        );
+    } else {
+        debug!(op = op.label(), ms, "operation finished");
+    }
+    result
+}
+
+/// Timings recorded on this thread since the last call, oldest first.
+pub fn take() -> Vec<Timing> {
+    RECORDED.with(|r| std::mem::take(&mut *r.borrow_mut()))
+}
+
+/// Per-operation totals, in order of first appearance: `(op, runs, total)`.
+pub fn summarize(timings: &[Timing]) -> Vec<(Op, usize, Duration)> {
+    let mut rows: Vec<(Op, usize, Duration)> = Vec::new();
+    for t in timings {
+        match rows.iter_mut().find(|(op, _, _)| *op == t.op) {
+            Some(row) => {
+                row.1 += 1;
+                row.2 += t.elapsed;
+            }
+            None => rows.push((t.op, 1, t.elapsed)),
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timed_operations_are_recorded_and_summarized() {
+        take();
+        let n = timed(Op::Command, || {
+            timed(Op::Load, || ());
+            timed(Op::Save, || ());
+            timed(Op::Save, || 2)
+        });
+        assert_eq!(n, 2);
+
+        let timings = take();
+        // Inner operations finish first.
+        let ops: Vec<_> = timings.iter().map(|t| t.op).collect();
+        assert_eq!(ops, [Op::Load, Op::Save, Op::Save, Op::Command]);
+        let rows = summarize(&timings);
+        let counts: Vec<_> = rows.iter().map(|(op, n, _)| (op.label(), *n)).collect();
+        assert_eq!(counts, [("load", 1), ("save", 2), ("command", 1)]);
+        assert!(rows[2].2 >= rows[1].2);
+        assert!(take().is_empty());
+    }
+}
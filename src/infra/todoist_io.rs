@@ -0,0 +1,402 @@
+//! Import from Todoist (`import --format todoist`).
+//!
+//! Two kinds of files are understood:
+//! - the CSV Todoist writes for a project (Export as template / backups), one
+//!   file per project: `TYPE,CONTENT,DESCRIPTION,PRIORITY,...,DATE,...` rows
+//!   of `section`, `task` and `note`;
+//! - JSON from the Todoist API: a sync response with `projects`, `sections`
+//!   and `items`, or a plain list of REST tasks.
+//!
+//! Mapping:
+//! - a task in a section goes to a project named after the section, other
+//!   tasks to their Todoist project (for CSV, the file name);
+//! - labels become tags (`@Deep Work` → `deep-work`); in CSV they are the
+//!   `@label` words of the task text;
+//! - Todoist p1..p4 become P1..P4. The CSV `PRIORITY` column counts like the
+//!   app (1 is p1), the API the other way round (4 is p1);
+//! - due strings go through the same reader as `--due`. Ones it can't read,
+//!   like recurring `every monday`, are kept in the notes instead;
+//! - descriptions and CSV `note` rows (comments) become notes;
+//! - API tasks keep their id as a `todoist:<id>` reference, so importing a
+//!   newer export with `--mode merge` updates them instead of adding copies.
+//!
+//! Subtasks are imported as ordinary todos.
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use time::{OffsetDateTime, PrimitiveDateTime, macros::format_description};
+
+use crate::{
+    app::due_input::parse_due,
+    domain::{
+        external_ref::ExternalRef,
+        todo::{DueAt, Notes, Priority, ProjectName, Tag, Title, Todo},
+    },
+};
+
+/// Read a Todoist export: JSON if it looks like JSON, CSV otherwise.
+pub fn import(path: &Path) -> Result<Vec<Todo>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed reading Todoist export: {}", path.display()))?;
+    let now = OffsetDateTime::now_utc();
+    if text.trim_start().starts_with(['{', '[']) {
+        parse_json(&text, now)
+    } else {
+        let project = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        parse_csv(&text, &project, now)
+    }
+}
+
+/// Tasks from a project's CSV export; `project` names the project (the file
+/// name, as Todoist names the file after it).
+pub fn parse_csv(text: &str, project: &str, now: OffsetDateTime) -> Result<Vec<Todo>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+    let headers = rdr
+        .headers()
+        .context("failed reading Todoist CSV header")?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let (Some(kind), Some(content)) = (column("TYPE"), column("CONTENT")) else {
+        bail!("not a Todoist CSV export (needs TYPE and CONTENT columns)");
+    };
+    let (description, priority, date) = (column("DESCRIPTION"), column("PRIORITY"), column("DATE"));
+
+    let mut todos: Vec<Todo> = Vec::new();
+    let mut section: Option<String> = None;
+    for (n, row) in rdr.records().enumerate() {
+        let row = row.with_context(|| format!("failed reading Todoist CSV row {}", n + 2))?;
+        let field = |i: Option<usize>| i.and_then(|i| row.get(i)).unwrap_or("").trim();
+        match field(Some(kind)).to_ascii_lowercase().as_str() {
+            "section" => section = Some(field(Some(content)).to_string()),
+            "task" => {
+                let (title, labels) = split_labels(field(Some(content)));
+                let task = Task {
+                    title,
+                    description: field(description).to_string(),
+                    project: section.clone().unwrap_or_else(|| project.to_string()),
+                    labels,
+                    priority: field(priority).parse().ok(),
+                    due: Some(field(date).to_string()).filter(|d| !d.is_empty()),
+                    done: false,
+                    id: None,
+                };
+                todos.extend(task.into_todo(now)?);
+            }
+            // Comments belong to the task above them.
+            "note" => {
+                if let Some(last) = todos.last_mut() {
+                    append_note(last, field(Some(content)))?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(todos)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonExport {
+    Sync {
+        #[serde(default)]
+        projects: Vec<JsonNamed>,
+        #[serde(default)]
+        sections: Vec<JsonNamed>,
+        #[serde(alias = "tasks")]
+        items: Vec<JsonTask>,
+    },
+    Tasks(Vec<JsonTask>),
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonNamed {
+    id: serde_json::Value,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonTask {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    content: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    project_id: Option<serde_json::Value>,
+    #[serde(default)]
+    section_id: Option<serde_json::Value>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    priority: Option<u8>,
+    #[serde(default)]
+    due: Option<JsonDue>,
+    #[serde(default, alias = "is_completed")]
+    checked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonDue {
+    #[serde(default)]
+    date: Option<String>,
+    #[serde(default)]
+    datetime: Option<String>,
+    #[serde(default)]
+    string: Option<String>,
+    #[serde(default)]
+    is_recurring: bool,
+}
+
+/// Tasks from Todoist API JSON (see the module docs).
+pub fn parse_json(text: &str, now: OffsetDateTime) -> Result<Vec<Todo>> {
+    let export: JsonExport =
+        serde_json::from_str(text).context("not a Todoist JSON export (no items or tasks)")?;
+    let (projects, sections, items) = match export {
+        JsonExport::Sync {
+            projects,
+            sections,
+            items,
+        } => (projects, sections, items),
+        JsonExport::Tasks(items) => (Vec::new(), Vec::new(), items),
+    };
+    // Ids are strings in newer APIs and numbers in older ones.
+    let names = |list: Vec<JsonNamed>| -> BTreeMap<String, String> {
+        list.into_iter().map(|n| (id_key(&n.id), n.name)).collect()
+    };
+    let (projects, sections) = (names(projects), names(sections));
+
+    let mut todos = Vec::new();
+    for item in items {
+        let section = item
+            .section_id
+            .as_ref()
+            .and_then(|id| sections.get(&id_key(id)));
+        let project = item
+            .project_id
+            .as_ref()
+            .and_then(|id| projects.get(&id_key(id)));
+        let due = item.due.map(|d| {
+            // A recurring due date is only readable as its text.
+            let exact = d.datetime.or(d.date).filter(|_| !d.is_recurring);
+            exact.or(d.string).unwrap_or_default()
+        });
+        let task = Task {
+            title: item.content,
+            description: item.description,
+            project: section.or(project).cloned().unwrap_or_default(),
+            labels: item.labels,
+            priority: item.priority.map(|p| 5u8.saturating_sub(p)),
+            due: due.filter(|d| !d.is_empty()),
+            done: item.checked,
+            id: item.id.as_ref().map(id_key),
+        };
+        todos.extend(task.into_todo(now)?);
+    }
+    Ok(todos)
+}
+
+fn id_key(id: &serde_json::Value) -> String {
+    match id {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A Todoist task in our terms; `priority` is 1 (p1) to 4.
+struct Task {
+    title: String,
+    description: String,
+    project: String,
+    labels: Vec<String>,
+    priority: Option<u8>,
+    due: Option<String>,
+    done: bool,
+    /// Todoist's id (API exports only).
+    id: Option<String>,
+}
+
+impl Task {
+    /// `None` for a task without a title.
+    fn into_todo(self, now: OffsetDateTime) -> Result<Option<Todo>> {
+        let Ok(title) = Title::parse(&self.title) else {
+            return Ok(None);
+        };
+        let mut todo = Todo::new(title);
+        if let Ok(project) = ProjectName::parse(&self.project) {
+            todo.project = project;
+        }
+        todo.tags = self.labels.iter().filter_map(|l| label_tag(l)).collect();
+        if let Some(p) = self
+            .priority
+            .and_then(|p| Priority::parse(format!("P{p}")).ok())
+        {
+            todo.priority = p;
+        }
+        append_note(&mut todo, &self.description)?;
+        if let Some(due) = self.due {
+            // Don't let `every friday` pass for next Friday only.
+            let recurring = due.to_ascii_lowercase().starts_with("every");
+            match read_due(&due, now).filter(|_| !recurring) {
+                Some(at) => todo.due = Some(at),
+                None => append_note(&mut todo, &format!("Todoist due: {due}"))?,
+            }
+        }
+        if self.done {
+            todo.mark_done()?;
+        }
+        todo.external_ref = self
+            .id
+            .and_then(|id| ExternalRef::parse(format!("todoist:{id}")).ok())
+            .map(|r| r.resolve_url(None));
+        Ok(Some(todo))
+    }
+}
+
+fn read_due(input: &str, now: OffsetDateTime) -> Option<DueAt> {
+    if let Ok(due) = parse_due(input, now) {
+        return Some(due);
+    }
+    // The API's "floating" times have no offset.
+    PrimitiveDateTime::parse(
+        input.trim(),
+        format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]"),
+    )
+    .ok()
+    .map(|at| DueAt::from_dt(at.assume_offset(now.offset())))
+}
+
+/// The task text without its `@label` words, and the labels.
+fn split_labels(content: &str) -> (String, Vec<String>) {
+    let mut labels = Vec::new();
+    let mut words = Vec::new();
+    for word in content.split_whitespace() {
+        match word.strip_prefix('@') {
+            Some(label) if !label.is_empty() => labels.push(label.to_string()),
+            _ => words.push(word),
+        }
+    }
+    (words.join(" "), labels)
+}
+
+/// A Todoist label as a tag: lowercase, spaces to dashes, other characters
+/// tags can't have dropped.
+fn label_tag(label: &str) -> Option<Tag> {
+    let slug: String = label
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_whitespace() { '-' } else { c })
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    Tag::parse(slug).ok()
+}
+
+fn append_note(todo: &mut Todo, text: &str) -> Result<()> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(());
+    }
+    let notes = match &todo.notes {
+        Some(n) if !n.as_str().is_empty() => format!("{}\n\n{text}", n.as_str()),
+        _ => text.to_string(),
+    };
+    todo.notes = Some(Notes::parse(notes)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    const NOW: OffsetDateTime = datetime!(2026-03-11 10:00 UTC);
+
+    fn find<'a>(todos: &'a [Todo], title: &str) -> &'a Todo {
+        todos
+            .iter()
+            .find(|t| t.title.as_str() == title)
+            .unwrap_or_else(|| panic!("no todo {title}"))
+    }
+
+    fn tags(todo: &Todo) -> Vec<&str> {
+        todo.tags.iter().map(|t| t.as_str()).collect()
+    }
+
+    #[test]
+    fn csv_sections_become_projects() {
+        let text = include_str!("../../tests/fixtures/todoist/Work.csv");
+        let todos = parse_csv(text, "Work", NOW).unwrap();
+        assert_eq!(todos.len(), 4);
+
+        let report = find(&todos, "Send quarterly report");
+        assert_eq!(report.project.as_str(), "Work");
+        assert_eq!(report.priority, Priority::P1);
+        assert_eq!(tags(report), ["deep-work", "finance"]);
+        assert_eq!(report.due.unwrap().as_dt(), datetime!(2026-03-13 17:00 UTC));
+        assert_eq!(
+            report.notes.as_ref().unwrap().as_str(),
+            "Numbers from the dashboard\n\nAsk Sam for the Q4 figures"
+        );
+
+        let standup = find(&todos, "Write standup notes");
+        assert_eq!(standup.project.as_str(), "Meetings");
+        assert_eq!(standup.priority, Priority::P4);
+        assert!(standup.due.is_none());
+        assert_eq!(
+            standup.notes.as_ref().unwrap().as_str(),
+            "Todoist due: every weekday"
+        );
+
+        let review = find(&todos, "Review PR");
+        assert_eq!(review.due.unwrap().as_dt(), datetime!(2026-03-12 17:00 UTC));
+
+        assert!(parse_csv("title,notes\nx,y\n", "Work", NOW).is_err());
+    }
+
+    #[test]
+    fn json_items_map_sections_labels_and_priorities() {
+        let text = include_str!("../../tests/fixtures/todoist/sync.json");
+        let todos = parse_json(text, NOW).unwrap();
+        assert_eq!(todos.len(), 3);
+
+        let milk = find(&todos, "Buy milk");
+        assert_eq!(milk.project.as_str(), "Groceries");
+        assert_eq!(milk.priority, Priority::P4);
+        assert_eq!(tags(milk), ["errands"]);
+
+        let taxes = find(&todos, "File taxes");
+        assert_eq!(taxes.project.as_str(), "Personal");
+        assert_eq!(taxes.priority, Priority::P1);
+        assert_eq!(taxes.due.unwrap().as_dt(), datetime!(2026-04-15 09:00 UTC));
+
+        let link = taxes.external_ref.as_ref().unwrap();
+        assert_eq!(link.to_string(), "todoist:2995104340");
+        assert!(link.url.as_deref().unwrap().ends_with("/task/2995104340"));
+
+        let plants = find(&todos, "Water plants");
+        assert_eq!(plants.project.as_str(), "Home");
+        assert_eq!(tags(plants), ["home-chores"]);
+        assert!(plants.status.is_done());
+        assert!(
+            plants
+                .notes
+                .as_ref()
+                .unwrap()
+                .as_str()
+                .contains("every sunday")
+        );
+
+        // A bare list of REST tasks.
+        let rest = r#"[{"content": "Call mom", "priority": 3, "due": {"date": "2026-03-12"}}]"#;
+        let todos = parse_json(rest, NOW).unwrap();
+        assert_eq!(todos[0].priority, Priority::P2);
+        assert_eq!(todos[0].project.as_str(), "Inbox");
+    }
+}
@@ -0,0 +1,70 @@
+//! The few Win32 calls std doesn't expose.
+//!
+//! Declared by hand rather than pulling in `windows-sys` for three functions.
+
+use std::{
+    ffi::{OsStr, c_void},
+    io,
+    os::windows::ffi::OsStrExt,
+    path::Path,
+};
+
+type Handle = *mut c_void;
+
+const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+const MOVEFILE_REPLACE_EXISTING: u32 = 0x1;
+const MOVEFILE_WRITE_THROUGH: u32 = 0x8;
+
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn GetStdHandle(std_handle: u32) -> Handle;
+    fn GetConsoleMode(console: Handle, mode: *mut u32) -> i32;
+    fn SetConsoleMode(console: Handle, mode: u32) -> i32;
+    fn MoveFileExW(existing: *const u16, new: *const u16, flags: u32) -> i32;
+}
+
+/// Turn on ANSI escape handling for stdout and stderr. Returns false if a
+/// console refused (pre-Windows 10); output that isn't a console is fine.
+pub fn enable_ansi() -> bool {
+    [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE]
+        .into_iter()
+        .all(|which| {
+            // SAFETY: plain Win32 calls on the process's own standard handles;
+            // `mode` outlives the call that writes it.
+            unsafe {
+                let handle = GetStdHandle(which);
+                let mut mode = 0;
+                if handle.is_null() || GetConsoleMode(handle, &mut mode) == 0 {
+                    return true;
+                }
+                mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                    || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+            }
+        })
+}
+
+/// Rename `from` over `to`, returning only once the move is on disk
+/// (`MOVEFILE_WRITE_THROUGH`). Windows can't fsync a directory, so this is
+/// what makes the rename itself durable.
+pub fn replace_durably(from: &Path, to: &Path) -> io::Result<()> {
+    let from = wide(from.as_os_str());
+    let to = wide(to.as_os_str());
+    // SAFETY: both buffers are NUL-terminated and live across the call.
+    let ok = unsafe {
+        MoveFileExW(
+            from.as_ptr(),
+            to.as_ptr(),
+            MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn wide(s: &OsStr) -> Vec<u16> {
+    s.encode_wide().chain(std::iter::once(0)).collect()
+}
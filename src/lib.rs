@@ -4,8 +4,16 @@
 //! - Integration tests (`tests/`) to import and exercise the app
 //! - A clean separation between the binary (`main.rs`) and the core logic
 //! - Future reuse (e.g. TUI-only binary, benchmarks, etc.)
+//! - Embedding from other languages via the optional `ffi` feature
+//!
+//! With `--no-default-features` only the pure core (domain, app, in-memory
+//! repository, schema decoding) is built, which also compiles for wasm32.
 
 pub mod app;
 pub mod domain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod infra;
+pub mod testing;
+#[cfg(feature = "native")]
 pub mod ui;
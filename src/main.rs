@@ -3,6 +3,7 @@
 //!
 //! Responsibilities:
 //! - Initialize logging
+//! - Install the crash-report panic hook
 //! - Detect config/data paths
 //! - Parse CLI arguments
 //! - Wire UI → application services → repository
@@ -13,11 +14,11 @@ use tracing::Level;
 mod app;
 mod domain;
 mod infra;
+mod testing;
 mod ui;
 
 fn main() -> Result<()> {
     // Parse only the global flags first (currently just --debug).
-    // We do this before initializing logging.
     let debug_enabled = ui::cli::peek_debug_flag();
 
     let level = if debug_enabled {
@@ -26,24 +27,29 @@ fn main() -> Result<()> {
         Level::INFO
     };
 
-    // Initialize structured logging.
-    tracing_subscriber::fmt()
-        .with_max_level(level)
-        .with_target(false)
-        .init();
-
     // Detect app paths once and share via context.
     let paths = infra::paths::AppPaths::detect()?;
 
     // Ensure directories exist early (cross-platform friendly).
-    std::fs::create_dir_all(&paths.config_dir)
+    infra::perms::create_dir(&paths.config_dir)
         .with_context(|| format!("failed creating config dir: {}", paths.config_dir.display()))?;
-    std::fs::create_dir_all(&paths.data_dir)
+    infra::perms::create_dir(&paths.data_dir)
         .with_context(|| format!("failed creating data dir: {}", paths.data_dir.display()))?;
 
     let config = infra::config::AppConfig::load_or_create(&paths)?;
+
+    // Initialize structured logging (the `[log]` file needs the config).
+    infra::logfile::init(level, &paths.data_dir, &config.log);
+
+    // Panics leave a crash report in the data dir from here on.
+    let journal = infra::journal::journal_path(&config.resolve_db_path(&paths));
+    infra::crash::install(paths.data_dir.clone(), journal);
+
     let ctx = app::context::AppContext::new(paths, config);
 
     // Delegate everything else to the CLI UI for now.
-    ui::cli::run(ctx)
+    match ui::cli::run(ctx) {
+        Err(e) if ui::cli::is_broken_pipe(&e) => Ok(()),
+        result => result,
+    }
 }
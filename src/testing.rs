@@ -0,0 +1,43 @@
+//! Helpers for tests (and replays) that need to control time.
+
+use std::sync::{Arc, Mutex};
+
+use time::{Duration, OffsetDateTime};
+
+use crate::domain::clock::Clock;
+
+/// A clock that reads the same time until it is set or advanced.
+///
+/// Clones share the time, so a test can keep one and move the clock it handed
+/// to a store or context.
+#[derive(Debug, Clone)]
+pub struct FixedClock {
+    at: Arc<Mutex<OffsetDateTime>>,
+}
+
+impl FixedClock {
+    pub fn new(at: OffsetDateTime) -> Self {
+        Self {
+            at: Arc::new(Mutex::new(at)),
+        }
+    }
+
+    pub fn set(&self, at: OffsetDateTime) {
+        *self.lock() = at;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.lock() += by;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, OffsetDateTime> {
+        // The guarded value is a plain time; a panic elsewhere can't break it.
+        self.at.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.lock()
+    }
+}
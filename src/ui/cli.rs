@@ -5,13 +5,24 @@
 use std::io::{self, Write};
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use tracing::{debug, info};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use tracing::{debug, info, warn};
 
 use crate::{
-    app::repository::TodoRepository,
     app::{context::AppContext, store::Store},
-    domain::todo::Title,
+    app::{repository::TodoRepository, warnings::Warning},
+    domain::todo::{Title, TodoId},
+    infra::{
+        config::ColorChoice,
+        git_sync::GitSync,
+        timings::{Op, timed},
+    },
+    ui::{
+        humanize,
+        style::Style,
+        symbols::SymbolSet,
+        theme::{Rgb, Role},
+    },
 };
 
 /// Top-level CLI definition.
@@ -23,6 +34,24 @@ struct Cli {
     #[arg(long, global = true)]
     debug: bool,
 
+    /// Don't ask for confirmation (see `confirm` in config.toml)
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// Print how long loading, saving, queries and syncing took
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Run as if it were this time (anything --due accepts, e.g.
+    /// 2026-03-01T09:00:00Z or "next friday 9am")
+    #[arg(long, global = true, value_name = "TIME")]
+    as_of: Option<String>,
+
+    /// Color output: auto (terminals, unless NO_COLOR is set), always or
+    /// never (default: `color` in config.toml)
+    #[arg(long, global = true, value_name = "WHEN")]
+    color: Option<String>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -33,8 +62,31 @@ enum Commands {
     Tui,
     /// Add a new todo
     Add {
-        /// Title of the todo
-        title: String,
+        /// Title of the todo; #tag, @project, !p1 and due:friday in it set
+        /// those fields (`-` reads titles from stdin, like --stdin)
+        #[arg(required_unless_present_any = ["template", "stdin"])]
+        title: Option<String>,
+
+        /// Keep #, @, ! and due: words in the title as typed
+        #[arg(long, conflicts_with_all = ["dictated", "stdin"])]
+        raw: bool,
+
+        /// Add one todo per line of stdin, all in one save. Lines may carry
+        /// #tag, @project, !p1 and due:friday; --project, --tag, --priority
+        /// and --due apply to lines without their own
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "title", "template", "notes", "notes_file", "remind", "escalate", "external_ref",
+                "badge", "color", "estimate", "energy", "dictated", "depends_on", "parent",
+            ]
+        )]
+        stdin: bool,
+
+        /// Start from a `[templates.<name>]` entry in config.toml; its title may
+        /// use {date}, {week}, {project}, ... (a given title replaces it)
+        #[arg(long)]
+        template: Option<String>,
 
         /// Project/context name (default: Inbox)
         #[arg(long)]
@@ -48,21 +100,92 @@ enum Commands {
         #[arg(long)]
         notes: Option<String>,
 
-        /// Priority: P1 (high) .. P4 (low)
+        /// Read the notes from this file (e.g. Markdown) instead
+        #[arg(long, conflicts_with = "notes")]
+        notes_file: Option<String>,
+
+        /// Priority: P1 (high) .. P4 (low), or a label from
+        /// [[priority_levels]]
         #[arg(long)]
         priority: Option<String>,
 
-        /// Due datetime in RFC3339, e.g. 2026-01-02T09:00:00Z
+        /// Due date: tomorrow, "friday 5pm", "in 3 days", next week, 2026-01-02
+        /// or RFC3339 (2026-01-02T09:00:00Z)
         #[arg(long)]
         due: Option<String>,
+
+        /// When `notify` should remind you, in any form --due accepts
+        #[arg(long)]
+        remind: Option<String>,
+
+        /// Reminder escalation instead of the priority's from config.toml:
+        /// "-1d, -1h, every 30m", or off
+        #[arg(long, allow_hyphen_values = true)]
+        escalate: Option<String>,
+
+        /// Item this todo tracks elsewhere: jira:ABC-123, github:owner/repo#12
+        #[arg(long = "ref")]
+        external_ref: Option<String>,
+
+        /// Link for --ref, when [links] in config.toml has no template for it
+        #[arg(long, requires = "external_ref")]
+        ref_url: Option<String>,
+
+        /// Badge shown before the title, e.g. an emoji: --badge 🔥
+        #[arg(long)]
+        badge: Option<String>,
+
+        /// Highlight color: red|orange|yellow|green|blue|purple|gray
+        #[arg(long)]
+        color: Option<String>,
+
+        /// Estimated effort, e.g. 45m, 2h, 1h30m, 1d (8h)
+        #[arg(long)]
+        estimate: Option<String>,
+
+        /// Energy it takes: low|medium|high
+        #[arg(long)]
+        energy: Option<String>,
+
+        /// Treat the title as a dictated sentence and pick out priority, due
+        /// date, project and tags: "call the dentist next tuesday its urgent"
+        #[arg(long)]
+        dictated: bool,
+
+        /// Todo that must be done first (repeatable, ID or unique prefix)
+        #[arg(long = "depends-on")]
+        depends_on: Vec<String>,
+
+        /// Make it a subtask of this todo (ID or unique prefix)
+        #[arg(long)]
+        parent: Option<String>,
+
+        /// Output format: text (default) or json (the ID and any warnings)
+        #[arg(long, default_value = "text")]
+        format: String,
     },
 
     /// List todos
     List {
-        /// Output format: table (default) or json
+        /// Query, e.g. "project:Work tag:rust due<2026-02-01 -tag:blocked urgent"
+        /// (combined with the flags below)
+        query: Vec<String>,
+
+        /// Output format: table (default), json or jsonl (one todo per line,
+        /// for jq and grep)
         #[arg(long, default_value = "table")]
         format: String,
 
+        /// With --format json or jsonl, only these keys per todo
+        /// (comma-separated), e.g. id,title,due,priority
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+
+        /// In the table, show due dates as RFC 3339 timestamps rather than
+        /// "due in 2d" or "today 17:00"
+        #[arg(long)]
+        absolute: bool,
+
         /// Filter by status: open|done
         #[arg(long)]
         status: Option<String>,
@@ -83,33 +206,239 @@ enum Commands {
         #[arg(long)]
         overdue: bool,
 
-        /// Filter by priority: P1..P4
+        /// Filter by priority: P1..P4 or a label from [[priority_levels]]
         #[arg(long)]
         priority: Option<String>,
 
-        /// Sort by: due|priority|created
+        /// Filter by energy: low|medium|high
+        #[arg(long)]
+        energy: Option<String>,
+
+        /// Filter by where todos were created: cli|tui|gui|api|email|import[:<file>]
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Filter by workflow state: open (not started), in-progress, ...
+        #[arg(long)]
+        state: Option<String>,
+
+        /// Sort by: due|priority|created|title|id|time (tracked, most first)|
+        /// urgency (most first, adds a URG column)
         #[arg(long, default_value = "due")]
         sort: String,
 
-        /// Sort descending
+        /// Break ties by these keys (comma-separated or repeated), before the
+        /// built-in due, priority, created, id order
+        #[arg(long = "then-by", value_delimiter = ',')]
+        then_by: Vec<String>,
+
+        /// Sort descending (by the --sort key; ties stay ascending)
         #[arg(long)]
         desc: bool,
+
+        /// Show todos under headings by project|tag|priority|due, with counts
+        /// (table and json; by tag, a todo is listed under each of its tags)
+        #[arg(long = "group-by")]
+        group_by: Option<String>,
+
+        /// Show only someday/maybe items (hidden otherwise)
+        #[arg(long)]
+        someday: bool,
+
+        /// Show only archived todos (hidden otherwise)
+        #[arg(long)]
+        archived: bool,
+
+        /// Only todos waiting on an open dependency
+        #[arg(long, conflicts_with = "ready")]
+        blocked: bool,
+
+        /// Only open todos with no open dependencies
+        #[arg(long)]
+        ready: bool,
+
+        /// Show at most this many todos
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Skip this many matching todos first (use with --limit to page)
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+    },
+
+    /// Show what to work on next: unblocked open todos by effective priority
+    Next {
+        /// How many todos to show
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+
+        /// Only todos taking at most this energy: low|medium|high
+        #[arg(long)]
+        energy: Option<String>,
+
+        /// Output format: table (default) or json
+        #[arg(long, default_value = "table")]
+        format: String,
+    },
+
+    /// Start working on a todo: move it into its project's first workflow
+    /// state and track time (stops any other running timer)
+    Start {
+        /// Todo ID (full UUID or unique prefix); omit to pick one
+        id: Option<String>,
+    },
+
+    /// Stop the running timer; with an ID, also move that todo back to open
+    Stop {
+        /// Todo ID; omit to just stop whatever is running
+        id: Option<String>,
+
+        /// Note to attach to the finished time entry
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// Summaries over your todos: counts by project/tag/priority, completion
+    /// rate and time to completion (`stats time` for tracked time)
+    #[command(args_conflicts_with_subcommands = true)]
+    Stats {
+        #[command(subcommand)]
+        action: Option<StatsAction>,
+
+        /// Window for the completion rate and time to completion, in days
+        #[arg(long, default_value_t = 30)]
+        days: u32,
+
+        /// Output format: table (default) or json
+        #[arg(long, default_value = "table")]
+        format: String,
+    },
+
+    /// Todos in kanban columns: open, each workflow state, done
+    Board {
+        /// Only this project's todos (and its workflow)
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Most todos shown per column
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Eisenhower matrix of open todos: urgent (due soon) by important
+    /// (P1/P2), in a 2x2 grid
+    Matrix {
+        #[command(subcommand)]
+        action: Option<MatrixAction>,
+
+        /// Days ahead a due date makes a todo urgent
+        #[arg(long, default_value_t = 2)]
+        within: u32,
+
+        /// Only this project's todos
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Most todos shown per quadrant
+        #[arg(long, default_value_t = 8)]
+        limit: usize,
+
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+
+    /// List, describe, rename and archive projects
+    Project {
+        #[command(subcommand)]
+        action: ProjectAction,
+    },
+
+    /// Longest chain of dependent todos (by estimate), with slack per todo
+    CriticalPath {
+        /// Only plan todos in this project
+        #[arg(long)]
+        project: Option<String>,
+    },
+
+    /// Estimate when each project's open backlog will be cleared at the recent
+    /// completion rate, and which due dates that pace misses
+    Forecast {
+        /// Completion history to base the rate on (e.g. 28d, 8w)
+        #[arg(long, default_value = "28d")]
+        window: String,
+
+        /// Only forecast this project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Output format: table (default) or json
+        #[arg(long, default_value = "table")]
+        format: String,
+    },
+
+    /// Open and completed todos as Markdown, grouped by project or by day,
+    /// for a standup or weekly review; or, given a name, a `[reports.<name>]`
+    /// table from config.toml
+    Report {
+        /// Run this `[reports.<name>]` table instead (see --list)
+        #[arg(conflicts_with_all = ["by", "since", "project"])]
+        name: Option<String>,
+
+        /// List the reports defined in config.toml
+        #[arg(long, conflicts_with = "name")]
+        list: bool,
+
+        /// Group by: project (default) or day (completed on, due on)
+        #[arg(long, default_value = "project")]
+        by: String,
+
+        /// Only todos completed since (e.g. 1d, 1w, 2026-01-01 or RFC3339)
+        #[arg(long, default_value = "7d")]
+        since: String,
+
+        /// Only this project's todos
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Write the report to this file instead of stdout
+        #[arg(long)]
+        out: Option<String>,
     },
 
     /// Show a single todo
     Show {
-        /// Todo ID (full UUID or unique prefix)
-        id: String,
+        /// Todo ID (full UUID or unique prefix); omit to pick one
+        id: Option<String>,
 
         /// Output format: table (default) or json
         #[arg(long, default_value = "table")]
         format: String,
+
+        /// Show dates as RFC 3339 timestamps rather than "due in 2d"
+        #[arg(long)]
+        absolute: bool,
+
+        /// Print only the notes, as stored (e.g. `> notes.md` to edit them
+        /// and load them back with `edit --notes-file`)
+        #[arg(long, conflicts_with_all = ["format", "absolute"])]
+        notes_only: bool,
     },
 
     /// Edit an existing todo by short ID (from `list`)
     Edit {
-        /// Short ID (first 8 chars shown in list)
-        id: String,
+        /// Short IDs (first 8 chars shown in list); every todo gets the same
+        /// changes. Omit to pick one
+        ids: Vec<String>,
+
+        /// Edit many todos at once as a table in $VISUAL / $EDITOR
+        #[arg(long, conflicts_with = "ids")]
+        bulk: bool,
+
+        /// With --bulk, which todos to include (repeatable, all must match):
+        /// --filter project=Work --filter tag=rust --filter overdue
+        #[arg(long, requires = "bulk")]
+        filter: Vec<String>,
 
         #[arg(long)]
         title: Option<String>,
@@ -117,6 +446,10 @@ enum Commands {
         #[arg(long)]
         notes: Option<String>,
 
+        /// Replace the notes with this file's contents
+        #[arg(long, conflicts_with_all = ["notes", "clear_notes"])]
+        notes_file: Option<String>,
+
         #[arg(long)]
         clear_notes: bool,
 
@@ -126,554 +459,6158 @@ enum Commands {
         #[arg(long)]
         priority: Option<String>,
 
+        /// New due date, in any form `add --due` accepts
         #[arg(long)]
         due: Option<String>,
 
         #[arg(long)]
         clear_due: bool,
 
+        /// New reminder time, in any form --due accepts
+        #[arg(long)]
+        remind: Option<String>,
+
+        #[arg(long)]
+        clear_remind: bool,
+
+        /// Reminder escalation instead of the priority's: "-1d, -1h, every 30m", or off
+        #[arg(long, allow_hyphen_values = true)]
+        escalate: Option<String>,
+
+        /// Follow the priority's escalation from config.toml again
+        #[arg(long, conflicts_with = "escalate")]
+        clear_escalation: bool,
+
+        /// Item this todo tracks elsewhere: jira:ABC-123, github:owner/repo#12
+        #[arg(long = "ref")]
+        external_ref: Option<String>,
+
+        /// Link for --ref, when [links] in config.toml has no template for it
+        #[arg(long, requires = "external_ref")]
+        ref_url: Option<String>,
+
+        #[arg(long, conflicts_with = "external_ref")]
+        clear_ref: bool,
+
         /// Replace tags entirely (repeatable): --tag work --tag urgent
-        #[arg(long = "tag")]
+        #[arg(long = "tag", conflicts_with_all = ["add_tags", "remove_tags"])]
         tags: Vec<String>,
 
-        #[arg(long)]
+        #[arg(long, conflicts_with_all = ["add_tags", "remove_tags"])]
         clear_tags: bool,
+
+        /// Add a tag, keeping the others (repeatable)
+        #[arg(long = "add-tag")]
+        add_tags: Vec<String>,
+
+        /// Remove a tag, keeping the others (repeatable)
+        #[arg(long = "remove-tag")]
+        remove_tags: Vec<String>,
+
+        /// Badge shown before the title, e.g. an emoji: --badge 🔥
+        #[arg(long)]
+        badge: Option<String>,
+
+        #[arg(long)]
+        clear_badge: bool,
+
+        /// Highlight color: red|orange|yellow|green|blue|purple|gray
+        #[arg(long)]
+        color: Option<String>,
+
+        #[arg(long)]
+        clear_color: bool,
+
+        /// Estimated effort, e.g. 45m, 2h, 1h30m, 1d (8h)
+        #[arg(long)]
+        estimate: Option<String>,
+
+        #[arg(long)]
+        clear_estimate: bool,
+
+        /// Energy it takes: low|medium|high
+        #[arg(long)]
+        energy: Option<String>,
+
+        #[arg(long)]
+        clear_energy: bool,
+
+        /// Replace dependencies entirely (repeatable): --depends-on <id>
+        #[arg(long = "depends-on")]
+        depends_on: Vec<String>,
+
+        #[arg(long)]
+        clear_depends_on: bool,
+
+        /// Make it a subtask of this todo (ID or unique prefix)
+        #[arg(long, conflicts_with = "clear_parent")]
+        parent: Option<String>,
+
+        /// No longer a subtask
+        #[arg(long)]
+        clear_parent: bool,
+
+        /// Move to a workflow state of the todo's project, e.g. review
+        #[arg(long, conflicts_with = "clear_state")]
+        state: Option<String>,
+
+        /// Move back to open (not started)
+        #[arg(long)]
+        clear_state: bool,
+
+        /// Output format: text (default) or json (edited IDs, failures and
+        /// warnings)
+        #[arg(long, default_value = "text")]
+        format: String,
     },
 
     /// Export todos to a JSON file (lossless).
     Export {
-        /// Format: json (lossless) or csv (basic)
+        /// Format: json (lossless), csv (basic), markdown (open and completed
+        /// todos by project) or timesheet (CSV, or Markdown if --out ends in .md)
         #[arg(long, default_value = "json")]
         format: String,
 
         /// Output file path
         #[arg(long)]
         out: String,
+
+        /// Timesheet: only time tracked since; markdown: only todos completed
+        /// since (30d, 2w, 2026-01-01 or RFC3339)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only export todos in this project (e.g. the client to invoice)
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Strip fields before sharing: title,notes,tags,time,source,badge
+        /// or a policy from [redact] in config.toml
+        #[arg(long, value_delimiter = ',')]
+        redact: Vec<String>,
+
+        /// Same todos, same file: sort by creation time and id, and (JSON)
+        /// write keys alphabetically, for exports kept under version control
+        #[arg(long)]
+        reproducible: bool,
+
+        /// With --reproducible, leave out fields that change without edits
+        /// (updated_at, notified_at, sync state); the file can't be imported
+        #[arg(long, requires = "reproducible")]
+        drop_volatile: bool,
+
+        /// JSON: also carry the journal's entries for the exported todos
+        /// (who changed what, when); `import` adds them to the journal here
+        #[arg(long)]
+        include_history: bool,
     },
 
-    /// Import todos from a JSON file (lossless). Replaces current DB.
+    /// Import todos from a file (by default replacing the current ones)
     Import {
-        /// Format: json (lossless) or csv (basic)
+        /// Format: json (lossless), csv (basic) or todoist (a Todoist CSV or
+        /// API JSON export)
         #[arg(long, default_value = "json")]
         format: String,
 
-        /// Input file path
+        /// Input file path, or a pattern like 'exports/*.json' to merge
+        /// several exports (matched by id, newest edit of each field wins)
         #[arg(long)]
         r#in: String,
+
+        /// replace: the file becomes the list; merge: match by id and keep
+        /// the newer edit; append: add everything as new todos
+        #[arg(long, default_value = "replace")]
+        mode: String,
     },
 
-    /// Mark a todo as done
+    /// Mark todos as done
     Done {
-        /// Todo ID (full UUID or unique prefix)
-        id: String,
+        /// Todo IDs (full UUID or unique prefix); omit to pick one
+        ids: Vec<String>,
     },
 
-    /// Mark a todo as open/undone
+    /// Mark todos as open/undone
     Undone {
+        /// Todo IDs (full UUID or unique prefix); omit to pick one
+        ids: Vec<String>,
+    },
+
+    /// Make a todo wait for another one to be done
+    Block {
+        /// Todo ID (full UUID or unique prefix); omit to pick one
+        id: Option<String>,
+
+        /// The todo it waits for
+        #[arg(long)]
+        on: String,
+    },
+
+    /// Stop a todo waiting for another one (or, without --on, for any)
+    Unblock {
+        /// Todo ID (full UUID or unique prefix); omit to pick one
+        id: Option<String>,
+
+        /// The todo it no longer waits for
+        #[arg(long)]
+        on: Option<String>,
+    },
+
+    /// Attach a file (copied into the data dir) or an http(s) link to a todo
+    Attach {
         /// Todo ID (full UUID or unique prefix)
         id: String,
+
+        /// File path or URL
+        target: String,
+
+        /// Name to show (default: the file name or URL)
+        #[arg(long)]
+        label: Option<String>,
+
+        /// Link the file where it is instead of copying it
+        #[arg(long)]
+        link: bool,
     },
 
-    /// Delete a todo (destructive)
-    Delete {
+    /// Remove an attachment from a todo (and its copy, if one was made)
+    Detach {
         /// Todo ID (full UUID or unique prefix)
         id: String,
 
-        /// Skip confirmation prompt
+        /// The attachment's number in `show` or its label
+        which: String,
+    },
+
+    /// Delete todos (destructive)
+    Delete {
+        /// Todo IDs (full UUID or unique prefix); omit to pick one
+        ids: Vec<String>,
+
+        /// Skip confirmation prompt (same as the global --force)
         #[arg(long)]
         yes: bool,
     },
-}
 
-/// Peek `--debug` from args without fully running the CLI.
-///
-/// This lets `main` initialize logging at the correct level before we do real work.
-pub fn peek_debug_flag() -> bool {
-    std::env::args().any(|a| a == "--debug")
-}
+    /// Move every todo matching a query to another project, after a preview
+    Move {
+        /// Query picking the todos, as `list` takes it:
+        /// "project:Inbox and tag:work"
+        #[arg(long)]
+        filter: String,
 
-pub fn run(ctx: AppContext) -> Result<()> {
-    let cli = Cli::parse();
-    let mut out = io::stdout();
-    run_inner(ctx, cli, &mut out)
-}
+        /// Project to move them to
+        #[arg(long)]
+        to: String,
 
-fn run_inner(ctx: AppContext, cli: Cli, out: &mut dyn Write) -> Result<()> {
-    debug!(?ctx.paths, "detected application paths");
-    debug!(?ctx.config, "loaded configuration");
+        /// Skip confirmation prompt (same as the global --force)
+        #[arg(long)]
+        yes: bool,
+    },
 
-    let db_path = ctx.config.resolve_db_path(&ctx.paths);
-    let mut store = {
-        let repo = crate::infra::fs_repo::JsonFileTodoRepository::load_or_init(db_path)?;
-        Store::new(repo)
-    };
+    /// Search and replace in titles and notes, after a preview
+    Replace {
+        /// Text to look for (a pattern with --regex)
+        #[arg(long, allow_hyphen_values = true)]
+        search: String,
 
-    // Seed defaults only if DB is empty/new.
-    if store.is_empty() {
-        let defaults = crate::app::seed::default_todos();
-        store.insert_many(defaults);
-        store.repo_mut().save_atomic()?;
-    }
+        /// Replacement; with --regex, $1 or ${1} insert a group
+        #[arg(long, allow_hyphen_values = true)]
+        with: String,
 
-    handle_command(&mut store, cli.command.unwrap_or(Commands::Tui), out)
-}
+        /// Treat --search as a regular expression
+        #[arg(long)]
+        regex: bool,
 
-pub fn run_with_args(ctx: AppContext, args: impl IntoIterator<Item = String>) -> Result<()> {
-    let cli = Cli::parse_from(args);
-    let mut out = io::stdout();
-    run_inner(ctx, cli, &mut out)
-}
+        /// Match case exactly (by default "clientx" finds "ClientX" too)
+        #[arg(long)]
+        case_sensitive: bool,
 
-/// Same as run_with_args, but writes output into a provided writer (tests).
-pub fn run_with_args_to_writer(
-    ctx: AppContext,
-    args: impl IntoIterator<Item = String>,
-    out: &mut dyn Write,
-) -> Result<()> {
-    let cli = Cli::parse_from(args);
-    run_inner(ctx, cli, out)
-}
+        /// Only todos matching this query, as `list` takes it
+        #[arg(long)]
+        filter: Option<String>,
 
-fn handle_command(
-    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
-    command: Commands,
-    out: &mut dyn Write,
-) -> Result<()> {
-    match command {
-        Commands::Tui => {
-            // Placeholder until Milestone 5 (ratatui foundation).
-            println!("TUI not implemented yet (coming in Milestone 5).");
-            println!("For now try: todo add \"Buy milk\"   or   todo list");
-        }
-        Commands::Add {
-            title,
-            project,
+        /// Fields to change: title, notes (default both)
+        #[arg(long = "in", value_delimiter = ',')]
+        fields: Vec<String>,
+
+        /// Skip confirmation prompt (same as the global --force)
+        #[arg(long)]
+        yes: bool,
+    },
+
+    /// Revert the last change (add, edit, done, delete, import, ...)
+    Undo {
+        /// Show what can be undone and redone instead
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Re-apply the last undone change
+    Redo,
+
+    /// Park a todo on the someday/maybe list (hidden from lists and `next`)
+    Someday {
+        /// Todo ID (full UUID or unique prefix); omit to pick one
+        id: Option<String>,
+
+        /// Bring it back to the active list instead
+        #[arg(long)]
+        promote: bool,
+    },
+
+    /// Archive a done todo, or all todos done more than --older-than days ago
+    /// (`[archive] after_days` does this on its own)
+    Archive {
+        /// Todo ID (full UUID or unique prefix)
+        id: Option<String>,
+
+        /// Archive everything completed more than this many days ago
+        #[arg(long, conflicts_with = "id")]
+        older_than: Option<u32>,
+    },
+
+    /// Bring a todo back from the archive
+    Unarchive {
+        /// Todo ID (full UUID or unique prefix); omit to pick one
+        id: Option<String>,
+    },
+
+    /// Go through someday items that haven't been looked at in a while
+    Review {
+        /// Ask about items parked or last reviewed longer ago than this (e.g. 30d, 2w)
+        #[arg(long, default_value = "30d")]
+        every: String,
+    },
+
+    /// Send desktop notifications for reminders and due todos, once each (run
+    /// from cron or a systemd timer)
+    Notify {
+        /// Print the notifications instead of showing them
+        #[arg(long)]
+        print: bool,
+    },
+
+    /// Off-site backups of the data dir to S3, WebDAV, an rclone remote or
+    /// a folder (`[backup]` in config.toml)
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+
+    /// Write a JSON snapshot of all todos for backups and run the `[snapshot]`
+    /// upload command (with `every` set, this also happens on its own)
+    Snapshot {
+        /// List the snapshots in the folder instead
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Resolve a sync conflict by choosing each differing field
+    Resolve {
+        /// Todo ID (full UUID or unique prefix)
+        id: String,
+
+        /// Other version to compare against: a conflict file or a todo JSON file
+        /// (default: the conflict saved by the last sync)
+        #[arg(long)]
+        with: Option<String>,
+
+        /// Pre-select a field without prompting (repeatable): --pick title=other.
+        /// Without a terminal, every differing field needs one
+        #[arg(long = "pick")]
+        picks: Vec<String>,
+    },
+
+    /// Check the access journal's hash chain for tampering
+    VerifyJournal,
+
+    /// Check the data and config files for problems (currently: other users
+    /// being able to read them)
+    Doctor,
+
+    /// Serve a live overdue/today board and a small todo API over HTTP
+    Serve {
+        #[command(subcommand)]
+        action: Option<ServeAction>,
+
+        /// Expose GET-only endpoints (no adding, completing or deleting todos)
+        #[arg(long)]
+        readonly: bool,
+
+        /// Address to listen on, e.g. 0.0.0.0:8080 to allow other machines
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+
+        /// Dashboard refresh interval in seconds
+        #[arg(long, default_value_t = 30)]
+        refresh: u64,
+    },
+
+    /// Answer line-delimited JSON-RPC 2.0 on stdin/stdout (for editor plugins)
+    Rpc,
+
+    /// Describe the `serve` HTTP API
+    Api {
+        #[command(subcommand)]
+        action: ApiAction,
+    },
+
+    /// Sync with other devices by exchanging delta files
+    Sync {
+        #[command(subcommand)]
+        action: SyncAction,
+    },
+
+    /// MQTT event publishing (configure `[mqtt]` in config.toml)
+    Mqtt {
+        #[command(subcommand)]
+        action: MqttAction,
+    },
+
+    /// Database schema version and layout
+    Schema {
+        #[command(subcommand)]
+        action: SchemaAction,
+    },
+
+    /// Migrate the database file to the current schema (same as `schema upgrade`)
+    Migrate {
+        /// Target version, e.g. v2 (default: the current one)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Report what each step would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Print the active keybindings (change them under `[keybindings]`)
+    Keys {
+        /// Output format: table (default) or json
+        #[arg(long, default_value = "table")]
+        format: String,
+    },
+
+    /// Read the JSON log file (enable with `file = true` under `[log]`)
+    Logs {
+        #[command(subcommand)]
+        action: LogsAction,
+    },
+
+    /// Named daily checklists, e.g. a morning routine
+    Routine {
+        #[command(subcommand)]
+        action: RoutineAction,
+    },
+
+    /// Past versions of the database
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum RoutineAction {
+    /// Every routine with today's progress and its streak
+    List,
+
+    /// Create a routine, or replace the steps of an existing one
+    Add {
+        name: String,
+
+        /// A step, in order (repeat for each)
+        #[arg(long = "step", required = true)]
+        steps: Vec<String>,
+    },
+
+    /// Start today's checklist and print it
+    Start { name: String },
+
+    /// Tick off steps for today, by number or text
+    Check {
+        name: String,
+        #[arg(required = true)]
+        steps: Vec<String>,
+
+        /// Untick them instead
+        #[arg(long)]
+        undo: bool,
+    },
+
+    /// Today's checklist and streak stats
+    Show { name: String },
+
+    /// Delete a routine and its history
+    Remove { name: String },
+}
+
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// List the git commits of the database (`[git] autosave` or `sync init`)
+    GitLog {
+        /// Only the last N commits
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum ApiAction {
+    /// Print the OpenAPI 3 document (as `serve` exposes at /openapi.json)
+    Spec {
+        /// Leave out the routes that change todos, as `serve --readonly` does
+        #[arg(long)]
+        readonly: bool,
+
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        out: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ServeAction {
+    /// Manage the API tokens `serve` checks requests against
+    Token {
+        #[command(subcommand)]
+        action: TokenAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum MatrixAction {
+    /// Move todos into a quadrant (do|schedule|delegate|eliminate, or 1-4) by
+    /// raising or lowering their priority and pulling in or pushing out their
+    /// due date
+    Move {
+        quadrant: String,
+
+        /// Todo IDs (full UUID or unique prefix); omit to pick one
+        ids: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenAction {
+    /// Create a token and print it (only a hash is kept, so copy it now)
+    Create {
+        /// Name to tell tokens apart and revoke this one by
+        name: String,
+
+        /// What the token may do: read|write|admin
+        #[arg(long, default_value = "read")]
+        scope: String,
+
+        /// Requests per minute for this token, instead of `[serve] rate_limit`
+        /// (0 = unlimited)
+        #[arg(long)]
+        rate_limit: Option<u32>,
+    },
+
+    /// Revoke a token by name; requests using it fail from then on
+    Revoke { name: String },
+
+    /// List tokens (names and scopes; the tokens themselves aren't stored)
+    List,
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Upload a backup, then delete the target's oldest beyond `keep`
+    Push {
+        /// A name from [backup.targets], s3://bucket/prefix, a WebDAV
+        /// https:// URL, an rclone remote:path or a folder
+        #[arg(long)]
+        target: String,
+    },
+
+    /// List the backups on a target, newest first
+    List {
+        #[arg(long)]
+        target: String,
+    },
+
+    /// Download a backup and unpack its files into a folder
+    Pull {
+        #[arg(long)]
+        target: String,
+
+        /// Backup file name (default: the newest)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Folder to unpack into
+        #[arg(long)]
+        out: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum LogsAction {
+    /// Print logged entries, oldest first, across rotated files
+    Show {
+        /// Only the last N entries (0 = all)
+        #[arg(long, default_value_t = 50)]
+        lines: usize,
+
+        /// Minimum level: error|warn|info|debug|trace
+        #[arg(long)]
+        level: Option<String>,
+
+        /// Output format: text (default) or json (one entry per line)
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+
+    /// Print the last entries, and with --follow keep printing new ones
+    Tail {
+        #[arg(short = 'n', long, default_value_t = 10)]
+        lines: usize,
+
+        /// Wait for new entries until interrupted
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SchemaAction {
+    /// Print the schema version, versions import can read, and every stored field
+    Info {
+        /// Output format: table (default) or json
+        #[arg(long, default_value = "table")]
+        format: String,
+    },
+
+    /// Migrate the database file to a newer schema version (logged next to it,
+    /// the old file kept as `<db>.v<N>.bak`)
+    Upgrade {
+        /// Target version, e.g. v2 (default: the current one)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Report what each step would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MqttAction {
+    /// Publish pending overdue transitions now (run from cron)
+    Check,
+
+    /// Send a test message to `<prefix>/test`
+    Test,
+}
+
+#[derive(Subcommand)]
+enum ProjectAction {
+    /// Every project with its open and done counts
+    List {
+        /// Include archived projects
+        #[arg(long)]
+        all: bool,
+
+        /// Output format: table (default) or json
+        #[arg(long, default_value = "table")]
+        format: String,
+    },
+
+    /// Set a project's description, color or workflow states
+    Edit {
+        name: String,
+
+        #[arg(long)]
+        description: Option<String>,
+
+        #[arg(long)]
+        clear_description: bool,
+
+        /// Color: red|orange|yellow|green|blue|purple|gray
+        #[arg(long)]
+        color: Option<String>,
+
+        #[arg(long)]
+        clear_color: bool,
+
+        /// Workflow states between open and done, in board order:
+        /// --states todo,doing,review
+        #[arg(long, value_delimiter = ',', conflicts_with = "clear_states")]
+        states: Vec<String>,
+
+        /// Go back to the default workflow (in-progress)
+        #[arg(long)]
+        clear_states: bool,
+    },
+
+    /// Rename a project, moving all of its todos (renaming onto an existing
+    /// project merges the two)
+    Rename { from: String, to: String },
+
+    /// Hide a project from `project list`; its todos are kept
+    Archive { name: String },
+
+    /// Bring back an archived project
+    Unarchive { name: String },
+}
+
+#[derive(Subcommand)]
+enum StatsAction {
+    /// Tracked time per tag, project or todo
+    Time {
+        /// Group by: tag|project|todo
+        #[arg(long, default_value = "tag")]
+        group_by: String,
+
+        /// Only count time since: 30d, 2w, 12h, 2026-01-01 or RFC3339
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Output format: table (default), csv or json
+        #[arg(long, default_value = "table")]
+        format: String,
+
+        /// Write to a file instead of stdout
+        #[arg(long)]
+        out: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SyncAction {
+    /// Show this device's sync id and known peers
+    Status,
+
+    /// Write the changes a peer hasn't seen yet to a delta file
+    Export {
+        /// Peer device id (from its `sync status`); omit to export everything
+        #[arg(long)]
+        peer: Option<String>,
+
+        /// Output file path
+        #[arg(long)]
+        out: String,
+
+        /// Write an unencrypted delta even if sync keys are set up
+        #[arg(long)]
+        plaintext: bool,
+    },
+
+    /// Merge a delta file received from another device
+    Import {
+        /// Input file path
+        #[arg(long)]
+        r#in: String,
+
+        /// Accept an unencrypted delta even if sync keys are set up
+        #[arg(long)]
+        plaintext: bool,
+    },
+
+    /// Manage end-to-end encryption keys for sync
+    Keys {
+        #[command(subcommand)]
+        action: SyncKeysAction,
+    },
+
+    /// Put the database under git next to it; every change is committed from
+    /// then on, and `sync push`/`sync pull` exchange them through a remote
+    Init {
+        /// Remote repository URL to use as `origin`
+        #[arg(long)]
+        remote: Option<String>,
+    },
+
+    /// Push committed changes to the git remote
+    Push {
+        #[arg(long, default_value = "origin")]
+        remote: String,
+    },
+
+    /// Fetch the git remote's changes and merge them (newest edit of a todo wins)
+    Pull {
+        #[arg(long, default_value = "origin")]
+        remote: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SyncKeysAction {
+    /// Create this device's key pair; exports are encrypted from then on
+    Init,
+
+    /// Show this device's key fingerprint and trusted peers
+    List,
+
+    /// Write an invite carrying this device's public key, sealed with a passphrase
+    Invite {
+        /// Output file path
+        #[arg(long)]
+        out: String,
+
+        /// Shared passphrase (default: $RUSTYTODO_SYNC_PASSPHRASE)
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+
+    /// Trust the device that wrote an invite
+    Add {
+        /// Invite file path
+        #[arg(long)]
+        r#in: String,
+
+        /// Shared passphrase (default: $RUSTYTODO_SYNC_PASSPHRASE)
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+
+    /// Stop trusting a peer device
+    Remove {
+        /// Peer device id (from `sync keys list`)
+        device: String,
+    },
+}
+
+/// Peek `--debug` from args without fully running the CLI.
+///
+/// This lets `main` initialize logging at the correct level before we do real work.
+pub fn peek_debug_flag() -> bool {
+    std::env::args().any(|a| a == "--debug")
+}
+
+pub fn run(ctx: AppContext) -> Result<()> {
+    let cli = parse_cli(std::env::args_os());
+    let mut out = io::stdout();
+    run_inner(ctx, cli, &mut out)
+}
+
+/// Priority labels from config.toml (P1-P4 if they don't check out).
+fn priorities(ctx: &AppContext) -> crate::app::priorities::PriorityScheme {
+    ctx.config.priority_scheme().unwrap_or_default()
+}
+
+/// Did `err` come from writing to a closed pipe (`list | head`)? That ends
+/// the output early but isn't a failure.
+pub fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        let kind = cause
+            .downcast_ref::<io::Error>()
+            .map(io::Error::kind)
+            .or_else(|| {
+                cause
+                    .downcast_ref::<serde_json::Error>()
+                    .and_then(serde_json::Error::io_error_kind)
+            });
+        kind == Some(io::ErrorKind::BrokenPipe)
+    })
+}
+
+/// How the command was invoked, for the journal and git commit messages.
+struct Invocation {
+    /// Subcommand name (`tui` if none).
+    name: String,
+    /// The command line without the program name, secrets redacted and
+    /// arguments with spaces quoted.
+    line: String,
+}
+
+/// Parse args, keeping the subcommand name around for the journal.
+fn parse_cli<I, T>(args: I) -> (Cli, Invocation)
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let args: Vec<std::ffi::OsString> = args.into_iter().map(Into::into).collect();
+    let matches = Cli::command().get_matches_from(&args);
+    let name = matches.subcommand_name().unwrap_or("tui").to_string();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let shown = args
+        .iter()
+        .skip(1)
+        .map(|a| a.to_string_lossy().into_owned());
+    let line = crate::infra::crash::redact_args(shown)
+        .into_iter()
+        .map(|a| {
+            if a.is_empty() || a.contains(char::is_whitespace) {
+                format!("{a:?}")
+            } else {
+                a
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    (cli, Invocation { name, line })
+}
+
+fn run_inner(ctx: AppContext, cli: (Cli, Invocation), out: &mut dyn Write) -> Result<()> {
+    use crate::infra::timings;
+
+    let show = cli.0.timings;
+    timings::set_slow_threshold(std::time::Duration::from_millis(ctx.config.log.slow_ms));
+    timings::take();
+    let result = timed(Op::Command, || run_command(ctx, cli, out));
+    let recorded = timings::take();
+    if show {
+        let rows = timings::summarize(&recorded);
+        writeln!(out, "Timings:")?;
+        for (op, runs, total) in rows {
+            writeln!(
+                out,
+                "  {:<8} {runs:>3}x {:>9.2} ms",
+                op.label(),
+                total.as_secs_f64() * 1000.0
+            )?;
+        }
+    }
+    result
+}
+
+fn run_command(
+    ctx: AppContext,
+    (
+        cli,
+        Invocation {
+            name: command_name,
+            line,
+        },
+    ): (Cli, Invocation),
+    out: &mut dyn Write,
+) -> Result<()> {
+    debug!(?ctx.paths, "detected application paths");
+    debug!(?ctx.config, "loaded configuration");
+
+    let ctx = match &cli.as_of {
+        None => ctx,
+        Some(input) => match crate::app::due_input::parse_due_in(input, ctx.now(), &ctx.zone) {
+            Ok(at) => {
+                let clock = crate::testing::FixedClock::new(at.as_dt());
+                ctx.with_clock(std::sync::Arc::new(clock))
+            }
+            Err(e) => {
+                writeln!(out, "invalid --as-of {input}: {e}")?;
+                return Ok(());
+            }
+        },
+    };
+    let mut ctx = ctx;
+    if let Some(when) = &cli.color {
+        match ColorChoice::parse(when) {
+            Some(choice) => ctx.config.color = choice,
+            None => {
+                writeln!(out, "invalid --color {when} (use auto|always|never)")?;
+                return Ok(());
+            }
+        }
+    }
+
+    let db_path = ctx.config.resolve_db_path(&ctx.paths);
+    // Works on the raw file, which may be too old to load as a store.
+    match cli.command {
+        Some(Commands::Schema { action }) => return schema_command(&db_path, action, out),
+        Some(Commands::Migrate { to, dry_run }) => {
+            return schema_command(&db_path, SchemaAction::Upgrade { to, dry_run }, out);
+        }
+        _ => {}
+    }
+    let history_path = crate::infra::history_file::history_path(&db_path);
+    let mut store = {
+        use crate::infra::{
+            fs_repo::{JsonFileTodoRepository, Layout},
+            history_file,
+        };
+
+        let key = ctx.config.db_key(&db_path)?;
+        // Leftovers of a crashed run; cleaning up never stops the command.
+        let now = std::time::SystemTime::now();
+        match crate::infra::recovery::recover(&db_path, key.as_ref(), now) {
+            Ok(cleanups) => {
+                for cleanup in cleanups {
+                    warn!("{cleanup}");
+                }
+            }
+            Err(e) => warn!(error = %format!("{e:#}"), "failed cleaning up after a crash"),
+        }
+        let history = history_file::load(&history_path, key.as_ref());
+        let repo = match &cli.command {
+            // A project listing only needs that project's shard.
+            Some(Commands::List {
+                project: Some(project),
+                ..
+            }) if ctx.config.shard_by_project => {
+                JsonFileTodoRepository::load_projects(db_path.clone(), &[project.as_str()], key)?
+            }
+            _ => {
+                let mut repo = JsonFileTodoRepository::load_with_key(db_path.clone(), key)?;
+                let layout = if ctx.config.shard_by_project {
+                    Layout::Sharded
+                } else {
+                    Layout::File
+                };
+                if repo.layout() != layout {
+                    info!(?layout, "converting database layout");
+                    repo.set_layout(layout);
+                    repo.save_atomic()?;
+                }
+                if repo.is_encrypted() != ctx.config.encryption {
+                    info!(
+                        encrypt = ctx.config.encryption,
+                        "converting database encryption"
+                    );
+                    if !ctx.config.encryption {
+                        repo.set_key(None);
+                    }
+                    repo.save_atomic()?;
+                    history_file::save(&history_path, &history, repo.key())?;
+                }
+                repo
+            }
+        };
+        Store::new(repo)
+            .with_history(history)
+            .with_clock(ctx.clock.clone())
+            .with_duplicate_policy(ctx.config.duplicate_ids)
+    };
+
+    // Seed defaults only if DB is empty/new.
+    if store.is_empty() && !store.repo_mut().is_partial() {
+        let defaults = crate::app::seed::default_todos(store.now());
+        store.insert_many(defaults)?;
+        store.repo_mut().save_atomic()?;
+        store.history_mut().discard_pending();
+        // Nobody asked for the samples, so nothing to warn about either.
+        store.take_warnings();
+    }
+
+    // Archive as its own undo step, before the command looks at the list.
+    if let Some(days) = ctx.config.archive.after_days
+        && !store.repo_mut().is_partial()
+    {
+        let now = store.now();
+        let archived = store.archive_older_than(days, now);
+        if !archived.is_empty() {
+            for t in &archived {
+                info!(id = %t.id.short(), title = t.title.as_str(), "archived");
+            }
+            info!(
+                count = archived.len(),
+                after_days = days,
+                "auto-archived done todos"
+            );
+            store.repo_mut().save_atomic()?;
+            store.history_mut().commit("archive", now);
+        }
+    }
+
+    let journal = &ctx.config.journal;
+    let journaling = journal.enabled || journal.hash_chain;
+    let mqtt = &ctx.config.mqtt;
+    let command = cli.command.unwrap_or(Commands::Tui);
+
+    let hooks = &ctx.config.hooks;
+    let publishing = journaling || mqtt.enabled || hooks.enabled;
+    let before = if publishing {
+        store.list_todos()
+    } else {
+        Vec::new()
+    };
+    // Start versioning from the state before this command.
+    if ctx.config.git.autosave
+        && GitSync::open(&db_path).is_none()
+        && let Err(e) = GitSync::init(&db_path, None)
+    {
+        warn!(error = %e, "could not set up git autosave");
+    }
+    if hooks.enabled {
+        let dir = crate::infra::hooks::dir(&ctx.paths);
+        if let Err(e) = crate::infra::hooks::run_pre(hooks, &dir, &command_name, &line) {
+            writeln!(out, "{e:#}")?;
+            return Ok(());
+        }
+    }
+    handle_command(&ctx, &mut store, command, cli.force, out)?;
+    let now = store.now();
+    store.history_mut().commit(&command_name, now);
+    if store.history().is_dirty() {
+        let key = store.repo_mut().key().cloned();
+        crate::infra::history_file::save(&history_path, store.history(), key.as_ref())?;
+    }
+    if publishing {
+        let key = store.repo_mut().key().cloned();
+        publish(
+            &ctx,
+            &db_path,
+            key.as_ref(),
+            &command_name,
+            &before,
+            &store.list_todos(),
+        )?;
+    }
+
+    // The change itself is saved; a failed commit is picked up by the next one.
+    if let Some(git) = GitSync::open(&db_path)
+        && let Err(e) = git.commit(&format!("rustlytodo {line}"))
+    {
+        warn!(error = %e, "git commit failed");
+    }
+
+    // Scheduled snapshots never fail the command that happened to run.
+    if !store.repo_mut().is_partial() {
+        use crate::infra::snapshot;
+
+        let config = &ctx.config.snapshot;
+        let folder = snapshot::folder(config, &ctx.paths);
+        // Snapshot files are dated when they were really written, --as-of or not.
+        let now = time::OffsetDateTime::now_utc();
+        match snapshot::is_due(config, &folder, now) {
+            Ok(false) => {}
+            Ok(true) => {
+                let key = store.repo_mut().key().cloned();
+                match snapshot::take(config, &folder, &store.list_todos(), key.as_ref(), now) {
+                    Ok(taken) => info!(path = %taken.path.display(), "wrote scheduled snapshot"),
+                    Err(e) => warn!(error = %e, "scheduled snapshot failed"),
+                }
+            }
+            Err(e) => warn!(error = %e, "scheduled snapshot failed"),
+        }
+    }
+    Ok(())
+}
+
+/// Tell MQTT, the journal and post hooks what the command changed.
+fn publish(
+    ctx: &AppContext,
+    db_path: &std::path::Path,
+    key: Option<&crate::infra::db_crypto::DbKey>,
+    command_name: &str,
+    before: &[crate::domain::todo::Todo],
+    after: &[crate::domain::todo::Todo],
+) -> Result<()> {
+    let journal = &ctx.config.journal;
+    let mqtt = &ctx.config.mqtt;
+
+    if mqtt.enabled {
+        // A broker being down must not make the command itself fail.
+        let state = crate::infra::mqtt::state_path(&ctx.paths);
+        if let Err(e) = crate::infra::mqtt::publish_changes(mqtt, &state, before, after) {
+            warn!(error = %e, "mqtt publishing failed");
+        }
+    }
+
+    if ctx.config.hooks.enabled {
+        use crate::infra::hooks;
+
+        let events = hooks::events(before, after);
+        hooks::run_post(
+            &ctx.config.hooks,
+            &hooks::dir(&ctx.paths),
+            command_name,
+            &events,
+        );
+    }
+
+    if journal.enabled || journal.hash_chain {
+        let changes = crate::infra::journal::diff(before, after);
+        let path = crate::infra::journal::journal_path(db_path);
+        crate::infra::journal::append(&path, key, command_name, changes, journal.hash_chain)?;
+    }
+    Ok(())
+}
+
+pub fn run_with_args(ctx: AppContext, args: impl IntoIterator<Item = String>) -> Result<()> {
+    let cli = parse_cli(args);
+    let mut out = io::stdout();
+    run_inner(ctx, cli, &mut out)
+}
+
+/// Same as run_with_args, but writes output into a provided writer (tests).
+pub fn run_with_args_to_writer(
+    ctx: AppContext,
+    args: impl IntoIterator<Item = String>,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let cli = parse_cli(args);
+    run_inner(ctx, cli, out)
+}
+
+fn handle_command(
+    ctx: &AppContext,
+    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
+    command: Commands,
+    force: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    match command {
+        Commands::Tui => {
+            // Placeholder until Milestone 5 (ratatui foundation).
+            println!("TUI not implemented yet (coming in Milestone 5).");
+            println!("For now try: todo add \"Buy milk\"   or   todo list");
+        }
+        Commands::Add {
+            title,
+            raw,
+            stdin,
+            template,
+            project,
             tags,
             notes,
+            notes_file,
+            priority,
+            due,
+            remind,
+            escalate,
+            external_ref,
+            ref_url,
+            badge,
+            color,
+            estimate,
+            energy,
+            dictated,
+            depends_on,
+            parent,
+            format,
+        } => {
+            use crate::domain::escalation::Escalation;
+            use crate::domain::todo::{
+                Badge, Color, DueAt, Energy, Estimate, Notes, ProjectName, Source, Tag, Todo,
+            };
+            use std::collections::BTreeSet;
+
+            let json = match output_format(&format, out)? {
+                Some(json) => json,
+                None => return Ok(()),
+            };
+            let now = store.now();
+            if stdin || title.as_deref() == Some("-") {
+                let per_todo = template.is_some()
+                    || notes.is_some()
+                    || notes_file.is_some()
+                    || remind.is_some()
+                    || escalate.is_some()
+                    || external_ref.is_some()
+                    || badge.is_some()
+                    || color.is_some()
+                    || estimate.is_some()
+                    || energy.is_some()
+                    || dictated
+                    || !depends_on.is_empty()
+                    || parent.is_some();
+                if per_todo {
+                    writeln!(
+                        out,
+                        "`add -` takes only --project, --tag, --priority and --due"
+                    )?;
+                    return Ok(());
+                }
+                let text =
+                    io::read_to_string(io::stdin()).context("failed reading todos from stdin")?;
+                let defaults = CaptureDefaults {
+                    project,
+                    tags,
+                    priority,
+                    due,
+                };
+                return add_captured(ctx, store, &text, defaults, json, out);
+            }
+            let template = match template {
+                None => None,
+                Some(name) => match ctx.config.template(&name) {
+                    Some(t) => Some(t.clone()),
+                    None => {
+                        let known: Vec<_> = ctx.config.templates.keys().cloned().collect();
+                        writeln!(
+                            out,
+                            "unknown template {name} (configured: {})",
+                            if known.is_empty() {
+                                "none".to_string()
+                            } else {
+                                known.join(", ")
+                            }
+                        )?;
+                        return Ok(());
+                    }
+                },
+            };
+            // Template title placeholders are filled in once the project is final.
+            let (title, title_template) = match (title, &template) {
+                (Some(title), _) => (title, None),
+                (None, Some(t)) => (t.title.clone(), Some(t.title.clone())),
+                (None, None) => unreachable!("clap requires a title or --template"),
+            };
+
+            // Explicit flags win over whatever the dictation parser or inline
+            // tokens picked up.
+            let mut todo = if dictated {
+                let d = crate::app::dictation::parse(&title, now);
+                let mut todo = Todo::new_at(Title::parse_with(&d.title, &ctx.config.titles)?, now);
+                if let Some(p) = d.project {
+                    todo.project = ProjectName::parse(p)?;
+                }
+                for t in d.tags {
+                    todo.tags.insert(Tag::parse(t)?);
+                }
+                if let Some(p) = d.priority {
+                    todo.priority = p;
+                }
+                todo.due = d.due.map(DueAt::from_dt);
+                todo
+            } else if raw || title_template.is_some() {
+                Todo::new_at(Title::parse_with(title, &ctx.config.titles)?, now)
+            } else {
+                let scheme = priorities(ctx);
+                match crate::app::capture::parse(&title, &ctx.config.titles, &scheme, now) {
+                    Ok(c) => c.into_todo(now),
+                    Err(msg) => {
+                        writeln!(out, "{msg} (--raw keeps it in the title)")?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            todo.source = Some(Source::Cli);
+
+            // Then the template, then explicit flags.
+            if let Some(t) = &template {
+                if let Some(p) = &t.project {
+                    todo.project = ProjectName::parse(p)?;
+                }
+                if let Some(p) = &t.priority {
+                    todo.priority = priorities(ctx).parse(p).map_err(anyhow::Error::msg)?;
+                }
+                for tag in &t.tags {
+                    todo.tags.insert(Tag::parse(tag)?);
+                }
+            }
+
+            if let Some(p) = project {
+                todo.project = ProjectName::parse(p)?;
+            }
+
+            if let Some(n) = notes {
+                todo.notes = Some(Notes::parse(n)?)
+            } else if let Some(path) = notes_file {
+                todo.notes = Some(read_notes_file(&path)?);
+            }
+
+            if !tags.is_empty() {
+                let mut set = BTreeSet::new();
+                for t in tags {
+                    set.insert(Tag::parse(t)?);
+                }
+                todo.tags.extend(set);
+            }
+
+            if let Some(p) = priority {
+                todo.priority = priorities(ctx).parse(&p).map_err(anyhow::Error::msg)?;
+            }
+
+            if let Some(d) = due {
+                match crate::app::due_input::parse_due_in(&d, now, &ctx.zone) {
+                    Ok(due) => todo.due = Some(due),
+                    Err(msg) => {
+                        writeln!(out, "{msg}")?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            if let Some(r) = remind {
+                match crate::app::due_input::parse_due_in(&r, now, &ctx.zone) {
+                    Ok(at) => todo.remind_at = Some(at),
+                    Err(msg) => {
+                        writeln!(out, "{msg}")?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            if let Some(e) = escalate {
+                todo.escalation = Some(Escalation::parse(e)?);
+            }
+            if let Some(r) = external_ref {
+                todo.external_ref = Some(parse_external_ref(ctx, &r, ref_url.as_deref())?);
+            }
+
+            if let Some(b) = badge {
+                todo.badge = Some(Badge::parse(b)?);
+            }
+
+            if let Some(c) = color {
+                todo.color = Some(Color::parse(c)?);
+            }
+
+            if let Some(e) = estimate {
+                todo.estimate = Some(Estimate::parse(e)?);
+            }
+            if let Some(e) = energy {
+                todo.energy = Some(Energy::parse(e)?);
+            }
+
+            if let Some(t) = &template {
+                use crate::app::templates::{TemplateVars, expand};
+
+                let vars = TemplateVars {
+                    now,
+                    project: todo.project.as_str(),
+                };
+                let filled = title_template
+                    .as_deref()
+                    .map(|tpl| expand(tpl, vars))
+                    .transpose();
+                let notes = match (&todo.notes, &t.notes) {
+                    (None, Some(n)) => expand(n, vars).map(Some),
+                    _ => Ok(None),
+                };
+                match (filled, notes) {
+                    (Ok(title), Ok(notes)) => {
+                        if let Some(title) = title {
+                            todo.title = Title::parse_with(title, &ctx.config.titles)?;
+                        }
+                        if let Some(n) = notes {
+                            todo.notes = Some(Notes::parse(n)?);
+                        }
+                    }
+                    (Err(msg), _) | (_, Err(msg)) => {
+                        writeln!(out, "template {msg}")?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            match resolve_dependencies(&store.list_todos(), todo.id, &depends_on) {
+                Ok(deps) => todo.depends_on = deps,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            }
+            if let Some(p) = &parent {
+                match resolve_id_input(&store.list_todos(), p) {
+                    Ok(parent) => todo.parent = Some(parent),
+                    Err(msg) => {
+                        writeln!(out, "{msg}")?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            if dictated {
+                writeln!(
+                    out,
+                    "Understood: \"{}\" [{}] {}{}{}",
+                    todo.title.as_str(),
+                    priorities(ctx).label(todo.priority),
+                    todo.project.as_str(),
+                    todo.due
+                        .map(|d| format!(" due {}", d.format_rfc3339()))
+                        .unwrap_or_default(),
+                    todo.tags
+                        .iter()
+                        .map(|t| format!(" #{}", t.as_str()))
+                        .collect::<String>()
+                )?;
+            }
+
+            // For now we insert the constructed todo directly.
+            // Later, add/edit will be proper use-cases with validation + events.
+            let id = todo.id;
+            store.insert_todo(todo)?;
+            store.repo_mut().save_atomic()?;
+            info!("Todo added");
+            let warnings = store.take_warnings();
+            if json {
+                let report = serde_json::json!({ "id": id, "warnings": warnings });
+                writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+            } else {
+                println!("Added {}", id.short());
+                print_warnings(ctx, &warnings, out)?;
+            }
+        }
+
+        Commands::List {
+            query,
+            format,
+            fields,
+            absolute,
+            status,
+            project,
+            tag,
+            search,
+            overdue,
+            priority,
+            energy,
+            source,
+            state,
+            sort,
+            then_by,
+            desc,
+            group_by,
+            someday,
+            archived,
+            blocked,
+            ready,
+            limit,
+            offset,
+        } => {
+            use crate::app::{
+                projection::Projection,
+                query::{self, GroupBy, ListQuery, SortKey, StatusFilter},
+            };
+            use crate::domain::todo::Energy;
+
+            let now = store.now();
+
+            let format = format.trim().to_ascii_lowercase();
+            let projection = if fields.is_empty() {
+                None
+            } else if format != "json" && format != "jsonl" {
+                writeln!(out, "--fields only applies to --format json or jsonl")?;
+                return Ok(());
+            } else {
+                match Projection::parse(&fields) {
+                    Ok(p) => Some(p),
+                    Err(e) => {
+                        writeln!(out, "{e}")?;
+                        return Ok(());
+                    }
+                }
+            };
+
+            let group_by = match group_by {
+                None => None,
+                Some(_) if format == "jsonl" => {
+                    writeln!(out, "--group-by applies to --format table or json")?;
+                    return Ok(());
+                }
+                Some(g) => match GroupBy::parse(&g) {
+                    Some(g) => Some(g),
+                    None => {
+                        writeln!(out, "unknown --group-by {g} (use project|tag|priority|due)")?;
+                        return Ok(());
+                    }
+                },
+            };
+
+            // Parse status flag
+            let status = match status.as_deref().map(|s| s.trim().to_ascii_lowercase()) {
+                None => None,
+                Some(s) if s == "open" => Some(StatusFilter::Open),
+                Some(s) if s == "done" => Some(StatusFilter::Done),
+                Some(other) => {
+                    writeln!(out, "unknown --status {other} (use open|done)")?;
+                    return Ok(());
+                }
+            };
+
+            // Parse priority
+            let priority = match priority {
+                None => None,
+                Some(p) => match priorities(ctx).parse(&p) {
+                    Ok(p) => Some(p),
+                    Err(e) => {
+                        writeln!(out, "{e}")?;
+                        return Ok(());
+                    }
+                },
+            };
+            let energy = energy.map(Energy::parse).transpose()?;
+
+            // Parse sort keys
+            let Some(sort_key) = SortKey::parse(&sort) else {
+                writeln!(
+                    out,
+                    "unknown --sort {sort} (use due|priority|created|title|id|time|urgency)"
+                )?;
+                return Ok(());
+            };
+            let mut then_by_keys = Vec::new();
+            for key in &then_by {
+                match SortKey::parse(key) {
+                    Some(k) => then_by_keys.push(k),
+                    None => {
+                        writeln!(
+                            out,
+                            "unknown --then-by {key} (use due|priority|created|title|id|time|urgency)"
+                        )?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            let mut q = ListQuery {
+                status,
+                project,
+                tag,
+                search,
+                overdue,
+                priority,
+                energy,
+                source,
+                state,
+                sort: sort_key,
+                then_by: then_by_keys,
+                desc,
+                someday,
+                archived,
+                blocked: (blocked || ready).then_some(blocked),
+                after: None,
+                offset,
+                limit,
+                terms: Vec::new(),
+                urgency: ctx.config.urgency.clone(),
+            };
+            if let Err(e) = q.add_query(&query.join(" "), ctx.local(now)) {
+                writeln!(out, "{e}")?;
+                return Ok(());
+            }
+
+            let todos = timed(Op::Query, || store.find_todos(&q, now));
+            let paged = limit.is_some() || offset > 0;
+
+            // Output is written as it's produced (through a buffer) rather
+            // than built up first, so large lists pipe well.
+            match format.as_str() {
+                "json" => {
+                    let mut w = io::BufWriter::new(&mut *out);
+                    match (group_by, &projection) {
+                        (Some(by), _) => {
+                            let scheme = priorities(ctx);
+                            let groups: Vec<_> = query::group(todos, by, now)
+                                .into_iter()
+                                .map(|(key, todos)| {
+                                    let rows = match &projection {
+                                        Some(p) => serde_json::json!(
+                                            todos.iter().map(|t| p.apply(t)).collect::<Vec<_>>()
+                                        ),
+                                        None => serde_json::json!(todos),
+                                    };
+                                    serde_json::json!({
+                                        "group": key.label(|p| scheme.label(p).to_string()),
+                                        "count": todos.len(),
+                                        "todos": rows,
+                                    })
+                                })
+                                .collect();
+                            serde_json::to_writer_pretty(&mut w, &groups)
+                        }
+                        (None, Some(p)) => {
+                            let projected: Vec<_> = todos.iter().map(|t| p.apply(t)).collect();
+                            serde_json::to_writer_pretty(&mut w, &projected)
+                        }
+                        (None, None) => serde_json::to_writer_pretty(&mut w, &todos),
+                    }
+                    .with_context(|| "failed serializing todos to json")?;
+                    writeln!(w)?;
+                    w.flush()?;
+                }
+                "jsonl" => {
+                    let mut w = io::BufWriter::new(&mut *out);
+                    for todo in &todos {
+                        match &projection {
+                            Some(p) => serde_json::to_writer(&mut w, &p.apply(todo)),
+                            None => serde_json::to_writer(&mut w, todo),
+                        }
+                        .with_context(|| "failed serializing todos to json")?;
+                        writeln!(w)?;
+                    }
+                    w.flush()?;
+                }
+                "table" => {
+                    let out = &mut io::BufWriter::new(&mut *out);
+                    let shown = todos.len();
+                    if todos.is_empty() {
+                        writeln!(out, "No matching todos.")?;
+                    } else {
+                        let symbols = SymbolSet::from_config(ctx.config.symbols);
+                        let style = Style::from_config(&ctx.config);
+                        let scheme = priorities(ctx);
+                        let pw = scheme.width().max(3);
+                        // Only lists with linked todos get the REF column,
+                        // and only lists with parents the DONE column.
+                        let refs = todos.iter().any(|t| t.external_ref.is_some());
+                        let progress =
+                            crate::app::subtasks::progress_by_parent(&store.list_todos());
+                        let parents = todos.iter().any(|t| progress.contains_key(&t.id));
+                        let absolute = absolute || ctx.config.absolute_dates;
+                        let dw = if absolute { 25 } else { 14 };
+                        // Sorting by urgency shows the scores (URG column).
+                        let urgencies = (q.sort == SortKey::Urgency
+                            || q.then_by.contains(&SortKey::Urgency))
+                        .then(|| query::effective_priorities(&store.list_todos()));
+                        let header = format!(
+                            "{:<10} {:<3} {:<pw$} {:<8} {:<10} {:<18} {:<dw$} {}{}{}TITLE",
+                            "ID",
+                            "S",
+                            "P",
+                            "!",
+                            "PROJECT",
+                            "TAGS",
+                            "DUE",
+                            if refs {
+                                format!("{:<18} ", "REF")
+                            } else {
+                                String::new()
+                            },
+                            if parents {
+                                format!("{:<5} ", "DONE")
+                            } else {
+                                String::new()
+                            },
+                            if urgencies.is_some() {
+                                format!("{:<5} ", "URG")
+                            } else {
+                                String::new()
+                            }
+                        );
+                        writeln!(out, "{}", style.paint(Role::Header, &header))?;
+
+                        let groups = match group_by {
+                            Some(by) => query::group(todos, by, now)
+                                .into_iter()
+                                .map(|(key, todos)| {
+                                    let label = key.label(|p| scheme.label(p).to_string());
+                                    (Some(format!("{label} ({})", todos.len())), todos)
+                                })
+                                .collect(),
+                            None => vec![(None, todos)],
+                        };
+                        for (heading, todos) in groups {
+                            if let Some(heading) = heading {
+                                writeln!(out, "\n{}", style.paint(Role::Header, &heading))?;
+                            }
+                            for todo in todos {
+                                let overdue = todo.is_overdue(now);
+                                let due = match todo.due {
+                                    None => "-".to_string(),
+                                    Some(d) if absolute => local_rfc3339(ctx, d.as_dt()),
+                                    Some(d) => humanize::due(ctx.local(d.as_dt()), now, overdue),
+                                };
+
+                                let overdue_mark = if overdue { "OVERDUE" } else { "" };
+
+                                let tags = if todo.tags.is_empty() {
+                                    "-".to_string()
+                                } else {
+                                    todo.tags
+                                        .iter()
+                                        .map(|t| format!("#{}", t.as_str()))
+                                        .collect::<Vec<_>>()
+                                        .join(",")
+                                };
+
+                                let link = match (&todo.external_ref, refs) {
+                                    (Some(r), _) => format!("{} ", ref_cell(r, 18)),
+                                    (None, true) => format!("{:<18} ", "-"),
+                                    (None, false) => String::new(),
+                                };
+                                let done_pct = match (progress.get(&todo.id), parents) {
+                                    (Some(p), _) => format!("{:<5} ", format!("{}%", p.percent())),
+                                    (None, true) => format!("{:<5} ", "-"),
+                                    (None, false) => String::new(),
+                                };
+                                let urgency = match &urgencies {
+                                    Some(eff) => {
+                                        let p =
+                                            eff.get(&todo.id).map_or(todo.priority, |e| e.priority);
+                                        let score = query::urgency(&todo, p, now, &q.urgency);
+                                        format!("{score:<5.1} ")
+                                    }
+                                    None => String::new(),
+                                };
+
+                                // Done todos are dimmed as a whole rather than
+                                // colored cell by cell.
+                                let done = todo.status.is_done();
+                                let paint = |role: Option<Role>, cell: String| match role {
+                                    Some(role) if !done => style.paint(role, &cell),
+                                    _ => cell,
+                                };
+                                let priority = format!("{:<pw$}", scheme.label(todo.priority));
+                                let priority = match scheme
+                                    .color(todo.priority)
+                                    .and_then(|c| Rgb::parse_hex(c).ok())
+                                {
+                                    Some(Rgb(r, g, b)) if !done => style.rgb((r, g, b), &priority),
+                                    _ => paint(Style::priority_role(todo.priority), priority),
+                                };
+                                let row = format!(
+                                    "{} {:<3} {} {} {} {} {} {}{}{}{}",
+                                    paint(Some(Role::Id), format!("{:<10}", todo.id.short())),
+                                    symbols.status(&todo),
+                                    priority,
+                                    paint(Some(Role::Overdue), format!("{overdue_mark:<8}")),
+                                    paint(
+                                        Some(Role::Project),
+                                        format!("{:<10}", todo.project.as_str())
+                                    ),
+                                    paint(Some(Role::Tags), format!("{tags:<18}")),
+                                    paint(
+                                        Some(if overdue { Role::Overdue } else { Role::Due }),
+                                        format!("{due:<dw$}")
+                                    ),
+                                    link,
+                                    done_pct,
+                                    urgency,
+                                    if done {
+                                        display_title(&todo, &Style::plain())
+                                    } else {
+                                        display_title(&todo, &style)
+                                    }
+                                );
+                                if done {
+                                    writeln!(out, "{}", style.dim(Role::Done, &row))?;
+                                } else {
+                                    writeln!(out, "{row}")?;
+                                }
+                            }
+                        }
+                    }
+                    if paged {
+                        let total = store.count_todos(&q, now);
+                        if shown > 0 {
+                            writeln!(
+                                out,
+                                "Showing {}-{} of {total} matching todos.",
+                                offset + 1,
+                                offset + shown
+                            )?;
+                        } else {
+                            writeln!(out, "{total} matching todos in all.")?;
+                        }
+                    }
+                    out.flush()?;
+                }
+                other => {
+                    writeln!(out, "unknown list format: {other} (use table|json|jsonl)")?;
+                }
+            }
+        }
+
+        Commands::Next {
+            limit,
+            energy,
+            format,
+        } => {
+            use crate::app::query::next_actions;
+            use crate::domain::todo::Energy;
+
+            let max_energy = energy.map(Energy::parse).transpose()?;
+            let todos = store.list_todos();
+            let next = next_actions(&todos, limit, max_energy);
+
+            match format.trim().to_ascii_lowercase().as_str() {
+                "json" => {
+                    let items: Vec<_> = next
+                        .iter()
+                        .map(|(t, e)| {
+                            serde_json::json!({
+                                "todo": t,
+                                "effective_priority": e.priority,
+                                "inherited_from": e.via,
+                            })
+                        })
+                        .collect();
+                    let s = serde_json::to_string_pretty(&items)
+                        .with_context(|| "failed serializing next todos to json")?;
+                    writeln!(out, "{s}")?;
+                }
+                "table" => {
+                    if next.is_empty() {
+                        writeln!(out, "Nothing to do next.")?;
+                        return Ok(());
+                    }
+                    let scheme = priorities(ctx);
+                    let pw = scheme.width().max(3);
+                    writeln!(
+                        out,
+                        "{:<10} {:<pw$} {:<10} {:<25} TITLE",
+                        "ID", "P", "PROJECT", "DUE"
+                    )?;
+                    for (todo, eff) in next {
+                        let due = todo
+                            .due
+                            .map(|d| d.format_rfc3339())
+                            .unwrap_or_else(|| "-".to_string());
+                        let inherited = match eff.via {
+                            Some(via) => format!(
+                                "  (inherits {} from {}, own {})",
+                                scheme.label(eff.priority),
+                                via.short(),
+                                scheme.label(todo.priority)
+                            ),
+                            None => String::new(),
+                        };
+                        writeln!(
+                            out,
+                            "{:<10} {:<pw$} {:<10} {:<25} {}{inherited}",
+                            todo.id.short(),
+                            scheme.label(eff.priority),
+                            todo.project.as_str(),
+                            due,
+                            display_title(&todo, &Style::from_config(&ctx.config))
+                        )?;
+                    }
+                }
+                other => {
+                    writeln!(out, "unknown --format {other} (use table|json)")?;
+                }
+            }
+        }
+
+        Commands::Start { id } => {
+            use crate::app::{stats::minutes, workflow};
+            use crate::domain::todo::{TodoPatch, format_minutes};
+
+            let todos = store.list_todos();
+            let todo_id = match pick_id(&todos, id.as_deref(), "Start")? {
+                Ok(x) => x,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+
+            let now = store.now();
+            for other in todos.iter().filter(|t| t.id != todo_id) {
+                if other.running_timer().is_some() {
+                    let entry = store.stop_timer(other.id, now, None)?;
+                    writeln!(
+                        out,
+                        "Stopped {} ({})",
+                        other.id.short(),
+                        format_minutes(minutes(entry.duration(now)))
+                    )?;
+                }
+            }
+
+            // Already in a later state (say review): leave it there.
+            if let Some(todo) = todos.iter().find(|t| t.id == todo_id)
+                && !todo.status.is_done()
+                && todo.state.is_none()
+            {
+                let state = workflow::first_state(&store.projects(), todo.project.as_str());
+                store.edit_todo(
+                    todo_id,
+                    TodoPatch {
+                        state: Some(Some(state)),
+                        ..TodoPatch::default()
+                    },
+                )?;
+            }
+            let state = store
+                .list_todos()
+                .into_iter()
+                .find(|t| t.id == todo_id)
+                .and_then(|t| t.state)
+                .map(|s| format!(" ({})", s.as_str()))
+                .unwrap_or_default();
+
+            match store.start_timer(todo_id, now) {
+                Ok(()) => {
+                    store.repo_mut().save_atomic()?;
+                    writeln!(out, "Started {}{state}", todo_id.short())?;
+                }
+                Err(e) => {
+                    store.repo_mut().save_atomic()?;
+                    writeln!(out, "{e}")?;
+                }
+            }
+        }
+
+        Commands::Stop { id, note } => {
+            use crate::app::stats::minutes;
+            use crate::domain::todo::{TodoPatch, format_minutes};
+
+            let todos = store.list_todos();
+            let explicit = id.is_some();
+            let targets: Vec<_> = match id {
+                Some(id) => match resolve_id_input(&todos, &id) {
+                    Ok(x) => vec![x],
+                    Err(msg) => {
+                        writeln!(out, "{msg}")?;
+                        return Ok(());
+                    }
+                },
+                None => todos
+                    .iter()
+                    .filter(|t| t.running_timer().is_some())
+                    .map(|t| t.id)
+                    .collect(),
+            };
+            if targets.is_empty() {
+                writeln!(out, "No timer is running.")?;
+                return Ok(());
+            }
+
+            let now = store.now();
+            let mut changed = false;
+            for todo_id in targets {
+                let state = todos
+                    .iter()
+                    .find(|t| t.id == todo_id)
+                    .and_then(|t| t.state.clone());
+                match store.stop_timer(todo_id, now, note.clone()) {
+                    Ok(entry) => {
+                        changed = true;
+                        writeln!(
+                            out,
+                            "Stopped {} ({})",
+                            todo_id.short(),
+                            format_minutes(minutes(entry.duration(now)))
+                        )?;
+                    }
+                    // Nothing to stop, but it can still go back to open.
+                    Err(_) if explicit && state.is_some() => {}
+                    Err(e) => writeln!(out, "{e}")?,
+                }
+                if explicit && let Some(state) = state {
+                    store.edit_todo(
+                        todo_id,
+                        TodoPatch {
+                            state: Some(None),
+                            ..TodoPatch::default()
+                        },
+                    )?;
+                    changed = true;
+                    writeln!(
+                        out,
+                        "Moved {} from {} back to open",
+                        todo_id.short(),
+                        state.as_str()
+                    )?;
+                }
+            }
+            if changed {
+                store.repo_mut().save_atomic()?;
+            }
+        }
+
+        Commands::Project { action } => project_command(store, action, out)?,
+
+        Commands::Keys { format } => {
+            use crate::app::keymap::Action;
+
+            let map = match ctx.config.key_map() {
+                Ok(map) => map,
+                Err(e) => {
+                    writeln!(out, "{e}")?;
+                    return Ok(());
+                }
+            };
+            let keys = |a: Action| {
+                map.keys(a)
+                    .iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<_>>()
+            };
+            match format.trim().to_ascii_lowercase().as_str() {
+                "json" => {
+                    let rows: serde_json::Map<_, _> = Action::ALL
+                        .iter()
+                        .map(|&a| (a.name().to_string(), serde_json::json!(keys(a))))
+                        .collect();
+                    writeln!(out, "{}", serde_json::to_string_pretty(&rows)?)?;
+                }
+                "table" => {
+                    writeln!(out, "{:<8} {:<16} DESCRIPTION", "ACTION", "KEYS")?;
+                    for a in Action::ALL {
+                        let custom = if map.is_custom(a) { " (custom)" } else { "" };
+                        writeln!(
+                            out,
+                            "{:<8} {:<16} {}{custom}",
+                            a.name(),
+                            keys(a).join(", "),
+                            a.description()
+                        )?;
+                    }
+                }
+                other => writeln!(out, "unknown keys format: {other} (use table|json)")?,
+            }
+        }
+        Commands::Logs { action } => logs_command(ctx, action, out)?,
+        Commands::Backup { action } => backup_command(ctx, store, action, out)?,
+        Commands::Routine { action } => routine_command(ctx, action, out)?,
+        Commands::History {
+            action: HistoryAction::GitLog { limit },
+        } => {
+            let db_path = ctx.config.resolve_db_path(&ctx.paths);
+            let Some(git) = GitSync::open(&db_path) else {
+                writeln!(
+                    out,
+                    "No git history: set autosave = true under [git] in config.toml."
+                )?;
+                return Ok(());
+            };
+            for entry in git.log(limit)? {
+                writeln!(out, "{}  {}  {}", entry.hash, entry.date, entry.message)?;
+            }
+        }
+
+        Commands::Board { project, limit } => board(
+            store,
+            project.as_deref(),
+            limit,
+            &Style::from_config(&ctx.config),
+            out,
+        )?,
+
+        Commands::Matrix {
+            action,
+            within,
+            project,
+            limit,
+            format,
+        } => {
+            let within = time::Duration::days(i64::from(within));
+            match action {
+                None => matrix(ctx, store, within, project.as_deref(), limit, &format, out)?,
+                Some(MatrixAction::Move { quadrant, ids }) => {
+                    matrix_move(ctx, store, within, &quadrant, &ids, out)?
+                }
+            }
+        }
+
+        Commands::Stats {
+            action: None,
+            days,
+            format,
+        } => stats_overview(store, days, &format, out)?,
+        Commands::Stats {
+            action: Some(action),
+            ..
+        } => match action {
+            StatsAction::Time {
+                group_by,
+                since,
+                format,
+                out: path,
+            } => {
+                use crate::app::stats::{TimeGroup, minutes, parse_since, time_by, total_tracked};
+                use crate::domain::todo::format_minutes;
+
+                let Some(group) = TimeGroup::parse(&group_by) else {
+                    writeln!(out, "unknown --group-by {group_by} (use tag|project|todo)")?;
+                    return Ok(());
+                };
+                let now = store.now();
+                let since = match since {
+                    None => None,
+                    Some(s) => match parse_since(&s, now) {
+                        Some(dt) => Some(dt),
+                        None => {
+                            writeln!(
+                                out,
+                                "invalid --since {s} (use e.g. 30d, 2w, 2026-01-01 or RFC3339)"
+                            )?;
+                            return Ok(());
+                        }
+                    },
+                };
+
+                let todos = store.list_todos();
+                let rows = time_by(&todos, group, since, now);
+
+                let mut file;
+                let sink: &mut dyn Write = match &path {
+                    Some(p) => {
+                        file = std::fs::File::create(p)
+                            .with_context(|| format!("failed creating file: {p}"))?;
+                        &mut file
+                    }
+                    None => out,
+                };
+
+                match format.trim().to_ascii_lowercase().as_str() {
+                    "csv" => crate::infra::csv_io::write_time_rows(&mut *sink, group, &rows)?,
+                    "json" => {
+                        let items: Vec<_> = rows
+                            .iter()
+                            .map(|r| {
+                                serde_json::json!({
+                                    group.label(): r.key,
+                                    "minutes": minutes(r.tracked),
+                                    "entries": r.entries,
+                                    "todos": r.todos,
+                                })
+                            })
+                            .collect();
+                        let s = serde_json::to_string_pretty(&items)
+                            .with_context(|| "failed serializing time stats to json")?;
+                        writeln!(sink, "{s}")?;
+                    }
+                    "table" => {
+                        if rows.is_empty() {
+                            writeln!(sink, "No tracked time.")?;
+                        } else {
+                            writeln!(
+                                sink,
+                                "{:<30} {:<10} {:<8} TODOS",
+                                group.label().to_ascii_uppercase(),
+                                "TIME",
+                                "ENTRIES"
+                            )?;
+                            for r in &rows {
+                                writeln!(
+                                    sink,
+                                    "{:<30} {:<10} {:<8} {}",
+                                    r.key,
+                                    format_minutes(minutes(r.tracked)),
+                                    r.entries,
+                                    r.todos
+                                )?;
+                            }
+                            let total = format_minutes(minutes(total_tracked(&todos, since, now)));
+                            writeln!(sink, "Total: {total}")?;
+                            if group == TimeGroup::Tag {
+                                writeln!(sink, "(todos with several tags count under each tag)")?;
+                            }
+                        }
+                    }
+                    other => {
+                        writeln!(out, "unknown --format {other} (use table|csv|json)")?;
+                        return Ok(());
+                    }
+                }
+
+                if let Some(p) = path {
+                    writeln!(out, "Wrote time stats to {p}")?;
+                }
+            }
+        },
+
+        Commands::CriticalPath { project } => {
+            use crate::app::planning::critical_path;
+            use crate::domain::todo::format_minutes;
+
+            let todos = store.list_todos();
+            let plan = critical_path(&todos, project.as_deref())?;
+            if plan.tasks.is_empty() {
+                writeln!(out, "No open todos to plan.")?;
+                return Ok(());
+            }
+
+            writeln!(
+                out,
+                "Critical path: {} todos, {}",
+                plan.path.len(),
+                format_minutes(plan.total)
+            )?;
+            for id in &plan.path {
+                if let Some(t) = plan.tasks.iter().find(|t| t.id == *id) {
+                    writeln!(
+                        out,
+                        "  {:<10} {:<8} {}",
+                        id.short(),
+                        format_minutes(t.duration),
+                        t.title
+                    )?;
+                }
+            }
+
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{:<2}{:<10} {:<8} {:<8} {:<8} {:<8} TITLE",
+                "", "ID", "EST", "START", "FINISH", "SLACK"
+            )?;
+            for t in &plan.tasks {
+                writeln!(
+                    out,
+                    "{:<2}{:<10} {:<8} {:<8} {:<8} {:<8} {}",
+                    if t.critical { "*" } else { "" },
+                    t.id.short(),
+                    format_minutes(t.duration),
+                    format_minutes(t.earliest_start),
+                    format_minutes(t.earliest_finish),
+                    format_minutes(t.slack),
+                    t.title
+                )?;
+            }
+            if plan.unestimated > 0 {
+                writeln!(
+                    out,
+                    "{} todo(s) have no estimate and count as 0m (set one with `edit --estimate`).",
+                    plan.unestimated
+                )?;
+            }
+        }
+
+        Commands::Report {
+            name,
+            list,
+            by,
+            since,
+            project,
+            out: path,
+        } => {
+            use crate::app::report::{Grouping, render};
+            use crate::app::stats::parse_since;
+
+            if list {
+                if ctx.config.reports.is_empty() {
+                    writeln!(
+                        out,
+                        "No reports defined (add [reports.<name>] tables to config.toml)"
+                    )?;
+                }
+                let width = ctx.config.reports.keys().map(|n| n.len()).max();
+                for (name, report) in &ctx.config.reports {
+                    let about = report.description.as_deref().unwrap_or(&report.filter);
+                    writeln!(out, "{name:<w$}  {about}", w = width.unwrap_or(0))?;
+                }
+                return Ok(());
+            }
+            if let Some(name) = name {
+                return custom_report(ctx, store, &name, path.as_deref(), out);
+            }
+
+            let Some(grouping) = Grouping::parse(&by) else {
+                writeln!(out, "unknown --by {by} (use project|day)")?;
+                return Ok(());
+            };
+            let Some(since_dt) = parse_since(&since, store.now()) else {
+                writeln!(
+                    out,
+                    "invalid --since {since} (use e.g. 1d, 1w, 2026-01-01 or RFC3339)"
+                )?;
+                return Ok(());
+            };
+            let mut todos = store.list_todos();
+            if let Some(p) = &project {
+                todos.retain(|t| t.project.as_str().eq_ignore_ascii_case(p.trim()));
+            }
+            let title = match &project {
+                Some(p) => format!("Report: {}", p.trim()),
+                None => "Report".to_string(),
+            };
+            let (md, counts) = render(&title, &todos, grouping, Some(since_dt));
+
+            match &path {
+                Some(p) => {
+                    std::fs::write(p, md).with_context(|| format!("failed writing report: {p}"))?;
+                    writeln!(
+                        out,
+                        "Wrote report ({} completed, {} open) to {p}",
+                        counts.completed, counts.open
+                    )?;
+                }
+                None => write!(out, "{md}")?,
+            }
+        }
+
+        Commands::Forecast {
+            window,
+            project,
+            format,
+        } => {
+            use crate::app::{forecast::forecast, stats::parse_since};
+            use time::format_description::well_known::Rfc3339;
+
+            let now = store.now();
+            let Some(since) = parse_since(&window, now).filter(|s| *s < now) else {
+                writeln!(out, "invalid --window {window} (use e.g. 28d, 8w)")?;
+                return Ok(());
+            };
+            let mut rows = forecast(&store.list_todos(), now - since, now);
+            if let Some(p) = &project {
+                rows.retain(|r| r.project.eq_ignore_ascii_case(p.trim()));
+            }
+            let fmt = |at: Option<time::OffsetDateTime>| at.and_then(|d| d.format(&Rfc3339).ok());
+
+            match format.trim().to_ascii_lowercase().as_str() {
+                "json" => {
+                    let items: Vec<_> = rows
+                        .iter()
+                        .map(|r| {
+                            serde_json::json!({
+                                "project": r.project,
+                                "open": r.open,
+                                "completed": r.completed,
+                                "per_day": r.per_day,
+                                "clear_by": fmt(r.clear_by),
+                                "dated": r.dated,
+                                "at_risk": r.at_risk.iter().map(|a| serde_json::json!({
+                                    "id": a.id,
+                                    "title": a.title,
+                                    "due": fmt(Some(a.due)),
+                                    "expected": fmt(a.expected),
+                                })).collect::<Vec<_>>(),
+                            })
+                        })
+                        .collect();
+                    let s = serde_json::to_string_pretty(&items)
+                        .with_context(|| "failed serializing forecast to json")?;
+                    writeln!(out, "{s}")?;
+                }
+                "table" => {
+                    if rows.is_empty() {
+                        writeln!(out, "No open todos to forecast.")?;
+                        return Ok(());
+                    }
+                    writeln!(
+                        out,
+                        "{:<16} {:>5} {:>9} {:<12} {:>8}",
+                        "PROJECT", "OPEN", "PER WEEK", "CLEAR BY", "AT RISK"
+                    )?;
+                    for r in &rows {
+                        let clear_by = r
+                            .clear_by
+                            .map_or_else(|| "never".to_string(), |d| d.date().to_string());
+                        writeln!(
+                            out,
+                            "{:<16} {:>5} {:>9.1} {:<12} {:>8}",
+                            r.project,
+                            r.open,
+                            r.per_day * 7.0,
+                            clear_by,
+                            format!("{}/{}", r.at_risk.len(), r.dated)
+                        )?;
+                    }
+                    for r in rows.iter().filter(|r| !r.at_risk.is_empty()) {
+                        writeln!(out)?;
+                        writeln!(out, "{}: due dates the current pace misses", r.project)?;
+                        for a in &r.at_risk {
+                            let expected = a
+                                .expected
+                                .map_or_else(|| "never".to_string(), |d| d.date().to_string());
+                            writeln!(
+                                out,
+                                "  {:<10} due {}  expected {}  {}",
+                                a.id.short(),
+                                a.due.date(),
+                                expected,
+                                a.title
+                            )?;
+                        }
+                    }
+                    writeln!(out)?;
+                    writeln!(
+                        out,
+                        "Based on todos completed in the last {window}, worked in priority order."
+                    )?;
+                }
+                other => {
+                    writeln!(out, "unknown forecast format: {other} (use table|json)")?;
+                }
+            }
+        }
+
+        Commands::Show {
+            id,
+            format,
+            absolute,
+            notes_only,
+        } => {
+            let todos = store.list_todos();
+            let todo_id = match pick_id(&todos, id.as_deref(), "Show")? {
+                Ok(x) => x,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+
+            let Some(todo) = store.repo_mut().get(todo_id) else {
+                writeln!(out, "todo not found")?;
+                return Ok(());
+            };
+            if notes_only {
+                if let Some(n) = &todo.notes {
+                    writeln!(out, "{}", n.as_str())?;
+                }
+                return Ok(());
+            }
+
+            match format.trim().to_ascii_lowercase().as_str() {
+                "json" => {
+                    let s = serde_json::to_string_pretty(&todo)
+                        .with_context(|| "failed serializing todo to json")?;
+                    writeln!(out, "{s}")?;
+                }
+                "table" => {
+                    // Human friendly details
+                    writeln!(out, "ID:       {}", todo.id.as_uuid_str())?;
+                    writeln!(out, "Short:    {}", todo.id.short())?;
+                    let symbols = SymbolSet::from_config(ctx.config.symbols);
+                    writeln!(
+                        out,
+                        "Status:   {} {}",
+                        symbols.status(&todo),
+                        match &todo.state {
+                            _ if todo.status.is_done() => "Done".to_string(),
+                            Some(state) => format!("Open ({})", state.as_str()),
+                            None => "Open".to_string(),
+                        }
+                    )?;
+                    writeln!(out, "Priority: {}", priorities(ctx).label(todo.priority))?;
+                    writeln!(out, "Project:  {}", todo.project.as_str())?;
+                    let absolute = absolute || ctx.config.absolute_dates;
+                    let due = match todo.due {
+                        None => "-".to_string(),
+                        Some(d) if absolute => local_rfc3339(ctx, d.as_dt()),
+                        Some(d) => format!(
+                            "{} ({})",
+                            humanize::due(
+                                ctx.local(d.as_dt()),
+                                store.now(),
+                                todo.is_overdue(store.now())
+                            ),
+                            local_rfc3339(ctx, d.as_dt())
+                        ),
+                    };
+                    writeln!(out, "Due:      {due}")?;
+
+                    let tags = if todo.tags.is_empty() {
+                        "-".to_string()
+                    } else {
+                        todo.tags
+                            .iter()
+                            .map(|t| format!("#{}", t.as_str()))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    writeln!(out, "Tags:     {tags}")?;
+                    if let Some(r) = todo.remind_at {
+                        writeln!(out, "Remind:   {}", local_rfc3339(ctx, r.as_dt()))?;
+                    }
+                    if let Some(e) = &todo.escalation {
+                        writeln!(out, "Escalate: {e}")?;
+                    }
+                    if let Some(r) = &todo.external_ref {
+                        match &r.url {
+                            Some(url) => writeln!(out, "Ref:      {r} {url}")?,
+                            None => writeln!(out, "Ref:      {r}")?,
+                        }
+                    }
+
+                    writeln!(
+                        out,
+                        "Title:    {}",
+                        display_title(&todo, &Style::from_config(&ctx.config))
+                    )?;
+                    if let Some(at) = todo.someday {
+                        writeln!(out, "Someday:  since {}", at.date())?;
+                    }
+                    if let Some(at) = todo.archived {
+                        writeln!(out, "Archived: {}", at.date())?;
+                    }
+                    if let Some(c) = todo.color {
+                        writeln!(out, "Color:    {}", c.label())?;
+                    }
+                    if let Some(e) = todo.energy {
+                        writeln!(out, "Energy:   {}", e.label())?;
+                    }
+                    if let Some(parent) = todo.parent {
+                        match todos.iter().find(|t| t.id == parent) {
+                            Some(p) => {
+                                writeln!(out, "Parent:   {} {}", parent.short(), p.title.as_str())?
+                            }
+                            None => writeln!(out, "Parent:   {} (deleted)", parent.short())?,
+                        }
+                    }
+                    if let Some(p) = crate::app::subtasks::progress(&todos, todo.id) {
+                        writeln!(
+                            out,
+                            "Subtasks: {}/{} done ({}%)",
+                            p.done,
+                            p.total,
+                            p.percent()
+                        )?;
+                    }
+                    if !todo.time_entries.is_empty() {
+                        use crate::app::stats::minutes;
+                        use crate::domain::{todo::format_minutes, tracking};
+
+                        let now = store.now();
+                        let total = tracking::total(&todo.time_entries, None, now);
+                        let running = if todo.running_timer().is_some() {
+                            " (timer running)"
+                        } else {
+                            ""
+                        };
+                        writeln!(out, "Tracked:  {}{running}", format_minutes(minutes(total)))?;
+                    }
+                    if let Some(src) = &todo.source {
+                        writeln!(out, "Source:   {src}")?;
+                    }
+                    if !todo.attachments.is_empty() {
+                        use crate::domain::attachment::AttachmentTarget;
+
+                        let dir = crate::infra::attachments::dir(&ctx.paths);
+                        writeln!(out, "Attachments:")?;
+                        for (i, a) in todo.attachments.iter().enumerate() {
+                            let place = match (
+                                &a.target,
+                                crate::infra::attachments::locate(&dir, &a.target),
+                            ) {
+                                (AttachmentTarget::Url(url), _) => url.clone(),
+                                (_, Some(path)) if path.exists() => path.display().to_string(),
+                                (_, path) => {
+                                    format!("{} (missing)", path.unwrap_or_default().display())
+                                }
+                            };
+                            writeln!(out, "  {}. {}  {place}", i + 1, a.label)?;
+                        }
+                    }
+                    if let Some(n) = &todo.notes {
+                        writeln!(out, "Notes:\n{}\n", n.as_str())?;
+                    }
+                }
+                other => {
+                    writeln!(out, "unknown show format: {other} (use table|json)")?;
+                }
+            }
+        }
+
+        Commands::Edit {
+            ids,
+            bulk,
+            filter,
+            title,
+            notes,
+            notes_file,
+            clear_notes,
+            project,
             priority,
             due,
+            clear_due,
+            remind,
+            clear_remind,
+            escalate,
+            clear_escalation,
+            external_ref,
+            ref_url,
+            clear_ref,
+            tags,
+            clear_tags,
+            add_tags,
+            remove_tags,
+            badge,
+            clear_badge,
+            color,
+            clear_color,
+            estimate,
+            clear_estimate,
+            energy,
+            clear_energy,
+            depends_on,
+            clear_depends_on,
+            parent,
+            clear_parent,
+            state,
+            clear_state,
+            format,
+        } => {
+            use crate::domain::escalation::Escalation;
+            use crate::domain::todo::{
+                Badge, Color, Energy, Estimate, Notes, ProjectName, Tag, TagsPatch, Title,
+                TodoPatch, WorkflowState,
+            };
+            use std::collections::BTreeSet;
+
+            if bulk {
+                bulk_edit(ctx, store, &filter, force, out)?;
+                return print_warnings(ctx, &store.take_warnings(), out);
+            }
+            let json = match output_format(&format, out)? {
+                Some(json) => json,
+                None => return Ok(()),
+            };
+
+            let todos = store.list_todos();
+            let targets = resolve_ids(&todos, &ids, "Edit", out)?;
+            if targets.is_empty() {
+                return Ok(());
+            }
+
+            let mut patch = TodoPatch::default();
+
+            if let Some(t) = title {
+                patch.title = Some(Title::parse_with(t, &ctx.config.titles)?)
+            }
+
+            if clear_notes {
+                patch.notes = Some(None);
+            } else if let Some(n) = notes {
+                patch.notes = Some(Some(Notes::parse(n)?));
+            } else if let Some(path) = notes_file {
+                patch.notes = Some(Some(read_notes_file(&path)?));
+            }
+
+            if let Some(p) = project {
+                patch.project = Some(ProjectName::parse(p)?);
+            }
+            if let Some(p) = priority {
+                patch.priority = Some(priorities(ctx).parse(&p).map_err(anyhow::Error::msg)?);
+            }
+
+            if clear_due {
+                patch.due = Some(None);
+            } else if let Some(d) = due {
+                match crate::app::due_input::parse_due_in(&d, store.now(), &ctx.zone) {
+                    Ok(due) => patch.due = Some(Some(due)),
+                    Err(msg) => {
+                        writeln!(out, "{msg}")?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            if clear_remind {
+                patch.remind_at = Some(None);
+            } else if let Some(r) = remind {
+                match crate::app::due_input::parse_due_in(&r, store.now(), &ctx.zone) {
+                    Ok(at) => patch.remind_at = Some(Some(at)),
+                    Err(msg) => {
+                        writeln!(out, "{msg}")?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            if clear_escalation {
+                patch.escalation = Some(None);
+            } else if let Some(e) = escalate {
+                patch.escalation = Some(Some(Escalation::parse(e)?));
+            }
+            if clear_ref {
+                patch.external_ref = Some(None);
+            } else if let Some(r) = external_ref {
+                patch.external_ref = Some(Some(parse_external_ref(ctx, &r, ref_url.as_deref())?));
+            }
+
+            let parse_tags = |tags: Vec<String>| -> Result<BTreeSet<Tag>> {
+                Ok(tags.into_iter().map(Tag::parse).collect::<Result<_, _>>()?)
+            };
+            if clear_tags {
+                patch.tags = Some(TagsPatch::Replace(BTreeSet::new()));
+            } else if !tags.is_empty() {
+                patch.tags = Some(TagsPatch::Replace(parse_tags(tags)?));
+            } else if !add_tags.is_empty() || !remove_tags.is_empty() {
+                patch.tags = Some(TagsPatch::Change {
+                    add: parse_tags(add_tags)?,
+                    remove: parse_tags(remove_tags)?,
+                });
+            }
+
+            if clear_badge {
+                patch.badge = Some(None);
+            } else if let Some(b) = badge {
+                patch.badge = Some(Some(Badge::parse(b)?));
+            }
+
+            if clear_color {
+                patch.color = Some(None);
+            } else if let Some(c) = color {
+                patch.color = Some(Some(Color::parse(c)?));
+            }
+
+            if clear_estimate {
+                patch.estimate = Some(None);
+            } else if let Some(e) = estimate {
+                patch.estimate = Some(Some(Estimate::parse(e)?));
+            }
+            if clear_energy {
+                patch.energy = Some(None);
+            } else if let Some(e) = energy {
+                patch.energy = Some(Some(Energy::parse(e)?));
+            }
+
+            if clear_depends_on {
+                patch.depends_on = Some(BTreeSet::new());
+            }
+            if clear_parent {
+                patch.parent = Some(None);
+            }
+
+            if clear_state {
+                patch.state = Some(None);
+            }
+            let state = state.map(WorkflowState::parse).transpose()?;
+            let projects = store.projects();
+
+            // With --format json, failures are reported in the JSON instead.
+            let mut edited = Vec::new();
+            let mut failed = Vec::new();
+            let mut fail = |id: &str, msg: String, out: &mut dyn Write| -> Result<()> {
+                if json {
+                    failed.push(serde_json::json!({ "id": id, "error": msg }));
+                } else {
+                    writeln!(out, "{id}: {msg}")?;
+                }
+                Ok(())
+            };
+            for (id, todo_id) in &targets {
+                let mut patch = patch.clone();
+                if let Some(state) = &state {
+                    // Checked against the project the todo ends up in.
+                    let project = match &patch.project {
+                        Some(p) => p.clone(),
+                        None => match todos.iter().find(|t| t.id == *todo_id) {
+                            Some(t) => t.project.clone(),
+                            None => continue,
+                        },
+                    };
+                    let states = crate::app::workflow::states_for(&projects, project.as_str());
+                    if !states.contains(state) {
+                        let names: Vec<_> = states.iter().map(WorkflowState::as_str).collect();
+                        let msg = format!(
+                            "{} has no state {} (states: {})",
+                            project.as_str(),
+                            state.as_str(),
+                            names.join(", ")
+                        );
+                        fail(id, msg, out)?;
+                        continue;
+                    }
+                    patch.state = Some(Some(state.clone()));
+                }
+                if !clear_depends_on && !depends_on.is_empty() {
+                    match resolve_dependencies(&todos, *todo_id, &depends_on) {
+                        Ok(deps) => patch.depends_on = Some(deps),
+                        Err(msg) => {
+                            fail(id, msg, out)?;
+                            continue;
+                        }
+                    }
+                }
+                if let Some(p) = &parent {
+                    let checked = resolve_id_input(&todos, p).and_then(|parent| {
+                        crate::app::subtasks::check_parent(&todos, *todo_id, parent)
+                            .map(|()| parent)
+                    });
+                    match checked {
+                        Ok(parent) => patch.parent = Some(Some(parent)),
+                        Err(msg) => {
+                            fail(id, msg, out)?;
+                            continue;
+                        }
+                    }
+                }
+                match store.edit_todo(*todo_id, patch) {
+                    Ok(true) => {
+                        edited.push(*todo_id);
+                        if !json {
+                            writeln!(out, "Edited {id}")?;
+                        }
+                    }
+                    Ok(false) if !json => writeln!(out, "Failed to edit {id}")?,
+                    Ok(false) => fail(id, "failed to edit".to_string(), out)?,
+                    Err(e) if e.is::<crate::app::errors::AppError>() => {
+                        fail(id, e.to_string(), out)?
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if !edited.is_empty() {
+                store.repo_mut().save_atomic()?;
+            }
+            let warnings = store.take_warnings();
+            if json {
+                let report = serde_json::json!({
+                    "edited": edited,
+                    "failed": failed,
+                    "warnings": warnings,
+                });
+                writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+            } else {
+                print_warnings(ctx, &warnings, out)?;
+            }
+        }
+
+        Commands::Done { ids } => {
+            let todos = store.list_todos();
+            let mut changed = 0;
+            for (id, todo_id) in resolve_ids(&todos, &ids, "Done", out)? {
+                match store.mark_done(todo_id) {
+                    Ok(()) => {
+                        changed += 1;
+                        writeln!(out, "Done {id}")?;
+                    }
+                    Err(e) => {
+                        writeln!(out, "{id}: {e}")?;
+                        continue;
+                    }
+                }
+                if ctx.config.complete_parents {
+                    let todos = store.list_todos();
+                    for parent in crate::app::subtasks::parents_to_complete(&todos, todo_id) {
+                        store.mark_done(parent)?;
+                        writeln!(out, "Done {} (all subtasks done)", parent.short())?;
+                    }
+                }
+            }
+            if changed > 0 {
+                store.repo_mut().save_atomic()?;
+            }
+        }
+
+        Commands::Undone { ids } => {
+            let todos = store.list_todos();
+            let mut changed = 0;
+            for (id, todo_id) in resolve_ids(&todos, &ids, "Undone", out)? {
+                match store.mark_open(todo_id) {
+                    Ok(()) => {
+                        changed += 1;
+                        writeln!(out, "Undone {id}")?;
+                    }
+                    Err(e) => writeln!(out, "{id}: {e}")?,
+                }
+            }
+            if changed > 0 {
+                store.repo_mut().save_atomic()?;
+            }
+        }
+
+        Commands::Move { filter, to, yes } => {
+            move_todos(ctx, store, &filter, &to, force || yes, out)?
+        }
+
+        Commands::Replace {
+            search,
+            with,
+            regex,
+            case_sensitive,
+            filter,
+            fields,
+            yes,
+        } => {
+            let options = ReplaceOptions {
+                search: &search,
+                with: &with,
+                regex,
+                case_sensitive,
+                filter: filter.as_deref(),
+                fields: &fields,
+            };
+            replace_text(ctx, store, options, force || yes, out)?
+        }
+
+        Commands::Delete { ids, yes } => {
+            let todos = store.list_todos();
+            let targets = resolve_ids(&todos, &ids, "Delete", out)?;
+            let question = match targets.as_slice() {
+                [] => return Ok(()),
+                [(_, todo_id)] => {
+                    let title = todos
+                        .iter()
+                        .find(|t| t.id == *todo_id)
+                        .map(|t| t.title.as_str().to_string())
+                        .unwrap_or_default();
+                    format!("Delete {} \"{title}\"?", todo_id.short())
+                }
+                many => format!("Delete {} todos?", many.len()),
+            };
+            if !confirmed(ctx, "delete", force || yes, &question, out)? {
+                return Ok(());
+            }
+
+            let mut deleted = 0;
+            for (id, todo_id) in targets {
+                match store.delete(todo_id) {
+                    Ok(()) => {
+                        deleted += 1;
+                        writeln!(out, "Deleted {id}")?;
+                    }
+                    Err(e) => writeln!(out, "{id}: {e}")?,
+                }
+            }
+            if deleted > 0 {
+                store.repo_mut().save_atomic()?;
+            }
+        }
+
+        Commands::Undo { list: true } => {
+            let history = store.history();
+            let steps = history
+                .undo_steps()
+                .iter()
+                .rev()
+                .map(|s| ("undo", s))
+                .chain(history.redo_steps().iter().rev().map(|s| ("redo", s)));
+            let mut any = false;
+            for (kind, step) in steps {
+                any = true;
+                writeln!(
+                    out,
+                    "{kind}  {}  {:<12} {} todo(s)",
+                    step.at.date(),
+                    step.label,
+                    step.changes.len()
+                )?;
+            }
+            if !any {
+                writeln!(out, "Nothing to undo")?;
+            }
+        }
+
+        Commands::Undo { list: false } => match store.undo() {
+            Some(step) => {
+                store.repo_mut().save_atomic()?;
+                writeln!(out, "Undid {} ({} todo(s))", step.label, step.changes.len())?;
+            }
+            None => writeln!(out, "Nothing to undo")?,
+        },
+
+        Commands::Redo => match store.redo() {
+            Some(step) => {
+                store.repo_mut().save_atomic()?;
+                writeln!(out, "Redid {} ({} todo(s))", step.label, step.changes.len())?;
+            }
+            None => writeln!(out, "Nothing to redo")?,
+        },
+
+        Commands::Block { id, on } => {
+            let todos = store.list_todos();
+            let todo_id = match pick_id(&todos, id.as_deref(), "Block")? {
+                Ok(x) => x,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+            let on = match resolve_id_input(&todos, &on) {
+                Ok(x) => x,
+                Err(msg) => {
+                    writeln!(out, "--on {on}: {msg}")?;
+                    return Ok(());
+                }
+            };
+            match store.block(todo_id, on) {
+                Ok(true) => {
+                    store.repo_mut().save_atomic()?;
+                    writeln!(out, "{} now waits for {}", todo_id.short(), on.short())?;
+                }
+                Ok(false) => writeln!(out, "{} already waits for {}", todo_id.short(), on.short())?,
+                Err(e) => writeln!(out, "{e}")?,
+            }
+        }
+
+        Commands::Unblock { id, on } => {
+            let todos = store.list_todos();
+            let todo_id = match pick_id(&todos, id.as_deref(), "Unblock")? {
+                Ok(x) => x,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+            let on = match on
+                .as_deref()
+                .map(|on| resolve_id_input(&todos, on))
+                .transpose()
+            {
+                Ok(x) => x,
+                Err(msg) => {
+                    writeln!(out, "--on: {msg}")?;
+                    return Ok(());
+                }
+            };
+            match store.unblock(todo_id, on) {
+                Ok(0) => match on {
+                    Some(on) => {
+                        writeln!(out, "{} does not wait for {}", todo_id.short(), on.short())?
+                    }
+                    None => writeln!(out, "{} does not wait for anything", todo_id.short())?,
+                },
+                Ok(n) => {
+                    store.repo_mut().save_atomic()?;
+                    writeln!(out, "Unblocked {} from {n} todo(s)", todo_id.short())?;
+                }
+                Err(e) => writeln!(out, "{e}")?,
+            }
+        }
+
+        Commands::Attach {
+            id,
+            target,
+            label,
+            link,
+        } => {
+            use crate::domain::{
+                attachment::{Attachment, AttachmentTarget},
+                todo::TodoPatch,
+            };
+            use crate::infra::attachments;
+
+            let todos = store.list_todos();
+            let todo = match resolve_id_input(&todos, &id) {
+                Ok(todo_id) => todos.iter().find(|t| t.id == todo_id).cloned(),
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+            let Some(todo) = todo else {
+                writeln!(out, "todo not found")?;
+                return Ok(());
+            };
+            let is_url = target.contains("://");
+            if is_url && link {
+                writeln!(out, "--link is for files; links are never copied")?;
+                return Ok(());
+            }
+            let path = std::path::PathBuf::from(&target);
+            if !is_url && !path.is_file() {
+                writeln!(out, "no such file: {target}")?;
+                return Ok(());
+            }
+            let guess = if is_url {
+                AttachmentTarget::Url(target.clone())
+            } else {
+                AttachmentTarget::Path(path.clone())
+            };
+            let mut attachment = match Attachment::new(label.as_deref(), guess) {
+                Ok(a) => a,
+                Err(e) => {
+                    writeln!(out, "{e}")?;
+                    return Ok(());
+                }
+            };
+            let dir = attachments::dir(&ctx.paths);
+            let copy = if is_url {
+                None
+            } else if link {
+                let path = std::fs::canonicalize(&path)
+                    .with_context(|| format!("failed resolving {target}"))?;
+                attachment.target = AttachmentTarget::Path(path);
+                None
+            } else {
+                let copy = attachments::copy_in(&dir, todo.id, &path)?;
+                attachment.target = AttachmentTarget::File(copy.clone());
+                Some(copy)
+            };
+
+            let mut list = todo.attachments.clone();
+            let label = attachment.label.clone();
+            list.push(attachment);
+            let patch = TodoPatch {
+                attachments: Some(list),
+                ..TodoPatch::default()
+            };
+            let saved = store
+                .edit_todo(todo.id, patch)
+                .and_then(|_| store.repo_mut().save_atomic());
+            if let Err(e) = saved {
+                if let Some(copy) = copy {
+                    let _ = attachments::remove(&dir, &copy);
+                }
+                return Err(e);
+            }
+            writeln!(out, "Attached {label} to {}", todo.id.short())?;
+        }
+
+        Commands::Detach { id, which } => {
+            use crate::domain::{
+                attachment::{self, AttachmentTarget},
+                todo::TodoPatch,
+            };
+            use crate::infra::attachments;
+
+            let todos = store.list_todos();
+            let todo = match resolve_id_input(&todos, &id) {
+                Ok(todo_id) => todos.iter().find(|t| t.id == todo_id).cloned(),
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+            let Some(todo) = todo else {
+                writeln!(out, "todo not found")?;
+                return Ok(());
+            };
+            let Some(i) = attachment::find(&todo.attachments, &which) else {
+                writeln!(out, "{} has no attachment {which}", todo.id.short())?;
+                return Ok(());
+            };
+            let mut list = todo.attachments.clone();
+            let removed = list.remove(i);
+            let patch = TodoPatch {
+                attachments: Some(list),
+                ..TodoPatch::default()
+            };
+            store.edit_todo(todo.id, patch)?;
+            store.repo_mut().save_atomic()?;
+            // Only after the save, so a failed one keeps the file it points to.
+            if let AttachmentTarget::File(copy) = &removed.target {
+                attachments::remove(&attachments::dir(&ctx.paths), copy)?;
+            }
+            writeln!(out, "Detached {} from {}", removed.label, todo.id.short())?;
+        }
+
+        Commands::Someday { id, promote } => {
+            let todos = store.list_todos();
+            let todo_id = match pick_id(&todos, id.as_deref(), "Someday")? {
+                Ok(x) => x,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+
+            let now = store.now();
+            let result = if promote {
+                store.promote(todo_id, now)
+            } else {
+                store.park(todo_id, now)
+            };
+            match result {
+                Ok(()) => {
+                    store.repo_mut().save_atomic()?;
+                    if promote {
+                        writeln!(out, "Promoted {}", todo_id.short())?;
+                    } else {
+                        writeln!(out, "Parked {} on the someday list", todo_id.short())?;
+                    }
+                }
+                Err(e) => writeln!(out, "{e}")?,
+            }
+        }
+
+        Commands::Archive { id, older_than } => {
+            let now = store.now();
+            let archived = match (id, older_than) {
+                (Some(id), _) => {
+                    let todos = store.list_todos();
+                    let todo_id = match resolve_id_input(&todos, &id) {
+                        Ok(x) => x,
+                        Err(msg) => {
+                            writeln!(out, "{msg}")?;
+                            return Ok(());
+                        }
+                    };
+                    if let Err(e) = store.archive(todo_id, now) {
+                        writeln!(out, "{e}")?;
+                        return Ok(());
+                    }
+                    store.repo_mut().get(todo_id).into_iter().collect()
+                }
+                (None, Some(days)) => store.archive_older_than(days, now),
+                (None, None) => match ctx.config.archive.after_days {
+                    Some(days) => store.archive_older_than(days, now),
+                    None => {
+                        writeln!(
+                            out,
+                            "Give a todo ID or --older-than <days> (or set [archive] after_days)"
+                        )?;
+                        return Ok(());
+                    }
+                },
+            };
+            if archived.is_empty() {
+                writeln!(out, "Nothing to archive")?;
+                return Ok(());
+            }
+            store.repo_mut().save_atomic()?;
+            for t in &archived {
+                writeln!(out, "Archived {}  {}", t.id.short(), t.title.as_str())?;
+            }
+            if archived.len() > 1 {
+                writeln!(out, "Archived {} todos", archived.len())?;
+            }
+        }
+
+        Commands::Unarchive { id } => {
+            let archived: Vec<_> = store
+                .list_todos()
+                .into_iter()
+                .filter(|t| t.is_archived())
+                .collect();
+            let todo_id = match pick_id(&archived, id.as_deref(), "Unarchive")? {
+                Ok(x) => x,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+            match store.unarchive(todo_id, store.now()) {
+                Ok(()) => {
+                    store.repo_mut().save_atomic()?;
+                    writeln!(out, "Unarchived {}", todo_id.short())?;
+                }
+                Err(e) => writeln!(out, "{e}")?,
+            }
+        }
+
+        Commands::Notify { print } => {
+            use crate::app::reminders::{AlertKind, pending};
+
+            let chains = match ctx.config.escalation_chains() {
+                Ok(chains) => chains,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+            let now = store.now();
+            let alerts = pending(&store.list_todos(), now, &chains);
+            if alerts.is_empty() {
+                writeln!(out, "Nothing to notify about.")?;
+                return Ok(());
+            }
+
+            let mut sent = 0;
+            for alert in &alerts {
+                let summary = match alert.kind {
+                    AlertKind::Reminder => "Reminder",
+                    AlertKind::Due if alert.overdue => "Overdue",
+                    AlertKind::Due => "Due now",
+                    AlertKind::Upcoming => "Due soon",
+                };
+                let body = format!("{} ({})", alert.title, alert.id.short());
+                if print {
+                    writeln!(out, "{summary}: {body}")?;
+                } else if let Err(e) = crate::infra::notify::send(summary, &body) {
+                    // Keep the rest pending for the next run.
+                    writeln!(out, "could not send notification: {e:#}")?;
+                    break;
+                }
+                store.mark_notified(alert.id, now);
+                sent += 1;
+            }
+            if sent > 0 {
+                store.repo_mut().save_atomic()?;
+                if !print {
+                    writeln!(out, "Sent {sent} notification(s).")?;
+                }
+            }
+        }
+
+        Commands::Snapshot { list } => {
+            use crate::infra::snapshot;
+
+            let folder = snapshot::folder(&ctx.config.snapshot, &ctx.paths);
+            if list {
+                let all = snapshot::list(&folder);
+                if all.is_empty() {
+                    writeln!(out, "No snapshots in {}", folder.display())?;
+                }
+                for (path, _) in all.iter().rev() {
+                    writeln!(out, "{}", path.display())?;
+                }
+                return Ok(());
+            }
+
+            let todos = store.list_todos();
+            let key = store.repo_mut().key().cloned();
+            let now = store.now();
+            let taken = snapshot::take(&ctx.config.snapshot, &folder, &todos, key.as_ref(), now)?;
+            writeln!(
+                out,
+                "Wrote snapshot of {} todo(s) to {}",
+                todos.len(),
+                taken.path.display()
+            )?;
+            if taken.pruned > 0 {
+                writeln!(out, "Removed {} old snapshot(s)", taken.pruned)?;
+            }
+            if taken.uploaded {
+                writeln!(out, "Ran upload_command")?;
+            }
+        }
+
+        Commands::Review { every } => {
+            use std::io::{BufRead, IsTerminal};
+
+            let now = store.now();
+            let Some(cutoff) = crate::app::stats::parse_since(&every, now) else {
+                writeln!(out, "invalid --every {every} (use e.g. 30d, 2w)")?;
+                return Ok(());
+            };
+
+            let mut due: Vec<_> = store
+                .list_todos()
+                .into_iter()
+                .filter(|t| t.someday.is_some_and(|at| at <= cutoff))
+                .collect();
+            due.sort_by_key(|t| t.someday);
+            if due.is_empty() {
+                writeln!(out, "Someday list reviewed: nothing older than {every}.")?;
+                return Ok(());
+            }
+
+            writeln!(
+                out,
+                "{} someday item(s) not reviewed in {every}:",
+                due.len()
+            )?;
+            if !std::io::stdin().is_terminal() {
+                for t in &due {
+                    writeln!(
+                        out,
+                        "  {}  {}",
+                        t.id.short(),
+                        display_title(t, &Style::from_config(&ctx.config))
+                    )?;
+                }
+                writeln!(out, "Promote with `someday <id> --promote`.")?;
+                return Ok(());
+            }
+
+            let mut changed = false;
+            for t in &due {
+                let since = t.someday.map(|d| d.date().to_string()).unwrap_or_default();
+                writeln!(
+                    out,
+                    "  {}  {}  (since {since})",
+                    t.id.short(),
+                    display_title(t, &Style::from_config(&ctx.config))
+                )?;
+                let result = loop {
+                    write!(out, "  [p]romote / [k]eep / [d]elete / [s]kip (s): ")?;
+                    out.flush()?;
+                    let mut line = String::new();
+                    std::io::stdin().lock().read_line(&mut line)?;
+                    match line.trim().to_ascii_lowercase().as_str() {
+                        "p" | "promote" => break Some(store.promote(t.id, now)),
+                        // Re-parking marks the item as reviewed.
+                        "k" | "keep" => break Some(store.park(t.id, now)),
+                        "d" | "delete" => break Some(store.delete(t.id)),
+                        "" | "s" | "skip" => break None,
+                        _ => {}
+                    }
+                };
+                match result {
+                    Some(Ok(())) => changed = true,
+                    Some(Err(e)) => writeln!(out, "  {e}")?,
+                    None => {}
+                }
+            }
+            if changed {
+                store.repo_mut().save_atomic()?;
+            }
+        }
+
+        Commands::Export {
+            format,
+            out: out_file,
+            since,
+            project,
+            redact,
+            reproducible,
+            drop_volatile,
+            include_history,
+        } => {
+            use crate::app::redact::{RedactField, Redaction};
+            use std::path::PathBuf;
+
+            let redaction = match Redaction::parse(&redact, &ctx.config.redact) {
+                Ok(r) => r,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+            let out_path = PathBuf::from(out_file);
+            let mut todos = store.list_todos();
+            if let Some(p) = &project {
+                todos.retain(|t| t.project.as_str().eq_ignore_ascii_case(p.trim()));
+            }
+            let mut journal_entries = None;
+            let mut todos: Vec<_> = todos.into_iter().map(|t| redaction.apply(t)).collect();
+            if reproducible {
+                todos.sort_by(|a, b| {
+                    a.created_at
+                        .cmp(&b.created_at)
+                        .then_with(|| a.id.cmp(&b.id))
+                });
+            }
+
+            match format.trim().to_ascii_lowercase().as_str() {
+                "json" => {
+                    use crate::infra::{db_schema, journal};
+
+                    let mut json = if reproducible {
+                        db_schema::write_reproducible(&todos, drop_volatile)?
+                    } else {
+                        db_schema::write_current(&todos)?
+                    };
+                    if include_history {
+                        let db_path = ctx.config.resolve_db_path(&ctx.paths);
+                        let ids = todos.iter().map(|t| t.id).collect();
+                        let mut entries = journal::about(
+                            journal::entries(
+                                &journal::journal_path(&db_path),
+                                store.repo_mut().key(),
+                            )?,
+                            &ids,
+                        );
+                        if redaction.fields.contains(&RedactField::Title) {
+                            for c in entries.iter_mut().flat_map(|e| e.changes.iter_mut()) {
+                                c.title = "[redacted]".to_string();
+                            }
+                        }
+                        json = db_schema::add_section(&json, "journal", &entries)?;
+                        journal_entries = Some(entries.len());
+                    }
+
+                    if let Some(parent) = out_path.parent()
+                        && !parent.as_os_str().is_empty()
+                    {
+                        std::fs::create_dir_all(parent).with_context(|| {
+                            format!("failed creating export directory: {}", parent.display())
+                        })?;
+                    }
+
+                    std::fs::write(&out_path, json).with_context(|| {
+                        format!("failed writing export file: {}", out_path.display())
+                    })?;
+                }
+                "csv" | "timesheet" | "markdown" | "md" if include_history => {
+                    writeln!(out, "--include-history needs --format json")?;
+                    return Ok(());
+                }
+                "csv" => {
+                    crate::infra::csv_io::export_csv(&out_path, &todos)?;
+                }
+                "timesheet" => {
+                    use crate::app::stats::parse_since;
+                    use crate::app::timesheet::{build, format_hm, to_markdown};
+
+                    let since = match since {
+                        None => None,
+                        Some(s) => match parse_since(&s, store.now()) {
+                            Some(dt) => Some(dt),
+                            None => {
+                                writeln!(
+                                    out,
+                                    "invalid --since {s} (use e.g. 30d, 2w, 2026-01-01 or RFC3339)"
+                                )?;
+                                return Ok(());
+                            }
+                        },
+                    };
+
+                    let rows = build(&todos, since, None, |p| {
+                        ctx.config.timesheet.rounding_for(p)
+                    });
+                    let is_md = out_path
+                        .extension()
+                        .is_some_and(|e| e.eq_ignore_ascii_case("md"));
+                    if is_md {
+                        if let Some(parent) = out_path.parent()
+                            && !parent.as_os_str().is_empty()
+                        {
+                            std::fs::create_dir_all(parent).with_context(|| {
+                                format!("failed creating export directory: {}", parent.display())
+                            })?;
+                        }
+                        std::fs::write(&out_path, to_markdown(&rows)).with_context(|| {
+                            format!("failed writing export file: {}", out_path.display())
+                        })?;
+                    } else {
+                        crate::infra::csv_io::export_timesheet(&out_path, &rows)?;
+                    }
+
+                    let total: u32 = rows.iter().map(|r| r.minutes).sum();
+                    writeln!(
+                        out,
+                        "Exported timesheet ({} rows, {}) to {}",
+                        rows.len(),
+                        format_hm(total),
+                        out_path.display()
+                    )?;
+                    return Ok(());
+                }
+                "markdown" | "md" => {
+                    use crate::app::report::{Grouping, render};
+                    use crate::app::stats::parse_since;
+
+                    let since = match since {
+                        None => None,
+                        Some(s) => match parse_since(&s, store.now()) {
+                            Some(dt) => Some(dt),
+                            None => {
+                                writeln!(
+                                    out,
+                                    "invalid --since {s} (use e.g. 30d, 2w, 2026-01-01 or RFC3339)"
+                                )?;
+                                return Ok(());
+                            }
+                        },
+                    };
+                    let (md, _) = render("Todos", &todos, Grouping::Project, since);
+
+                    if let Some(parent) = out_path.parent()
+                        && !parent.as_os_str().is_empty()
+                    {
+                        std::fs::create_dir_all(parent).with_context(|| {
+                            format!("failed creating export directory: {}", parent.display())
+                        })?;
+                    }
+                    std::fs::write(&out_path, md).with_context(|| {
+                        format!("failed writing export file: {}", out_path.display())
+                    })?;
+                }
+                other => {
+                    println!("unknown export format: {other} (use json|csv|markdown|timesheet)");
+                    return Ok(());
+                }
+            }
+
+            let mut notes = Vec::new();
+            if let Some(n) = journal_entries {
+                notes.push(format!("{n} journal entries"));
+            }
+            if !redaction.is_empty() {
+                notes.push(format!("redacted: {}", redaction.describe()));
+            }
+            if notes.is_empty() {
+                println!("Exported {} todos to {}", todos.len(), out_path.display());
+            } else {
+                println!(
+                    "Exported {} todos to {} ({})",
+                    todos.len(),
+                    out_path.display(),
+                    notes.join("; ")
+                );
+            }
+        }
+
+        Commands::Import { format, r#in, mode } => {
+            use crate::app::merge::{self, ImportMode};
+            use crate::infra::glob;
+            use std::path::PathBuf;
+
+            let format = format.trim().to_ascii_lowercase();
+            if !matches!(format.as_str(), "json" | "csv" | "todoist") {
+                println!("unknown import format: {format} (use json|csv|todoist)");
+                return Ok(());
+            }
+            let Some(mode) = ImportMode::parse(&mode) else {
+                writeln!(out, "unknown --mode {mode} (use replace|merge|append)")?;
+                return Ok(());
+            };
+
+            let mut history = Vec::new();
+            let (incoming, source) = if !glob::is_pattern(&r#in) {
+                let in_path = PathBuf::from(r#in);
+                let file = read_import_file(&in_path, &format)?;
+                history = file.journal;
+                (file.todos, in_path.display().to_string())
+            } else {
+                let paths = glob::expand(&r#in)?;
+                if paths.is_empty() {
+                    writeln!(out, "No files match {}", r#in)?;
+                    return Ok(());
+                }
+                // Parsing dominates on big exports, so read the files side by side.
+                let loaded: Vec<Result<ImportFile>> = std::thread::scope(|s| {
+                    let handles: Vec<_> = paths
+                        .iter()
+                        .map(|p| s.spawn(|| read_import_file(p, &format)))
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|h| h.join().expect("import reader panicked"))
+                        .collect()
+                });
+
+                let mut lists = Vec::new();
+                let mut failed = 0;
+                for (path, result) in paths.iter().zip(loaded) {
+                    match result {
+                        Ok(file) => {
+                            lists.push(file.todos);
+                            history.extend(file.journal);
+                        }
+                        Err(e) => {
+                            failed += 1;
+                            writeln!(out, "{}: {e:#}", path.display())?;
+                        }
+                    }
+                }
+                if failed > 0 {
+                    writeln!(
+                        out,
+                        "Nothing imported: {failed} of {} files could not be read",
+                        paths.len()
+                    )?;
+                    return Ok(());
+                }
+
+                let (todos, stats) = merge::combine(lists);
+                for (path, s) in paths.iter().zip(&stats) {
+                    let mut line = format!(
+                        "{}: {} todos, {} new, {} merged",
+                        path.display(),
+                        s.read,
+                        s.added,
+                        s.merged
+                    );
+                    if s.conflicts > 0 {
+                        line.push_str(&format!(", {} conflicting fields", s.conflicts));
+                    }
+                    writeln!(out, "{line}")?;
+                }
+                (todos, format!("{} files", paths.len()))
+            };
+
+            let current = store.list_todos();
+            let count = incoming.len();
+            let question = match mode {
+                ImportMode::Replace => format!(
+                    "Replace all {} todos with the {count} from {source}?",
+                    current.len()
+                ),
+                ImportMode::Merge => format!(
+                    "Merge the {count} todos from {source} into the {} there are?",
+                    current.len()
+                ),
+                ImportMode::Append => format!("Add the {count} todos from {source}?"),
+            };
+            if !confirmed(ctx, "import", force, &question, out)? {
+                return Ok(());
+            }
+            let (todos, stats) = merge::import(&current, incoming, mode);
+            let dups = match store.set_all(todos) {
+                Ok(dups) => dups,
+                Err(e) => {
+                    writeln!(
+                        out,
+                        "Nothing imported: {e} (set duplicate_ids = \"keep-first\" or \"keep-last\" to keep one of each)"
+                    )?;
+                    return Ok(());
+                }
+            };
+            store.repo_mut().save_atomic()?; // persist immediately
+
+            match mode {
+                ImportMode::Replace => writeln!(out, "Imported {count} todos from {source}")?,
+                ImportMode::Merge => writeln!(
+                    out,
+                    "Merged {source}: {} inserted, {} updated, {} skipped",
+                    stats.inserted, stats.updated, stats.skipped
+                )?,
+                ImportMode::Append => {
+                    writeln!(out, "Appended {} todos from {source}", stats.inserted)?
+                }
+            }
+            report_duplicates(&dups, out)?;
+            if !history.is_empty() {
+                use crate::infra::journal;
+
+                history.sort_by_key(|e| e.at);
+                let path = journal::journal_path(&ctx.config.resolve_db_path(&ctx.paths));
+                let key = store.repo_mut().key().cloned();
+                let added =
+                    journal::restore(&path, key.as_ref(), history, ctx.config.journal.hash_chain)?;
+                writeln!(out, "Restored {added} journal entries")?;
+            }
+        }
+
+        Commands::Resolve { id, with, picks } => {
+            use crate::app::sync::SyncConflict;
+            use crate::domain::crdt::{self, TodoField};
+            use crate::domain::todo::Todo;
+            use std::collections::BTreeMap;
+            use std::io::{BufRead, IsTerminal};
+            use std::path::PathBuf;
+
+            let todos = store.list_todos();
+            let todo_id = match pick_id(&todos, Some(&id), "Resolve")? {
+                Ok(x) => x,
+                Err(msg) => {
+                    writeln!(out, "{msg}")?;
+                    return Ok(());
+                }
+            };
+            let Some(local) = store.repo_mut().get(todo_id) else {
+                writeln!(out, "todo not found")?;
+                return Ok(());
+            };
+
+            let conflict_file = crate::infra::sync_store::conflicts_dir(&ctx.paths)
+                .join(format!("{}.json", todo_id.as_uuid_str()));
+            let source = with.map(PathBuf::from).unwrap_or(conflict_file.clone());
+            if !source.exists() {
+                writeln!(
+                    out,
+                    "No conflict recorded for {} (use --with <file>)",
+                    todo_id.short()
+                )?;
+                return Ok(());
+            }
+
+            let text = crate::infra::sync_store::read_conflict(&source, store.repo_mut().key())?;
+            let other: Todo = match serde_json::from_str::<SyncConflict>(&text) {
+                Ok(c) => c.discarded,
+                Err(_) => serde_json::from_str(&text).with_context(|| {
+                    format!("{} is neither a conflict nor a todo file", source.display())
+                })?,
+            };
+            if other.id != local.id {
+                writeln!(out, "{} holds a different todo", source.display())?;
+                return Ok(());
+            }
+
+            let mut preselected = BTreeMap::new();
+            for p in picks {
+                let parsed = p.split_once('=').and_then(|(f, side)| {
+                    let side = match side.trim() {
+                        "local" => false,
+                        "other" => true,
+                        _ => return None,
+                    };
+                    TodoField::parse(f).map(|f| (f, side))
+                });
+                let Some((field, take_other)) = parsed else {
+                    writeln!(out, "invalid --pick {p} (use <field>=local|other)")?;
+                    return Ok(());
+                };
+                preselected.insert(field, take_other);
+            }
+
+            let fields = crdt::diff_fields(&local, &other);
+            if fields.is_empty() {
+                writeln!(out, "Versions of {} are identical.", todo_id.short())?;
+            }
+
+            // Without a terminal to ask, every field has to be picked up front:
+            // the conflict file goes once the todo is resolved.
+            let interactive = std::io::stdin().is_terminal();
+            let undecided: Vec<&str> = fields
+                .iter()
+                .filter(|f| !preselected.contains_key(f))
+                .map(|f| f.name())
+                .collect();
+            if !interactive && !undecided.is_empty() {
+                anyhow::bail!(
+                    "no terminal to ask which version to keep; pass --pick <field>=local|other for {}",
+                    undecided.join(", ")
+                );
+            }
+            let mut merged = local.clone();
+            let mut taken = Vec::new();
+            let now = store.now();
+
+            for field in fields {
+                writeln!(out, "{}:", field.name())?;
+                writeln!(out, "  [l] local: {}", crdt::field_value(&local, field))?;
+                writeln!(out, "  [o] other: {}", crdt::field_value(&other, field))?;
+
+                let take_other = match preselected.get(&field) {
+                    Some(&choice) => choice,
+                    None => loop {
+                        write!(out, "Keep which? [l/o] (l): ")?;
+                        out.flush()?;
+                        let mut line = String::new();
+                        std::io::stdin().lock().read_line(&mut line)?;
+                        match line.trim().to_ascii_lowercase().as_str() {
+                            "" | "l" | "local" => break false,
+                            "o" | "other" => break true,
+                            _ => {}
+                        }
+                    },
+                };
+
+                if take_other {
+                    crdt::adopt_field(&mut merged, &other, field, now);
+                    taken.push(field.name());
+                }
+            }
+
+            if !taken.is_empty() {
+                store.replace_todo(merged);
+                store.repo_mut().save_atomic()?;
+            }
+            if source == conflict_file {
+                std::fs::remove_file(&conflict_file).with_context(|| {
+                    format!("failed removing conflict file: {}", conflict_file.display())
+                })?;
+            }
+
+            if taken.is_empty() {
+                writeln!(out, "Resolved {}: kept local version", todo_id.short())?;
+            } else {
+                writeln!(
+                    out,
+                    "Resolved {}: took other version for {}",
+                    todo_id.short(),
+                    taken.join(", ")
+                )?;
+            }
+        }
+
+        Commands::Serve {
+            action: Some(ServeAction::Token { action }),
+            ..
+        } => token_command(ctx, store, action, out)?,
+        Commands::Serve {
+            action: None,
+            readonly,
+            bind,
+            refresh,
         } => {
-            use crate::domain::todo::{DueAt, Notes, Priority, ProjectName, Tag, Todo};
-            use std::collections::BTreeSet;
+            // Once a token exists, every request needs one. Without any, only
+            // loopback may change todos.
+            let token_file = crate::infra::api_tokens::path(&ctx.paths);
+            let tokens = !crate::infra::api_tokens::load(&token_file)?.is_empty();
+            let listener = std::net::TcpListener::bind(&bind)
+                .with_context(|| format!("failed binding {bind}"))?;
+            let addr = listener.local_addr()?;
+            let db_path = ctx.config.resolve_db_path(&ctx.paths);
+            let opts = crate::ui::http::ServeOptions {
+                key: store.repo_mut().key().cloned(),
+                db_path,
+                clock: ctx.clock.clone(),
+                capture: crate::ui::http::CaptureRules::from_config(&ctx.config),
+                refresh: std::time::Duration::from_secs(refresh.max(1)),
+                config: Some((
+                    crate::infra::config::ConfigWatcher::new(&ctx.paths),
+                    ctx.paths.clone(),
+                )),
+                writable: !readonly,
+                tokens: tokens.then_some(token_file),
+                rate_limit: ctx.config.serve.rate_limit,
+            };
+            if !readonly && !tokens && !addr.ip().is_loopback() {
+                writeln!(
+                    out,
+                    "Refusing to let anyone who can reach {addr} change your todos: create a token with `serve token create` or pass --readonly"
+                )?;
+                return Ok(());
+            }
+
+            info!(%addr, readonly, tokens, "serving dashboard");
+            if readonly {
+                writeln!(
+                    out,
+                    "Serving read-only board on http://{addr}/ (Ctrl+C to stop)"
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    "Serving board and todo API on http://{addr}/ (Ctrl+C to stop)"
+                )?;
+            }
+            if tokens {
+                writeln!(
+                    out,
+                    "API tokens required (open the board as http://{addr}/?token=...)"
+                )?;
+            }
+            out.flush()?;
+            crate::ui::http::serve(listener, opts)?;
+        }
+        Commands::Api {
+            action:
+                ApiAction::Spec {
+                    readonly,
+                    out: file,
+                },
+        } => {
+            let doc = crate::ui::openapi::document(!readonly);
+            let text = serde_json::to_string_pretty(&doc)?;
+            match file {
+                Some(file) => {
+                    std::fs::write(&file, format!("{text}\n"))
+                        .with_context(|| format!("failed writing {file}"))?;
+                    writeln!(out, "Wrote OpenAPI spec to {file}")?;
+                }
+                None => writeln!(out, "{text}")?,
+            }
+        }
+        Commands::Schema { .. } | Commands::Migrate { .. } => {
+            unreachable!("handled before the store is loaded")
+        }
+
+        Commands::Rpc => {
+            use crate::infra::fs_repo::JsonFileTodoRepository;
+
+            let db_path = ctx.config.resolve_db_path(&ctx.paths);
+            let key = store.repo_mut().key().cloned();
+            info!(db = %db_path.display(), "answering json-rpc on stdin");
+            crate::ui::rpc::serve(
+                std::io::stdin().lock(),
+                &mut *out,
+                &crate::ui::http::CaptureRules::from_config(&ctx.config),
+                || {
+                    let repo = JsonFileTodoRepository::load_with_key(db_path.clone(), key.clone())?;
+                    Ok(Store::new(repo).with_clock(ctx.clock.clone()))
+                },
+                |s: &mut Store<JsonFileTodoRepository>| s.repo_mut().save_atomic(),
+            )?;
+        }
+        Commands::Mqtt { action } => {
+            use crate::infra::mqtt;
+
+            let cfg = &ctx.config.mqtt;
+            if !cfg.enabled {
+                writeln!(out, "MQTT is disabled (set enabled = true under [mqtt])")?;
+                return Ok(());
+            }
+            match action {
+                MqttAction::Check => {
+                    let todos = store.list_todos();
+                    let n =
+                        mqtt::publish_changes(cfg, &mqtt::state_path(&ctx.paths), &todos, &todos)?;
+                    writeln!(out, "Published {n} event(s) to {}", cfg.broker)?;
+                }
+                MqttAction::Test => {
+                    let mut client = mqtt::Client::connect(cfg)?;
+                    let topic = format!("{}/test", cfg.topic_prefix.trim_end_matches('/'));
+                    client.publish(&topic, "hello from rustlytodo", false)?;
+                    client.disconnect()?;
+                    writeln!(out, "Published to {topic} on {}", cfg.broker)?;
+                }
+            }
+        }
+        Commands::Doctor => {
+            use crate::infra::{fs_repo, history_file, journal, perms};
+
+            let db_path = ctx.config.resolve_db_path(&ctx.paths);
+            let mut roots = vec![ctx.paths.config_dir.clone(), ctx.paths.data_dir.clone()];
+            // A `storage_path` elsewhere: only our files, not the whole folder.
+            if !db_path.starts_with(&ctx.paths.data_dir) {
+                roots.extend([
+                    journal::journal_path(&db_path),
+                    history_file::history_path(&db_path),
+                    fs_repo::shard_dir(&db_path),
+                    db_path,
+                ]);
+            }
+
+            let exposed = perms::exposed(&roots);
+            for e in &exposed {
+                writeln!(
+                    out,
+                    "warning: {} is readable by other users ({})",
+                    e.path.display(),
+                    e.detail
+                )?;
+            }
+            if !exposed.is_empty() {
+                if cfg!(unix) {
+                    writeln!(out, "Fix with: chmod 600 <file>")?;
+                }
+                anyhow::bail!("doctor found {} problem(s)", exposed.len());
+            }
+            writeln!(out, "No problems found")?;
+        }
+        Commands::VerifyJournal => {
+            let db_path = ctx.config.resolve_db_path(&ctx.paths);
+            let path = crate::infra::journal::journal_path(&db_path);
+            if !path.exists() {
+                writeln!(
+                    out,
+                    "No journal at {} (enable [journal] in config)",
+                    path.display()
+                )?;
+                return Ok(());
+            }
+
+            let report = crate::infra::journal::verify(&path, store.repo_mut().key())?;
+            for p in &report.problems {
+                writeln!(out, "{p}")?;
+            }
+            if !report.is_ok() {
+                anyhow::bail!(
+                    "journal verification failed: {} problem(s) in {}",
+                    report.problems.len(),
+                    path.display()
+                );
+            }
+
+            writeln!(
+                out,
+                "Journal OK: {} entries ({} chained)",
+                report.entries, report.chained
+            )?;
+            if let Some(head) = report.head {
+                writeln!(out, "Chain head: {head}")?;
+            }
+        }
+
+        Commands::Sync { action } => {
+            use crate::app::sync::{apply_delta, build_delta, stamp_local_changes};
+            use crate::infra::sync_crypto::{self, SyncKeys};
+            use crate::infra::sync_store::{self, DeltaFile};
+            use std::path::PathBuf;
+
+            let db_path = ctx.config.resolve_db_path(&ctx.paths);
+            let state_path = sync_store::state_path(&ctx.paths);
+            let mut state = sync_store::load_or_init(&state_path)?;
+            let keys_path = sync_crypto::keys_path(&ctx.paths);
+            let keys = sync_crypto::load(&keys_path)?;
+
+            match action {
+                SyncAction::Status => {
+                    writeln!(out, "Device:  {}", state.device_id)?;
+                    writeln!(out, "Tracked: {} todos", state.snapshot.len())?;
+                    if state.peers.is_empty() {
+                        writeln!(out, "Peers:   -")?;
+                    } else {
+                        writeln!(out, "Peers:")?;
+                        for peer in state.peers.keys() {
+                            writeln!(out, "  {peer}")?;
+                        }
+                    }
+                    match &keys {
+                        Some(k) => writeln!(out, "Keys:    {} (encrypted)", k.fingerprint())?,
+                        None => writeln!(out, "Keys:    - (plaintext; see `sync keys init`)")?,
+                    }
+                    sync_store::save(&state_path, &state)?;
+                }
+                SyncAction::Export {
+                    peer,
+                    out: path,
+                    plaintext,
+                } => {
+                    let mut todos = store.list_todos();
+                    if stamp_local_changes(&mut state, &mut todos) > 0 {
+                        store.set_all(todos.clone())?;
+                        store.repo_mut().save_atomic()?;
+                    }
+
+                    let delta = timed(Op::Sync, || build_delta(&state, &todos, peer.as_deref()));
+                    let path = PathBuf::from(path);
+                    let encrypted_for = match keys.filter(|_| !plaintext) {
+                        Some(k) => {
+                            let recipients = match peer {
+                                Some(p) => vec![p],
+                                None => k.peers.keys().cloned().collect(),
+                            };
+                            sync_store::write_sealed(&path, &k.seal(&delta, &recipients)?)?;
+                            Some(recipients.len())
+                        }
+                        None => {
+                            sync_store::write_delta(&path, &delta)?;
+                            None
+                        }
+                    };
+                    sync_store::save(&state_path, &state)?;
+
+                    writeln!(
+                        out,
+                        "Exported {} changes and {} deletions to {}",
+                        delta.todos.len(),
+                        delta.tombstones.len(),
+                        path.display()
+                    )?;
+                    if let Some(n) = encrypted_for {
+                        writeln!(out, "Encrypted for {n} trusted peer(s).")?;
+                    }
+                }
+                SyncAction::Import { r#in, plaintext } => {
+                    let path = PathBuf::from(r#in);
+                    let delta = match (sync_store::read_delta(&path)?, &keys) {
+                        (DeltaFile::Sealed(sealed), Some(k)) => k.open(&sealed)?,
+                        (DeltaFile::Sealed(_), None) => {
+                            writeln!(
+                                out,
+                                "Delta is encrypted but this device has no sync keys (run `sync keys init`)."
+                            )?;
+                            return Ok(());
+                        }
+                        (DeltaFile::Plain(delta), None) => delta,
+                        (DeltaFile::Plain(delta), Some(_)) if plaintext => delta,
+                        (DeltaFile::Plain(_), Some(_)) => {
+                            writeln!(
+                                out,
+                                "Refusing unencrypted delta while sync keys are set up (use --plaintext to accept)."
+                            )?;
+                            return Ok(());
+                        }
+                    };
+                    if delta.from == state.device_id {
+                        writeln!(out, "Refusing to import a delta exported by this device.")?;
+                        return Ok(());
+                    }
+
+                    let mut todos = store.list_todos();
+                    stamp_local_changes(&mut state, &mut todos);
+                    let report = timed(Op::Sync, || apply_delta(&mut state, &mut todos, delta));
+
+                    let dups = store.set_all(todos)?;
+                    report_duplicates(&dups, out)?;
+                    store.repo_mut().save_atomic()?;
+                    sync_store::save(&state_path, &state)?;
+                    let written = sync_store::write_conflicts(
+                        &sync_store::conflicts_dir(&ctx.paths),
+                        &report.conflicts,
+                        store.repo_mut().key(),
+                    )?;
+
+                    writeln!(
+                        out,
+                        "Synced from {}: {} inserted, {} updated, {} deleted, {} unchanged",
+                        path.display(),
+                        report.inserted,
+                        report.updated,
+                        report.deleted,
+                        report.unchanged
+                    )?;
+                    for (c, p) in report.conflicts.iter().zip(written) {
+                        let fields = c
+                            .fields
+                            .iter()
+                            .map(|f| f.name())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        writeln!(
+                            out,
+                            "Conflict on {} ({fields}): kept latest edit, other version saved to {}",
+                            c.kept.id.short(),
+                            p.display()
+                        )?;
+                    }
+                }
+                SyncAction::Init { remote } => {
+                    if ctx.config.shard_by_project {
+                        writeln!(
+                            out,
+                            "Git sync needs the single-file layout (turn off shard_by_project)."
+                        )?;
+                        return Ok(());
+                    }
+                    let git = GitSync::init(&db_path, remote.as_deref())?;
+                    writeln!(out, "Syncing with git in {}", git.dir().display())?;
+                }
+                SyncAction::Push { remote } => {
+                    let Some(git) = GitSync::open(&db_path) else {
+                        writeln!(out, "Git sync is not set up (run `sync init`).")?;
+                        return Ok(());
+                    };
+                    git.commit("rustlytodo sync push")?;
+                    timed(Op::Sync, || git.push(&remote))?;
+                    writeln!(out, "Pushed to {remote}.")?;
+                }
+                SyncAction::Pull { remote } => {
+                    use crate::infra::db_schema::DbContents;
+                    use crate::infra::git_sync::Pulled;
+
+                    let key = store.repo_mut().key().cloned();
+                    let Some(git) = GitSync::open(&db_path).map(|g| g.with_key(key)) else {
+                        writeln!(out, "Git sync is not set up (run `sync init`).")?;
+                        return Ok(());
+                    };
+                    git.commit("rustlytodo sync pull")?;
+                    let local = DbContents {
+                        projects: store.projects(),
+                        todos: store.list_todos(),
+                    };
+                    let (contents, merge) = match timed(Op::Sync, || git.pull(&remote, &local))? {
+                        Pulled::UpToDate => {
+                            writeln!(out, "Already up to date with {remote}.")?;
+                            return Ok(());
+                        }
+                        Pulled::FastForward(contents) => (contents, None),
+                        Pulled::Merged { contents, merge } => (contents, Some(merge)),
+                    };
+
+                    let dups = store.set_all(contents.todos)?;
+                    report_duplicates(&dups, out)?;
+                    for p in contents.projects {
+                        store.update_project(&p.name.clone(), |record| *record = p);
+                    }
+                    store.repo_mut().save_atomic()?;
+                    git.commit(&format!("Merge todos from {remote}"))?;
+
+                    match merge {
+                        None => writeln!(out, "Fast-forwarded to {remote}.")?,
+                        Some(m) => {
+                            writeln!(
+                                out,
+                                "Merged {remote}: {} todo(s) taken from there, {} deleted",
+                                m.from_remote, m.deleted
+                            )?;
+                            for id in m.conflicts {
+                                writeln!(
+                                    out,
+                                    "Conflict on {}: edited on both sides, kept the newest",
+                                    id.short()
+                                )?;
+                            }
+                        }
+                    }
+                }
+                SyncAction::Keys { action } => match action {
+                    SyncKeysAction::Init => {
+                        if let Some(k) = keys {
+                            writeln!(out, "Sync keys already exist ({}).", k.fingerprint())?;
+                            return Ok(());
+                        }
+                        let k = SyncKeys::generate(state.device_id.clone())?;
+                        sync_crypto::save(&keys_path, &k)?;
+                        sync_store::save(&state_path, &state)?;
+                        writeln!(out, "Created sync keys for {}", k.device_id)?;
+                        writeln!(out, "Fingerprint: {}", k.fingerprint())?;
+                        writeln!(
+                            out,
+                            "Exchange invites with `sync keys invite` / `sync keys add` on each device."
+                        )?;
+                    }
+                    SyncKeysAction::List => {
+                        let Some(k) = keys else {
+                            writeln!(out, "No sync keys (run `sync keys init`).")?;
+                            return Ok(());
+                        };
+                        writeln!(out, "This device: {} ({})", k.device_id, k.fingerprint())?;
+                        if k.peers.is_empty() {
+                            writeln!(out, "Trusted peers: -")?;
+                        } else {
+                            writeln!(out, "Trusted peers:")?;
+                            for (device, public) in &k.peers {
+                                writeln!(out, "  {device} ({})", sync_crypto::fingerprint(public))?;
+                            }
+                        }
+                    }
+                    SyncKeysAction::Invite {
+                        out: path,
+                        passphrase,
+                    } => {
+                        let Some(k) = keys else {
+                            writeln!(out, "No sync keys (run `sync keys init`).")?;
+                            return Ok(());
+                        };
+                        let path = PathBuf::from(path);
+                        let invite = k.invite(&sync_passphrase(passphrase)?)?;
+                        sync_store::write_invite(&path, &invite)?;
+                        writeln!(out, "Wrote invite to {}", path.display())?;
+                    }
+                    SyncKeysAction::Add { r#in, passphrase } => {
+                        let Some(mut k) = keys else {
+                            writeln!(out, "No sync keys (run `sync keys init`).")?;
+                            return Ok(());
+                        };
+                        let invite = sync_store::read_invite(&PathBuf::from(r#in))?;
+                        let device = k.accept_invite(&invite, &sync_passphrase(passphrase)?)?;
+                        sync_crypto::save(&keys_path, &k)?;
+                        writeln!(
+                            out,
+                            "Trusted {device} ({})",
+                            sync_crypto::fingerprint(&k.peers[&device])
+                        )?;
+                    }
+                    SyncKeysAction::Remove { device } => {
+                        let Some(mut k) = keys else {
+                            writeln!(out, "No sync keys (run `sync keys init`).")?;
+                            return Ok(());
+                        };
+                        if k.peers.remove(&device).is_some() {
+                            sync_crypto::save(&keys_path, &k)?;
+                            writeln!(out, "Removed {device}")?;
+                        } else {
+                            writeln!(out, "{device} is not a trusted peer")?;
+                        }
+                    }
+                },
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An external reference padded to `width`, as a clickable link (OSC 8) when
+/// stdout is a terminal and it has one.
+fn ref_cell(r: &crate::domain::external_ref::ExternalRef, width: usize) -> String {
+    use std::io::IsTerminal;
+
+    let text = r.to_string();
+    let pad = " ".repeat(width.saturating_sub(text.chars().count()));
+    match &r.url {
+        Some(url) if io::stdout().is_terminal() => {
+            format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\{pad}")
+        }
+        _ => format!("{text}{pad}"),
+    }
+}
+
+/// `at` in RFC 3339 with the configured zone's offset.
+fn local_rfc3339(ctx: &AppContext, at: time::OffsetDateTime) -> String {
+    ctx.local(at)
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_else(|_| "<invalid-datetime>".to_string())
+}
+
+/// Title with its badge, in the todo's color if `style` has colors.
+fn display_title(todo: &crate::domain::todo::Todo, style: &Style) -> String {
+    let title = match &todo.badge {
+        Some(b) => format!("{} {}", b.as_str(), todo.title.as_str()),
+        None => todo.title.as_str().to_string(),
+    };
+    match todo.color {
+        Some(c) => style.rgb(c.rgb(), &title),
+        None => title,
+    }
+}
+
+/// `move --filter --to`: preview, confirm, then move as one undo step.
+fn move_todos(
+    ctx: &AppContext,
+    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
+    filter: &str,
+    to: &str,
+    force: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::{projects, query::ListQuery};
+    use crate::domain::todo::ProjectName;
+
+    let now = store.now();
+    let mut q = ListQuery::default();
+    if let Err(e) = q.add_query(filter, now) {
+        writeln!(out, "{e}")?;
+        return Ok(());
+    }
+    // An existing project keeps its spelling.
+    let known = store.projects();
+    let to = match projects::find(&known, to) {
+        Some(p) => p.name.clone(),
+        None => match store
+            .list_todos()
+            .into_iter()
+            .find(|t| t.project.as_str().eq_ignore_ascii_case(to.trim()))
+        {
+            Some(t) => t.project,
+            None => ProjectName::parse(to)?,
+        },
+    };
+
+    let todos: Vec<_> = store
+        .find_todos(&q, now)
+        .into_iter()
+        .filter(|t| !t.project.as_str().eq_ignore_ascii_case(to.as_str()))
+        .collect();
+    if todos.is_empty() {
+        writeln!(out, "No todos to move.")?;
+        return Ok(());
+    }
+
+    writeln!(out, "{:<10} {:<20} TITLE", "ID", "FROM")?;
+    for t in &todos {
+        writeln!(
+            out,
+            "{:<10} {:<20} {}",
+            t.id.short(),
+            t.project.as_str(),
+            t.title.as_str()
+        )?;
+    }
+    let question = format!("Move {} todo(s) to {}?", todos.len(), to.as_str());
+    if !confirmed(ctx, "move", force, &question, out)? {
+        return Ok(());
+    }
+
+    let ids: Vec<_> = todos.iter().map(|t| t.id).collect();
+    let moved = store.move_todos(&ids, &to);
+    store.repo_mut().save_atomic()?;
+    writeln!(out, "Moved {moved} todo(s) to {}", to.as_str())?;
+    if projects::find(&known, to.as_str()).is_some_and(|p| p.archived) {
+        writeln!(out, "Note: {} is archived.", to.as_str())?;
+    }
+    Ok(())
+}
+
+struct ReplaceOptions<'a> {
+    search: &'a str,
+    with: &'a str,
+    regex: bool,
+    case_sensitive: bool,
+    filter: Option<&'a str>,
+    fields: &'a [String],
+}
+
+/// `replace`: preview every change, then apply all of them (one undo step)
+/// or none if any result is invalid.
+fn replace_text(
+    ctx: &AppContext,
+    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
+    options: ReplaceOptions,
+    force: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::{query::ListQuery, regex::Regex, replace};
+
+    if options.search.is_empty() {
+        writeln!(out, "--search must not be empty")?;
+        return Ok(());
+    }
+    let ignore_case = !options.case_sensitive;
+    let pattern = if options.regex {
+        match Regex::new(options.search, ignore_case) {
+            Ok(r) => r,
+            Err(e) => {
+                writeln!(out, "invalid --search pattern: {e}")?;
+                return Ok(());
+            }
+        }
+    } else {
+        Regex::literal(options.search, ignore_case)
+    };
+    let mut fields = replace::Fields::default();
+    if !options.fields.is_empty() {
+        fields = replace::Fields {
+            title: false,
+            notes: false,
+        };
+        for f in options.fields {
+            match f.trim().to_ascii_lowercase().as_str() {
+                "title" => fields.title = true,
+                "notes" => fields.notes = true,
+                other => {
+                    writeln!(out, "unknown field for --in: {other} (use title, notes)")?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    let now = store.now();
+    let mut q = ListQuery::default();
+    if let Some(filter) = options.filter
+        && let Err(e) = q.add_query(filter, now)
+    {
+        writeln!(out, "{e}")?;
+        return Ok(());
+    }
+    let todos = store.find_todos(&q, now);
+    let plan = replace::plan(&todos, &pattern, options.with, !options.regex, fields);
+    if plan.changes.is_empty() {
+        writeln!(out, "No matches.")?;
+        return Ok(());
+    }
+
+    for c in &plan.changes {
+        writeln!(out, "{} {}:", c.id.short(), c.field)?;
+        writeln!(out, "  - {}", c.before.replace('\n', "\n    "))?;
+        writeln!(out, "  + {}", c.after.replace('\n', "\n    "))?;
+    }
+    if !plan.rejected.is_empty() {
+        for r in &plan.rejected {
+            writeln!(out, "{} {}: {}", r.id.short(), r.field, r.reason)?;
+        }
+        writeln!(
+            out,
+            "Nothing replaced: {} change(s) would be invalid.",
+            plan.rejected.len()
+        )?;
+        return Ok(());
+    }
+    let matches: usize = plan.changes.iter().map(|c| c.count).sum();
+    let question = format!(
+        "Replace {matches} match(es) in {} todo(s)?",
+        plan.todo_count()
+    );
+    if !confirmed(ctx, "replace", force, &question, out)? {
+        return Ok(());
+    }
+
+    let count = plan.todo_count();
+    for (id, patch) in plan.patches {
+        store.edit_todo(id, patch)?;
+    }
+    store.repo_mut().save_atomic()?;
+    writeln!(out, "Replaced {matches} match(es) in {count} todo(s)")?;
+    Ok(())
+}
+
+/// `edit --bulk`: round-trip the matching todos through the user's editor.
+fn bulk_edit(
+    ctx: &AppContext,
+    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
+    filters: &[String],
+    force: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::{bulk_edit, query::ListQuery};
+    use std::io::{BufRead, IsTerminal};
+
+    let mut q = ListQuery::default();
+    for f in filters {
+        if let Err(msg) = q.add_filter(f) {
+            writeln!(out, "{msg}")?;
+            return Ok(());
+        }
+    }
+    let todos = store.find_todos(&q, store.now());
+    if todos.is_empty() {
+        writeln!(out, "No todos match.")?;
+        return Ok(());
+    }
+
+    let interactive = std::io::stdin().is_terminal();
+    let ask = |out: &mut dyn Write, prompt: &str| -> Result<String> {
+        write!(out, "{prompt}")?;
+        out.flush()?;
+        let mut line = String::new();
+        std::io::stdin().lock().read_line(&mut line)?;
+        Ok(line.trim().to_ascii_lowercase())
+    };
+
+    let path = std::env::temp_dir().join(format!("rustytodo-bulk-{}.tsv", std::process::id()));
+    std::fs::write(&path, bulk_edit::to_table(&todos))
+        .with_context(|| format!("failed writing {}", path.display()))?;
+
+    let plan = loop {
+        run_editor(&path)?;
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed reading {}", path.display()))?;
+        match bulk_edit::plan(&todos, &text) {
+            Ok(plan) => break plan,
+            Err(errors) => {
+                for e in &errors {
+                    writeln!(out, "line {}: {}", e.line, e.message)?;
+                }
+                if !interactive || ask(out, "Edit again? [Y/n] ")? == "n" {
+                    writeln!(out, "Nothing changed; your edits are in {}", path.display())?;
+                    return Ok(());
+                }
+            }
+        }
+    };
+    let _ = std::fs::remove_file(&path);
+
+    let mut plan = plan;
+    if plan.is_empty() {
+        writeln!(out, "No changes.")?;
+        return Ok(());
+    }
+    if !plan.deletes.is_empty() {
+        writeln!(out, "Rows removed for:")?;
+        for id in &plan.deletes {
+            if let Some(t) = todos.iter().find(|t| t.id == *id) {
+                writeln!(out, "  {}  {}", id.short(), t.title.as_str())?;
+            }
+        }
+        let question = format!("Delete {} todo(s)?", plan.deletes.len());
+        if !confirmed(ctx, "delete", force, &question, out)? {
+            writeln!(out, "Keeping them.")?;
+            plan.deletes.clear();
+        }
+    }
+
+    let mut edited = 0;
+    for e in plan.edits {
+        let mut changed = store.edit_todo(e.id, e.patch)?;
+        match e.done {
+            Some(true) => changed |= store.mark_done(e.id).is_ok(),
+            Some(false) => changed |= store.mark_open(e.id).is_ok(),
+            None => {}
+        }
+        if changed {
+            edited += 1;
+        }
+    }
+    let mut deleted = 0;
+    for id in &plan.deletes {
+        if store.delete(*id).is_ok() {
+            deleted += 1;
+        }
+    }
+    if edited + deleted > 0 {
+        store.repo_mut().save_atomic()?;
+    }
+    writeln!(out, "Edited {edited}, deleted {deleted}.")?;
+    Ok(())
+}
+
+/// What one `import` file holds.
+struct ImportFile {
+    todos: Vec<crate::domain::todo::Todo>,
+    /// Journal entries from `export --include-history`.
+    journal: Vec<crate::infra::journal::JournalEntry>,
+}
+
+/// Load one `import` file, marking todos that don't know their origin as
+/// imported from it.
+fn read_import_file(path: &std::path::Path, format: &str) -> Result<ImportFile> {
+    let mut journal = Vec::new();
+    let mut todos = if format == "csv" {
+        crate::infra::csv_io::import_csv(path)?
+    } else if format == "todoist" {
+        crate::infra::todoist_io::import(path)?
+    } else {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed reading import file: {}", path.display()))?;
+        let todos = crate::infra::db_schema::load_any(&text)?;
+        journal = crate::infra::journal::from_export(&text)?;
+        todos
+    };
 
-            let title = Title::parse(title)?;
-            let mut todo = Todo::new(title);
+    // Keep the origin of todos that already know it (e.g. a JSON backup).
+    let file = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |f| f.to_string_lossy().into_owned(),
+    );
+    for t in todos.iter_mut().filter(|t| t.source.is_none()) {
+        t.source = Some(crate::domain::todo::Source::Import(file.clone()));
+    }
+    Ok(ImportFile { todos, journal })
+}
 
-            if let Some(p) = project {
-                todo.project = ProjectName::parse(p)?;
-            }
+fn board(
+    store: &mut Store<impl TodoRepository>,
+    project: Option<&str>,
+    limit: usize,
+    style: &Style,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::workflow::columns;
 
-            if let Some(n) = notes {
-                todo.notes = Some(Notes::parse(n)?)
-            }
+    let todos: Vec<_> = store
+        .list_todos()
+        .into_iter()
+        .filter(|t| project.is_none_or(|p| t.project.as_str().eq_ignore_ascii_case(p.trim())))
+        .collect();
+    if todos.is_empty() {
+        writeln!(out, "No todos.")?;
+        return Ok(());
+    }
+    for (i, column) in columns(&todos, &store.projects()).iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        writeln!(
+            out,
+            "{} ({})",
+            column.name.to_uppercase(),
+            column.todos.len()
+        )?;
+        for todo in column.todos.iter().take(limit) {
+            writeln!(out, "  {}  {}", todo.id.short(), display_title(todo, style))?;
+        }
+        if column.todos.len() > limit {
+            writeln!(out, "  … and {} more", column.todos.len() - limit)?;
+        }
+    }
+    Ok(())
+}
 
-            if !tags.is_empty() {
-                let mut set = BTreeSet::new();
-                for t in tags {
-                    set.insert(Tag::parse(t)?);
-                }
-                todo.tags = set;
-            }
+/// Width of one quadrant column in `matrix`.
+const QUADRANT_WIDTH: usize = 40;
 
-            if let Some(p) = priority {
-                todo.priority = Priority::parse(p)?;
-            }
+fn matrix(
+    ctx: &AppContext,
+    store: &mut Store<impl TodoRepository>,
+    within: time::Duration,
+    project: Option<&str>,
+    limit: usize,
+    format: &str,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::matrix::matrix;
 
-            if let Some(d) = due {
-                todo.due = Some(DueAt::parse_rfc3339(d)?);
-            }
+    let Some(json) = output_format(format, out)? else {
+        return Ok(());
+    };
+    let todos: Vec<_> = store
+        .list_todos()
+        .into_iter()
+        .filter(|t| project.is_none_or(|p| t.project.as_str().eq_ignore_ascii_case(p.trim())))
+        .collect();
+    let cells = matrix(&todos, store.now(), within);
 
-            // For now we insert the constructed todo directly.
-            // Later, add/edit will be proper use-cases with validation + events.
-            let id = todo.id;
-            store.insert_todo(todo);
-            store.repo_mut().save_atomic()?;
-            info!("Todo added");
-            println!("Added {}", id.short());
+    if json {
+        let value: serde_json::Map<_, _> = cells
+            .iter()
+            .map(|(q, todos)| {
+                let key = q.label().to_ascii_lowercase().replace(' ', "_");
+                (key, serde_json::json!(todos))
+            })
+            .collect();
+        writeln!(out, "{}", serde_json::to_string_pretty(&value)?)?;
+        return Ok(());
+    }
+
+    let style = Style::from_config(&ctx.config);
+    let w = QUADRANT_WIDTH;
+    let header = format!("{:<15}{:<w$}{}", "", "URGENT", "NOT URGENT");
+    writeln!(out, "{}", style.paint(Role::Header, header.trim_end()))?;
+    for (row, pair) in cells.chunks(2).enumerate() {
+        let side = if row == 0 {
+            "IMPORTANT"
+        } else {
+            "NOT IMPORTANT"
+        };
+        // Each cell as lines: its heading, then its todos.
+        let lines: Vec<Vec<String>> = pair
+            .iter()
+            .map(|(q, todos)| {
+                let mut lines = vec![format!("{} {} ({})", q.number(), q.label(), todos.len())];
+                for todo in todos.iter().take(limit) {
+                    let cell = format!("{}  {}", todo.id.short(), todo.title.as_str());
+                    lines.push(clip(&cell, w - 2));
+                }
+                if todos.len() > limit {
+                    lines.push(format!("… and {} more", todos.len() - limit));
+                }
+                lines
+            })
+            .collect();
+        let height = lines.iter().map(Vec::len).max().unwrap_or(0);
+        writeln!(out)?;
+        for i in 0..height {
+            let left = lines[0].get(i).map_or("", String::as_str);
+            let right = lines
+                .get(1)
+                .and_then(|l| l.get(i))
+                .map_or("", String::as_str);
+            let label = if i == 0 { side } else { "" };
+            let pad = w.saturating_sub(left.chars().count());
+            let line = format!("{label:<15}{left}{}{right}", " ".repeat(pad));
+            if i == 0 {
+                writeln!(out, "{}", style.paint(Role::Header, line.trim_end()))?;
+            } else {
+                writeln!(out, "{}", line.trim_end())?;
+            }
         }
+    }
+    Ok(())
+}
 
-        Commands::List {
-            format,
-            status,
-            project,
-            tag,
-            search,
-            overdue,
-            priority,
-            sort,
-            desc,
-        } => {
-            use crate::app::query::{ListQuery, SortKey, StatusFilter, apply_list_query};
-            use crate::domain::todo::Priority;
+fn matrix_move(
+    ctx: &AppContext,
+    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
+    within: time::Duration,
+    quadrant: &str,
+    ids: &[String],
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::matrix::{Quadrant, move_to};
 
-            let now = time::OffsetDateTime::now_utc();
+    let Some(to) = Quadrant::parse(quadrant) else {
+        writeln!(
+            out,
+            "unknown quadrant {quadrant} (use do|schedule|delegate|eliminate, or 1-4)"
+        )?;
+        return Ok(());
+    };
+    let todos = store.list_todos();
+    let now = store.now();
+    let mut changed = 0;
+    for (id, todo_id) in resolve_ids(&todos, ids, "Move", out)? {
+        let Some(todo) = todos.iter().find(|t| t.id == todo_id) else {
+            writeln!(out, "{id}: todo not found")?;
+            continue;
+        };
+        let patch = move_to(todo, to, now, within);
+        let mut what = Vec::new();
+        if let Some(p) = patch.priority {
+            what.push(format!("priority {}", priorities(ctx).label(p)));
+        }
+        if let Some(Some(due)) = patch.due {
+            what.push(format!("due {}", local_rfc3339(ctx, due.as_dt())));
+        }
+        if what.is_empty() {
+            writeln!(out, "{id} is already in {}", to.label())?;
+            continue;
+        }
+        store.edit_todo(todo_id, patch)?;
+        changed += 1;
+        writeln!(out, "Moved {id} to {} ({})", to.label(), what.join(", "))?;
+    }
+    if changed > 0 {
+        store.repo_mut().save_atomic()?;
+    }
+    print_warnings(ctx, &store.take_warnings(), out)
+}
 
-            // Parse status flag
-            let status = match status.as_deref().map(|s| s.trim().to_ascii_lowercase()) {
-                None => None,
-                Some(s) if s == "open" => Some(StatusFilter::Open),
-                Some(s) if s == "done" => Some(StatusFilter::Done),
-                Some(other) => {
-                    writeln!(out, "unknown --status {other} (use open|done)")?;
-                    return Ok(());
-                }
-            };
+/// Notes from the file at `path` (`--notes-file`). At most twice the notes
+/// limit is read, so pointing it at something huge fails fast.
+fn read_notes_file(path: &str) -> Result<crate::domain::todo::Notes> {
+    use crate::domain::{errors::DomainError, todo::Notes};
+    use std::io::Read;
 
-            // Parse priority
-            let priority = match priority {
-                None => None,
-                Some(p) => Some(Priority::parse(p).map_err(|e| anyhow::anyhow!(e))?),
-            };
+    let limit = 2 * Notes::MAX_LEN as u64;
+    let file =
+        std::fs::File::open(path).with_context(|| format!("failed opening notes file: {path}"))?;
+    let mut bytes = Vec::new();
+    file.take(limit + 1)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("failed reading notes file: {path}"))?;
+    if bytes.len() as u64 > limit {
+        return Err(DomainError::NotesTooLong {
+            max: Notes::MAX_LEN,
+        })
+        .with_context(|| format!("notes file {path}"));
+    }
+    let text =
+        String::from_utf8(bytes).with_context(|| format!("notes file {path} is not UTF-8 text"))?;
+    Notes::parse(text).with_context(|| format!("notes file {path}"))
+}
 
-            // Parse sort key
-            let sort_key = match sort.trim().to_ascii_lowercase().as_str() {
-                "due" => SortKey::Due,
-                "priority" => SortKey::Priority,
-                "created" => SortKey::Created,
-                other => {
-                    writeln!(out, "unknown --sort {other} (use due|priority|created)")?;
-                    return Ok(());
-                }
-            };
+/// `text` cut to `width` characters, with an ellipsis if it was longer.
+fn clip(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut clipped: String = text.chars().take(width.saturating_sub(1)).collect();
+    clipped.push('…');
+    clipped
+}
 
-            let q = ListQuery {
-                status,
-                project,
-                tag,
-                search,
-                overdue,
-                priority,
-                sort: sort_key,
-                desc,
-            };
+/// `report <name>`: the `[reports.<name>]` view as a table, to `path` (without
+/// colors) if given.
+fn custom_report(
+    ctx: &AppContext,
+    store: &mut Store<impl TodoRepository>,
+    name: &str,
+    path: Option<&str>,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::{
+        custom_reports::Column,
+        query::{self, effective_priorities},
+        stats::minutes,
+    };
+    use crate::domain::{
+        todo::{Status, Todo, format_minutes},
+        tracking,
+    };
+    use crate::ui::table::{Row, Table};
 
-            let todos = store.list_todos();
-            let todos = apply_list_query(todos, &q, now);
+    let Some((name, definition)) = ctx.config.report(name) else {
+        let names: Vec<&str> = ctx.config.reports.keys().map(String::as_str).collect();
+        if names.is_empty() {
+            writeln!(
+                out,
+                "no report {name} (add [reports.{name}] to config.toml)"
+            )?;
+        } else {
+            writeln!(out, "no report {name} (use {})", names.join(", "))?;
+        }
+        return Ok(());
+    };
+    let now = store.now();
+    let report = match definition.compile(ctx.config.urgency.clone(), ctx.local(now)) {
+        Ok(r) => r,
+        Err(e) => {
+            writeln!(out, "[reports.{name}] {e}")?;
+            return Ok(());
+        }
+    };
+    let todos = store.find_todos(&report.query, now);
 
-            match format.trim().to_ascii_lowercase().as_str() {
-                "json" => {
-                    let s = serde_json::to_string_pretty(&todos)
-                        .with_context(|| "failed serializing todos to json")?;
-                    writeln!(out, "{s}")?;
-                }
-                "table" => {
-                    if todos.is_empty() {
-                        writeln!(out, "No matching todos.")?;
-                    } else {
-                        writeln!(
-                            out,
-                            "{:<10} {:<2} {:<3} {:<8} {:<10} {:<18} {:<25} {}",
-                            "ID", "S", "P", "!", "PROJECT", "TAGS", "DUE", "TITLE"
-                        )?;
+    let symbols = SymbolSet::from_config(ctx.config.symbols);
+    let scheme = priorities(ctx);
+    let absolute = ctx.config.absolute_dates;
+    let eff = effective_priorities(&store.list_todos());
+    let date = |at: time::OffsetDateTime| ctx.local(at).date().to_string();
+    let cell = |column: Column, todo: &Todo| -> (String, Option<Role>) {
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        match column {
+            Column::Id => (todo.id.short(), Some(Role::Id)),
+            Column::Status => (symbols.status(todo).to_string(), None),
+            Column::Priority => (
+                scheme.label(todo.priority).to_string(),
+                Style::priority_role(todo.priority),
+            ),
+            Column::Project => (todo.project.as_str().to_string(), Some(Role::Project)),
+            Column::Tags => (
+                or_dash((!todo.tags.is_empty()).then(|| {
+                    todo.tags
+                        .iter()
+                        .map(|t| format!("#{}", t.as_str()))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })),
+                Some(Role::Tags),
+            ),
+            Column::Due => {
+                let overdue = todo.is_overdue(now);
+                let due = todo.due.map(|d| match absolute {
+                    true => local_rfc3339(ctx, d.as_dt()),
+                    false => humanize::due(ctx.local(d.as_dt()), now, overdue),
+                });
+                let role = if overdue { Role::Overdue } else { Role::Due };
+                (or_dash(due), Some(role))
+            }
+            Column::State => (
+                or_dash(todo.state.as_ref().map(|s| s.as_str().to_string())),
+                None,
+            ),
+            Column::Energy => (or_dash(todo.energy.map(|e| e.label().to_string())), None),
+            Column::Estimate => (or_dash(todo.estimate.map(|e| e.label())), None),
+            Column::Tracked => (
+                or_dash((!todo.time_entries.is_empty()).then(|| {
+                    format_minutes(minutes(tracking::total(&todo.time_entries, None, now)))
+                })),
+                None,
+            ),
+            Column::Ref => (
+                or_dash(todo.external_ref.as_ref().map(|r| r.to_string())),
+                None,
+            ),
+            Column::Urgency => {
+                let p = eff.get(&todo.id).map_or(todo.priority, |e| e.priority);
+                let score = query::urgency(todo, p, now, &report.query.urgency);
+                (format!("{score:.1}"), None)
+            }
+            Column::Created => (date(todo.created_at), None),
+            Column::Completed => match todo.status {
+                Status::Done { completed_at } => (date(completed_at), None),
+                Status::Open => ("-".to_string(), None),
+            },
+            Column::Title => (display_title(todo, &Style::plain()), None),
+        }
+    };
 
-                        for todo in todos {
-                            let due = todo
-                                .due
-                                .map(|d| d.format_rfc3339())
-                                .unwrap_or_else(|| "-".to_string());
+    let mut table = Table::new(report.columns.iter().map(|c| c.header()));
+    let groups = match report.group_by {
+        Some(by) => query::group(todos, by, now)
+            .into_iter()
+            .map(|(key, todos)| {
+                let label = key.label(|p| scheme.label(p).to_string());
+                (Some(format!("{label} ({})", todos.len())), todos)
+            })
+            .collect(),
+        None => vec![(None, todos)],
+    };
+    for (heading, todos) in groups {
+        table.section(heading);
+        for todo in &todos {
+            table.row(Row {
+                cells: report.columns.iter().map(|c| cell(*c, todo)).collect(),
+                dim: todo.status.is_done(),
+            });
+        }
+    }
 
-                            let overdue_mark = if todo.is_overdue(now) { "OVERDUE" } else { "" };
+    let style = match path {
+        Some(_) => Style::plain(),
+        None => Style::from_config(&ctx.config),
+    };
+    let mut text = Vec::new();
+    if let Some(about) = &definition.description {
+        writeln!(text, "{}\n", style.paint(Role::Header, about))?;
+    }
+    if table.is_empty() {
+        writeln!(text, "No matching todos.")?;
+    } else {
+        table.write(&style, &mut text)?;
+    }
+    match path {
+        Some(p) => {
+            std::fs::write(p, text).with_context(|| format!("failed writing report: {p}"))?;
+            writeln!(out, "Wrote report {name} to {p}")?;
+        }
+        None => out.write_all(&text)?,
+    }
+    Ok(())
+}
 
-                            let tags = if todo.tags.is_empty() {
-                                "-".to_string()
-                            } else {
-                                todo.tags
-                                    .iter()
-                                    .map(|t| format!("#{}", t.as_str()))
-                                    .collect::<Vec<_>>()
-                                    .join(",")
-                            };
+fn stats_overview(
+    store: &mut Store<impl TodoRepository>,
+    days: u32,
+    format: &str,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::stats::{CountRow, minutes, overview};
+    use crate::domain::todo::format_minutes;
 
-                            writeln!(
-                                out,
-                                "{:<10} {:<2} {:<3} {:<8} {:<10} {:<18} {:<25} {}",
-                                todo.id.short(),
-                                todo.status_symbol(),
-                                todo.priority.label(),
-                                overdue_mark,
-                                todo.project.as_str(),
-                                tags,
-                                due,
-                                todo.title.as_str()
-                            )?;
-                        }
-                    }
-                }
-                other => {
-                    writeln!(out, "unknown list format: {other} (use table|json)")?;
+    let o = overview(&store.list_todos(), days, store.now());
+    let avg = o.avg_time_to_complete;
+    match format.trim().to_ascii_lowercase().as_str() {
+        "json" => {
+            let rows = |rows: &[CountRow]| -> Vec<serde_json::Value> {
+                rows.iter()
+                    .map(|r| {
+                        serde_json::json!({
+                            "key": r.key,
+                            "open": r.open,
+                            "done": r.done,
+                            "overdue": r.overdue,
+                        })
+                    })
+                    .collect()
+            };
+            let value = serde_json::json!({
+                "total": o.total,
+                "open": o.open,
+                "done": o.done,
+                "overdue": o.overdue,
+                "by_project": rows(&o.by_project),
+                "by_tag": rows(&o.by_tag),
+                "by_priority": rows(&o.by_priority),
+                "days": o.days,
+                "created": o.created,
+                "completed": o.completed,
+                "completion_rate": o.completion_rate,
+                "avg_minutes_to_complete": avg.map(minutes),
+            });
+            let s = serde_json::to_string_pretty(&value)
+                .with_context(|| "failed serializing stats to json")?;
+            writeln!(out, "{s}")?;
+        }
+        "table" => {
+            writeln!(
+                out,
+                "Todos: {} ({} open, {} done, {} overdue)",
+                o.total, o.open, o.done, o.overdue
+            )?;
+            for (label, rows) in [
+                ("PROJECT", &o.by_project),
+                ("TAG", &o.by_tag),
+                ("PRIORITY", &o.by_priority),
+            ] {
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "{label:<24} {:>6} {:>6} {:>8}",
+                    "OPEN", "DONE", "OVERDUE"
+                )?;
+                for r in rows {
+                    writeln!(
+                        out,
+                        "{:<24} {:>6} {:>6} {:>8}",
+                        r.key, r.open, r.done, r.overdue
+                    )?;
                 }
             }
+            writeln!(out)?;
+            writeln!(out, "Last {} days:", o.days)?;
+            writeln!(out, "  Created:   {}", o.created)?;
+            writeln!(out, "  Completed: {}", o.completed)?;
+            if let Some(rate) = o.completion_rate {
+                writeln!(
+                    out,
+                    "  Completion rate: {:.0}% of todos created",
+                    rate * 100.0
+                )?;
+            }
+            if let Some(avg) = avg {
+                let shown = if avg >= time::Duration::days(1) {
+                    format!("{:.1} days", avg.as_seconds_f64() / 86_400.0)
+                } else {
+                    format_minutes(minutes(avg))
+                };
+                writeln!(out, "  Average time to complete: {shown}")?;
+            }
         }
+        other => writeln!(out, "unknown --format {other} (use table|json)")?,
+    }
+    Ok(())
+}
 
-        Commands::Show { id, format } => {
-            let todos = store.list_todos();
-            let todo_id = match resolve_id_input(&todos, &id) {
-                Ok(x) => x,
-                Err(msg) => {
-                    writeln!(out, "{msg}")?;
-                    return Ok(());
-                }
-            };
+fn routine_command(ctx: &AppContext, action: RoutineAction, out: &mut dyn Write) -> Result<()> {
+    use crate::domain::routine::Routine;
+    use crate::infra::routines_file;
 
-            let Some(todo) = store.repo_mut().get(todo_id) else {
-                writeln!(out, "todo not found")?;
-                return Ok(());
+    let path = routines_file::routines_path(&ctx.config.resolve_db_path(&ctx.paths));
+    let mut routines = routines_file::load(&path)?;
+    let today = ctx.now().date();
+    let symbols = SymbolSet::from_config(ctx.config.symbols);
+
+    // Reports a missing or ambiguous name itself.
+    let find = |routines: &[Routine], name: &str, out: &mut dyn Write| -> Result<Option<usize>> {
+        let hits: Vec<usize> = (0..routines.len())
+            .filter(|&i| routines[i].answers_to(name))
+            .collect();
+        let exact = hits
+            .iter()
+            .copied()
+            .find(|&i| routines[i].name.eq_ignore_ascii_case(name.trim()));
+        match (exact, hits.as_slice()) {
+            (Some(i), _) | (None, &[i]) => return Ok(Some(i)),
+            (None, []) => writeln!(out, "No routine called {name:?} (see `routine list`).")?,
+            (None, _) => writeln!(
+                out,
+                "{name:?} matches several routines: {}",
+                hits.iter()
+                    .map(|&i| routines[i].name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?,
+        }
+        Ok(None)
+    };
+    let print = |out: &mut dyn Write, r: &Routine| -> Result<()> {
+        let done = r.run(today).map(|run| &run.done);
+        let ticked = |s: &String| done.is_some_and(|d| d.contains(s));
+        writeln!(
+            out,
+            "{} ({today}): {}/{}",
+            r.name,
+            r.steps.iter().filter(|s| ticked(s)).count(),
+            r.steps.len()
+        )?;
+        for (i, step) in r.steps.iter().enumerate() {
+            let mark = if ticked(step) {
+                symbols.done()
+            } else {
+                symbols.open()
             };
+            writeln!(out, "  {mark} {}. {step}", i + 1)?;
+        }
+        let streak = r.streak(today);
+        writeln!(
+            out,
+            "Streak: {} day(s) (best {}, {} complete overall)",
+            streak.current, streak.best, streak.completed
+        )?;
+        Ok(())
+    };
 
-            match format.trim().to_ascii_lowercase().as_str() {
-                "json" => {
-                    let s = serde_json::to_string_pretty(&todo)
-                        .with_context(|| "failed serializing todo to json")?;
-                    writeln!(out, "{s}")?;
+    match action {
+        RoutineAction::List => {
+            if routines.is_empty() {
+                writeln!(out, "No routines yet (add one with `routine add`).")?;
+                return Ok(());
+            }
+            writeln!(
+                out,
+                "{:<24} {:>6} {:>7} {:>5}",
+                "ROUTINE", "TODAY", "STREAK", "BEST"
+            )?;
+            for r in &routines {
+                let done = r.run(today).map_or(0, |run| {
+                    r.steps.iter().filter(|s| run.done.contains(*s)).count()
+                });
+                let streak = r.streak(today);
+                writeln!(
+                    out,
+                    "{:<24} {:>6} {:>7} {:>5}",
+                    r.name,
+                    format!("{done}/{}", r.steps.len()),
+                    streak.current,
+                    streak.best
+                )?;
+            }
+            return Ok(());
+        }
+        RoutineAction::Add { name, steps } => {
+            let routine = Routine::new(&name, steps)?;
+            match routines
+                .iter_mut()
+                .find(|r| r.name.eq_ignore_ascii_case(routine.name.trim()))
+            {
+                Some(existing) => {
+                    existing.steps = routine.steps;
+                    writeln!(out, "Updated routine {}", existing.name)?;
                 }
-                "table" => {
-                    // Human friendly details
-                    writeln!(out, "ID:       {}", todo.id.as_uuid_str())?;
-                    writeln!(out, "Short:    {}", todo.id.short())?;
-                    writeln!(
-                        out,
-                        "Status:   {}",
-                        if todo.status.is_done() {
-                            "Done"
-                        } else {
-                            "Open"
-                        }
-                    )?;
-                    writeln!(out, "Priority: {}", todo.priority.label())?;
-                    writeln!(out, "Project:  {}", todo.project.as_str())?;
+                None => {
                     writeln!(
                         out,
-                        "Due:      {}",
-                        todo.due
-                            .map(|d| d.format_rfc3339())
-                            .unwrap_or_else(|| "-".to_string())
+                        "Added routine {} ({} steps)",
+                        routine.name,
+                        routine.steps.len()
                     )?;
-
-                    let tags = if todo.tags.is_empty() {
-                        "-".to_string()
-                    } else {
-                        todo.tags
-                            .iter()
-                            .map(|t| format!("#{}", t.as_str()))
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    };
-                    writeln!(out, "Tags:     {tags}")?;
-
-                    writeln!(out, "Title:    {}", todo.title.as_str())?;
-                    if let Some(n) = &todo.notes {
-                        writeln!(out, "Notes:\n{}\n", n.as_str())?;
-                    }
-                }
-                other => {
-                    writeln!(out, "unknown show format: {other} (use table|json)")?;
+                    routines.push(routine);
                 }
             }
         }
-
-        Commands::Edit {
-            id,
-            title,
-            notes,
-            clear_notes,
-            project,
-            priority,
-            due,
-            clear_due,
-            tags,
-            clear_tags,
-        } => {
-            use crate::domain::todo::{DueAt, Notes, Priority, ProjectName, Tag, Title, TodoPatch};
-            use std::collections::BTreeSet;
-
-            let todos = store.list_todos();
-            let todo_id = match resolve_id_input(&todos, &id) {
-                Ok(x) => x,
-                Err(msg) => {
-                    println!("{msg}");
-                    return Ok(());
-                }
+        RoutineAction::Start { name } => {
+            let Some(i) = find(&routines, &name, out)? else {
+                return Ok(());
             };
-
-            let mut patch = TodoPatch::default();
-
-            if let Some(t) = title {
-                patch.title = Some(Title::parse(t)?)
+            routines[i].start(today);
+            print(out, &routines[i])?;
+        }
+        RoutineAction::Check { name, steps, undo } => {
+            let Some(i) = find(&routines, &name, out)? else {
+                return Ok(());
+            };
+            for step in &steps {
+                routines[i].check(today, step, !undo)?;
             }
+            print(out, &routines[i])?;
+            if !undo && routines[i].is_complete(today) {
+                writeln!(out, "All done for today.")?;
+            }
+        }
+        RoutineAction::Show { name } => {
+            if let Some(i) = find(&routines, &name, out)? {
+                print(out, &routines[i])?;
+            }
+            return Ok(());
+        }
+        RoutineAction::Remove { name } => {
+            let Some(i) = find(&routines, &name, out)? else {
+                return Ok(());
+            };
+            let removed = routines.remove(i);
+            writeln!(out, "Removed routine {}", removed.name)?;
+        }
+    }
+    routines_file::save(&path, &routines)
+}
 
-            if clear_notes {
-                patch.notes = Some(None);
-            } else if let Some(n) = notes {
-                patch.notes = Some(Some(Notes::parse(n)?));
+fn token_command(
+    ctx: &AppContext,
+    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
+    action: TokenAction,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::infra::api_tokens::{self, Scope};
+    use time::format_description::well_known::Rfc3339;
+
+    let file = api_tokens::path(&ctx.paths);
+    let mut tokens = api_tokens::load(&file)?;
+    match action {
+        TokenAction::Create {
+            name,
+            scope,
+            rate_limit,
+        } => {
+            let Some(scope) = Scope::parse(&scope) else {
+                writeln!(out, "unknown --scope {scope} (use read|write|admin)")?;
+                return Ok(());
+            };
+            let secret = match api_tokens::create(&mut tokens, &name, scope, store.now()) {
+                Ok(secret) => secret,
+                Err(e) => {
+                    writeln!(out, "{e}")?;
+                    return Ok(());
+                }
+            };
+            if let Some(token) = tokens.last_mut() {
+                token.rate_limit = rate_limit;
             }
-
-            if let Some(p) = project {
-                patch.project = Some(ProjectName::parse(p)?);
+            api_tokens::save(&file, &tokens)?;
+            writeln!(out, "Created {} token {}:", scope.label(), name.trim())?;
+            writeln!(out, "{secret}")?;
+            writeln!(
+                out,
+                "It won't be shown again; pass it as `Authorization: Bearer <token>`."
+            )?;
+        }
+        TokenAction::Revoke { name } => {
+            if api_tokens::revoke(&mut tokens, &name) {
+                api_tokens::save(&file, &tokens)?;
+                writeln!(out, "Revoked token {}", name.trim())?;
+            } else {
+                writeln!(out, "No token named {}", name.trim())?;
             }
-            if let Some(p) = priority {
-                patch.priority = Some(Priority::parse(p)?);
+        }
+        TokenAction::List => {
+            if tokens.is_empty() {
+                writeln!(
+                    out,
+                    "No API tokens; `serve` is open to anyone who can reach it."
+                )?;
             }
-
-            if clear_due {
-                patch.due = Some(None);
-            } else if let Some(d) = due {
-                patch.due = Some(Some(DueAt::parse_rfc3339(d)?));
+            for t in &tokens {
+                let created = t.created.format(&Rfc3339).unwrap_or_default();
+                let limit = match t.rate_limit {
+                    None => String::new(),
+                    Some(0) => ", no rate limit".to_string(),
+                    Some(n) => format!(", {n} requests/min"),
+                };
+                writeln!(
+                    out,
+                    "{:<20} {:<6} created {created}{limit}",
+                    t.name,
+                    t.scope.label()
+                )?;
             }
+        }
+    }
+    Ok(())
+}
 
-            if clear_tags {
-                patch.tags = Some(BTreeSet::new());
-            } else if !tags.is_empty() {
-                let mut set = BTreeSet::new();
-                for t in tags {
-                    set.insert(Tag::parse(t)?);
-                }
-                patch.tags = Some(set);
-            }
+fn backup_command(
+    ctx: &AppContext,
+    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
+    action: BackupAction,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::infra::{backup, db_crypto};
 
-            let changed = store.edit_todo(todo_id, patch)?;
-            if changed {
-                store.repo_mut().save_atomic()?;
-                println!("Edited {}", id);
-            } else {
-                println!("Failed to edit {}", id);
-            }
+    let config = &ctx.config.backup;
+    let spec = match &action {
+        BackupAction::Push { target } | BackupAction::List { target } => target,
+        BackupAction::Pull { target, .. } => target,
+    };
+    let spec = config.targets.get(spec.trim()).unwrap_or(spec);
+    let target = match backup::target(spec) {
+        Ok(t) => t,
+        Err(e) => {
+            writeln!(out, "{e}")?;
+            return Ok(());
         }
+    };
+    let passphrase = || -> Result<db_crypto::DbKey> {
+        let command = ctx.config.encryption_passphrase_command.as_deref();
+        db_crypto::DbKey::new(&db_crypto::passphrase(command, false)?)
+    };
 
-        Commands::Done { id } => {
-            let todos = store.list_todos();
-            let todo_id = match resolve_id_input(&todos, &id) {
-                Ok(x) => x,
-                Err(msg) => {
-                    println!("{msg}");
-                    return Ok(());
-                }
+    match action {
+        BackupAction::Push { .. } => {
+            let db_path = ctx.config.resolve_db_path(&ctx.paths);
+            let key = match store.repo_mut().key().cloned() {
+                Some(key) if config.encrypt => Some(key),
+                None if config.encrypt => Some(passphrase()?),
+                _ => None,
             };
-
-            match store.mark_done(todo_id) {
-                Ok(()) => {
-                    store.repo_mut().save_atomic()?;
-                    println!("Done {}", id);
-                }
-                Err(e) => {
-                    println!("{e}");
-                }
+            let now = time::OffsetDateTime::now_utc();
+            let pushed = backup::push(
+                &*target,
+                &ctx.paths.data_dir,
+                &db_path,
+                key.as_ref(),
+                config.keep,
+                now,
+            )?;
+            writeln!(
+                out,
+                "Pushed {} ({} files, {} KB{}) to {}",
+                pushed.name,
+                pushed.files,
+                pushed.bytes.div_ceil(1024),
+                if key.is_some() { ", encrypted" } else { "" },
+                target.describe()
+            )?;
+            if pushed.pruned > 0 {
+                writeln!(out, "Removed {} old backup(s)", pushed.pruned)?;
             }
         }
+        BackupAction::List { .. } => {
+            let all = backup::list(&*target)?;
+            if all.is_empty() {
+                writeln!(out, "No backups in {}", target.describe())?;
+            }
+            for (name, _) in all.iter().rev() {
+                writeln!(out, "{name}")?;
+            }
+        }
+        BackupAction::Pull { name, out: dir, .. } => {
+            let name = match name {
+                Some(n) => n,
+                None => match backup::list(&*target)?.pop() {
+                    Some((n, _)) => n,
+                    None => {
+                        writeln!(out, "No backups in {}", target.describe())?;
+                        return Ok(());
+                    }
+                },
+            };
+            let dir = std::path::PathBuf::from(dir);
+            let key = store.repo_mut().key().cloned();
+            let written = backup::pull(&*target, &name, &dir, || match key {
+                Some(k) => Ok(k),
+                None => passphrase(),
+            })?;
+            writeln!(
+                out,
+                "Unpacked {name} ({written} files) into {}",
+                dir.display()
+            )?;
+        }
+    }
+    Ok(())
+}
 
-        Commands::Undone { id } => {
-            let todos = store.list_todos();
-            let todo_id = match resolve_id_input(&todos, &id) {
-                Ok(x) => x,
-                Err(msg) => {
-                    println!("{msg}");
+fn logs_command(ctx: &AppContext, action: LogsAction, out: &mut dyn Write) -> Result<()> {
+    use crate::infra::logfile::{self, FILE_NAME, LogEntry};
+
+    let dir = logfile::log_dir(&ctx.paths.data_dir);
+    let entries = logfile::read_entries(&dir)
+        .with_context(|| format!("failed reading logs in {}", dir.display()))?;
+    if entries.is_empty() && !ctx.config.log.file {
+        writeln!(
+            out,
+            "No log file yet: set file = true under [log] in config.toml."
+        )?;
+        return Ok(());
+    }
+    let last = |entries: Vec<LogEntry>, n: usize| -> Vec<LogEntry> {
+        let skip = if n == 0 {
+            0
+        } else {
+            entries.len().saturating_sub(n)
+        };
+        entries.into_iter().skip(skip).collect()
+    };
+
+    match action {
+        LogsAction::Show {
+            lines,
+            level,
+            format,
+        } => {
+            let min = match level.as_deref().map(logfile::parse_level) {
+                None => tracing::Level::TRACE,
+                Some(Some(l)) => l,
+                Some(None) => {
+                    writeln!(
+                        out,
+                        "unknown --level {} (use error|warn|info|debug|trace)",
+                        level.unwrap_or_default()
+                    )?;
                     return Ok(());
                 }
             };
-
-            match store.mark_open(todo_id) {
-                Ok(()) => {
-                    store.repo_mut().save_atomic()?;
-                    println!("Undone {}", id);
+            let entries: Vec<_> = entries
+                .into_iter()
+                .filter(|e| e.level().is_some_and(|l| l <= min))
+                .collect();
+            let json = match format.trim().to_ascii_lowercase().as_str() {
+                "text" => false,
+                "json" => true,
+                other => {
+                    writeln!(out, "unknown logs format: {other} (use text|json)")?;
+                    return Ok(());
                 }
-                Err(e) => {
-                    println!("{e}");
+            };
+            for e in last(entries, lines) {
+                if json {
+                    writeln!(out, "{}", serde_json::to_string(&e)?)?;
+                } else {
+                    writeln!(out, "{}", e.render())?;
                 }
             }
         }
-
-        Commands::Delete { id, yes } => {
-            if !yes {
-                // Minimal confirmation: require explicit flag.
-                // (Better interactive prompts later; this is safe & scriptable.)
-                println!("Refusing to delete without confirmation. Re-run with --yes.");
+        LogsAction::Tail { lines, follow } => {
+            for e in last(entries, lines) {
+                writeln!(out, "{}", e.render())?;
+            }
+            if !follow {
                 return Ok(());
             }
+            out.flush()?;
 
-            let todos = store.list_todos();
-            let todo_id = match resolve_id_input(&todos, &id) {
-                Ok(x) => x,
-                Err(msg) => {
-                    println!("{msg}");
-                    return Ok(());
+            // Poll the current file; a shorter file means it was rotated.
+            let path = dir.join(FILE_NAME);
+            let mut offset = std::fs::metadata(&path).map_or(0, |m| m.len());
+            loop {
+                std::thread::sleep(std::time::Duration::from_millis(500));
+                let len = std::fs::metadata(&path).map_or(0, |m| m.len());
+                if len < offset {
+                    offset = 0;
                 }
-            };
-
-            match store.delete(todo_id) {
-                Ok(()) => {
-                    store.repo_mut().save_atomic()?;
-                    println!("Deleted {}", id);
+                if len == offset {
+                    continue;
                 }
-                Err(e) => {
-                    println!("{e}");
+                let bytes = std::fs::read(&path)
+                    .with_context(|| format!("failed reading {}", path.display()))?;
+                let Some(text) = bytes.get(offset as usize..) else {
+                    continue;
+                };
+                // Leave a half-written last line for the next round.
+                let complete = text.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+                for e in logfile::parse_lines(&String::from_utf8_lossy(&text[..complete])) {
+                    writeln!(out, "{}", e.render())?;
                 }
+                out.flush()?;
+                offset += complete as u64;
             }
         }
+    }
+    Ok(())
+}
 
-        Commands::Export { format, out } => {
-            use std::path::PathBuf;
+fn project_command(
+    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
+    action: ProjectAction,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::projects::overview;
+    use crate::domain::todo::{Color, ProjectName, WorkflowState};
 
-            let out_path = PathBuf::from(out);
-            let todos = store.list_todos();
+    let rows = overview(&store.projects(), &store.list_todos());
+    // Existing projects keep their spelling; `edit` may also start a new one.
+    let known = |name: &str| {
+        rows.iter()
+            .find(|r| r.project.is_named(name))
+            .map(|r| r.project.name.clone())
+    };
 
+    let archive = matches!(action, ProjectAction::Archive { .. });
+    match action {
+        ProjectAction::List { all, format } => {
+            let rows: Vec<_> = rows.iter().filter(|r| all || !r.project.archived).collect();
             match format.trim().to_ascii_lowercase().as_str() {
                 "json" => {
-                    let json = crate::infra::db_schema::write_current(&todos)?;
-
-                    if let Some(parent) = out_path.parent() {
-                        if !parent.as_os_str().is_empty() {
-                            std::fs::create_dir_all(parent).with_context(|| {
-                                format!("failed creating export directory: {}", parent.display())
-                            })?;
+                    let items: Vec<_> = rows
+                        .iter()
+                        .map(|r| {
+                            serde_json::json!({
+                                "name": r.project.name.as_str(),
+                                "description": r.project.description,
+                                "color": r.project.color.map(Color::label),
+                                "archived": r.project.archived,
+                                "states": r.project.workflow().iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+                                "open": r.open,
+                                "done": r.done,
+                            })
+                        })
+                        .collect();
+                    writeln!(out, "{}", serde_json::to_string_pretty(&items)?)?;
+                }
+                "table" => {
+                    if rows.is_empty() {
+                        writeln!(out, "No projects.")?;
+                        return Ok(());
+                    }
+                    writeln!(
+                        out,
+                        "{:<20} {:>5} {:>5} {:<8} DESCRIPTION",
+                        "PROJECT", "OPEN", "DONE", "COLOR"
+                    )?;
+                    for r in rows {
+                        let mut description = r.project.description.clone().unwrap_or_default();
+                        if r.project.archived {
+                            description = format!("(archived) {description}");
                         }
+                        let line = format!(
+                            "{:<20} {:>5} {:>5} {:<8} {}",
+                            r.project.name.as_str(),
+                            r.open,
+                            r.done,
+                            r.project.color.map_or("-", Color::label),
+                            description
+                        );
+                        writeln!(out, "{}", line.trim_end())?;
                     }
-
-                    std::fs::write(&out_path, json).with_context(|| {
-                        format!("failed writing export file: {}", out_path.display())
-                    })?;
                 }
-                "csv" => {
-                    crate::infra::csv_io::export_csv(&out_path, &todos)?;
+                other => writeln!(out, "unknown project format: {other} (use table|json)")?,
+            }
+        }
+        ProjectAction::Edit {
+            name,
+            description,
+            clear_description,
+            color,
+            clear_color,
+            states,
+            clear_states,
+        } => {
+            let name = match known(&name) {
+                Some(n) => n,
+                None => ProjectName::parse(&name)?,
+            };
+            let color = color.map(Color::parse).transpose()?;
+            let mut workflow: Vec<WorkflowState> = Vec::new();
+            for s in states.iter().filter(|s| !s.trim().is_empty()) {
+                let s = WorkflowState::parse(s)?;
+                if !workflow.contains(&s) {
+                    workflow.push(s);
                 }
-                other => {
-                    println!("unknown export format: {other} (use json|csv)");
-                    return Ok(());
+            }
+            let description = description
+                .map(|d| d.trim().to_string())
+                .filter(|d| !d.is_empty());
+            store.update_project(&name, |p| {
+                if clear_description {
+                    p.description = None;
+                } else if description.is_some() {
+                    p.description = description;
+                }
+                if clear_color {
+                    p.color = None;
+                } else if color.is_some() {
+                    p.color = color;
+                }
+                if clear_states {
+                    p.states.clear();
+                } else if !workflow.is_empty() {
+                    p.states = workflow;
+                }
+            });
+            store.repo_mut().save_atomic()?;
+            writeln!(out, "Updated project {}", name.as_str())?;
+        }
+        ProjectAction::Rename { from, to } => {
+            let to = ProjectName::parse(&to)?;
+            let merging =
+                known(to.as_str()).filter(|_| !to.as_str().eq_ignore_ascii_case(from.trim()));
+            match store.rename_project(&from, &to) {
+                Ok(moved) => {
+                    store.repo_mut().save_atomic()?;
+                    let verb = if merging.is_some() {
+                        "Merged"
+                    } else {
+                        "Renamed"
+                    };
+                    writeln!(
+                        out,
+                        "{verb} {} into {} ({moved} todo(s) moved)",
+                        from.trim(),
+                        to.as_str()
+                    )?;
                 }
+                Err(e) => writeln!(out, "{}: {e}", from.trim())?,
             }
-
-            println!("Exported {} todos to {}", todos.len(), out_path.display());
         }
+        ProjectAction::Archive { name } | ProjectAction::Unarchive { name } => {
+            let Some(name) = known(&name) else {
+                writeln!(out, "{}: project not found", name.trim())?;
+                return Ok(());
+            };
+            store.update_project(&name, |p| p.archived = archive);
+            store.repo_mut().save_atomic()?;
+            let verb = if archive { "Archived" } else { "Unarchived" };
+            writeln!(out, "{verb} project {}", name.as_str())?;
+        }
+    }
+    Ok(())
+}
 
-        Commands::Import { format, r#in } => {
-            use std::path::PathBuf;
+fn schema_command(
+    db_path: &std::path::Path,
+    action: SchemaAction,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::infra::db_schema::{
+        CURRENT_SCHEMA_VERSION, SUPPORTED_VERSIONS, TIME_FORMAT, describe_changes, describe_fields,
+        parse_version, upgrade, version_of,
+    };
 
-            let in_path = PathBuf::from(r#in);
+    if crate::infra::fs_repo::is_encrypted_on_disk(db_path) {
+        anyhow::bail!(
+            "{} is encrypted; schema commands read the plain file (turn encryption off first)",
+            db_path.display()
+        );
+    }
+    let on_disk = match std::fs::read_to_string(db_path) {
+        Ok(text) => Some(
+            serde_json::from_str::<serde_json::Value>(&text)
+                .with_context(|| format!("failed parsing db file: {}", db_path.display()))?,
+        ),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("failed reading db file: {}", db_path.display()));
+        }
+    };
+    let shards = crate::infra::fs_repo::shard_dir(db_path);
+    let sharded = on_disk.is_none() && shards.is_dir();
 
-            let todos = match format.trim().to_ascii_lowercase().as_str() {
+    match action {
+        SchemaAction::Info { format } => {
+            let fields = describe_fields();
+            let supported: Vec<_> = SUPPORTED_VERSIONS.iter().map(|v| format!("v{v}")).collect();
+            match format.trim().to_ascii_lowercase().as_str() {
                 "json" => {
-                    let text = std::fs::read_to_string(&in_path).with_context(|| {
-                        format!("failed reading import file: {}", in_path.display())
-                    })?;
-                    crate::infra::db_schema::load_any(&text)?
+                    let info = serde_json::json!({
+                        "current_version": CURRENT_SCHEMA_VERSION,
+                        "supported_versions": SUPPORTED_VERSIONS,
+                        "database": db_path,
+                        "sharded": sharded,
+                        "database_version": on_disk.as_ref().map(version_of),
+                        "time_format": TIME_FORMAT,
+                        "fields": fields,
+                    });
+                    writeln!(out, "{}", serde_json::to_string_pretty(&info)?)?;
                 }
-                "csv" => crate::infra::csv_io::import_csv(&in_path)?,
-                other => {
-                    println!("unknown import format: {other} (use json|csv)");
-                    return Ok(());
+                "table" => {
+                    writeln!(out, "Schema version: v{CURRENT_SCHEMA_VERSION}")?;
+                    writeln!(out, "Import reads:   {}", supported.join(", "))?;
+                    match &on_disk {
+                        Some(v) => writeln!(
+                            out,
+                            "Database:       {} (v{})",
+                            db_path.display(),
+                            version_of(v)
+                        )?,
+                        None if sharded => writeln!(
+                            out,
+                            "Database:       {} (sharded by project)",
+                            shards.display()
+                        )?,
+                        None => writeln!(
+                            out,
+                            "Database:       {} (not created yet)",
+                            db_path.display()
+                        )?,
+                    }
+                    writeln!(out)?;
+                    writeln!(
+                        out,
+                        "{:<14} {:<8} {:<9} DESCRIPTION",
+                        "FIELD", "TYPE", "OPTIONAL"
+                    )?;
+                    for f in &fields {
+                        writeln!(
+                            out,
+                            "{:<14} {:<8} {:<9} {}",
+                            f.name,
+                            f.kind,
+                            if f.optional { "yes" } else { "" },
+                            f.description
+                        )?;
+                    }
+                    writeln!(out)?;
+                    writeln!(out, "Times are stored as {TIME_FORMAT}.")?;
                 }
+                other => writeln!(out, "unknown schema format: {other} (use table|json)")?,
+            }
+        }
+        SchemaAction::Upgrade { to, dry_run } => {
+            let target = match to {
+                None => CURRENT_SCHEMA_VERSION,
+                Some(to) => match parse_version(&to) {
+                    Some(v) => v,
+                    None => {
+                        writeln!(out, "invalid --to {to} (use e.g. v2)")?;
+                        return Ok(());
+                    }
+                },
+            };
+            if sharded {
+                writeln!(
+                    out,
+                    "{} is sharded; set shard_by_project = false and run any command to merge it back first.",
+                    shards.display()
+                )?;
+                return Ok(());
+            }
+            let Some(doc) = on_disk else {
+                writeln!(out, "No database at {} yet.", db_path.display())?;
+                return Ok(());
             };
+            let from = version_of(&doc);
+            let (upgraded, steps) = upgrade(doc.clone(), target)?;
+            if steps.is_empty() {
+                writeln!(out, "Database is already at v{target}.")?;
+                return Ok(());
+            }
+
+            if dry_run {
+                let mut at = doc;
+                for step in &steps {
+                    let next = (step.apply)(at.clone())?;
+                    writeln!(out, "v{} -> v{}: {}", step.from, step.to, step.description)?;
+                    for line in describe_changes(&at, &next) {
+                        writeln!(out, "  {line}")?;
+                    }
+                    at = next;
+                }
+                writeln!(
+                    out,
+                    "Dry run: {} left at v{from}; nothing written.",
+                    db_path.display()
+                )?;
+                return Ok(());
+            }
 
-            let count = todos.len();
+            let backup = crate::infra::fs_repo::back_up_before_migration(db_path, from)?;
+            let doc = upgraded;
+            let json = serde_json::to_string_pretty(&doc).context("failed serializing db JSON")?;
+            crate::infra::fs_repo::replace_file(db_path, json.as_bytes())?;
 
-            store.set_all(todos);
-            store.repo_mut().save_atomic()?; // persist immediately
+            let now = time::OffsetDateTime::now_utc()
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap_or_default();
+            let mut log = String::new();
+            for step in &steps {
+                log.push_str(&format!(
+                    "{now} v{} -> v{}: {}\n",
+                    step.from, step.to, step.description
+                ));
+                info!(from = step.from, to = step.to, "applied schema migration");
+            }
+            let log_path = {
+                let mut p = db_path.as_os_str().to_owned();
+                p.push(".migrations.log");
+                std::path::PathBuf::from(p)
+            };
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&log_path)
+                .and_then(|mut f| f.write_all(log.as_bytes()))
+                .with_context(|| format!("failed writing {}", log_path.display()))?;
+
+            write!(out, "{log}")?;
+            writeln!(
+                out,
+                "Upgraded {} from v{from} to v{target} (log: {}, backup: {}).",
+                db_path.display(),
+                log_path.display(),
+                backup.display()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// `--format text|json` of commands that change todos: `Some(true)` for
+/// json, `None` (after saying so) for anything else.
+/// `add --stdin` flags, for lines that don't set their own.
+struct CaptureDefaults {
+    project: Option<String>,
+    tags: Vec<String>,
+    priority: Option<String>,
+    due: Option<String>,
+}
+
+/// Add one todo per line of `text` (see [`crate::app::capture`]). Nothing
+/// is added unless every line reads.
+fn add_captured(
+    ctx: &AppContext,
+    store: &mut Store<crate::infra::fs_repo::JsonFileTodoRepository>,
+    text: &str,
+    defaults: CaptureDefaults,
+    json: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    use crate::app::{capture, due_input::parse_due_in};
+    use crate::domain::todo::{ProjectName, Source, Tag, Todo};
+
+    let now = store.now();
+    let scheme = priorities(ctx);
+    let project = defaults.project.map(ProjectName::parse).transpose()?;
+    let tags = defaults
+        .tags
+        .iter()
+        .map(Tag::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    let priority = match defaults.priority.map(|p| scheme.parse(&p)).transpose() {
+        Ok(p) => p,
+        Err(msg) => {
+            writeln!(out, "{msg}")?;
+            return Ok(());
+        }
+    };
+    let due = match defaults
+        .due
+        .map(|d| parse_due_in(&d, now, &ctx.zone))
+        .transpose()
+    {
+        Ok(d) => d,
+        Err(msg) => {
+            writeln!(out, "{msg}")?;
+            return Ok(());
+        }
+    };
+
+    let captures = match capture::parse_lines(text, &ctx.config.titles, &scheme, now) {
+        Ok(c) => c,
+        Err(errors) => {
+            for e in &errors {
+                writeln!(out, "{e}")?;
+            }
+            writeln!(out, "Nothing added; fix the lines above and try again")?;
+            return Ok(());
+        }
+    };
+    if captures.is_empty() {
+        writeln!(out, "Nothing to add")?;
+        return Ok(());
+    }
+
+    let todos: Vec<Todo> = captures
+        .into_iter()
+        .map(|c| {
+            let mut todo = Todo::new_at(c.title, now);
+            todo.source = Some(Source::Cli);
+            if let Some(p) = c.project.or_else(|| project.clone()) {
+                todo.project = p;
+            }
+            todo.tags = c.tags;
+            todo.tags.extend(tags.iter().cloned());
+            if let Some(p) = c.priority.or(priority) {
+                todo.priority = p;
+            }
+            todo.due = c.due.or(due);
+            todo
+        })
+        .collect();
+    let ids: Vec<TodoId> = todos.iter().map(|t| t.id).collect();
+    store.insert_many(todos)?;
+    store.repo_mut().save_atomic()?;
+    info!(count = ids.len(), "Todos added");
+    let warnings = store.take_warnings();
+    if json {
+        let report = serde_json::json!({ "ids": ids, "warnings": warnings });
+        writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+    } else {
+        writeln!(out, "Added {} todos", ids.len())?;
+        print_warnings(ctx, &warnings, out)?;
+    }
+    Ok(())
+}
 
-            println!("Imported {} todos from {}", count, in_path.display());
+fn output_format(format: &str, out: &mut dyn Write) -> Result<Option<bool>> {
+    match format.trim().to_ascii_lowercase().as_str() {
+        "text" => Ok(Some(false)),
+        "json" => Ok(Some(true)),
+        other => {
+            writeln!(out, "unknown --format {other} (use text|json)")?;
+            Ok(None)
         }
     }
+}
+
+/// Print `warnings` as `warning: <id>: <message>` lines, in the theme's
+/// warning color.
+fn print_warnings(ctx: &AppContext, warnings: &[Warning], out: &mut dyn Write) -> Result<()> {
+    let style = Style::from_config(&ctx.config);
+    for w in warnings {
+        writeln!(
+            out,
+            "{}",
+            style.paint(Role::Warning, &format!("warning: {w}"))
+        )?;
+    }
     Ok(())
 }
 
-fn resolve_id_input(
+/// Name the IDs that several incoming todos shared, of which one was kept.
+fn report_duplicates(dups: &[TodoId], out: &mut dyn Write) -> Result<()> {
+    if !dups.is_empty() {
+        let ids: Vec<_> = dups.iter().map(TodoId::short).collect();
+        writeln!(out, "Kept one todo per duplicated ID: {}", ids.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Ask `question` if the `confirm` policy covers `operation`.
+///
+/// Without a terminal to ask on, covered operations are refused unless forced.
+fn confirmed(
+    ctx: &AppContext,
+    operation: &str,
+    force: bool,
+    question: &str,
+    out: &mut dyn Write,
+) -> Result<bool> {
+    use std::io::{BufRead, IsTerminal};
+
+    if force || !ctx.config.needs_confirmation(operation) {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        writeln!(
+            out,
+            "Refusing to {operation} without confirmation. Re-run with --force."
+        )?;
+        return Ok(false);
+    }
+    write!(out, "{question} [y/N] ")?;
+    out.flush()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(matches!(
+        line.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Open `path` in `$VISUAL`, else `$EDITOR`, else `vi`, and wait for it to exit.
+fn run_editor(path: &std::path::Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // Allow "code --wait" and the like.
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("failed starting editor {editor:?}"))?;
+    if !status.success() {
+        anyhow::bail!("editor {editor:?} exited with {status}");
+    }
+    Ok(())
+}
+
+/// `--ref provider:key` with its link: `--ref-url`, else the `[links]`
+/// template, else the provider's built-in one.
+fn parse_external_ref(
+    ctx: &AppContext,
+    input: &str,
+    url: Option<&str>,
+) -> Result<crate::domain::external_ref::ExternalRef> {
+    let mut r = crate::domain::external_ref::ExternalRef::parse(input)?;
+    if let Some(url) = url {
+        r = r.with_url(url)?;
+    }
+    let template = ctx.config.link_template(&r.provider);
+    Ok(r.resolve_url(template))
+}
+
+/// Resolve `--depends-on` inputs to ids, rejecting self-dependencies.
+fn resolve_dependencies(
+    todos: &[crate::domain::todo::Todo],
+    own: crate::domain::todo::TodoId,
+    inputs: &[String],
+) -> Result<std::collections::BTreeSet<crate::domain::todo::TodoId>, String> {
+    let mut deps = std::collections::BTreeSet::new();
+    for input in inputs {
+        let id = resolve_id_input(todos, input)?;
+        if id == own {
+            return Err("a todo cannot depend on itself".to_string());
+        }
+        deps.insert(id);
+    }
+    Ok(deps)
+}
+
+/// Passphrase for sync invites: `--passphrase`, else `$RUSTYTODO_SYNC_PASSPHRASE`.
+fn sync_passphrase(arg: Option<String>) -> Result<String> {
+    arg.or_else(|| std::env::var("RUSTYTODO_SYNC_PASSPHRASE").ok())
+        .ok_or_else(|| {
+            anyhow::anyhow!("missing passphrase (--passphrase or RUSTYTODO_SYNC_PASSPHRASE)")
+        })
+}
+
+/// Resolve every id argument, reporting the ones that don't match exactly
+/// one todo. The same todo named twice is only returned once.
+/// No ids at all opens the picker (see [`pick_id`]) for one.
+fn resolve_ids(
+    todos: &[crate::domain::todo::Todo],
+    ids: &[String],
+    prompt: &str,
+    out: &mut dyn Write,
+) -> Result<Vec<(String, crate::domain::todo::TodoId)>> {
+    if ids.is_empty() {
+        return Ok(match pick_id(todos, None, prompt)? {
+            Ok(todo_id) => vec![(todo_id.short(), todo_id)],
+            Err(msg) => {
+                writeln!(out, "{msg}")?;
+                Vec::new()
+            }
+        });
+    }
+    let mut resolved: Vec<(String, crate::domain::todo::TodoId)> = Vec::new();
+    for id in ids {
+        match pick_id(todos, Some(id), prompt)? {
+            Ok(todo_id) if resolved.iter().any(|(_, seen)| *seen == todo_id) => {}
+            Ok(todo_id) => resolved.push((id.clone(), todo_id)),
+            Err(msg) => writeln!(out, "{id}: {msg}")?,
+        }
+    }
+    Ok(resolved)
+}
+
+/// Resolve an id argument like [`resolve_id_input`], but in a terminal open
+/// the fuzzy picker when the id is missing (`None`) or matches several todos.
+fn pick_id(
+    todos: &[crate::domain::todo::Todo],
+    input: Option<&str>,
+    prompt: &str,
+) -> Result<Result<crate::domain::todo::TodoId, String>> {
+    use crate::ui::picker;
+
+    let candidates: Vec<crate::domain::todo::Todo> = match input {
+        Some(input) => {
+            let matches = prefix_matches(todos, input.trim());
+            if matches.len() < 2 || !picker::available() {
+                return Ok(resolve_id_input(todos, input));
+            }
+            matches.into_iter().cloned().collect()
+        }
+        None if picker::available() => todos.to_vec(),
+        None => {
+            return Ok(Err(
+                "missing todo id (pass one, or run in a terminal to pick)".to_string(),
+            ));
+        }
+    };
+    Ok(picker::pick(&candidates, prompt)?.ok_or_else(|| "No todo picked.".to_string()))
+}
+
+/// Todos whose short id is `s` or whose UUID starts with it.
+fn prefix_matches<'a>(
+    todos: &'a [crate::domain::todo::Todo],
+    s: &str,
+) -> Vec<&'a crate::domain::todo::Todo> {
+    if s.len() < 4 {
+        return Vec::new();
+    }
+    todos
+        .iter()
+        .filter(|t| t.id.short() == s || t.id.as_uuid_str().starts_with(s))
+        .collect()
+}
+
+pub(crate) fn resolve_id_input(
     todos: &[crate::domain::todo::Todo],
     input: &str,
 ) -> Result<crate::domain::todo::TodoId, String> {
@@ -689,13 +6626,10 @@ fn resolve_id_input(
         return Err("id prefix too short (use at least 4 chars, or full UUID)".to_string());
     }
 
-    let mut matches = Vec::new();
-    for t in todos {
-        let full = t.id.as_uuid_str();
-        if t.id.short() == s || full.starts_with(s) {
-            matches.push((t.id, t.title.as_str().to_string()));
-        }
-    }
+    let matches: Vec<_> = prefix_matches(todos, s)
+        .into_iter()
+        .map(|t| (t.id, t.title.as_str().to_string()))
+        .collect();
 
     match matches.len() {
         0 => Err(format!("no todo found matching id: {}", s)),
@@ -0,0 +1,351 @@
+//! Minimal desktop companion window (feature `gui`).
+//!
+//! Lists todos and offers a quick-add box over the same JSON store the CLI uses.
+//!
+//! The file is polled for writes by other processes. Without local edits in
+//! flight they are simply reloaded; if a save would clobber them, the save is
+//! held back and a banner offers to reload (drop ours), merge, or overwrite.
+//!
+//! config.toml is watched too: theme and `storage_path` changes apply live, and
+//! Ctrl+Shift+R re-reads it on demand.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use eframe::egui;
+
+use crate::{
+    app::{
+        capture,
+        context::AppContext,
+        merge::three_way,
+        priorities::PriorityScheme,
+        query::{ListQuery, StatusFilter},
+        repository::TodoRepository,
+        store::Store,
+    },
+    domain::todo::{Source, TitleRules, Todo, TodoId},
+    infra::{
+        config::{AppConfig, ConfigWatcher, Theme},
+        db_crypto::DbKey,
+        fs_repo::JsonFileTodoRepository,
+        paths::AppPaths,
+    },
+};
+
+/// Open the window and block until it is closed.
+pub fn run(ctx: AppContext) -> Result<()> {
+    let app = GuiApp::load(ctx)?;
+
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("rustlytodo")
+            .with_inner_size([420.0, 560.0]),
+        ..Default::default()
+    };
+
+    eframe::run_native("rustlytodo", options, Box::new(|_cc| Ok(Box::new(app))))
+        .map_err(|e| anyhow!("gui failed: {e}"))
+}
+
+/// How often the database file is checked for external changes.
+const POLL_EVERY: Duration = Duration::from_secs(1);
+
+struct GuiApp {
+    paths: AppPaths,
+    config: ConfigWatcher,
+    /// Theme to show; `theme_applied` is cleared when it changes.
+    theme: Theme,
+    theme_applied: bool,
+    titles: TitleRules,
+    priorities: PriorityScheme,
+    db_path: PathBuf,
+    /// For an encrypted database; asked for once, at start.
+    key: Option<DbKey>,
+    store: Store<JsonFileTodoRepository>,
+    /// Todos as last loaded from or saved to disk (merge base).
+    base: Vec<Todo>,
+    /// Local edits not written because the file changed underneath us.
+    conflict: bool,
+    last_poll: Instant,
+    draft: String,
+    show_done: bool,
+    /// Last error, shown until the next successful action.
+    error: Option<String>,
+    /// Last notice (e.g. "reloaded"), shown until the next action.
+    info: Option<String>,
+}
+
+impl GuiApp {
+    fn load(ctx: AppContext) -> Result<Self> {
+        let db_path = ctx.config.resolve_db_path(&ctx.paths);
+        let key = ctx.config.db_key(&db_path)?;
+        let repo = JsonFileTodoRepository::load_with_key(db_path.clone(), key.clone())?;
+        let store = Store::new(repo);
+        Ok(Self {
+            config: ConfigWatcher::new(&ctx.paths),
+            paths: ctx.paths,
+            theme: ctx.config.theme.name,
+            theme_applied: false,
+            titles: ctx.config.titles,
+            priorities: ctx.config.priority_scheme().unwrap_or_default(),
+            db_path,
+            key,
+            base: store.list_todos(),
+            store,
+            conflict: false,
+            last_poll: Instant::now(),
+            draft: String::new(),
+            show_done: false,
+            error: None,
+            info: None,
+        })
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let repo = JsonFileTodoRepository::load_with_key(self.db_path.clone(), self.key.clone())?;
+        self.store = Store::new(repo);
+        self.base = self.store.list_todos();
+        self.conflict = false;
+        Ok(())
+    }
+
+    /// Write local edits unless the file changed since we read it.
+    fn save(&mut self) -> Result<()> {
+        if self.conflict || self.store.repo_mut().changed_on_disk()? {
+            self.conflict = true;
+            return Ok(());
+        }
+        self.overwrite()
+    }
+
+    fn overwrite(&mut self) -> Result<()> {
+        self.store.repo_mut().save_atomic()?;
+        self.base = self.store.list_todos();
+        self.conflict = false;
+        Ok(())
+    }
+
+    /// Combine our edits with what is on disk now, then save.
+    fn merge(&mut self) -> Result<()> {
+        let remote = JsonFileTodoRepository::load_with_key(self.db_path.clone(), self.key.clone())?;
+        let merged = three_way(&self.base, &self.store.list_todos(), &remote.list());
+
+        // Save on top of the revision we just merged with.
+        self.store = Store::new(remote);
+        self.store.set_all(merged.todos)?;
+        self.overwrite()?;
+        self.info = Some(match merged.conflicts.len() {
+            0 => "Merged changes from disk".to_string(),
+            n => format!("Merged; kept your version of {n} field(s) edited on both sides"),
+        });
+        Ok(())
+    }
+
+    fn apply_config(&mut self, cfg: AppConfig) -> Result<()> {
+        if cfg.theme.name != self.theme {
+            self.theme = cfg.theme.name;
+            self.theme_applied = false;
+        }
+        self.titles = cfg.titles;
+        self.priorities = cfg.priority_scheme().map_err(|e| anyhow!(e))?;
+
+        let db_path = cfg.resolve_db_path(&self.paths);
+        if db_path != self.db_path {
+            if self.conflict {
+                return Err(anyhow!(
+                    "storage_path changed; resolve the unsaved edits first, then reload the config"
+                ));
+            }
+            self.key = cfg.db_key(&db_path)?;
+            self.db_path = db_path;
+            self.reload()?;
+        }
+        self.info = Some("Applied config changes".to_string());
+        Ok(())
+    }
+
+    fn reload_config(&mut self) -> Result<()> {
+        let cfg = self.config.reload()?;
+        self.apply_config(cfg)
+    }
+
+    /// Pick up external writes; only reload silently if nothing would be lost.
+    fn poll(&mut self) -> Result<()> {
+        if self.last_poll.elapsed() < POLL_EVERY {
+            return Ok(());
+        }
+        self.last_poll = Instant::now();
+        if let Some(cfg) = self.config.poll()? {
+            self.apply_config(cfg)?;
+        }
+        if !self.conflict && self.store.repo_mut().changed_on_disk()? {
+            self.reload()?;
+            self.info = Some("Reloaded changes made elsewhere".to_string());
+        }
+        Ok(())
+    }
+
+    fn quick_add(&mut self) -> Result<()> {
+        let now = self.store.now();
+        let capture = capture::parse(&self.draft, &self.titles, &self.priorities, now)
+            .map_err(|e| anyhow!(e))?;
+        let mut todo = capture.into_todo(now);
+        todo.source = Some(Source::Gui);
+        self.store.insert_todo(todo)?;
+        self.save()?;
+        self.draft.clear();
+        Ok(())
+    }
+
+    fn set_done(&mut self, id: TodoId, done: bool) -> Result<()> {
+        if done {
+            self.store.mark_done(id)?;
+        } else {
+            self.store.mark_open(id)?;
+        }
+        self.save()
+    }
+
+    /// Record the outcome of a user action for display.
+    fn report(&mut self, result: Result<()>) {
+        self.error = result.err().map(|e| format!("{e:#}"));
+        if self.error.is_some() {
+            self.info = None;
+        }
+    }
+}
+
+impl eframe::App for GuiApp {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        let res = self.poll();
+        if res.is_err() {
+            self.report(res);
+        }
+        ui.ctx().request_repaint_after(POLL_EVERY);
+
+        let reload_config = egui::KeyboardShortcut::new(
+            egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+            egui::Key::R,
+        );
+        if ui.input_mut(|i| i.consume_shortcut(&reload_config)) {
+            let res = self.reload_config();
+            self.report(res);
+        }
+        if !self.theme_applied {
+            ui.ctx().set_visuals(visuals(self.theme));
+            self.theme_applied = true;
+        }
+
+        egui::CentralPanel::default().show(ui, |ui| {
+            if self.conflict {
+                ui.group(|ui| {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 170, 0),
+                        "The todo file was changed elsewhere; your latest edits are not saved yet.",
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button("Reload (discard mine)").clicked() {
+                            let res = self.reload();
+                            self.report(res);
+                        }
+                        if ui.button("Merge").clicked() {
+                            let res = self.merge();
+                            self.report(res);
+                        }
+                        if ui.button("Overwrite theirs").clicked() {
+                            let res = self.overwrite();
+                            self.report(res);
+                        }
+                    });
+                });
+            }
+
+            ui.horizontal(|ui| {
+                let input = ui.add(
+                    egui::TextEdit::singleline(&mut self.draft)
+                        .hint_text("Title #tag @project !p1 due:friday"),
+                );
+                let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Add").clicked() || submitted {
+                    let res = self.quick_add();
+                    self.report(res);
+                    input.request_focus();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_done, "Show done");
+                let reload = ui
+                    .button("Reload")
+                    .on_hover_text("Re-read the todo file (Ctrl+Shift+R re-reads config.toml)");
+                if reload.clicked() {
+                    let res = self.reload();
+                    self.report(res);
+                }
+            });
+
+            if let Some(err) = &self.error {
+                ui.colored_label(egui::Color32::RED, err);
+            } else if let Some(info) = &self.info {
+                ui.label(info);
+            }
+
+            ui.separator();
+
+            let q = ListQuery {
+                status: (!self.show_done).then_some(StatusFilter::Open),
+                ..ListQuery::default()
+            };
+            let todos = self.store.find_todos(&q, self.store.now());
+
+            let mut toggled = None;
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for todo in &todos {
+                    let mut done = todo.status.is_done();
+                    let badge = todo
+                        .badge
+                        .as_ref()
+                        .map(|b| format!("{} ", b.as_str()))
+                        .unwrap_or_default();
+                    let label = format!(
+                        "{}  [{}] {badge}{}",
+                        self.priorities.label(todo.priority),
+                        todo.project.as_str(),
+                        todo.title.as_str()
+                    );
+                    let mut text = egui::RichText::new(label);
+                    if let Some(c) = todo.color {
+                        let (r, g, b) = c.rgb();
+                        text = text.color(egui::Color32::from_rgb(r, g, b));
+                    }
+                    if ui.checkbox(&mut done, text).changed() {
+                        toggled = Some((todo.id, done));
+                    }
+                }
+            });
+
+            if let Some((id, done)) = toggled {
+                let res = self.set_done(id, done);
+                self.report(res);
+            }
+        });
+    }
+}
+
+fn visuals(theme: Theme) -> egui::Visuals {
+    match theme {
+        Theme::Dark => egui::Visuals::dark(),
+        Theme::Light => egui::Visuals::light(),
+        Theme::HighContrast => {
+            let mut v = egui::Visuals::dark();
+            v.override_text_color = Some(egui::Color32::WHITE);
+            v.panel_fill = egui::Color32::BLACK;
+            v.window_fill = egui::Color32::BLACK;
+            v
+        }
+    }
+}
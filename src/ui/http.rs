@@ -0,0 +1,1055 @@
+//! HTTP server for dashboards and small scripts (`serve`).
+//!
+//! Plain HTTP/1.1 over `std::net`, a thread per connection: this is meant for
+//! a wall display or two polling every few seconds, not for heavy traffic. The
+//! store is reloaded from disk on every request, so CLI edits show up on the
+//! next refresh. With `--readonly` only `GET` (and `HEAD`) is accepted.
+//!
+//! Endpoints:
+//! - `/` - auto-refreshing HTML board (overdue + due today)
+//! - `/api/board` - the same data as JSON
+//! - `/api/todos` - todos as stored, in list order; `?limit=N` pages them and
+//!   `&cursor=<next_cursor>` fetches the page after the last one
+//! - `/api/todos/<id>` - one todo, as `export` writes it
+//! - `/metrics` - Prometheus gauges of open/overdue/due-today todos per
+//!   project, and counters of requests served and refused
+//! - `/healthz` - liveness probe
+//! - `/openapi.json` - OpenAPI 3 description of all this, from [`ROUTES`]
+//! - `POST /-/reload` - re-read config.toml now (allowed even read-only; it
+//!   doesn't touch todos)
+//!
+//! Unless read-only, todos can be changed too:
+//! - `POST /api/todos` - add `{"title": "..."}`; the title takes the inline
+//!   `#tag @project !p1 due:friday` tokens `add` does
+//! - `PATCH /api/todos/<id>` - edit with the fields to change
+//! - `POST /api/todos/<id>/done`, `POST /api/todos/<id>/reopen`
+//! - `DELETE /api/todos/<id>`
+//!
+//! Bodies use the `export` schema's field names; `title`, `notes`, `project`,
+//! `tags`, `priority` and `due` can be set (`due` also as RFC 3339 or a phrase
+//! like `friday 5pm`, in UTC), and `null` clears `notes` or `due`.
+//!
+//! Writes go through a [`WriteQueue`]: one writer applies them in arrival
+//! order and saves each batch once, and a request is answered only after its
+//! change is on disk (or rejected).
+//!
+//! With [`ServeOptions::tokens`] set, every route but `/healthz` needs an API
+//! token (see `infra::api_tokens`) as `Authorization: Bearer <token>` or a
+//! `token=<token>` query parameter (which the board page passes on to its
+//! own requests). Reads need `read` scope, changing todos `write`, and
+//! `/-/reload` `admin`. The token file is re-read per request, so revoking
+//! takes effect at once.
+//!
+//! Each client (its API token, or its address without one) may make
+//! [`ServeOptions::rate_limit`] requests a minute, or its token's own limit;
+//! beyond that it gets `429 Too Many Requests` with a `Retry-After`. Every
+//! request is logged (method, route, status, latency, client) and counted in
+//! `/metrics`.
+//!
+//! config.toml is also re-checked before every request, so moving
+//! `storage_path` or changing `[serve] rate_limit` takes effect without a
+//! restart.
+
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use tracing::{info, warn};
+
+use crate::{
+    app::{
+        capture,
+        due_input::parse_due,
+        errors::AppError,
+        priorities::PriorityScheme,
+        query::{self, ListQuery},
+        rate_limit::RateLimiter,
+        repository::TodoRepository,
+        stats,
+        store::Store,
+        write_queue::{Mutation, WriteError, WriteQueue},
+    },
+    domain::{
+        clock::Clock,
+        errors::DomainError,
+        todo::{
+            DueAt, Notes, Priority, ProjectName, Source, Tag, TagsPatch, Title, TitleRules, Todo,
+            TodoId, TodoPatch,
+        },
+    },
+    infra::{
+        api_tokens::{self, ApiToken, Scope},
+        config::{AppConfig, ConfigWatcher},
+        db_crypto::DbKey,
+        fs_repo::JsonFileTodoRepository,
+        paths::AppPaths,
+    },
+};
+
+/// How titles of todos added over HTTP or JSON-RPC are read: config.toml's
+/// `[titles]` and `[[priority_levels]]`.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureRules {
+    pub titles: TitleRules,
+    pub priorities: PriorityScheme,
+}
+
+impl CaptureRules {
+    /// Priority levels that don't check out fall back to P1-P4.
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self {
+            titles: cfg.titles,
+            priorities: cfg.priority_scheme().unwrap_or_default(),
+        }
+    }
+}
+
+/// Settings for [`serve`].
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub db_path: PathBuf,
+    /// Where "now" comes from for due phrases, new todos and the board.
+    pub clock: Arc<dyn Clock>,
+    /// For todos added with `POST /api/todos`.
+    pub capture: CaptureRules,
+    /// For an encrypted database.
+    pub key: Option<DbKey>,
+    /// How often the dashboard page polls for fresh data.
+    pub refresh: Duration,
+    /// Config to follow for live changes (`None` = fixed settings).
+    pub config: Option<(ConfigWatcher, AppPaths)>,
+    /// Accept `POST`/`DELETE` on `/api/todos`.
+    pub writable: bool,
+    /// API tokens file requests must present a token from (`None` = open).
+    pub tokens: Option<PathBuf>,
+    /// Requests per minute per client (0 = unlimited); a token's own
+    /// `rate_limit` takes precedence.
+    pub rate_limit: u32,
+}
+
+impl ServeOptions {
+    /// Apply config.toml edits; `force` re-reads even if unchanged.
+    fn update_config(&mut self, force: bool) -> Result<bool> {
+        let Some((watcher, paths)) = &mut self.config else {
+            return Ok(false);
+        };
+        let cfg = if force {
+            watcher.reload()?
+        } else {
+            match watcher.poll()? {
+                Some(cfg) => cfg,
+                None => return Ok(false),
+            }
+        };
+        self.rate_limit = cfg.serve.rate_limit;
+        self.capture = CaptureRules {
+            titles: cfg.titles,
+            priorities: cfg.priority_scheme().map_err(anyhow::Error::msg)?,
+        };
+        let db_path = cfg.resolve_db_path(paths);
+        if db_path != self.db_path {
+            info!(db = %db_path.display(), "config changed: now serving another database");
+            self.key = cfg.db_key(&db_path)?;
+            self.db_path = db_path;
+        }
+        Ok(true)
+    }
+}
+
+/// Handle connections on `listener` until the process is stopped.
+pub fn serve(listener: TcpListener, opts: ServeOptions) -> Result<()> {
+    let writable = opts.writable;
+    let shared = Arc::new(Mutex::new(opts));
+    let queue = writable.then(|| write_queue(Arc::clone(&shared)));
+    let traffic = Arc::new(Mutex::new(Traffic::default()));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = lock(&shared).update_config(false) {
+                    warn!(error = %e, "ignoring invalid config change");
+                }
+                let (shared, queue) = (Arc::clone(&shared), queue.clone());
+                let traffic = Arc::clone(&traffic);
+                std::thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &shared, queue.as_ref(), &traffic) {
+                        warn!(error = %e, "request failed");
+                    }
+                });
+            }
+            Err(e) => warn!(error = %e, "failed accepting connection"),
+        }
+    }
+    Ok(())
+}
+
+fn lock<T>(shared: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // A panicking request can't leave the options or counters half-updated.
+    shared.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The writer follows `storage_path` changes like the read routes do.
+fn write_queue(shared: Arc<Mutex<ServeOptions>>) -> WriteQueue {
+    WriteQueue::spawn(
+        move || {
+            let opts = lock(&shared).clone();
+            Ok(Store::new(load_repo(&opts)?).with_clock(opts.clock))
+        },
+        |store: &mut Store<JsonFileTodoRepository>| store.repo_mut().save_atomic(),
+    )
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+    /// Seconds, for a `Retry-After` header.
+    retry_after: Option<u64>,
+}
+
+impl Response {
+    fn json(value: serde_json::Value) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "application/json",
+            body: value.to_string(),
+            retry_after: None,
+        }
+    }
+
+    fn text(status: &'static str, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{body}\n"),
+            retry_after: None,
+        }
+    }
+
+    fn rate_limited(wait: Duration) -> Self {
+        let seconds = wait.as_secs_f64().ceil().max(1.0) as u64;
+        Self {
+            retry_after: Some(seconds),
+            ..Self::text(
+                "429 Too Many Requests",
+                &format!("rate limit exceeded; retry in {seconds}s"),
+            )
+        }
+    }
+
+    fn code(&self) -> u16 {
+        self.status
+            .split_whitespace()
+            .next()
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+/// Rate-limit state and request counters, shared by all connections.
+#[derive(Debug, Default)]
+struct Traffic {
+    limiter: RateLimiter,
+    /// Requests and the seconds spent on them, by method, route and status.
+    requests: BTreeMap<(&'static str, &'static str, u16), (u64, f64)>,
+    /// Requests refused for going over the limit, by client (token names
+    /// only, so addresses don't pile up as labels).
+    limited: BTreeMap<String, u64>,
+}
+
+impl Traffic {
+    fn record(&mut self, method: &'static str, route: &'static str, code: u16, took: Duration) {
+        let entry = self.requests.entry((method, route, code)).or_default();
+        entry.0 += 1;
+        entry.1 += took.as_secs_f64();
+    }
+
+    /// Prometheus counters, appended to `/metrics`.
+    fn exposition(&self) -> String {
+        let mut text = String::from(
+            "# HELP rustlytodo_http_requests_total HTTP requests answered.\n\
+             # TYPE rustlytodo_http_requests_total counter\n",
+        );
+        for ((method, route, code), (count, _)) in &self.requests {
+            text.push_str(&format!(
+                "rustlytodo_http_requests_total{{method=\"{method}\",route=\"{route}\",code=\"{code}\"}} {count}\n"
+            ));
+        }
+        text.push_str(
+            "# HELP rustlytodo_http_request_seconds_total Time spent answering HTTP requests.\n\
+             # TYPE rustlytodo_http_request_seconds_total counter\n",
+        );
+        for ((method, route, code), (_, seconds)) in &self.requests {
+            text.push_str(&format!(
+                "rustlytodo_http_request_seconds_total{{method=\"{method}\",route=\"{route}\",code=\"{code}\"}} {seconds:.6}\n"
+            ));
+        }
+        text.push_str(
+            "# HELP rustlytodo_http_rate_limited_total Requests refused for exceeding the rate limit.\n\
+             # TYPE rustlytodo_http_rate_limited_total counter\n",
+        );
+        for (client, count) in &self.limited {
+            let client = escape_label(client);
+            text.push_str(&format!(
+                "rustlytodo_http_rate_limited_total{{client=\"{client}\"}} {count}\n"
+            ));
+        }
+        text
+    }
+}
+
+/// Largest request body accepted (a todo title is far smaller).
+const MAX_BODY: usize = 64 * 1024;
+
+fn handle_connection(
+    mut stream: TcpStream,
+    shared: &Mutex<ServeOptions>,
+    queue: Option<&WriteQueue>,
+    traffic: &Mutex<Traffic>,
+) -> Result<()> {
+    let started = Instant::now();
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let peer = stream.peer_addr()?.ip().to_string();
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Only Content-Length and Authorization matter to us.
+    let mut length = 0;
+    let mut bearer = None;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("authorization") {
+                bearer = value.trim().strip_prefix("Bearer ").map(str::to_string);
+            }
+        }
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let (target, query_token) = take_token(parts.next().unwrap_or("/"));
+    let target = target.as_str();
+    let path = target.split('?').next().unwrap_or(target);
+    let found = find_route(method, path);
+
+    let (tokens, rate_limit) = {
+        let opts = lock(shared);
+        (opts.tokens.clone(), opts.rate_limit)
+    };
+    // Unknown routes still need a token, so probing them tells nothing.
+    let needed = match found {
+        Some(route) => route.scope,
+        None if matches!(method, "GET" | "HEAD") => Some(Scope::Read),
+        None => Some(Scope::Write),
+    };
+    let auth = match (tokens, needed) {
+        (Some(file), Some(needed)) => {
+            authorize(&file, bearer.or(query_token).as_deref(), needed).map(Some)
+        }
+        _ => Ok(None),
+    };
+    let (client, limit, denied) = match auth {
+        Ok(Some(token)) => (
+            format!("token:{}", token.name),
+            token.rate_limit.unwrap_or(rate_limit),
+            None,
+        ),
+        Ok(None) => (peer, rate_limit, None),
+        Err(denied) => (peer, rate_limit, Some(denied)),
+    };
+    let limited = {
+        let mut traffic = lock(traffic);
+        let limited = traffic.limiter.check(&client, limit, started).err();
+        if limited.is_some() {
+            let key = if client.starts_with("token:") {
+                client.clone()
+            } else {
+                "anonymous".to_string()
+            };
+            *traffic.limited.entry(key).or_default() += 1;
+        }
+        limited
+    };
+
+    let response = match (method, queue) {
+        _ if let Some(wait) = limited => Response::rate_limited(wait),
+        _ if let Some(denied) = denied => denied,
+        ("POST", _) if target == "/-/reload" => match lock(shared).update_config(true) {
+            Ok(true) => Response::text("200 OK", "config reloaded"),
+            Ok(false) => Response::text("409 Conflict", "not following a config file"),
+            Err(e) => Response::text("400 Bad Request", &format!("{e:#}")),
+        },
+        ("GET" | "HEAD", _) => route(target, &lock(shared).clone(), traffic),
+        ("POST" | "PATCH" | "DELETE", Some(_)) if length > MAX_BODY => {
+            Response::text("413 Payload Too Large", "request body too large")
+        }
+        ("POST" | "PATCH" | "DELETE", Some(queue)) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            // Not held while waiting: the writer locks the options to load.
+            let opts = lock(shared).clone();
+            write_route(method, target, &body, queue, &opts)
+        }
+        (_, None) => Response::text(
+            "405 Method Not Allowed",
+            "read-only server: only GET is allowed",
+        ),
+        (_, Some(_)) => Response::text("405 Method Not Allowed", "method not allowed"),
+    };
+
+    let took = started.elapsed();
+    let method_label = match method {
+        "GET" => "GET",
+        "HEAD" => "HEAD",
+        "POST" => "POST",
+        "PATCH" => "PATCH",
+        "DELETE" => "DELETE",
+        _ => "other",
+    };
+    let route_label = found.map_or("other", |r| r.path);
+    let code = response.code();
+    lock(traffic).record(method_label, route_label, code, took);
+    info!(
+        method = method_label,
+        route = route_label,
+        status = code,
+        latency_ms = took.as_secs_f64() * 1000.0,
+        client,
+        "http request"
+    );
+
+    let allow = if queue.is_some() {
+        "GET, HEAD, POST, PATCH, DELETE"
+    } else {
+        "GET, HEAD"
+    };
+    let retry = response
+        .retry_after
+        .map(|s| format!("Retry-After: {s}\r\n"))
+        .unwrap_or_default();
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAllow: {allow}\r\n{retry}Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    if method != "HEAD" {
+        stream.write_all(response.body.as_bytes())?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+/// Split a `token` query parameter off `target`.
+fn take_token(target: &str) -> (String, Option<String>) {
+    let Some((path, params)) = target.split_once('?') else {
+        return (target.to_string(), None);
+    };
+    let mut token = None;
+    let rest: Vec<_> = params
+        .split('&')
+        .filter(|p| match p.strip_prefix("token=") {
+            Some(t) => {
+                token = Some(t.to_string());
+                false
+            }
+            None => true,
+        })
+        .collect();
+    if rest.is_empty() {
+        (path.to_string(), token)
+    } else {
+        (format!("{path}?{}", rest.join("&")), token)
+    }
+}
+
+/// The token in `file` that `presented` is, if it has at least `needed`
+/// scope; otherwise the response to send instead.
+fn authorize(
+    file: &std::path::Path,
+    presented: Option<&str>,
+    needed: Scope,
+) -> Result<ApiToken, Response> {
+    let tokens = match api_tokens::load(file) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            warn!(error = %e, "failed loading API tokens");
+            return Err(Response::text(
+                "500 Internal Server Error",
+                "failed loading API tokens",
+            ));
+        }
+    };
+    match presented.and_then(|p| api_tokens::find(&tokens, p)) {
+        None => Err(Response::text(
+            "401 Unauthorized",
+            "missing or unknown API token",
+        )),
+        Some(t) if t.scope < needed => Err(Response::text(
+            "403 Forbidden",
+            &format!(
+                "token {} has {} scope; this needs {}",
+                t.name,
+                t.scope.label(),
+                needed.label()
+            ),
+        )),
+        Some(t) => Ok(t.clone()),
+    }
+}
+
+/// `POST /api/todos`, `PATCH /api/todos/<id>`, `POST /api/todos/<id>/done|reopen`,
+/// `DELETE /api/todos/<id>`.
+fn write_route(
+    method: &str,
+    target: &str,
+    body: &[u8],
+    queue: &WriteQueue,
+    opts: &ServeOptions,
+) -> Response {
+    let now = opts.clock.now();
+    let rest = target.strip_prefix("/api/todos").unwrap_or(target);
+    let mutation = match (method, rest) {
+        ("POST", "" | "/") => match object(body).and_then(|b| new_todo(&b, &opts.capture, now)) {
+            Ok(todo) => Mutation::Add(Box::new(todo)),
+            Err(msg) => return Response::text("400 Bad Request", &msg),
+        },
+        (_, rest) if target.starts_with("/api/todos/") => {
+            let mut parts = rest.trim_start_matches('/').splitn(2, '/');
+            let Ok(id) = TodoId::parse_uuid(parts.next().unwrap_or_default()) else {
+                return Response::text("400 Bad Request", "invalid todo id (use the full id)");
+            };
+            match (method, parts.next()) {
+                ("PATCH", None) => match object(body).and_then(|b| fields(&b, false, now)) {
+                    Ok((_, patch)) => Mutation::Edit(id, Box::new(patch)),
+                    Err(msg) => return Response::text("400 Bad Request", &msg),
+                },
+                ("POST", Some("done")) => Mutation::Done(id),
+                ("POST", Some("reopen")) => Mutation::Reopen(id),
+                ("DELETE", None) => Mutation::Delete(id),
+                _ => return Response::text("404 Not Found", "not found"),
+            }
+        }
+        _ => return Response::text("404 Not Found", "not found"),
+    };
+
+    let created = matches!(mutation, Mutation::Add(_));
+    match queue.submit(mutation) {
+        Ok(id) => Response {
+            status: if created { "201 Created" } else { "200 OK" },
+            ..Response::json(json!({ "id": id }))
+        },
+        Err(WriteError::Rejected(e)) => {
+            let status = match e {
+                AppError::TodoNotFound => "404 Not Found",
+                _ => "409 Conflict",
+            };
+            Response::text(status, &e.to_string())
+        }
+        Err(e @ WriteError::Storage(_)) => {
+            warn!(error = %e, "failed saving todos");
+            Response::text("500 Internal Server Error", "failed saving todos")
+        }
+    }
+}
+
+/// Fields [`fields`] can set.
+const SETTABLE: &str = "title, notes, project, tags, priority, due";
+
+fn object(body: &[u8]) -> Result<serde_json::Map<String, Value>, String> {
+    serde_json::from_slice(body).map_err(|e| format!("expected a JSON object with {SETTABLE}: {e}"))
+}
+
+/// A todo from a `{"title": "...", ...}` body; other fields override what
+/// the title's inline tokens set.
+pub(crate) fn new_todo(
+    body: &serde_json::Map<String, Value>,
+    rules: &CaptureRules,
+    now: OffsetDateTime,
+) -> Result<Todo, String> {
+    let (title, patch) = fields(body, true, now)?;
+    let capture = capture::parse(
+        &title.unwrap_or_default(),
+        &rules.titles,
+        &rules.priorities,
+        now,
+    )?;
+    let mut todo = capture.into_todo(now);
+    todo.apply_patch_at(patch, now);
+    todo.source = Some(Source::Api);
+    Ok(todo)
+}
+
+/// The settable fields of a JSON body, as a patch. With `raw_title` the
+/// title is returned as given, for [`capture`] to read, instead of patched.
+/// Due phrases are read relative to `now`.
+pub(crate) fn fields(
+    body: &serde_json::Map<String, Value>,
+    raw_title: bool,
+    now: OffsetDateTime,
+) -> Result<(Option<String>, TodoPatch), String> {
+    if raw_title && !body.contains_key("title") {
+        return Err("title is required".to_string());
+    }
+
+    let mut title = None;
+    let mut patch = TodoPatch::default();
+    for (key, value) in body {
+        let text = || {
+            value
+                .as_str()
+                .ok_or_else(|| format!("{key} must be a string"))
+        };
+        let invalid = |e: DomainError| format!("{key}: {e}");
+        match key.as_str() {
+            "title" if raw_title => title = Some(text()?.to_string()),
+            "title" => patch.title = Some(Title::parse(text()?).map_err(invalid)?),
+            "notes" if value.is_null() => patch.notes = Some(None),
+            "notes" => patch.notes = Some(Some(Notes::parse(text()?).map_err(invalid)?)),
+            "project" => patch.project = Some(ProjectName::parse(text()?).map_err(invalid)?),
+            "priority" => patch.priority = Some(Priority::parse(text()?).map_err(invalid)?),
+            "tags" => {
+                let tags = value
+                    .as_array()
+                    .ok_or("tags must be a list of strings")?
+                    .iter()
+                    .map(|t| match t.as_str() {
+                        Some(t) => Tag::parse(t).map_err(invalid),
+                        None => Err("tags must be a list of strings".to_string()),
+                    })
+                    .collect::<Result<_, _>>()?;
+                patch.tags = Some(TagsPatch::Replace(tags));
+            }
+            "due" => {
+                patch.due = Some(match value {
+                    Value::Null => None,
+                    Value::String(s) => Some(parse_due(s, now)?),
+                    // The export form.
+                    other => Some(
+                        serde_json::from_value::<DueAt>(other.clone())
+                            .map_err(|e| format!("due: {e}"))?,
+                    ),
+                });
+            }
+            other => return Err(format!("{other} can't be set (use {SETTABLE})")),
+        }
+    }
+    Ok((title, patch))
+}
+
+/// One route, as [`openapi`](crate::ui::openapi) documents it and token
+/// checks look up its scope. Keep in step with [`route`] and [`write_route`].
+#[derive(Debug, Clone, Copy)]
+pub struct Route {
+    pub method: &'static str,
+    /// With `{id}` for a todo's full ID.
+    pub path: &'static str,
+    pub summary: &'static str,
+    /// Token scope needed once tokens are in use (`None` = open to all).
+    pub scope: Option<Scope>,
+    /// Only served without `--readonly`.
+    pub write: bool,
+    /// Query parameters: name, OpenAPI type, description.
+    pub params: &'static [(&'static str, &'static str, &'static str)],
+    /// Successful status and content type of the response.
+    pub ok: (&'static str, &'static str),
+}
+
+const JSON: &str = "application/json";
+const TEXT: &str = "text/plain";
+
+/// Everything [`serve`] answers.
+pub const ROUTES: &[Route] = &[
+    Route {
+        method: "GET",
+        path: "/",
+        summary: "Auto-refreshing HTML board of overdue and due-today todos",
+        scope: Some(Scope::Read),
+        write: false,
+        params: &[],
+        ok: ("200", "text/html"),
+    },
+    Route {
+        method: "GET",
+        path: "/api/board",
+        summary: "The board's overdue and due-today todos",
+        scope: Some(Scope::Read),
+        write: false,
+        params: &[],
+        ok: ("200", JSON),
+    },
+    Route {
+        method: "GET",
+        path: "/api/todos",
+        summary: "Todos in list order, a page at a time",
+        scope: Some(Scope::Read),
+        write: false,
+        params: &[
+            ("limit", "integer", "Todos per page"),
+            ("cursor", "string", "next_cursor of the previous page"),
+        ],
+        ok: ("200", JSON),
+    },
+    Route {
+        method: "GET",
+        path: "/api/todos/{id}",
+        summary: "One todo, as export writes it",
+        scope: Some(Scope::Read),
+        write: false,
+        params: &[],
+        ok: ("200", JSON),
+    },
+    Route {
+        method: "GET",
+        path: "/metrics",
+        summary: "Prometheus gauges of open, overdue and due-today todos per project, and request counters",
+        scope: Some(Scope::Read),
+        write: false,
+        params: &[],
+        ok: ("200", TEXT),
+    },
+    Route {
+        method: "GET",
+        path: "/healthz",
+        summary: "Liveness probe",
+        scope: None,
+        write: false,
+        params: &[],
+        ok: ("200", TEXT),
+    },
+    Route {
+        method: "GET",
+        path: "/openapi.json",
+        summary: "This document",
+        scope: None,
+        write: false,
+        params: &[],
+        ok: ("200", JSON),
+    },
+    Route {
+        method: "POST",
+        path: "/-/reload",
+        summary: "Re-read config.toml now",
+        scope: Some(Scope::Admin),
+        write: false,
+        params: &[],
+        ok: ("200", TEXT),
+    },
+    Route {
+        method: "POST",
+        path: "/api/todos",
+        summary: "Add a todo; the title takes inline #tag @project !p1 due:friday tokens",
+        scope: Some(Scope::Write),
+        write: true,
+        params: &[],
+        ok: ("201", JSON),
+    },
+    Route {
+        method: "PATCH",
+        path: "/api/todos/{id}",
+        summary: "Edit a todo's title, notes, project, tags, priority or due date",
+        scope: Some(Scope::Write),
+        write: true,
+        params: &[],
+        ok: ("200", JSON),
+    },
+    Route {
+        method: "POST",
+        path: "/api/todos/{id}/done",
+        summary: "Mark a todo done",
+        scope: Some(Scope::Write),
+        write: true,
+        params: &[],
+        ok: ("200", JSON),
+    },
+    Route {
+        method: "POST",
+        path: "/api/todos/{id}/reopen",
+        summary: "Reopen a done todo",
+        scope: Some(Scope::Write),
+        write: true,
+        params: &[],
+        ok: ("200", JSON),
+    },
+    Route {
+        method: "DELETE",
+        path: "/api/todos/{id}",
+        summary: "Delete a todo",
+        scope: Some(Scope::Write),
+        write: true,
+        params: &[],
+        ok: ("200", JSON),
+    },
+];
+
+/// The route `method path` is served by (`HEAD` as `GET`).
+fn find_route(method: &str, path: &str) -> Option<&'static Route> {
+    let method = if method == "HEAD" { "GET" } else { method };
+    ROUTES.iter().find(|r| {
+        r.method == method && {
+            let mut want = r.path.split('/');
+            let mut got = path.split('/');
+            loop {
+                match (want.next(), got.next()) {
+                    (None, None) => break true,
+                    (Some("{id}"), Some(seg)) if !seg.is_empty() => {}
+                    (Some(w), Some(g)) if w == g => {}
+                    _ => break false,
+                }
+            }
+        }
+    })
+}
+
+fn route(target: &str, opts: &ServeOptions, traffic: &Mutex<Traffic>) -> Response {
+    let path = target.split('?').next().unwrap_or(target);
+    match path {
+        "/" | "/index.html" => Response {
+            status: "200 OK",
+            content_type: "text/html; charset=utf-8",
+            body: DASHBOARD.replace("{{REFRESH_MS}}", &opts.refresh.as_millis().to_string()),
+            retry_after: None,
+        },
+        "/healthz" => Response::text("200 OK", "ok"),
+        "/openapi.json" => Response::json(crate::ui::openapi::document(opts.writable)),
+        "/api/todos" => todos_page(target, opts),
+        p if let Some(id) = p.strip_prefix("/api/todos/") => todo_json(id, opts),
+        "/api/board" | "/metrics" => match load_todos(opts) {
+            Ok(todos) if path == "/api/board" => {
+                Response::json(board_json(&todos, opts.clock.now()))
+            }
+            Ok(todos) => Response {
+                status: "200 OK",
+                content_type: "text/plain; version=0.0.4; charset=utf-8",
+                body: metrics(&todos, opts.clock.now()) + &lock(traffic).exposition(),
+                retry_after: None,
+            },
+            Err(e) => {
+                warn!(error = %e, "failed loading todos");
+                Response::text("500 Internal Server Error", "failed loading todos")
+            }
+        },
+        _ => Response::text("404 Not Found", "not found"),
+    }
+}
+
+/// `/api/todos/<id>`: the todo as `export` writes it.
+fn todo_json(id: &str, opts: &ServeOptions) -> Response {
+    let Ok(id) = TodoId::parse_uuid(id) else {
+        return Response::text("400 Bad Request", "invalid todo id (use the full id)");
+    };
+    match load_repo(opts) {
+        Ok(repo) => match repo.get(id) {
+            Some(todo) => Response::json(json!(todo)),
+            None => Response::text("404 Not Found", "todo not found"),
+        },
+        Err(e) => {
+            warn!(error = %e, "failed loading todos");
+            Response::text("500 Internal Server Error", "failed loading todos")
+        }
+    }
+}
+
+fn load_repo(opts: &ServeOptions) -> Result<JsonFileTodoRepository> {
+    JsonFileTodoRepository::load_with_key(opts.db_path.clone(), opts.key.clone())
+        .with_context(|| format!("failed loading {}", opts.db_path.display()))
+}
+
+fn load_todos(opts: &ServeOptions) -> Result<Vec<Todo>> {
+    Ok(load_repo(opts)?.list())
+}
+
+/// `/api/todos[?limit=N][&cursor=ID]`: one page in `list` order.
+///
+/// `next_cursor` is the ID of the page's last todo, or null on the last page.
+/// The cursor is a position in the sort order rather than an offset, so todos
+/// added or removed between requests don't shift later pages.
+fn todos_page(target: &str, opts: &ServeOptions) -> Response {
+    let mut q = ListQuery::default();
+    let params = target.split_once('?').map_or("", |(_, qs)| qs);
+    for param in params.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+        match key {
+            "limit" => match value.parse::<usize>() {
+                Ok(n) if n > 0 => q.limit = Some(n),
+                _ => return Response::text("400 Bad Request", "limit must be a positive number"),
+            },
+            "cursor" => match TodoId::parse_uuid(value) {
+                Ok(id) => q.after = Some(id),
+                Err(_) => return Response::text("400 Bad Request", "invalid cursor"),
+            },
+            _ => {
+                return Response::text(
+                    "400 Bad Request",
+                    &format!("unknown parameter {key} (use limit, cursor)"),
+                );
+            }
+        }
+    }
+
+    let repo = match load_repo(opts) {
+        Ok(repo) => repo,
+        Err(e) => {
+            warn!(error = %e, "failed loading todos");
+            return Response::text("500 Internal Server Error", "failed loading todos");
+        }
+    };
+    if let Some(id) = q.after
+        && repo.get(id).is_none()
+    {
+        return Response::text("410 Gone", "cursor todo was deleted; start again");
+    }
+
+    let now = opts.clock.now();
+    let total = repo.count(&q, now);
+    // Ask for one extra to learn whether another page follows.
+    let limit = q.limit;
+    q.limit = limit.map(|n| n + 1);
+    let mut todos = repo.find(&q, now);
+    let next_cursor = match limit {
+        Some(n) if todos.len() > n => {
+            todos.truncate(n);
+            todos.last().map(|t| t.id)
+        }
+        _ => None,
+    };
+    Response::json(json!({ "todos": todos, "total": total, "next_cursor": next_cursor }))
+}
+
+fn board_json(todos: &[Todo], now: OffsetDateTime) -> serde_json::Value {
+    let board = query::board(todos, now);
+    let item = |t: &Todo| {
+        json!({
+            "id": t.id,
+            "title": t.title.as_str(),
+            "project": t.project.as_str(),
+            "priority": t.priority.label(),
+            "due": t.due.map(|d| d.format_rfc3339()),
+            "badge": t.badge.as_ref().map(|b| b.as_str()),
+            "color": t.color.map(|c| c.label()),
+        })
+    };
+    json!({
+        "generated_at": now.format(&Rfc3339).unwrap_or_default(),
+        "overdue": board.overdue.iter().map(item).collect::<Vec<_>>(),
+        "today": board.today.iter().map(item).collect::<Vec<_>>(),
+    })
+}
+
+/// Prometheus text exposition of per-project load.
+fn metrics(todos: &[Todo], now: OffsetDateTime) -> String {
+    let load = stats::project_load(todos, now);
+    type Gauge = (&'static str, &'static str, fn(&stats::ProjectLoad) -> usize);
+    let gauges: [Gauge; 3] = [
+        ("rustlytodo_open_todos", "Open todos", |l| l.open),
+        (
+            "rustlytodo_overdue_todos",
+            "Open todos past their due date",
+            |l| l.overdue,
+        ),
+        (
+            "rustlytodo_due_today_todos",
+            "Open todos due later today (UTC)",
+            |l| l.due_today,
+        ),
+    ];
+
+    let mut text = String::new();
+    for (name, help, value) in gauges {
+        text.push_str(&format!("# HELP {name} {help}.\n# TYPE {name} gauge\n"));
+        for l in &load {
+            let project = escape_label(&l.project);
+            text.push_str(&format!("{name}{{project=\"{project}\"}} {}\n", value(l)));
+        }
+    }
+    text
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Self-contained page; renders `/api/board` with `textContent` only, so todo
+/// titles can't inject markup.
+const DASHBOARD: &str = r##"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>rustlytodo board</title>
+<style>
+  body { font-family: system-ui, sans-serif; background: #111; color: #eee; margin: 2rem; }
+  main { display: grid; grid-template-columns: 1fr 1fr; gap: 2rem; }
+  h1 { font-size: 1.4rem; color: #999; font-weight: normal; }
+  h2 { border-bottom: 2px solid #444; padding-bottom: .3rem; }
+  h2.overdue { color: #ff6b6b; border-color: #ff6b6b; }
+  ul { list-style: none; padding: 0; font-size: 1.5rem; }
+  li { padding: .4rem 0; border-bottom: 1px solid #222; }
+  .meta { color: #888; font-size: 1rem; margin-left: .5rem; }
+  .empty { color: #666; font-style: italic; }
+  #status { color: #666; font-size: .9rem; }
+</style>
+</head>
+<body>
+<h1>rustlytodo board <span id="status"></span></h1>
+<main>
+  <section><h2 class="overdue">Overdue <span id="overdue-count"></span></h2><ul id="overdue"></ul></section>
+  <section><h2>Due today <span id="today-count"></span></h2><ul id="today"></ul></section>
+</main>
+<script>
+const COLORS = { red: "#ff6b6b", orange: "#ffa94d", yellow: "#ffd43b", green: "#69db7c",
+                 blue: "#74c0fc", purple: "#b197fc", gray: "#adb5bd" };
+function render(id, items) {
+  const ul = document.getElementById(id);
+  ul.replaceChildren();
+  document.getElementById(id + "-count").textContent = "(" + items.length + ")";
+  if (items.length === 0) {
+    const li = document.createElement("li");
+    li.className = "empty";
+    li.textContent = "nothing";
+    ul.appendChild(li);
+  }
+  for (const t of items) {
+    const li = document.createElement("li");
+    li.textContent = (t.badge ? t.badge + " " : "") + t.title;
+    if (t.color) li.style.color = COLORS[t.color] || "";
+    const meta = document.createElement("span");
+    meta.className = "meta";
+    const due = t.due ? new Date(t.due).toLocaleString([], { dateStyle: "short", timeStyle: "short" }) : "";
+    meta.textContent = t.priority + " · " + t.project + " · " + due;
+    li.appendChild(meta);
+    ul.appendChild(li);
+  }
+}
+async function refresh() {
+  try {
+    const res = await fetch("/api/board" + location.search, { cache: "no-store" });
+    const board = await res.json();
+    render("overdue", board.overdue);
+    render("today", board.today);
+    document.getElementById("status").textContent = "updated " + new Date().toLocaleTimeString();
+  } catch (e) {
+    document.getElementById("status").textContent = "offline, retrying";
+  }
+}
+refresh();
+setInterval(refresh, {{REFRESH_MS}});
+</script>
+</body>
+</html>
+"##;
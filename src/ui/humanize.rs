@@ -0,0 +1,85 @@
+//! Due dates as people say them: `due in 2d`, `overdue 3h`, `today 17:00`.
+//!
+//! Tables use these unless `--absolute` (or `absolute_dates` in config.toml)
+//! asks for RFC 3339. Days are counted in the due date's own UTC offset, the
+//! one it was entered with.
+
+use time::{Duration, OffsetDateTime, macros::format_description};
+
+/// How `due` reads at `now`. `overdue` is the todo's own verdict (done todos
+/// aren't overdue), so a past due date reads `overdue 3h` or `due 3d ago`.
+pub fn due(due: OffsetDateTime, now: OffsetDateTime, overdue: bool) -> String {
+    if overdue {
+        return format!("overdue {}", span(now - due));
+    }
+    if due < now {
+        return format!("due {} ago", span(now - due));
+    }
+    let today = now.to_offset(due.offset()).date();
+    let clock = format_description!("[hour]:[minute]");
+    let time = due.format(&clock).unwrap_or_default();
+    if due.date() == today {
+        format!("today {time}")
+    } else if today.next_day() == Some(due.date()) {
+        format!("tomorrow {time}")
+    } else {
+        format!("due in {}", span(due - now))
+    }
+}
+
+/// `45m`, `3h`, `2d` or `5w`, rounded down (at least `1m`).
+pub fn span(d: Duration) -> String {
+    let d = d.abs();
+    match d.whole_minutes() {
+        m if m < 60 => format!("{}m", m.max(1)),
+        _ if d.whole_hours() < 24 => format!("{}h", d.whole_hours()),
+        _ if d.whole_days() < 14 => format!("{}d", d.whole_days()),
+        _ => format!("{}w", d.whole_weeks()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    const NOW: OffsetDateTime = datetime!(2026-05-04 09:00 UTC);
+
+    #[test]
+    fn due_dates_read_relative_to_now() {
+        assert_eq!(
+            due(datetime!(2026-05-04 17:00 UTC), NOW, false),
+            "today 17:00"
+        );
+        assert_eq!(
+            due(datetime!(2026-05-05 08:30 UTC), NOW, false),
+            "tomorrow 08:30"
+        );
+        assert_eq!(
+            due(datetime!(2026-05-06 12:00 UTC), NOW, false),
+            "due in 2d"
+        );
+        assert_eq!(
+            due(datetime!(2026-06-15 09:00 UTC), NOW, false),
+            "due in 6w"
+        );
+        assert_eq!(
+            due(datetime!(2026-05-04 06:00 UTC), NOW, true),
+            "overdue 3h"
+        );
+        assert_eq!(
+            due(datetime!(2026-05-04 08:59:30 UTC), NOW, true),
+            "overdue 1m"
+        );
+        assert_eq!(
+            due(datetime!(2026-05-01 09:00 UTC), NOW, false),
+            "due 3d ago"
+        );
+        // 01:00 on the 5th in UTC+2 is still the 4th's evening in UTC, but it
+        // was entered as "tomorrow" over there.
+        assert_eq!(
+            due(datetime!(2026-05-05 01:00 +2), NOW, false),
+            "tomorrow 01:00"
+        );
+    }
+}
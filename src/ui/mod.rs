@@ -1,3 +1,14 @@
-//! User interfaces (CLI, TUI).
+//! User interfaces (CLI, GUI, HTTP dashboard, JSON-RPC for editors).
 
 pub mod cli;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod http;
+pub mod humanize;
+pub mod openapi;
+pub mod picker;
+pub mod rpc;
+pub mod style;
+pub mod symbols;
+pub mod table;
+pub mod theme;
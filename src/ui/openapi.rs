@@ -0,0 +1,190 @@
+//! OpenAPI 3 description of the `serve` HTTP API, built from
+//! [`ROUTES`](crate::ui::http::ROUTES) so it can't drift from what is
+//! served. Exposed at `/openapi.json` and by `api spec`.
+
+use serde_json::{Map, Value, json};
+
+use crate::ui::http::{ROUTES, Route};
+
+/// The document for a server with (`writable`) or without the write routes.
+pub fn document(writable: bool) -> Value {
+    let mut paths = Map::new();
+    for route in ROUTES.iter().filter(|r| writable || !r.write) {
+        let ops = paths
+            .entry(route.path)
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .expect("path items are objects");
+        ops.insert(route.method.to_ascii_lowercase(), operation(route));
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "rustlytodo",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Todos over HTTP (`rustytodo serve`). Once API tokens exist, \
+                send one as `Authorization: Bearer <token>` or a `token` query parameter; \
+                `x-scope` is the scope an operation needs.",
+        },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" },
+            },
+            "schemas": {
+                "NewTodo": {
+                    "allOf": [
+                        { "$ref": "#/components/schemas/TodoFields" },
+                        { "type": "object", "required": ["title"] },
+                    ],
+                },
+                "TodoFields": {
+                    "type": "object",
+                    "description": "Settable fields of the export schema; null clears notes or due.",
+                    "additionalProperties": false,
+                    "properties": {
+                        "title": { "type": "string", "example": "Fix login bug #work !p1 due:friday" },
+                        "notes": { "type": "string", "nullable": true },
+                        "project": { "type": "string", "example": "Work" },
+                        "tags": { "type": "array", "items": { "type": "string" } },
+                        "priority": { "type": "string", "enum": ["P1", "P2", "P3", "P4"] },
+                        "due": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "RFC 3339, or a phrase like `friday 5pm` (UTC)",
+                        },
+                    },
+                },
+                "TodoRef": {
+                    "type": "object",
+                    "properties": { "id": { "type": "string", "format": "uuid" } },
+                },
+            },
+        },
+    })
+}
+
+fn operation(route: &Route) -> Value {
+    let mut params: Vec<Value> = route
+        .params
+        .iter()
+        .map(|(name, kind, doc)| {
+            json!({ "name": name, "in": "query", "required": false,
+                    "description": doc, "schema": { "type": kind } })
+        })
+        .collect();
+    if route.path.contains("{id}") {
+        params.push(json!({ "name": "id", "in": "path", "required": true,
+                            "description": "The todo's full ID",
+                            "schema": { "type": "string", "format": "uuid" } }));
+    }
+
+    let (status, content_type) = route.ok;
+    let schema = match (content_type, route.write) {
+        ("application/json", true) => json!({ "$ref": "#/components/schemas/TodoRef" }),
+        ("application/json", false) => json!({ "type": "object" }),
+        _ => json!({ "type": "string" }),
+    };
+    let mut responses = Map::new();
+    responses.insert(
+        status.to_string(),
+        json!({ "description": "OK", "content": { content_type: { "schema": schema } } }),
+    );
+    if !route.params.is_empty() || route.write {
+        responses.insert("400".into(), json!({ "description": "Bad request" }));
+    }
+    responses.insert(
+        "429".into(),
+        json!({ "description": "Rate limited; retry after the Retry-After seconds" }),
+    );
+    if route.path.contains("{id}") {
+        responses.insert("404".into(), json!({ "description": "No such todo" }));
+        responses.insert(
+            "409".into(),
+            json!({ "description": "Not possible in the todo's state" }),
+        );
+    }
+
+    let mut op = json!({
+        "summary": route.summary,
+        "operationId": operation_id(route),
+        "parameters": params,
+    });
+    if let Some(scope) = route.scope {
+        responses.insert(
+            "401".into(),
+            json!({ "description": "Missing or unknown token" }),
+        );
+        responses.insert(
+            "403".into(),
+            json!({ "description": "Token lacks the scope" }),
+        );
+        op["security"] = json!([{ "bearer": [] }]);
+        op["x-scope"] = json!(scope.label());
+    }
+    let body = match (route.method, route.path) {
+        ("POST", "/api/todos") => Some("NewTodo"),
+        ("PATCH", _) => Some("TodoFields"),
+        _ => None,
+    };
+    if let Some(schema) = body {
+        op["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": {
+                "schema": { "$ref": format!("#/components/schemas/{schema}") } } },
+        });
+    }
+    op["responses"] = Value::Object(responses);
+    op
+}
+
+/// `getApiTodos`, `postApiTodosIdDone`, ...
+fn operation_id(route: &Route) -> String {
+    let mut id = route.method.to_ascii_lowercase();
+    for word in route.path.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            id.push(first.to_ascii_uppercase());
+            id.extend(chars);
+        }
+    }
+    if route.path == "/" {
+        id.push_str("Board");
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_route_is_documented_once() {
+        let doc = document(true);
+        let paths = doc["paths"].as_object().unwrap();
+        let ops: usize = paths.values().map(|p| p.as_object().unwrap().len()).sum();
+        assert_eq!(ops, ROUTES.len());
+
+        let done = &paths["/api/todos/{id}/done"]["post"];
+        assert_eq!(done["operationId"], "postApiTodosIdDone");
+        assert_eq!(done["x-scope"], "write");
+        assert_eq!(done["parameters"][0]["in"], "path");
+        let edit = &paths["/api/todos/{id}"]["patch"];
+        assert_eq!(edit["operationId"], "patchApiTodosId");
+        assert!(edit["requestBody"].is_object());
+        assert!(paths["/healthz"]["get"].get("security").is_none());
+
+        let ids: std::collections::BTreeSet<_> = paths
+            .values()
+            .flat_map(|p| p.as_object().unwrap().values())
+            .map(|op| op["operationId"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(ids.len(), ROUTES.len());
+
+        let readonly = document(false);
+        assert!(readonly["paths"]["/api/todos/{id}"].get("delete").is_none());
+        assert!(readonly["paths"]["/api/todos/{id}"]["get"].is_object());
+        assert!(readonly["paths"]["/api/todos"].get("post").is_none());
+    }
+}
@@ -0,0 +1,262 @@
+//! Interactive fuzzy picker for commands that take a todo ID (`done`, `show`,
+//! `start`, ...) when none is given or a prefix matches several todos.
+//!
+//! Type to narrow the list by title, project and tags (see
+//! [`crate::app::fuzzy`]), Up/Down or Ctrl-P/Ctrl-N to move, Enter to pick,
+//! Esc or Ctrl-C to cancel. Only offered when stdin and stderr are
+//! terminals; the list is drawn on stderr so piped stdout stays clean. The
+//! terminal is put in raw mode with `stty`; where that isn't available the
+//! picker falls back to a numbered menu read line by line.
+
+use std::{
+    io::{BufRead, IsTerminal, Read, Write},
+    process::{Command, Stdio},
+};
+
+use anyhow::Result;
+
+use crate::{
+    app::fuzzy,
+    domain::todo::{Todo, TodoId},
+};
+
+/// Rows shown at once.
+const ROWS: usize = 10;
+
+/// Can the picker run here?
+pub fn available() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Text the query is matched against: title, project and tags.
+fn haystack(todo: &Todo) -> String {
+    let mut text = format!("{}  {}", todo.title.as_str(), todo.project.as_str());
+    for tag in &todo.tags {
+        text.push_str(&format!(" #{}", tag.as_str()));
+    }
+    text
+}
+
+/// Columns for a title in a row, so rows don't wrap and break the redraw.
+const TITLE_WIDTH: usize = 50;
+
+fn row(todo: &Todo) -> String {
+    let mark = if todo.status.is_done() { "x" } else { " " };
+    let mut text = format!(
+        "{}  {}",
+        todo.title.truncated(TITLE_WIDTH),
+        todo.project.as_str()
+    );
+    for tag in &todo.tags {
+        text.push_str(&format!(" #{}", tag.as_str()));
+    }
+    format!("[{mark}] {}  {text}", todo.id.short())
+}
+
+/// Let the user pick one of `todos` (open ones listed first). `None` if they
+/// cancelled.
+pub fn pick(todos: &[Todo], prompt: &str) -> Result<Option<TodoId>> {
+    let mut todos: Vec<&Todo> = todos.iter().collect();
+    todos.sort_by_key(|t| t.status.is_done());
+    let haystacks: Vec<String> = todos.iter().map(|t| haystack(t)).collect();
+    let picked = match RawMode::enter() {
+        Some(raw) => {
+            let picked = pick_raw(&todos, &haystacks, prompt);
+            drop(raw);
+            picked?
+        }
+        None => pick_lines(&todos, &haystacks, prompt)?,
+    };
+    Ok(picked.map(|i| todos[i].id))
+}
+
+fn pick_raw(todos: &[&Todo], haystacks: &[String], prompt: &str) -> Result<Option<usize>> {
+    let mut err = std::io::stderr().lock();
+    let mut input = std::io::stdin().lock();
+    let mut query = String::new();
+    let mut selected = 0;
+
+    loop {
+        let matches = fuzzy::rank(&query, haystacks);
+        selected = selected.min(matches.len().saturating_sub(1));
+
+        // Redraw below the prompt line, then put the cursor back after it.
+        write!(err, "\r\x1b[J{prompt}> {query}")?;
+        let shown = matches.len().min(ROWS);
+        for (n, &i) in matches.iter().take(ROWS).enumerate() {
+            let marker = if n == selected { ">" } else { " " };
+            write!(err, "\r\n{marker} {}", row(todos[i]))?;
+        }
+        if matches.len() > ROWS {
+            write!(err, "\r\n  … and {} more", matches.len() - ROWS)?;
+        }
+        let lines = shown + usize::from(matches.len() > ROWS);
+        if lines > 0 {
+            write!(err, "\x1b[{lines}A")?;
+        }
+        let col = prompt.chars().count() + 2 + query.chars().count();
+        write!(err, "\r\x1b[{col}C")?;
+        err.flush()?;
+
+        let Some(key) = read_key(&mut input)? else {
+            continue;
+        };
+        match key {
+            Key::Enter => {
+                write!(err, "\r\x1b[J")?;
+                return Ok(matches.get(selected).copied());
+            }
+            Key::Cancel => {
+                write!(err, "\r\x1b[J")?;
+                return Ok(None);
+            }
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected = (selected + 1).min(shown.saturating_sub(1)),
+            Key::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            Key::Char(c) => {
+                query.push(c);
+                selected = 0;
+            }
+        }
+    }
+}
+
+enum Key {
+    Char(char),
+    Backspace,
+    Up,
+    Down,
+    Enter,
+    Cancel,
+}
+
+/// Next key from a raw-mode terminal; `None` when the read timed out or the
+/// byte means nothing to the picker.
+fn read_key(input: &mut impl Read) -> Result<Option<Key>> {
+    let byte = |input: &mut dyn Read| -> Result<Option<u8>> {
+        let mut b = [0u8; 1];
+        Ok((input.read(&mut b)? == 1).then_some(b[0]))
+    };
+    let Some(b) = byte(input)? else {
+        return Ok(None);
+    };
+    Ok(match b {
+        b'\r' | b'\n' => Some(Key::Enter),
+        // Ctrl-C, Ctrl-D, Ctrl-G
+        0x03 | 0x04 | 0x07 => Some(Key::Cancel),
+        0x7f | 0x08 => Some(Key::Backspace),
+        0x10 => Some(Key::Up),
+        0x0e => Some(Key::Down),
+        // A lone Esc times out; arrows come as Esc [ A/B (or Esc O A/B).
+        0x1b => match byte(input)? {
+            None => Some(Key::Cancel),
+            Some(b'[' | b'O') => match byte(input)? {
+                Some(b'A') => Some(Key::Up),
+                Some(b'B') => Some(Key::Down),
+                _ => None,
+            },
+            Some(_) => None,
+        },
+        b if b < 0x20 => None,
+        b if b < 0x80 => Some(Key::Char(b as char)),
+        // The first byte of a UTF-8 sequence says how many follow.
+        b => {
+            let len = match b {
+                0xc0..=0xdf => 1,
+                0xe0..=0xef => 2,
+                _ => 3,
+            };
+            let mut buf = vec![b];
+            for _ in 0..len {
+                match byte(input)? {
+                    Some(next) => buf.push(next),
+                    None => return Ok(None),
+                }
+            }
+            std::str::from_utf8(&buf)
+                .ok()
+                .and_then(|s| s.chars().next())
+                .map(Key::Char)
+        }
+    })
+}
+
+/// Numbered menu for terminals `stty` can't drive: a number picks, other
+/// text narrows the list, an empty line cancels.
+fn pick_lines(todos: &[&Todo], haystacks: &[String], prompt: &str) -> Result<Option<usize>> {
+    let mut err = std::io::stderr().lock();
+    let mut query = String::new();
+    loop {
+        let matches = fuzzy::rank(&query, haystacks);
+        if matches.is_empty() {
+            writeln!(err, "No todo matches \"{query}\".")?;
+        }
+        for (n, &i) in matches.iter().take(ROWS).enumerate() {
+            writeln!(err, "{:>2}) {}", n + 1, row(todos[i]))?;
+        }
+        if matches.len() > ROWS {
+            writeln!(err, "    … and {} more", matches.len() - ROWS)?;
+        }
+        write!(
+            err,
+            "{prompt} (number, or text to filter; empty to cancel): "
+        )?;
+        err.flush()?;
+
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        match line.parse::<usize>() {
+            Ok(n) if (1..=matches.len().min(ROWS)).contains(&n) => {
+                return Ok(Some(matches[n - 1]));
+            }
+            _ => query = line.to_string(),
+        }
+    }
+}
+
+/// Raw, no-echo terminal until dropped. Reads time out after 0.1s so a lone
+/// Esc can be told apart from an arrow key.
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enter() -> Option<Self> {
+        if !cfg!(unix) {
+            return None;
+        }
+        let saved = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+        let saved = String::from_utf8(saved.stdout).ok()?.trim().to_string();
+        stty(&["raw", "-echo", "min", "0", "time", "1"]).then_some(Self { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[self.saved.as_str()]);
+    }
+}
+
+fn stty(args: &[&str]) -> bool {
+    Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
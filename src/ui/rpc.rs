@@ -0,0 +1,330 @@
+//! JSON-RPC 2.0 over stdin/stdout for editor plugins (`rpc`).
+//!
+//! One request per line in, one response per line out, so a Neovim or VS Code
+//! plugin can keep a single process running instead of spawning the CLI per
+//! command. Requests without an `id` are notifications and get no response;
+//! batches aren't supported. The store is reloaded for every request, so edits
+//! made from the CLI in the meantime are seen, and saved after every change.
+//!
+//! Methods mirror the store's operations; `id` params take a full or short id:
+//! - `list` `{"query": "project:Work due<=today", "sort": "due", "limit": 20}`
+//!   (all optional; `query` is the `list --query` language) - an array of todos
+//! - `get` `{"id"}` - the todo
+//! - `add` `{"title", "notes", "project", "tags", "priority", "due"}` - the new
+//!   todo; the title takes the inline tokens `add` does, as in `serve`
+//! - `edit` `{"id", ...the fields to change}` - the edited todo
+//! - `done`, `reopen`, `start`, `stop` `{"id"}` - the todo afterwards
+//! - `delete` `{"id"}` - the deleted todo
+//!
+//! Todos are in the `export` schema. Errors carry the standard codes, plus
+//! [`NOT_FOUND`] and [`CONFLICT`] for todos that are missing or in the wrong
+//! state.
+
+use std::io::{BufRead, Write};
+
+use anyhow::Result;
+use serde_json::{Map, Value, json};
+
+use crate::{
+    app::{
+        errors::AppError,
+        query::{ListQuery, SortKey},
+        repository::TodoRepository,
+        store::Store,
+    },
+    domain::todo::TodoId,
+    ui::http::{CaptureRules, fields, new_todo},
+};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// No todo has the given id.
+pub const NOT_FOUND: i64 = -32001;
+/// The todo can't do that in its state (already done, no timer running, ...).
+pub const CONFLICT: i64 = -32002;
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+
+    fn internal(e: anyhow::Error) -> Self {
+        match e.downcast::<AppError>() {
+            Ok(e) => e.into(),
+            Err(e) => Self::new(INTERNAL_ERROR, format!("{e:#}")),
+        }
+    }
+}
+
+impl From<AppError> for RpcError {
+    fn from(e: AppError) -> Self {
+        let code = match e {
+            AppError::TodoNotFound => NOT_FOUND,
+            _ => CONFLICT,
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+/// Answer requests from `input` on `output` until `input` ends. `load` is
+/// called for every request and `save` after every change; `add` reads
+/// titles by `rules`.
+pub fn serve<R, L, S>(
+    input: impl BufRead,
+    mut output: impl Write,
+    rules: &CaptureRules,
+    mut load: L,
+    mut save: S,
+) -> Result<()>
+where
+    R: TodoRepository,
+    L: FnMut() -> Result<Store<R>>,
+    S: FnMut(&mut Store<R>) -> Result<()>,
+{
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = respond(&line, rules, &mut load, &mut save) {
+            writeln!(output, "{response}")?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// The response line for one request line, or `None` for a notification.
+fn respond<R, L, S>(line: &str, rules: &CaptureRules, load: &mut L, save: &mut S) -> Option<Value>
+where
+    R: TodoRepository,
+    L: FnMut() -> Result<Store<R>>,
+    S: FnMut(&mut Store<R>) -> Result<()>,
+{
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(error(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ));
+        }
+    };
+    let Some(request) = request.as_object() else {
+        return Some(error(
+            Value::Null,
+            RpcError::new(
+                INVALID_REQUEST,
+                "expected one request object (batches aren't supported)",
+            ),
+        ));
+    };
+    let id = request.get("id").cloned();
+    let method = request.get("method").and_then(Value::as_str);
+    let params = match request.get("params") {
+        None | Some(Value::Null) => Ok(Map::new()),
+        Some(Value::Object(params)) => Ok(params.clone()),
+        Some(_) => Err(RpcError::params("params must be an object")),
+    };
+
+    let result = match (request.get("jsonrpc").and_then(Value::as_str), method) {
+        (Some("2.0"), Some(method)) => params.and_then(|p| call(method, p, rules, load, save)),
+        _ => Err(RpcError::new(
+            INVALID_REQUEST,
+            "expected jsonrpc \"2.0\" and a method",
+        )),
+    };
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error(id, e),
+    })
+}
+
+fn error(id: Value, e: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.code, "message": e.message } })
+}
+
+fn call<R, L, S>(
+    method: &str,
+    mut params: Map<String, Value>,
+    rules: &CaptureRules,
+    load: &mut L,
+    save: &mut S,
+) -> Result<Value, RpcError>
+where
+    R: TodoRepository,
+    L: FnMut() -> Result<Store<R>>,
+    S: FnMut(&mut Store<R>) -> Result<()>,
+{
+    const METHODS: &str = "list, get, add, edit, done, reopen, start, stop, delete";
+    if !METHODS.split(", ").any(|m| m == method) {
+        return Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method {method} (use {METHODS})"),
+        ));
+    }
+    let mut store = load().map_err(RpcError::internal)?;
+    let now = store.now();
+    if method == "list" {
+        return list(&store, &params, now);
+    }
+    if method == "add" {
+        let todo = new_todo(&params, rules, now).map_err(RpcError::params)?;
+        let id = todo.id;
+        store.insert_todo(todo)?;
+        save(&mut store).map_err(RpcError::internal)?;
+        return todo_value(&store, id);
+    }
+
+    let todos = store.list_todos();
+    let id = match params.remove("id") {
+        Some(Value::String(id)) => crate::ui::cli::resolve_id_input(&todos, &id)
+            .map_err(|e| RpcError::new(NOT_FOUND, e))?,
+        _ => return Err(RpcError::params("id is required")),
+    };
+    let before = todo_value(&store, id)?;
+    match method {
+        "get" => return Ok(before),
+        "edit" => {
+            let (_, patch) = fields(&params, false, now).map_err(RpcError::params)?;
+            store.edit_todo(id, patch).map_err(RpcError::internal)?;
+        }
+        "done" => store.mark_done(id)?,
+        "reopen" => store.mark_open(id)?,
+        "start" => store.start_timer(id, now)?,
+        "stop" => {
+            store.stop_timer(id, now, None)?;
+        }
+        _ => store.delete(id)?,
+    }
+    save(&mut store).map_err(RpcError::internal)?;
+    if method == "delete" {
+        Ok(before)
+    } else {
+        todo_value(&store, id)
+    }
+}
+
+fn list<R: TodoRepository>(
+    store: &Store<R>,
+    params: &Map<String, Value>,
+    now: time::OffsetDateTime,
+) -> Result<Value, RpcError> {
+    let mut q = ListQuery::default();
+    for (key, value) in params {
+        match (key.as_str(), value) {
+            ("query", Value::String(query)) => q.add_query(query, now).map_err(RpcError::params)?,
+            ("sort", Value::String(sort)) => {
+                q.sort = SortKey::parse(sort)
+                    .ok_or_else(|| RpcError::params(format!("unknown sort {sort}")))?;
+            }
+            ("limit", Value::Number(n)) if n.as_u64().is_some_and(|n| n > 0) => {
+                q.limit = n.as_u64().map(|n| n as usize);
+            }
+            ("limit", _) => return Err(RpcError::params("limit must be a positive number")),
+            ("query" | "sort", _) => {
+                return Err(RpcError::params(format!("{key} must be a string")));
+            }
+            _ => {
+                return Err(RpcError::params(format!(
+                    "unknown param {key} (use query, sort, limit)"
+                )));
+            }
+        }
+    }
+    Ok(json!(store.find_todos(&q, now)))
+}
+
+fn todo_value<R: TodoRepository>(store: &Store<R>, id: TodoId) -> Result<Value, RpcError> {
+    store
+        .list_todos()
+        .into_iter()
+        .find(|t| t.id == id)
+        .map(|t| json!(t))
+        .ok_or_else(|| AppError::TodoNotFound.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::fs_repo::JsonFileTodoRepository;
+
+    #[test]
+    fn requests_get_one_response_line_each() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("db.json");
+        let requests = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"add","params":{"title":"Write docs #work !p1","notes":"for the plugin"}}"#,
+            r#"{"jsonrpc":"2.0","method":"add","params":{"title":"Silent"}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"list","params":{"query":"tag:work"}}"#,
+            "",
+            r#"{"jsonrpc":"2.0","id":3,"method":"done","params":{"id":"ID"}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"done","params":{"id":"ID"}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"edit","params":{"id":"ID","priority":"P3","color":"red"}}"#,
+            r#"{"jsonrpc":"2.0","id":6,"method":"fly"}"#,
+            r#"not json"#,
+            r#"{"jsonrpc":"2.0","id":7,"method":"delete","params":{"id":"ID"}}"#,
+            r#"{"jsonrpc":"2.0","id":8,"method":"get","params":{"id":"ID"}}"#,
+        ];
+        let serve_lines = |lines: &[&str]| -> Vec<Value> {
+            let mut out = Vec::new();
+            serve(
+                lines.join("\n").as_bytes(),
+                &mut out,
+                &CaptureRules::default(),
+                || {
+                    Ok(Store::new(JsonFileTodoRepository::load_or_init(
+                        db.clone(),
+                    )?))
+                },
+                |s: &mut Store<JsonFileTodoRepository>| s.repo_mut().save_atomic(),
+            )
+            .unwrap();
+            String::from_utf8(out)
+                .unwrap()
+                .lines()
+                .map(|l| serde_json::from_str(l).unwrap())
+                .collect()
+        };
+
+        let added = serve_lines(&requests[..2]);
+        assert_eq!(added.len(), 1, "notifications get no response");
+        assert_eq!(added[0]["id"], 1);
+        assert_eq!(added[0]["result"]["priority"], "P1");
+        assert_eq!(added[0]["result"]["notes"], "for the plugin");
+        let short = &added[0]["result"]["id"].as_str().unwrap()[..8];
+
+        let rest: Vec<String> = requests[2..]
+            .iter()
+            .map(|r| r.replace("ID", short))
+            .collect();
+        let rest: Vec<&str> = rest.iter().map(String::as_str).collect();
+        let replies = serve_lines(&rest);
+        assert_eq!(replies[0]["result"].as_array().unwrap().len(), 1);
+        assert!(replies[1]["result"]["status"].is_object());
+        assert_eq!(replies[2]["error"]["code"], CONFLICT);
+        assert_eq!(replies[3]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(replies[4]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(replies[5]["error"]["code"], PARSE_ERROR);
+        assert_eq!(replies[5]["id"], Value::Null);
+        assert_eq!(replies[6]["result"]["title"], "Write docs");
+        assert_eq!(replies[7]["error"]["code"], NOT_FOUND);
+    }
+}
@@ -0,0 +1,125 @@
+//! Whether and how text output is colored.
+//!
+//! `color` in config.toml (or `--color`) says when: `auto` colors terminals
+//! unless `NO_COLOR` is set, `always` also colors pipes (e.g. into `less -R`),
+//! `never` writes plain text. The colors come from the theme.
+
+use crate::{
+    domain::todo::Priority,
+    infra::config::{AppConfig, ColorChoice},
+    ui::theme::{Role, ThemeSpec},
+};
+
+/// Colors to write, or none for plain text.
+#[derive(Debug, Clone)]
+pub struct Style {
+    theme: Option<ThemeSpec>,
+}
+
+impl Style {
+    /// The configured theme, if `config.color` allows colors here.
+    pub fn from_config(config: &AppConfig) -> Self {
+        let on = colors_on(
+            config.color,
+            |key| std::env::var_os(key).is_some(),
+            || {
+                use std::io::IsTerminal;
+                std::io::stdout().is_terminal()
+            },
+        );
+        let theme = on.then(|| {
+            config
+                .theme_spec()
+                .unwrap_or_else(|_| ThemeSpec::builtin(config.theme.name))
+        });
+        Self { theme }
+    }
+
+    pub fn plain() -> Self {
+        Self { theme: None }
+    }
+
+    pub fn is_colored(&self) -> bool {
+        self.theme.is_some()
+    }
+
+    /// `text` in `role`'s color.
+    pub fn paint(&self, role: Role, text: &str) -> String {
+        match &self.theme {
+            Some(spec) => spec.paint(role, text),
+            None => text.to_string(),
+        }
+    }
+
+    /// `text` faint, in `role`'s color.
+    pub fn dim(&self, role: Role, text: &str) -> String {
+        match &self.theme {
+            Some(spec) => spec.paint_dim(role, text),
+            None => text.to_string(),
+        }
+    }
+
+    /// `text` in an exact color (a todo's own `color`).
+    pub fn rgb(&self, (r, g, b): (u8, u8, u8), text: &str) -> String {
+        match &self.theme {
+            Some(_) => format!("\x1b[38;2;{r};{g};{b}m{text}\x1b[0m"),
+            None => text.to_string(),
+        }
+    }
+
+    /// The role for a priority, if it stands out.
+    pub fn priority_role(priority: Priority) -> Option<Role> {
+        match priority {
+            Priority::P1 => Some(Role::Urgent),
+            Priority::P2 => Some(Role::High),
+            Priority::P3 | Priority::P4 => None,
+        }
+    }
+}
+
+/// Should output be colored under `choice`, given whether an environment
+/// variable is set and whether stdout is a terminal?
+pub fn colors_on(
+    choice: ColorChoice,
+    env_set: impl Fn(&str) -> bool,
+    terminal: impl Fn() -> bool,
+) -> bool {
+    match choice {
+        ColorChoice::Auto => !env_set("NO_COLOR") && terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_colors_terminals_without_no_color() {
+        let unset = |_: &str| false;
+        let no_color = |key: &str| key == "NO_COLOR";
+        assert!(colors_on(ColorChoice::Auto, unset, || true));
+        assert!(!colors_on(ColorChoice::Auto, unset, || false));
+        assert!(!colors_on(ColorChoice::Auto, no_color, || true));
+        assert!(colors_on(ColorChoice::Always, no_color, || false));
+        assert!(!colors_on(ColorChoice::Never, unset, || true));
+    }
+
+    #[test]
+    fn plain_style_writes_text_unchanged() {
+        let plain = Style::plain();
+        assert_eq!(plain.paint(Role::Overdue, "OVERDUE"), "OVERDUE");
+        assert_eq!(plain.dim(Role::Done, "x"), "x");
+
+        let config = AppConfig {
+            color: ColorChoice::Always,
+            ..AppConfig::default()
+        };
+        let colored = Style::from_config(&config);
+        assert!(colored.is_colored());
+        assert!(colored.dim(Role::Done, "x").starts_with("\x1b[2;38;2;"));
+        assert_eq!(Style::priority_role(Priority::P1), Some(Role::Urgent));
+        assert_eq!(Style::priority_role(Priority::P4), None);
+    }
+}
@@ -0,0 +1,102 @@
+//! Status symbols for text output (`symbols` in config.toml).
+//!
+//! `☐`/`☑` render as boxes of tofu on the Linux console, legacy Windows
+//! consoles and non-UTF-8 locales, so `auto` looks at the environment and
+//! falls back to `[ ]`/`[x]` there. Nerd Font glyphs are opt-in only: there
+//! is no way to tell whether the terminal font has them.
+
+use crate::{domain::todo::Todo, infra::config::Symbols};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolSet {
+    Unicode,
+    Ascii,
+    NerdFont,
+}
+
+impl SymbolSet {
+    /// The set to use for the configured preference, detecting for `auto`.
+    pub fn from_config(pref: Symbols) -> Self {
+        match pref {
+            Symbols::Auto => Self::detect(|key| std::env::var(key).ok()),
+            Symbols::Unicode => SymbolSet::Unicode,
+            Symbols::Ascii => SymbolSet::Ascii,
+            Symbols::NerdFont => SymbolSet::NerdFont,
+        }
+    }
+
+    /// Unicode unless the terminal (`TERM`), the locale (`LC_ALL`, `LC_CTYPE`,
+    /// `LANG`) or, on Windows, the console suggests it won't display.
+    pub fn detect(env: impl Fn(&str) -> Option<String>) -> Self {
+        let set = |key: &str| env(key).filter(|v| !v.is_empty());
+
+        if matches!(set("TERM").as_deref(), Some("dumb" | "linux" | "vt100")) {
+            return SymbolSet::Ascii;
+        }
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter().find_map(set);
+        if let Some(locale) = locale {
+            let locale = locale.to_ascii_lowercase();
+            if !locale.contains("utf-8") && !locale.contains("utf8") {
+                return SymbolSet::Ascii;
+            }
+        } else if cfg!(windows) && set("WT_SESSION").is_none() && set("TERM_PROGRAM").is_none() {
+            // Plain conhost; Windows Terminal and VS Code set these.
+            return SymbolSet::Ascii;
+        }
+        SymbolSet::Unicode
+    }
+
+    pub fn open(self) -> &'static str {
+        match self {
+            SymbolSet::Unicode => "☐",
+            SymbolSet::Ascii => "[ ]",
+            SymbolSet::NerdFont => "\u{f0131}",
+        }
+    }
+
+    pub fn done(self) -> &'static str {
+        match self {
+            SymbolSet::Unicode => "☑",
+            SymbolSet::Ascii => "[x]",
+            SymbolSet::NerdFont => "\u{f0132}",
+        }
+    }
+
+    pub fn status(self, todo: &Todo) -> &'static str {
+        if todo.status.is_done() {
+            self.done()
+        } else {
+            self.open()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)]) -> SymbolSet {
+        SymbolSet::detect(|key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn auto_falls_back_to_ascii_where_glyphs_break() {
+        let utf8 = ("LANG", "en_US.UTF-8");
+        assert_eq!(
+            detect(&[utf8, ("TERM", "xterm-256color")]),
+            SymbolSet::Unicode
+        );
+        assert_eq!(detect(&[utf8, ("TERM", "linux")]), SymbolSet::Ascii);
+        assert_eq!(detect(&[("LANG", "C")]), SymbolSet::Ascii);
+        // LC_ALL wins over LANG.
+        assert_eq!(detect(&[("LC_ALL", "POSIX"), utf8]), SymbolSet::Ascii);
+        assert_eq!(
+            detect(&[("LC_ALL", ""), ("LC_CTYPE", "de_DE.utf8")]),
+            SymbolSet::Unicode
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Text tables whose columns are as wide as their widest cell.
+//!
+//! Cells are measured before they are colored, so escape codes don't throw
+//! the alignment off. The last column isn't padded, so long titles don't
+//! leave trailing spaces.
+
+use std::io::{self, Write};
+
+use crate::ui::{style::Style, theme::Role};
+
+/// One row: each cell's text and the role to color it with.
+#[derive(Debug, Clone, Default)]
+pub struct Row {
+    pub cells: Vec<(String, Option<Role>)>,
+    /// Dimmed as a whole rather than colored cell by cell (done todos).
+    pub dim: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    headers: Vec<String>,
+    /// Rows under an optional heading.
+    sections: Vec<(Option<String>, Vec<Row>)>,
+}
+
+impl Table {
+    pub fn new(headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            headers: headers.into_iter().map(Into::into).collect(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Start a new group of rows, under `heading` if given.
+    pub fn section(&mut self, heading: Option<String>) {
+        self.sections.push((heading, Vec::new()));
+    }
+
+    pub fn row(&mut self, row: Row) {
+        match self.sections.last_mut() {
+            Some((_, rows)) => rows.push(row),
+            None => self.sections.push((None, vec![row])),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.iter().all(|(_, rows)| rows.is_empty())
+    }
+
+    pub fn write(&self, style: &Style, out: &mut dyn Write) -> io::Result<()> {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for (_, rows) in &self.sections {
+            for row in rows {
+                for (i, (text, _)) in row.cells.iter().enumerate() {
+                    if let Some(w) = widths.get_mut(i) {
+                        *w = (*w).max(text.chars().count());
+                    }
+                }
+            }
+        }
+        let last = widths.len().saturating_sub(1);
+        let line = |cells: &mut dyn Iterator<Item = (usize, &str, Option<Role>)>| {
+            cells
+                .map(|(i, text, role)| {
+                    let cell = if i == last {
+                        text.to_string()
+                    } else {
+                        format!("{text:<width$}", width = widths[i])
+                    };
+                    match role {
+                        Some(role) => style.paint(role, &cell),
+                        None => cell,
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let header = line(
+            &mut self
+                .headers
+                .iter()
+                .enumerate()
+                .map(|(i, h)| (i, h.as_str(), None)),
+        );
+        writeln!(out, "{}", style.paint(Role::Header, &header))?;
+        for (heading, rows) in &self.sections {
+            if let Some(heading) = heading {
+                writeln!(out, "\n{}", style.paint(Role::Header, heading))?;
+            }
+            for row in rows {
+                let mut cells = row
+                    .cells
+                    .iter()
+                    .take(widths.len())
+                    .enumerate()
+                    .map(|(i, (text, role))| (i, text.as_str(), role.filter(|_| !row.dim)));
+                let text = line(&mut cells);
+                if row.dim {
+                    writeln!(out, "{}", style.dim(Role::Done, &text))?;
+                } else {
+                    writeln!(out, "{text}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(cells: &[&str]) -> Row {
+        Row {
+            cells: cells.iter().map(|c| (c.to_string(), None)).collect(),
+            dim: false,
+        }
+    }
+
+    #[test]
+    fn columns_fit_their_widest_cell_across_sections() {
+        let mut table = Table::new(["ID", "P", "TITLE"]);
+        assert!(table.is_empty());
+        table.section(Some("Work (2)".to_string()));
+        table.row(row(&["3f2a1b0c", "P1", "Ship it"]));
+        table.row(row(&["77aa", "High", "Write the release notes"]));
+        table.section(Some("Home (1)".to_string()));
+        table.row(row(&["9c", "P4", "Water plants"]));
+
+        let mut out = Vec::new();
+        table.write(&Style::plain(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "ID       P    TITLE\n\
+             \n\
+             Work (2)\n\
+             3f2a1b0c P1   Ship it\n\
+             77aa     High Write the release notes\n\
+             \n\
+             Home (1)\n\
+             9c       P4   Water plants\n"
+        );
+    }
+}
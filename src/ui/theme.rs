@@ -0,0 +1,253 @@
+//! Colors for text output (`theme` in config.toml).
+//!
+//! A theme is one of the built-in palettes, optionally with some of its
+//! colors replaced from `[theme.colors]`:
+//!
+//! ```toml
+//! [theme]
+//! name = "light"
+//!
+//! [theme.colors]
+//! overdue = "#d70000"
+//! tags = "#5f87af"
+//! ```
+//!
+//! [`ThemeSpec::resolve`] turns that into the color of every [`Role`];
+//! `ui::style` decides whether to use it.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::infra::config::{Theme, ThemeConfig};
+
+/// What a piece of text is, for picking its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    Header,
+    Id,
+    Done,
+    Urgent,
+    High,
+    Overdue,
+    Project,
+    Tags,
+    Due,
+    Warning,
+}
+
+impl Role {
+    pub const ALL: [Role; 10] = [
+        Role::Header,
+        Role::Id,
+        Role::Done,
+        Role::Urgent,
+        Role::High,
+        Role::Overdue,
+        Role::Project,
+        Role::Tags,
+        Role::Due,
+        Role::Warning,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::Header => "header",
+            Role::Id => "id",
+            Role::Done => "done",
+            Role::Urgent => "urgent",
+            Role::High => "high",
+            Role::Overdue => "overdue",
+            Role::Project => "project",
+            Role::Tags => "tags",
+            Role::Due => "due",
+            Role::Warning => "warning",
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        let s = input.trim().to_ascii_lowercase();
+        Self::ALL.into_iter().find(|r| r.name() == s)
+    }
+}
+
+/// A 24-bit color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// Parse `#rrggbb` or `#rgb`.
+    pub fn parse_hex(input: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid color {input:?} (use #rrggbb or #rgb)");
+        let hex = input.trim().strip_prefix('#').ok_or_else(invalid)?;
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let digits: Vec<u8> = match hex.len() {
+            3 => hex
+                .chars()
+                .map(|c| u8::from_str_radix(&format!("{c}{c}"), 16).unwrap())
+                .collect(),
+            6 => (0..3)
+                .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap())
+                .collect(),
+            _ => return Err(invalid()),
+        };
+        Ok(Rgb(digits[0], digits[1], digits[2]))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// The color of every role.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeSpec {
+    colors: BTreeMap<Role, Rgb>,
+    /// Roles whose color comes from `[theme.colors]`.
+    custom: Vec<Role>,
+}
+
+impl ThemeSpec {
+    /// A built-in palette.
+    pub fn builtin(theme: Theme) -> Self {
+        let palette: [(u8, u8, u8); 10] = match theme {
+            Theme::Dark => [
+                (188, 188, 188),
+                (135, 135, 135),
+                (98, 98, 98),
+                (255, 135, 95),
+                (215, 175, 95),
+                (255, 95, 95),
+                (135, 175, 255),
+                (135, 215, 175),
+                (215, 215, 135),
+                (255, 215, 0),
+            ],
+            Theme::Light => [
+                (48, 48, 48),
+                (118, 118, 118),
+                (158, 158, 158),
+                (215, 95, 0),
+                (175, 135, 0),
+                (175, 0, 0),
+                (0, 95, 175),
+                (0, 135, 95),
+                (135, 95, 0),
+                (175, 135, 0),
+            ],
+            Theme::HighContrast => [
+                (255, 255, 255),
+                (255, 255, 255),
+                (192, 192, 192),
+                (255, 128, 0),
+                (255, 255, 0),
+                (255, 64, 64),
+                (0, 255, 255),
+                (0, 255, 0),
+                (255, 255, 0),
+                (255, 255, 0),
+            ],
+        };
+        let colors = Role::ALL
+            .into_iter()
+            .zip(palette)
+            .map(|(role, (r, g, b))| (role, Rgb(r, g, b)))
+            .collect();
+        Self {
+            colors,
+            custom: Vec::new(),
+        }
+    }
+
+    /// The configured palette with `[theme.colors]` applied. Errors name the
+    /// entry at fault.
+    pub fn resolve(config: &ThemeConfig) -> Result<Self, String> {
+        let mut spec = Self::builtin(config.name);
+        for (name, value) in &config.colors {
+            let Some(role) = Role::parse(name) else {
+                let names: Vec<_> = Role::ALL.iter().map(|r| r.name()).collect();
+                return Err(format!(
+                    "[theme.colors] unknown role {name} (use {})",
+                    names.join(", ")
+                ));
+            };
+            let color = Rgb::parse_hex(value).map_err(|e| format!("[theme.colors] {name}: {e}"))?;
+            spec.colors.insert(role, color);
+            spec.custom.push(role);
+        }
+        Ok(spec)
+    }
+
+    pub fn color(&self, role: Role) -> Rgb {
+        self.colors[&role]
+    }
+
+    /// Was `role`'s color set in config.toml?
+    pub fn is_custom(&self, role: Role) -> bool {
+        self.custom.contains(&role)
+    }
+
+    /// `text` in `role`'s color, as ANSI escapes.
+    pub fn paint(&self, role: Role, text: &str) -> String {
+        let Rgb(r, g, b) = self.color(role);
+        format!("\x1b[38;2;{r};{g};{b}m{text}\x1b[0m")
+    }
+
+    /// Like [`paint`](Self::paint), but faint.
+    pub fn paint_dim(&self, role: Role, text: &str) -> String {
+        let Rgb(r, g, b) = self.color(role);
+        format!("\x1b[2;38;2;{r};{g};{b}m{text}\x1b[0m")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_colors_parse_in_both_lengths() {
+        assert_eq!(Rgb::parse_hex("#FF8000"), Ok(Rgb(255, 128, 0)));
+        assert_eq!(Rgb::parse_hex(" #f80 "), Ok(Rgb(255, 136, 0)));
+        assert_eq!(Rgb(255, 128, 0).to_string(), "#ff8000");
+        for bad in ["ff8000", "#ff80", "#gg0000", "#", "red"] {
+            assert!(Rgb::parse_hex(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn overrides_replace_palette_colors() {
+        let config = ThemeConfig {
+            name: Theme::Light,
+            colors: [("Overdue".to_string(), "#123456".to_string())].into(),
+        };
+        let spec = ThemeSpec::resolve(&config).unwrap();
+        assert_eq!(spec.color(Role::Overdue), Rgb(0x12, 0x34, 0x56));
+        assert_eq!(
+            spec.color(Role::Due),
+            ThemeSpec::builtin(Theme::Light).color(Role::Due)
+        );
+        assert!(spec.is_custom(Role::Overdue) && !spec.is_custom(Role::Due));
+        assert_eq!(
+            spec.paint(Role::Overdue, "x"),
+            "\x1b[38;2;18;52;86mx\x1b[0m"
+        );
+
+        let bad = |name: &str, value: &str| ThemeConfig {
+            name: Theme::Dark,
+            colors: [(name.to_string(), value.to_string())].into(),
+        };
+        let err = ThemeSpec::resolve(&bad("borders", "#fff")).unwrap_err();
+        assert!(
+            err.starts_with("[theme.colors] unknown role borders"),
+            "{err}"
+        );
+        let err = ThemeSpec::resolve(&bad("done", "grey")).unwrap_err();
+        assert!(
+            err.starts_with("[theme.colors] done: invalid color"),
+            "{err}"
+        );
+    }
+}
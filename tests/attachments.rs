@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tempfile::tempdir;
+
+use rustytodo::app::context::AppContext;
+use rustytodo::domain::todo::Todo;
+use rustytodo::infra::config::AppConfig;
+use rustytodo::infra::paths::AppPaths;
+
+fn test_ctx(dir: &Path) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.join("cfg"),
+        data_dir: dir.join("data"),
+    };
+    let cfg = AppConfig {
+        storage_path: Some(dir.join("db.json")),
+        ..AppConfig::default()
+    };
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn find(ctx: &AppContext, title: &str) -> Result<Todo> {
+    let todos: Vec<Todo> = serde_json::from_str(&run(ctx, &["list", "--format", "json"])?)?;
+    Ok(todos
+        .into_iter()
+        .find(|t| t.title.as_str() == title)
+        .unwrap())
+}
+
+#[test]
+fn attach_copies_files_and_detach_removes_them() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(&ctx, &["add", "Review contract"])?;
+    let todo = find(&ctx, "Review contract")?;
+    let id = todo.id.short();
+    let source = dir.path().join("contract.pdf");
+    std::fs::write(&source, b"%PDF-1.7")?;
+    let source = source.to_str().unwrap();
+
+    assert!(run(&ctx, &["attach", &id, source])?.contains("Attached contract.pdf"));
+    run(
+        &ctx,
+        &["attach", &id, source, "--link", "--label", "Original"],
+    )?;
+    run(
+        &ctx,
+        &[
+            "attach",
+            &id,
+            "https://example.com/thread",
+            "--label",
+            "Thread",
+        ],
+    )?;
+    assert!(run(&ctx, &["attach", &id, "missing.pdf"])?.contains("no such file"));
+    std::fs::remove_file(source)?;
+
+    let copy = dir
+        .path()
+        .join("data/attachments")
+        .join(todo.id.as_uuid_str())
+        .join("contract.pdf");
+    assert_eq!(std::fs::read(&copy)?, b"%PDF-1.7");
+    let shown = run(&ctx, &["show", &id])?;
+    assert!(
+        shown.contains(&format!("1. contract.pdf  {}", copy.display())),
+        "{shown}"
+    );
+    assert!(
+        shown.contains("2. Original") && shown.contains("(missing)"),
+        "{shown}"
+    );
+    assert!(
+        shown.contains("3. Thread  https://example.com/thread"),
+        "{shown}"
+    );
+
+    assert!(run(&ctx, &["detach", &id, "contract.pdf"])?.contains("Detached contract.pdf"));
+    assert!(!copy.exists());
+    assert!(run(&ctx, &["detach", &id, "5"])?.contains("has no attachment 5"));
+    let shown = run(&ctx, &["show", &id])?;
+    assert!(
+        shown.contains("1. Original") && !shown.contains("contract.pdf  "),
+        "{shown}"
+    );
+    Ok(())
+}
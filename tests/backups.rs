@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tempfile::tempdir;
+
+use rustytodo::app::context::AppContext;
+use rustytodo::infra::config::{AppConfig, SnapshotConfig};
+use rustytodo::infra::paths::AppPaths;
+use rustytodo::infra::snapshot;
+
+/// A context with `cfg`, keeping the database under `dir`.
+fn test_ctx_with(dir: &Path, mut cfg: AppConfig) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.join("cfg"),
+        data_dir: dir.join("data"),
+    };
+    cfg.storage_path = Some(dir.join("db.json"));
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+#[test]
+fn backups_push_to_a_folder_target_and_pull_back() -> Result<()> {
+    let dir = tempdir()?;
+    let offsite = dir.path().join("offsite");
+    let mut cfg = AppConfig::default();
+    cfg.backup.encrypt = false;
+    cfg.backup.keep = 1;
+    cfg.backup
+        .targets
+        .insert("nas".into(), offsite.display().to_string());
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    run(&ctx, &["add", "Water plants"])?;
+    let pushed = run(&ctx, &["backup", "push", "--target", "nas"])?;
+    assert!(pushed.starts_with("Pushed rustytodo-backup-"), "{pushed}");
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let again = run(&ctx, &["backup", "push", "--target", "nas"])?;
+    assert!(again.contains("Removed 1 old backup(s)"), "{again}");
+    assert_eq!(
+        run(&ctx, &["backup", "list", "--target", "nas"])?
+            .lines()
+            .count(),
+        1
+    );
+
+    let restored = dir.path().join("restored");
+    let msg = run(
+        &ctx,
+        &[
+            "backup",
+            "pull",
+            "--target",
+            "nas",
+            "--out",
+            restored.to_str().unwrap(),
+        ],
+    )?;
+    assert!(msg.starts_with("Unpacked rustytodo-backup-"), "{msg}");
+    let db = std::fs::read_to_string(restored.join("database/db.json"))?;
+    assert!(db.contains("Water plants"));
+
+    let bad = run(&ctx, &["backup", "push", "--target", "s3://"])?;
+    assert!(bad.contains("s3 target needs a bucket"), "{bad}");
+    Ok(())
+}
+
+#[test]
+fn scheduled_snapshots_run_after_commands() -> Result<()> {
+    let dir = tempdir()?;
+    let folder = dir.path().join("backups");
+    let cfg = AppConfig {
+        snapshot: SnapshotConfig {
+            every: Some("7d".to_string()),
+            folder: Some(folder.clone()),
+            ..Default::default()
+        },
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    run(&ctx, &["add", "Back up the laptop"])?;
+    let snapshots = snapshot::list(&folder);
+    assert_eq!(snapshots.len(), 1);
+    let json = std::fs::read_to_string(&snapshots[0].0)?;
+    assert!(json.contains("Back up the laptop"));
+
+    // Not due again for a week.
+    run(&ctx, &["list"])?;
+    assert_eq!(snapshot::list(&folder).len(), 1);
+    let listed = run(&ctx, &["snapshot", "--list"])?;
+    assert!(listed.contains("rustytodo-"), "{listed}");
+    Ok(())
+}
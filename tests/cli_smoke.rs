@@ -1,11 +1,53 @@
+use std::path::Path;
+
 use anyhow::Result;
 use tempfile::tempdir;
 
 use rustytodo::app::context::AppContext;
+use rustytodo::domain::todo::Todo;
 use rustytodo::infra::config::{AppConfig, Theme};
 use rustytodo::infra::paths::AppPaths;
 
+fn test_ctx(dir: &Path) -> AppContext {
+    test_ctx_with(dir, AppConfig::default())
+}
+
+/// A context with `cfg`, keeping the database under `dir` unless `cfg`
+/// names another place.
+fn test_ctx_with(dir: &Path, mut cfg: AppConfig) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.join("cfg"),
+        data_dir: dir.join("data"),
+    };
+    cfg.storage_path.get_or_insert_with(|| dir.join("db.json"));
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn todos(ctx: &AppContext) -> Result<Vec<Todo>> {
+    Ok(serde_json::from_str(&run(
+        ctx,
+        &["list", "--format", "json"],
+    )?)?)
+}
+
+fn find(ctx: &AppContext, title: &str) -> Result<Todo> {
+    Ok(todos(ctx)?
+        .into_iter()
+        .find(|t| t.title.as_str() == title)
+        .unwrap())
+}
+
 #[test]
+#[allow(clippy::field_reassign_with_default)]
 fn done_and_delete_flow() -> Result<()> {
     let dir = tempdir()?;
 
@@ -15,7 +57,7 @@ fn done_and_delete_flow() -> Result<()> {
     };
 
     let mut cfg = AppConfig::default();
-    cfg.theme = Theme::Dark;
+    cfg.theme = Theme::Dark.into();
     cfg.storage_path = Some(dir.path().join("db.json"));
 
     let ctx = AppContext::new(paths, cfg);
@@ -59,3 +101,1759 @@ fn done_and_delete_flow() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn dictated_add_extracts_fields_and_flags_win() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    let out = run(
+        &ctx,
+        &[
+            "add",
+            "--dictated",
+            "remind me to call the dentist tomorrow afternoon its urgent",
+            "--project",
+            "Health",
+        ],
+    )?;
+    assert!(out.contains("Understood: \"Call the dentist\" [P1] Health due"));
+
+    let todos: Vec<Todo> = serde_json::from_str(&run(
+        &ctx,
+        &["list", "--format", "json", "--search", "dentist"],
+    )?)?;
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].project.as_str(), "Health");
+    assert!(todos[0].due.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn template_add_fills_title_placeholders() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg: AppConfig = toml::from_str(
+        r#"
+        [templates.weekly]
+        title = "Weekly report W{week} for {project}"
+        project = "Ops"
+        tags = ["report"]
+        "#,
+    )?;
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    run(&ctx, &["add", "--template", "weekly", "--project", "Acme"])?;
+
+    let todos: Vec<Todo> = serde_json::from_str(&run(
+        &ctx,
+        &["list", "--format", "json", "--tag", "report"],
+    )?)?;
+    assert_eq!(todos.len(), 1);
+    let week = time::OffsetDateTime::now_utc().iso_week();
+    assert_eq!(
+        todos[0].title.as_str(),
+        format!("Weekly report W{week:02} for Acme")
+    );
+    Ok(())
+}
+
+#[test]
+fn confirm_policy_refuses_without_force() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg = AppConfig {
+        confirm: vec!["delete".into(), "import".into()],
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+    let count = || -> Result<usize> { Ok(todos(&ctx)?.len()) };
+
+    let before = count()?;
+    let backup = dir.path().join("backup.json");
+    run(
+        &ctx,
+        &[
+            "export",
+            "--format",
+            "json",
+            "--out",
+            backup.to_str().unwrap(),
+        ],
+    )?;
+
+    let first = todos(&ctx)?[0].id.short();
+
+    // Tests have no terminal to answer on, so confirmable operations refuse.
+    assert!(run(&ctx, &["delete", &first])?.contains("Re-run with --force"));
+    assert!(
+        run(&ctx, &["import", "--in", backup.to_str().unwrap()])?.contains("Re-run with --force")
+    );
+    assert_eq!(count()?, before);
+
+    run(&ctx, &["--force", "delete", &first])?;
+    assert_eq!(count()?, before - 1);
+    run(
+        &ctx,
+        &["import", "--force", "--in", backup.to_str().unwrap()],
+    )?;
+    assert_eq!(count()?, before);
+
+    Ok(())
+}
+
+#[test]
+fn undo_and_redo_survive_between_runs() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let titles = || -> Result<Vec<String>> {
+        Ok(todos(&ctx)?
+            .iter()
+            .map(|t| t.title.as_str().to_string())
+            .collect())
+    };
+
+    // Seeding a fresh database isn't undoable.
+    let seeded = titles()?;
+    assert!(run(&ctx, &["undo"])?.contains("Nothing to undo"));
+
+    run(&ctx, &["add", "Water plants"])?;
+    let first = todos(&ctx)?[0].id.short();
+    run(&ctx, &["delete", &first, "--yes"])?;
+    assert_eq!(titles()?.len(), seeded.len());
+
+    assert!(run(&ctx, &["undo"])?.contains("Undid delete"));
+    assert_eq!(titles()?.len(), seeded.len() + 1);
+    assert!(run(&ctx, &["undo"])?.contains("Undid add"));
+    assert_eq!(titles()?, seeded);
+
+    assert!(run(&ctx, &["redo"])?.contains("Redid add"));
+    assert!(titles()?.contains(&"Water plants".to_string()));
+    let history = run(&ctx, &["undo", "--list"])?;
+    assert!(history.contains("undo") && history.contains("redo"));
+
+    // A new change drops what could still be redone.
+    run(&ctx, &["add", "Feed cat"])?;
+    assert!(run(&ctx, &["redo"])?.contains("Nothing to redo"));
+    Ok(())
+}
+
+#[test]
+fn import_merges_files_matching_a_pattern() -> Result<()> {
+    use rustytodo::domain::todo::{Priority, Title, TodoPatch};
+    use rustytodo::infra::db_schema::write_current;
+
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    let exports = dir.path().join("exports");
+    std::fs::create_dir(&exports)?;
+    let shared = Todo::new(Title::parse("Shared")?);
+    let mut edited = shared.clone();
+    edited.apply_patch(TodoPatch {
+        priority: Some(Priority::P1),
+        ..TodoPatch::default()
+    });
+    let laptop = vec![shared, Todo::new(Title::parse("Laptop")?)];
+    let desktop = vec![edited, Todo::new(Title::parse("Desktop")?)];
+    std::fs::write(exports.join("laptop.json"), write_current(&laptop)?)?;
+    std::fs::write(exports.join("desktop.json"), write_current(&desktop)?)?;
+    std::fs::write(exports.join("notes.txt"), "not an export")?;
+
+    let pattern = exports.join("*.json");
+    let report = run(&ctx, &["import", "--in", pattern.to_str().unwrap()])?;
+    assert!(report.contains("desktop.json: 2 todos, 2 new, 0 merged"));
+    assert!(report.contains("laptop.json: 2 todos, 1 new, 1 merged"));
+    assert!(report.contains("Imported 3 todos from 2 files"));
+
+    assert_eq!(todos(&ctx)?.len(), 3);
+    assert_eq!(find(&ctx, "Shared")?.priority, Priority::P1);
+
+    // One unreadable file stops the whole import.
+    std::fs::write(exports.join("broken.json"), "{")?;
+    let report = run(&ctx, &["import", "--in", pattern.to_str().unwrap()])?;
+    assert!(report.contains("Nothing imported: 1 of 3 files"));
+    Ok(())
+}
+
+#[test]
+fn todoist_exports_import_into_projects_and_tags() -> Result<()> {
+    use rustytodo::domain::todo::{Priority, Source};
+
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/todoist/*");
+    let report = run(&ctx, &["import", "--format", "todoist", "--in", fixtures])?;
+    assert!(report.contains("Imported 7 todos from 2 files"), "{report}");
+
+    let report = find(&ctx, "Send quarterly report")?;
+    assert_eq!(report.project.as_str(), "Work");
+    assert_eq!(report.priority, Priority::P1);
+    assert_eq!(report.source, Some(Source::Import("Work.csv".to_string())));
+    assert!(
+        todos(&ctx)?
+            .iter()
+            .any(|t| t.project.as_str() == "Groceries")
+    );
+    Ok(())
+}
+
+#[test]
+fn csv_round_trips_subtasks_and_dependencies() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(&ctx, &["add", "Release 1.0"])?;
+    let release = find(&ctx, "Release 1.0")?.id;
+    run(
+        &ctx,
+        &["add", "Write changelog", "--parent", &release.short()],
+    )?;
+    let changelog = find(&ctx, "Write changelog")?.id;
+    run(
+        &ctx,
+        &[
+            "add",
+            "Announce",
+            "--depends-on",
+            &changelog.short(),
+            "--depends-on",
+            &release.short(),
+        ],
+    )?;
+
+    let csv = dir.path().join("todos.csv");
+    let csv_arg = csv.to_str().unwrap();
+    run(&ctx, &["export", "--format", "csv", "--out", csv_arg])?;
+    let text = std::fs::read_to_string(&csv)?;
+    assert!(
+        text.lines()
+            .next()
+            .unwrap()
+            .ends_with(",parent_id,depends_on"),
+        "{text}"
+    );
+
+    run(&ctx, &["--force", "delete", &changelog.short()])?;
+    run(&ctx, &["import", "--format", "csv", "--in", csv_arg])?;
+    assert_eq!(find(&ctx, "Write changelog")?.parent, Some(release));
+    assert_eq!(
+        find(&ctx, "Announce")?.depends_on,
+        [changelog, release].into()
+    );
+
+    // Hand-written rows may refer to each other by short id, in any order,
+    // and leave the new columns out altogether.
+    std::fs::write(
+        &csv,
+        "id,title,status,priority,project,due,notes,tags,parent_id,depends_on\n\
+         0000000a-0000-4000-8000-000000000001,Ship,open,P2,Inbox,,,,,0000000b\n\
+         0000000b-0000-4000-8000-000000000002,Test,open,P2,Inbox,,,,0000000a,\n",
+    )?;
+    run(&ctx, &["import", "--format", "csv", "--in", csv_arg])?;
+    let (ship, test) = (find(&ctx, "Ship")?, find(&ctx, "Test")?);
+    assert_eq!(test.parent, Some(ship.id));
+    assert_eq!(ship.depends_on, [test.id].into());
+
+    std::fs::write(
+        &csv,
+        "id,title,status,priority,project\n\
+         0000000c-0000-4000-8000-000000000003,Plain,open,P3,Inbox\n",
+    )?;
+    run(&ctx, &["import", "--format", "csv", "--in", csv_arg])?;
+    assert_eq!(todos(&ctx)?.len(), 1);
+
+    std::fs::write(
+        &csv,
+        "id,title,status,priority,project,parent_id\n\
+         0000000c-0000-4000-8000-000000000003,Plain,open,P3,Inbox,ffff\n",
+    )?;
+    let err = run(&ctx, &["import", "--format", "csv", "--in", csv_arg]).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("row 2: parent_id: no row has id ffff"),
+        "{err:#}"
+    );
+    Ok(())
+}
+
+#[test]
+fn startup_clears_temp_files_left_by_a_crash() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(&ctx, &["add", "Keep me"])?;
+    let tmp = dir.path().join("db.json.tmp");
+    let saved = std::fs::read(dir.path().join("db.json"))?;
+    std::fs::write(&tmp, &saved[..saved.len() / 3])?;
+    let an_hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    std::fs::File::options()
+        .append(true)
+        .open(&tmp)?
+        .set_modified(an_hour_ago)?;
+
+    assert!(run(&ctx, &["list"])?.contains("Keep me"));
+    assert!(!tmp.exists());
+    Ok(())
+}
+
+#[test]
+fn done_edit_and_delete_take_several_ids() -> Result<()> {
+    use rustytodo::domain::todo::Priority;
+
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    let seeded = todos(&ctx)?;
+    let a = seeded[0].id.short();
+    let b = seeded[1].id.short();
+
+    let report = run(&ctx, &["done", &a, &b, "zzzzzzzz"])?;
+    assert!(report.contains(&format!("Done {a}")) && report.contains(&format!("Done {b}")));
+    assert!(report.contains("zzzzzzzz: no todo found"));
+    assert_eq!(
+        todos(&ctx)?.iter().filter(|t| t.status.is_done()).count(),
+        2
+    );
+
+    let report = run(&ctx, &["done", &a])?;
+    assert!(report.contains("already done"));
+
+    run(&ctx, &["edit", &a, &b, "--priority", "P1"])?;
+    assert_eq!(
+        todos(&ctx)?
+            .iter()
+            .filter(|t| t.priority == Priority::P1)
+            .count(),
+        2
+    );
+
+    run(&ctx, &["edit", &a, "--tag", "home", "--tag", "errand"])?;
+    run(
+        &ctx,
+        &["edit", &a, "--add-tag", "urgent", "--remove-tag", "errand"],
+    )?;
+    let tagged = todos(&ctx)?
+        .into_iter()
+        .find(|t| t.id == seeded[0].id)
+        .unwrap();
+    let names: Vec<_> = tagged.tags.iter().map(|t| t.as_str()).collect();
+    assert_eq!(names, ["home", "urgent"]);
+
+    run(&ctx, &["delete", &a, &b, "--yes"])?;
+    assert_eq!(todos(&ctx)?.len(), seeded.len() - 2);
+    Ok(())
+}
+
+#[test]
+fn migrate_dry_run_reports_and_upgrades_keep_a_backup() -> Result<()> {
+    let dir = tempdir()?;
+    let db = dir.path().join("db.json");
+    let v1 = r#"{"schema_version": 1, "todos": []}"#;
+    std::fs::write(&db, v1)?;
+    let ctx = test_ctx(dir.path());
+
+    let report = run(&ctx, &["migrate", "--dry-run"])?;
+    assert!(
+        report.starts_with(
+            "v1 -> v2: add project records\n  + projects: empty list\n  ~ schema_version: 1 -> 2\n"
+        ),
+        "{report}"
+    );
+    assert!(report.contains("nothing written"), "{report}");
+    assert_eq!(std::fs::read_to_string(&db)?, v1);
+
+    let done = run(&ctx, &["migrate"])?;
+    assert!(done.contains("backup: "), "{done}");
+    let backup = dir.path().join("db.json.v1.bak");
+    assert_eq!(std::fs::read_to_string(&backup)?, v1);
+    let stored: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&db)?)?;
+    assert_eq!(stored["schema_version"], 2);
+    assert_eq!(run(&ctx, &["migrate"])?, "Database is already at v2.\n");
+
+    // Loading an old file for any other command backs it up too.
+    std::fs::remove_file(&backup)?;
+    std::fs::write(&db, v1)?;
+    run(&ctx, &["add", "Water plants"])?;
+    assert_eq!(std::fs::read_to_string(&backup)?, v1);
+    Ok(())
+}
+
+#[test]
+fn project_commands_rename_and_archive() -> Result<()> {
+    let dir = tempdir()?;
+    let db = dir.path().join("db.json");
+    // An existing v1 database is upgraded the first time it is saved.
+    std::fs::write(&db, r#"{"schema_version": 1, "todos": []}"#)?;
+    let ctx = test_ctx(dir.path());
+
+    run(&ctx, &["add", "Quarterly report", "--project", "Side"])?;
+    run(
+        &ctx,
+        &[
+            "project",
+            "edit",
+            "side",
+            "--description",
+            "Weekend hacking",
+        ],
+    )?;
+    let stored: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&db)?)?;
+    assert_eq!(stored["schema_version"], 2);
+    assert_eq!(stored["projects"][0]["name"], "Side");
+
+    let renamed = run(&ctx, &["project", "rename", "SIDE", "Acme"])?;
+    assert!(
+        renamed.contains("Renamed SIDE into Acme (1 todo(s) moved)"),
+        "{renamed}"
+    );
+    let list = run(&ctx, &["project", "list"])?;
+    assert!(
+        list.contains("Acme") && list.contains("Weekend hacking"),
+        "{list}"
+    );
+    assert!(!list.contains("Side"));
+    let todos = run(&ctx, &["list", "--project", "acme", "--format", "json"])?;
+    assert!(todos.contains("Quarterly report"));
+
+    run(&ctx, &["project", "archive", "acme"])?;
+    assert!(!run(&ctx, &["project", "list"])?.contains("Acme"));
+    assert!(run(&ctx, &["project", "list", "--all"])?.contains("(archived) Weekend hacking"));
+    assert!(run(&ctx, &["project", "archive", "nope"])?.contains("project not found"));
+    Ok(())
+}
+
+#[test]
+fn logs_show_filters_the_log_file_by_level() -> Result<()> {
+    use rustytodo::infra::logfile::{self, LogEntry, RotatingFile};
+
+    let dir = tempdir()?;
+    let mut cfg = AppConfig::default();
+    assert!(
+        run(&test_ctx_with(dir.path(), cfg.clone()), &["logs", "show"])?
+            .contains("set file = true")
+    );
+
+    cfg.log.file = true;
+    let ctx = test_ctx_with(dir.path(), cfg);
+    let mut file = RotatingFile::open(&logfile::log_dir(&ctx.paths.data_dir), 1 << 20, 1)?;
+    for (level, message) in [
+        ("INFO", "started"),
+        ("WARN", "slow save"),
+        ("ERROR", "boom"),
+    ] {
+        let entry = LogEntry {
+            ts: "2026-03-01T12:00:00Z".to_string(),
+            level: level.to_string(),
+            target: "rustytodo".to_string(),
+            message: message.to_string(),
+            spans: Vec::new(),
+            fields: Default::default(),
+        };
+        file.write_line(&serde_json::to_string(&entry)?)?;
+    }
+
+    let warn = run(&ctx, &["logs", "show", "--level", "warn"])?;
+    assert!(
+        warn.contains("slow save") && warn.contains("boom"),
+        "{warn}"
+    );
+    assert!(!warn.contains("started"));
+    let tail = run(&ctx, &["logs", "tail", "-n", "1"])?;
+    assert_eq!(tail.lines().count(), 1);
+    assert!(tail.contains("ERROR boom"));
+    Ok(())
+}
+
+#[test]
+fn timings_flag_prints_a_breakdown() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let out = run(&ctx, &["list", "--timings"])?;
+    let report = out.split("Timings:").nth(1).unwrap_or_default();
+    for op in ["load", "query", "command"] {
+        assert!(report.contains(&format!("  {op} ")), "{out}");
+    }
+    Ok(())
+}
+
+#[test]
+fn notify_alerts_once_per_reminder() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(
+        &ctx,
+        &["add", "Water plants", "--remind", "2020-01-01T09:00:00Z"],
+    )?;
+    let first = run(&ctx, &["notify", "--print"])?;
+    assert!(first.contains("Reminder: Water plants"), "{first}");
+    assert!(run(&ctx, &["notify", "--print"])?.contains("Nothing to notify about."));
+
+    let id = find(&ctx, "Water plants")?.id.short();
+    run(&ctx, &["edit", &id, "--remind", "2020-01-02T09:00:00Z"])?;
+    assert!(run(&ctx, &["notify", "--print"])?.contains("Reminder: Water plants"));
+    Ok(())
+}
+
+#[test]
+fn notify_escalates_by_priority_until_overridden() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg = AppConfig {
+        escalation: [("P1".to_string(), "-1d, -1h".to_string())].into(),
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    let due = time::OffsetDateTime::now_utc() + time::Duration::hours(12);
+    let due = due.format(&time::format_description::well_known::Rfc3339)?;
+    run(
+        &ctx,
+        &["add", "Renew passport", "--priority", "p1", "--due", &due],
+    )?;
+    run(
+        &ctx,
+        &["add", "Water plants", "--priority", "p3", "--due", &due],
+    )?;
+    let first = run(&ctx, &["notify", "--print"])?;
+    assert!(first.contains("Due soon: Renew passport"), "{first}");
+    assert!(!first.contains("Water plants"), "{first}");
+    assert!(run(&ctx, &["notify", "--print"])?.contains("Nothing to notify about."));
+
+    let id = find(&ctx, "Renew passport")?.id.short();
+    run(&ctx, &["edit", &id, "--escalate", "-2h, every 30m, -1h"])?;
+    let shown = run(&ctx, &["show", &id])?;
+    assert!(shown.contains("Escalate: -2h, -1h, every 30m"), "{shown}");
+    let bad = run(&ctx, &["edit", &id, "--escalate", "soon"]);
+    assert!(bad.is_err());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn data_files_are_private_and_doctor_flags_exposed_ones() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir()?;
+    let db = dir.path().join("todos").join("db.json");
+    let cfg = AppConfig {
+        storage_path: Some(db.clone()),
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+    let mode = |p: &Path| -> Result<u32> { Ok(std::fs::metadata(p)?.permissions().mode() & 0o777) };
+
+    run(&ctx, &["add", "Door code 4711"])?;
+    assert_eq!(mode(&db)?, 0o600);
+    assert_eq!(mode(db.parent().unwrap())?, 0o700);
+    assert!(run(&ctx, &["doctor"])?.contains("No problems found"));
+
+    std::fs::set_permissions(&db, std::fs::Permissions::from_mode(0o644))?;
+    // The next save replaces the file with a private one again.
+    run(&ctx, &["add", "Another"])?;
+    assert_eq!(mode(&db)?, 0o600);
+
+    let history = db.with_extension("history.json");
+    std::fs::set_permissions(&history, std::fs::Permissions::from_mode(0o644))?;
+    let err = run(&ctx, &["doctor"]).unwrap_err();
+    assert!(err.to_string().contains("1 problem(s)"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn git_autosave_commits_each_change_with_its_command() -> Result<()> {
+    let dir = tempdir()?;
+    let mut cfg = AppConfig {
+        storage_path: Some(dir.path().join("todos").join("db.json")),
+        ..AppConfig::default()
+    };
+    cfg.git.autosave = true;
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    run(&ctx, &["add", "Buy milk", "--priority", "P1"])?;
+    run(&ctx, &["list"])?;
+    run(&ctx, &["add", "Call mum"])?;
+    let log = run(&ctx, &["history", "git-log"])?;
+    let messages: Vec<_> = log
+        .lines()
+        .map(|l| l.splitn(4, "  ").last().unwrap_or_default())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "rustlytodo add \"Call mum\"",
+            "rustlytodo add \"Buy milk\" --priority P1",
+            "Start versioning todos with git",
+        ],
+        "{log}"
+    );
+    assert_eq!(
+        run(&ctx, &["history", "git-log", "-n", "1"])?
+            .lines()
+            .count(),
+        1
+    );
+    Ok(())
+}
+
+#[test]
+fn stats_reports_counts_and_completions() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let stats = || -> Result<serde_json::Value> {
+        Ok(serde_json::from_str(&run(
+            &ctx,
+            &["stats", "--format", "json"],
+        )?)?)
+    };
+
+    let before = stats()?;
+    run(
+        &ctx,
+        &["add", "File taxes", "--tag", "admin", "--priority", "P1"],
+    )?;
+    run(&ctx, &["done", &find(&ctx, "File taxes")?.id.short()])?;
+
+    let after = stats()?;
+    assert_eq!(after["total"], before["total"].as_u64().unwrap() + 1);
+    assert_eq!(after["done"], before["done"].as_u64().unwrap() + 1);
+    assert_eq!(
+        after["completed"],
+        before["completed"].as_u64().unwrap() + 1
+    );
+    let admin = after["by_tag"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["key"] == "admin")
+        .unwrap();
+    assert_eq!((&admin["open"], &admin["done"]), (&0.into(), &1.into()));
+
+    let table = run(&ctx, &["stats", "--days", "7"])?;
+    assert!(table.contains("Last 7 days:"), "{table}");
+    assert!(table.contains("PRIORITY"), "{table}");
+    Ok(())
+}
+
+#[test]
+fn routines_track_todays_steps_and_streak() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg = AppConfig {
+        symbols: rustytodo::infra::config::Symbols::Ascii,
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    run(
+        &ctx,
+        &[
+            "routine",
+            "add",
+            "Morning routine",
+            "--step",
+            "Stretch",
+            "--step",
+            "Water",
+        ],
+    )?;
+    let started = run(&ctx, &["routine", "start", "morning"])?;
+    assert!(started.contains("Morning routine"), "{started}");
+    assert!(started.contains("[ ] 1. Stretch"), "{started}");
+
+    let checked = run(&ctx, &["routine", "check", "morning", "1", "water"])?;
+    assert!(checked.contains("[x] 2. Water"), "{checked}");
+    assert!(checked.contains("Streak: 1 day(s)"), "{checked}");
+    assert!(checked.contains("All done for today."), "{checked}");
+
+    let list = run(&ctx, &["routine", "list"])?;
+    assert!(
+        list.lines()
+            .any(|l| l.starts_with("Morning routine") && l.contains("2/2"))
+    );
+    assert!(run(&ctx, &["routine", "show", "evening"])?.contains("No routine called"));
+
+    run(&ctx, &["routine", "remove", "morning"])?;
+    assert!(run(&ctx, &["routine", "list"])?.contains("No routines yet"));
+    Ok(())
+}
+
+#[test]
+fn start_moves_todos_through_workflow_states() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let ids = |args: &[&str]| -> Result<Vec<String>> {
+        let list: serde_json::Value =
+            serde_json::from_str(&run(&ctx, &[&["list", "--format", "json"], args].concat())?)?;
+        Ok(list
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["id"].as_str().unwrap().to_string())
+            .collect())
+    };
+
+    run(&ctx, &["add", "Write draft", "--project", "Side"])?;
+    run(&ctx, &["add", "Ship release", "--project", "Acme"])?;
+    let draft = ids(&["--search", "draft"])?.remove(0);
+    let ship = ids(&["--search", "ship"])?.remove(0);
+
+    let started = run(&ctx, &["start", &draft])?;
+    assert!(started.contains("(in-progress)"), "{started}");
+    assert_eq!(ids(&["--state", "in-progress"])?, [draft.as_str()]);
+    // The sample todos of a new database are open too.
+    assert!(ids(&["--state", "open"])?.contains(&ship));
+    assert!(!ids(&["--state", "open"])?.contains(&draft));
+
+    run(
+        &ctx,
+        &["project", "edit", "acme", "--states", "doing,review"],
+    )?;
+    assert!(run(&ctx, &["start", &ship])?.contains("(doing)"));
+    let refused = run(&ctx, &["edit", &ship, "--state", "in-progress"])?;
+    assert!(
+        refused.contains("Acme has no state in-progress (states: doing, review)"),
+        "{refused}"
+    );
+    run(&ctx, &["edit", &ship, "--state", "review"])?;
+    let shown = run(&ctx, &["show", &ship])?;
+    assert!(shown.contains("Open (review)"), "{shown}");
+
+    run(&ctx, &["edit", &draft, "--project", "Acme"])?;
+    let board = run(&ctx, &["board", "--project", "acme"])?;
+    let headers: Vec<_> = board
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with(' '))
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            "OPEN (0)",
+            "DOING (0)",
+            "REVIEW (1)",
+            // Left over from the default workflow: still shown.
+            "IN-PROGRESS (1)",
+            "DONE (0)"
+        ],
+        "{board}"
+    );
+
+    let stopped = run(&ctx, &["stop", &draft])?;
+    assert!(stopped.contains("back to open"), "{stopped}");
+    assert!(ids(&["--state", "in-progress"])?.is_empty());
+
+    run(&ctx, &["done", &ship])?;
+    assert_eq!(ids(&["--state", "done"])?, [ship]);
+    assert!(run(&ctx, &["board", "--project", "acme"])?.contains("DONE (1)"));
+    Ok(())
+}
+
+#[test]
+fn redacted_export_strips_private_fields() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg: AppConfig = toml::from_str(
+        r#"
+        [redact]
+        team = ["notes", "time"]
+        "#,
+    )?;
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    run(
+        &ctx,
+        &[
+            "add",
+            "Plan offsite",
+            "--project",
+            "Team",
+            "--notes",
+            "budget is tight",
+            "--tag",
+            "hr",
+        ],
+    )?;
+    let shared = dir.path().join("shared.json");
+    let shared_arg = shared.to_str().unwrap();
+    run(
+        &ctx,
+        &[
+            "export",
+            "--project",
+            "team",
+            "--out",
+            shared_arg,
+            "--redact",
+            "team,tags",
+        ],
+    )?;
+    let text = std::fs::read_to_string(&shared)?;
+    assert!(text.contains("Plan offsite"), "{text}");
+    assert!(
+        !text.contains("budget is tight") && !text.contains("\"hr\""),
+        "{text}"
+    );
+
+    // The database itself keeps everything.
+    assert!(run(&ctx, &["list", "--format", "json"])?.contains("budget is tight"));
+
+    let unknown = run(
+        &ctx,
+        &["export", "--out", shared_arg, "--redact", "attachments"],
+    )?;
+    assert!(
+        unknown.contains("unknown redact field attachments"),
+        "{unknown}"
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn encryption_setting_encrypts_and_decrypts_the_database() -> Result<()> {
+    let dir = tempdir()?;
+    let db = dir.path().join("db.json");
+    let history = dir.path().join("db.history.json");
+    let cfg: AppConfig = toml::from_str(
+        r#"
+        encryption = true
+        encryption_passphrase_command = "echo hunter2"
+        "#,
+    )?;
+    let run_with =
+        |cfg: &AppConfig, args: &[&str]| run(&test_ctx_with(dir.path(), cfg.clone()), args);
+
+    run_with(&cfg, &["add", "Surprise party"])?;
+    for file in [&db, &history] {
+        let text = std::fs::read_to_string(file)?;
+        assert!(text.starts_with("{\"format\":\"rustytodo-encrypted-v1\""));
+        assert!(!text.contains("Surprise party"));
+    }
+    assert!(run_with(&cfg, &["list"])?.contains("Surprise party"));
+
+    let mut wrong = cfg.clone();
+    wrong.encryption_passphrase_command = Some("echo nope".into());
+    assert!(run_with(&wrong, &["list"]).is_err());
+
+    // Turning encryption off decrypts on the next run.
+    let mut plain = cfg.clone();
+    plain.encryption = false;
+    run_with(&plain, &["list"])?;
+    assert!(std::fs::read_to_string(&db)?.contains("Surprise party"));
+    assert!(run_with(&plain, &["undo"])?.contains("Undid"));
+    Ok(())
+}
+
+#[test]
+fn move_previews_confirms_and_undoes_as_one_step() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let in_work = || -> Result<usize> {
+        let list: serde_json::Value = serde_json::from_str(&run(
+            &ctx,
+            &["list", "--project", "work", "--format", "json"],
+        )?)?;
+        Ok(list.as_array().unwrap().len())
+    };
+
+    run(&ctx, &["add", "Expense report", "--tag", "work"])?;
+    run(&ctx, &["add", "Book flights", "--tag", "work"])?;
+    run(&ctx, &["add", "Buy milk", "--tag", "home"])?;
+    let before = in_work()?;
+
+    let filter = "project:Inbox and tag:work";
+    let refused = run(&ctx, &["move", "--filter", filter, "--to", "work"])?;
+    assert!(refused.contains("Expense report") && refused.contains("Book flights"));
+    assert!(!refused.contains("Buy milk"), "{refused}");
+    assert!(refused.contains("Refusing to move"), "{refused}");
+    assert_eq!(in_work()?, before);
+
+    let moved = run(&ctx, &["move", "--filter", filter, "--to", "work", "--yes"])?;
+    // The sample "Work" project keeps its spelling.
+    assert!(moved.contains("Moved 2 todo(s) to Work"), "{moved}");
+    assert_eq!(in_work()?, before + 2);
+    assert!(
+        run(&ctx, &["move", "--filter", filter, "--to", "Work", "--yes"])?.contains("No todos")
+    );
+
+    run(&ctx, &["undo"])?;
+    assert_eq!(in_work()?, before);
+    Ok(())
+}
+
+#[test]
+fn replace_previews_validates_and_applies_in_bulk() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(
+        &ctx,
+        &["add", "Invoice clientX", "--notes", "clientX pays net 30"],
+    )?;
+    run(&ctx, &["add", "Call clientx", "--tag", "calls"])?;
+    run(&ctx, &["add", "clientX"])?;
+
+    let refused = run(
+        &ctx,
+        &["replace", "--search", "clientX", "--with", "ClientY"],
+    )?;
+    assert!(refused.contains("- Invoice clientX"), "{refused}");
+    assert!(refused.contains("+ Invoice ClientY"), "{refused}");
+    assert!(refused.contains("Refusing to replace"), "{refused}");
+    assert!(!run(&ctx, &["list"])?.contains("ClientY"));
+
+    // Emptying a title is invalid, so nothing at all changes.
+    let invalid = run(
+        &ctx,
+        &[
+            "replace",
+            "--search",
+            "^clientx$",
+            "--with",
+            "",
+            "--regex",
+            "--yes",
+        ],
+    )?;
+    assert!(invalid.contains("Nothing replaced"), "{invalid}");
+
+    let filtered = run(
+        &ctx,
+        &[
+            "replace",
+            "--search",
+            "client(x)",
+            "--with",
+            "Client-$1",
+            "--regex",
+            "--filter",
+            "tag:calls",
+            "--yes",
+        ],
+    )?;
+    assert!(
+        filtered.contains("Replaced 1 match(es) in 1 todo(s)"),
+        "{filtered}"
+    );
+    assert!(run(&ctx, &["list"])?.contains("Call Client-x"));
+
+    let all = run(
+        &ctx,
+        &[
+            "replace", "--search", "clientX", "--with", "ClientY", "--yes",
+        ],
+    )?;
+    assert!(all.contains("Replaced 3 match(es) in 2 todo(s)"), "{all}");
+    let list = run(&ctx, &["list"])?;
+    assert!(
+        list.contains("Invoice ClientY") && !list.contains("clientX"),
+        "{list}"
+    );
+
+    run(&ctx, &["undo"])?;
+    assert_eq!(run(&ctx, &["list"])?.matches("clientX").count(), 2);
+    Ok(())
+}
+
+#[test]
+fn block_and_unblock_drive_blocked_and_ready_lists() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let titles = |extra: &[&str]| -> Result<Vec<String>> {
+        let mut args = vec!["list", "--project", "Deploy", "--format", "json"];
+        args.extend(extra);
+        let list: serde_json::Value = serde_json::from_str(&run(&ctx, &args)?)?;
+        Ok(list
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["title"].as_str().unwrap().to_string())
+            .collect())
+    };
+
+    for title in ["Write migration", "Run migration", "Announce"] {
+        run(&ctx, &["add", title, "--project", "Deploy"])?;
+    }
+    let write = find(&ctx, "Write migration")?.id.short();
+    let migrate = find(&ctx, "Run migration")?.id.short();
+    let announce = find(&ctx, "Announce")?.id.short();
+
+    assert!(run(&ctx, &["block", &migrate, "--on", &write])?.contains("now waits for"));
+    run(&ctx, &["block", &announce, "--on", &migrate])?;
+    let cycle = run(&ctx, &["block", &write, "--on", &announce])?;
+    assert!(cycle.contains("dependency cycle"), "{cycle}");
+    let edit = run(&ctx, &["edit", &write, "--depends-on", &announce])?;
+    assert!(edit.contains("dependency cycle"), "{edit}");
+
+    assert_eq!(titles(&["--blocked"])?, ["Run migration", "Announce"]);
+    assert_eq!(titles(&["--ready"])?, ["Write migration"]);
+
+    run(&ctx, &["done", &write])?;
+    assert_eq!(titles(&["--ready"])?, ["Run migration"]);
+
+    assert!(run(&ctx, &["unblock", &announce])?.contains("from 1 todo(s)"));
+    assert!(titles(&["--blocked"])?.is_empty());
+    assert!(run(&ctx, &["unblock", &announce])?.contains("does not wait for anything"));
+    Ok(())
+}
+
+#[test]
+fn list_json_fields_trims_each_object() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(&ctx, &["add", "File taxes", "--priority", "P1"])?;
+    let json = run(
+        &ctx,
+        &[
+            "list",
+            "--format",
+            "json",
+            "--fields",
+            "id,title,due,priority",
+        ],
+    )?;
+    let list: serde_json::Value = serde_json::from_str(&json)?;
+    for todo in list.as_array().unwrap() {
+        let mut keys: Vec<_> = todo
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["due", "id", "priority", "title"]);
+    }
+    assert!(
+        list.as_array()
+            .unwrap()
+            .iter()
+            .any(|t| t["title"] == "File taxes" && t["priority"] == "P1")
+    );
+
+    assert!(run(&ctx, &["list", "--fields", "id"])?.contains("only applies to --format json"));
+    assert!(
+        run(&ctx, &["list", "--format", "json", "--fields", "id,colour"])?
+            .contains("unknown field colour")
+    );
+    Ok(())
+}
+
+#[test]
+fn reproducible_exports_only_change_with_the_todos() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let file = dir.path().join("todos.json");
+    let file_arg = file.to_str().unwrap();
+    let export = |extra: &[&str]| -> Result<String> {
+        let mut args = vec!["export", "--out", file_arg, "--reproducible"];
+        args.extend(extra);
+        run(&ctx, &args)?;
+        Ok(std::fs::read_to_string(&file)?)
+    };
+
+    run(&ctx, &["add", "Water plants"])?;
+    let first = export(&["--drop-volatile"])?;
+    assert!(!first.contains("updated_at"), "{first}");
+    let id = find(&ctx, "Water plants")?.id.short();
+
+    // Touching a todo without changing it leaves the export alone.
+    run(&ctx, &["edit", &id, "--title", "Water plants"])?;
+    assert_eq!(export(&["--drop-volatile"])?, first);
+    run(&ctx, &["edit", &id, "--title", "Water the plants"])?;
+    assert_ne!(export(&["--drop-volatile"])?, first);
+
+    // With volatile fields kept the file imports as usual.
+    assert!(export(&[])?.contains("updated_at"));
+    run(&ctx, &["import", "--force", "--in", file_arg])?;
+    assert!(run(&ctx, &["list"])?.contains("Water the plants"));
+    Ok(())
+}
+
+#[test]
+fn import_merge_keeps_local_todos_and_newer_edits() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let count = || -> Result<usize> { Ok(todos(&ctx)?.len()) };
+
+    run(&ctx, &["add", "Shared todo"])?;
+    let backup = dir.path().join("backup.json");
+    let backup_arg = backup.to_str().unwrap();
+    run(&ctx, &["export", "--out", backup_arg])?;
+    let before = count()?;
+
+    // Added after the export: merging keeps it; replacing would drop it.
+    run(&ctx, &["add", "Only here"])?;
+    let merged = run(
+        &ctx,
+        &["import", "--force", "--mode", "merge", "--in", backup_arg],
+    )?;
+    assert!(
+        merged.contains(&format!("0 inserted, 0 updated, {before} skipped")),
+        "{merged}"
+    );
+    assert_eq!(count()?, before + 1);
+
+    let appended = run(
+        &ctx,
+        &["import", "--force", "--mode", "append", "--in", backup_arg],
+    )?;
+    assert!(
+        appended.contains(&format!("Appended {before} todos")),
+        "{appended}"
+    );
+    assert_eq!(count()?, 2 * before + 1);
+
+    run(&ctx, &["import", "--force", "--in", backup_arg])?;
+    assert_eq!(count()?, before);
+    assert!(
+        run(&ctx, &["import", "--mode", "upsert", "--in", backup_arg])?.contains("unknown --mode")
+    );
+    Ok(())
+}
+
+#[test]
+fn external_refs_link_todos_and_join_imports() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg = AppConfig {
+        links: [(
+            "jira".to_string(),
+            "https://acme.atlassian.net/browse/{key}".to_string(),
+        )]
+        .into(),
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    assert!(!run(&ctx, &["list"])?.contains("REF"));
+    run(&ctx, &["add", "Fix login bug", "--ref", "jira:ABC-123"])?;
+    let list = run(&ctx, &["list", "ref:jira"])?;
+    assert!(
+        list.contains("REF") && list.contains("jira:ABC-123"),
+        "{list}"
+    );
+    assert_eq!(list.lines().count(), 2, "{list}");
+
+    let todos: Vec<Todo> = serde_json::from_str(&run(
+        &ctx,
+        &["list", "ref:jira:abc-123", "--format", "json"],
+    )?)?;
+    let id = todos[0].id.short();
+    let show = run(&ctx, &["show", &id])?;
+    assert!(
+        show.contains("jira:ABC-123 https://acme.atlassian.net/browse/ABC-123"),
+        "{show}"
+    );
+    run(&ctx, &["edit", &id, "--clear-ref"])?;
+    assert!(!run(&ctx, &["show", &id])?.contains("Ref:"));
+
+    // Todoist tasks are matched by their id on a second import.
+    let export = "tests/fixtures/todoist/sync.json";
+    let first = run(
+        &ctx,
+        &[
+            "import", "--force", "--format", "todoist", "--mode", "merge", "--in", export,
+        ],
+    )?;
+    assert!(first.contains("3 inserted"), "{first}");
+    let again = run(
+        &ctx,
+        &[
+            "import", "--force", "--format", "todoist", "--mode", "merge", "--in", export,
+        ],
+    )?;
+    assert!(again.contains("0 inserted, 3 updated"), "{again}");
+    assert_eq!(run(&ctx, &["list", "ref:todoist"])?.lines().count(), 4);
+    Ok(())
+}
+
+#[test]
+fn old_done_todos_are_archived_on_startup_and_can_come_back() -> Result<()> {
+    let dir = tempdir()?;
+    let db = dir.path().join("db.json");
+    let ctx = test_ctx(dir.path());
+
+    run(&ctx, &["add", "Old chore"])?;
+    run(&ctx, &["add", "Fresh chore"])?;
+    let old = find(&ctx, "Old chore")?.id.short();
+    let fresh = find(&ctx, "Fresh chore")?.id.short();
+    run(&ctx, &["done", &old])?;
+    run(&ctx, &["done", &fresh])?;
+    assert_eq!(
+        run(&ctx, &["archive", "--older-than", "3"])?,
+        "Nothing to archive\n"
+    );
+    assert_eq!(
+        run(&ctx, &["archive", "--older-than", "4294967295"])?,
+        "Nothing to archive\n"
+    );
+
+    // Pretend "Old chore" was finished ten days ago.
+    let mut stored: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&db)?)?;
+    let ten_days_ago = time::OffsetDateTime::now_utc() - time::Duration::days(10);
+    for t in stored["todos"].as_array_mut().unwrap() {
+        if t["title"] == "Old chore" {
+            t["status"]["Done"]["completed_at"] = serde_json::to_value(ten_days_ago)?;
+        }
+    }
+    std::fs::write(&db, serde_json::to_string(&stored)?)?;
+
+    let policy = AppConfig {
+        archive: rustytodo::infra::config::ArchiveConfig {
+            after_days: Some(7),
+        },
+        ..AppConfig::default()
+    };
+    let list = run(
+        &test_ctx_with(dir.path(), policy),
+        &["list", "--status", "done"],
+    )?;
+    assert!(
+        !list.contains("Old chore") && list.contains("Fresh chore"),
+        "{list}"
+    );
+    let archived = run(&ctx, &["list", "is:archived"])?;
+    assert!(archived.contains("Old chore"), "{archived}");
+    assert!(run(&ctx, &["show", &old])?.contains("Archived: "));
+
+    assert_eq!(
+        run(&ctx, &["unarchive", &old])?,
+        format!("Unarchived {old}\n")
+    );
+    assert!(!run(&ctx, &["list", "--archived"])?.contains("Old chore"));
+    assert_eq!(
+        run(&ctx, &["archive", &fresh])?,
+        format!("Archived {fresh}  Fresh chore\n")
+    );
+    assert_eq!(
+        run(&ctx, &["archive", &old])?,
+        format!("Archived {old}  Old chore\n")
+    );
+    let archived = run(&ctx, &["list", "--archived"])?;
+    assert!(archived.contains("Old chore") && archived.contains("Fresh chore"));
+    Ok(())
+}
+
+#[test]
+fn commands_run_at_the_injected_time_or_as_of() -> Result<()> {
+    use rustytodo::testing::FixedClock;
+    use time::macros::datetime;
+
+    let dir = tempdir()?;
+    let clock = FixedClock::new(datetime!(2030-01-01 09:00 UTC));
+    let ctx = test_ctx(dir.path()).with_clock(std::sync::Arc::new(clock.clone()));
+
+    run(&ctx, &["add", "Pay rent", "--due", "tomorrow 9am"])?;
+    let rent = find(&ctx, "Pay rent")?;
+    assert_eq!(rent.created_at, datetime!(2030-01-01 09:00 UTC));
+    assert_eq!(
+        rent.due.map(|d| d.as_dt()),
+        Some(datetime!(2030-01-02 09:00 UTC))
+    );
+
+    assert!(!run(&ctx, &["list", "is:overdue"])?.contains("Pay rent"));
+    let later = run(
+        &ctx,
+        &["list", "is:overdue", "--as-of", "2030-01-03T00:00:00Z"],
+    )?;
+    assert!(later.contains("Pay rent"), "{later}");
+    clock.advance(time::Duration::days(2));
+    assert!(run(&ctx, &["list", "is:overdue"])?.contains("Pay rent"));
+
+    assert!(run(&ctx, &["list", "--as-of", "someday soon"])?.starts_with("invalid --as-of"));
+    Ok(())
+}
+
+#[test]
+fn keys_prints_the_active_keybindings() -> Result<()> {
+    use rustytodo::app::keymap::KeySpec;
+
+    let dir = tempdir()?;
+    let cfg = AppConfig {
+        keybindings: [("done".to_string(), KeySpec::One("Enter".to_string()))].into(),
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    let table = run(&ctx, &["keys"])?;
+    assert!(table.starts_with("ACTION   KEYS"), "{table}");
+    assert!(
+        table.contains("done     enter            toggle done on the selected todo (custom)"),
+        "{table}"
+    );
+    assert!(
+        table.contains("quit     q, ctrl+c        quit\n"),
+        "{table}"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&run(&ctx, &["keys", "--format", "json"])?)?;
+    assert_eq!(json["redo"], serde_json::json!(["ctrl+r"]));
+    Ok(())
+}
+
+#[test]
+fn imports_with_duplicate_ids_are_refused_unless_a_policy_keeps_one() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    let file = dir.path().join("dups.json");
+    let file = file.to_str().unwrap();
+    run(&ctx, &["export", "--format", "json", "--out", file])?;
+    let mut export: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(file)?)?;
+    let todos = export["todos"].as_array_mut().unwrap();
+    let mut copy = todos[0].clone();
+    copy["title"] = "Second copy".into();
+    todos.push(copy);
+    let count = todos.len();
+    std::fs::write(file, serde_json::to_string(&export)?)?;
+
+    let refused = run(&ctx, &["import", "--in", file])?;
+    assert!(
+        refused.starts_with("Nothing imported: duplicate todo IDs: "),
+        "{refused}"
+    );
+    let after: Vec<serde_json::Value> =
+        serde_json::from_str(&run(&ctx, &["list", "--format", "json"])?)?;
+    assert_eq!(after.len(), count - 1);
+
+    let keep_last = AppConfig {
+        duplicate_ids: rustytodo::app::repository::DuplicatePolicy::KeepLast,
+        ..AppConfig::default()
+    };
+    let report = run(
+        &test_ctx_with(dir.path(), keep_last),
+        &["import", "--in", file],
+    )?;
+    assert!(
+        report.contains("Kept one todo per duplicated ID: "),
+        "{report}"
+    );
+    let list = run(&ctx, &["list"])?;
+    assert!(list.contains("Second copy"), "{list}");
+    Ok(())
+}
+
+#[test]
+fn color_flag_styles_the_list_table() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(
+        &ctx,
+        &["add", "Late report", "--due", "2020-01-02T09:00:00Z"],
+    )?;
+    run(&ctx, &["add", "Finished chore"])?;
+    run(&ctx, &["done", &find(&ctx, "Finished chore")?.id.short()])?;
+
+    // Tests don't write to a terminal, so `auto` (the default) is plain.
+    let plain = run(&ctx, &["list"])?;
+    assert!(!plain.contains('\x1b'), "{plain}");
+    assert_eq!(run(&ctx, &["--color", "never", "list"])?, plain);
+
+    let colored = run(&ctx, &["--color", "always", "list"])?;
+    let line = |text: &str| colored.lines().find(|l| l.contains(text)).unwrap();
+    let late = line("Late report");
+    assert!(late.contains("\x1b[38;2;255;95;95mOVERDUE"), "{late:?}");
+    assert!(line("Finished chore").starts_with("\x1b[2;"), "{colored}");
+
+    assert_eq!(
+        run(&ctx, &["--color", "sometimes", "list"])?,
+        "invalid --color sometimes (use auto|always|never)\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn adds_and_edits_warn_without_failing() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    let added: serde_json::Value = serde_json::from_str(&run(
+        &ctx,
+        &[
+            "--as-of",
+            "2026-05-01T12:00:00Z",
+            "add",
+            "Expenses",
+            "--due",
+            "2026-04-30T09:00:00Z",
+            "--tag",
+            "work",
+            "--format",
+            "json",
+        ],
+    )?)?;
+    let codes: Vec<_> = added["warnings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|w| w["code"].as_str().unwrap())
+        .collect();
+    assert_eq!(codes, ["due_in_past", "tag_looks_like_project"]);
+    let id = added["id"].as_str().unwrap()[..8].to_string();
+
+    // The todo was added all the same.
+    assert!(run(&ctx, &["list"])?.contains("Expenses"));
+
+    let edited = run(
+        &ctx,
+        &[
+            "--as-of",
+            "2026-05-01T12:00:00Z",
+            "edit",
+            &id,
+            "--remind",
+            "2026-05-03T09:00:00Z",
+        ],
+    )?;
+    assert_eq!(
+        edited,
+        format!("Edited {id}\nwarning: {id}: reminder is after the due date\n")
+    );
+    assert_eq!(
+        run(&ctx, &["edit", &id, "--title", "Expense report"])?,
+        format!("Edited {id}\n")
+    );
+    Ok(())
+}
+
+#[test]
+fn list_jsonl_prints_one_todo_per_line() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(&ctx, &["add", "File taxes", "--priority", "P1"])?;
+    let json: Vec<serde_json::Value> =
+        serde_json::from_str(&run(&ctx, &["list", "--format", "json"])?)?;
+    let jsonl = run(&ctx, &["list", "--format", "jsonl"])?;
+    let lines: Vec<serde_json::Value> = jsonl
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(lines, json);
+
+    let fields = run(
+        &ctx,
+        &["list", "--format", "jsonl", "--fields", "title,priority"],
+    )?;
+    assert!(
+        fields
+            .lines()
+            .any(|l| l == r#"{"title":"File taxes","priority":"P1"}"#),
+        "{fields}"
+    );
+    assert_eq!(
+        run(
+            &ctx,
+            &["list", "--format", "jsonl", "--search", "nothing like this"]
+        )?,
+        ""
+    );
+    Ok(())
+}
+
+#[test]
+fn priority_levels_rename_priorities_in_and_out() -> Result<()> {
+    use rustytodo::app::priorities::PriorityLevel;
+    use rustytodo::domain::todo::Priority;
+
+    let dir = tempdir()?;
+    let level = |label: &str, priority| PriorityLevel {
+        label: label.to_string(),
+        priority,
+        color: None,
+    };
+    let cfg = AppConfig {
+        priority_levels: vec![
+            level("High", Priority::P1),
+            level("Medium", Priority::P3),
+            level("Low", Priority::P4),
+        ],
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    run(&ctx, &["add", "Fix outage", "--priority", "high"])?;
+    run(&ctx, &["add", "Tidy desk", "--priority", "P4"])?;
+    let outage = find(&ctx, "Fix outage")?;
+    // Stored as the internal priority.
+    assert_eq!(outage.priority, Priority::P1);
+
+    let table = run(&ctx, &["list", "--priority", "Low"])?;
+    let header = table.lines().next().unwrap();
+    assert!(header.contains(" P      "), "{header}");
+    let row = table.lines().find(|l| l.contains("Tidy desk")).unwrap();
+    assert!(row.contains(" Low    "), "{row}");
+    assert!(!table.contains("Fix outage"), "{table}");
+
+    let show = run(&ctx, &["show", &outage.id.short()])?;
+    assert!(show.contains("Priority: High"), "{show}");
+    assert_eq!(
+        run(&ctx, &["list", "--priority", "urgent"])?,
+        "unknown priority urgent (use High, Medium, Low)\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn parents_show_subtask_progress_and_can_complete_themselves() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg = AppConfig {
+        complete_parents: true,
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+    let id_of = |title: &str| -> Result<String> { Ok(find(&ctx, title)?.id.short()) };
+
+    run(&ctx, &["add", "Release 1.0"])?;
+    let release = id_of("Release 1.0")?;
+    run(&ctx, &["add", "Write changelog", "--parent", &release])?;
+    run(&ctx, &["add", "Tag the build", "--parent", &release])?;
+    let changelog = id_of("Write changelog")?;
+    let tag = id_of("Tag the build")?;
+
+    run(&ctx, &["done", &changelog])?;
+    let table = run(&ctx, &["list"])?;
+    assert!(
+        table.lines().next().unwrap().contains(" DONE  TITLE"),
+        "{table}"
+    );
+    let row = table.lines().find(|l| l.contains("Release 1.0")).unwrap();
+    assert!(row.contains(" 50%   Release 1.0"), "{row}");
+    let show = run(&ctx, &["show", &release])?;
+    assert!(show.contains("Subtasks: 1/2 done (50%)"), "{show}");
+    assert!(run(&ctx, &["show", &tag])?.contains(&format!("Parent:   {release} Release 1.0")));
+
+    // A todo can't move under its own subtask.
+    let cycle = run(&ctx, &["edit", &release, "--parent", &tag])?;
+    assert!(
+        cycle.contains("can't be a subtask of its own subtask"),
+        "{cycle}"
+    );
+
+    assert_eq!(
+        run(&ctx, &["done", &tag])?,
+        format!("Done {tag}\nDone {release} (all subtasks done)\n")
+    );
+    run(&ctx, &["edit", &tag, "--clear-parent"])?;
+    assert!(!run(&ctx, &["show", &tag])?.contains("Parent:"));
+    Ok(())
+}
+
+#[test]
+fn inline_tokens_in_add_titles_fill_fields() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(
+        &ctx,
+        &[
+            "--as-of",
+            "2026-05-04T09:00:00Z",
+            "add",
+            "Fix login bug #work #bug @Backend !p1 due:friday",
+        ],
+    )?;
+    let bug = find(&ctx, "Fix login bug")?;
+    assert_eq!(bug.project.as_str(), "Backend");
+    let tags: Vec<_> = bug.tags.iter().map(|t| t.as_str()).collect();
+    assert_eq!(tags, ["bug", "work"]);
+    assert_eq!(bug.priority, rustytodo::domain::todo::Priority::P1);
+    assert_eq!(bug.due.unwrap().as_dt().date().to_string(), "2026-05-08");
+
+    // Flags win; --raw keeps the title as typed.
+    run(&ctx, &["add", "Plan trip @Home !p2", "--project", "Travel"])?;
+    assert_eq!(find(&ctx, "Plan trip")?.project.as_str(), "Travel");
+    run(&ctx, &["add", "--raw", "Reply to @anna #42"])?;
+    assert!(find(&ctx, "Reply to @anna #42")?.tags.is_empty());
+
+    let msg = run(&ctx, &["add", "Ship it !p7"])?;
+    assert!(msg.contains("!p7: unknown priority p7"), "{msg}");
+    assert!(msg.contains("--raw keeps it in the title"), "{msg}");
+    Ok(())
+}
+
+#[test]
+fn list_group_by_shows_headings_and_grouped_json() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    run(&ctx, &["add", "Fix login bug #work @Backend"])?;
+    run(&ctx, &["add", "Write API docs #work @Backend"])?;
+    run(&ctx, &["add", "Buy milk #home @Errands"])?;
+
+    let table = run(&ctx, &["list", "--group-by", "project", "tag:work"])?;
+    assert!(table.contains("Backend (2)"), "{table}");
+    assert!(!table.contains("Errands"), "{table}");
+
+    let json = run(
+        &ctx,
+        &[
+            "list",
+            "--group-by",
+            "tag",
+            "--format",
+            "json",
+            "--fields",
+            "title",
+        ],
+    )?;
+    let groups: serde_json::Value = serde_json::from_str(&json)?;
+    let heads: Vec<_> = groups
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|g| (g["group"].as_str().unwrap(), g["count"].as_u64().unwrap()))
+        .collect();
+    assert!(heads.contains(&("#home", 1)) && heads.contains(&("#work", 2)));
+    let home = groups
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|g| g["group"] == "#home")
+        .unwrap();
+    assert_eq!(home["todos"][0], serde_json::json!({ "title": "Buy milk" }));
+
+    let bad = run(&ctx, &["list", "--group-by", "colour"])?;
+    assert!(bad.contains("unknown --group-by colour"), "{bad}");
+    let jsonl = run(&ctx, &["list", "--group-by", "due", "--format", "jsonl"])?;
+    assert!(jsonl.contains("--group-by applies to"), "{jsonl}");
+    Ok(())
+}
+
+#[test]
+fn due_dates_read_relative_unless_absolute() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg = AppConfig {
+        timezone: "UTC".to_string(),
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+    let at = ["--as-of", "2026-05-04T09:00:00Z"];
+    let with = |args: &[&str]| run(&ctx, &[&at[..], args].concat());
+
+    with(&["add", "Ship release", "--due", "2026-05-04T17:00:00Z"])?;
+    with(&["add", "File taxes", "--due", "2026-05-04T06:00:00Z"])?;
+    with(&["add", "Plan offsite", "--due", "2026-05-07T10:00:00Z"])?;
+
+    let table = with(&["list", "--search", "Ship"])?;
+    assert!(table.contains("today 17:00"), "{table}");
+    assert!(with(&["list", "--search", "taxes"])?.contains("overdue 3h"));
+    assert!(with(&["list", "--search", "offsite"])?.contains("due in 3d"));
+
+    let absolute = with(&["list", "--search", "Ship", "--absolute"])?;
+    assert!(absolute.contains("2026-05-04T17:00:00Z"), "{absolute}");
+    assert!(!absolute.contains("today 17:00"));
+    Ok(())
+}
+
+#[test]
+fn times_show_and_parse_in_the_configured_timezone() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg = AppConfig {
+        timezone: "+02:00".to_string(),
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+    let at = ["--as-of", "2026-05-04 09:00"];
+    let with = |args: &[&str]| run(&ctx, &[&at[..], args].concat());
+
+    with(&["add", "Ship release", "--due", "2026-05-04 23:30"])?;
+    let json: serde_json::Value =
+        serde_json::from_str(&with(&["list", "--search", "Ship", "--format", "json"])?)?;
+    // Stored in UTC: [year, ordinal, hour, minute, second, nanos, offset h/m/s].
+    let due = &json[0]["due"];
+    assert_eq!(due[2], 21, "{due}");
+    assert_eq!(due[3], 30, "{due}");
+    assert_eq!(&due.as_array().unwrap()[6..], [0, 0, 0], "{due}");
+
+    let table = with(&["list", "--search", "Ship"])?;
+    assert!(table.contains("today 23:30"), "{table}");
+    let absolute = with(&["list", "--search", "Ship", "--absolute"])?;
+    assert!(absolute.contains("2026-05-04T23:30:00+02:00"), "{absolute}");
+
+    let shown = with(&["show", json[0]["id"].as_str().unwrap()])?;
+    assert!(
+        shown.contains("today 23:30 (2026-05-04T23:30:00+02:00)"),
+        "{shown}"
+    );
+    Ok(())
+}
+
+#[test]
+fn list_sorts_by_urgency_with_a_score_column() -> Result<()> {
+    let dir = tempdir()?;
+    let mut cfg = AppConfig::default();
+    cfg.urgency.tag.insert("next".to_string(), 20.0);
+    let ctx = test_ctx_with(dir.path(), cfg);
+    let add = |title: &str, extra: &[&str]| {
+        run(
+            &ctx,
+            &[&["add", title, "--project", "Urg"][..], extra].concat(),
+        )
+    };
+
+    add("Water plants", &["--priority", "P4"])?;
+    add("Pay rent", &["--priority", "P2", "--due", "2000-01-01"])?;
+    add("Release prep", &["--priority", "P1"])?;
+    add("Read paper", &["--priority", "P4", "--tag", "next"])?;
+
+    let table = run(&ctx, &["list", "--project", "Urg", "--sort", "urgency"])?;
+    assert!(table.lines().next().unwrap().contains("URG"), "{table}");
+    let order: Vec<_> = ["Read paper", "Pay rent", "Release prep", "Water plants"]
+        .iter()
+        .map(|t| table.find(t).unwrap())
+        .collect();
+    assert!(order.windows(2).all(|w| w[0] < w[1]), "{table}");
+    assert!(table.contains("6.0"), "{table}");
+
+    assert!(!run(&ctx, &["list", "--project", "Urg"])?.contains("URG"));
+    Ok(())
+}
+
+#[test]
+fn matrix_sorts_todos_into_quadrants_and_moves_them() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let at = ["--as-of", "2026-05-04T09:00:00Z"];
+    let with = |args: &[&str]| run(&ctx, &[&at[..], args].concat());
+    let add = |title: &str, extra: &[&str]| {
+        with(&[&["add", title, "--project", "Mx"][..], extra].concat())
+    };
+
+    add(
+        "Fix outage",
+        &["--priority", "P1", "--due", "2026-05-04T12:00:00Z"],
+    )?;
+    add("Plan roadmap", &["--priority", "P2", "--due", "2026-06-01"])?;
+    add(
+        "Reply to newsletter",
+        &["--priority", "P4", "--due", "2026-05-05"],
+    )?;
+    add("Tidy desk", &["--priority", "P3"])?;
+    let desk = find(&ctx, "Tidy desk")?.id.short();
+
+    let grid = with(&["matrix", "--project", "Mx"])?;
+    let lines: Vec<&str> = grid.lines().collect();
+    assert!(
+        lines[0].contains("URGENT") && lines[0].contains("NOT URGENT"),
+        "{grid}"
+    );
+    let row = |title: &str| lines.iter().position(|l| l.contains(title)).unwrap();
+    let col = |title: &str| lines[row(title)].find(title).unwrap();
+    assert_eq!(row("Fix outage"), row("Plan roadmap"), "{grid}");
+    assert!(col("Fix outage") < col("Plan roadmap"), "{grid}");
+    assert!(row("Reply to newsletter") > row("Fix outage"), "{grid}");
+    assert!(col("Tidy desk") > col("Reply to newsletter"), "{grid}");
+    assert!(grid.contains("1 Do first (1)") && grid.contains("4 Eliminate (1)"));
+
+    let moved = with(&["matrix", "move", "do", &desk])?;
+    assert!(
+        moved.contains("Moved") && moved.contains("priority P2"),
+        "{moved}"
+    );
+    let json: serde_json::Value =
+        serde_json::from_str(&with(&["matrix", "--project", "Mx", "--format", "json"])?)?;
+    assert_eq!(json["do_first"].as_array().unwrap().len(), 2);
+    assert_eq!(json["eliminate"].as_array().unwrap().len(), 0);
+    assert!(with(&["matrix", "move", "1", &desk])?.contains("already in Do first"));
+    assert!(with(&["matrix", "move", "later", &desk])?.contains("unknown quadrant"));
+    Ok(())
+}
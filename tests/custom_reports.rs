@@ -0,0 +1,162 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tempfile::tempdir;
+
+use rustytodo::app::context::AppContext;
+use rustytodo::app::custom_reports::ReportDefinition;
+use rustytodo::domain::todo::Todo;
+use rustytodo::infra::config::{AppConfig, ColorChoice};
+use rustytodo::infra::paths::AppPaths;
+
+fn test_ctx(dir: &Path) -> AppContext {
+    test_ctx_with(dir, AppConfig::default())
+}
+
+/// A context with `cfg`, keeping the database under `dir`.
+fn test_ctx_with(dir: &Path, mut cfg: AppConfig) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.join("cfg"),
+        data_dir: dir.join("data"),
+    };
+    cfg.storage_path = Some(dir.join("db.json"));
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn find(ctx: &AppContext, title: &str) -> Result<Todo> {
+    let todos: Vec<Todo> = serde_json::from_str(&run(ctx, &["list", "--format", "json"])?)?;
+    Ok(todos
+        .into_iter()
+        .find(|t| t.title.as_str() == title)
+        .unwrap())
+}
+
+#[test]
+fn reports_from_config_render_their_columns() -> Result<()> {
+    let dir = tempdir()?;
+    let mut cfg = AppConfig {
+        color: ColorChoice::Never,
+        ..AppConfig::default()
+    };
+    cfg.reports.insert(
+        "standup".to_string(),
+        ReportDefinition {
+            description: Some("Open work by project".to_string()),
+            filter: "status:open -tag:later -project:Inbox -project:Work".to_string(),
+            columns: vec!["priority".into(), "tags".into(), "title".into()],
+            sort: vec!["priority".into()],
+            group_by: Some("project".to_string()),
+            ..ReportDefinition::default()
+        },
+    );
+    let ctx = test_ctx_with(dir.path(), cfg);
+
+    run(
+        &ctx,
+        &["add", "Fix login", "--project", "Web", "--priority", "P1"],
+    )?;
+    run(
+        &ctx,
+        &["add", "Update docs", "--project", "Web", "--tag", "docs"],
+    )?;
+    run(
+        &ctx,
+        &[
+            "add",
+            "Order chairs",
+            "--project",
+            "Office",
+            "--priority",
+            "P2",
+        ],
+    )?;
+    run(
+        &ctx,
+        &["add", "Someday idea", "--project", "Web", "--tag", "later"],
+    )?;
+
+    assert_eq!(
+        run(&ctx, &["report", "Standup"])?,
+        "Open work by project\n\n\
+         P  TAGS  TITLE\n\
+         \n\
+         Office (1)\n\
+         P2 -     Order chairs\n\
+         \n\
+         Web (2)\n\
+         P1 -     Fix login\n\
+         P3 #docs Update docs\n"
+    );
+    assert!(run(&ctx, &["report", "--list"])?.contains("standup  Open work by project"));
+    assert_eq!(
+        run(&ctx, &["report", "weekly"])?,
+        "no report weekly (use standup)\n"
+    );
+    let file = dir.path().join("standup.txt");
+    run(
+        &ctx,
+        &["report", "standup", "--out", file.to_str().unwrap()],
+    )?;
+    assert!(std::fs::read_to_string(&file)?.contains("P1 -     Fix login"));
+    // Without a name, the Markdown report is unchanged.
+    assert!(run(&ctx, &["report"])?.starts_with("# Report"));
+    Ok(())
+}
+
+#[test]
+fn report_and_markdown_export_group_open_and_completed_todos() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+    let as_of = ["--as-of", "2026-05-04T09:00:00Z"];
+    let at = |args: &[&str]| run(&ctx, &[&as_of[..], args].concat());
+
+    at(&["add", "Ship release", "--project", "Work"])?;
+    at(&["add", "Groceries", "--project", "Home"])?;
+    at(&["done", &find(&ctx, "Ship release")?.id.short()])?;
+
+    // The store starts with sample todos; keep to the two projects above.
+    let day = at(&["report", "--by", "day"])?;
+    assert!(
+        day.contains("## Completed\n\n### 2026-05-04\n\n- [x] Ship release (Work)\n"),
+        "{day}"
+    );
+    assert!(day.contains("- [ ] Groceries (Home)\n"), "{day}");
+    assert_eq!(
+        at(&["report", "--project", "work"])?,
+        "# Report: work\n\nCompleted since 2026-04-27.\n\
+         \n## Completed\n\n### Work\n\n- [x] Ship release\n\
+         \n## Open\n\n### Work\n\n- [ ] Fix CI flaky test #build #rust due 2026-05-07\n"
+    );
+    let home = at(&["report", "--project", "home"])?;
+    assert!(home.starts_with("# Report: home\n"), "{home}");
+    assert!(home.contains("_Nothing completed._") && home.contains("### Home"));
+    assert_eq!(
+        at(&["report", "--by", "week"])?,
+        "unknown --by week (use project|day)\n"
+    );
+
+    let md = dir.path().join("out/todos.md");
+    at(&[
+        "export",
+        "--format",
+        "markdown",
+        "--out",
+        md.to_str().unwrap(),
+    ])?;
+    let exported = std::fs::read_to_string(&md)?;
+    assert!(exported.starts_with("# Todos\n"), "{exported}");
+    assert!(
+        exported.contains("### Work\n\n- [x] Ship release\n"),
+        "{exported}"
+    );
+    Ok(())
+}
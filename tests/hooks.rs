@@ -0,0 +1,97 @@
+//! Hook scripts are run through `sh`, so these only run on Unix.
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use anyhow::Result;
+use tempfile::tempdir;
+
+use rustytodo::app::context::AppContext;
+use rustytodo::domain::todo::Todo;
+use rustytodo::infra::config::{AppConfig, HooksConfig};
+use rustytodo::infra::paths::AppPaths;
+
+/// A context with `cfg`, keeping the database under `dir`.
+fn test_ctx_with(dir: &Path, mut cfg: AppConfig) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.join("cfg"),
+        data_dir: dir.join("data"),
+    };
+    cfg.storage_path = Some(dir.join("db.json"));
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn find(ctx: &AppContext, title: &str) -> Result<Todo> {
+    let todos: Vec<Todo> = serde_json::from_str(&run(ctx, &["list", "--format", "json"])?)?;
+    Ok(todos
+        .into_iter()
+        .find(|t| t.title.as_str() == title)
+        .unwrap())
+}
+
+/// Install an executable `hooks/<name>` script running `body`.
+fn hook(ctx: &AppContext, name: &str, body: &str) -> Result<()> {
+    let hooks = ctx.paths.config_dir.join("hooks");
+    std::fs::create_dir_all(&hooks)?;
+    let path = hooks.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\n{body}\n"))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[test]
+fn hooks_run_around_commands_and_can_veto_them() -> Result<()> {
+    let dir = tempdir()?;
+    let cfg = AppConfig {
+        hooks: HooksConfig {
+            enabled: true,
+            timeout_secs: 5,
+        },
+        ..AppConfig::default()
+    };
+    let ctx = test_ctx_with(dir.path(), cfg);
+    let log = dir.path().join("hooks.log");
+    hook(
+        &ctx,
+        "post-add",
+        &format!(
+            "cat >/dev/null; echo \"added by $RUSTYTODO_COMMAND\" >> {}",
+            log.display()
+        ),
+    )?;
+    hook(
+        &ctx,
+        "post-done",
+        &format!("grep -o '\"title\":\"[^\"]*\"' >> {}", log.display()),
+    )?;
+    hook(
+        &ctx,
+        "pre-delete",
+        "echo 'deleting is disabled' >&2; exit 3",
+    )?;
+
+    run(&ctx, &["add", "Water plants"])?;
+    let id = find(&ctx, "Water plants")?.id.short();
+    run(&ctx, &["done", &id])?;
+    assert_eq!(
+        std::fs::read_to_string(&log)?,
+        "added by add\n\"title\":\"Water plants\"\n"
+    );
+
+    let refused = run(&ctx, &["--force", "delete", &id])?;
+    assert_eq!(refused, "pre-delete hook failed: deleting is disabled\n");
+    assert!(
+        run(&ctx, &["list", "--search", "Water", "--status", "done"])?.contains("Water plants")
+    );
+    Ok(())
+}
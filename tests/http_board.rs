@@ -0,0 +1,578 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tempfile::{TempDir, tempdir};
+
+use rustytodo::app::context::AppContext;
+use rustytodo::app::priorities::PriorityLevel;
+use rustytodo::domain::clock::SystemClock;
+use rustytodo::domain::todo::{Priority, Todo};
+use rustytodo::infra::config::{AppConfig, ConfigWatcher};
+use rustytodo::infra::paths::AppPaths;
+use rustytodo::testing::FixedClock;
+use rustytodo::ui::http::{CaptureRules, ServeOptions, serve};
+
+fn test_ctx(dir: &TempDir) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.path().join("cfg"),
+        data_dir: dir.path().join("data"),
+    };
+    let cfg = AppConfig {
+        storage_path: Some(dir.path().join("db.json")),
+        ..AppConfig::default()
+    };
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn request(addr: std::net::SocketAddr, method: &str, path: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    write!(stream, "{method} {path} HTTP/1.1\r\nHost: test\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+#[test]
+fn readonly_server_shows_board_and_rejects_writes() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    run(
+        &ctx,
+        &["add", "Renew passport", "--due", "2000-01-01T09:00:00Z"],
+    )?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let opts = ServeOptions {
+        key: None,
+        db_path: dir.path().join("db.json"),
+        clock: Arc::new(SystemClock),
+        capture: CaptureRules::default(),
+        refresh: Duration::from_secs(5),
+        config: None,
+        writable: false,
+        tokens: None,
+        rate_limit: 0,
+    };
+    std::thread::spawn(move || serve(listener, opts));
+
+    let page = request(addr, "GET", "/")?;
+    assert!(page.starts_with("HTTP/1.1 200 OK"), "{page}");
+    assert!(page.contains("setInterval(refresh, 5000)"));
+
+    let board = request(addr, "GET", "/api/board")?;
+    let body = board.split("\r\n\r\n").nth(1).unwrap_or_default();
+    let json: serde_json::Value = serde_json::from_str(body)?;
+    let overdue = json["overdue"].as_array().unwrap();
+    assert!(overdue.iter().any(|t| t["title"] == "Renew passport"));
+
+    // Edits made while serving show up on the next poll.
+    run(
+        &ctx,
+        &["add", "Call plumber", "--due", "2000-01-02T09:00:00Z"],
+    )?;
+    assert!(request(addr, "GET", "/api/board")?.contains("Call plumber"));
+
+    let metrics = request(addr, "GET", "/metrics")?;
+    assert!(metrics.contains("# TYPE rustlytodo_overdue_todos gauge"));
+    assert!(
+        metrics.contains("rustlytodo_overdue_todos{project=\"Inbox\"} 2"),
+        "{metrics}"
+    );
+
+    assert!(request(addr, "POST", "/api/todos")?.starts_with("HTTP/1.1 405"));
+    assert!(request(addr, "GET", "/nope")?.starts_with("HTTP/1.1 404"));
+    Ok(())
+}
+
+#[test]
+fn config_edits_switch_the_served_database() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    run(
+        &ctx,
+        &[
+            "add",
+            "Only in the first db",
+            "--due",
+            "2000-01-01T09:00:00Z",
+        ],
+    )?;
+
+    let other = AppContext::new(
+        ctx.paths.clone(),
+        AppConfig {
+            storage_path: Some(dir.path().join("other.json")),
+            ..AppConfig::default()
+        },
+    );
+    run(
+        &other,
+        &[
+            "add",
+            "Only in the second db",
+            "--due",
+            "2000-01-01T09:00:00Z",
+        ],
+    )?;
+
+    let config_file = AppConfig::config_file_path(&ctx.paths);
+    std::fs::create_dir_all(&ctx.paths.config_dir)?;
+    std::fs::write(&config_file, "")?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let opts = ServeOptions {
+        key: None,
+        db_path: dir.path().join("db.json"),
+        clock: Arc::new(SystemClock),
+        capture: CaptureRules::default(),
+        refresh: Duration::from_secs(5),
+        config: Some((ConfigWatcher::new(&ctx.paths), ctx.paths.clone())),
+        writable: false,
+        tokens: None,
+        rate_limit: 0,
+    };
+    std::thread::spawn(move || serve(listener, opts));
+    assert!(request(addr, "GET", "/api/board")?.contains("first db"));
+
+    let other_db = dir.path().join("other.json");
+    std::fs::write(
+        &config_file,
+        format!("storage_path = {:?}\n", other_db.to_str().unwrap()),
+    )?;
+    assert!(request(addr, "GET", "/api/board")?.contains("second db"));
+
+    assert!(request(addr, "POST", "/-/reload")?.starts_with("HTTP/1.1 200"));
+    std::fs::write(&config_file, "theme = ")?;
+    assert!(request(addr, "POST", "/-/reload")?.starts_with("HTTP/1.1 400"));
+    // A broken config keeps the last good settings.
+    assert!(request(addr, "GET", "/api/board")?.contains("second db"));
+    Ok(())
+}
+
+#[test]
+fn api_todos_pages_with_cursors() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    run(&ctx, &["list"])?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let opts = ServeOptions {
+        key: None,
+        db_path: dir.path().join("db.json"),
+        clock: Arc::new(SystemClock),
+        capture: CaptureRules::default(),
+        refresh: Duration::from_secs(5),
+        config: None,
+        writable: false,
+        tokens: None,
+        rate_limit: 0,
+    };
+    std::thread::spawn(move || serve(listener, opts));
+    let get = |path: &str| -> Result<serde_json::Value> {
+        let response = request(addr, "GET", path)?;
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+        Ok(serde_json::from_str(body)?)
+    };
+
+    let all = get("/api/todos")?;
+    let total = all["todos"].as_array().unwrap().len();
+    assert_eq!(all["total"], total);
+    assert!(all["next_cursor"].is_null());
+
+    let mut seen = Vec::new();
+    let mut path = "/api/todos?limit=2".to_string();
+    loop {
+        let page = get(&path)?;
+        assert_eq!(page["total"], total);
+        seen.extend(
+            page["todos"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| t["id"].clone()),
+        );
+        match page["next_cursor"].as_str() {
+            Some(cursor) => path = format!("/api/todos?limit=2&cursor={cursor}"),
+            None => break,
+        }
+    }
+    let every: Vec<_> = all["todos"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["id"].clone())
+        .collect();
+    assert_eq!(seen, every);
+
+    assert!(request(addr, "GET", "/api/todos?limit=0")?.starts_with("HTTP/1.1 400"));
+    let unknown = format!("/api/todos?cursor={}", uuid::Uuid::new_v4());
+    assert!(request(addr, "GET", &unknown)?.starts_with("HTTP/1.1 410"));
+    Ok(())
+}
+
+fn send(addr: std::net::SocketAddr, method: &str, path: &str, body: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+#[test]
+fn concurrent_api_writes_all_land() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    run(&ctx, &["list"])?;
+    let before = run(&ctx, &["list", "--format", "json"])?;
+    let before = serde_json::from_str::<serde_json::Value>(&before)?
+        .as_array()
+        .unwrap()
+        .len();
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let opts = ServeOptions {
+        key: None,
+        db_path: dir.path().join("db.json"),
+        clock: Arc::new(SystemClock),
+        capture: CaptureRules::default(),
+        refresh: Duration::from_secs(5),
+        config: None,
+        writable: true,
+        tokens: None,
+        rate_limit: 0,
+    };
+    std::thread::spawn(move || serve(listener, opts));
+
+    let clients: Vec<_> = (0..6)
+        .map(|client| {
+            std::thread::spawn(move || -> Result<Vec<String>> {
+                (0..4)
+                    .map(|n| {
+                        let body = format!(r#"{{"title": "Task {client}-{n} #api"}}"#);
+                        send(addr, "POST", "/api/todos", &body)
+                    })
+                    .collect()
+            })
+        })
+        .collect();
+    for client in clients {
+        for response in client.join().unwrap()? {
+            assert!(response.starts_with("HTTP/1.1 201"), "{response}");
+        }
+    }
+
+    let list = run(&ctx, &["list", "--tag", "api", "--format", "json"])?;
+    let todos: Vec<serde_json::Value> = serde_json::from_str(&list)?;
+    assert_eq!(todos.len(), 24);
+    let all = run(&ctx, &["list", "--format", "json"])?;
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&all)?
+            .as_array()
+            .unwrap()
+            .len(),
+        before + 24
+    );
+
+    let id = todos[0]["id"].as_str().unwrap();
+    let done = send(addr, "POST", &format!("/api/todos/{id}/done"), "")?;
+    assert!(done.starts_with("HTTP/1.1 200"), "{done}");
+    let again = send(addr, "POST", &format!("/api/todos/{id}/done"), "")?;
+    assert!(again.starts_with("HTTP/1.1 409"), "{again}");
+    let gone = send(addr, "DELETE", &format!("/api/todos/{id}"), "")?;
+    assert!(gone.starts_with("HTTP/1.1 200"), "{gone}");
+    let missing = send(addr, "DELETE", &format!("/api/todos/{id}"), "")?;
+    assert!(missing.starts_with("HTTP/1.1 404"), "{missing}");
+    let bad = send(addr, "POST", "/api/todos", r##"{"title": "#only"}"##)?;
+    assert!(bad.starts_with("HTTP/1.1 400"), "{bad}");
+    Ok(())
+}
+
+#[test]
+fn api_adds_use_the_server_clock_and_priority_levels() -> Result<()> {
+    let now = time::macros::datetime!(2030-01-07 09:00 UTC);
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    run(&ctx, &["list"])?;
+    let cfg = AppConfig {
+        priority_levels: vec![PriorityLevel {
+            label: "Urgent".to_string(),
+            priority: Priority::P1,
+            color: None,
+        }],
+        ..AppConfig::default()
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let opts = ServeOptions {
+        key: None,
+        db_path: dir.path().join("db.json"),
+        clock: Arc::new(FixedClock::new(
+            time::macros::datetime!(2030-01-07 09:00 UTC),
+        )),
+        capture: CaptureRules::from_config(&cfg),
+        refresh: Duration::from_secs(5),
+        config: None,
+        writable: true,
+        tokens: None,
+        rate_limit: 0,
+    };
+    std::thread::spawn(move || serve(listener, opts));
+
+    let added = send(
+        addr,
+        "POST",
+        "/api/todos",
+        r#"{"title": "Pay rent !urgent due:tomorrow"}"#,
+    )?;
+    assert!(added.starts_with("HTTP/1.1 201"), "{added}");
+    let list = run(&ctx, &["list", "--format", "json"])?;
+    let todos: Vec<Todo> = serde_json::from_str(&list)?;
+    let rent = todos
+        .iter()
+        .find(|t| t.title.as_str() == "Pay rent")
+        .unwrap();
+    assert_eq!(rent.priority, Priority::P1);
+    assert_eq!(rent.created_at, now);
+    assert_eq!(
+        rent.due.unwrap().as_dt().date(),
+        now.date().next_day().unwrap()
+    );
+    Ok(())
+}
+
+#[test]
+fn api_edits_todos_with_export_field_names() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    run(&ctx, &["list"])?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let opts = ServeOptions {
+        key: None,
+        db_path: dir.path().join("db.json"),
+        clock: Arc::new(SystemClock),
+        capture: CaptureRules::default(),
+        refresh: Duration::from_secs(5),
+        config: None,
+        writable: true,
+        tokens: None,
+        rate_limit: 0,
+    };
+    std::thread::spawn(move || serve(listener, opts));
+    let body = |response: &str| -> Result<serde_json::Value> {
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+        Ok(serde_json::from_str(body)?)
+    };
+
+    let added = send(
+        addr,
+        "POST",
+        "/api/todos",
+        r#"{"title": "Book venue #party", "project": "Events", "priority": "P2",
+            "due": "2030-05-01T17:00:00Z"}"#,
+    )?;
+    assert!(added.starts_with("HTTP/1.1 201"), "{added}");
+    let id = body(&added)?["id"].as_str().unwrap().to_string();
+
+    let edited = send(
+        addr,
+        "PATCH",
+        &format!("/api/todos/{id}"),
+        r#"{"title": "Book the venue", "tags": ["party", "urgent"], "notes": "Call first",
+            "due": null}"#,
+    )?;
+    assert!(edited.starts_with("HTTP/1.1 200"), "{edited}");
+
+    let todo = body(&request(addr, "GET", &format!("/api/todos/{id}"))?)?;
+    assert_eq!(todo["title"], "Book the venue");
+    assert_eq!(todo["project"], "Events");
+    assert_eq!(todo["priority"], "P2");
+    assert_eq!(todo["tags"], serde_json::json!(["party", "urgent"]));
+    assert_eq!(todo["notes"], "Call first");
+    assert!(todo["due"].is_null(), "{todo}");
+    let shown = run(&ctx, &["show", &id])?;
+    assert!(shown.contains("Book the venue"), "{shown}");
+
+    let unknown = send(
+        addr,
+        "PATCH",
+        &format!("/api/todos/{id}"),
+        r#"{"status": "Done"}"#,
+    )?;
+    assert!(unknown.starts_with("HTTP/1.1 400"), "{unknown}");
+    let bad = send(
+        addr,
+        "PATCH",
+        &format!("/api/todos/{id}"),
+        r#"{"priority": "urgent"}"#,
+    )?;
+    assert!(bad.starts_with("HTTP/1.1 400"), "{bad}");
+    let other = format!(
+        "/api/todos/{}",
+        "0".repeat(8) + "-0000-4000-8000-" + &"0".repeat(12)
+    );
+    assert!(send(addr, "PATCH", &other, r#"{"title": "x"}"#)?.starts_with("HTTP/1.1 404"));
+    assert!(request(addr, "GET", &other)?.starts_with("HTTP/1.1 404"));
+    Ok(())
+}
+
+#[test]
+fn api_tokens_gate_routes_by_scope() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    let create = |name: &str, scope: &str| -> Result<String> {
+        let out = run(&ctx, &["serve", "token", "create", name, "--scope", scope])?;
+        Ok(out.lines().nth(1).unwrap_or_default().to_string())
+    };
+    let reader = create("wall", "read")?;
+    let writer = create("phone", "write")?;
+    assert!(run(&ctx, &["serve", "token", "create", "wall"])?.contains("already exists"));
+    let tokens = std::fs::read_to_string(dir.path().join("data/api_tokens.json"))?;
+    assert!(!tokens.contains(&reader) && reader.starts_with("rtd_"));
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let opts = ServeOptions {
+        key: None,
+        db_path: dir.path().join("db.json"),
+        clock: Arc::new(SystemClock),
+        capture: CaptureRules::default(),
+        refresh: Duration::from_secs(5),
+        config: None,
+        writable: true,
+        tokens: Some(dir.path().join("data/api_tokens.json")),
+        rate_limit: 0,
+    };
+    std::thread::spawn(move || serve(listener, opts));
+    let call = |method: &str, path: &str, token: &str| -> Result<String> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let body = r#"{"title": "From the phone"}"#;
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nAuthorization: Bearer {token}\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    };
+
+    assert!(request(addr, "GET", "/healthz")?.starts_with("HTTP/1.1 200"));
+    let spec = request(addr, "GET", "/openapi.json")?;
+    let body = spec.split("\r\n\r\n").nth(1).unwrap_or_default();
+    let spec: serde_json::Value = serde_json::from_str(body)?;
+    assert_eq!(spec["paths"]["/api/todos"]["post"]["x-scope"], "write");
+    assert!(request(addr, "GET", "/api/board")?.starts_with("HTTP/1.1 401"));
+    assert!(call("GET", "/api/board", "rtd_nope")?.starts_with("HTTP/1.1 401"));
+    assert!(call("GET", "/api/board", &reader)?.starts_with("HTTP/1.1 200"));
+    let board = format!("/api/todos?limit=1&token={reader}");
+    assert!(request(addr, "GET", &board)?.starts_with("HTTP/1.1 200"));
+
+    assert!(call("POST", "/api/todos", &reader)?.starts_with("HTTP/1.1 403"));
+    assert!(call("POST", "/api/todos", &writer)?.starts_with("HTTP/1.1 201"));
+    assert!(call("POST", "/-/reload", &writer)?.starts_with("HTTP/1.1 403"));
+
+    assert!(run(&ctx, &["serve", "token", "revoke", "phone"])?.contains("Revoked token phone"));
+    assert!(call("POST", "/api/todos", &writer)?.starts_with("HTTP/1.1 401"));
+    let list = run(&ctx, &["serve", "token", "list"])?;
+    assert!(list.contains("wall") && !list.contains("phone"), "{list}");
+    Ok(())
+}
+
+#[test]
+fn api_spec_matches_the_served_routes() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    let full: serde_json::Value = serde_json::from_str(&run(&ctx, &["api", "spec"])?)?;
+    assert_eq!(full["openapi"], "3.0.3");
+    assert!(full["paths"]["/api/todos/{id}"]["delete"].is_object());
+
+    let file = dir.path().join("openapi.json");
+    let msg = run(
+        &ctx,
+        &["api", "spec", "--readonly", "--out", file.to_str().unwrap()],
+    )?;
+    assert!(msg.contains("Wrote OpenAPI spec"));
+    let readonly: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&file)?)?;
+    assert!(readonly["paths"]["/api/todos"]["get"].is_object());
+    assert!(readonly["paths"]["/api/todos"].get("post").is_none());
+    Ok(())
+}
+
+#[test]
+fn clients_over_their_rate_limit_get_429_and_show_in_metrics() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    let out = run(
+        &ctx,
+        &["serve", "token", "create", "wall", "--rate-limit", "2"],
+    )?;
+    let wall = out.lines().nth(1).unwrap_or_default().to_string();
+    let out = run(&ctx, &["serve", "token", "create", "ops"])?;
+    let ops = out.lines().nth(1).unwrap_or_default().to_string();
+    let list = run(&ctx, &["serve", "token", "list"])?;
+    assert!(list.contains("2 requests/min"), "{list}");
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    let opts = ServeOptions {
+        key: None,
+        db_path: dir.path().join("db.json"),
+        clock: Arc::new(SystemClock),
+        capture: CaptureRules::default(),
+        refresh: Duration::from_secs(5),
+        config: None,
+        writable: false,
+        tokens: Some(dir.path().join("data/api_tokens.json")),
+        rate_limit: 100,
+    };
+    std::thread::spawn(move || serve(listener, opts));
+    let board = |token: &str| request(addr, "GET", &format!("/api/board?token={token}"));
+
+    assert!(board(&wall)?.starts_with("HTTP/1.1 200"));
+    assert!(board(&wall)?.starts_with("HTTP/1.1 200"));
+    let limited = board(&wall)?;
+    assert!(limited.starts_with("HTTP/1.1 429"), "{limited}");
+    assert!(limited.contains("Retry-After: 30"), "{limited}");
+    // Other tokens have their own allowance.
+    assert!(board(&ops)?.starts_with("HTTP/1.1 200"));
+
+    let metrics = request(addr, "GET", &format!("/metrics?token={ops}"))?;
+    assert!(
+        metrics.contains(
+            r#"rustlytodo_http_requests_total{method="GET",route="/api/board",code="200"} 3"#
+        ),
+        "{metrics}"
+    );
+    assert!(
+        metrics.contains(r#"rustlytodo_http_rate_limited_total{client="token:wall"} 1"#),
+        "{metrics}"
+    );
+    assert!(metrics.contains("rustlytodo_http_request_seconds_total"));
+    Ok(())
+}
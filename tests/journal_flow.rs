@@ -0,0 +1,83 @@
+use anyhow::Result;
+use tempfile::{TempDir, tempdir};
+
+use rustytodo::app::context::AppContext;
+use rustytodo::infra::config::{AppConfig, JournalConfig};
+use rustytodo::infra::paths::AppPaths;
+
+fn chained_ctx(dir: &TempDir) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.path().join("cfg"),
+        data_dir: dir.path().join("data"),
+    };
+    let cfg = AppConfig {
+        storage_path: Some(dir.path().join("db.json")),
+        journal: JournalConfig {
+            enabled: true,
+            hash_chain: true,
+        },
+        ..AppConfig::default()
+    };
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+#[test]
+fn mutations_are_journaled_and_tampering_is_detected() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = chained_ctx(&dir);
+
+    run(&ctx, &["add", "Write report"])?;
+    run(&ctx, &["list"])?; // read-only: no entry
+    run(&ctx, &["add", "Review budget"])?;
+
+    let journal = dir.path().join("db.journal.jsonl");
+    let text = std::fs::read_to_string(&journal)?;
+    assert_eq!(text.lines().count(), 2);
+    assert!(text.contains("\"command\":\"add\""));
+
+    let ok = run(&ctx, &["verify-journal"])?;
+    assert!(ok.contains("Journal OK: 2 entries (2 chained)"), "{ok}");
+
+    std::fs::write(&journal, text.replace("Write report", "Write nothing"))?;
+    assert!(run(&ctx, &["verify-journal"]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn exported_history_is_restored_on_another_machine() -> Result<()> {
+    let (old, new) = (tempdir()?, tempdir()?);
+    let (old_ctx, new_ctx) = (chained_ctx(&old), chained_ctx(&new));
+    let export = old.path().join("export.json");
+    let export_arg = export.to_str().unwrap();
+
+    run(&old_ctx, &["add", "Write report"])?;
+    run(&old_ctx, &["add", "Review budget"])?;
+    run(
+        &old_ctx,
+        &["export", "--out", export_arg, "--include-history"],
+    )?;
+    let text = std::fs::read_to_string(&export)?;
+    assert!(text.contains("\"journal\""), "{text}");
+
+    let msg = run(&new_ctx, &["--force", "import", "--in", export_arg])?;
+    assert!(msg.contains("Restored 2 journal entries"), "{msg}");
+    let journal = std::fs::read_to_string(new.path().join("db.journal.jsonl"))?;
+    assert!(journal.contains("Write report") && journal.contains("Review budget"));
+    let ok = run(&new_ctx, &["verify-journal"])?;
+    assert!(ok.contains("Journal OK: 3 entries (3 chained)"), "{ok}");
+
+    // Importing the same file again doesn't repeat them.
+    let msg = run(&new_ctx, &["--force", "import", "--in", export_arg])?;
+    assert!(msg.contains("Restored 0 journal entries"), "{msg}");
+    Ok(())
+}
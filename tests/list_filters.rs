@@ -5,6 +5,7 @@ use rustytodo::app::context::AppContext;
 use rustytodo::infra::config::{AppConfig, Theme};
 use rustytodo::infra::paths::AppPaths;
 
+#[allow(clippy::field_reassign_with_default)]
 fn test_ctx() -> Result<AppContext> {
     let dir = tempdir()?;
 
@@ -14,7 +15,7 @@ fn test_ctx() -> Result<AppContext> {
     };
 
     let mut cfg = AppConfig::default();
-    cfg.theme = Theme::Dark;
+    cfg.theme = Theme::Dark.into();
     cfg.storage_path = Some(dir.path().join("db.json"));
 
     Ok(AppContext::new(paths, cfg))
@@ -94,3 +95,95 @@ fn list_sort_priority_orders_p1_first() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn someday_items_only_show_with_someday_flag() -> Result<()> {
+    let ctx = test_ctx()?;
+    let list = |extra: &[&str]| -> Result<Vec<rustytodo::domain::todo::Todo>> {
+        let mut args: Vec<String> = vec![
+            "rustytodo".into(),
+            "list".into(),
+            "--format".into(),
+            "json".into(),
+        ];
+        args.extend(extra.iter().map(|s| s.to_string()));
+        let mut buf = Vec::new();
+        rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), args, &mut buf)?;
+        Ok(serde_json::from_slice(&buf)?)
+    };
+
+    let before = list(&[])?;
+    let parked = before[0].id;
+
+    let mut out = Vec::new();
+    rustytodo::ui::cli::run_with_args_to_writer(
+        ctx.clone(),
+        vec!["rustytodo".into(), "someday".into(), parked.short()],
+        &mut out,
+    )?;
+
+    let active = list(&[])?;
+    assert_eq!(active.len(), before.len() - 1);
+    assert!(active.iter().all(|t| t.id != parked));
+
+    let someday = list(&["--someday"])?;
+    assert_eq!(someday.len(), 1);
+    assert_eq!(someday[0].id, parked);
+
+    Ok(())
+}
+
+#[test]
+fn list_source_filter_matches_cli_adds_only() -> Result<()> {
+    let ctx = test_ctx()?;
+
+    let mut out = Vec::new();
+    rustytodo::ui::cli::run_with_args_to_writer(
+        ctx.clone(),
+        ["rustytodo", "add", "Typed by hand"].map(String::from),
+        &mut out,
+    )?;
+
+    // Seeded todos have no source, so only the new one matches.
+    let mut buf = Vec::new();
+    rustytodo::ui::cli::run_with_args_to_writer(
+        ctx,
+        ["rustytodo", "list", "--format", "json", "--source", "cli"].map(String::from),
+        &mut buf,
+    )?;
+    let todos: Vec<rustytodo::domain::todo::Todo> = serde_json::from_slice(&buf)?;
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].title.as_str(), "Typed by hand");
+
+    Ok(())
+}
+
+#[test]
+fn list_limit_and_offset_page_through_matches() -> Result<()> {
+    let ctx = test_ctx()?;
+    let run = |args: &[&str]| -> Result<String> {
+        let mut buf = Vec::new();
+        let argv = std::iter::once("rustytodo")
+            .chain(args.iter().copied())
+            .map(String::from);
+        rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    };
+
+    let all: Vec<rustytodo::domain::todo::Todo> =
+        serde_json::from_str(&run(&["list", "--format", "json"])?)?;
+    assert!(all.len() >= 3);
+
+    let page: Vec<rustytodo::domain::todo::Todo> = serde_json::from_str(&run(&[
+        "list", "--format", "json", "--limit", "2", "--offset", "1",
+    ])?)?;
+    let ids: Vec<_> = page.iter().map(|t| t.id).collect();
+    assert_eq!(ids, vec![all[1].id, all[2].id]);
+
+    let table = run(&["list", "--limit", "2", "--offset", "1"])?;
+    assert!(
+        table.contains(&format!("Showing 2-3 of {} matching todos.", all.len())),
+        "{table}"
+    );
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tempfile::tempdir;
+
+use rustytodo::app::context::AppContext;
+use rustytodo::domain::todo::Todo;
+use rustytodo::infra::config::AppConfig;
+use rustytodo::infra::paths::AppPaths;
+
+fn test_ctx(dir: &Path) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.join("cfg"),
+        data_dir: dir.join("data"),
+    };
+    let cfg = AppConfig {
+        storage_path: Some(dir.join("db.json")),
+        ..AppConfig::default()
+    };
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn find(ctx: &AppContext, title: &str) -> Result<Todo> {
+    let todos: Vec<Todo> = serde_json::from_str(&run(ctx, &["list", "--format", "json"])?)?;
+    Ok(todos
+        .into_iter()
+        .find(|t| t.title.as_str() == title)
+        .unwrap())
+}
+
+#[test]
+fn notes_round_trip_through_files() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(dir.path());
+
+    let file = dir.path().join("plan.md");
+    let notes = "# Plan\n\n- \"quotes\" and $vars\n- `code`";
+    std::fs::write(&file, format!("{notes}\n"))?;
+    let file = file.to_str().unwrap();
+    run(&ctx, &["add", "Write plan", "--notes-file", file])?;
+    let id = find(&ctx, "Write plan")?.id.short();
+    assert_eq!(
+        run(&ctx, &["show", &id, "--notes-only"])?,
+        format!("{notes}\n")
+    );
+
+    std::fs::write(
+        file,
+        run(&ctx, &["show", &id, "--notes-only"])? + "- more\n",
+    )?;
+    run(&ctx, &["edit", &id, "--notes-file", file])?;
+    assert_eq!(
+        run(&ctx, &["show", &id, "--notes-only"])?,
+        format!("{notes}\n- more\n")
+    );
+
+    std::fs::write(file, "x".repeat(30_000))?;
+    let err = run(&ctx, &["edit", &id, "--notes-file", file]).unwrap_err();
+    assert!(format!("{err:#}").contains("notes are too long"), "{err:#}");
+    assert!(run(&ctx, &["edit", &id, "--notes-file", "missing.md"]).is_err());
+    run(&ctx, &["edit", &id, "--clear-notes"])?;
+    assert_eq!(run(&ctx, &["show", &id, "--notes-only"])?, "");
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tempfile::tempdir;
+
+use rustytodo::app::context::AppContext;
+use rustytodo::domain::todo::Todo;
+use rustytodo::infra::config::AppConfig;
+use rustytodo::infra::fs_repo::shard_dir;
+use rustytodo::infra::paths::AppPaths;
+
+/// A context with `cfg`, keeping the database under `dir`.
+fn test_ctx_with(dir: &Path, mut cfg: AppConfig) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.join("cfg"),
+        data_dir: dir.join("data"),
+    };
+    cfg.storage_path = Some(dir.join("db.json"));
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+#[test]
+fn sharded_storage_converts_and_lists_one_project() -> Result<()> {
+    let dir = tempdir()?;
+    let db = dir.path().join("db.json");
+    let mut cfg = AppConfig::default();
+    let run_with =
+        |cfg: &AppConfig, args: &[&str]| run(&test_ctx_with(dir.path(), cfg.clone()), args);
+
+    run_with(&cfg, &["add", "Quarterly report", "--project", "Work"])?;
+    run_with(&cfg, &["add", "Buy milk", "--project", "Home"])?;
+    let everything = run_with(&cfg, &["list", "--format", "json"])?;
+    assert!(db.exists());
+
+    cfg.shard_by_project = true;
+    assert_eq!(run_with(&cfg, &["list", "--format", "json"])?, everything);
+    let shards = shard_dir(&db);
+    assert!(shards.is_dir() && !db.exists());
+
+    let all: Vec<Todo> = serde_json::from_str(&everything)?;
+    let work = run_with(&cfg, &["list", "--project", "work", "--format", "json"])?;
+    let todos: Vec<Todo> = serde_json::from_str(&work)?;
+    let expected = all.iter().filter(|t| t.project.as_str() == "Work").count();
+    assert_eq!(todos.len(), expected);
+    assert!(todos.iter().any(|t| t.title.as_str() == "Quarterly report"));
+
+    cfg.shard_by_project = false;
+    assert_eq!(run_with(&cfg, &["list", "--format", "json"])?, everything);
+    assert!(db.exists() && !shards.exists());
+    Ok(())
+}
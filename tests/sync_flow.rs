@@ -0,0 +1,272 @@
+use std::io::IsTerminal;
+
+use anyhow::Result;
+use tempfile::{TempDir, tempdir};
+
+use rustytodo::app::context::AppContext;
+use rustytodo::domain::todo::Todo;
+use rustytodo::infra::config::AppConfig;
+use rustytodo::infra::paths::AppPaths;
+
+fn device_ctx(dir: &TempDir, name: &str) -> AppContext {
+    let root = dir.path().join(name);
+    let paths = AppPaths {
+        config_dir: root.join("cfg"),
+        data_dir: root.join("data"),
+    };
+    let cfg = AppConfig {
+        storage_path: Some(root.join("db.json")),
+        ..AppConfig::default()
+    };
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn list(ctx: &AppContext) -> Result<Vec<Todo>> {
+    Ok(serde_json::from_str(&run(
+        ctx,
+        &["list", "--format", "json"],
+    )?)?)
+}
+
+#[test]
+fn two_devices_converge_through_delta_files() -> Result<()> {
+    let dir = tempdir()?;
+    let a = device_ctx(&dir, "a");
+    let b = device_ctx(&dir, "b");
+    let a_to_b = dir.path().join("a-to-b.json");
+    let b_to_a = dir.path().join("b-to-a.json");
+    let a_to_b = a_to_b.to_str().unwrap();
+    let b_to_a = b_to_a.to_str().unwrap();
+
+    // Both devices start with their own seed data.
+    run(&a, &["sync", "export", "--out", a_to_b])?;
+    run(&b, &["sync", "export", "--out", b_to_a])?;
+    run(&b, &["sync", "import", "--in", a_to_b])?;
+    run(&a, &["sync", "import", "--in", b_to_a])?;
+
+    let mut ids_a: Vec<_> = list(&a)?.into_iter().map(|t| t.id).collect();
+    let mut ids_b: Vec<_> = list(&b)?.into_iter().map(|t| t.id).collect();
+    ids_a.sort();
+    ids_b.sort();
+    assert_eq!(ids_a.len(), 6);
+    assert_eq!(ids_a, ids_b);
+
+    // A completes a todo; B learns about it on the next round.
+    let first = list(&a)?[0].id.as_uuid_str();
+    run(&a, &["done", &first])?;
+    run(&a, &["sync", "export", "--out", a_to_b])?;
+    run(&b, &["sync", "import", "--in", a_to_b])?;
+
+    let on_b = list(&b)?.into_iter().find(|t| t.id.as_uuid_str() == first);
+    assert!(on_b.unwrap().status.is_done());
+
+    Ok(())
+}
+
+#[test]
+fn resolve_picks_fields_from_the_saved_conflict() -> Result<()> {
+    let dir = tempdir()?;
+    let a = device_ctx(&dir, "a");
+    let b = device_ctx(&dir, "b");
+    let a_to_b = dir.path().join("a-to-b.json");
+    let b_to_a = dir.path().join("b-to-a.json");
+    let a_to_b = a_to_b.to_str().unwrap();
+    let b_to_a = b_to_a.to_str().unwrap();
+
+    run(&a, &["sync", "export", "--out", a_to_b])?;
+    run(&b, &["sync", "import", "--in", a_to_b])?;
+
+    // Both devices rename the same todo offline; B's edit is later and wins.
+    let id = list(&a)?[0].id.as_uuid_str();
+    run(&a, &["edit", &id, "--title", "Title from A"])?;
+    run(&a, &["sync", "export", "--out", a_to_b])?;
+    run(&b, &["edit", &id, "--title", "Title from B"])?;
+    run(&b, &["sync", "export", "--out", b_to_a])?;
+
+    let imported = run(&a, &["sync", "import", "--in", b_to_a])?;
+    assert!(imported.contains("Conflict on"), "{imported}");
+    let title = |ctx| -> Result<String> {
+        let t = list(ctx)?.into_iter().find(|t| t.id.as_uuid_str() == id);
+        Ok(t.unwrap().title.as_str().to_string())
+    };
+    assert_eq!(title(&a)?, "Title from B");
+
+    // With nobody to ask, an unpicked field is an error and the conflict stays.
+    if !std::io::stdin().is_terminal() {
+        let err = run(&a, &["resolve", &id]).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("--pick <field>=local|other for title"),
+            "{err}"
+        );
+    }
+
+    let out = run(&a, &["resolve", &id, "--pick", "title=other"])?;
+    assert!(out.contains("took other version for title"), "{out}");
+    assert_eq!(title(&a)?, "Title from A");
+
+    // The conflict file is consumed.
+    let out = run(&a, &["resolve", &id])?;
+    assert!(out.contains("No conflict recorded"), "{out}");
+
+    // The resolution is the newest edit, so it propagates back to B.
+    run(&a, &["sync", "export", "--out", a_to_b])?;
+    run(&b, &["sync", "import", "--in", a_to_b])?;
+    assert_eq!(title(&b)?, "Title from A");
+
+    Ok(())
+}
+
+#[test]
+fn encrypted_deltas_need_trusted_keys() -> Result<()> {
+    let dir = tempdir()?;
+    let a = device_ctx(&dir, "a");
+    let b = device_ctx(&dir, "b");
+    let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+    let (a_invite, b_invite, a_to_b) = (path("a.invite"), path("b.invite"), path("a-to-b.json"));
+
+    run(&a, &["sync", "keys", "init"])?;
+    run(&b, &["sync", "keys", "init"])?;
+
+    // Nothing is exportable until a peer is trusted.
+    assert!(run(&a, &["sync", "export", "--out", &a_to_b]).is_err());
+
+    run(
+        &a,
+        &[
+            "sync",
+            "keys",
+            "invite",
+            "--out",
+            &a_invite,
+            "--passphrase",
+            "pw",
+        ],
+    )?;
+    run(
+        &b,
+        &[
+            "sync",
+            "keys",
+            "invite",
+            "--out",
+            &b_invite,
+            "--passphrase",
+            "pw",
+        ],
+    )?;
+    assert!(
+        run(
+            &b,
+            &[
+                "sync",
+                "keys",
+                "add",
+                "--in",
+                &a_invite,
+                "--passphrase",
+                "nope"
+            ]
+        )
+        .is_err()
+    );
+    run(
+        &b,
+        &[
+            "sync",
+            "keys",
+            "add",
+            "--in",
+            &a_invite,
+            "--passphrase",
+            "pw",
+        ],
+    )?;
+    run(
+        &a,
+        &[
+            "sync",
+            "keys",
+            "add",
+            "--in",
+            &b_invite,
+            "--passphrase",
+            "pw",
+        ],
+    )?;
+
+    run(&a, &["add", "Secret plans"])?;
+    let exported = run(&a, &["sync", "export", "--out", &a_to_b])?;
+    assert!(
+        exported.contains("Encrypted for 1 trusted peer"),
+        "{exported}"
+    );
+    assert!(!std::fs::read_to_string(&a_to_b)?.contains("Secret plans"));
+
+    run(&b, &["sync", "import", "--in", &a_to_b])?;
+    assert!(list(&b)?.iter().any(|t| t.title.as_str() == "Secret plans"));
+
+    // With keys set up, plaintext deltas are refused unless asked for.
+    run(&a, &["sync", "export", "--out", &a_to_b, "--plaintext"])?;
+    let refused = run(&b, &["sync", "import", "--in", &a_to_b])?;
+    assert!(refused.contains("Refusing unencrypted delta"), "{refused}");
+
+    Ok(())
+}
+
+#[test]
+fn git_sync_pushes_and_merges_by_todo() -> Result<()> {
+    let dir = tempdir()?;
+    let remote = dir.path().join("remote.git");
+    let status = std::process::Command::new("git")
+        .args(["init", "--quiet", "--bare"])
+        .arg(&remote)
+        .status()?;
+    assert!(status.success());
+    let remote = remote.to_str().unwrap();
+
+    let a = device_ctx(&dir, "a");
+    let b = device_ctx(&dir, "b");
+    run(&a, &["sync", "init", "--remote", remote])?;
+    run(&a, &["sync", "push"])?;
+    // B starts from A's list rather than its own seed data.
+    run(&b, &["sync", "init", "--remote", remote])?;
+    let pulled = run(&b, &["sync", "pull"])?;
+    assert!(pulled.contains("Merged origin"), "{pulled}");
+    assert_eq!(list(&b)?.len(), 6);
+
+    // Concurrent edits on both sides.
+    let first = list(&a)?[0].id.short();
+    run(&a, &["add", "From A"])?;
+    run(&a, &["sync", "push"])?;
+    run(&b, &["add", "From B"])?;
+    run(&b, &["delete", &first, "--yes"])?;
+    let pushed = run(&b, &["sync", "push"]);
+    assert!(pushed.is_err(), "B is behind and must pull first");
+    run(&b, &["sync", "pull"])?;
+    run(&b, &["sync", "push"])?;
+    run(&a, &["sync", "pull"])?;
+
+    let titles = |ctx: &AppContext| -> Result<Vec<String>> {
+        let mut t: Vec<_> = list(ctx)?
+            .into_iter()
+            .map(|t| t.title.as_str().to_string())
+            .collect();
+        t.sort();
+        Ok(t)
+    };
+    let on_a = titles(&a)?;
+    assert_eq!(on_a, titles(&b)?);
+    assert!(on_a.contains(&"From A".to_string()) && on_a.contains(&"From B".to_string()));
+    assert_eq!(on_a.len(), 7);
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use tempfile::{TempDir, tempdir};
+
+use rustytodo::app::context::AppContext;
+use rustytodo::domain::todo::Todo;
+use rustytodo::infra::config::AppConfig;
+use rustytodo::infra::paths::AppPaths;
+
+fn test_ctx(dir: &TempDir) -> AppContext {
+    let paths = AppPaths {
+        config_dir: dir.path().join("cfg"),
+        data_dir: dir.path().join("data"),
+    };
+    let cfg = AppConfig {
+        storage_path: Some(dir.path().join("db.json")),
+        ..AppConfig::default()
+    };
+    AppContext::new(paths, cfg)
+}
+
+fn run(ctx: &AppContext, args: &[&str]) -> Result<String> {
+    let mut buf = Vec::new();
+    let argv = std::iter::once("rustytodo")
+        .chain(args.iter().copied())
+        .map(String::from);
+    rustytodo::ui::cli::run_with_args_to_writer(ctx.clone(), argv, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn find(ctx: &AppContext, title: &str) -> Result<Todo> {
+    let todos: Vec<Todo> = serde_json::from_str(&run(ctx, &["list", "--format", "json"])?)?;
+    Ok(todos
+        .into_iter()
+        .find(|t| t.title.as_str() == title)
+        .unwrap())
+}
+
+#[test]
+fn start_switches_timers_and_stats_export_csv() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    run(&ctx, &["add", "Client work", "--tag", "billable"])?;
+    run(&ctx, &["add", "Admin"])?;
+    let client = find(&ctx, "Client work")?.id.short();
+    let admin = find(&ctx, "Admin")?.id.short();
+
+    run(&ctx, &["start", &client])?;
+    let switched = run(&ctx, &["start", &admin])?;
+    assert!(
+        switched.contains(&format!("Stopped {client}")),
+        "{switched}"
+    );
+    run(&ctx, &["stop", "--note", "paperwork"])?;
+    assert_eq!(run(&ctx, &["stop"])?.trim(), "No timer is running.");
+
+    let admin_todo = find(&ctx, "Admin")?;
+    assert_eq!(admin_todo.time_entries.len(), 1);
+    assert_eq!(
+        admin_todo.time_entries[0].note.as_deref(),
+        Some("paperwork")
+    );
+
+    let csv = run(
+        &ctx,
+        &[
+            "stats",
+            "time",
+            "--group-by",
+            "tag",
+            "--since",
+            "30d",
+            "--format",
+            "csv",
+        ],
+    )?;
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("tag,minutes,hours,entries,todos"));
+    let keys: Vec<_> = lines.map(|l| l.split(',').next().unwrap()).collect();
+    assert!(
+        keys.contains(&"billable") && keys.contains(&"(untagged)"),
+        "{csv}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn timesheet_export_filters_by_project() -> Result<()> {
+    let dir = tempdir()?;
+    let ctx = test_ctx(&dir);
+    run(&ctx, &["add", "Design review", "--project", "Acme"])?;
+    run(&ctx, &["add", "Personal"])?;
+    for title in ["Design review", "Personal"] {
+        let id = find(&ctx, title)?.id.short();
+        run(&ctx, &["start", &id])?;
+        run(&ctx, &["stop", "--note", "call with client"])?;
+    }
+
+    let md = dir.path().join("acme.md");
+    let md_str = md.to_str().unwrap();
+    let msg = run(
+        &ctx,
+        &[
+            "export",
+            "--format",
+            "timesheet",
+            "--project",
+            "acme",
+            "--since",
+            "7d",
+            "--out",
+            md_str,
+        ],
+    )?;
+    assert!(msg.contains("Exported timesheet (1 rows"), "{msg}");
+
+    let text = std::fs::read_to_string(&md)?;
+    assert!(text.starts_with("| Date | Project | Task | Duration | Notes |"));
+    assert!(
+        text.contains("| Acme | Design review | 0:01 | call with client |"),
+        "{text}"
+    );
+    assert!(!text.contains("Personal"));
+
+    Ok(())
+}